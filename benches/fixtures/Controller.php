@@ -0,0 +1,38 @@
+<?php
+
+declare(strict_types=1);
+
+namespace App\Controller;
+
+use App\Entity\User;
+use Aurynx\Routing\Attributes\Route;
+use Aurynx\Security\Attributes\IsGranted;
+
+#[Route(path: '/api/users', name: 'users_')]
+final class UserController extends AbstractController
+{
+    #[Route(path: '', methods: ['GET'], name: 'list')]
+    #[IsGranted('ROLE_ADMIN')]
+    public function list(int $page = 1, int $limit = 20): array
+    {
+        return [];
+    }
+
+    #[Route(path: '/{id}', methods: ['GET'], name: 'show', requirements: ['id' => '\d+'])]
+    public function show(#[MapEntity] User $user): User
+    {
+        return $user;
+    }
+
+    #[Route(path: '', methods: ['POST'], name: 'create')]
+    #[IsGranted('ROLE_ADMIN', message: 'Only admins can create users')]
+    public function create(#[MapRequestPayload] CreateUserRequest $request): User
+    {
+        return new User($request->name);
+    }
+
+    #[Route(path: '/{id}', methods: ['DELETE'], name: 'delete')]
+    public static function delete(int $id): void
+    {
+    }
+}
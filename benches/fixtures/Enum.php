@@ -0,0 +1,44 @@
+<?php
+
+declare(strict_types=1);
+
+namespace App\Enum;
+
+use App\Attribute\Label;
+
+#[\Attribute(\Attribute::TARGET_CLASS_CONSTANT)]
+final class Description
+{
+    public function __construct(public string $text)
+    {
+    }
+}
+
+interface HasLabel
+{
+    public function label(): string;
+}
+
+enum Status: string implements HasLabel
+{
+    #[Label('Active')]
+    #[Description('The account is active')]
+    case Active = 'active';
+
+    #[Label('Suspended')]
+    case Suspended = 'suspended';
+
+    #[Label('Deleted')]
+    case Deleted = 'deleted';
+
+    public function label(): string
+    {
+        return ucfirst($this->value);
+    }
+}
+
+trait TimestampableTrait
+{
+    #[Column(type: 'datetime_immutable')]
+    private ?\DateTimeImmutable $createdAt = null;
+}
@@ -0,0 +1,139 @@
+//! Benchmarks for the extraction pipeline
+//!
+//! Run with `cargo bench` (optionally `cargo bench -- <filter>`). Covers the
+//! parser on representative fixtures, the writers on synthetic 10k-class
+//! metadata, and incremental manifest diffing. Criterion compares each run
+//! with the previous one; `cargo bench --bench pipeline -- --save-baseline
+//! main` stores a named baseline and `--baseline main` compares against it.
+
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+use aurynx::incremental::{MANIFEST_FILE, Manifest, perform_incremental_scan, scan_with_manifest};
+use aurynx::metadata::{
+    AttributeArgument, AttributeMap, MethodModifiers, PhpClassMetadata, PhpMethodMetadata,
+};
+use aurynx::parser::PhpMetadataExtractor;
+use aurynx::scanner::ExtractorPool;
+use aurynx::writer::render_cache;
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+const FIXTURES: [(&str, &str); 3] = [
+    ("entity", include_str!("fixtures/Entity.php")),
+    ("controller", include_str!("fixtures/Controller.php")),
+    ("enum", include_str!("fixtures/Enum.php")),
+];
+
+fn parser(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parser");
+    let mut extractor = PhpMetadataExtractor::new().unwrap();
+    for (name, source) in FIXTURES {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                extractor
+                    .extract_metadata(black_box(source), PathBuf::from("/bench/Fixture.php"))
+                    .unwrap()
+            });
+        });
+    }
+    group.finish();
+}
+
+fn writer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("writer");
+    let metadata = synthetic_metadata(10_000);
+    for format in ["php", "json"] {
+        group.bench_function(format!("{format}_10k"), |b| {
+            b.iter(|| render_cache(black_box(&metadata), format, false, None).unwrap());
+        });
+    }
+    group.finish();
+}
+
+fn incremental(c: &mut Criterion) {
+    // Set up on the first sample, so a filter that skips the benchmark
+    // skips writing the project too
+    let mut project = None;
+    // Quiet, so the scan summary is neither timed nor printed per iteration
+    let pool = ExtractorPool::default();
+    c.bench_function("incremental/unchanged_1k", |b| {
+        let (_dir, src, manifest_path) = project.get_or_insert_with(|| {
+            let dir = fixture_project(1_000);
+            let src = dir.path().join("src");
+            let manifest_path = dir.path().join(MANIFEST_FILE);
+            let (_, manifest) =
+                perform_incremental_scan(&manifest_path, std::slice::from_ref(&src), &[], u64::MAX)
+                    .unwrap();
+            manifest.save(&manifest_path).unwrap();
+            (dir, src, manifest_path)
+        });
+        let paths = std::slice::from_ref(src);
+        b.iter(|| {
+            let manifest = Manifest::load(manifest_path).unwrap();
+            scan_with_manifest(manifest, paths, &[], u64::MAX, &pool, true).unwrap()
+        });
+    });
+}
+
+criterion_group!(benches, parser, writer, incremental);
+criterion_main!(benches);
+
+/// Metadata shaped like a typical application class map
+fn synthetic_metadata(count: usize) -> Vec<PhpClassMetadata> {
+    (0..count)
+        .map(|i| {
+            let mut class = PhpClassMetadata::new(
+                format!("\\App\\Module{}\\Service{i}", i % 50),
+                PathBuf::from(format!("/app/src/Module{}/Service{i}.php", i % 50)),
+                "class".to_string(),
+            );
+            class.file_hash = format!("{i:016x}");
+            class.attributes.insert(
+                "\\Aurynx\\Routing\\Attributes\\Route".to_string(),
+                vec![vec![
+                    AttributeArgument::Named {
+                        key: "path".to_string(),
+                        value: format!("'/api/service/{i}'"),
+                    },
+                    AttributeArgument::Named {
+                        key: "methods".to_string(),
+                        value: "['GET', 'POST']".to_string(),
+                    },
+                ]],
+            );
+            class.implements = vec!["\\JsonSerializable".to_string()];
+            class.methods = (0..3)
+                .map(|m| PhpMethodMetadata {
+                    name: format!("action{m}"),
+                    visibility: "public".to_string(),
+                    modifiers: MethodModifiers::default(),
+                    attributes: AttributeMap::new(),
+                    parameters: vec![],
+                    return_type: Some("void".to_string()),
+                })
+                .collect();
+            class
+        })
+        .collect()
+}
+
+/// Temporary project with `count` small PHP files under `src/`
+fn fixture_project(count: usize) -> TempDir {
+    let project = TempDir::new().unwrap();
+    let src = project.path().join("src");
+    std::fs::create_dir(&src).unwrap();
+    for i in 0..count {
+        write_class(&src, i);
+    }
+    project
+}
+
+fn write_class(dir: &Path, i: usize) {
+    std::fs::write(
+        dir.join(format!("Service{i}.php")),
+        format!("<?php namespace App; #[Service] class Service{i} {{ public function run(): void {{}} }}"),
+    )
+    .unwrap();
+}
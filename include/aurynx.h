@@ -0,0 +1,24 @@
+/*
+ * C ABI of the aurynx scanner, for PHP's FFI extension.
+ *
+ * Build the library with:
+ *   cargo rustc --release --lib --crate-type cdylib --no-default-features --features ffi
+ *
+ * Strings are NUL-terminated UTF-8 JSON. Every non-NULL string returned by
+ * the library must be released with aurynx_free().
+ */
+
+#define FFI_SCOPE "aurynx"
+#define FFI_LIB "libaurynx.so"
+
+/* paths: JSON array of paths; options: JSON object or NULL.
+ * Returns the scan report or {"error": {"code", "message"}}; never NULL. */
+char *aurynx_scan(const char *paths, const char *options);
+
+/* Class from the last successful scan as JSON, or NULL if not found. */
+char *aurynx_get_class(const char *fqcn);
+
+/* Classes of one source string; path may be NULL. Never NULL. */
+char *aurynx_extract(const char *source, const char *path);
+
+void aurynx_free(char *s);
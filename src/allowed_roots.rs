@@ -0,0 +1,66 @@
+//! Scan path allowlist (`allowed_roots`)
+//!
+//! A daemon running under a privileged deploy user can read much more than
+//! the project it serves. With `allowed_roots` configured, every scanned path
+//! must lie inside one of the roots, like PHP's `open_basedir`, whatever the
+//! CLI flags point at; otherwise the command stops before a file is read.
+//! Paths are compared after resolving symlinks and `..`, and the scanner
+//! never follows symlinks, so nothing outside the roots is scanned or
+//! watched.
+
+use crate::error::{AurynxError, Result};
+use std::path::{Path, PathBuf};
+
+/// Check that every path in `paths` lies inside one of `roots`
+///
+/// # Errors
+///
+/// Returns a config error naming the first path outside the roots, or a
+/// root or path that cannot be resolved.
+pub fn check(paths: &[PathBuf], roots: &[PathBuf]) -> Result<()> {
+    let roots = roots
+        .iter()
+        .map(|root| resolve(root, "Allowed root"))
+        .collect::<Result<Vec<_>>>()?;
+
+    for path in paths {
+        let resolved = resolve(path, "Scan path")?;
+        if !roots.iter().any(|root| resolved.starts_with(root)) {
+            return Err(AurynxError::config_error(format!(
+                "Scan path {} is outside allowed_roots",
+                path.display()
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn resolve(path: &Path, what: &str) -> Result<PathBuf> {
+    path.canonicalize().map_err(|e| {
+        AurynxError::config_error(format!("{what} {} cannot be resolved: {e}", path.display()))
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check_resolves_paths_before_comparing() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("app");
+        let outside = temp_dir.path().join("etc");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+        let roots = [root.clone()];
+
+        assert!(check(&[root.join("src"), root.clone()], &roots).is_ok());
+        assert!(check(&[root.join("src/../../etc")], &roots).is_err());
+        assert!(check(&[root.join("link")], &roots).is_err());
+        assert!(check(&[root.join("missing")], &roots).is_err());
+    }
+}
@@ -0,0 +1,389 @@
+//! Config-defined derived artifacts
+//!
+//! Each entry of the `artifacts` config list is a small mapping rule
+//! evaluated after a scan: "for classes (or methods, or properties) with
+//! attribute X, emit an entry keyed by argument Y with arguments A, B into
+//! file Z". Teams can derive their own registries (console commands, event
+//! listeners, message handlers...) without a dedicated exporter.
+//!
+//! Arguments are named by their parameter name; `name:0` also accepts the
+//! first positional argument. Every entry carries `class` (and `member` for
+//! method and property rules) next to the selected arguments. PHP artifacts
+//! keep argument values as PHP expressions; JSON artifacts decode literals
+//! and keep other expressions as strings.
+
+use crate::metadata::{AttributeArgument, AttributeMap, PhpClassMetadata};
+use crate::openapi::{argument, matches_attribute, php_string, php_string_list};
+use crate::writer::php_literal;
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+/// One rule of the `artifacts` config list
+#[derive(Debug, Clone, Deserialize)]
+pub struct ArtifactMapping {
+    /// Attribute to look for (suffix match on a namespace boundary)
+    pub attribute: String,
+    /// Where the attribute is declared: `class` (default), `method` or `property`
+    pub on: Option<String>,
+    /// Argument whose literal keys the entries; without it the artifact is a list
+    pub key: Option<String>,
+    /// Arguments to emit (default: all of them, positional ones by index)
+    pub arguments: Option<Vec<String>>,
+    /// Artifact file
+    pub output: PathBuf,
+    /// `php` or `json` (default: by the output extension, otherwise php)
+    pub format: Option<String>,
+}
+
+impl ArtifactMapping {
+    /// Check the rule before any scanning happens
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first invalid field.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.attribute.trim().is_empty() {
+            return Err("artifact attribute must not be empty".to_string());
+        }
+        if let Some(on) = &self.on
+            && !["class", "method", "property"].contains(&on.as_str())
+        {
+            return Err(format!(
+                "Invalid artifact target '{on}' for {} (allowed: class, method, property)",
+                self.attribute
+            ));
+        }
+        if let Some(format) = &self.format
+            && !["php", "json"].contains(&format.as_str())
+        {
+            return Err(format!(
+                "Invalid artifact format '{format}' for {} (allowed: php, json)",
+                self.attribute
+            ));
+        }
+        Ok(())
+    }
+
+    fn is_json(&self) -> bool {
+        self.format.as_deref().map_or_else(
+            || self.output.extension().is_some_and(|ext| ext == "json"),
+            |format| format == "json",
+        )
+    }
+}
+
+/// Matched attribute instance: selected arguments as (name, PHP expression)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactEntry {
+    /// Entry key (`None` for list artifacts)
+    pub key: Option<String>,
+    /// Class FQCN without the leading backslash
+    pub class: String,
+    /// Method or property name for member rules
+    pub member: Option<String>,
+    pub arguments: Vec<(String, String)>,
+}
+
+/// Entries of one rule, in class name order
+#[must_use]
+pub fn collect_entries(
+    metadata_list: &[PhpClassMetadata], mapping: &ArtifactMapping,
+) -> Vec<ArtifactEntry> {
+    let mut classes: Vec<&PhpClassMetadata> = metadata_list.iter().collect();
+    classes.sort_by(|a, b| a.fqcn.cmp(&b.fqcn));
+
+    let mut entries = Vec::new();
+    for class in classes {
+        let members: Vec<(Option<&str>, &AttributeMap)> = match mapping.on.as_deref() {
+            Some("method") => class
+                .methods
+                .iter()
+                .map(|method| (Some(method.name.as_str()), &method.attributes))
+                .collect(),
+            Some("property") => class
+                .properties
+                .iter()
+                .map(|property| (Some(property.name.as_str()), &property.attributes))
+                .collect(),
+            _ => vec![(None, &class.attributes)],
+        };
+
+        let class_name = class.fqcn.trim_start_matches('\\');
+        for (member, attributes) in members {
+            let instances = attributes
+                .iter()
+                .filter(|(name, _)| matches_attribute(name, &mapping.attribute))
+                .flat_map(|(_, instances)| instances);
+
+            for args in instances {
+                let key = mapping.key.as_ref().map(|spec| {
+                    select(args, spec).and_then(php_string).unwrap_or_else(|| {
+                        member.map_or_else(
+                            || class_name.to_string(),
+                            |member| format!("{class_name}::{member}"),
+                        )
+                    })
+                });
+                entries.push(ArtifactEntry {
+                    key,
+                    class: class_name.to_string(),
+                    member: member.map(ToString::to_string),
+                    arguments: selected_arguments(args, mapping.arguments.as_deref()),
+                });
+            }
+        }
+    }
+
+    entries
+}
+
+/// Evaluate every rule and write its artifact; returns each output with its entry count
+///
+/// # Errors
+///
+/// Returns an error if an artifact cannot be written.
+pub fn write_artifacts(
+    metadata_list: &[PhpClassMetadata], mappings: &[ArtifactMapping],
+) -> Result<Vec<(PathBuf, usize)>> {
+    let mut written = Vec::with_capacity(mappings.len());
+    for mapping in mappings {
+        let entries = collect_entries(metadata_list, mapping);
+        let content = if mapping.is_json() {
+            render_json(&entries, mapping.key.is_some())?
+        } else {
+            render_php(&entries, mapping.key.is_some())
+        };
+
+        if let Some(parent) = mapping.output.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&mapping.output, content)
+            .with_context(|| format!("Failed to write artifact {}", mapping.output.display()))?;
+        written.push((mapping.output.clone(), entries.len()));
+    }
+    Ok(written)
+}
+
+/// PHP file returning the entries (keyed when `keyed`, later duplicates win)
+#[must_use]
+pub fn render_php(entries: &[ArtifactEntry], keyed: bool) -> String {
+    let mut out = String::from("<?php declare(strict_types=1);\n\nreturn [\n");
+    for entry in entries {
+        let mut fields = vec![format!("'class' => {}", php_literal(&entry.class))];
+        if let Some(member) = &entry.member {
+            fields.push(format!("'member' => {}", php_literal(member)));
+        }
+        fields.extend(
+            entry
+                .arguments
+                .iter()
+                .map(|(name, value)| format!("{} => {}", php_literal(name), value.trim())),
+        );
+
+        out.push_str("    ");
+        if keyed && let Some(key) = &entry.key {
+            let _ = write!(out, "{} => ", php_literal(key));
+        }
+        let _ = writeln!(out, "[{}],", fields.join(", "));
+    }
+    out.push_str("];\n");
+    out
+}
+
+/// Pretty-printed JSON object (keyed) or array of the entries
+///
+/// # Errors
+///
+/// Returns an error if serialization fails.
+pub fn render_json(entries: &[ArtifactEntry], keyed: bool) -> Result<String> {
+    let objects = entries.iter().map(|entry| {
+        let mut object = Map::new();
+        object.insert("class".to_string(), entry.class.clone().into());
+        if let Some(member) = &entry.member {
+            object.insert("member".to_string(), member.clone().into());
+        }
+        for (name, value) in &entry.arguments {
+            object.insert(name.clone(), json_value(value));
+        }
+        (entry.key.clone(), Value::Object(object))
+    });
+
+    let value = if keyed {
+        let map: IndexMap<String, Value> = objects
+            .map(|(key, object)| (key.unwrap_or_default(), object))
+            .collect();
+        serde_json::to_value(map)?
+    } else {
+        Value::Array(objects.map(|(_, object)| object).collect())
+    };
+    Ok(serde_json::to_string_pretty(&value)? + "\n")
+}
+
+/// Argument by spec: `name` (named only) or `name:N` (named, else positional N)
+fn select<'a>(args: &'a [AttributeArgument], spec: &str) -> Option<&'a str> {
+    match spec.rsplit_once(':') {
+        Some((name, position)) => argument(args, name, position.parse().ok()),
+        None => argument(args, spec, None),
+    }
+}
+
+fn selected_arguments(
+    args: &[AttributeArgument], specs: Option<&[String]>,
+) -> Vec<(String, String)> {
+    let Some(specs) = specs else {
+        let mut position = 0;
+        return args
+            .iter()
+            .map(|arg| match arg {
+                AttributeArgument::Named { key, value } => (key.clone(), value.clone()),
+                AttributeArgument::Positional(value) => {
+                    position += 1;
+                    ((position - 1).to_string(), value.clone())
+                },
+            })
+            .collect();
+    };
+
+    specs
+        .iter()
+        .filter_map(|spec| {
+            let name = spec
+                .rsplit_once(':')
+                .map_or(spec.as_str(), |(name, _)| name);
+            select(args, spec).map(|value| (name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// JSON value of a PHP literal; other expressions are kept as strings
+fn json_value(value: &str) -> Value {
+    let value = value.trim();
+    match value {
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        "null" => return Value::Null,
+        _ => {},
+    }
+    if let Some(text) = php_string(value) {
+        return text.into();
+    }
+    if let Ok(number) = value.parse::<i64>() {
+        return number.into();
+    }
+    if let Some(number) = value
+        .parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+    {
+        return Value::Number(number);
+    }
+    if let Some(list) = php_string_list(value) {
+        return list.into();
+    }
+    value.into()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::parser::tests::extract;
+    use serde_json::json;
+
+    const CODE: &str = r"<?php
+namespace App;
+use App\Attribute\AsCommand;
+use App\Attribute\AsListener;
+
+#[AsCommand('app:import', description: 'Import data', aliases: ['imp'])]
+final class ImportCommand {}
+
+#[AsCommand(name: 'app:export', hidden: true)]
+final class ExportCommand {}
+
+final class Listeners {
+    #[AsListener(event: UserCreated::class, priority: 10)]
+    public function onUserCreated(): void {}
+
+    public function helper(): void {}
+}
+";
+
+    fn mapping(value: Value) -> ArtifactMapping {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_keyed_class_artifact() {
+        let rule = mapping(json!({
+            "attribute": "Attribute\\AsCommand",
+            "key": "name:0",
+            "arguments": ["description", "hidden"],
+            "output": "commands.php",
+        }));
+        let entries = collect_entries(&extract("/src/App.php", CODE), &rule);
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].key.as_deref(), Some("app:export"));
+        assert_eq!(entries[1].key.as_deref(), Some("app:import"));
+        assert_eq!(
+            entries[1].arguments,
+            [("description".to_string(), "'Import data'".to_string())]
+        );
+
+        let php = render_php(&entries, true);
+        assert!(php.contains(
+            "    'app:export' => ['class' => 'App\\\\ExportCommand', 'hidden' => true],\n"
+        ));
+
+        let json: Value = serde_json::from_str(&render_json(&entries, true).unwrap()).unwrap();
+        assert_eq!(json["app:import"]["description"], "Import data");
+        assert_eq!(json["app:export"]["hidden"], true);
+    }
+
+    #[test]
+    fn test_method_rule_lists_all_arguments() {
+        let rule = mapping(json!({
+            "attribute": "AsListener",
+            "on": "method",
+            "output": "listeners.json",
+        }));
+        assert!(rule.is_json());
+        let entries = collect_entries(&extract("/src/App.php", CODE), &rule);
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].member.as_deref(), Some("onUserCreated"));
+
+        let json: Value = serde_json::from_str(&render_json(&entries, false).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            json!([{
+                "class": "App\\Listeners",
+                "member": "onUserCreated",
+                "event": "\\App\\UserCreated::class",
+                "priority": 10,
+            }])
+        );
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected() {
+        let rule = mapping(json!({"attribute": "X", "on": "function", "output": "x.php"}));
+        assert!(
+            rule.validate()
+                .unwrap_err()
+                .contains("Invalid artifact target")
+        );
+
+        let rule = mapping(json!({"attribute": "X", "format": "yaml", "output": "x.php"}));
+        assert!(
+            rule.validate()
+                .unwrap_err()
+                .contains("Invalid artifact format")
+        );
+    }
+}
@@ -0,0 +1,326 @@
+//! Attribute target and repeatability checks, with vendor stubs (`attribute_stubs`)
+//!
+//! PHP checks where an attribute may be used, and whether it may be
+//! repeated, only when `newInstance()` is called, so a method-only attribute
+//! on a property or a second `#[Entity]` fails at runtime, if ever. The flags
+//! of the `#[Attribute(...)]` declaration on the attribute class are all it
+//! takes to check them statically, but most attribute classes live in vendor
+//! packages outside the scanned paths. With `attribute_stubs` enabled, the
+//! attribute classes that are used but not scanned are looked up in
+//! Composer's classmap, and only the files declaring them are extracted.
+//! These stubs complete the declarations for the checks and count as known
+//! attributes; they are never written to the cache.
+//!
+//! Promoted constructor parameters are also properties, so attributes on
+//! constructor parameters may target either.
+
+use crate::composer::Classmap;
+use crate::metadata::{AttributeArgument, AttributeSite, PhpClassMetadata, fold_class_name};
+use crate::parser::PhpMetadataExtractor;
+use anyhow::Result;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+
+const TARGET_CLASS: u32 = 1;
+const TARGET_METHOD: u32 = 4;
+const TARGET_PROPERTY: u32 = 8;
+const TARGET_CLASS_CONSTANT: u32 = 16;
+const TARGET_PARAMETER: u32 = 32;
+const TARGET_ALL: u32 = 63;
+const IS_REPEATABLE: u32 = 64;
+
+/// `Attribute` class constants usable in the flags
+const FLAGS: [(&str, u32); 8] = [
+    ("TARGET_CLASS", TARGET_CLASS),
+    ("TARGET_FUNCTION", 2),
+    ("TARGET_METHOD", TARGET_METHOD),
+    ("TARGET_PROPERTY", TARGET_PROPERTY),
+    ("TARGET_CLASS_CONSTANT", TARGET_CLASS_CONSTANT),
+    ("TARGET_PARAMETER", TARGET_PARAMETER),
+    ("TARGET_ALL", TARGET_ALL),
+    ("IS_REPEATABLE", IS_REPEATABLE),
+];
+
+/// Flags of an attribute class
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttributeDeclaration {
+    /// `Attribute::TARGET_*` bits
+    pub targets: u32,
+    pub repeatable: bool,
+}
+
+/// An attribute used where its declaration does not allow it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MisusedAttribute {
+    /// Attribute FQCN as written to the cache
+    pub attribute: String,
+    /// Declaration using it (`Class` or `Class::member`)
+    pub usage: String,
+    pub file: PathBuf,
+    /// What the declaration does not allow
+    pub problem: String,
+}
+
+impl fmt::Display for MisusedAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} on {} ({})",
+            self.attribute,
+            self.problem,
+            self.usage,
+            self.file.display()
+        )
+    }
+}
+
+/// Attribute classes used by `classes` but not among them, extracted from
+/// the files `classmap` maps them to
+///
+/// Classes without `#[Attribute]` and files that cannot be read or parsed
+/// are left out. Results are sorted by FQCN.
+///
+/// # Errors
+///
+/// Returns an error if the PHP parser cannot be initialized.
+pub fn find_attribute_stubs(
+    classes: &[PhpClassMetadata], classmap: &Classmap,
+) -> Result<Vec<PhpClassMetadata>> {
+    let scanned: HashSet<String> = classes
+        .iter()
+        .map(|class| fold_class_name(&class.fqcn))
+        .collect();
+    let wanted: HashSet<String> = classes
+        .iter()
+        .flat_map(PhpClassMetadata::attribute_names)
+        .map(|attribute| fold_class_name(attribute))
+        .filter(|attribute| !scanned.contains(attribute))
+        .collect();
+    let files: BTreeSet<&PathBuf> = classmap
+        .iter()
+        .filter(|(fqcn, _)| wanted.contains(&fold_class_name(fqcn)))
+        .map(|(_, file)| file)
+        .collect();
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut extractor = PhpMetadataExtractor::new()?;
+    let mut stubs = Vec::new();
+    for file in files {
+        let Ok(source) = std::fs::read_to_string(file) else {
+            continue;
+        };
+        let Ok(metadata) = extractor.extract_metadata(&source, file.clone()) else {
+            continue;
+        };
+        stubs.extend(metadata.into_iter().filter(|class| {
+            wanted.contains(&fold_class_name(&class.fqcn))
+                && attribute_arguments(class).is_some()
+        }));
+    }
+    stubs.sort_by(|a, b| a.fqcn.cmp(&b.fqcn));
+    Ok(stubs)
+}
+
+/// Flags of the `#[Attribute]` on `class`
+///
+/// `None` if `class` is not an attribute class, or its flags are not a
+/// literal combination of integers and `Attribute` constants.
+#[must_use]
+pub fn declaration(class: &PhpClassMetadata) -> Option<AttributeDeclaration> {
+    let flags = attribute_arguments(class)?.iter().find_map(|arg| match arg {
+        AttributeArgument::Positional(value) => Some(value.as_str()),
+        AttributeArgument::Named { key, value } if key == "flags" => Some(value.as_str()),
+        AttributeArgument::Named { .. } => None,
+    });
+    let flags = match flags {
+        None => TARGET_ALL,
+        Some(expression) => parse_flags(expression)?,
+    };
+    Some(AttributeDeclaration {
+        targets: flags & TARGET_ALL,
+        repeatable: flags & IS_REPEATABLE != 0,
+    })
+}
+
+/// Arguments of the `#[Attribute]` on `class`, if it is an attribute class
+fn attribute_arguments(class: &PhpClassMetadata) -> Option<&[AttributeArgument]> {
+    let (_, instances) = class
+        .attributes
+        .iter()
+        .find(|(name, _)| fold_class_name(name) == "attribute")?;
+    Some(instances.first().map_or(&[][..], Vec::as_slice))
+}
+
+fn parse_flags(expression: &str) -> Option<u32> {
+    expression.split('|').map(str::trim).try_fold(0, |flags, token| {
+        let value = token.parse::<u32>().ok().or_else(|| {
+            let name = token.trim_start_matches('\\').split_once("::")?;
+            if !name.0.eq_ignore_ascii_case("Attribute") {
+                return None;
+            }
+            FLAGS.iter().find(|(flag, _)| *flag == name.1).map(|(_, value)| *value)
+        })?;
+        Some(flags | value)
+    })
+}
+
+/// Attribute uses in `classes` that the declaration of their class (scanned
+/// or among `stubs`) does not allow
+///
+/// Attributes without a known declaration are skipped. Results are in scan
+/// order.
+#[must_use]
+pub fn find_misused_attributes(
+    classes: &[PhpClassMetadata], stubs: &[PhpClassMetadata],
+) -> Vec<MisusedAttribute> {
+    let declarations: HashMap<String, AttributeDeclaration> = classes
+        .iter()
+        .chain(stubs)
+        .filter_map(|class| Some((fold_class_name(&class.fqcn), declaration(class)?)))
+        .collect();
+
+    let mut misused = Vec::new();
+    for class in classes {
+        for (usage, site, attributes) in class.attribute_maps() {
+            let (target, target_name) = site_targets(site);
+            for (attribute, instances) in attributes {
+                let Some(declaration) = declarations.get(&fold_class_name(attribute)) else {
+                    continue;
+                };
+                let mut report = |problem: String| {
+                    misused.push(MisusedAttribute {
+                        attribute: attribute.clone(),
+                        usage: usage.clone(),
+                        file: class.file.clone(),
+                        problem,
+                    });
+                };
+                if declaration.targets & target == 0 {
+                    report(format!(
+                        "is not allowed on a {target_name} (targets: {})",
+                        target_names(declaration.targets)
+                    ));
+                }
+                if instances.len() > 1 && !declaration.repeatable {
+                    report(format!(
+                        "is repeated {} times but not IS_REPEATABLE",
+                        instances.len()
+                    ));
+                }
+            }
+        }
+    }
+    misused
+}
+
+/// Targets an attribute at `site` satisfies, and their name
+const fn site_targets(site: AttributeSite) -> (u32, &'static str) {
+    match site {
+        AttributeSite::Class => (TARGET_CLASS, "class"),
+        AttributeSite::Method => (TARGET_METHOD, "method"),
+        AttributeSite::Parameter { constructor: true } => {
+            (TARGET_PARAMETER | TARGET_PROPERTY, "parameter")
+        },
+        AttributeSite::Parameter { constructor: false } => (TARGET_PARAMETER, "parameter"),
+        AttributeSite::Property => (TARGET_PROPERTY, "property"),
+        AttributeSite::EnumCase => (TARGET_CLASS_CONSTANT, "class constant"),
+    }
+}
+
+fn target_names(targets: u32) -> String {
+    let names: Vec<&str> = FLAGS
+        .iter()
+        .filter(|(_, value)| value.is_power_of_two() && targets & value != 0)
+        .map(|(flag, _)| flag.trim_start_matches("TARGET_"))
+        .collect();
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(", ").to_lowercase().replace('_', " ")
+    }
+}
+
+/// Composer classmap of the project the scanned paths belong to, if any
+#[must_use]
+pub fn find_classmap(scan_paths: &[PathBuf]) -> Option<PathBuf> {
+    crate::vendor::composer_dir(scan_paths)
+        .map(|dir| dir.join("autoload_classmap.php"))
+        .filter(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_vendor_stubs_complete_the_declarations() {
+        let temp_dir = TempDir::new().unwrap();
+        let route_file = temp_dir.path().join("Route.php");
+        std::fs::write(
+            &route_file,
+            r"<?php
+namespace Vendor\Routing;
+use Attribute;
+#[Attribute(Attribute::TARGET_CLASS | Attribute::TARGET_METHOD)]
+final class Route {}
+final class Router {}
+",
+        )
+        .unwrap();
+        let classmap = Classmap::from([
+            ("Vendor\\Routing\\Route".to_string(), route_file.clone()),
+            ("Vendor\\Routing\\Router".to_string(), route_file),
+        ]);
+        let source = r"<?php
+namespace App;
+use Vendor\Routing\Route;
+#[\Attribute(\Attribute::TARGET_PROPERTY)]
+final class Column {}
+#[Route('/users')]
+final class UserController {
+    #[Route('/list')] #[Route('/all')]
+    public function list() {}
+    #[Route('/id')] #[Column]
+    public $id;
+    public function __construct(#[Column] public string $name) {}
+}
+";
+        let classes = PhpMetadataExtractor::new()
+            .unwrap()
+            .extract_metadata(source, PathBuf::from("/src/UserController.php"))
+            .unwrap();
+
+        let stubs = find_attribute_stubs(&classes, &classmap).unwrap();
+        assert_eq!(stubs.len(), 1);
+        assert_eq!(stubs[0].fqcn, "\\Vendor\\Routing\\Route");
+
+        let problems: Vec<String> = find_misused_attributes(&classes, &stubs)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                "\\Vendor\\Routing\\Route is repeated 2 times but not IS_REPEATABLE on \
+                 \\App\\UserController::list() (/src/UserController.php)",
+                "\\Vendor\\Routing\\Route is not allowed on a property (targets: class, method) \
+                 on \\App\\UserController::$id (/src/UserController.php)",
+            ]
+        );
+        assert!(find_misused_attributes(&classes, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_declaration_flags() {
+        let flags = |expression: &str| parse_flags(expression);
+        assert_eq!(flags("\\Attribute::TARGET_METHOD | \\Attribute::IS_REPEATABLE"), Some(68));
+        assert_eq!(flags("Attribute::TARGET_ALL"), Some(63));
+        assert_eq!(flags("5"), Some(5));
+        assert_eq!(flags("self::TARGETS"), None);
+    }
+}
@@ -0,0 +1,326 @@
+//! Scan throughput benchmark (`discovery:benchmark`)
+//!
+//! Runs the scan pipeline over a real project several times and times each
+//! phase separately: file discovery, parsing and cache rendering. The first
+//! run is reported as cold (fresh extractors, possibly cold page cache), the
+//! median of the remaining runs as warm. Results can be stored as a JSON
+//! baseline and compared against later runs to evaluate hardware and tuning.
+
+use crate::scanner::{
+    ExtractorPool, ignore_matcher, php_files_under, scan_files_detailed_with_pool,
+};
+use crate::writer::render_cache;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Benchmark parameters
+#[derive(Debug, Clone)]
+pub struct BenchmarkOptions {
+    pub paths: Vec<PathBuf>,
+    pub ignore: Vec<String>,
+    /// Total number of runs, including the cold one
+    pub iterations: usize,
+    pub max_file_size: u64,
+    /// Cache format rendered in the render phase ("php" or "json")
+    pub format: String,
+}
+
+/// Wall time of each phase of one run, in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTimings {
+    pub discover_ms: f64,
+    pub parse_ms: f64,
+    pub render_ms: f64,
+}
+
+impl PhaseTimings {
+    #[must_use]
+    pub fn total_ms(&self) -> f64 {
+        self.discover_ms + self.parse_ms + self.render_ms
+    }
+}
+
+/// Outcome of a benchmark, also the on-disk baseline format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    /// PHP files found by discovery
+    pub files: usize,
+    /// Total size of those files
+    pub bytes: u64,
+    /// Declarations extracted
+    pub classes: usize,
+    /// Size of the rendered cache
+    pub output_bytes: usize,
+    pub format: String,
+    pub iterations: usize,
+    pub cold: PhaseTimings,
+    /// Median of the runs after the first (`None` with a single iteration)
+    pub warm: Option<PhaseTimings>,
+}
+
+impl BenchmarkReport {
+    /// Timings used for comparisons: warm when available, otherwise cold
+    #[must_use]
+    pub fn steady(&self) -> PhaseTimings {
+        self.warm.unwrap_or(self.cold)
+    }
+
+    /// Load a baseline written by [`Self::save`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a benchmark report.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid baseline {}", path.display()))
+    }
+
+    /// Store this report as a baseline, replacing the previous one atomically
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the baseline cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        crate::writer::write_if_changed(path, content.as_bytes())
+            .with_context(|| format!("Failed to write baseline {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Run the benchmark
+///
+/// # Errors
+///
+/// Returns an error if no iterations are requested, no PHP files are found
+/// or rendering the cache fails.
+pub fn run_benchmark(options: &BenchmarkOptions) -> Result<BenchmarkReport> {
+    if options.iterations == 0 {
+        bail!("At least one iteration is required");
+    }
+
+    let pool = ExtractorPool::default();
+    let mut report = run_once(options, &pool)?;
+    if report.files == 0 {
+        bail!("No PHP files found in {:?}", options.paths);
+    }
+
+    let warm_runs = (1..options.iterations)
+        .map(|_| run_once(options, &pool).map(|run| run.cold))
+        .collect::<Result<Vec<_>>>()?;
+    report.warm = median(&warm_runs);
+    Ok(report)
+}
+
+/// One pass over the pipeline, reported with its timings as `cold`
+fn run_once(options: &BenchmarkOptions, pool: &ExtractorPool) -> Result<BenchmarkReport> {
+    let start = Instant::now();
+    let matcher = ignore_matcher(&options.paths, &options.ignore);
+    let files: Vec<PathBuf> = options
+        .paths
+        .iter()
+        .flat_map(|root| php_files_under(root, matcher.as_ref()))
+        .collect();
+    let discover = start.elapsed();
+
+    let bytes = files
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|meta| meta.len())
+        .sum();
+
+    let start = Instant::now();
+    let scans = scan_files_detailed_with_pool(&files, options.max_file_size, pool);
+    let metadata: Vec<_> = scans.into_iter().flat_map(|scan| scan.classes).collect();
+    let parse = start.elapsed();
+
+    let start = Instant::now();
+    let output = black_box(render_cache(&metadata, &options.format, false, None)?);
+    let render = start.elapsed();
+
+    Ok(BenchmarkReport {
+        files: files.len(),
+        bytes,
+        classes: metadata.len(),
+        output_bytes: output.len(),
+        format: options.format.clone(),
+        iterations: options.iterations,
+        cold: PhaseTimings {
+            discover_ms: millis(discover),
+            parse_ms: millis(parse),
+            render_ms: millis(render),
+        },
+        warm: None,
+    })
+}
+
+/// Human-readable per-phase throughput
+#[must_use]
+pub fn format_report(report: &BenchmarkReport) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{} files ({:.1} MB), {} classes, {} cache {:.1} MB, {} iteration(s)",
+        report.files,
+        megabytes(report.bytes),
+        report.classes,
+        report.format,
+        megabytes(report.output_bytes as u64),
+        report.iterations,
+    );
+
+    let mut runs = vec![("cold", report.cold)];
+    if let Some(warm) = report.warm {
+        runs.push(("warm", warm));
+    }
+
+    for (label, t) in runs {
+        let _ = writeln!(out, "{label}: {:.1} ms total", t.total_ms());
+        let _ = writeln!(
+            out,
+            "  discover {:>9.1} ms  {:>10.0} files/s",
+            t.discover_ms,
+            per_second(report.files as u64, t.discover_ms),
+        );
+        let _ = writeln!(
+            out,
+            "  parse    {:>9.1} ms  {:>10.0} files/s  {:>8.1} MB/s  {:>10.0} classes/s",
+            t.parse_ms,
+            per_second(report.files as u64, t.parse_ms),
+            megabytes(report.bytes) * 1000.0 / t.parse_ms.max(f64::EPSILON),
+            per_second(report.classes as u64, t.parse_ms),
+        );
+        let _ = writeln!(
+            out,
+            "  render   {:>9.1} ms  {:>10.0} classes/s",
+            t.render_ms,
+            per_second(report.classes as u64, t.render_ms),
+        );
+    }
+
+    out
+}
+
+/// Per-phase change against a baseline (negative percentages are faster)
+#[must_use]
+pub fn format_comparison(report: &BenchmarkReport, baseline: &BenchmarkReport) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "vs baseline ({} files, {} classes):",
+        baseline.files, baseline.classes
+    );
+    if baseline.files != report.files || baseline.format != report.format {
+        let _ = writeln!(
+            out,
+            "  note: baseline was taken on a different file set or format"
+        );
+    }
+
+    let current = report.steady();
+    let base = baseline.steady();
+    for (phase, now, then) in [
+        ("discover", current.discover_ms, base.discover_ms),
+        ("parse", current.parse_ms, base.parse_ms),
+        ("render", current.render_ms, base.render_ms),
+        ("total", current.total_ms(), base.total_ms()),
+    ] {
+        let change = (now - then) / then.max(f64::EPSILON) * 100.0;
+        let _ = writeln!(
+            out,
+            "  {phase:<8} {then:>9.1} ms -> {now:>9.1} ms  {change:>+7.1}%"
+        );
+    }
+
+    out
+}
+
+/// Phase-wise median of `runs`
+fn median(runs: &[PhaseTimings]) -> Option<PhaseTimings> {
+    if runs.is_empty() {
+        return None;
+    }
+    let pick = |f: fn(&PhaseTimings) -> f64| {
+        let mut values: Vec<f64> = runs.iter().map(f).collect();
+        values.sort_by(f64::total_cmp);
+        values[values.len() / 2]
+    };
+    Some(PhaseTimings {
+        discover_ms: pick(|t| t.discover_ms),
+        parse_ms: pick(|t| t.parse_ms),
+        render_ms: pick(|t| t.render_ms),
+    })
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn megabytes(bytes: u64) -> f64 {
+    bytes as f64 / 1024.0 / 1024.0
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn per_second(count: u64, ms: f64) -> f64 {
+    count as f64 * 1000.0 / ms.max(f64::EPSILON)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_run_benchmark_and_compare_with_baseline() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("A.php"), "<?php class A {}").unwrap();
+        fs::write(src.join("B.php"), "<?php interface B {} enum C {}").unwrap();
+
+        let options = BenchmarkOptions {
+            paths: vec![src],
+            ignore: vec![],
+            iterations: 3,
+            max_file_size: crate::scanner::DEFAULT_MAX_FILE_SIZE,
+            format: "php".to_string(),
+        };
+        let report = run_benchmark(&options).unwrap();
+
+        assert_eq!(report.files, 2);
+        assert_eq!(report.classes, 3);
+        assert!(report.output_bytes > 0);
+        assert!(report.warm.is_some());
+        assert!(format_report(&report).contains("classes/s"));
+
+        let baseline_path = temp_dir.path().join("bench/baseline.json");
+        report.save(&baseline_path).unwrap();
+        let baseline = BenchmarkReport::load(&baseline_path).unwrap();
+        let comparison = format_comparison(&report, &baseline);
+        assert!(comparison.contains("parse"));
+        assert!(!comparison.contains("different file set"));
+    }
+
+    #[test]
+    fn test_run_benchmark_requires_php_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = BenchmarkOptions {
+            paths: vec![temp_dir.path().to_path_buf()],
+            ignore: vec![],
+            iterations: 1,
+            max_file_size: crate::scanner::DEFAULT_MAX_FILE_SIZE,
+            format: "php".to_string(),
+        };
+
+        assert!(run_benchmark(&options).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+//! Lightweight class lists (`class_lists`)
+//!
+//! Each entry of the `class_lists` config list writes the FQCN and file of
+//! every declaration of one kind (interfaces or abstract classes) next to
+//! the cache, so consumers that only resolve bindings, such as a container
+//! compiler, need not load the full cache. Lists follow the cache: one-shot
+//! scans write them after the cache, the daemon on every flush, and a list
+//! whose content did not change is left untouched.
+
+use crate::metadata::PhpClassMetadata;
+use crate::writer::escape_php_string;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+/// Kinds a list can select
+pub const LIST_KINDS: [&str; 2] = ["interface", "abstract"];
+
+/// One entry of the `class_lists` config list
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClassList {
+    /// `interface` or `abstract` (abstract classes)
+    pub kind: String,
+    /// List file
+    pub output: PathBuf,
+    /// `php` or `json` (default: by the output extension, otherwise php)
+    pub format: Option<String>,
+}
+
+impl ClassList {
+    /// Check the entry before any scanning happens
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first invalid field.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if !LIST_KINDS.contains(&self.kind.as_str()) {
+            return Err(format!(
+                "Invalid class list kind '{}' (allowed: {})",
+                self.kind,
+                LIST_KINDS.join(", ")
+            ));
+        }
+        if let Some(format) = &self.format
+            && !["php", "json"].contains(&format.as_str())
+        {
+            return Err(format!(
+                "Invalid class list format '{format}' for {} (allowed: php, json)",
+                self.output.display()
+            ));
+        }
+        Ok(())
+    }
+
+    fn is_json(&self) -> bool {
+        self.format.as_deref().map_or_else(
+            || self.output.extension().is_some_and(|ext| ext == "json"),
+            |format| format == "json",
+        )
+    }
+
+    fn selects(&self, class: &PhpClassMetadata) -> bool {
+        match self.kind.as_str() {
+            "interface" => class.kind == "interface",
+            "abstract" => class.kind == "class" && class.modifiers.is_abstract,
+            _ => false,
+        }
+    }
+}
+
+/// Classes selected by `list` as (FQCN without the leading backslash, file), by FQCN
+#[must_use]
+pub fn collect(metadata_list: &[PhpClassMetadata], list: &ClassList) -> Vec<(String, String)> {
+    let mut entries: Vec<(String, String)> = metadata_list
+        .iter()
+        .filter(|class| list.selects(class))
+        .map(|class| {
+            (
+                class.fqcn.trim_start_matches('\\').to_string(),
+                class.file.to_string_lossy().into_owned(),
+            )
+        })
+        .collect();
+    entries.sort();
+    entries
+}
+
+/// PHP file returning `FQCN => file`
+#[must_use]
+pub fn render_php(entries: &[(String, String)]) -> String {
+    let mut out = String::from("<?php declare(strict_types=1);\n\nreturn [\n");
+    for (fqcn, file) in entries {
+        let _ = writeln!(
+            out,
+            "    '{}' => '{}',",
+            escape_php_string(fqcn),
+            escape_php_string(file)
+        );
+    }
+    out.push_str("];\n");
+    out
+}
+
+/// Pretty-printed JSON object `FQCN => file`
+///
+/// # Errors
+///
+/// Returns an error if serialization fails.
+pub fn render_json(entries: &[(String, String)]) -> Result<String> {
+    let object: serde_json::Map<String, serde_json::Value> = entries
+        .iter()
+        .map(|(fqcn, file)| (fqcn.clone(), serde_json::Value::String(file.clone())))
+        .collect();
+    let mut json = serde_json::to_string_pretty(&object)?;
+    json.push('\n');
+    Ok(json)
+}
+
+/// Write every list whose content changed; returns each output with its entry count
+///
+/// # Errors
+///
+/// Returns an error if a list cannot be written.
+pub fn write_class_lists(
+    metadata_list: &[PhpClassMetadata], lists: &[ClassList],
+) -> Result<Vec<(PathBuf, usize)>> {
+    let mut written = Vec::with_capacity(lists.len());
+    for list in lists {
+        let entries = collect(metadata_list, list);
+        let content = if list.is_json() {
+            render_json(&entries)?
+        } else {
+            render_php(&entries)
+        };
+        crate::writer::write_if_changed(&list.output, content.as_bytes())
+            .with_context(|| format!("Failed to write class list {}", list.output.display()))?;
+        written.push((list.output.clone(), entries.len()));
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use tempfile::TempDir;
+
+    fn declaration(fqcn: &str, kind: &str, is_abstract: bool) -> PhpClassMetadata {
+        let mut class = PhpClassMetadata::new(
+            fqcn.to_string(),
+            PathBuf::from(format!("/src/{}.php", fqcn.rsplit('\\').next().unwrap())),
+            kind.to_string(),
+        );
+        class.modifiers.is_abstract = is_abstract;
+        class
+    }
+
+    fn list(kind: &str, output: PathBuf) -> ClassList {
+        ClassList {
+            kind: kind.to_string(),
+            output,
+            format: None,
+        }
+    }
+
+    #[test]
+    fn test_lists_select_interfaces_and_abstract_classes() {
+        let metadata = [
+            declaration("\\App\\Repository", "interface", false),
+            declaration("\\App\\BaseController", "class", true),
+            declaration("\\App\\UserController", "class", false),
+            declaration("\\App\\Loggable", "trait", false),
+        ];
+
+        assert_eq!(
+            collect(&metadata, &list("interface", PathBuf::new())),
+            [(
+                "App\\Repository".to_string(),
+                "/src/Repository.php".to_string()
+            )]
+        );
+        assert_eq!(
+            render_php(&collect(&metadata, &list("abstract", PathBuf::new()))),
+            "<?php declare(strict_types=1);\n\nreturn [\n    'App\\\\BaseController' => '/src/BaseController.php',\n];\n"
+        );
+        assert!(list("enum", PathBuf::new()).validate().is_err());
+    }
+
+    #[test]
+    fn test_write_class_lists_by_extension() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("interfaces.json");
+        let metadata = [declaration("\\App\\Repository", "interface", false)];
+
+        let written = write_class_lists(&metadata, &[list("interface", output.clone())]).unwrap();
+
+        assert_eq!(written, [(output.clone(), 1)]);
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(json["App\\Repository"], "/src/Repository.php");
+    }
+}
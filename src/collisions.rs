@@ -0,0 +1,182 @@
+//! Names that only differ in case
+//!
+//! PHP class names are case-insensitive, and so are the default filesystems
+//! of macOS and Windows, while Linux is case-sensitive. Code that works on a
+//! developer's Mac can therefore break in production, or the other way round:
+//! `User.php` and `user.php` are one file on macOS but two on Linux, and a
+//! class `User` in `user.php` only autoloads (PSR-4) where file names ignore
+//! case. The scanner itself treats every path and name as distinct; this
+//! module finds the collisions so they can be reported as warnings.
+
+use crate::metadata::PhpClassMetadata;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A name clash that depends on case sensitivity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaseCollision {
+    /// Files whose paths differ only in case
+    Path { paths: Vec<PathBuf> },
+    /// Declarations whose FQCNs are equal ignoring case, with their files
+    Fqcn {
+        declarations: Vec<(String, PathBuf)>,
+    },
+    /// A declaration whose file name matches its short name only ignoring case
+    FileName { fqcn: String, file: PathBuf },
+}
+
+impl fmt::Display for CaseCollision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Path { paths } => {
+                let paths: Vec<_> = paths.iter().map(|p| p.display().to_string()).collect();
+                write!(
+                    f,
+                    "{} differ only in case and are the same file on case-insensitive filesystems",
+                    paths.join(", ")
+                )
+            },
+            Self::Fqcn { declarations } => {
+                let list: Vec<_> = declarations
+                    .iter()
+                    .map(|(fqcn, file)| format!("{fqcn} ({})", file.display()))
+                    .collect();
+                write!(
+                    f,
+                    "{} declare the same class (PHP class names are case-insensitive)",
+                    list.join(", ")
+                )
+            },
+            Self::FileName { fqcn, file } => write!(
+                f,
+                "{fqcn} is declared in {}; autoloading it fails on case-sensitive filesystems",
+                file.display()
+            ),
+        }
+    }
+}
+
+/// Find case collisions among the files and declarations of a scan
+///
+/// Results are ordered: path collisions, then FQCN collisions, then file
+/// name mismatches, each sorted by name.
+#[must_use]
+pub fn find_case_collisions(classes: &[PhpClassMetadata]) -> Vec<CaseCollision> {
+    let mut collisions = Vec::new();
+
+    let mut paths: BTreeMap<String, Vec<&Path>> = BTreeMap::new();
+    for class in classes {
+        let group = paths
+            .entry(fold(&class.file.to_string_lossy()))
+            .or_default();
+        if !group.contains(&class.file.as_path()) {
+            group.push(&class.file);
+        }
+    }
+    collisions.extend(
+        paths
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .map(|mut group| {
+                group.sort();
+                CaseCollision::Path {
+                    paths: group.into_iter().map(Path::to_path_buf).collect(),
+                }
+            }),
+    );
+
+    let mut fqcns: BTreeMap<String, Vec<(String, PathBuf)>> = BTreeMap::new();
+    for class in classes {
+        fqcns
+            .entry(fold(&class.fqcn))
+            .or_default()
+            .push((class.fqcn.clone(), class.file.clone()));
+    }
+    collisions.extend(fqcns.into_values().filter(|group| group.len() > 1).map(
+        |mut declarations| {
+            declarations.sort();
+            CaseCollision::Fqcn { declarations }
+        },
+    ));
+
+    // Autoloaders only load `.php` files, so templates are not checked
+    let mut mismatches: Vec<_> = classes
+        .iter()
+        .filter(|class| class.file.extension().is_some_and(|ext| ext == "php"))
+        .filter(|class| {
+            let short_name = class.fqcn.rsplit('\\').next().unwrap_or_default();
+            class
+                .file
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| stem != short_name && fold(stem) == fold(short_name))
+        })
+        .map(|class| CaseCollision::FileName {
+            fqcn: class.fqcn.clone(),
+            file: class.file.clone(),
+        })
+        .collect();
+    mismatches.sort_by_cached_key(ToString::to_string);
+    collisions.extend(mismatches);
+
+    collisions
+}
+
+/// Case-folded key (PHP folds ASCII only)
+fn fold(name: &str) -> String {
+    name.to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(fqcn: &str, file: &str) -> PhpClassMetadata {
+        PhpClassMetadata::new(fqcn.to_string(), PathBuf::from(file), "class".to_string())
+    }
+
+    #[test]
+    fn test_finds_case_collisions() {
+        let classes = [
+            class("\\App\\User", "/src/User.php"),
+            class("\\App\\user", "/src/user.php"),
+            class("\\App\\Order", "/src/order.php"),
+            class("\\App\\Invoice", "/src/Invoice.php"),
+        ];
+
+        let collisions = find_case_collisions(&classes);
+
+        assert_eq!(
+            collisions,
+            vec![
+                CaseCollision::Path {
+                    paths: vec![
+                        PathBuf::from("/src/User.php"),
+                        PathBuf::from("/src/user.php")
+                    ],
+                },
+                CaseCollision::Fqcn {
+                    declarations: vec![
+                        ("\\App\\User".to_string(), PathBuf::from("/src/User.php")),
+                        ("\\App\\user".to_string(), PathBuf::from("/src/user.php")),
+                    ],
+                },
+                CaseCollision::FileName {
+                    fqcn: "\\App\\Order".to_string(),
+                    file: PathBuf::from("/src/order.php"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_same_file_with_several_classes_is_not_a_collision() {
+        let classes = [
+            class("\\App\\Status", "/src/Status.php"),
+            class("\\App\\StatusLabel", "/src/Status.php"),
+        ];
+
+        assert!(find_case_collisions(&classes).is_empty());
+    }
+}
@@ -0,0 +1,264 @@
+//! Cross-check against Composer's classmap (`discovery:verify-classmap`)
+//!
+//! `vendor/composer/autoload_classmap.php` lists every class Composer can
+//! autoload with its file. After `composer dump-autoload --optimize` it
+//! covers the PSR-4 and PSR-0 namespaces too, which makes it an independent
+//! inventory of the project: a class it maps under the scanned paths but the
+//! cache lacks is a scanner blind spot, and a cached class it does not map
+//! cannot be autoloaded.
+//!
+//! The classmap is read as Composer generates it: `'Fqcn' => $baseDir .
+//! '/path'` entries, with `$vendorDir` and `$baseDir` derived from the file's
+//! location the way its own header derives them.
+
+use crate::metadata::PhpClassMetadata;
+use crate::reader::string_literal;
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Default classmap location, relative to the project root
+pub const DEFAULT_CLASSMAP: &str = "vendor/composer/autoload_classmap.php";
+
+/// Classes of a Composer classmap, by FQCN (without leading backslash)
+pub type Classmap = BTreeMap<String, PathBuf>;
+
+/// Load a Composer classmap, resolving its paths
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is not a classmap
+/// generated by Composer.
+pub fn load_classmap(path: &Path) -> Result<Classmap> {
+    let content = crate::retry::with_retry(path, || std::fs::read_to_string(path))
+        .with_context(|| format!("Failed to read classmap {}", path.display()))?;
+    let composer_dir = std::path::absolute(path)?
+        .parent()
+        .map_or_else(PathBuf::new, Path::to_path_buf);
+    parse_classmap(&content, &composer_dir)
+        .with_context(|| format!("Invalid classmap {}", path.display()))
+}
+
+/// Parse classmap content; `composer_dir` is the directory it was read from
+///
+/// # Errors
+///
+/// Returns an error if an entry refers to an unknown variable or the content
+/// has no `return array(` block.
+pub fn parse_classmap(content: &str, composer_dir: &Path) -> Result<Classmap> {
+    // $vendorDir = dirname(__DIR__);
+    let vendor_dir = composer_dir.parent().unwrap_or(composer_dir).to_path_buf();
+    let mut base_dir = vendor_dir.parent().unwrap_or(&vendor_dir).to_path_buf();
+    let mut classmap = Classmap::new();
+    let mut in_array = false;
+
+    for line in content.lines().map(str::trim) {
+        if let Some(expr) = line.strip_prefix("$baseDir = ") {
+            // dirname($vendorDir) nested once per level above the vendor dir
+            let levels = expr.matches("dirname(").count();
+            base_dir = vendor_dir
+                .ancestors()
+                .nth(levels)
+                .unwrap_or(&vendor_dir)
+                .to_path_buf();
+        } else if line.starts_with("return array(") || line.starts_with("return [") {
+            in_array = true;
+        } else if in_array && line.starts_with('\'') {
+            let (fqcn, rest) = string_literal(line).context("Unterminated class name")?;
+            let expr = rest
+                .trim_start()
+                .strip_prefix("=>")
+                .with_context(|| format!("Expected '=>' after {fqcn}"))?
+                .trim()
+                .trim_end_matches(',');
+            let file = resolve(expr, &vendor_dir, &base_dir)
+                .with_context(|| format!("Unsupported path for {fqcn}: {expr}"))?;
+            classmap.insert(fqcn, file);
+        }
+    }
+
+    if !in_array {
+        bail!("No 'return array(' block found");
+    }
+    Ok(classmap)
+}
+
+/// Evaluate `$baseDir . '/path'`, `$vendorDir . '/path'` or `'path'`
+fn resolve(expr: &str, vendor_dir: &Path, base_dir: &Path) -> Option<PathBuf> {
+    let (root, literal) = if let Some(rest) = expr.strip_prefix("$baseDir") {
+        (
+            Some(base_dir),
+            rest.trim_start().strip_prefix('.')?.trim_start(),
+        )
+    } else if let Some(rest) = expr.strip_prefix("$vendorDir") {
+        (
+            Some(vendor_dir),
+            rest.trim_start().strip_prefix('.')?.trim_start(),
+        )
+    } else {
+        (None, expr)
+    };
+    let (path, rest) = string_literal(literal)?;
+    if !rest.trim().is_empty() {
+        return None;
+    }
+    Some(root.map_or_else(
+        || PathBuf::from(&path),
+        |root| root.join(path.trim_start_matches('/')),
+    ))
+}
+
+/// A class known to one side only
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClassLocation {
+    pub class: String,
+    pub file: PathBuf,
+}
+
+/// Differences between the cache and the classmap
+#[derive(Debug, Default, Serialize)]
+pub struct ClassmapDiff {
+    /// Mapped under the scanned paths, but not in the cache
+    pub missing_from_cache: Vec<ClassLocation>,
+    /// In the cache, but not mapped
+    pub missing_from_classmap: Vec<ClassLocation>,
+}
+
+impl ClassmapDiff {
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.missing_from_cache.is_empty() && self.missing_from_classmap.is_empty()
+    }
+}
+
+/// Compare cached classes with the classmap entries `scanned` accepts
+///
+/// Class names are compared ignoring case and a leading backslash, as PHP
+/// resolves them.
+#[must_use]
+pub fn compare(
+    classmap: &Classmap, classes: &[PhpClassMetadata], scanned: impl Fn(&Path) -> bool,
+) -> ClassmapDiff {
+    let key = |fqcn: &str| fqcn.trim_start_matches('\\').to_ascii_lowercase();
+    let cached: HashSet<String> = classes.iter().map(|class| key(&class.fqcn)).collect();
+    let mapped: HashSet<String> = classmap.keys().map(|fqcn| key(fqcn)).collect();
+
+    let missing_from_cache = classmap
+        .iter()
+        .filter(|(fqcn, file)| !cached.contains(&key(fqcn)) && scanned(file))
+        .map(|(fqcn, file)| ClassLocation {
+            class: fqcn.clone(),
+            file: file.clone(),
+        })
+        .collect();
+
+    let mut missing_from_classmap: Vec<ClassLocation> = classes
+        .iter()
+        .filter(|class| !mapped.contains(&key(&class.fqcn)))
+        .map(|class| ClassLocation {
+            class: class.fqcn.trim_start_matches('\\').to_string(),
+            file: class.file.clone(),
+        })
+        .collect();
+    missing_from_classmap.sort_by(|a, b| a.class.cmp(&b.class));
+
+    ClassmapDiff {
+        missing_from_cache,
+        missing_from_classmap,
+    }
+}
+
+/// Text report: one line per difference, then a summary line
+#[must_use]
+pub fn format_diff(diff: &ClassmapDiff) -> String {
+    let mut report = String::new();
+    for entry in &diff.missing_from_cache {
+        let _ = writeln!(
+            report,
+            "missing from cache: {} ({})",
+            entry.class,
+            entry.file.display()
+        );
+    }
+    for entry in &diff.missing_from_classmap {
+        let _ = writeln!(
+            report,
+            "missing from classmap: {} ({})",
+            entry.class,
+            entry.file.display()
+        );
+    }
+    let _ = writeln!(
+        report,
+        "Classmap check: {} missing from cache, {} missing from classmap",
+        diff.missing_from_cache.len(),
+        diff.missing_from_classmap.len()
+    );
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    const CLASSMAP: &str = r"<?php
+
+// autoload_classmap.php @generated by Composer
+
+$vendorDir = dirname(__DIR__);
+$baseDir = dirname($vendorDir);
+
+return array(
+    'App\\Entity\\User' => $baseDir . '/src/Entity/User.php',
+    'App\\Legacy\\Helper' => $baseDir . '/src/Legacy/helpers.php',
+    'Composer\\InstalledVersions' => $vendorDir . '/composer/InstalledVersions.php',
+);
+";
+
+    #[test]
+    fn test_parse_classmap() {
+        let classmap = parse_classmap(CLASSMAP, Path::new("/app/vendor/composer")).unwrap();
+
+        assert_eq!(
+            classmap["App\\Entity\\User"],
+            PathBuf::from("/app/src/Entity/User.php")
+        );
+        assert_eq!(
+            classmap["Composer\\InstalledVersions"],
+            PathBuf::from("/app/vendor/composer/InstalledVersions.php")
+        );
+    }
+
+    #[test]
+    fn test_compare_within_scanned_paths() {
+        let classmap = parse_classmap(CLASSMAP, Path::new("/app/vendor/composer")).unwrap();
+        let classes = [
+            PhpClassMetadata::new(
+                "\\App\\Entity\\user".to_string(),
+                PathBuf::from("/app/src/Entity/User.php"),
+                "class".to_string(),
+            ),
+            PhpClassMetadata::new(
+                "\\App\\Generated\\Proxy".to_string(),
+                PathBuf::from("/app/src/Generated/Proxy.php"),
+                "class".to_string(),
+            ),
+        ];
+
+        let diff = compare(&classmap, &classes, |file| file.starts_with("/app/src"));
+
+        assert_eq!(
+            diff.missing_from_cache,
+            vec![ClassLocation {
+                class: "App\\Legacy\\Helper".to_string(),
+                file: PathBuf::from("/app/src/Legacy/helpers.php"),
+            }]
+        );
+        assert_eq!(diff.missing_from_classmap[0].class, "App\\Generated\\Proxy");
+        assert!(format_diff(&diff).ends_with("1 missing from cache, 1 missing from classmap\n"));
+    }
+}
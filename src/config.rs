@@ -1,13 +1,40 @@
+use crate::artifacts::ArtifactMapping;
+use crate::class_lists::ClassList;
+use crate::container::ContainerConfig;
+use crate::derived::DerivedOutput;
+use crate::encryption::{EncryptionConfig, Key};
+use crate::exporters::ExporterConfig;
+#[cfg(feature = "daemon")]
+use crate::daemon::batching::BatchTuning;
+#[cfg(feature = "daemon")]
+use crate::daemon::hooks::UpdateHook;
 use crate::error::{AurynxError, Result};
+use crate::openapi::OpenApiConfig;
+use crate::policy::PolicyRule;
+use crate::retry::RetryPolicy;
+use crate::scanner::{OnError, ScanOptions};
+use crate::writer::CacheLayout;
 use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Debug, Deserialize, Default)]
 pub struct ConfigFile {
     pub paths: Option<Vec<PathBuf>>,
     pub output: Option<PathBuf>,
     pub ignore: Option<Vec<String>>,
+    pub output_exclude: Option<Vec<String>>, // scanned, but left out of the cache
+    pub vendor_packages: Option<Vec<String>>, // Composer packages scanned on top of `paths`
+    pub allowed_roots: Option<Vec<PathBuf>>, // scanned paths must lie inside these, whatever the CLI says
+    pub cache_layout: Option<CacheLayout>,   // grouping and order of the PHP cache array
+    pub include_file_context: Option<bool>,  // namespace, imports and symbols of each class's file
+    pub queries: Option<BTreeMap<String, PathBuf>>, // key => .scm file, captures land in `custom`
+    pub signature_hash: Option<bool>,        // hash of each class without bodies and file
+    pub report: Option<bool>,                // write aurynx.report.json next to the cache
+    pub nice: Option<bool>,                  // throttled, low-priority scanning
     pub watch: Option<bool>,
     pub socket: Option<PathBuf>,
     pub pid: Option<PathBuf>,
@@ -15,15 +42,58 @@ pub struct ConfigFile {
     pub verbose: Option<bool>,
     pub log_file: Option<PathBuf>,
     pub log_level: Option<String>,
+    pub log_filter: Option<String>, // env-filter directives, e.g. "aurynx::parser=warn"
     pub log_format: Option<String>,
+    pub log_target: Option<String>, // syslog or journald instead of stdout/log_file
     pub force: Option<bool>,
     pub write_to_disk: Option<bool>,
+    pub read_only: Option<bool>, // daemon serves the existing cache, no scanning or watching
     pub pretty: Option<bool>,
+    pub poll_fallback: Option<bool>,
+    pub watch_composer: Option<bool>,
+    pub watcher: Option<String>,
+    pub temp_file_patterns: Option<Vec<String>>,
+    pub resolve_hierarchy: Option<bool>,
+    pub inherit_attributes: Option<bool>,
+    pub path_root: Option<PathBuf>,
+    pub path_map: Option<HashMap<PathBuf, PathBuf>>,
+    pub openapi: Option<OpenApiConfig>,
+    pub container: Option<ContainerConfig>,
+    pub artifacts: Option<Vec<ArtifactMapping>>,
+    pub class_lists: Option<Vec<ClassList>>,
+    pub derived: Option<Vec<DerivedOutput>>, // route tables, entity maps... rewritten with the cache
+    pub exporters: Option<Vec<ExporterConfig>>, // external commands fed the metadata after a scan
+    pub journal_file: Option<PathBuf>, // daemon flush diffs, read by discovery:log
+    pub journal_max_entries: Option<usize>, // default: 1000
+    pub policies: Option<Vec<PolicyRule>>,
+    #[cfg(feature = "daemon")]
+    pub on_update: Option<Vec<UpdateHook>>,
+    pub shared_memory: Option<String>, // POSIX shm name for PHP FFI delivery (watch mode)
+    pub on_error: Option<String>,      // skip, warn or fail on files that cannot be scanned
+    pub php_version: Option<String>,   // targeted PHP version; newer syntax is reported
+    pub frontends: Option<Vec<String>>, // file kinds scanned besides .php, e.g. "phtml"
+    pub check_attributes: Option<bool>, // warn about attributes that match no known class
+    pub known_attributes: Option<Vec<String>>, // vendor attributes (FQCNs or `Namespace\\` prefixes)
+    pub attribute_stubs: Option<bool>, // read vendor attribute classes via Composer's classmap
+    pub encryption: Option<EncryptionConfig>, // encrypt JSON caches and manifests at rest
 
     // Security and performance limits
     pub max_file_size_mb: Option<u64>, // Maximum PHP file size in MB (default: 10MB)
     pub max_request_size: Option<usize>, // Maximum IPC request size in bytes (default: 1KB)
     pub max_cache_entries: Option<usize>, // Maximum number of cached classes (default: 50,000)
+    pub max_class_members: Option<usize>, // Methods, properties and cases kept per class (default: all)
+    pub io_retries: Option<u32>, // Retries of transient read errors, e.g. ESTALE on NFS (default: 3)
+    pub io_retry_delay_ms: Option<u64>, // Delay before the first retry, doubled per retry (default: 50ms)
+
+    // Adaptive batching (watch mode)
+    pub batch_threshold: Option<usize>, // Files above which a batch counts as a mass change (default: 100)
+    pub debounce_ms: Option<u64>,       // Debounce window for normal batches (default: 300ms)
+    pub mass_debounce_ms: Option<u64>,  // Debounce window for mass changes (default: 1000ms)
+
+    // Bounded memory mode (watch mode)
+    pub max_memory_entries: Option<usize>, // Classes kept in memory, the rest spill to disk (default: all)
+    pub spill_file: Option<PathBuf>, // Spill file location (default: aurynx.spill next to output)
+    pub max_memory_mb: Option<u64>, // Resident memory budget; sheds classes to the spill file or warns
 }
 
 impl ConfigFile {
@@ -62,7 +132,7 @@ impl ConfigFile {
         }
     }
 
-    pub fn validate(&self) -> Result<()> {
+    fn validate_logging(&self) -> Result<()> {
         if let Some(level) = &self.log_level {
             let valid_levels = ["trace", "debug", "info", "warn", "error"];
             if !valid_levels.contains(&level.as_str()) {
@@ -72,6 +142,14 @@ impl ConfigFile {
             }
         }
 
+        if let Some(filter) = &self.log_filter
+            && let Err(e) = crate::logger::validate_filter(filter)
+        {
+            return Err(AurynxError::config_error(format!(
+                "Invalid log_filter: {e:#}"
+            )));
+        }
+
         if let Some(format) = &self.log_format {
             let valid_formats = ["text", "json"];
             if !valid_formats.contains(&format.as_str()) {
@@ -81,6 +159,108 @@ impl ConfigFile {
             }
         }
 
+        if let Some(target) = &self.log_target {
+            let valid_targets = crate::logger::LOG_TARGETS;
+            if !valid_targets.contains(&target.as_str()) {
+                return Err(AurynxError::config_error(format!(
+                    "Invalid log_target: '{target}'. Allowed: {valid_targets:?}"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_retries(&self) -> Result<()> {
+        if let Some(retries) = self.io_retries
+            && retries > 10
+        {
+            return Err(AurynxError::config_error(format!(
+                "io_retries too large: {retries} (maximum: 10)"
+            )));
+        }
+
+        if let Some(ms) = self.io_retry_delay_ms
+            && !(1..=5_000).contains(&ms)
+        {
+            return Err(AurynxError::config_error(format!(
+                "io_retry_delay_ms out of range: {ms}ms (allowed: 1ms to 5000ms)"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Check the derived files and commands run after a scan
+    fn validate_outputs(&self) -> Result<()> {
+        for mapping in self.artifacts.iter().flatten() {
+            mapping.validate().map_err(AurynxError::config_error)?;
+        }
+
+        for list in self.class_lists.iter().flatten() {
+            list.validate().map_err(AurynxError::config_error)?;
+        }
+
+        for output in self.derived.iter().flatten() {
+            output.validate().map_err(AurynxError::config_error)?;
+        }
+
+        for exporter in self.exporters.iter().flatten() {
+            exporter.validate().map_err(AurynxError::config_error)?;
+        }
+
+        if self.journal_max_entries == Some(0) {
+            return Err(AurynxError::config_error("journal_max_entries must be at least 1"));
+        }
+
+        for name in self.frontends.iter().flatten() {
+            if !crate::frontend::is_known(name) {
+                let allowed: Vec<&str> =
+                    crate::frontend::FRONTENDS.iter().map(|frontend| frontend.name()).collect();
+                return Err(AurynxError::config_error(format!(
+                    "Unknown frontend '{name}' (allowed: {})",
+                    allowed.join(", ")
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_php_version(&self) -> Result<()> {
+        if let Some(version) = &self.php_version
+            && crate::php_version::PhpVersion::parse(version).is_none()
+        {
+            return Err(AurynxError::config_error(format!(
+                "Invalid php_version: '{version}'. Expected MAJOR.MINOR, e.g. \"8.1\""
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.validate_logging()?;
+        self.validate_retries()?;
+        self.validate_php_version()?;
+
+        if let Some(watcher) = &self.watcher {
+            let valid_watchers = ["notify", "watchman"];
+            if !valid_watchers.contains(&watcher.as_str()) {
+                return Err(AurynxError::config_error(format!(
+                    "Invalid watcher: '{watcher}'. Allowed: {valid_watchers:?}"
+                )));
+            }
+        }
+
+        if let Some(policy) = &self.on_error {
+            let valid_policies = crate::scanner::ON_ERROR_POLICIES;
+            if !valid_policies.contains(&policy.as_str()) {
+                return Err(AurynxError::config_error(format!(
+                    "Invalid on_error: '{policy}'. Allowed: {valid_policies:?}"
+                )));
+            }
+        }
+
         // Validate limits
         if let Some(size) = self.max_file_size_mb {
             if size == 0 {
@@ -95,6 +275,10 @@ impl ConfigFile {
             }
         }
 
+        if self.max_class_members == Some(0) {
+            return Err(AurynxError::config_error("max_class_members must be greater than 0"));
+        }
+
         if let Some(size) = self.max_request_size {
             if size < 256 {
                 return Err(AurynxError::config_error(format!(
@@ -121,6 +305,50 @@ impl ConfigFile {
             }
         }
 
+        for (name, is_zero) in [
+            ("max_memory_entries", self.max_memory_entries == Some(0)),
+            ("max_memory_mb", self.max_memory_mb == Some(0)),
+            ("batch_threshold", self.batch_threshold == Some(0)),
+        ] {
+            if is_zero {
+                return Err(AurynxError::config_error(format!(
+                    "{name} must be greater than 0"
+                )));
+            }
+        }
+
+        for (name, value) in [
+            ("debounce_ms", self.debounce_ms),
+            ("mass_debounce_ms", self.mass_debounce_ms),
+        ] {
+            if let Some(ms) = value
+                && !(10..=60_000).contains(&ms)
+            {
+                return Err(AurynxError::config_error(format!(
+                    "{name} out of range: {ms}ms (allowed: 10ms to 60000ms)"
+                )));
+            }
+        }
+
+        self.validate_outputs()?;
+
+        for rule in self.policies.iter().flatten() {
+            rule.validate().map_err(AurynxError::config_error)?;
+        }
+
+        #[cfg(feature = "daemon")]
+        for hook in self.on_update.iter().flatten() {
+            hook.validate().map_err(AurynxError::config_error)?;
+        }
+
+        if let Some(name) = &self.shared_memory
+            && (!name.starts_with('/') || name.len() < 2 || name[1..].contains('/'))
+        {
+            return Err(AurynxError::config_error(format!(
+                "Invalid shared_memory name '{name}' (expected /name)"
+            )));
+        }
+
         Ok(())
     }
 
@@ -141,4 +369,90 @@ impl ConfigFile {
     pub fn max_cache_entries_limit(&self) -> usize {
         self.max_cache_entries.unwrap_or(50_000)
     }
+
+    /// Get the daemon memory budget in bytes (default: none)
+    #[must_use]
+    pub fn max_memory_bytes(&self) -> Option<u64> {
+        self.max_memory_mb.map(|mb| mb * 1024 * 1024)
+    }
+
+    /// Get adaptive batching thresholds (defaults: 100 files, 300ms / 1000ms)
+    #[cfg(feature = "daemon")]
+    #[must_use]
+    pub fn batch_tuning(&self) -> BatchTuning {
+        let defaults = BatchTuning::default();
+        BatchTuning {
+            mass_threshold: self.batch_threshold.unwrap_or(defaults.mass_threshold),
+            debounce: self
+                .debounce_ms
+                .map_or(defaults.debounce, Duration::from_millis),
+            mass_debounce: self
+                .mass_debounce_ms
+                .map_or(defaults.mass_debounce, Duration::from_millis),
+        }
+    }
+
+    /// Get the key cache files are encrypted with (default: none)
+    ///
+    /// # Errors
+    ///
+    /// Returns a config error if the configured key cannot be loaded.
+    pub fn encryption_key(&self) -> Result<Option<Key>> {
+        self.encryption
+            .as_ref()
+            .map(EncryptionConfig::load_key)
+            .transpose()
+    }
+
+    /// Get how the project's files are read and parsed, loading the `queries`
+    /// files relative to `base`
+    ///
+    /// # Errors
+    ///
+    /// Returns a config error if a query file cannot be read or compiled.
+    pub fn scan_options(&self, base: &Path) -> Result<ScanOptions> {
+        let queries = self
+            .queries
+            .as_ref()
+            .map(|files| {
+                let files = files
+                    .iter()
+                    .map(|(key, file)| (key.clone(), base.join(file)))
+                    .collect();
+                crate::custom_queries::load(&files)
+            })
+            .transpose()?
+            .filter(|queries| !queries.is_empty())
+            .map(Arc::new);
+        Ok(ScanOptions {
+            on_error: self
+                .on_error
+                .as_deref()
+                .and_then(OnError::parse)
+                .unwrap_or_default(),
+            php_version: self
+                .php_version
+                .as_deref()
+                .and_then(crate::php_version::PhpVersion::parse),
+            frontends: crate::frontend::Frontends::new(self.frontends.as_deref().unwrap_or_default()),
+            include_file_context: self.include_file_context.unwrap_or(false),
+            queries,
+            allowed_roots: self
+                .allowed_roots
+                .as_ref()
+                .map(|roots| roots.iter().map(|root| base.join(root)).collect()),
+        })
+    }
+
+    /// Get the retry policy for transient IO errors (defaults: 3 retries, 50ms)
+    #[must_use]
+    pub fn retry_policy(&self) -> RetryPolicy {
+        let defaults = RetryPolicy::default();
+        RetryPolicy {
+            retries: self.io_retries.unwrap_or(defaults.retries),
+            delay: self
+                .io_retry_delay_ms
+                .map_or(defaults.delay, Duration::from_millis),
+        }
+    }
 }
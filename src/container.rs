@@ -0,0 +1,429 @@
+//! Dependency-injection definition export (`discovery:export container`)
+//!
+//! Turns classes carrying a configured service attribute into container
+//! definitions: service id, sharing, constructor arguments and tags. Nothing
+//! is framework-specific; the `container` section of `aurynx.json` maps the
+//! project's own attributes to these roles:
+//!
+//! - `service`: class attribute declaring a service (`id`, `shared` arguments)
+//! - `autowire`: parameter attribute overriding an argument with a service
+//!   reference, literal value, environment variable or container parameter
+//! - `tag`: repeatable class attribute adding a tag (`name`; other named
+//!   arguments become tag attributes)
+//!
+//! Constructor parameters without an override are wired by class type hint;
+//! parameters with a default value are left to PHP. Anything else is
+//! reported as unresolved.
+
+use crate::metadata::{AttributeArgument, AttributeMap, PhpClassMetadata, PhpParameterMetadata};
+use crate::openapi::{argument, matches_attribute, php_string};
+use crate::writer::php_literal;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// `container` section of `aurynx.json`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ContainerConfig {
+    /// Class attribute declaring a service (required for the export)
+    pub service: Option<ServiceMapping>,
+    /// Parameter attribute overriding a constructor argument
+    pub autowire: Option<AutowireMapping>,
+    /// Class attribute adding a tag
+    pub tag: Option<TagMapping>,
+}
+
+/// Attribute names are matched against the end of the resolved FQCN on a
+/// namespace boundary; the other fields name attribute arguments.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceMapping {
+    pub attribute: String,
+    /// Service id (default: `id`; falls back to the class name)
+    pub id: Option<String>,
+    /// Boolean sharing flag (default: `shared`; services are shared by default)
+    pub shared: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AutowireMapping {
+    pub attribute: String,
+    /// Service reference (default: `service`)
+    pub service: Option<String>,
+    /// Literal value (default: `value`, also the first positional argument)
+    pub value: Option<String>,
+    /// Environment variable name (default: `env`)
+    pub env: Option<String>,
+    /// Container parameter name (default: `param`)
+    pub param: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TagMapping {
+    pub attribute: String,
+    /// Tag name (default: `name`, also the first positional argument)
+    pub name: Option<String>,
+}
+
+/// How the container obtains a constructor argument
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgumentDefinition {
+    /// Service id
+    Service(String),
+    /// PHP expression of a literal
+    Value(String),
+    Env(String),
+    Param(String),
+}
+
+/// Tag with its name and extra attributes (raw PHP expressions)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagDefinition {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceDefinition {
+    pub id: String,
+    /// Class FQCN without the leading backslash
+    pub class: String,
+    pub shared: bool,
+    /// Constructor arguments by parameter name (for named-argument calls)
+    pub arguments: Vec<(String, ArgumentDefinition)>,
+    pub tags: Vec<TagDefinition>,
+}
+
+/// Outcome of a container export
+#[derive(Debug, Clone, Default)]
+pub struct ContainerDefinitions {
+    pub services: Vec<ServiceDefinition>,
+    /// `Class::$parameter` constructor arguments that could not be wired
+    pub unresolved: Vec<String>,
+}
+
+/// Build service definitions from the scanned metadata
+///
+/// Returns `None` when no service attribute is configured.
+#[must_use]
+pub fn build_definitions(
+    metadata_list: &[PhpClassMetadata], config: &ContainerConfig,
+) -> Option<ContainerDefinitions> {
+    let service = config.service.as_ref()?;
+    let by_name: HashMap<&str, &PhpClassMetadata> = metadata_list
+        .iter()
+        .map(|class| (class.fqcn.as_str(), class))
+        .collect();
+
+    let mut classes: Vec<&PhpClassMetadata> = metadata_list
+        .iter()
+        .filter(|class| class.kind == "class" && !class.modifiers.is_abstract)
+        .collect();
+    classes.sort_by(|a, b| a.fqcn.cmp(&b.fqcn));
+
+    let mut definitions = ContainerDefinitions::default();
+    for class in classes {
+        let Some(service_args) = instances(&class.attributes, &service.attribute).next() else {
+            continue;
+        };
+        let class_name = class.fqcn.trim_start_matches('\\').to_string();
+
+        let id = argument(service_args, service.id.as_deref().unwrap_or("id"), None)
+            .and_then(php_string)
+            .unwrap_or_else(|| class_name.clone());
+        let shared = argument(
+            service_args,
+            service.shared.as_deref().unwrap_or("shared"),
+            None,
+        )
+        .is_none_or(|value| !value.trim().eq_ignore_ascii_case("false"));
+
+        let mut arguments = Vec::new();
+        for parameter in constructor_parameters(class, &by_name) {
+            match wire(parameter, config.autowire.as_ref()) {
+                Some(definition) => arguments.push((parameter.name.clone(), definition)),
+                None if parameter.default_value.is_some() => {},
+                None => definitions
+                    .unresolved
+                    .push(format!("{class_name}::${}", parameter.name)),
+            }
+        }
+
+        let tags = config.tag.as_ref().map_or_else(Vec::new, |tag| {
+            instances(&class.attributes, &tag.attribute)
+                .filter_map(|args| tag_definition(args, tag.name.as_deref().unwrap_or("name")))
+                .collect()
+        });
+
+        definitions.services.push(ServiceDefinition {
+            id,
+            class: class_name,
+            shared,
+            arguments,
+            tags,
+        });
+    }
+
+    Some(definitions)
+}
+
+/// Render the definitions as a PHP file returning an array keyed by service id
+#[must_use]
+pub fn render_definitions(definitions: &ContainerDefinitions) -> String {
+    let mut out = String::from("<?php declare(strict_types=1);\n\n");
+    out.push_str(
+        "// Generated by `aurynx discovery:export container`: id => [class, shared, arguments, tags]\n",
+    );
+    out.push_str("return [\n");
+
+    for service in &definitions.services {
+        let _ = writeln!(out, "    {} => [", php_literal(&service.id));
+        let _ = writeln!(out, "        'class' => {},", php_literal(&service.class));
+        let _ = writeln!(out, "        'shared' => {},", service.shared);
+
+        out.push_str("        'arguments' => [");
+        if !service.arguments.is_empty() {
+            out.push('\n');
+            for (name, definition) in &service.arguments {
+                let (kind, value) = match definition {
+                    ArgumentDefinition::Service(id) => ("service", php_literal(id)),
+                    ArgumentDefinition::Value(expression) => ("value", expression.clone()),
+                    ArgumentDefinition::Env(name) => ("env", php_literal(name)),
+                    ArgumentDefinition::Param(name) => ("param", php_literal(name)),
+                };
+                let _ = writeln!(
+                    out,
+                    "            {} => ['{kind}' => {value}],",
+                    php_literal(name)
+                );
+            }
+            out.push_str("        ");
+        }
+        out.push_str("],\n");
+
+        let tags: Vec<String> = service
+            .tags
+            .iter()
+            .map(|tag| {
+                let mut fields = vec![format!("'name' => {}", php_literal(&tag.name))];
+                fields.extend(
+                    tag.attributes
+                        .iter()
+                        .map(|(key, value)| format!("{} => {value}", php_literal(key))),
+                );
+                format!("[{}]", fields.join(", "))
+            })
+            .collect();
+        let _ = writeln!(out, "        'tags' => [{}],", tags.join(", "));
+        out.push_str("    ],\n");
+    }
+
+    out.push_str("];\n");
+    out
+}
+
+/// Write the rendered definitions to `output_path`, unless they are
+/// already there
+///
+/// # Errors
+///
+/// Returns an error if the definitions cannot be written.
+pub fn write_definitions(definitions: &ContainerDefinitions, output_path: &Path) -> Result<()> {
+    let content = render_definitions(definitions);
+    crate::writer::write_if_changed(output_path, content.as_bytes()).with_context(|| {
+        format!(
+            "Failed to write container definitions {}",
+            output_path.display()
+        )
+    })?;
+    Ok(())
+}
+
+/// Argument lists of every instance of the attribute `name`
+fn instances<'a>(
+    attributes: &'a AttributeMap, name: &'a str,
+) -> impl Iterator<Item = &'a Vec<AttributeArgument>> + 'a {
+    attributes
+        .iter()
+        .filter(move |(attribute, _)| matches_attribute(attribute, name))
+        .flat_map(|(_, instances)| instances)
+}
+
+/// Constructor parameters, inherited from the nearest scanned parent if needed
+fn constructor_parameters<'a>(
+    class: &'a PhpClassMetadata, by_name: &HashMap<&str, &'a PhpClassMetadata>,
+) -> &'a [PhpParameterMetadata] {
+    let mut current = Some(class);
+    while let Some(declaring) = current {
+        if let Some(constructor) = declaring
+            .methods
+            .iter()
+            .find(|method| method.name.eq_ignore_ascii_case("__construct"))
+        {
+            return &constructor.parameters;
+        }
+        current = declaring
+            .extends
+            .as_deref()
+            .and_then(|parent| by_name.get(parent).copied());
+    }
+    &[]
+}
+
+/// Definition of one constructor argument: the autowire override, else the class type hint
+fn wire(
+    parameter: &PhpParameterMetadata, autowire: Option<&AutowireMapping>,
+) -> Option<ArgumentDefinition> {
+    let overridden = autowire.and_then(|mapping| {
+        let args = instances(&parameter.attributes, &mapping.attribute).next()?;
+        let literal = |key: Option<&str>, default: &str| {
+            argument(args, key.unwrap_or(default), None).and_then(php_string)
+        };
+
+        literal(mapping.service.as_deref(), "service")
+            .map(|id| ArgumentDefinition::Service(id.trim_start_matches('\\').to_string()))
+            .or_else(|| literal(mapping.env.as_deref(), "env").map(ArgumentDefinition::Env))
+            .or_else(|| literal(mapping.param.as_deref(), "param").map(ArgumentDefinition::Param))
+            .or_else(|| {
+                argument(args, mapping.value.as_deref().unwrap_or("value"), Some(0))
+                    .map(|value| ArgumentDefinition::Value(value.trim().to_string()))
+            })
+    });
+
+    overridden.or_else(|| {
+        // Only a plain class type can be autowired (no builtins, unions or nullables)
+        let type_hint = parameter.type_hint.as_deref()?;
+        (type_hint.starts_with('\\') && !type_hint.contains(['?', '|', '&']))
+            .then(|| ArgumentDefinition::Service(type_hint.trim_start_matches('\\').to_string()))
+    })
+}
+
+fn tag_definition(args: &[AttributeArgument], name_key: &str) -> Option<TagDefinition> {
+    let name = argument(args, name_key, Some(0)).and_then(php_string)?;
+    let attributes = args
+        .iter()
+        .filter_map(|arg| match arg {
+            AttributeArgument::Named { key, value } if key != name_key => {
+                Some((key.clone(), value.trim().to_string()))
+            },
+            _ => None,
+        })
+        .collect();
+    Some(TagDefinition { name, attributes })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::parser::tests::extract;
+    use serde_json::json;
+
+    fn config() -> ContainerConfig {
+        serde_json::from_value(json!({
+            "service": {"attribute": "DI\\Service"},
+            "autowire": {"attribute": "DI\\Autowire"},
+            "tag": {"attribute": "DI\\Tag"},
+        }))
+        .unwrap()
+    }
+
+    const SERVICES: &str = r"<?php
+namespace App;
+use App\DI\Autowire;
+use App\DI\Service;
+use App\DI\Tag;
+use Psr\Log\LoggerInterface;
+
+#[Service]
+#[Tag('mailer.transport', priority: 10)]
+final class SmtpTransport {
+    public function __construct(
+        #[Autowire(env: 'SMTP_DSN')] private string $dsn,
+        #[Autowire(3)] private int $retries,
+        private int $timeout = 30,
+    ) {}
+}
+
+#[Service(id: 'mailer', shared: false)]
+final class Mailer {
+    public function __construct(
+        private SmtpTransport $transport,
+        #[Autowire(service: 'monolog.logger.mail')] private LoggerInterface $logger,
+        private string $from,
+    ) {}
+}
+
+final class NotAService {
+    public function __construct(private Mailer $mailer) {}
+}
+";
+
+    #[test]
+    fn test_definitions_from_configured_attributes() {
+        let metadata = extract("/src/Services.php", SERVICES);
+        let definitions = build_definitions(&metadata, &config()).unwrap();
+
+        assert_eq!(definitions.services.len(), 2);
+        let mailer = &definitions.services[0];
+        assert_eq!(mailer.id, "mailer");
+        assert_eq!(mailer.class, "App\\Mailer");
+        assert!(!mailer.shared);
+        assert_eq!(
+            mailer.arguments,
+            [
+                (
+                    "transport".to_string(),
+                    ArgumentDefinition::Service("App\\SmtpTransport".to_string())
+                ),
+                (
+                    "logger".to_string(),
+                    ArgumentDefinition::Service("monolog.logger.mail".to_string())
+                ),
+            ]
+        );
+        assert_eq!(definitions.unresolved, ["App\\Mailer::$from"]);
+
+        let transport = &definitions.services[1];
+        assert_eq!(transport.id, "App\\SmtpTransport");
+        assert!(transport.shared);
+        assert_eq!(
+            transport.arguments,
+            [
+                (
+                    "dsn".to_string(),
+                    ArgumentDefinition::Env("SMTP_DSN".to_string())
+                ),
+                (
+                    "retries".to_string(),
+                    ArgumentDefinition::Value("3".to_string())
+                ),
+            ]
+        );
+        assert_eq!(
+            transport.tags,
+            [TagDefinition {
+                name: "mailer.transport".to_string(),
+                attributes: vec![("priority".to_string(), "10".to_string())],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_render_and_unconfigured_export() {
+        let metadata = extract("/src/Services.php", SERVICES);
+        assert!(build_definitions(&metadata, &ContainerConfig::default()).is_none());
+
+        let definitions = build_definitions(&metadata, &config()).unwrap();
+        let php = render_definitions(&definitions);
+
+        assert!(php.contains(
+            "    'mailer' => [\n        'class' => 'App\\\\Mailer',\n        'shared' => false,\n"
+        ));
+        assert!(php.contains("            'dsn' => ['env' => 'SMTP_DSN'],\n"));
+        assert!(
+            php.contains("        'tags' => [['name' => 'mailer.transport', 'priority' => 10]],\n")
+        );
+    }
+}
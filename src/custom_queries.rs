@@ -0,0 +1,150 @@
+//! Custom tree-sitter queries (`queries`)
+//!
+//! Conventions the extractor knows nothing about (a `protected $table`
+//! property, a `dispatch()` call in a constructor, a docblock tag) can be
+//! pulled out of each class by a query of your own. Each configured key
+//! names a `.scm` file; its query runs inside every class declaration, and
+//! every match becomes one entry of `custom[key]`, mapping capture names to
+//! the captured source text. Captures starting with `_` only constrain the
+//! match (e.g. with `#eq?`) and are left out.
+//!
+//! The manifest records a hash of each query's source, so editing a `.scm`
+//! file makes the next incremental scan reparse every file with classes.
+
+use crate::error::{AurynxError, Result};
+use crate::metadata::CustomCaptures;
+use indexmap::IndexMap;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tree_sitter::{Node, Query, QueryCursor, StreamingIterator};
+
+/// A compiled query and the hash of its source
+pub struct CustomQuery {
+    pub key: String,
+    pub query: Query,
+    pub source_hash: String,
+}
+
+/// Compiled queries, in key order
+pub type CustomQueries = Vec<CustomQuery>;
+
+/// Read and compile the configured queries (key => `.scm` file)
+///
+/// # Errors
+///
+/// Returns a config error naming the file that cannot be read or does not
+/// compile against the PHP grammar.
+pub fn load(files: &BTreeMap<String, PathBuf>) -> Result<CustomQueries> {
+    files
+        .iter()
+        .map(|(key, file)| {
+            let source = std::fs::read_to_string(file).map_err(|e| {
+                AurynxError::io_error(format!("Failed to read query file {}", file.display()), e)
+            })?;
+            let query = Query::new(&crate::parser::PHP_LANGUAGE, &source).map_err(|e| {
+                AurynxError::config_error(format!(
+                    "Invalid query '{key}' in {}: {e}",
+                    file.display()
+                ))
+            })?;
+            Ok(CustomQuery {
+                key: key.clone(),
+                query,
+                source_hash: format!("{:016x}", xxhash_rust::xxh3::xxh3_64(source.as_bytes())),
+            })
+        })
+        .collect()
+}
+
+/// Source hash of each query by key, as recorded in the manifest (empty
+/// without queries)
+#[must_use]
+pub fn source_hashes(queries: Option<&CustomQueries>) -> BTreeMap<String, String> {
+    queries
+        .into_iter()
+        .flatten()
+        .map(|query| (query.key.clone(), query.source_hash.clone()))
+        .collect()
+}
+
+/// Captures of `queries` within the class declaration `node`
+pub(crate) fn extract(queries: &CustomQueries, node: Node, source: &str) -> CustomCaptures {
+    let mut cursor = QueryCursor::new();
+    queries
+        .iter()
+        .map(|query| (query.key.clone(), run(&mut cursor, &query.query, node, source)))
+        .collect()
+}
+
+fn run(
+    cursor: &mut QueryCursor, query: &Query, node: Node, source: &str,
+) -> Vec<IndexMap<String, String>> {
+    let names = query.capture_names();
+    let mut entries = Vec::new();
+    let mut matches = cursor.matches(query, node, source.as_bytes());
+    while let Some(m) = matches.next() {
+        let mut entry = IndexMap::new();
+        for capture in m.captures {
+            let name = names[capture.index as usize];
+            if name.starts_with('_') || entry.contains_key(name) {
+                continue;
+            }
+            let text = capture.node.utf8_text(source.as_bytes()).unwrap_or_default();
+            entry.insert(name.to_string(), text.to_string());
+        }
+        entries.push(entry);
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use std::io::Write;
+    use tree_sitter::Parser;
+
+    #[test]
+    fn test_captures_are_attached_per_class() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"(property_declaration
+                 (property_element name: (variable_name (name) @_name)
+                                   default_value: (string (string_content) @table))
+                 (#eq? @_name "table"))"#
+        )
+        .unwrap();
+        let queries = load(&BTreeMap::from([("table".to_string(), file.path().into())])).unwrap();
+
+        let source = r"<?php
+class User { protected $table = 'users'; protected $key = 'id'; }
+class Post {}
+";
+        let mut parser = Parser::new();
+        parser.set_language(&crate::parser::PHP_LANGUAGE).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let root = tree.root_node();
+        let mut walker = root.walk();
+        let classes: Vec<_> = root
+            .children(&mut walker)
+            .filter(|node| node.kind() == "class_declaration")
+            .map(|node| extract(&queries, node, source))
+            .collect();
+
+        assert_eq!(classes[0]["table"].len(), 1);
+        assert_eq!(classes[0]["table"][0]["table"], "users");
+        assert!(!classes[0]["table"][0].contains_key("_name"));
+        assert!(classes[1]["table"].is_empty());
+
+        let hashes = source_hashes(Some(&queries));
+        write!(file, " @class").unwrap();
+        let edited = load(&BTreeMap::from([("table".to_string(), file.path().into())])).unwrap();
+        assert_ne!(source_hashes(Some(&edited)), hashes);
+        assert!(source_hashes(None).is_empty());
+
+        let mut invalid = tempfile::NamedTempFile::new().unwrap();
+        write!(invalid, "(no_such_node) @x").unwrap();
+        assert!(load(&BTreeMap::from([("x".to_string(), invalid.path().into())])).is_err());
+    }
+}
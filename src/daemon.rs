@@ -1,24 +1,54 @@
 #![allow(clippy::unwrap_used, clippy::expect_used)] // Allow unwrap/expect for RwLock poisoning and signal setup
 
+pub mod batching;
+mod errors;
+mod events;
+pub mod hooks;
+#[cfg(target_os = "linux")]
+pub mod init;
 mod lock;
+mod request;
+#[cfg(unix)]
+mod sendfile;
+mod shm;
+mod snapshot;
+#[cfg(unix)]
+mod stale;
+pub mod state;
+mod store;
+#[cfg(target_os = "linux")]
+pub mod supervisor;
 
 use crate::cache_strategy::{CacheStrategy, detect_cache_strategy};
-use crate::error::{AurynxError, Result};
-use crate::incremental::{FileEntry, MANIFEST_FILE, Manifest, perform_incremental_scan};
+use crate::error::{AurynxError, Result, code};
+use crate::incremental::{MANIFEST_FILE, Manifest, entry_from_scan, scan_with_manifest};
+use crate::journal::JournalEntry;
 use crate::metadata::PhpClassMetadata;
+use crate::path_map::PathMap;
 use crate::scanner;
-use crate::writer::write_php_cache;
+use crate::writer::render_php_cache;
 use anyhow::Context;
+use batching::{BatchStats, BatchTuning, EventQueue};
+use errors::ErrorLog;
+use hooks::{ChangeTracker, FlushDiff, HookRunner, RemovalReason, RemovedClass, UpdateEvent, UpdateHook};
+use ignore::gitignore::Gitignore;
+use ignore::overrides::Override;
 use lock::DaemonLock;
-use notify::{RecommendedWatcher, RecursiveMode, Watcher};
-use std::collections::HashMap;
+use notify::{PollWatcher, RecursiveMode, Watcher};
+use request::{CodeRequest, ResponseFormat};
+use shm::SharedMemory;
+use snapshot::{CacheSnapshot, SnapshotCell};
+use state::DaemonState;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
 use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
-use std::sync::mpsc::{RecvTimeoutError, channel};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant, SystemTime};
+use store::{ClassStore, SPILL_FILE};
 use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
-use tracing::{debug, info, warn};
+use tracing::{debug, error, info, warn};
 
 /// Exit codes
 
@@ -28,14 +58,99 @@ const EXIT_SUCCESS: i32 = 0;
 const EXIT_SIGNAL_ERROR: i32 = 2;
 #[allow(dead_code)]
 const EXIT_RUNTIME_ERROR: i32 = 3;
+
+/// Version of the IPC response formats (see the `version` command)
+///
+/// 2: `ERROR:` lines and `getErrors` entries carry an error code.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// IPC error response: `ERROR: <code> <message>`
+fn error_line(code: &str, message: impl std::fmt::Display) -> String {
+    format!("ERROR: {code} {message}\n")
+}
+
+/// Answer to a command that would rescan the cache of a read-only daemon
+fn read_only_error(command: &str) -> String {
+    error_line(
+        code::UNAVAILABLE,
+        format_args!("{command} not available: daemon is read-only"),
+    )
+}
+
+/// Log a failed daemon action with the stable code of its error
+fn log_error(action: &str, error: &AurynxError) {
+    warn!(emoji = "⚠️", code = error.code(), "{action}: {error}");
+}
+
+/// Compact JSON terminated by a newline (`json` IPC responses)
+fn json_line<T: serde::Serialize + ?Sized>(value: &T) -> serde_json::Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Resident set size of the daemon process (Linux only)
+fn resident_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Bytes of rendered code a snapshot holds (PHP, and JSON once requested)
+fn snapshot_bytes(snapshot: &CacheSnapshot) -> usize {
+    let php = snapshot
+        .php_code
+        .get()
+        .and_then(|php| php.as_ref().ok())
+        .map_or(0, Vec::len);
+    let json = snapshot
+        .json_code
+        .get()
+        .and_then(|json| json.as_ref().ok())
+        .map_or(0, Vec::len);
+    php + json
+}
+
 /// IPC Protocol: Plain text commands, plain text responses
 /// NO JSON! Direct PHP code delivery for zero overhead.
 ///
 /// Commands:
-/// - "getCode" or "getCacheCode" -> Returns PHP code directly
+/// - "getCode" or "getCacheCode" -> Returns PHP code directly (absolute paths,
+///   resolved by the daemon even when `path_root` is set); served zero-copy
+///   from the cache file while it is up to date, otherwise from the snapshot
+///   rendered after the last batch
 /// - "getFilePath" -> Returns file path as plain text
+/// - "getShm" -> Returns "<name> <size> <generation>" of the shared-memory
+///   segment holding the PHP code (with `shared_memory` configured), for
+///   `shm_open()` + `mmap()` via PHP FFI
+/// - "getErrors" -> Returns "ERRORS <count>" followed by one
+///   "<unix time>\t<path>\t<message>\t<code>" line per file failing to scan
+/// - "getRemoved" -> Returns "REMOVED <count> flush:<n>" followed by one
+///   "<fqcn>\t<file>\t<reason>" line per class the last cache flush removed
+///   (reason: `file_deleted`, `class_removed` or `filtered`); `n` counts the
+///   flushes that changed the cache file, so clients can tell missed flushes
 /// - "ping" -> Returns "PONG"
-/// - "stats" -> Returns "total:N strategy:X uptime:Y"
+/// - "version" -> Returns "version:X git:H schema:N protocol:P features:a,b"
+///   (see [`Daemon::version_info`]) for capability negotiation
+/// - "stats" -> Returns "total:N strategy:X uptime:Y errors:N" followed by
+///   batching counters (see [`BatchStats::to_stats_fields`]), memory usage
+///   and the spilled count
+/// - "stats verbose" -> Returns one "key:value" line per counter (memory,
+///   per-path class counts, queue depth, evictions, errors, batching),
+///   terminated by an empty line
+/// - "rescanPath <dir>" -> Rescans the PHP files under a directory (or one
+///   file) of the watched paths and merges them into the cache, dropping
+///   files that disappeared from it; returns
+///   "RESCANNED files:N removed:N classes:N"
+/// - "scanOnDemand [incremental|full]" -> Rescans all watched paths (full:
+///   reparsing every file) and writes the cache file before answering
+///   "SCANNED mode:M classes:N duration_ms:D"
+/// - "pause" -> Suspends event processing, returns "PAUSED"
+/// - "resume" -> Resumes watching with one consolidated rescan, returns "RESUMED"
+///
+/// Errors are answered as "ERROR: <code> <message>", where the code is one
+/// of [`crate::error::code`].
 ///
 /// CRITICAL: This is a performance-critical path. DO NOT add JSON serialization.
 /// PHP library expects raw PHP code, not JSON-wrapped data.
@@ -46,26 +161,149 @@ pub struct DaemonConfig {
     pub socket_path: PathBuf,
     pub pid_file: PathBuf,
     pub ignore_patterns: Vec<String>,
+    /// Patterns of scanned files whose classes are left out of every response
+    pub output_exclude: Vec<String>,
     pub verbose: bool,
     pub is_tty: bool,
     pub force: bool,
     pub write_to_disk: bool,
+    /// Serve the existing cache file without scanning or watching (`--read-only`)
+    pub read_only: bool,
+    /// Stop cleanly after this long without IPC requests or file changes
+    pub exit_on_idle: Option<Duration>,
+    /// Stop on the first runtime error instead of logging it and carrying on
+    pub exit_on_error: bool,
     pub pretty: bool,
     pub format: String,
+    /// Grouping and order of the PHP cache file (`getCode` stays flat)
+    pub cache_layout: crate::writer::CacheLayout,
 
     // Configurable limits
     pub max_file_size: u64,       // Maximum PHP file size in bytes
     pub max_request_size: usize,  // Maximum IPC request size in bytes
     pub max_cache_entries: usize, // Maximum number of cached classes
+
+    /// Fall back to polling when the OS file watch limit is exhausted
+    pub poll_fallback: bool,
+    /// Full rescan when `composer.lock` or `vendor/composer/installed.json` changes
+    pub watch_composer: bool,
+    /// Watcher backend: "notify" (native OS watcher) or "watchman"
+    pub watcher_backend: String,
+    /// Extra editor temp file patterns (on top of the built-in ones)
+    pub temp_file_patterns: Vec<String>,
+    /// Run the hierarchy resolution pass before writing the cache
+    pub resolve_hierarchy: bool,
+    /// Merge inherited class attributes (implies hierarchy resolution)
+    pub inherit_attributes: bool,
+    /// Add `signature_hash` to every class (see `crate::signature`)
+    pub signature_hash: bool,
+    /// Write cache file paths relative to this project root
+    pub path_root: Option<PathBuf>,
+    /// Local path prefix → path prefix seen by cache consumers
+    pub path_map: HashMap<PathBuf, PathBuf>,
+    /// Adaptive debounce thresholds
+    pub batch_tuning: BatchTuning,
+    /// Classes kept in memory before spilling to disk (`None` keeps all in memory)
+    pub max_memory_entries: Option<usize>,
+    /// Methods, properties and cases kept per class (see `crate::member_limit`)
+    pub max_class_members: Option<usize>,
+    /// Spill file for bounded memory mode (defaults to `aurynx.spill` next to the output)
+    pub spill_file: Option<PathBuf>,
+    /// Resident memory budget in bytes (`max_memory_mb`, `None` for no budget)
+    pub max_memory_bytes: Option<u64>,
+    /// Hooks notified after each flush that changes the cache file
+    pub on_update: Vec<UpdateHook>,
+    /// Interface and abstract class lists rewritten with the cache file
+    pub class_lists: Vec<crate::class_lists::ClassList>,
+    /// Derived outputs (route tables, entity maps...) rewritten with the cache file
+    pub derived: Vec<crate::derived::DerivedOutput>,
+    /// Change journal appended on every flush (see `crate::journal`)
+    pub journal_file: Option<PathBuf>,
+    /// Entries kept in the change journal
+    pub journal_max_entries: usize,
+    /// POSIX shared-memory name (`/name`) receiving every published snapshot
+    pub shared_memory: Option<String>,
+    /// Log the N slowest files of the initial scan (`--profile-files`)
+    pub profile_files: Option<usize>,
+    /// How files are read and parsed (`on_error`, `php_version`, `frontends`...)
+    pub scan_options: scanner::ScanOptions,
+}
+
+/// File watcher backend in use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WatchBackend {
+    /// Native OS watcher (inotify, `FSEvents`, ...)
+    Native,
+    /// Polling fallback after the OS watch limit was exhausted
+    Polling,
+    /// Watchman subscription (recursive watches are handled server-side)
+    Watchman,
 }
 
 pub struct Daemon {
-    cache: Arc<RwLock<HashMap<String, PhpClassMetadata>>>,
+    cache: Arc<RwLock<ClassStore>>,
     manifest: Arc<RwLock<Manifest>>,
     config: DaemonConfig,
     strategy: CacheStrategy,
     start_time: Instant,
     shutdown_rx: Option<UnboundedReceiver<()>>,
+    /// Ignore matcher shared by the scanner and the watcher
+    ignore: Option<Override>,
+    output_exclude: Option<Override>,
+    /// Editor temp file matcher (swap, backup and atomic-save files)
+    temp_files: Gitignore,
+    /// Prefix remapping between local and consumer paths (`path_map`)
+    path_map: PathMap,
+    /// Extractors reused by every watch batch
+    extractors: scanner::ExtractorPool,
+    /// Batch sizes, debounce windows and rescan durations (`stats`)
+    batch_stats: BatchStats,
+    /// Bumped whenever the in-memory cache changes
+    cache_generation: u64,
+    /// Cache generation last written to the cache file (File strategy)
+    cache_file_generation: Option<u64>,
+    /// Immutable view served to IPC readers, republished per batch
+    snapshot: SnapshotCell<CacheSnapshot>,
+    /// File watcher (set once `run()` starts watching)
+    watcher: Option<Box<dyn Watcher>>,
+    /// Event channel shared by the native and the polling watcher
+    watch_tx: Option<EventQueue<notify::Result<notify::Event>>>,
+    /// Whether the OS watch limit was hit (reported only once)
+    watch_limit_hit: bool,
+    /// Backend currently delivering file events
+    backend: WatchBackend,
+    /// Event processing suspended via the `pause` IPC command
+    paused: bool,
+    /// Full rescan requested (`resume` IPC command or composer state change)
+    rescan_pending: bool,
+    /// Composer files whose changes trigger a full rescan (`watch_composer`)
+    composer_files: Vec<PathBuf>,
+    /// `vendor/` next to `composer.lock`, reparsed after a composer change
+    vendor_dir: Option<PathBuf>,
+    /// `vendor_dir` after a composer change, reparsed by the next full rescan
+    stale_vendor: Option<PathBuf>,
+    /// Worker running the `on_update` hooks (`None` without hooks)
+    hooks: Option<HookRunner>,
+    /// Classes of the last flushed cache (`on_update` diffs, `getRemoved`)
+    flushed: ChangeTracker,
+    /// Flushes that changed the cache file
+    flushes: u64,
+    /// Classes removed by the last such flush (`getRemoved`)
+    last_removed: Vec<RemovedClass>,
+    /// Shared-memory segments announced by `getShm` (`None` when disabled)
+    shm: Option<SharedMemory>,
+    /// Files failing to scan (`getErrors`)
+    errors: ErrorLog,
+    /// Snapshots published while over `max_memory_mb` (`stats verbose`)
+    over_memory_budget: u64,
+    /// RSS found by the last budget check, while over budget (warn once per crossing)
+    over_budget_rss: Option<u64>,
+    /// Files of batches rejected under `on_error: fail`, retried with the next batch
+    held_back: Vec<PathBuf>,
+    /// Last IPC request or processed file change (`exit_on_idle`)
+    last_activity: Instant,
+    /// Runtime error the daemon stops with (`exit_on_error`)
+    fatal: Option<AurynxError>,
     /// Daemon lock held for entire lifetime (prevents concurrent instances)
     _lock: DaemonLock,
 }
@@ -82,8 +320,9 @@ impl Daemon {
 
         // Acquire daemon lock atomically (prevents race conditions)
         let lock_path = DaemonLock::path_from_cache(&config.output_path);
+        // A typed error, so `--foreground` exits with the lock conflict status
         let lock = DaemonLock::acquire(&lock_path, &config.socket_path, config.force)
-            .context("Failed to acquire daemon lock")?;
+            .map_err(|e| AurynxError::lock_error(lock_path.clone(), format!("{e:#}")))?;
 
         info!(
             lock_path = ?lock_path,
@@ -92,13 +331,80 @@ impl Daemon {
             "Daemon lock acquired successfully"
         );
 
+        // Leftovers of a crashed daemon are adopted; files still in use by a
+        // daemon of another cache are not touched (unless forced)
+        #[cfg(unix)]
+        if !config.force {
+            stale::adopt_pid_file(&config.pid_file, &config.socket_path)?;
+            stale::adopt_socket(&config.socket_path)?;
+        }
+
+        let temp_files = scanner::temp_file_matcher(&config.temp_file_patterns);
+        let path_map = PathMap::new(&config.path_map);
+        let scan_options = config.scan_options.clone();
+
+        // Bounded memory mode: spill cold classes to disk
+        let cache = match config.max_memory_entries {
+            Some(capacity) => {
+                let spill_file = config.spill_file.clone().unwrap_or_else(|| {
+                    config
+                        .output_path
+                        .parent()
+                        .unwrap_or_else(|| Path::new("."))
+                        .join(SPILL_FILE)
+                });
+                info!(capacity, spill_file = ?spill_file, "Bounded memory mode enabled");
+                ClassStore::spilling(capacity, &spill_file).with_context(|| {
+                    format!("Failed to create spill file {}", spill_file.display())
+                })?
+            },
+            None => ClassStore::in_memory(),
+        };
+
+        let hooks = HookRunner::start(config.on_update.clone());
+        let shm = config
+            .shared_memory
+            .as_deref()
+            .map(SharedMemory::new)
+            .transpose()
+            .context("Failed to set up shared memory delivery")?;
+
         Ok(Self {
-            cache: Arc::new(RwLock::new(HashMap::new())),
+            cache: Arc::new(RwLock::new(cache)),
             manifest: Arc::new(RwLock::new(Manifest::default())),
             config,
             strategy,
             start_time: Instant::now(),
             shutdown_rx: None,
+            ignore: None,
+            output_exclude: None,
+            temp_files,
+            path_map,
+            extractors: scanner::ExtractorPool::new(scan_options),
+            batch_stats: BatchStats::default(),
+            cache_generation: 0,
+            cache_file_generation: None,
+            snapshot: SnapshotCell::new(CacheSnapshot::default()),
+            watcher: None,
+            watch_tx: None,
+            watch_limit_hit: false,
+            backend: WatchBackend::Native,
+            paused: false,
+            rescan_pending: false,
+            composer_files: Vec::new(),
+            vendor_dir: None,
+            stale_vendor: None,
+            hooks,
+            flushed: ChangeTracker::default(),
+            flushes: 0,
+            last_removed: Vec::new(),
+            shm,
+            errors: ErrorLog::default(),
+            over_memory_budget: 0,
+            over_budget_rss: None,
+            held_back: Vec::new(),
+            last_activity: Instant::now(),
+            fatal: None,
             _lock: lock,
         })
     }
@@ -156,6 +462,9 @@ impl Daemon {
             .map(|p| std::fs::canonicalize(p).unwrap_or_else(|_| p.clone()))
             .collect();
         self.config.paths = canonical_paths;
+        self.ignore = scanner::ignore_matcher(&self.config.paths, &self.config.ignore_patterns);
+        self.output_exclude =
+            scanner::ignore_matcher(&self.config.paths, &self.config.output_exclude);
 
         // Lock already acquired in new()
         // The atomic lock prevents race conditions even with 100+ concurrent requests
@@ -204,37 +513,62 @@ impl Daemon {
             });
         });
 
-        // Initial scan
-        self.log_craft("initial metadata scan...");
-        self.scan_initial()?;
+        // Initial scan (read-only: the cache built beforehand)
+        let scan_start = Instant::now();
+        let initial = if self.config.read_only {
+            self.load_cache()
+        } else {
+            self.log_craft("initial metadata scan...");
+            self.scan_initial(false)
+        };
+        if let Err(e) = initial {
+            let _ = self.cleanup_files();
+            return Err(e);
+        }
+        self.publish_snapshot();
         let class_count = self.cache.read().unwrap().len();
-        self.log_info(&format!(
+        info!(
+            emoji = "✨",
+            event = events::INITIAL_SCAN,
+            classes = class_count,
+            errors = self.errors.len(),
+            duration_ms = events::millis(scan_start.elapsed()),
             "Metadata crafted: {class_count} classes discovered"
-        ));
+        );
 
         // Write initial cache file (for File strategy)
-        if self.strategy == CacheStrategy::File {
+        if self.strategy == CacheStrategy::File && !self.config.read_only {
             self.log_info("Attempting to write cache file...");
             match self.write_cache_file() {
                 Ok(()) => self.log_info(&format!("Cache crafted at {:?}", self.config.output_path)),
-                Err(e) => self.log_warn(&format!("Failed to write cache: {e}")),
+                Err(e) => self.runtime_error("Failed to write cache", e),
             }
         }
 
-        // Setup file watcher
-        let (tx, rx) = channel();
-        let mut watcher: RecommendedWatcher = notify::recommended_watcher(tx)?;
-
-        for path in &self.config.paths {
-            watcher.watch(path, RecursiveMode::Recursive)?;
-            self.log_info(&format!("Watching crafted: {path:?}"));
+        // Setup file watcher (ignored directories are never registered);
+        // read-only, the channel stays open but never receives events
+        let (tx, rx) = EventQueue::channel();
+        if !self.config.read_only {
+            self.watcher = Some(self.create_watcher(tx.clone())?);
+            for path in self.config.paths.clone() {
+                let count = self.watch_tree(&path)?;
+                self.log_info(&format!("Watching crafted: {path:?} ({count} directories)"));
+            }
+            if self.config.watch_composer {
+                self.watch_composer_files();
+            }
         }
+        self.watch_tx = Some(tx);
 
         // Setup Unix socket server (for IPC)
         #[cfg(unix)]
         let socket_listener = self.setup_unix_socket()?;
 
         info!(
+            event = events::DAEMON_START,
+            pid,
+            strategy = ?self.strategy,
+            backend = ?self.backend,
             "🪄 Daemon ready! Strategy: {:?}, Socket: {:?}, Output: {:?}, Verbose: {}",
             self.strategy, self.config.socket_path, self.config.output_path, self.config.verbose
         );
@@ -248,33 +582,38 @@ impl Daemon {
         let mut pending_changes: Vec<PathBuf> = Vec::new();
 
         let result = loop {
+            let generation = self.cache_generation;
+
             // Check for shutdown signal (non-blocking)
-            if let Some(ref mut rx) = self.shutdown_rx
-                && rx.try_recv().is_ok() {
-                    self.log_info("Shutdown signal received, cleaning up...");
-                    break Ok(());
-                }
+            if self.shutdown_requested() {
+                info!(
+                    emoji = "✨",
+                    event = events::SHUTDOWN,
+                    "Shutdown signal received, cleaning up..."
+                );
+                break Ok(());
+            }
+            if let Some(result) = self.stop_reason() {
+                break result;
+            }
 
             // Collect file system events (adaptive batching)
             let batch_start = Instant::now();
             let base_debounce = Duration::from_millis(50);
+            let tuning = self.config.batch_tuning;
 
             // Collect first event
-            match rx.recv_timeout(base_debounce) {
+            match self.recv_event(&rx, base_debounce) {
                 Ok(Ok(event)) => match self.collect_event_paths(event) {
                     Ok(paths) => pending_changes.extend(paths),
                     Err(e) => {
-                        self.log_warn(&format!("Error collecting event paths: {e}"));
+                        log_error("Error collecting event paths", &e);
                     },
                 },
-                Ok(Err(e)) => {
-                    self.log_warn(&format!("Watch error: {e}"));
-                },
+                Ok(Err(e)) => self.on_watch_error(e),
                 Err(RecvTimeoutError::Timeout) => {
                     // Continue collecting events if we already have some
-                    if !pending_changes.is_empty()
-                        && batch_start.elapsed() < Duration::from_millis(300)
-                    {
+                    if !pending_changes.is_empty() && batch_start.elapsed() < tuning.debounce {
                         continue;
                     }
                 },
@@ -285,24 +624,21 @@ impl Daemon {
             }
 
             // Continue collecting more events with adaptive debounce
-            let adaptive_debounce = if pending_changes.len() > 100 {
-                Duration::from_millis(1000) // Longer debounce for mass changes
-            } else {
-                Duration::from_millis(300) // Normal debounce
-            };
+            // (longer window for mass changes)
+            let window_pending = pending_changes.len();
+            let adaptive_debounce = tuning.debounce_for(window_pending);
 
             let collect_deadline = Instant::now() + adaptive_debounce;
-            while Instant::now() < collect_deadline {
-                match rx.recv_timeout(Duration::from_millis(10)) {
+            // A shutdown signal ends the window early
+            while Instant::now() < collect_deadline && !self.shutdown_requested() {
+                match self.recv_event(&rx, Duration::from_millis(10)) {
                     Ok(Ok(event)) => match self.collect_event_paths(event) {
                         Ok(paths) => pending_changes.extend(paths),
                         Err(e) => {
-                            self.log_warn(&format!("Error collecting event paths: {e}"));
+                            log_error("Error collecting event paths", &e);
                         },
                     },
-                    Ok(Err(e)) => {
-                        self.log_warn(&format!("Watch error: {e}"));
-                    },
+                    Ok(Err(e)) => self.on_watch_error(e),
                     Err(RecvTimeoutError::Timeout) => break,
                     Err(RecvTimeoutError::Disconnected) => {
                         self.log_info("Watcher disconnected, shutting down");
@@ -313,6 +649,7 @@ impl Daemon {
 
             // Process batch if we have pending changes
             if !pending_changes.is_empty() {
+                self.last_activity = Instant::now();
                 // Remove duplicates
                 pending_changes.sort();
                 pending_changes.dedup();
@@ -328,28 +665,54 @@ impl Daemon {
                 }
 
                 // Process batch in parallel
+                self.batch_stats.record_window(&tuning, window_pending);
+                let rescan_start = Instant::now();
                 match self.batch_rescan_files(&pending_changes) {
                     Ok(()) => dirty = true,
-                    Err(e) => {
-                        self.log_warn(&format!("Error in batch rescan: {e}"));
-                    },
+                    Err(e) => self.runtime_error("Error in batch rescan", e),
                 }
+                self.finish_batch(
+                    pending_changes.len(),
+                    rescan_start.elapsed(),
+                    adaptive_debounce,
+                );
 
                 pending_changes.clear();
             }
 
+            // Summaries of warnings aggregated during the last window
+            crate::logger::flush_repeated(false);
+
+            // Readers only ever see a published snapshot
+            if self.snapshot.load().generation != self.cache_generation {
+                self.publish_snapshot();
+            }
+
             // Check for IPC requests (non-blocking)
             #[cfg(unix)]
             if let Err(e) = self.check_ipc_requests(&socket_listener) {
-                self.log_warn(&format!("IPC error: {e}"));
+                log_error("IPC error", &e);
                 // Continue despite IPC errors
             }
+            // Removals and `rescanPath` update the cache outside of batches
+            if self.cache_generation != generation {
+                dirty = true;
+            }
+
+            // Consolidated rescan (after a pause or a dependency update)
+            if self.rescan_pending {
+                self.rescan_pending = false;
+                match self.full_rescan(false) {
+                    Ok(()) => dirty = true,
+                    Err(e) => self.runtime_error("Full rescan failed", e),
+                }
+            }
 
             // Periodic flush (only for File strategy)
             if self.strategy == CacheStrategy::File && dirty
                 && last_write.elapsed() >= Duration::from_millis(300) {
                     if let Err(e) = self.write_cache_file() {
-                        self.log_warn(&format!("Failed to write cache: {e}"));
+                        self.runtime_error("Failed to write cache", e);
                     } else {
                         let count = self.cache.read().unwrap().len();
                         self.log(&format!("Cache recrafted: {count} classes"));
@@ -365,7 +728,7 @@ impl Daemon {
         // Final cache flush if dirty
         if self.strategy == CacheStrategy::File && dirty {
             if let Err(e) = self.write_cache_file() {
-                self.log_warn(&format!("Failed to write final cache: {e}"));
+                log_error("Failed to write final cache", &e);
             } else {
                 let count = self.cache.read().unwrap().len();
                 self.log_info(&format!("Final cache crafted: {count} classes"));
@@ -374,8 +737,13 @@ impl Daemon {
 
         // Cleanup files
         self.cleanup_files()?;
+        crate::logger::flush_repeated(true);
 
-        info!("Daemon stopped gracefully");
+        info!(
+            event = events::DAEMON_STOP,
+            uptime_s = self.start_time.elapsed().as_secs(),
+            "Daemon stopped gracefully"
+        );
         if self.config.is_tty {
             println!("\n🪄 Daemon stopped gracefully\n");
         }
@@ -433,21 +801,361 @@ impl Daemon {
         let _ = shutdown_tx.send(());
     }
 
-    fn scan_initial(&mut self) -> Result<()> {
+    /// Whether a shutdown signal arrived (the message is left in the channel)
+    fn shutdown_requested(&self) -> bool {
+        self.shutdown_rx.as_ref().is_some_and(|rx| !rx.is_empty())
+    }
+
+    /// Rescan all configured paths and re-register watches
+    ///
+    /// Unchanged files keep their manifest entries unless `reparse` is set.
+    fn full_rescan(&mut self, reparse: bool) -> Result<()> {
+        let rescan_start = Instant::now();
+        self.cache_generation += 1;
+        self.log_craft("full rescan...");
+        self.cache.write().unwrap().clear();
+        self.scan_initial(reparse)?;
+
+        // Directories may have appeared while events were ignored
+        for path in self.config.paths.clone() {
+            self.watch_tree(&path)?;
+        }
+
+        let class_count = self.cache.read().unwrap().len();
+        info!(
+            emoji = "✨",
+            event = events::FULL_RESCAN,
+            classes = class_count,
+            errors = self.errors.len(),
+            duration_ms = events::millis(rescan_start.elapsed()),
+            "Full rescan complete: {class_count} classes"
+        );
+        Ok(())
+    }
+
+    /// Answer `rescanPath <dir>`
+    fn rescan_path(&mut self, dir: &str) -> String {
+        let start = Instant::now();
+        match self.try_rescan_path(dir) {
+            Ok((files, removed)) => {
+                let classes = self.cache.read().unwrap().len();
+                info!(
+                    emoji = "✨",
+                    event = events::RESCAN_PATH,
+                    path = dir,
+                    files,
+                    removed,
+                    classes,
+                    duration_ms = events::millis(start.elapsed()),
+                    "Rescan of {dir} complete: {files} files"
+                );
+                format!("RESCANNED files:{files} removed:{removed} classes:{classes}\n")
+            },
+            Err(e) => {
+                log_error(&format!("Rescan of {dir} failed"), &e);
+                error_line(e.code(), e)
+            },
+        }
+    }
+
+    /// Answer `reload` after `discovery:promote` installed a new cache
+    ///
+    /// A read-only daemon loads the promoted cache file; a watching daemon
+    /// rescans on top of the promoted manifest instead, so its next flush
+    /// does not overwrite the promoted cache with its older state. The
+    /// snapshot is published before the response is sent.
+    fn reload(&mut self) -> String {
+        let start = Instant::now();
+        let reloaded = if self.config.read_only {
+            self.load_cache()
+        } else {
+            self.full_rescan(false)
+        };
+        if let Err(e) = reloaded {
+            log_error("Reload failed", &e);
+            return error_line(e.code(), e);
+        }
+
+        self.cache_generation += 1;
+        self.publish_snapshot();
+        let classes = self.snapshot.load().total;
+        info!(
+            emoji = "✨",
+            event = events::RELOAD,
+            classes,
+            duration_ms = events::millis(start.elapsed()),
+            "Reload complete: {classes} classes"
+        );
+        format!("RELOADED classes:{classes}\n")
+    }
+
+    /// Snapshot file of a `snapshot` or `restore` request, inside the
+    /// directory of the cache file
+    fn snapshot_path(&self, file: &str) -> std::result::Result<PathBuf, String> {
+        let path = Path::new(file);
+        if !path.is_absolute() {
+            return Err(error_line(
+                code::INVALID_REQUEST,
+                format_args!("Snapshot path must be absolute: {file}"),
+            ));
+        }
+        let dir = self.config.output_path.parent().unwrap_or_else(|| Path::new(""));
+        state::confine(path, dir)
+            .map_err(|e| error_line(code::INVALID_REQUEST, format_args!("{e:#}")))
+    }
+
+    /// Answer `snapshot <file>` by writing the in-memory state to `file`
+    fn take_snapshot(&self, file: &str) -> String {
+        let path = match self.snapshot_path(file) {
+            Ok(path) => path,
+            Err(response) => return response,
+        };
+        let classes = self.cache.read().unwrap().to_vec();
+        let classes = match classes {
+            Ok(classes) => classes,
+            Err(e) => {
+                return error_line(code::IO, format_args!("Failed to read spilled classes: {e}"));
+            },
+        };
+        let state = DaemonState {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            taken_at: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            generation: self.cache_generation,
+            paused: self.paused,
+            paths: self.config.paths.clone(),
+            classes,
+            manifest: self.manifest.read().unwrap().clone(),
+        };
+        let classes = state.classes.len();
+        match state.save(&path) {
+            Ok(bytes) => {
+                info!(
+                    emoji = "✨",
+                    event = events::SNAPSHOT,
+                    classes,
+                    bytes,
+                    "Snapshot written to {}",
+                    path.display()
+                );
+                format!("SNAPSHOT classes:{classes} bytes:{bytes}\n")
+            },
+            Err(e) => error_line(code::IO, format_args!("{e:#}")),
+        }
+    }
+
+    /// Answer `restore <file>` by replacing the in-memory state with a snapshot
+    ///
+    /// A watching daemon is paused afterwards, so file events do not replace
+    /// the restored classes until `resume` rescans.
+    fn restore_snapshot(&mut self, file: &str) -> String {
+        let path = match self.snapshot_path(file) {
+            Ok(path) => path,
+            Err(response) => return response,
+        };
+        let state = match DaemonState::load(&path) {
+            Ok(state) => state,
+            Err(e) => return error_line(code::INVALID_REQUEST, format_args!("{e:#}")),
+        };
+
+        let classes = state.classes.len();
+        let mut cache = self.cache.write().unwrap();
+        cache.clear();
+        for class in state.classes {
+            cache.insert(class.fqcn.clone(), class);
+        }
+        drop(cache);
+        self.errors.sync(&state.manifest);
+        *self.manifest.write().unwrap() = state.manifest;
+        if !self.config.read_only {
+            self.paused = true;
+        }
+
+        self.cache_generation += 1;
+        self.publish_snapshot();
+        if self.strategy == CacheStrategy::File
+            && !self.config.read_only
+            && let Err(e) = self.write_cache_file()
+        {
+            log_error("Failed to write cache", &e);
+            return error_line(e.code(), e);
+        }
+        info!(
+            emoji = "✨",
+            event = events::RESTORE,
+            classes,
+            generation = state.generation,
+            "Restored snapshot {} taken by {} at {}: {classes} classes",
+            path.display(),
+            state.version,
+            crate::journal::format_time(state.taken_at)
+        );
+        format!(
+            "RESTORED classes:{classes} generation:{} taken_at:{}\n",
+            state.generation, state.taken_at
+        )
+    }
+
+    /// Answer `scanOnDemand [incremental|full]` once the cache is up to date
+    ///
+    /// The connection blocks until the rescan is done. With the file
+    /// strategy, the cache file is written before the response is sent, so
+    /// deploy scripts can rely on it without restarting the daemon.
+    fn scan_on_demand(&mut self, mode: &str) -> String {
+        let (mode, reparse) = match mode {
+            "" | "incremental" => ("incremental", false),
+            "full" => ("full", true),
+            _ => {
+                return error_line(
+                    code::INVALID_REQUEST,
+                    format_args!("Unknown scan mode: {mode} (expected incremental or full)"),
+                );
+            },
+        };
+
+        let start = Instant::now();
+        if let Err(e) = self.full_rescan(reparse) {
+            log_error("Scan on demand failed", &e);
+            return error_line(e.code(), e);
+        }
+        self.publish_snapshot();
+        if self.strategy == CacheStrategy::File
+            && let Err(e) = self.write_cache_file()
+        {
+            log_error("Failed to write cache", &e);
+            return error_line(e.code(), e);
+        }
+
+        let classes = self.snapshot.load().total;
+        let duration_ms = events::millis(start.elapsed());
+        info!(
+            emoji = "✨",
+            event = events::SCAN_ON_DEMAND,
+            mode,
+            classes,
+            duration_ms,
+            "Scan on demand ({mode}) complete: {classes} classes"
+        );
+        format!("SCANNED mode:{mode} classes:{classes} duration_ms:{duration_ms}\n")
+    }
+
+    /// Rescan the PHP files under `dir`; returns the scanned and removed file counts
+    ///
+    /// Only the subtree is touched: files that disappeared from it are
+    /// dropped, new directories in it get watched, and the snapshot is
+    /// published right away so the next request on the same connection sees
+    /// the result.
+    fn try_rescan_path(&mut self, dir: &str) -> Result<(usize, usize)> {
+        let requested = self.path_map.to_local(Path::new(dir));
+        let dir = std::fs::canonicalize(&requested)
+            .or_else(|_| std::path::absolute(&requested))
+            .map_err(|e| AurynxError::io_error(format!("Invalid path: {dir}"), e))?;
+        if !self.config.paths.iter().any(|root| dir.starts_with(root)) {
+            return Err(AurynxError::invalid_request_error(format!(
+                "{} is not under a watched path",
+                dir.display()
+            )));
+        }
+        if scanner::is_ignored(self.ignore.as_ref(), &dir) {
+            return Err(AurynxError::invalid_request_error(format!(
+                "{} is ignored",
+                dir.display()
+            )));
+        }
+
+        let files = if dir.is_dir() {
+            self.watch_tree(&dir)?;
+            let frontends = self.extractors.options().frontends;
+            scanner::source_files_under(&dir, self.ignore.as_ref(), frontends)
+        } else if dir.is_file() && self.extractors.options().frontends.is_source_file(&dir) {
+            vec![dir.clone()]
+        } else {
+            Vec::new()
+        };
+
+        // Files of the subtree that are gone since they were scanned
+        let found: HashSet<&Path> = files.iter().map(PathBuf::as_path).collect();
+        let removed: Vec<String> = self
+            .manifest
+            .read()
+            .unwrap()
+            .files
+            .keys()
+            .filter(|file| {
+                let file = Path::new(file.as_str());
+                file.starts_with(&dir) && !found.contains(file)
+            })
+            .cloned()
+            .collect();
+        for file in &removed {
+            self.cache.write().unwrap().remove_file(Path::new(file));
+            self.manifest.write().unwrap().files.remove(file);
+            self.errors.clear(Path::new(file));
+            self.cache_generation += 1;
+        }
+
+        self.batch_rescan_files(&files)?;
+        self.publish_snapshot();
+        Ok((files.len(), removed.len()))
+    }
+
+    /// Record a processed batch in the stats and log its `scan_batch` event
+    fn finish_batch(&mut self, files: usize, elapsed: Duration, debounce: Duration) {
+        self.batch_stats.record_batch(files, elapsed);
+        info!(
+            event = events::SCAN_BATCH,
+            files,
+            classes = self.cache.read().unwrap().len(),
+            errors = self.errors.len(),
+            duration_ms = events::millis(elapsed),
+            debounce_ms = events::millis(debounce),
+            "Batch crafted: {files} files"
+        );
+    }
+
+    fn scan_initial(&mut self, reparse: bool) -> Result<()> {
         let manifest_path = if let Some(parent) = self.config.output_path.parent() {
             parent.join(MANIFEST_FILE)
         } else {
             PathBuf::from(MANIFEST_FILE)
         };
+        let mut manifest = if reparse {
+            Manifest::default()
+        } else {
+            Manifest::load(&manifest_path)?
+        };
+        // Installed packages can keep their mtimes (e.g. restored from a CI
+        // cache), so after a dependency update vendor files are reparsed
+        if let Some(vendor_dir) = self.stale_vendor.take() {
+            let invalidated = manifest.invalidate_under(&vendor_dir);
+            self.log_info(&format!(
+                "Reparsing {invalidated} files under {}",
+                vendor_dir.display()
+            ));
+        }
 
-        let (metadata, new_manifest) = perform_incremental_scan(
-            &manifest_path,
+        if self.config.profile_files.is_some() {
+            crate::profile::start();
+        }
+        let (metadata, new_manifest) = scan_with_manifest(
+            manifest,
             &self.config.paths,
             &self.config.ignore_patterns,
             self.config.max_file_size,
+            &self.extractors,
+            false,
         )?;
+        if let Some(top) = self.config.profile_files {
+            info!(
+                "{}",
+                crate::profile::format_report(&crate::profile::finish(), top).trim_end()
+            );
+        }
 
         // Update manifest
+        self.errors.sync(&new_manifest);
         *self.manifest.write().unwrap() = new_manifest;
 
         // Update cache
@@ -455,20 +1163,305 @@ impl Daemon {
         for m in metadata {
             cache.insert(m.fqcn.clone(), m);
         }
+        drop(cache);
+
+        let manifest = self.manifest.read().unwrap();
+        let failures = manifest.failed_files().into_iter().map(|(path, entry)| {
+            (
+                Path::new(path),
+                entry.parse_error.as_deref().unwrap_or_default(),
+            )
+        });
+        scanner::check_failures(self.extractors.options().on_error, failures)
+            .map_err(AurynxError::other)
+    }
+
+    /// Fill the cache from the existing cache file (`--read-only`)
+    ///
+    /// The file was written by a scan with the same configuration, so its
+    /// classes are served as they are, without the post-scan passes. A file
+    /// that cannot be loaded leaves the cache as it was.
+    fn load_cache(&self) -> Result<()> {
+        let metadata = crate::reader::read_cache(&self.config.output_path)?;
+        let count = metadata.len();
+        let mut cache = self.cache.write().unwrap();
+        cache.clear();
+        for m in metadata {
+            cache.insert(m.fqcn.clone(), m);
+        }
+        drop(cache);
+        self.log_info(&format!(
+            "Read-only: serving {count} classes from {}",
+            self.config.output_path.display()
+        ));
+        Ok(())
+    }
+
+    /// Register non-recursive watches for every non-ignored directory under `root`
+    ///
+    /// Returns the number of directories registered. Exhausting the OS watch
+    /// limit is reported (and may switch to polling) rather than failing.
+    fn watch_tree(&mut self, root: &Path) -> Result<usize> {
+        match self.try_watch_tree(root) {
+            Ok(count) => Ok(count),
+            Err(e) if matches!(e.kind, notify::ErrorKind::MaxFilesWatch) => {
+                self.on_watch_limit()?;
+                Ok(0)
+            },
+            Err(e) => Err(AurynxError::watcher_error(
+                format!("Failed to watch {}", root.display()),
+                e,
+            )),
+        }
+    }
+
+    fn try_watch_tree(&mut self, root: &Path) -> notify::Result<usize> {
+        let Some(watcher) = self.watcher.as_mut() else {
+            return Ok(0);
+        };
+
+        // Watchman watches recursively on its side; ignored paths are filtered per event
+        if self.backend == WatchBackend::Watchman {
+            watcher.watch(root, RecursiveMode::Recursive)?;
+            return Ok(1);
+        }
+
+        let dirs = scanner::watch_directories(root, self.ignore.as_ref());
+
+        for dir in &dirs {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        }
+
+        Ok(dirs.len())
+    }
+
+    /// Nearest directory containing `composer.lock`, starting from the scanned paths
+    fn composer_root(&self) -> Option<PathBuf> {
+        self.config.paths.iter().find_map(|path| {
+            path.ancestors()
+                .find(|dir| dir.join("composer.lock").is_file())
+                .map(Path::to_path_buf)
+        })
+    }
+
+    /// Watch the composer files of the project containing the configured paths
+    ///
+    /// The parent directories are watched non-recursively so that atomic
+    /// replaces (write to temp + rename) are seen; `vendor/` usually stays
+    /// ignored for regular scanning.
+    fn watch_composer_files(&mut self) {
+        let Some(root) = self.composer_root() else {
+            self.log_warn(
+                "watch_composer enabled but no composer.lock found above the scanned paths",
+            );
+            return;
+        };
+
+        self.composer_files = vec![
+            root.join("composer.lock"),
+            root.join("vendor/composer/installed.json"),
+        ];
+        // Honours `config.vendor-dir` of composer.json
+        self.vendor_dir = crate::vendor::composer_dir(&self.config.paths)
+            .and_then(|dir| dir.parent().map(Path::to_path_buf))
+            .or_else(|| Some(root.join("vendor")));
+
+        for file in self.composer_files.clone() {
+            let Some(dir) = file.parent().filter(|d| d.is_dir()) else {
+                continue;
+            };
+            let Some(watcher) = self.watcher.as_mut() else {
+                return;
+            };
+            match watcher.watch(dir, RecursiveMode::NonRecursive) {
+                Ok(()) => self.log_info(&format!("Watching composer state: {}", file.display())),
+                Err(e) => self.log_warn(&format!("Failed to watch {}: {e}", dir.display())),
+            }
+        }
+    }
+
+    /// Create the configured watcher backend (falls back to the native watcher)
+    fn create_watcher(
+        &mut self, tx: EventQueue<notify::Result<notify::Event>>,
+    ) -> Result<Box<dyn Watcher>> {
+        #[cfg(unix)]
+        if self.config.watcher_backend == "watchman" {
+            match crate::watchman::WatchmanWatcher::new(tx.clone(), notify::Config::default()) {
+                Ok(watcher) => {
+                    self.backend = WatchBackend::Watchman;
+                    self.log_info("Using Watchman watcher backend");
+                    return Ok(Box::new(watcher));
+                },
+                Err(e) => {
+                    self.log_warn(&format!(
+                        "Watchman unavailable, falling back to native watcher: {e}"
+                    ));
+                },
+            }
+        }
+
+        Ok(Box::new(notify::recommended_watcher(tx)?))
+    }
+
+    /// Handle an error reported by the watcher at runtime
+    fn on_watch_error(&mut self, error: notify::Error) {
+        if matches!(error.kind, notify::ErrorKind::MaxFilesWatch) {
+            if let Err(e) = self.on_watch_limit() {
+                self.runtime_error("Polling fallback failed", e);
+            } else if !self.config.poll_fallback {
+                // Changes in some directories are missed from now on
+                self.stop_on_error(AurynxError::watcher_error("File watch limit reached", error));
+            }
+        } else {
+            warn!(
+                emoji = "⚠️",
+                event = events::WATCH_ERROR,
+                error = %error,
+                "Watch error: {error}"
+            );
+            self.stop_on_error(AurynxError::watcher_error("Watch error", error));
+        }
+    }
+
+    /// Log an error the daemon carries on after, unless it stops on errors
+    fn runtime_error(&mut self, action: &str, error: AurynxError) {
+        log_error(action, &error);
+        self.stop_on_error(error);
+    }
+
+    /// Remember the first runtime error to stop with (`exit_on_error`)
+    fn stop_on_error(&mut self, error: AurynxError) {
+        if self.config.exit_on_error && self.fatal.is_none() {
+            self.fatal = Some(error);
+        }
+    }
+
+    /// Result to stop with: a runtime error (`exit_on_error`) or idleness (`exit_on_idle`)
+    fn stop_reason(&mut self) -> Option<Result<()>> {
+        if let Some(error) = self.fatal.take() {
+            error!(
+                code = error.code(),
+                "Stopping on runtime error (--exit-on-error): {error}"
+            );
+            return Some(Err(error));
+        }
+        let idle = self.config.exit_on_idle?;
+        if self.last_activity.elapsed() < idle {
+            return None;
+        }
+        info!(
+            emoji = "✨",
+            event = events::IDLE_EXIT,
+            idle_s = idle.as_secs(),
+            "No requests or file changes for {}s, shutting down",
+            idle.as_secs()
+        );
+        Some(Ok(()))
+    }
+
+    /// Report OS watch exhaustion and optionally switch to the polling watcher
+    fn on_watch_limit(&mut self) -> Result<()> {
+        if self.watch_limit_hit {
+            return Ok(());
+        }
+        self.watch_limit_hit = true;
+
+        let current = std::fs::read_to_string("/proc/sys/fs/inotify/max_user_watches")
+            .map_or_else(|_| "unknown".to_string(), |v| v.trim().to_string());
+        warn!(
+            emoji = "⚠️",
+            event = events::WATCH_LIMIT,
+            max_user_watches = %current,
+            polling = self.config.poll_fallback,
+            "File watch limit reached (fs.inotify.max_user_watches = {current}); changes in some \
+             directories will be missed. Raise it with `sudo sysctl -w \
+             fs.inotify.max_user_watches=524288` (persist it in /etc/sysctl.d/), ignore large \
+             directories, or restart with --poll-fallback"
+        );
+
+        let Some(tx) = self.watch_tx.clone() else {
+            return Ok(());
+        };
+        if !self.config.poll_fallback {
+            return Ok(());
+        }
+
+        self.log_warn("Falling back to polling for file changes (1s interval)");
+        let config = notify::Config::default().with_poll_interval(Duration::from_secs(1));
+        self.watcher = Some(Box::new(PollWatcher::new(tx, config)?));
+        self.backend = WatchBackend::Polling;
+
+        for path in self.config.paths.clone() {
+            let count = self.try_watch_tree(&path).map_err(|e| {
+                AurynxError::watcher_error(format!("Failed to poll {}", path.display()), e)
+            })?;
+            self.log_info(&format!(
+                "Polling crafted: {} ({count} directories)",
+                path.display()
+            ));
+        }
 
         Ok(())
     }
 
     /// Collect paths from event for batch processing
-    fn collect_event_paths(&self, event: notify::Event) -> Result<Vec<PathBuf>> {
+    fn collect_event_paths(&mut self, event: notify::Event) -> Result<Vec<PathBuf>> {
         use notify::EventKind;
+        use notify::event::ModifyKind;
 
         let mut paths = Vec::new();
 
+        // Paused: drop events, `resume` triggers one consolidated rescan
+        if self.paused {
+            return Ok(paths);
+        }
+
+        // Consumer-side paths (e.g. from a host-side watcher) map back to local ones
+        let mut event = event;
+        if !self.path_map.is_empty() {
+            for path in &mut event.paths {
+                *path = self.path_map.to_local(path);
+            }
+        }
+
+        // Editor temp file churn never reaches the scanner
+        event
+            .paths
+            .retain(|p| !scanner::is_temp_file(&self.temp_files, p));
+
+        // Dependency update: rescan everything once instead of per-file
+        if let Some(file) = event.paths.iter().find(|p| self.composer_files.contains(p)) {
+            if !self.rescan_pending {
+                self.log_info(&format!(
+                    "{} changed, scheduling full rescan",
+                    file.display()
+                ));
+            }
+            self.rescan_pending = true;
+            self.stale_vendor.clone_from(&self.vendor_dir);
+        }
+
         match event.kind {
             EventKind::Modify(_) | EventKind::Create(_) => {
                 for path in event.paths {
-                    if path.extension().and_then(|s| s.to_str()) == Some("php") {
+                    if scanner::is_ignored(self.ignore.as_ref(), &path) {
+                        continue;
+                    }
+                    if path.is_dir() {
+                        // New or moved-in directory: watch it and pick up files created before the watch
+                        if matches!(
+                            event.kind,
+                            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_))
+                        ) {
+                            self.watch_tree(&path)?;
+                            let frontends = self.extractors.options().frontends;
+                            paths.extend(scanner::source_files_under(
+                                &path,
+                                self.ignore.as_ref(),
+                                frontends,
+                            ));
+                        }
+                    } else if self.extractors.options().frontends.is_source_file(&path) {
                         paths.push(path);
                     }
                 }
@@ -476,8 +1469,12 @@ impl Daemon {
             EventKind::Remove(_) => {
                 // Handle removals separately
                 for path in event.paths {
-                    let mut cache = self.cache.write().unwrap();
-                    cache.retain(|_, m| m.file != path);
+                    if scanner::is_ignored(self.ignore.as_ref(), &path) {
+                        continue;
+                    }
+                    self.cache.write().unwrap().remove_file(&path);
+                    self.errors.clear(&path);
+                    self.cache_generation += 1;
                 }
             },
             _ => {},
@@ -491,24 +1488,44 @@ impl Daemon {
         if paths.is_empty() {
             return Ok(());
         }
+        let mut paths = paths.to_vec();
+        if !self.held_back.is_empty() {
+            paths.append(&mut self.held_back);
+            paths.sort();
+            paths.dedup();
+        }
 
-        // Use scan_files_with_limit which handles parallel processing internally
+        // Parallel scan reusing the daemon's extractors across batches
         let max_file_size = self.config.max_file_size;
-        let all_metadata = scanner::scan_files_with_limit(paths, max_file_size);
+        let scans = scanner::scan_files_detailed_with_pool(&paths, max_file_size, &self.extractors);
+
+        // Under `on_error: fail` a batch is applied only when every file scanned
+        let failures = scans.iter().filter_map(|scan| {
+            scan.error
+                .as_ref()
+                .map(|error| (scan.path.as_path(), error.message.as_str()))
+        });
+        if let Err(e) = scanner::check_failures(self.extractors.options().on_error, failures) {
+            for scan in &scans {
+                if let Some(error) = &scan.error {
+                    self.errors.record(&scan.path, error.code, &error.message);
+                }
+            }
+            let count = paths.len();
+            self.held_back = paths;
+            return Err(AurynxError::other(format!(
+                "Batch of {count} files rejected: {e}"
+            )));
+        }
+        self.cache_generation += 1;
 
         // Update cache with results
         let mut cache = self.cache.write().unwrap();
         let mut manifest = self.manifest.write().unwrap();
 
-        for metadata in all_metadata {
-            let path = metadata.file.clone();
+        for scan in scans {
+            let path = scan.path.clone();
             let path_str = path.to_string_lossy().to_string();
-
-            // Remove old entries for this file
-            cache.retain(|_, m| m.file != path);
-
-            // Update manifest - get parsed classes for this file
-            let parsed_metadata = vec![metadata.clone()];
             let mtime = std::fs::metadata(&path)
                 .and_then(|m| m.modified())
                 .map(|t| {
@@ -518,49 +1535,176 @@ impl Daemon {
                 })
                 .unwrap_or(0);
 
-            manifest.files.insert(
-                path_str,
-                FileEntry {
-                    mtime,
-                    classes: parsed_metadata.clone(),
-                },
-            );
+            // A failing file keeps the classes of its last successful scan
+            if let Some(error) = &scan.error {
+                self.errors.record(&path, error.code, &error.message);
+                let previous = manifest.files.remove(&path_str);
+                manifest
+                    .files
+                    .insert(path_str, entry_from_scan(scan, mtime, previous));
+                continue;
+            }
+            self.errors.clear(&path);
+
+            // Remove old entries for this file
+            cache.remove_file(&path);
 
             // Security: check cache size limit
-            if cache.len() >= self.config.max_cache_entries {
+            if cache.len() >= self.config.max_cache_entries && !scan.classes.is_empty() {
                 self.log_warn(&format!(
                     "Cache limit reached ({} entries), skipping new entries",
                     self.config.max_cache_entries
                 ));
-                continue;
-            }
-
-            // Add new entries (with limit check)
-            for m in parsed_metadata {
-                if cache.len() >= self.config.max_cache_entries {
-                    self.log_warn("Cache limit reached, stopping scan");
-                    break;
+            } else {
+                // Add new entries (with limit check)
+                for m in &scan.classes {
+                    if cache.len() >= self.config.max_cache_entries {
+                        self.log_warn("Cache limit reached, stopping scan");
+                        break;
+                    }
+                    cache.insert(m.fqcn.clone(), m.clone());
                 }
-                cache.insert(m.fqcn.clone(), m);
             }
+
+            let previous = manifest.files.remove(&path_str);
+            manifest
+                .files
+                .insert(path_str, entry_from_scan(scan, mtime, previous));
         }
 
         Ok(())
     }
 
-    fn write_cache_file(&self) -> Result<()> {
-        let cache = self.cache.read().unwrap();
-        let metadata: Vec<_> = cache.values().cloned().collect();
+    /// Post-scan passes of the cache (see `crate::pipeline`)
+    const fn output_passes(&self) -> crate::pipeline::OutputPasses<'_> {
+        crate::pipeline::OutputPasses {
+            inherit_attributes: self.config.inherit_attributes,
+            resolve_hierarchy: self.config.resolve_hierarchy,
+            signature_hash: self.config.signature_hash,
+            max_class_members: self.config.max_class_members,
+            output_exclude: self.output_exclude.as_ref(),
+        }
+    }
+
+    /// Sorted copy of the cache, with post-scan passes applied
+    fn cache_snapshot(&self) -> Result<Vec<PhpClassMetadata>> {
+        let mut metadata = self
+            .cache
+            .read()
+            .unwrap()
+            .to_vec()
+            .context("Failed to read spilled classes")?;
+        metadata.sort_by(|a, b| a.fqcn.cmp(&b.fqcn));
+        // A loaded cache file already went through these passes
+        if self.config.read_only {
+            return Ok(metadata);
+        }
+
+        self.output_passes().apply(&mut metadata);
+        self.path_map.apply(&mut metadata);
+
+        Ok(metadata)
+    }
+
+    /// Why the class last flushed from `file` (a cache path) is gone
+    fn removal_reason(&self, file: &Path) -> RemovalReason {
+        let local = self.path_map.to_local(file);
+        if !local.exists() {
+            RemovalReason::FileDeleted
+        } else if scanner::is_ignored(self.output_exclude.as_ref(), &local) {
+            RemovalReason::Filtered
+        } else {
+            RemovalReason::ClassRemoved
+        }
+    }
+
+    /// Answer `getRemoved`
+    fn removed_response(&self) -> String {
+        let mut response = format!(
+            "REMOVED {} flush:{}\n",
+            self.last_removed.len(),
+            self.flushes
+        );
+        for class in &self.last_removed {
+            let _ = writeln!(
+                response,
+                "{}\t{}\t{}",
+                class.fqcn,
+                class.file.display(),
+                class.reason.as_str()
+            );
+        }
+        response
+    }
 
-        // Atomic write cache
-        let temp = self.config.output_path.with_extension("tmp");
+    fn write_cache_file(&mut self) -> Result<()> {
+        let write_start = Instant::now();
+        let metadata = self.cache_snapshot()?;
+        let path_root = self
+            .config
+            .path_root
+            .as_ref()
+            .map(|root| self.path_map.to_mapped(root));
+
+        // Atomic write cache (skipped when the rendered content is unchanged)
+        let written = crate::writer::write_cache_if_changed(
+            &metadata,
+            &self.config.output_path,
+            &self.config.format,
+            self.config.pretty,
+            path_root.as_deref(),
+            self.config.cache_layout,
+        )?;
+        if !written {
+            self.log("Cache content unchanged, skipped rewrite");
+        }
+        info!(
+            event = events::CACHE_WRITE,
+            classes = metadata.len(),
+            written,
+            duration_ms = events::millis(write_start.elapsed()),
+            "Cache flushed to {}",
+            self.config.output_path.display()
+        );
+        self.cache_file_generation = Some(self.cache_generation);
 
-        match self.config.format.as_str() {
-            "json" => crate::writer::write_json_cache(&metadata, &temp, self.config.pretty)?,
-            _ => write_php_cache(&metadata, &temp, self.config.pretty)?,
+        // Tracked on every flush: an unchanged first write still sets the baseline
+        let fingerprints = self.hooks.is_some() || self.config.journal_file.is_some();
+        let diff = self.flushed.update(&metadata, fingerprints);
+        if written || diff.first {
+            self.append_journal(metadata.len(), &diff);
+        }
+        if written {
+            let removed: Vec<RemovedClass> = diff
+                .removed
+                .into_iter()
+                .map(|(fqcn, file)| RemovedClass {
+                    reason: self.removal_reason(&file),
+                    fqcn,
+                    file,
+                })
+                .collect();
+            self.flushes += 1;
+            if let Some(hooks) = &self.hooks {
+                hooks.notify(UpdateEvent {
+                    cache: self.config.output_path.clone(),
+                    classes: metadata.len(),
+                    changed: diff.changed,
+                    removed: removed.iter().map(|class| class.fqcn.clone()).collect(),
+                    removed_classes: removed.clone(),
+                    attributes: diff.attributes,
+                });
+            }
+            self.last_removed = removed;
         }
 
-        std::fs::rename(temp, &self.config.output_path)?;
+        // A list is rewritten only when its own content changed
+        if let Err(e) = crate::class_lists::write_class_lists(&metadata, &self.config.class_lists) {
+            warn!(emoji = "⚠️", "Failed to write class lists: {e:#}");
+        }
+        if let Err(e) = crate::derived::write_derived(&metadata, &self.config.derived) {
+            warn!(emoji = "⚠️", "Failed to write derived outputs: {e:#}");
+        }
 
         // Write manifest
         if let Some(parent) = self.config.output_path.parent() {
@@ -572,12 +1716,40 @@ impl Daemon {
         Ok(())
     }
 
+    /// Record a flush in `journal_file`; the start of the daemon is recorded
+    /// without a diff
+    fn append_journal(&self, classes: usize, diff: &FlushDiff) {
+        let Some(path) = &self.config.journal_file else {
+            return;
+        };
+        let mut entry = JournalEntry::now(classes);
+        if diff.first {
+            entry.start = true;
+        } else {
+            entry.changed = diff
+                .changed
+                .iter()
+                .filter(|fqcn| diff.added.binary_search(fqcn).is_err())
+                .cloned()
+                .collect();
+            entry.added.clone_from(&diff.added);
+            entry.removed = diff.removed.iter().map(|(fqcn, _)| fqcn.clone()).collect();
+        }
+        if let Err(e) = crate::journal::append(path, &entry, self.config.journal_max_entries) {
+            warn!(emoji = "⚠️", "Failed to append to the change journal: {e:#}");
+        }
+    }
+
     #[cfg(unix)]
     fn setup_unix_socket(&self) -> Result<std::os::unix::net::UnixListener> {
         use std::os::unix::fs::PermissionsExt;
 
-        // Remove old socket if exists
-        let _ = std::fs::remove_file(&self.config.socket_path);
+        // Only a stale socket is replaced, unless forced
+        if self.config.force {
+            let _ = std::fs::remove_file(&self.config.socket_path);
+        } else {
+            stale::adopt_socket(&self.config.socket_path)?;
+        }
 
         let listener =
             std::os::unix::net::UnixListener::bind(&self.config.socket_path).map_err(|e| {
@@ -607,10 +1779,42 @@ impl Daemon {
     }
 
     #[cfg(unix)]
-    fn check_ipc_requests(&self, listener: &std::os::unix::net::UnixListener) -> Result<()> {
+    /// Answer the commands that respond with one text block
+    fn text_response(&mut self, command: &str) -> Option<String> {
+        let response = match command {
+            "getFilePath" => self.file_path_response(),
+            "getErrors" => self.errors.to_response(),
+            "getRemoved" => self.removed_response(),
+            "getShm" => self.shm_handshake(),
+            "version" => self.version_info(),
+            "stats verbose" => self.verbose_stats(),
+            "stats" => self.stats(),
+            "reload" => self.reload(),
+            command if let Some(file) = command.strip_prefix("snapshot ") => {
+                self.take_snapshot(file.trim())
+            },
+            command if let Some(file) = command.strip_prefix("restore ") => {
+                self.restore_snapshot(file.trim())
+            },
+            command if self.config.read_only && command.starts_with("rescanPath ") => {
+                read_only_error("rescanPath")
+            },
+            command if self.config.read_only && command.starts_with("scanOnDemand") => {
+                read_only_error("scanOnDemand")
+            },
+            command if let Some(mode) = command.strip_prefix("scanOnDemand") => {
+                self.scan_on_demand(mode.trim())
+            },
+            command => self.rescan_path(command.strip_prefix("rescanPath ")?.trim()),
+        };
+        Some(response)
+    }
+
+    fn check_ipc_requests(&mut self, listener: &std::os::unix::net::UnixListener) -> Result<()> {
         // Try to accept connection (non-blocking)
         match listener.accept() {
             Ok((stream, _addr)) => {
+                self.last_activity = Instant::now();
                 // Set blocking mode for the connection
                 stream
                     .set_nonblocking(false)
@@ -639,10 +1843,13 @@ impl Daemon {
 
                     // Security: limit request size
                     if line.len() > self.config.max_request_size {
-                        let error_msg = format!(
-                            "ERROR: Request too large: {} bytes (max: {})\n",
-                            line.len(),
-                            self.config.max_request_size
+                        let error_msg = error_line(
+                            code::INVALID_REQUEST,
+                            format_args!(
+                                "Request too large: {} bytes (max: {})",
+                                line.len(),
+                                self.config.max_request_size
+                            ),
                         );
                         let _ = writer.write_all(error_msg.as_bytes());
                         let _ = writer.flush();
@@ -654,58 +1861,34 @@ impl Daemon {
                     let trimmed = line.trim();
 
                     match trimmed {
-                        "getCode" | "getCacheCode" | "getPhpCode" => {
-                            // Return raw PHP code directly (CRITICAL: No JSON wrapper!)
-                            match self.generate_php_code() {
-                                Ok(code) => {
-                                    if let Err(e) = writer.write_all(code.as_bytes()) {
-                                        warn!(error = %e, "IPC write error");
-                                        break;
-                                    }
-                                    if let Err(e) = writer.flush() {
-                                        warn!(error = %e, "IPC flush error");
-                                        break;
-                                    }
-                                },
-                                Err(e) => {
-                                    let error_msg =
-                                        format!("ERROR: Failed to generate PHP code: {e}\n");
-                                    let _ = writer.write_all(error_msg.as_bytes());
-                                    let _ = writer.flush();
-                                },
+                        command if let Some(request) = CodeRequest::parse(command) => {
+                            if !self.serve_code(&mut writer, request) {
+                                break;
                             }
                         },
-                        "getFilePath" => {
-                            // Return file path as plain text
-                            if self.strategy == CacheStrategy::File {
-                                let path = self.config.output_path.to_string_lossy();
-                                let _ = writer.write_all(path.as_bytes());
-                                let _ = writer.write_all(b"\n");
-                                let _ = writer.flush();
-                            } else {
-                                let _ = writer.write_all(b"ERROR: File strategy not available\n");
-                                let _ = writer.flush();
-                            }
+                        command if let Some(response) = self.text_response(command) => {
+                            let _ = writer.write_all(response.as_bytes());
+                            let _ = writer.flush();
                         },
                         "ping" => {
                             let _ = writer.write_all(b"PONG\n");
                             let _ = writer.flush();
                         },
-                        "stats" => {
-                            // Return plain text stats
-                            let cache = self.cache.read().unwrap();
-                            let stats = format!(
-                                "total:{} strategy:{:?} uptime:{}\n",
-                                cache.len(),
-                                self.strategy,
-                                self.start_time.elapsed().as_secs()
-                            );
-                            let _ = writer.write_all(stats.as_bytes());
+                        "pause" | "resume" if self.config.read_only => {
+                            let _ = writer.write_all(read_only_error(trimmed).as_bytes());
+                            let _ = writer.flush();
+                        },
+                        "pause" | "resume" => {
+                            let response = self.set_paused(trimmed == "pause");
+                            let _ = writer.write_all(response);
                             let _ = writer.flush();
                         },
                         _ => {
                             // Unknown command - send error as plain text
-                            let error_msg = format!("ERROR: Unknown command: {trimmed}\n");
+                            let error_msg = error_line(
+                                code::INVALID_REQUEST,
+                                format_args!("Unknown command: {trimmed}"),
+                            );
                             let _ = writer.write_all(error_msg.as_bytes());
                             let _ = writer.flush();
                         },
@@ -724,15 +1907,393 @@ impl Daemon {
         Ok(())
     }
 
-    fn generate_php_code(&self) -> Result<String> {
-        let cache = self.cache.read().unwrap();
-        let metadata: Vec<_> = cache.values().cloned().collect();
+    /// Handle `pause`/`resume`; resuming schedules one consolidated rescan
+    fn set_paused(&mut self, paused: bool) -> &'static [u8] {
+        if paused && !self.paused {
+            info!(
+                emoji = "✨",
+                event = events::PAUSE,
+                "Watching paused via IPC"
+            );
+        } else if !paused && self.paused {
+            self.rescan_pending = true;
+            info!(
+                emoji = "✨",
+                event = events::RESUME,
+                "Watching resumed via IPC"
+            );
+        }
+        self.paused = paused;
+
+        if paused { b"PAUSED\n" } else { b"RESUMED\n" }
+    }
+
+    /// Take one event from the watcher channel, keeping its depth counter in sync
+    fn recv_event(
+        &self, rx: &Receiver<notify::Result<notify::Event>>, timeout: Duration,
+    ) -> std::result::Result<notify::Result<notify::Event>, RecvTimeoutError> {
+        let event = rx.recv_timeout(timeout)?;
+        if let Some(tx) = &self.watch_tx {
+            tx.received();
+        }
+        Ok(event)
+    }
+
+    /// Answer `stats` with plain text counters on one line
+    fn stats(&self) -> String {
+        let snapshot = self.snapshot.load();
+        format!(
+            "total:{} strategy:{:?} uptime:{} errors:{} {} memory_rss_bytes:{} memory_cache_bytes:{} spilled:{}\n",
+            snapshot.total,
+            self.strategy,
+            self.start_time.elapsed().as_secs(),
+            self.errors.len(),
+            self.batch_stats.to_stats_fields(),
+            resident_memory_bytes().unwrap_or(0),
+            snapshot.memory_bytes,
+            snapshot.spilled
+        )
+    }
+
+    /// Answer `stats verbose`: one `key:value` line per counter, then an empty line
+    ///
+    /// Keys are stable; new ones may be added. `path` is repeated once per
+    /// configured path as `path:<classes> <path>`.
+    fn verbose_stats(&self) -> String {
+        let snapshot = self.snapshot.load();
+        let mut per_path = vec![0usize; self.config.paths.len()];
+        let (total, evictions) = {
+            let cache = self.cache.read().unwrap();
+            for file in cache.files() {
+                if let Some(i) = self.config.paths.iter().position(|p| file.starts_with(p)) {
+                    per_path[i] += 1;
+                }
+            }
+            (cache.len(), cache.evictions())
+        };
+
+        let mut lines = vec![
+            "version:2".to_string(),
+            format!("total:{total}"),
+            format!("generation:{}", self.cache_generation),
+            format!("strategy:{:?}", self.strategy),
+            format!("uptime:{}", self.start_time.elapsed().as_secs()),
+            format!("memory_rss_bytes:{}", resident_memory_bytes().unwrap_or(0)),
+            format!("memory_cache_bytes:{}", snapshot.memory_bytes),
+            format!("memory_snapshot_bytes:{}", snapshot_bytes(&snapshot)),
+            format!(
+                "memory_budget_bytes:{}",
+                self.config.max_memory_bytes.unwrap_or(0)
+            ),
+            format!("memory_over_budget:{}", self.over_memory_budget),
+            format!("spilled:{}", snapshot.spilled),
+            format!("evictions:{evictions}"),
+            format!(
+                "queue_depth:{}",
+                self.watch_tx.as_ref().map_or(0, EventQueue::depth)
+            ),
+            format!("paused:{}", u8::from(self.paused)),
+            format!("read_only:{}", u8::from(self.config.read_only)),
+            format!("errors:{}", self.errors.len()),
+        ];
+        lines.extend(
+            self.batch_stats
+                .to_stats_fields()
+                .split(' ')
+                .map(str::to_string),
+        );
+        for (path, count) in self.config.paths.iter().zip(per_path) {
+            lines.push(format!("path:{count} {}", path.display()));
+        }
+
+        lines.join("\n") + "\n\n"
+    }
+
+    /// Answer `version`: build, metadata schema and IPC features of this daemon
+    ///
+    /// `protocol` is bumped when the response format of an existing command
+    /// changes; new commands only extend `features`. Commands that depend on
+    /// the configuration (`getFilePath`, `getShm`, and `pause`, `resume` and
+    /// `rescanPath`, which a read-only daemon refuses) are listed only when
+    /// they can succeed.
+    fn version_info(&self) -> String {
+        let mut features = vec!["getCode", "getClass", "json"];
+        if self.strategy == CacheStrategy::File {
+            features.push("getFilePath");
+        }
+        if self.shm.is_some() {
+            features.push("getShm");
+        }
+        features.extend(["getErrors", "getRemoved", "ping"]);
+        if !self.config.read_only {
+            features.extend(["pause", "resume", "rescanPath", "scanOnDemand"]);
+        }
+        features.extend(["reload", "restore", "snapshot"]);
+        features.extend(["stats", "stats-verbose", "version"]);
+
+        format!(
+            "version:{} git:{} schema:{} protocol:{PROTOCOL_VERSION} features:{}\n",
+            env!("CARGO_PKG_VERSION"),
+            env!("GIT_HASH"),
+            crate::schema::SCHEMA_VERSION,
+            features.join(",")
+        )
+    }
+
+    /// Answer `getShm` with the current shared-memory segment
+    fn shm_handshake(&self) -> String {
+        self.shm
+            .as_ref()
+            .and_then(SharedMemory::current)
+            .map_or_else(
+                || error_line(code::UNAVAILABLE, "Shared memory not enabled"),
+                |segment| format!("{} {} {}\n", segment.name, segment.size, segment.generation),
+            )
+    }
+
+    /// `getFilePath` response: the cache file path as plain text
+    fn file_path_response(&self) -> String {
+        if self.strategy == CacheStrategy::File {
+            format!("{}\n", self.config.output_path.to_string_lossy())
+        } else {
+            error_line(code::UNAVAILABLE, "File strategy not available")
+        }
+    }
+
+    /// Answer `getCode`; returns `false` if the connection should be closed
+    #[cfg(unix)]
+    fn serve_code(
+        &self, writer: &mut std::os::unix::net::UnixStream,
+        request: std::result::Result<CodeRequest, String>,
+    ) -> bool {
+        let response = match request {
+            Ok(CodeRequest::Cache(ResponseFormat::Php)) => return self.serve_php_code(writer),
+            Ok(CodeRequest::Cache(ResponseFormat::Json)) => {
+                let snapshot = self.snapshot.load();
+                snapshot
+                    .json_code
+                    .get_or_init(|| {
+                        self.snapshot_classes(&snapshot)
+                            .map_err(|e| error_line(e.code(), e))
+                            .and_then(|classes| {
+                                json_line(&*classes).map_err(|e| error_line(code::JSON, e))
+                            })
+                    })
+                    .clone()
+            },
+            Ok(CodeRequest::Class(fqcn, format)) => self
+                .render_class(fqcn, format)
+                .map_err(|e| error_line(e.code(), e)),
+            Err(e) => Err(error_line(code::INVALID_REQUEST, e)),
+        };
+
+        let response = response.unwrap_or_else(String::into_bytes);
+        if let Err(e) = writer.write_all(&response).and_then(|()| writer.flush()) {
+            warn!(error = %e, "IPC write error");
+            return false;
+        }
+        true
+    }
+
+    /// Answer `getClass <fqcn> <format>` from the current snapshot
+    fn render_class(&self, fqcn: &str, format: ResponseFormat) -> Result<Vec<u8>> {
+        // Cached FQCNs carry the leading backslash
+        let key = format!("\\{fqcn}");
+        let not_found = || AurynxError::not_found(format!("Class not found: {fqcn}"));
+        let class = if self.config.max_memory_entries.is_some()
+            && !self.config.inherit_attributes
+            && !self.config.resolve_hierarchy
+        {
+            self.stored_class(&key)?.ok_or_else(not_found)?
+        } else {
+            let classes = self.snapshot_classes(&self.snapshot.load())?;
+            classes
+                .binary_search_by(|class| class.fqcn.as_str().cmp(&key))
+                .map(|index| classes[index].clone())
+                .map_err(|_| not_found())?
+        };
+
+        match format {
+            ResponseFormat::Php => {
+                let mut code = Vec::new();
+                render_php_cache(std::slice::from_ref(&class), &mut code, self.config.pretty)?;
+                Ok(code)
+            },
+            ResponseFormat::Json => Ok(json_line(&class)?),
+        }
+    }
+
+    /// Class `key` as the cache has it, read from the store alone
+    ///
+    /// Bounded memory mode looks classes up this way instead of reading the
+    /// whole spill file; resolving a hierarchy needs every class, so that
+    /// still goes through [`Self::cache_snapshot`].
+    fn stored_class(&self, key: &str) -> Result<Option<PhpClassMetadata>> {
+        let Some(mut class) = self
+            .cache
+            .read()
+            .unwrap()
+            .get(key)
+            .context("Failed to read spilled class")?
+        else {
+            return Ok(None);
+        };
+        // A loaded cache file already went through the post-scan passes
+        if self.config.read_only {
+            return Ok(Some(class));
+        }
+        if scanner::is_ignored(self.output_exclude.as_ref(), &class.file) {
+            return Ok(None);
+        }
+
+        let classes = std::slice::from_mut(&mut class);
+        self.output_passes().trim(classes);
+        self.path_map.apply(classes);
+        Ok(Some(class))
+    }
+
+    /// Classes of a snapshot, read from the store on first use
+    ///
+    /// The main loop publishes a snapshot before serving IPC, so the store
+    /// is at the snapshot's generation. Bounded memory mode reads them again
+    /// each time rather than keeping a second copy of the store in memory.
+    fn snapshot_classes(&self, snapshot: &CacheSnapshot) -> Result<Arc<Vec<PhpClassMetadata>>> {
+        if self.config.max_memory_entries.is_some() {
+            return Ok(Arc::new(self.cache_snapshot()?));
+        }
+        snapshot
+            .classes
+            .get_or_init(|| {
+                self.cache_snapshot()
+                    .map(Arc::new)
+                    .map_err(|e| e.to_string())
+            })
+            .clone()
+            .map_err(AurynxError::other)
+    }
+
+    /// PHP cache of a snapshot, rendered on first use
+    fn snapshot_php_code<'a>(
+        &self, snapshot: &'a CacheSnapshot,
+    ) -> &'a std::result::Result<Vec<u8>, String> {
+        snapshot.php_code.get_or_init(|| {
+            let classes = self.snapshot_classes(snapshot).map_err(|e| e.to_string())?;
+            let mut code = Vec::new();
+            render_php_cache(&classes, &mut code, self.config.pretty).map_err(|e| e.to_string())?;
+            Ok(code)
+        })
+    }
+
+    fn serve_php_code(&self, writer: &mut std::os::unix::net::UnixStream) -> bool {
+        // Up-to-date cache file: hand it to the kernel, no regeneration
+        if self.can_send_cache_file() {
+            return match sendfile::send_file(writer, &self.config.output_path) {
+                Ok(_) => true,
+                Err(e) => {
+                    warn!(error = %e, "IPC sendfile error");
+                    false
+                },
+            };
+        }
+
+        // Return raw PHP code directly (CRITICAL: No JSON wrapper!)
+        let snapshot = self.snapshot.load();
+        match self.snapshot_php_code(&snapshot) {
+            Ok(code) => {
+                if let Err(e) = writer.write_all(code) {
+                    warn!(error = %e, "IPC write error");
+                    return false;
+                }
+                if let Err(e) = writer.flush() {
+                    warn!(error = %e, "IPC flush error");
+                    return false;
+                }
+            },
+            Err(e) => {
+                let error_msg =
+                    error_line(code::OTHER, format_args!("Failed to generate PHP code: {e}"));
+                let _ = writer.write_all(error_msg.as_bytes());
+                let _ = writer.flush();
+            },
+        }
+        true
+    }
+
+    /// Whether `getCode` can be served from the written cache file as-is
+    ///
+    /// Only a flat PHP cache without `path_root` has exactly the bytes
+    /// `getCode` would generate, and only while no change is waiting to be
+    /// flushed.
+    fn can_send_cache_file(&self) -> bool {
+        self.strategy == CacheStrategy::File
+            && self.cache_file_generation == Some(self.cache_generation)
+            && self.config.format == "php"
+            && self.config.path_root.is_none()
+            && self.config.cache_layout.is_default()
+    }
+
+    /// Check the resident set against `max_memory_mb` after a batch
+    ///
+    /// Over budget, bounded memory mode spills the coldest classes, about as
+    /// much as the overage, and keeps the hot set at its reduced size; without
+    /// a spill file there is nothing to shed, so a warning is logged each time
+    /// usage crosses the budget. RSS is only known on Linux.
+    fn enforce_memory_budget(&mut self) {
+        let (Some(budget), Some(rss)) = (self.config.max_memory_bytes, resident_memory_bytes())
+        else {
+            return;
+        };
+        if rss <= budget {
+            self.over_budget_rss = None;
+            return;
+        }
+        self.over_memory_budget += 1;
+
+        let spilled = self.cache.write().unwrap().shed(rss - budget);
+        if spilled > 0 {
+            warn!(
+                rss_bytes = rss,
+                budget_bytes = budget,
+                spilled,
+                "Memory budget exceeded, spilled {} classes to disk",
+                spilled
+            );
+        } else if self.over_budget_rss.is_none() {
+            warn!(
+                rss_bytes = rss,
+                budget_bytes = budget,
+                "Memory usage of {} MB exceeds max_memory_mb ({} MB); set max_memory_entries to spill classes to disk",
+                rss / (1024 * 1024),
+                budget / (1024 * 1024)
+            );
+        }
+        self.over_budget_rss = Some(rss);
+    }
 
-        // Use existing writer to generate PHP code
-        let temp_file = tempfile::NamedTempFile::new()?;
-        write_php_cache(&metadata, temp_file.path(), self.config.pretty)?;
+    /// Publish the current cache for IPC readers
+    ///
+    /// Rendering waits for the first request, except for the shared memory
+    /// segment, which readers map without asking the daemon.
+    fn publish_snapshot(&mut self) {
+        self.enforce_memory_budget();
+        {
+            let cache = self.cache.read().unwrap();
+            self.snapshot.store(CacheSnapshot {
+                generation: self.cache_generation,
+                total: cache.len(),
+                spilled: cache.spilled_len(),
+                memory_bytes: cache.memory_bytes(),
+                ..CacheSnapshot::default()
+            });
+        }
 
-        let code = std::fs::read_to_string(temp_file.path())?;
-        Ok(code)
+        if self.shm.is_some() {
+            let snapshot = self.snapshot.load();
+            if let (Ok(code), Some(shm)) = (self.snapshot_php_code(&snapshot), &mut self.shm)
+                && let Err(e) = shm.publish(code, self.cache_generation)
+            {
+                warn!(error = %e, "Failed to write shared memory segment");
+            }
+        }
     }
 }
@@ -0,0 +1,214 @@
+//! Adaptive event batching: tuning knobs and counters
+//!
+//! The watch loop waits for a short quiet window after the first event before
+//! rescanning, and waits longer once a batch grows past a threshold (branch
+//! switches, `composer install`) so mass changes are processed in one pass.
+//! The counters are reported by the `stats` IPC command to make tuning visible.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::Duration;
+
+/// Thresholds of the adaptive debounce (configurable in `aurynx.json`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchTuning {
+    /// Pending file count above which a batch counts as a mass change
+    pub mass_threshold: usize,
+    /// Quiet window before processing a normal batch
+    pub debounce: Duration,
+    /// Quiet window before processing a mass change
+    pub mass_debounce: Duration,
+}
+
+impl Default for BatchTuning {
+    fn default() -> Self {
+        Self {
+            mass_threshold: 100,
+            debounce: Duration::from_millis(300),
+            mass_debounce: Duration::from_secs(1),
+        }
+    }
+}
+
+impl BatchTuning {
+    /// Debounce window for a batch with `pending` changed files
+    #[must_use]
+    pub const fn debounce_for(&self, pending: usize) -> Duration {
+        if pending > self.mass_threshold {
+            self.mass_debounce
+        } else {
+            self.debounce
+        }
+    }
+}
+
+/// Counters describing the batches processed so far
+#[derive(Debug, Clone, Default)]
+pub struct BatchStats {
+    /// Batches rescanned
+    pub batches: u64,
+    /// Files rescanned across all batches
+    pub files: u64,
+    /// Size of the most recent batch
+    pub last_size: usize,
+    /// Largest batch seen
+    pub max_size: usize,
+    /// Times the normal debounce window was chosen
+    pub normal_windows: u64,
+    /// Times the mass-change debounce window was chosen
+    pub mass_windows: u64,
+    /// Rescan time of the most recent batch
+    pub last_rescan: Duration,
+    /// Longest batch rescan
+    pub max_rescan: Duration,
+    /// Rescan time across all batches
+    pub total_rescan: Duration,
+}
+
+impl BatchStats {
+    /// Record which debounce window was chosen for a batch
+    pub const fn record_window(&mut self, tuning: &BatchTuning, pending: usize) {
+        if pending > tuning.mass_threshold {
+            self.mass_windows += 1;
+        } else {
+            self.normal_windows += 1;
+        }
+    }
+
+    /// Record a processed batch of `size` files that took `elapsed` to rescan
+    pub fn record_batch(&mut self, size: usize, elapsed: Duration) {
+        self.batches += 1;
+        self.files += size as u64;
+        self.last_size = size;
+        self.max_size = self.max_size.max(size);
+        self.last_rescan = elapsed;
+        self.max_rescan = self.max_rescan.max(elapsed);
+        self.total_rescan += elapsed;
+    }
+
+    /// `key:value` pairs appended to the `stats` IPC response
+    #[must_use]
+    pub fn to_stats_fields(&self) -> String {
+        format!(
+            "batches:{} batch_files:{} batch_last:{} batch_max:{} debounce_normal:{} debounce_mass:{} rescan_last_ms:{} rescan_max_ms:{} rescan_total_ms:{}",
+            self.batches,
+            self.files,
+            self.last_size,
+            self.max_size,
+            self.normal_windows,
+            self.mass_windows,
+            self.last_rescan.as_millis(),
+            self.max_rescan.as_millis(),
+            self.total_rescan.as_millis(),
+        )
+    }
+}
+
+/// Watcher event sender counting the events not yet taken by the watch loop
+///
+/// `std::sync::mpsc` has no length, so the depth reported by `stats verbose`
+/// is tracked here: incremented per sent event, decremented by
+/// [`EventQueue::received`].
+#[derive(Debug)]
+pub struct EventQueue<T> {
+    tx: Sender<T>,
+    depth: Arc<AtomicUsize>,
+}
+
+// Not derived: events themselves need not be `Clone`
+impl<T> Clone for EventQueue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            depth: Arc::clone(&self.depth),
+        }
+    }
+}
+
+impl<T> EventQueue<T> {
+    /// Counting sender and its receiver, like [`std::sync::mpsc::channel`]
+    #[must_use]
+    pub fn channel() -> (Self, Receiver<T>) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let queue = Self {
+            tx,
+            depth: Arc::new(AtomicUsize::new(0)),
+        };
+        (queue, rx)
+    }
+
+    /// Send an event (dropped when the watch loop is gone)
+    pub fn send(&self, event: T) {
+        self.depth.fetch_add(1, Ordering::Relaxed);
+        if self.tx.send(event).is_err() {
+            self.depth.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Record that the watch loop took one event from the channel
+    pub fn received(&self) {
+        let _ = self
+            .depth
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |depth| {
+                depth.checked_sub(1)
+            });
+    }
+
+    /// Events sent but not received yet
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.depth.load(Ordering::Relaxed)
+    }
+}
+
+impl notify::EventHandler for EventQueue<notify::Result<notify::Event>> {
+    fn handle_event(&mut self, event: notify::Result<notify::Event>) {
+        self.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debounce_window_and_counters() {
+        let tuning = BatchTuning {
+            mass_threshold: 2,
+            ..BatchTuning::default()
+        };
+        let mut stats = BatchStats::default();
+
+        assert_eq!(tuning.debounce_for(2), tuning.debounce);
+        assert_eq!(tuning.debounce_for(3), tuning.mass_debounce);
+
+        stats.record_window(&tuning, 1);
+        stats.record_batch(1, Duration::from_millis(5));
+        stats.record_window(&tuning, 5);
+        stats.record_batch(5, Duration::from_millis(20));
+
+        assert_eq!(
+            stats.to_stats_fields(),
+            "batches:2 batch_files:6 batch_last:5 batch_max:5 debounce_normal:1 debounce_mass:1 rescan_last_ms:20 rescan_max_ms:20 rescan_total_ms:25"
+        );
+    }
+
+    #[test]
+    fn test_event_queue_tracks_depth() {
+        let (queue, rx) = EventQueue::channel();
+        queue.send(1);
+        queue.send(2);
+        assert_eq!(queue.depth(), 2);
+
+        while rx.try_recv().is_ok() {
+            queue.received();
+        }
+        queue.received();
+        assert_eq!(queue.depth(), 0);
+
+        drop(rx);
+        queue.send(3);
+        assert_eq!(queue.depth(), 0);
+    }
+}
@@ -0,0 +1,172 @@
+//! Files that currently fail to scan (`getErrors` IPC command)
+//!
+//! A file that cannot be read or parsed keeps the classes of its last
+//! successful scan, if any, and is retried on its next change. Without this
+//! log the failure is only visible in the daemon output, so the classes of a
+//! new broken file seem to silently vanish from the cache.
+
+use crate::error::code;
+use crate::incremental::Manifest;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Last scan error of one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanError {
+    /// Stable error code (see [`crate::error::code`])
+    pub code: String,
+    pub message: String,
+    /// When the file started failing with this message
+    pub since: SystemTime,
+}
+
+/// Current scan errors by file
+#[derive(Debug, Default)]
+pub struct ErrorLog {
+    errors: BTreeMap<PathBuf, ScanError>,
+}
+
+impl ErrorLog {
+    /// Record a failed scan; the timestamp is kept while the message is unchanged
+    pub fn record(&mut self, path: &Path, code: &str, message: &str) {
+        if self
+            .errors
+            .get(path)
+            .is_some_and(|error| error.message == message)
+        {
+            return;
+        }
+        self.errors.insert(
+            path.to_path_buf(),
+            ScanError {
+                code: code.to_string(),
+                message: message.to_string(),
+                since: SystemTime::now(),
+            },
+        );
+    }
+
+    /// Forget the error of a file that was scanned successfully or removed
+    pub fn clear(&mut self, path: &Path) {
+        self.errors.remove(path);
+    }
+
+    /// Replace the log with the failed files of a full scan
+    pub fn sync(&mut self, manifest: &Manifest) {
+        let previous = std::mem::take(&mut self.errors);
+        for (path, entry) in manifest.failed_files() {
+            let path = PathBuf::from(path);
+            let message = entry.parse_error.as_deref().unwrap_or_default();
+            // Manifests written before error codes existed have none
+            let code = entry.error_code.as_deref().unwrap_or(code::OTHER);
+            let since = previous
+                .get(&path)
+                .filter(|error| error.message == message)
+                .map_or_else(SystemTime::now, |error| error.since);
+            self.errors.insert(
+                path,
+                ScanError {
+                    code: code.to_string(),
+                    message: message.to_string(),
+                    since,
+                },
+            );
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// `getErrors` response: `ERRORS <count>`, then one
+    /// `<unix time>\t<path>\t<message>\t<code>` line per file, sorted by path
+    ///
+    /// The code comes last so clients reading the first three fields keep
+    /// working.
+    #[must_use]
+    pub fn to_response(&self) -> String {
+        let mut response = format!("ERRORS {}\n", self.errors.len());
+        for (path, error) in &self.errors {
+            let since = error
+                .since
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            // Keep one line per file
+            let message = error.message.replace(['\n', '\r', '\t'], " ");
+            let _ = writeln!(
+                response,
+                "{since}\t{}\t{message}\t{}",
+                path.display(),
+                error.code
+            );
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::incremental::FileEntry;
+
+    #[test]
+    fn test_error_log_records_and_clears() {
+        let mut log = ErrorLog::default();
+        assert_eq!(log.to_response(), "ERRORS 0\n");
+
+        log.record(
+            Path::new("/src/B.php"),
+            code::IO,
+            "Could not read file:\nbad utf-8",
+        );
+        log.record(
+            Path::new("/src/A.php"),
+            code::PARSE,
+            "Error parsing PHP code",
+        );
+        let since = log.errors[Path::new("/src/A.php")].since;
+        log.record(
+            Path::new("/src/A.php"),
+            code::PARSE,
+            "Error parsing PHP code",
+        );
+        assert_eq!(log.errors[Path::new("/src/A.php")].since, since);
+
+        let response = log.to_response();
+        let lines: Vec<_> = response.lines().collect();
+        assert_eq!(lines[0], "ERRORS 2");
+        assert!(lines[1].ends_with("\t/src/A.php\tError parsing PHP code\tAX2003"));
+        assert!(lines[2].ends_with("\t/src/B.php\tCould not read file: bad utf-8\tAX3001"));
+
+        log.clear(Path::new("/src/A.php"));
+        assert_eq!(log.len(), 1);
+    }
+
+    #[test]
+    fn test_sync_keeps_timestamps_of_unchanged_errors() {
+        let mut log = ErrorLog::default();
+        log.record(Path::new("/src/A.php"), code::PARSE, "broken");
+        log.record(Path::new("/src/Fixed.php"), code::PARSE, "broken");
+        let since = log.errors[Path::new("/src/A.php")].since;
+
+        let mut manifest = Manifest::default();
+        for (path, error) in [("/src/A.php", Some("broken")), ("/src/Fixed.php", None)] {
+            manifest.files.insert(
+                path.to_string(),
+                FileEntry {
+                    parse_error: error.map(str::to_string),
+                    ..FileEntry::default()
+                },
+            );
+        }
+        log.sync(&manifest);
+
+        assert_eq!(log.len(), 1);
+        assert_eq!(log.errors[Path::new("/src/A.php")].since, since);
+    }
+}
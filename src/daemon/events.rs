@@ -0,0 +1,55 @@
+//! Stable event names for structured logs
+//!
+//! Every significant daemon action is logged with an `event` field naming it
+//! and fields describing it, so `--log-format json` output can feed
+//! dashboards without parsing messages. Names and fields only ever get added;
+//! the message text is free to change.
+//!
+//! | event            | fields                                                     |
+//! |------------------|------------------------------------------------------------|
+//! | `daemon_start`   | `pid`, `strategy`, `backend`                               |
+//! | `initial_scan`   | `classes`, `errors`, `duration_ms`                         |
+//! | `scan_batch`     | `files`, `classes`, `errors`, `duration_ms`, `debounce_ms` |
+//! | `full_rescan`    | `classes`, `errors`, `duration_ms`                         |
+//! | `rescan_path`    | `path`, `files`, `removed`, `classes`, `duration_ms`       |
+//! | `reload`         | `classes`, `duration_ms`                                   |
+//! | `scan_on_demand` | `mode`, `classes`, `duration_ms`                           |
+//! | `snapshot`       | `classes`, `bytes`                                         |
+//! | `restore`        | `classes`, `generation`                                    |
+//! | `cache_write`    | `classes`, `written`, `duration_ms`                        |
+//! | `watch_error`    | `error`                                                    |
+//! | `watch_limit`    | `max_user_watches`, `polling`                              |
+//! | `pause`          |                                                            |
+//! | `resume`         |                                                            |
+//! | `idle_exit`      | `idle_s`                                                   |
+//! | `shutdown`       |                                                            |
+//! | `daemon_stop`    | `uptime_s`                                                 |
+//! | `hook_run`       | `hook`                                                     |
+//! | `hook_failed`    | `hook`, `error`                                            |
+
+use std::time::Duration;
+
+pub const DAEMON_START: &str = "daemon_start";
+pub const INITIAL_SCAN: &str = "initial_scan";
+pub const SCAN_BATCH: &str = "scan_batch";
+pub const FULL_RESCAN: &str = "full_rescan";
+pub const RESCAN_PATH: &str = "rescan_path";
+pub const RELOAD: &str = "reload";
+pub const SCAN_ON_DEMAND: &str = "scan_on_demand";
+pub const SNAPSHOT: &str = "snapshot";
+pub const RESTORE: &str = "restore";
+pub const CACHE_WRITE: &str = "cache_write";
+pub const WATCH_ERROR: &str = "watch_error";
+pub const WATCH_LIMIT: &str = "watch_limit";
+pub const PAUSE: &str = "pause";
+pub const RESUME: &str = "resume";
+pub const IDLE_EXIT: &str = "idle_exit";
+pub const SHUTDOWN: &str = "shutdown";
+pub const DAEMON_STOP: &str = "daemon_stop";
+pub const HOOK_RUN: &str = "hook_run";
+pub const HOOK_FAILED: &str = "hook_failed";
+
+/// Whole milliseconds, as logged in `duration_ms` fields
+pub fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
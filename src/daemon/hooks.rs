@@ -0,0 +1,472 @@
+//! `on_update` hooks: notify other processes when the daemon flushes a new cache
+//!
+//! Each flush that changes the cache file is diffed against the previous
+//! flush by class fingerprint, and every hook receives the changed and
+//! removed FQCNs as JSON, with the file and reason of each removal: URL hooks
+//! as the body of a `POST`, exec hooks on stdin (with `AURYNX_CACHE_FILE` in
+//! the environment). Hooks run in order on a background thread so a slow
+//! endpoint or worker restart never stalls the watch loop.
+//!
+//! Only plain `http://` URLs are supported; use an exec hook (`curl`) for
+//! HTTPS endpoints.
+
+use crate::daemon::events;
+use crate::metadata::PhpClassMetadata;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{Sender, channel};
+use std::time::Duration;
+use tracing::{debug, warn};
+use xxhash_rust::xxh3::xxh3_64;
+
+/// One entry of the `on_update` config list
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateHook {
+    /// `http://` endpoint receiving the payload as a JSON `POST`
+    pub url: Option<String>,
+    /// Extra request headers for `url` (e.g. an auth token)
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Command and arguments, run with the payload on stdin
+    pub exec: Option<Vec<String>>,
+    /// Request or command timeout in milliseconds (default: 5000)
+    pub timeout_ms: Option<u64>,
+}
+
+/// Payload sent to every hook
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UpdateEvent {
+    pub cache: PathBuf,
+    /// Classes in the new cache
+    pub classes: usize,
+    /// Added or modified classes
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+    /// `removed` with the last file and the reason of each removal
+    pub removed_classes: Vec<RemovedClass>,
+    /// Attributes of the changed classes, before and after the change, and
+    /// of the removed classes, sorted
+    pub attributes: Vec<String>,
+}
+
+/// Why a class is missing from a flush
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemovalReason {
+    /// Its file no longer exists
+    FileDeleted,
+    /// Its file still exists but no longer declares it
+    ClassRemoved,
+    /// Its file matches `output_exclude`
+    Filtered,
+}
+
+impl RemovalReason {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::FileDeleted => "file_deleted",
+            Self::ClassRemoved => "class_removed",
+            Self::Filtered => "filtered",
+        }
+    }
+}
+
+/// A class present in the previous flush but not in the current one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RemovedClass {
+    pub fqcn: String,
+    /// File the class was last flushed from (as written to the cache)
+    pub file: PathBuf,
+    pub reason: RemovalReason,
+}
+
+impl UpdateHook {
+    /// Check the hook before the daemon starts
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first invalid field.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        match (&self.url, &self.exec) {
+            (Some(url), None) => parse_url(url).map(|_| ()),
+            (None, Some(command)) if command.is_empty() => {
+                Err("on_update 'exec' must not be empty".to_string())
+            },
+            (None, Some(_)) => Ok(()),
+            _ => Err("on_update hook must have exactly one of 'url' or 'exec'".to_string()),
+        }
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.unwrap_or(5000))
+    }
+
+    fn run(&self, payload: &[u8], cache: &Path) -> std::result::Result<(), String> {
+        match (&self.url, self.exec.as_deref()) {
+            (Some(url), _) => post(url, &self.headers, payload, self.timeout()),
+            (None, Some([program, args @ ..])) => {
+                exec(program, args, payload, cache, self.timeout())
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Classes of the last flushed cache, diffed on every flush
+#[derive(Debug, Default)]
+pub struct ChangeTracker {
+    /// Class fingerprints, kept only while hooks need `changed`
+    fingerprints: HashMap<String, u64>,
+    /// Attributes used by each class, kept along with the fingerprints
+    attributes: HashMap<String, Vec<String>>,
+    files: HashMap<String, PathBuf>,
+    /// Whether a flush was recorded yet
+    flushed: bool,
+}
+
+/// What one flush changed, sorted
+#[derive(Debug, Default)]
+pub struct FlushDiff {
+    /// Added or modified FQCNs (always empty without fingerprints)
+    pub changed: Vec<String>,
+    /// FQCNs absent from the previous flush (also in `changed` with fingerprints)
+    pub added: Vec<String>,
+    /// Removed FQCNs with the file they were last flushed from
+    pub removed: Vec<(String, PathBuf)>,
+    /// Attributes affected by `changed` and `removed` (see [`UpdateEvent`])
+    pub attributes: Vec<String>,
+    /// First flush, diffed against nothing
+    pub first: bool,
+}
+
+impl ChangeTracker {
+    /// Record `metadata` as flushed and diff it against the previous flush
+    pub fn update(&mut self, metadata: &[PhpClassMetadata], fingerprints: bool) -> FlushDiff {
+        let mut previous_fingerprints = std::mem::take(&mut self.fingerprints);
+        let mut previous_attributes = std::mem::take(&mut self.attributes);
+        let mut previous_files = std::mem::take(&mut self.files);
+        let mut changed = Vec::new();
+        let mut added = Vec::new();
+        let mut affected = BTreeSet::new();
+
+        for class in metadata {
+            if fingerprints {
+                let fingerprint = serde_json::to_vec(class).map_or(0, |json| xxh3_64(&json));
+                let attributes = class_attributes(class);
+                let previous = previous_attributes.remove(&class.fqcn);
+                if previous_fingerprints.remove(&class.fqcn) != Some(fingerprint) {
+                    changed.push(class.fqcn.clone());
+                    affected.extend(attributes.iter().cloned());
+                    affected.extend(previous.into_iter().flatten());
+                }
+                self.fingerprints.insert(class.fqcn.clone(), fingerprint);
+                self.attributes.insert(class.fqcn.clone(), attributes);
+            }
+            if previous_files.remove(&class.fqcn).is_none() {
+                added.push(class.fqcn.clone());
+            }
+            self.files.insert(class.fqcn.clone(), class.file.clone());
+        }
+        // What is left belongs to the removed classes
+        affected.extend(previous_attributes.into_values().flatten());
+
+        let mut removed: Vec<(String, PathBuf)> = previous_files.into_iter().collect();
+        changed.sort();
+        added.sort();
+        removed.sort();
+        FlushDiff {
+            changed,
+            added,
+            removed,
+            attributes: affected.into_iter().collect(),
+            first: !std::mem::replace(&mut self.flushed, true),
+        }
+    }
+}
+
+/// Attributes declared on `class` and its members, and inherited ones
+fn class_attributes(class: &PhpClassMetadata) -> Vec<String> {
+    let inherited = class.inherited_attributes.iter().flat_map(|map| map.keys());
+    let attributes: BTreeSet<&String> = class.attribute_names().chain(inherited).collect();
+    attributes.into_iter().cloned().collect()
+}
+
+/// Background worker running the configured hooks in order
+pub struct HookRunner {
+    tx: Sender<UpdateEvent>,
+}
+
+impl HookRunner {
+    /// Start the worker thread (`None` when no hooks are configured)
+    #[must_use]
+    pub fn start(hooks: Vec<UpdateHook>) -> Option<Self> {
+        if hooks.is_empty() {
+            return None;
+        }
+
+        let (tx, rx) = channel::<UpdateEvent>();
+        std::thread::spawn(move || {
+            for event in rx {
+                let payload = match serde_json::to_vec(&event) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        warn!(error = %e, "Failed to serialize update event");
+                        continue;
+                    },
+                };
+                for hook in &hooks {
+                    let target = hook
+                        .url
+                        .clone()
+                        .unwrap_or_else(|| hook.exec.as_deref().unwrap_or_default().join(" "));
+                    match hook.run(&payload, &event.cache) {
+                        Ok(()) => debug!(
+                            event = events::HOOK_RUN,
+                            hook = %target,
+                            "on_update hook done"
+                        ),
+                        Err(e) => warn!(
+                            event = events::HOOK_FAILED,
+                            hook = %target,
+                            error = %e,
+                            "on_update hook failed"
+                        ),
+                    }
+                }
+            }
+        });
+
+        Some(Self { tx })
+    }
+
+    /// Queue an event; hooks for earlier events finish first
+    pub fn notify(&self, event: UpdateEvent) {
+        if self.tx.send(event).is_err() {
+            warn!("on_update worker stopped, event dropped");
+        }
+    }
+}
+
+/// Split an `http://host[:port]/path` URL into host, port and path
+fn parse_url(url: &str) -> std::result::Result<(&str, u16, &str), String> {
+    let Some(rest) = url.strip_prefix("http://") else {
+        return Err(format!(
+            "on_update URL '{url}' must start with http:// (use an exec hook with curl for HTTPS)"
+        ));
+    };
+    let (authority, path) = rest.find('/').map_or((rest, "/"), |i| rest.split_at(i));
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse()
+                .map_err(|_| format!("Invalid port in on_update URL '{url}'"))?,
+        ),
+        None => (authority, 80),
+    };
+    if host.is_empty() {
+        return Err(format!("Missing host in on_update URL '{url}'"));
+    }
+    Ok((host, port, path))
+}
+
+fn post(
+    url: &str, headers: &BTreeMap<String, String>, payload: &[u8], timeout: Duration,
+) -> std::result::Result<(), String> {
+    let (host, port, path) = parse_url(url)?;
+    let address = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| e.to_string())?
+        .next()
+        .ok_or_else(|| format!("Cannot resolve {host}"))?;
+
+    let mut stream = TcpStream::connect_timeout(&address, timeout).map_err(|e| e.to_string())?;
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|()| stream.set_write_timeout(Some(timeout)))
+        .map_err(|e| e.to_string())?;
+
+    let mut request = format!(
+        "POST {path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        payload.len()
+    );
+    for (name, value) in headers {
+        let _ = write!(request, "{name}: {value}\r\n");
+    }
+    request.push_str("\r\n");
+    stream
+        .write_all(request.as_bytes())
+        .and_then(|()| stream.write_all(payload))
+        .map_err(|e| e.to_string())?;
+
+    let mut response = Vec::new();
+    stream
+        .read_to_end(&mut response)
+        .map_err(|e| e.to_string())?;
+    let status_line = String::from_utf8_lossy(&response)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    match status_line.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => Ok(()),
+        _ => Err(format!("Unexpected response: {status_line}")),
+    }
+}
+
+fn exec(
+    program: &str, args: &[String], payload: &[u8], cache: &Path, timeout: Duration,
+) -> std::result::Result<(), String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .env("AURYNX_CACHE_FILE", cache)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    // A command that ignores stdin closes the pipe early; that is not an error
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(payload);
+    }
+
+    let deadline = std::time::Instant::now() + timeout;
+    loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) if status.success() => return Ok(()),
+            Some(status) => return Err(format!("Command exited with {status}")),
+            None if std::time::Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("Command timed out after {}ms", timeout.as_millis()));
+            },
+            None => std::thread::sleep(Duration::from_millis(10)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::parser::tests::extract;
+    use std::net::TcpListener;
+
+    fn extract_all(files: &[(&str, &str)]) -> Vec<PhpClassMetadata> {
+        files
+            .iter()
+            .flat_map(|(file, code)| extract(file, code))
+            .collect()
+    }
+
+    #[test]
+    fn test_tracker_reports_changed_and_removed_classes() {
+        let a = ("/src/A.php", "<?php namespace App; class A {}");
+        let mut tracker = ChangeTracker::default();
+        let diff = tracker.update(
+            &extract_all(&[a, ("/src/B.php", "<?php namespace App; class B {}")]),
+            true,
+        );
+        assert_eq!(diff.changed, ["\\App\\A", "\\App\\B"]);
+        assert!(diff.removed.is_empty());
+        assert!(diff.first);
+
+        let diff = tracker.update(
+            &extract_all(&[a, ("/src/C.php", "<?php namespace App; class C {}")]),
+            true,
+        );
+        assert_eq!(diff.changed, ["\\App\\C"]);
+        assert_eq!(diff.added, ["\\App\\C"]);
+        assert!(!diff.first);
+        assert_eq!(
+            diff.removed,
+            [("\\App\\B".to_string(), PathBuf::from("/src/B.php"))]
+        );
+
+        // Any edit of the source file counts, even without metadata changes
+        let edited = ("/src/A.php", "<?php namespace App; class A { /* v2 */ }");
+        let diff = tracker.update(&extract_all(&[edited]), true);
+        assert_eq!(diff.changed, ["\\App\\A"]);
+        assert!(diff.added.is_empty());
+
+        // Removals are still tracked without fingerprints
+        let diff = tracker.update(&[], false);
+        assert!(diff.changed.is_empty());
+        assert_eq!(
+            diff.removed,
+            [("\\App\\A".to_string(), PathBuf::from("/src/A.php"))]
+        );
+    }
+
+    #[test]
+    fn test_tracker_reports_affected_attributes() {
+        let mut tracker = ChangeTracker::default();
+        let route = "<?php namespace App; class A { #[Route('/a')] public function a() {} }";
+        let listener = "<?php namespace App; #[AsListener] class B {}";
+        let entity = "<?php namespace App; #[Entity] class C {}";
+        tracker.update(
+            &extract_all(&[("/A.php", route), ("/B.php", listener), ("/C.php", entity)]),
+            true,
+        );
+
+        // A moved from Route to Get, B is unchanged, C was removed
+        let get = "<?php namespace App; class A { #[Get('/a')] public function a() {} }";
+        let diff = tracker.update(&extract_all(&[("/A.php", get), ("/B.php", listener)]), true);
+        assert_eq!(diff.changed, ["\\App\\A"]);
+        assert_eq!(diff.attributes, ["\\App\\Entity", "\\App\\Get", "\\App\\Route"]);
+    }
+
+    #[test]
+    fn test_url_hook_posts_payload() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !String::from_utf8_lossy(&request).contains("\"removed\"") {
+                let n = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let hook: UpdateHook = serde_json::from_value(serde_json::json!({
+            "url": format!("http://127.0.0.1:{port}/cache/bust"),
+            "headers": {"Authorization": "Bearer secret"},
+        }))
+        .unwrap();
+        assert!(hook.validate().is_ok());
+        hook.run(br#"{"changed":[],"removed":[]}"#, Path::new("cache.php"))
+            .unwrap();
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /cache/bust HTTP/1.1\r\n"));
+        assert!(request.contains("Authorization: Bearer secret\r\n"));
+        assert!(request.ends_with(r#"{"changed":[],"removed":[]}"#));
+    }
+
+    #[test]
+    fn test_hook_validation() {
+        let hook = |value| serde_json::from_value::<UpdateHook>(value).unwrap();
+
+        let https = hook(serde_json::json!({"url": "https://example.com/hook"}));
+        assert!(https.validate().unwrap_err().contains("http://"));
+
+        let both = hook(serde_json::json!({"url": "http://localhost", "exec": ["true"]}));
+        assert!(both.validate().unwrap_err().contains("exactly one"));
+
+        assert_eq!(
+            parse_url("http://localhost:8080").unwrap(),
+            ("localhost", 8080, "/")
+        );
+    }
+}
@@ -0,0 +1,118 @@
+#![allow(unsafe_code)]
+//! Minimal init for running the daemon as PID 1 (`--container`)
+//!
+//! PID 1 inherits every orphaned process of the container and has to reap
+//! them, and the kernel drops signals PID 1 installed no handler for, so a
+//! `docker stop` during startup would wait for the kill timeout. [`supervise`]
+//! forks: the child runs the daemon as an ordinary process, the parent
+//! forwards signals to it and reaps zombies until it exits. Reaping in a
+//! separate process keeps `waitpid(-1)` away from the daemon's own children
+//! (hook commands), whose exit status the daemon collects itself.
+
+use std::io;
+
+/// Which process continues after [`supervise`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Run the daemon
+    Daemon,
+    /// The daemon exited; exit with this code
+    Exited(i32),
+}
+
+/// Fork a reaping supervisor when running as PID 1
+///
+/// Returns [`Role::Daemon`] right away when this is not PID 1. Must be called
+/// before any thread is started.
+///
+/// # Errors
+///
+/// Returns an error if the signal mask cannot be changed or `fork` fails.
+pub fn supervise() -> io::Result<Role> {
+    if std::process::id() != 1 {
+        return Ok(Role::Daemon);
+    }
+
+    // Block everything so no signal is lost between fork and sigtimedwait;
+    // the child restores the previous mask
+    let (all, previous) = unsafe {
+        let mut all: libc::sigset_t = std::mem::zeroed();
+        let mut previous: libc::sigset_t = std::mem::zeroed();
+        libc::sigfillset(&raw mut all);
+        if libc::sigprocmask(libc::SIG_SETMASK, &raw const all, &raw mut previous) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        (all, previous)
+    };
+
+    match unsafe { libc::fork() } {
+        -1 => {
+            let error = io::Error::last_os_error();
+            unsafe {
+                libc::sigprocmask(libc::SIG_SETMASK, &raw const previous, std::ptr::null_mut())
+            };
+            Err(error)
+        },
+        0 => {
+            unsafe {
+                libc::sigprocmask(libc::SIG_SETMASK, &raw const previous, std::ptr::null_mut())
+            };
+            Ok(Role::Daemon)
+        },
+        child => Ok(Role::Exited(forward_and_reap(child, &all))),
+    }
+}
+
+/// Forward signals to `child` and reap every exited process until `child` exits
+fn forward_and_reap(child: libc::pid_t, signals: &libc::sigset_t) -> i32 {
+    let timeout = libc::timespec {
+        tv_sec: 1,
+        tv_nsec: 0,
+    };
+    loop {
+        let signal =
+            unsafe { libc::sigtimedwait(signals, std::ptr::null_mut(), &raw const timeout) };
+        if signal > 0 && signal != libc::SIGCHLD {
+            unsafe { libc::kill(child, signal) };
+        }
+
+        loop {
+            let mut status = 0;
+            let pid = unsafe { libc::waitpid(-1, &raw mut status, libc::WNOHANG) };
+            if pid <= 0 {
+                break;
+            }
+            if pid == child {
+                return exit_code(status);
+            }
+        }
+    }
+}
+
+/// Shell-style exit code of a wait status (128 + signal when killed)
+pub(crate) const fn exit_code(status: libc::c_int) -> i32 {
+    if libc::WIFEXITED(status) {
+        libc::WEXITSTATUS(status)
+    } else if libc::WIFSIGNALED(status) {
+        128 + libc::WTERMSIG(status)
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supervise_is_a_no_op_outside_pid_1() {
+        assert_eq!(supervise().ok(), Some(Role::Daemon));
+    }
+
+    #[test]
+    fn test_exit_code_of_wait_status() {
+        // Encoded as by the kernel: exit status in the high byte, signal in the low bits
+        assert_eq!(exit_code(3 << 8), 3);
+        assert_eq!(exit_code(libc::SIGTERM), 128 + libc::SIGTERM);
+    }
+}
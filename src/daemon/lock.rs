@@ -317,7 +317,7 @@ impl DaemonLock {
 
     /// Check if a process with given PID is running
     #[cfg(unix)]
-    fn is_process_running(pid: u32) -> bool {
+    pub(super) fn is_process_running(pid: u32) -> bool {
         // Use kill(pid, 0) - sends null signal to check process existence
         // 0 = success, -1 = error. If error is EPERM, process exists but we can't signal it.
         unsafe {
@@ -331,7 +331,7 @@ impl DaemonLock {
     }
 
     #[cfg(windows)]
-    fn is_process_running(pid: u32) -> bool {
+    pub(super) fn is_process_running(pid: u32) -> bool {
         use std::process::Command;
 
         Command::new("tasklist")
@@ -0,0 +1,93 @@
+//! Code requests with a per-request response format
+//!
+//! `getCode [php|json]` returns the whole cache and `getClass <fqcn> [php|json]`
+//! a single class. PHP is the default, so existing clients keep receiving the
+//! array they `eval`, while tooling on the same daemon can ask for JSON.
+
+/// Serialization of a code response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    /// `<?php ... return [...];` as in the cache file
+    Php,
+    /// One line of JSON (the class list, or one class object)
+    Json,
+}
+
+impl ResponseFormat {
+    fn parse(name: Option<&str>) -> Result<Self, String> {
+        match name {
+            None | Some("php") => Ok(Self::Php),
+            Some("json") => Ok(Self::Json),
+            Some(other) => Err(format!("Unknown format: {other} (expected php or json)")),
+        }
+    }
+}
+
+/// A request for cache contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeRequest<'a> {
+    /// The whole cache (`getCode`, `getCacheCode`, `getPhpCode`)
+    Cache(ResponseFormat),
+    /// One class by FQCN, without a leading backslash (`getClass`)
+    Class(&'a str, ResponseFormat),
+}
+
+impl<'a> CodeRequest<'a> {
+    /// Parse a request line; `None` if it is not a code request
+    pub fn parse(line: &'a str) -> Option<Result<Self, String>> {
+        let mut words = line.split_whitespace();
+        let request = match words.next()? {
+            "getCode" | "getCacheCode" | "getPhpCode" => {
+                ResponseFormat::parse(words.next()).map(Self::Cache)
+            },
+            "getClass" => words
+                .next()
+                .ok_or_else(|| "Usage: getClass <fqcn> [php|json]".to_string())
+                .and_then(|fqcn| {
+                    ResponseFormat::parse(words.next())
+                        .map(|format| Self::Class(fqcn.trim_start_matches('\\'), format))
+                }),
+            _ => return None,
+        };
+        if words.next().is_some() {
+            return Some(Err(format!("Too many arguments: {line}")));
+        }
+        Some(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_code_requests() {
+        assert_eq!(
+            CodeRequest::parse("getCacheCode"),
+            Some(Ok(CodeRequest::Cache(ResponseFormat::Php)))
+        );
+        assert_eq!(
+            CodeRequest::parse("getCode json"),
+            Some(Ok(CodeRequest::Cache(ResponseFormat::Json)))
+        );
+        assert_eq!(
+            CodeRequest::parse("getClass \\App\\User json"),
+            Some(Ok(CodeRequest::Class("App\\User", ResponseFormat::Json)))
+        );
+        assert_eq!(
+            CodeRequest::parse("getClass App\\User"),
+            Some(Ok(CodeRequest::Class("App\\User", ResponseFormat::Php)))
+        );
+        assert_eq!(CodeRequest::parse("stats"), None);
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_arguments() {
+        assert!(matches!(CodeRequest::parse("getClass"), Some(Err(_))));
+        assert!(matches!(CodeRequest::parse("getCode yaml"), Some(Err(_))));
+        assert!(matches!(
+            CodeRequest::parse("getClass App\\User json extra"),
+            Some(Err(_))
+        ));
+    }
+}
@@ -0,0 +1,89 @@
+#![allow(unsafe_code)]
+
+//! Zero-copy delivery of the cache file over the IPC socket
+//!
+//! On Linux the kernel copies the file straight into the socket with
+//! `sendfile(2)`. Other platforms, and filesystems that do not support it,
+//! fall back to a buffered copy, which still avoids regenerating the PHP code.
+
+use std::fs::File;
+use std::io;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+/// Write the whole file at `path` to `out`
+pub fn send_file(out: &mut UnixStream, path: &Path) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        use std::os::fd::AsRawFd;
+
+        let mut sent = 0u64;
+        while sent < len {
+            let chunk = usize::try_from(len - sent).unwrap_or(usize::MAX);
+            // SAFETY: both descriptors stay open for the duration of the call;
+            // a null offset makes the kernel advance the file position, so the
+            // fallback below resumes where sendfile stopped.
+            let n = unsafe {
+                libc::sendfile(
+                    out.as_raw_fd(),
+                    file.as_raw_fd(),
+                    std::ptr::null_mut(),
+                    chunk,
+                )
+            };
+            match u64::try_from(n) {
+                Ok(0) => return Ok(sent), // File shrank while sending
+                Ok(n) => sent += n,
+                Err(_) => {
+                    let err = io::Error::last_os_error();
+                    match err.raw_os_error() {
+                        Some(libc::EINTR | libc::EAGAIN) => {},
+                        Some(libc::EINVAL | libc::ENOSYS) => {
+                            return Ok(sent + io::copy(&mut file, out)?);
+                        },
+                        _ => return Err(err),
+                    }
+                },
+            }
+        }
+        Ok(sent)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = len;
+        io::copy(&mut file, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use std::io::Read;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_send_file_delivers_whole_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("cache.php");
+        let content = format!("<?php return [{}];", "'x'=>1,".repeat(20_000));
+        std::fs::write(&path, &content).unwrap();
+
+        let (mut tx, mut rx) = UnixStream::pair().unwrap();
+        let reader = std::thread::spawn(move || {
+            let mut received = String::new();
+            rx.read_to_string(&mut received).unwrap();
+            received
+        });
+
+        let sent = send_file(&mut tx, &path).unwrap();
+        drop(tx);
+
+        assert_eq!(sent, content.len() as u64);
+        assert_eq!(reader.join().unwrap(), content);
+    }
+}
@@ -0,0 +1,241 @@
+//! Shared-memory cache delivery for PHP FFI
+//!
+//! With `shared_memory` set, every published snapshot is also written to a
+//! POSIX shared-memory segment. PHP maps it with `shm_open()` + `mmap()` via
+//! FFI after asking the `getShm` IPC command for the current segment, so a
+//! request reads the cache without any copy through the socket.
+//!
+//! Each generation gets its own segment (`<name>.<generation>`): a segment is
+//! never modified once announced. The previous segment stays for one more
+//! generation, so a reader that asked `getShm` just before a switch can still
+//! open it, and is unlinked on the switch after that. Unlinking does not
+//! invalidate existing mappings, so a reader keeps a consistent view until it
+//! unmaps. A reader that is slower than two switches gets `ENOENT` from
+//! `shm_open()` and asks `getShm` again.
+//!
+//! Segments of the configured name left behind by a daemon that did not exit
+//! cleanly are removed when the next one starts.
+//!
+//! POSIX shared memory on Linux lives in `/dev/shm`, so the segments are
+//! plain files there; other platforms are not supported.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Directory backing `shm_open()` names
+#[cfg(target_os = "linux")]
+const SHM_DIR: &str = "/dev/shm";
+
+/// Segment announced to IPC readers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// Name for `shm_open()` (leading slash included)
+    pub name: String,
+    /// Size of the cache in bytes
+    pub size: usize,
+    /// Cache generation stored in the segment
+    pub generation: u64,
+}
+
+/// Writer of the shared-memory segments of one daemon
+#[derive(Debug)]
+pub struct SharedMemory {
+    dir: PathBuf,
+    /// Configured base name, without the leading slash
+    base: String,
+    current: Option<Segment>,
+    /// Segment announced before `current`, kept for readers that are about
+    /// to open it
+    previous: Option<Segment>,
+}
+
+impl SharedMemory {
+    /// Segments named `/<name>.<generation>` in the system shared-memory directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on platforms without `/dev/shm`.
+    pub fn new(name: &str) -> io::Result<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            Self::in_dir(Path::new(SHM_DIR), name)
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = name;
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "shared memory delivery requires Linux (/dev/shm)",
+            ))
+        }
+    }
+
+    /// Segments stored as files in `dir`
+    ///
+    /// Removes the segments and partial writes of `name` that a previous
+    /// daemon left in `dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `name` is not a single path component.
+    pub fn in_dir(dir: &Path, name: &str) -> io::Result<Self> {
+        let base = name.strip_prefix('/').unwrap_or(name);
+        if base.is_empty() || base.contains('/') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid shared memory name '{name}' (expected /name)"),
+            ));
+        }
+
+        let shm = Self {
+            dir: dir.to_path_buf(),
+            base: base.to_string(),
+            current: None,
+            previous: None,
+        };
+        shm.remove_stale();
+        Ok(shm)
+    }
+
+    /// Remove `<base>.<generation>` segments and `.<base>.*` temp files
+    fn remove_stale(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let segment_prefix = format!("{}.", self.base);
+        let temp_prefix = format!(".{}.", self.base);
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let Some(file_name) = file_name.to_str() else {
+                continue;
+            };
+            let stale = file_name.strip_prefix(&segment_prefix).is_some_and(|generation| {
+                !generation.is_empty() && generation.bytes().all(|b| b.is_ascii_digit())
+            }) || file_name.starts_with(&temp_prefix);
+            if stale {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    /// Write `data` as the segment of `generation`, unlinking the one before
+    /// the previous segment
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the segment cannot be written; the previously
+    /// announced segment stays current.
+    pub fn publish(&mut self, data: &[u8], generation: u64) -> io::Result<()> {
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|segment| segment.generation == generation)
+        {
+            return Ok(());
+        }
+
+        let file_name = format!("{}.{generation}", self.base);
+        let path = self.dir.join(&file_name);
+
+        // Readers only learn the name after the rename, so they never see a partial write
+        let mut temp = tempfile::Builder::new()
+            .prefix(&format!(".{}.", self.base))
+            .tempfile_in(&self.dir)?;
+        temp.write_all(data)?;
+        // PHP-FPM workers usually run as another user than the daemon
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            temp.as_file()
+                .set_permissions(fs::Permissions::from_mode(0o644))?;
+        }
+        temp.persist(&path).map_err(|e| e.error)?;
+
+        let previous = self.current.replace(Segment {
+            name: format!("/{file_name}"),
+            size: data.len(),
+            generation,
+        });
+        if let Some(retired) = std::mem::replace(&mut self.previous, previous) {
+            let _ = fs::remove_file(self.path_of(&retired));
+        }
+
+        Ok(())
+    }
+
+    /// Segment currently announced by `getShm`
+    #[must_use]
+    pub const fn current(&self) -> Option<&Segment> {
+        self.current.as_ref()
+    }
+
+    fn path_of(&self, segment: &Segment) -> PathBuf {
+        self.dir.join(segment.name.trim_start_matches('/'))
+    }
+}
+
+impl Drop for SharedMemory {
+    fn drop(&mut self) {
+        for segment in [self.current.take(), self.previous.take()].into_iter().flatten() {
+            let _ = fs::remove_file(self.path_of(&segment));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_publish_replaces_segment_per_generation() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut shm = SharedMemory::in_dir(dir.path(), "/aurynx-cache").unwrap();
+
+        shm.publish(b"<?php return [];", 1).unwrap();
+        let first = shm.current().unwrap().clone();
+        assert_eq!(first.name, "/aurynx-cache.1");
+        assert_eq!(first.size, 16);
+        assert_eq!(
+            fs::read(dir.path().join("aurynx-cache.1")).unwrap(),
+            b"<?php return [];"
+        );
+
+        shm.publish(b"<?php return ['A' => []];", 2).unwrap();
+        assert_eq!(shm.current().unwrap().name, "/aurynx-cache.2");
+        // One generation of grace for readers that just asked `getShm`
+        assert!(dir.path().join("aurynx-cache.1").exists());
+
+        shm.publish(b"<?php return [];", 3).unwrap();
+        assert!(!dir.path().join("aurynx-cache.1").exists());
+        assert!(dir.path().join("aurynx-cache.2").exists());
+
+        drop(shm);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_removes_segments_left_by_a_crashed_daemon() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in ["aurynx-cache.7", ".aurynx-cache.tmp1", "aurynx-cache.x", "other.3"] {
+            fs::write(dir.path().join(file), b"").unwrap();
+        }
+
+        let _shm = SharedMemory::in_dir(dir.path(), "/aurynx-cache").unwrap();
+        let mut left: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["aurynx-cache.x", "other.3"]);
+    }
+
+    #[test]
+    fn test_rejects_nested_names() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(SharedMemory::in_dir(dir.path(), "/a/b").is_err());
+        assert!(SharedMemory::in_dir(dir.path(), "/").is_err());
+    }
+}
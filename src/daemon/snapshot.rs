@@ -0,0 +1,98 @@
+//! Immutable read snapshots of the daemon cache
+//!
+//! After every batch the daemon publishes a [`CacheSnapshot`] with its
+//! counters. The classes and the PHP and JSON caches are rendered by the
+//! first request that needs them and then shared by every later reader of
+//! the same generation, so batches nobody reads cost no rendering. IPC
+//! readers only clone the current `Arc`, and a reader keeps a consistent view
+//! even while a newer snapshot is published.
+
+use crate::metadata::PhpClassMetadata;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+/// Atomically replaceable `Arc<T>`
+///
+/// The lock is only held to clone or swap the pointer, never while a reader
+/// uses the value.
+#[derive(Debug)]
+pub struct SnapshotCell<T> {
+    current: RwLock<Arc<T>>,
+}
+
+impl<T> SnapshotCell<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(value)),
+        }
+    }
+
+    /// Current snapshot
+    pub fn load(&self) -> Arc<T> {
+        Arc::clone(&self.current.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Publish a new snapshot; readers holding the old one are unaffected
+    pub fn store(&self, value: T) {
+        let value = Arc::new(value);
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) = value;
+    }
+}
+
+/// What IPC readers see of the cache at one generation
+#[derive(Debug)]
+pub struct CacheSnapshot {
+    /// Cache generation the snapshot was taken at
+    pub generation: u64,
+    /// Classes in the cache
+    pub total: usize,
+    /// Classes spilled to disk (bounded memory mode)
+    pub spilled: usize,
+    /// Estimated memory of the classes kept in the store, in bytes
+    pub memory_bytes: u64,
+    /// Classes as rendered, sorted by FQCN, or the error that prevented
+    /// reading them (never filled in bounded memory mode, where they are read
+    /// from the store on demand instead of kept in memory)
+    pub classes: OnceLock<Result<Arc<Vec<PhpClassMetadata>>, String>>,
+    /// PHP cache, rendered on the first `getCode`, or the error that
+    /// prevented rendering it
+    pub php_code: OnceLock<Result<Vec<u8>, String>>,
+    /// JSON cache, rendered on the first `getCode json`, or the IPC error line
+    /// answering it
+    pub json_code: OnceLock<Result<Vec<u8>, String>>,
+}
+
+impl Default for CacheSnapshot {
+    fn default() -> Self {
+        Self {
+            generation: 0,
+            total: 0,
+            spilled: 0,
+            memory_bytes: 0,
+            classes: OnceLock::new(),
+            php_code: OnceLock::new(),
+            json_code: OnceLock::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readers_keep_their_snapshot_across_store() {
+        let cell = SnapshotCell::new(CacheSnapshot::default());
+        let before = cell.load();
+
+        cell.store(CacheSnapshot {
+            generation: 1,
+            total: 3,
+            ..CacheSnapshot::default()
+        });
+
+        assert_eq!(before.generation, 0);
+        assert_eq!(before.total, 0);
+        assert_eq!(cell.load().generation, 1);
+        assert_eq!(cell.load().total, 3);
+    }
+}
@@ -0,0 +1,173 @@
+//! Socket and PID files left behind by a daemon that died
+//!
+//! A daemon killed by a crash, `SIGKILL` or the OOM killer cannot clean up its
+//! socket and PID file. The OS releases its lock, so the next daemon for the
+//! same cache starts normally, but the leftover files must not simply be
+//! deleted: another daemon, watching a different cache, may be configured with
+//! the same paths and still be using them. Before starting, the daemon
+//! therefore checks the owner. A socket that refuses connections and a PID
+//! file naming a process that is gone are stale and removed, so the new daemon
+//! can create its own; a live owner is reported as an error instead, without
+//! needing `--force` in the common crash case.
+
+use super::lock::DaemonLock;
+use crate::error::{AurynxError, Result};
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use tracing::info;
+
+/// Remove a PID file whose process is gone; fail if it names a live daemon
+///
+/// A daemon that has not bound its socket yet (it does so after the initial
+/// scan) is only visible through its PID file. Unreadable PID files are stale.
+/// Returns whether a stale file was removed.
+///
+/// # Errors
+///
+/// Returns [`AurynxError::DaemonAlreadyRunning`] if another live daemon wrote
+/// the file, or an IO error if it cannot be removed.
+pub fn adopt_pid_file(pid_file: &Path, socket_path: &Path) -> Result<bool> {
+    let Ok(content) = fs::read_to_string(pid_file) else {
+        return Ok(false);
+    };
+    let pid = content.trim().parse::<u32>().ok();
+    if let Some(pid) = pid
+        && pid != std::process::id()
+        && DaemonLock::is_process_running(pid)
+        && is_daemon_process(pid)
+    {
+        return Err(AurynxError::daemon_running_error(
+            pid,
+            socket_path.to_path_buf(),
+        ));
+    }
+
+    remove(pid_file, "PID file")?;
+    info!(
+        path = ?pid_file,
+        pid = content.trim(),
+        "Removed stale PID file of a daemon that is no longer running"
+    );
+    Ok(true)
+}
+
+/// Remove a socket nobody accepts connections on; fail if it is in use
+///
+/// Returns whether a stale socket was removed.
+///
+/// # Errors
+///
+/// Returns an error if a process still accepts connections on the socket, if
+/// the path holds something other than a socket (which is never deleted), or
+/// if the socket cannot be removed.
+pub fn adopt_socket(socket_path: &Path) -> Result<bool> {
+    let file_type = match fs::symlink_metadata(socket_path) {
+        Ok(metadata) => metadata.file_type(),
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(e) => {
+            return Err(AurynxError::io_error(
+                format!("Failed to inspect socket {}", socket_path.display()),
+                e,
+            ));
+        },
+    };
+    if !file_type.is_socket() {
+        return Err(AurynxError::config_error(format!(
+            "Socket path {} exists and is not a socket",
+            socket_path.display()
+        )));
+    }
+
+    match UnixStream::connect(socket_path) {
+        Ok(_) => Err(AurynxError::other(format!(
+            "Socket {} is in use by another process",
+            socket_path.display()
+        ))),
+        Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+            remove(socket_path, "socket")?;
+            info!(
+                path = ?socket_path,
+                "Removed stale socket of a daemon that is no longer running"
+            );
+            Ok(true)
+        },
+        Err(e) => Err(AurynxError::io_error(
+            format!("Failed to check socket {}", socket_path.display()),
+            e,
+        )),
+    }
+}
+
+fn remove(path: &Path, what: &str) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(AurynxError::io_error(
+            format!("Failed to remove stale {what} {}", path.display()),
+            e,
+        )),
+    }
+}
+
+/// Whether a running process is a daemon rather than a recycled PID
+///
+/// Only Linux can tell; elsewhere every running process counts.
+fn is_daemon_process(pid: u32) -> bool {
+    #[cfg(target_os = "linux")]
+    if let Ok(cmdline) = fs::read(format!("/proc/{pid}/cmdline")) {
+        return cmdline
+            .split(|&byte| byte == 0)
+            .any(|arg| arg == b"discovery:scan");
+    }
+    let _ = pid;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::error::code;
+    use std::os::unix::net::UnixListener;
+    use tempfile::TempDir;
+
+    /// Above Linux's and macOS's maximum PID
+    const DEAD_PID: u32 = 99_999_999;
+
+    #[test]
+    fn test_adopts_socket_and_pid_file_of_dead_daemon() {
+        let temp_dir = TempDir::new().unwrap();
+        let socket = temp_dir.path().join("daemon.sock");
+        let pid_file = temp_dir.path().join("daemon.pid");
+        drop(UnixListener::bind(&socket).unwrap());
+        fs::write(&pid_file, DEAD_PID.to_string()).unwrap();
+
+        assert!(adopt_pid_file(&pid_file, &socket).unwrap());
+        assert!(adopt_socket(&socket).unwrap());
+        assert!(!socket.exists() && !pid_file.exists());
+
+        // Nothing left to adopt
+        assert!(!adopt_pid_file(&pid_file, &socket).unwrap());
+        assert!(!adopt_socket(&socket).unwrap());
+    }
+
+    #[test]
+    fn test_keeps_live_socket_and_other_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let socket = temp_dir.path().join("daemon.sock");
+        let _listener = UnixListener::bind(&socket).unwrap();
+        assert_eq!(adopt_socket(&socket).unwrap_err().code(), code::OTHER);
+        assert!(socket.exists());
+
+        let not_a_socket = temp_dir.path().join("notes.txt");
+        fs::write(&not_a_socket, "keep me").unwrap();
+        assert_eq!(
+            adopt_socket(&not_a_socket).unwrap_err().code(),
+            code::CONFIG
+        );
+        assert!(not_a_socket.exists());
+    }
+}
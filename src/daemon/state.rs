@@ -0,0 +1,189 @@
+//! Daemon state snapshots (`discovery:snapshot`, `discovery:restore`)
+//!
+//! When a daemon misbehaves, its cache file only shows the last flush, and
+//! with the memory strategy there is no file at all. The `snapshot <file>`
+//! IPC command makes the daemon write what it holds in memory (every class,
+//! the manifest, the cache generation and whether it is paused) to a file,
+//! encrypted like manifests when `encryption` is enabled. `restore <file>`
+//! loads such a file into a daemon, e.g. a `--read-only` one on a support
+//! machine, which then serves exactly the captured classes.
+//!
+//! Paths are resolved by the daemon, so the CLI sends absolute ones. The
+//! daemon may run as another user than its clients, so it only reads and
+//! writes snapshots inside the directory of its cache file.
+
+use crate::incremental::Manifest;
+use crate::metadata::PhpClassMetadata;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Everything a snapshot file holds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonState {
+    /// Version of the daemon that took the snapshot
+    pub version: String,
+    /// Unix time the snapshot was taken, in seconds
+    pub taken_at: u64,
+    /// Cache generation at that time
+    pub generation: u64,
+    /// Whether watching was paused
+    pub paused: bool,
+    /// Watched paths
+    pub paths: Vec<PathBuf>,
+    /// Classes in cache order
+    pub classes: Vec<PhpClassMetadata>,
+    pub manifest: Manifest,
+}
+
+impl DaemonState {
+    /// Write the state to `path` (atomically); returns the file size
+    pub(crate) fn save(&self, path: &Path) -> Result<usize> {
+        let content = crate::encryption::seal(&serde_json::to_vec(self)?)?;
+        crate::writer::write_if_changed(path, &content)
+            .with_context(|| format!("Failed to write snapshot {}", path.display()))?;
+        Ok(content.len())
+    }
+
+    /// Read a snapshot file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or decrypted, or is not a
+    /// snapshot.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read(path)
+            .with_context(|| format!("Failed to read snapshot {}", path.display()))?;
+        let content = crate::encryption::open(content)
+            .with_context(|| format!("Failed to read snapshot {}", path.display()))?;
+        serde_json::from_slice(&content)
+            .with_context(|| format!("{} is not a daemon snapshot", path.display()))
+    }
+}
+
+/// Resolve the snapshot file a client named, which must lie inside `dir`
+///
+/// Symlinks are resolved before the check, in the directories and in the
+/// file itself; the file does not have to exist yet.
+///
+/// # Errors
+///
+/// Returns an error if `file` or `dir` cannot be resolved or `file` is
+/// outside `dir`.
+pub(crate) fn confine(file: &Path, dir: &Path) -> Result<PathBuf> {
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let dir = dir
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", dir.display()))?;
+    let name = file
+        .file_name()
+        .with_context(|| format!("Not a file name: {}", file.display()))?;
+    let parent = file.parent().unwrap_or_else(|| Path::new("/"));
+    let resolved = parent
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", parent.display()))?
+        .join(name);
+    let resolved = resolved.canonicalize().unwrap_or(resolved);
+    if !resolved.starts_with(&dir) {
+        bail!(
+            "Snapshot {} is outside the cache directory {}",
+            file.display(),
+            dir.display()
+        );
+    }
+    Ok(resolved)
+}
+
+/// Ask the daemon behind `socket` to write its state to `out`; returns its
+/// response line
+///
+/// # Errors
+///
+/// Returns an error if the daemon cannot be reached or fails to write the file.
+#[cfg(unix)]
+pub fn request_snapshot(socket: &Path, out: &Path) -> Result<String> {
+    send(socket, "snapshot", out, "SNAPSHOT")
+}
+
+/// Ask the daemon behind `socket` to load the snapshot `file`; returns its
+/// response line
+///
+/// # Errors
+///
+/// Returns an error if the daemon cannot be reached or rejects the file.
+#[cfg(unix)]
+pub fn request_restore(socket: &Path, file: &Path) -> Result<String> {
+    send(socket, "restore", file, "RESTORED")
+}
+
+#[cfg(unix)]
+fn send(socket: &Path, command: &str, file: &Path, expected: &str) -> Result<String> {
+    let file = std::path::absolute(file)
+        .with_context(|| format!("Failed to resolve {}", file.display()))?;
+    let command = format!("{command} {}", file.display());
+    let response = crate::warm::request(socket, &command)?;
+    let response = String::from_utf8_lossy(&response).trim_end().to_string();
+    if !response.starts_with(expected) {
+        bail!("Daemon answered {command} with {response}");
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::parser::PhpMetadataExtractor;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_state_round_trips_through_a_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("snap.bin");
+        let state = DaemonState {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            taken_at: 1_792_137_740,
+            generation: 7,
+            paused: true,
+            paths: vec![PathBuf::from("/app/src")],
+            classes: PhpMetadataExtractor::new()
+                .unwrap()
+                .extract_metadata("<?php namespace App; class User {}", "User.php".into())
+                .unwrap(),
+            manifest: Manifest::default(),
+        };
+
+        state.save(&path).unwrap();
+        let loaded = DaemonState::load(&path).unwrap();
+
+        assert_eq!(loaded.generation, 7);
+        assert!(loaded.paused);
+        assert_eq!(loaded.classes, state.classes);
+
+        std::fs::write(&path, "{}").unwrap();
+        assert!(DaemonState::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_snapshots_are_confined_to_the_cache_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = temp_dir.path().join("var/cache");
+        std::fs::create_dir_all(&cache).unwrap();
+        let cache = cache.canonicalize().unwrap();
+
+        assert_eq!(
+            confine(&cache.join("new.snap"), &cache).unwrap(),
+            cache.join("new.snap")
+        );
+        assert!(confine(&temp_dir.path().join("var/x.snap"), &cache).is_err());
+        assert!(confine(&cache.join("../x.snap"), &cache).is_err());
+        assert!(confine(&cache.join(".."), &cache).is_err());
+        assert!(confine(Path::new("/etc/passwd"), &cache).is_err());
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("/etc/passwd", cache.join("link.snap")).unwrap();
+            assert!(confine(&cache.join("link.snap"), &cache).is_err());
+        }
+    }
+}
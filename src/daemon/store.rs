@@ -0,0 +1,602 @@
+//! Daemon class store with optional disk spilling (bounded memory mode)
+//!
+//! By default every class lives in memory. With `max_memory_entries` set, only
+//! the most recently written entries stay resident; older ones are appended
+//! to a spill file as JSON lines and indexed by offset. Files that keep
+//! changing therefore stay hot, while the bulk of a large codebase, which is
+//! only needed when the cache is rendered, lives on disk.
+//!
+//! Replaced and removed records leave garbage in the spill file, which is
+//! compacted once it outweighs the live records.
+//!
+//! The store keeps an estimate of the memory its hot entries use, and can shed
+//! entries to disk on demand when the daemon exceeds `max_memory_mb`.
+
+use crate::metadata::PhpClassMetadata;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Default spill file name (next to the cache output)
+pub const SPILL_FILE: &str = "aurynx.spill";
+
+/// Garbage below this size never triggers a compaction
+const MIN_COMPACT_BYTES: u64 = 1024 * 1024;
+
+/// Location of a spilled record in the spill file
+#[derive(Debug, Clone)]
+struct Spilled {
+    /// Source file of the class (to drop entries without reading them back)
+    file: PathBuf,
+    offset: u64,
+    len: u64,
+}
+
+/// Spill file rewritten by a compaction, not yet in use
+struct Compacted {
+    file: File,
+    /// New offset of every live record
+    offsets: Vec<(String, u64)>,
+    end: u64,
+}
+
+/// Spill file and index of the records evicted to it
+#[derive(Debug)]
+struct Spill {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    /// End of the file (offset of the next record)
+    end: u64,
+    /// Bytes of records that were replaced or removed
+    garbage: u64,
+    index: HashMap<String, Spilled>,
+}
+
+/// Class map keyed by FQCN, optionally spilling cold entries to disk
+#[derive(Debug)]
+pub struct ClassStore {
+    /// Hot entries with their write tick and estimated size
+    hot: HashMap<String, (PhpClassMetadata, u64, u64)>,
+    /// Estimated size of all hot entries, in bytes
+    hot_bytes: u64,
+    /// Write tick → FQCN of hot entries, oldest first
+    order: BTreeMap<u64, String>,
+    /// Source file → FQCNs of every entry, spilled ones included
+    by_file: HashMap<PathBuf, Vec<String>>,
+    tick: u64,
+    /// Hot entry limit (`None` keeps everything in memory)
+    capacity: Option<usize>,
+    spill: Option<Spill>,
+    /// Entries moved to the spill file so far
+    evictions: u64,
+}
+
+impl ClassStore {
+    /// Store keeping every entry in memory
+    #[must_use]
+    pub fn in_memory() -> Self {
+        Self {
+            hot: HashMap::new(),
+            hot_bytes: 0,
+            order: BTreeMap::new(),
+            by_file: HashMap::new(),
+            tick: 0,
+            capacity: None,
+            spill: None,
+            evictions: 0,
+        }
+    }
+
+    /// Store keeping at most `capacity` entries in memory, spilling to `path`
+    ///
+    /// Any existing file at `path` is truncated; it is removed on drop.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the spill file cannot be created.
+    pub fn spilling(capacity: usize, path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .truncate(true)
+            .open(path)?;
+
+        Ok(Self {
+            capacity: Some(capacity.max(1)),
+            spill: Some(Spill {
+                path: path.to_path_buf(),
+                writer: BufWriter::new(file),
+                end: 0,
+                garbage: 0,
+                index: HashMap::new(),
+            }),
+            ..Self::in_memory()
+        })
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.hot.len() + self.spill.as_ref().map_or(0, |spill| spill.index.len())
+    }
+
+    /// Entries currently on disk
+    #[must_use]
+    pub fn spilled_len(&self) -> usize {
+        self.spill.as_ref().map_or(0, |spill| spill.index.len())
+    }
+
+    /// Entries moved to disk since the store was created
+    #[must_use]
+    pub const fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// Estimated memory used by the entries kept in memory, in bytes
+    #[must_use]
+    pub const fn memory_bytes(&self) -> u64 {
+        self.hot_bytes
+    }
+
+    /// Source file of every entry, spilled ones included
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        let spilled = self
+            .spill
+            .iter()
+            .flat_map(|spill| spill.index.values().map(|entry| entry.file.as_path()));
+        self.hot
+            .values()
+            .map(|(metadata, ..)| metadata.file.as_path())
+            .chain(spilled)
+    }
+
+    /// Insert or replace the class `fqcn`; it becomes the hottest entry
+    pub fn insert(&mut self, fqcn: String, metadata: PhpClassMetadata) {
+        let spilled = self.spill.as_mut().and_then(|spill| spill.forget(&fqcn));
+        let previous = match self.hot.get(&fqcn) {
+            Some((old, tick, bytes)) => {
+                self.order.remove(tick);
+                self.hot_bytes -= bytes;
+                Some(old.file.clone())
+            },
+            None => spilled,
+        };
+        if previous.as_deref() != Some(metadata.file.as_path()) {
+            if let Some(previous) = previous {
+                self.unindex(&previous, &fqcn);
+            }
+            self.by_file
+                .entry(metadata.file.clone())
+                .or_default()
+                .push(fqcn.clone());
+        }
+
+        self.tick += 1;
+        let bytes = estimated_size(&metadata);
+        self.hot_bytes += bytes;
+        self.order.insert(self.tick, fqcn.clone());
+        self.hot.insert(fqcn, (metadata, self.tick, bytes));
+
+        self.evict();
+    }
+
+    /// Drop every class declared in `file`
+    pub fn remove_file(&mut self, file: &Path) {
+        let Some(classes) = self.by_file.remove(file) else {
+            return;
+        };
+        for fqcn in classes {
+            if let Some((_, tick, bytes)) = self.hot.remove(&fqcn) {
+                self.order.remove(&tick);
+                self.hot_bytes -= bytes;
+            }
+            if let Some(spill) = &mut self.spill {
+                spill.forget(&fqcn);
+            }
+        }
+
+        if let Some(spill) = &mut self.spill {
+            spill.compact_if_needed();
+        }
+    }
+
+    /// Drop `fqcn` from the classes of `file`
+    fn unindex(&mut self, file: &Path, fqcn: &str) {
+        if let Some(classes) = self.by_file.get_mut(file) {
+            classes.retain(|name| name != fqcn);
+            if classes.is_empty() {
+                self.by_file.remove(file);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.hot.clear();
+        self.hot_bytes = 0;
+        self.order.clear();
+        self.by_file.clear();
+        if let Some(spill) = &mut self.spill
+            && let Err(e) = spill.reset()
+        {
+            warn!("Failed to truncate spill file {:?}: {}", spill.path, e);
+        }
+    }
+
+    /// Copy of every entry, reading spilled ones back from disk
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a spilled record cannot be read or decoded.
+    pub fn to_vec(&self) -> io::Result<Vec<PhpClassMetadata>> {
+        let mut all: Vec<PhpClassMetadata> = self
+            .hot
+            .values()
+            .map(|(metadata, ..)| metadata.clone())
+            .collect();
+
+        if let Some(spill) = &self.spill {
+            let mut file = File::open(&spill.path)?;
+            let mut buffer = Vec::new();
+            for entry in spill.index.values() {
+                all.push(read_record(&mut file, entry, &mut buffer)?);
+            }
+        }
+
+        Ok(all)
+    }
+
+    /// Copy of the class `fqcn`, read back from disk if it was spilled
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if its spilled record cannot be read or decoded.
+    pub fn get(&self, fqcn: &str) -> io::Result<Option<PhpClassMetadata>> {
+        if let Some((metadata, ..)) = self.hot.get(fqcn) {
+            return Ok(Some(metadata.clone()));
+        }
+        let Some(spill) = &self.spill else {
+            return Ok(None);
+        };
+        let Some(entry) = spill.index.get(fqcn) else {
+            return Ok(None);
+        };
+        let mut file = File::open(&spill.path)?;
+        read_record(&mut file, entry, &mut Vec::new()).map(Some)
+    }
+
+    /// Spill the coldest entries until about `bytes` of memory are freed
+    ///
+    /// The hot set then keeps its reduced size, so later writes spill instead
+    /// of growing it again. Returns the number of entries spilled; without a
+    /// spill file nothing can be shed.
+    pub fn shed(&mut self, bytes: u64) -> usize {
+        if self.spill.is_none() {
+            return 0;
+        }
+
+        let mut freed = 0;
+        let mut spilled = 0;
+        while freed < bytes {
+            let Some(size) = self.spill_coldest() else {
+                break;
+            };
+            freed += size;
+            spilled += 1;
+        }
+        self.capacity = Some(self.hot.len().max(1));
+        self.flush_spill();
+        spilled
+    }
+
+    /// Move the coldest entries to disk until the hot set fits its capacity
+    fn evict(&mut self) {
+        let (Some(capacity), Some(_)) = (self.capacity, &self.spill) else {
+            return;
+        };
+
+        while self.hot.len() > capacity {
+            if self.spill_coldest().is_none() {
+                break;
+            }
+        }
+        self.flush_spill();
+    }
+
+    /// Move the coldest hot entry to disk, returning its estimated size
+    ///
+    /// `None` if there is nothing to spill or the spill file cannot be written.
+    fn spill_coldest(&mut self) -> Option<u64> {
+        let spill = self.spill.as_mut()?;
+        let (_, fqcn) = self.order.pop_first()?;
+        let Some((metadata, tick, bytes)) = self.hot.remove(&fqcn) else {
+            return Some(0);
+        };
+        if let Err(e) = spill.append(&fqcn, &metadata) {
+            // Keep the entry in memory rather than losing it
+            warn!("Failed to spill {} to {:?}: {}", fqcn, spill.path, e);
+            self.order.insert(tick, fqcn.clone());
+            self.hot.insert(fqcn, (metadata, tick, bytes));
+            return None;
+        }
+        self.hot_bytes -= bytes;
+        self.evictions += 1;
+        Some(bytes)
+    }
+
+    fn flush_spill(&mut self) {
+        let Some(spill) = &mut self.spill else {
+            return;
+        };
+        // Spilled records must be readable through separate handles
+        if let Err(e) = spill.writer.flush() {
+            warn!("Failed to flush spill file {:?}: {}", spill.path, e);
+        }
+        spill.compact_if_needed();
+    }
+}
+
+/// Decode the record of `entry` from the spill file, using `buffer` for its bytes
+fn read_record(
+    file: &mut File, entry: &Spilled, buffer: &mut Vec<u8>,
+) -> io::Result<PhpClassMetadata> {
+    buffer.resize(usize::try_from(entry.len).unwrap_or(usize::MAX), 0);
+    file.seek(SeekFrom::Start(entry.offset))?;
+    file.read_exact(buffer)?;
+    Ok(serde_json::from_slice(buffer)?)
+}
+
+/// Approximate memory held by one class
+///
+/// Metadata is mostly strings and lists of strings, so the length of its JSON
+/// encoding plus the struct itself tracks its heap use closely enough for a
+/// budget, without walking every field by hand.
+fn estimated_size(metadata: &PhpClassMetadata) -> u64 {
+    struct Counter(u64);
+
+    impl Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len() as u64;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(std::mem::size_of::<PhpClassMetadata>() as u64);
+    // Serializing into a counter cannot fail
+    let _ = serde_json::to_writer(&mut counter, metadata);
+    counter.0
+}
+
+impl Spill {
+    fn append(&mut self, fqcn: &str, metadata: &PhpClassMetadata) -> io::Result<()> {
+        let mut record = serde_json::to_vec(metadata)?;
+        let len = record.len() as u64;
+        record.push(b'\n');
+        self.writer.write_all(&record)?;
+
+        self.index.insert(
+            fqcn.to_string(),
+            Spilled {
+                file: metadata.file.clone(),
+                offset: self.end,
+                len,
+            },
+        );
+        self.end += len + 1;
+        Ok(())
+    }
+
+    /// Drop `fqcn` from the index, accounting its record as garbage
+    ///
+    /// Returns the source file of the dropped record.
+    fn forget(&mut self, fqcn: &str) -> Option<PathBuf> {
+        let entry = self.index.remove(fqcn)?;
+        self.garbage += entry.len + 1;
+        Some(entry.file)
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        self.index.clear();
+        self.end = 0;
+        self.garbage = 0;
+        self.writer.flush()?;
+        self.writer.get_mut().set_len(0)?;
+        self.writer.seek(SeekFrom::Start(0))?;
+        Ok(())
+    }
+
+    fn compact_if_needed(&mut self) {
+        if self.garbage < MIN_COMPACT_BYTES || self.garbage < self.end - self.garbage {
+            return;
+        }
+        if let Err(e) = self.compact() {
+            warn!("Failed to compact spill file {:?}: {}", self.path, e);
+        }
+    }
+
+    /// Rewrite the spill file with live records only
+    ///
+    /// The index keeps pointing into the old file until the new one has
+    /// replaced it, so a failed compaction leaves the store readable.
+    fn compact(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        let temp = self.path.with_extension("compact");
+        let Compacted { file, offsets, end } = match self.write_live_records(&temp) {
+            Ok(compacted) => compacted,
+            Err(e) => {
+                let _ = fs::remove_file(&temp);
+                return Err(e);
+            },
+        };
+
+        for (fqcn, offset) in offsets {
+            if let Some(entry) = self.index.get_mut(&fqcn) {
+                entry.offset = offset;
+            }
+        }
+        self.writer = BufWriter::new(file);
+        self.end = end;
+        self.garbage = 0;
+        Ok(())
+    }
+
+    /// Copy the live records to `temp` and move it over the spill file
+    ///
+    fn write_live_records(&self, temp: &Path) -> io::Result<Compacted> {
+        let mut source = File::open(&self.path)?;
+        let mut out = BufWriter::new(File::create(temp)?);
+        let mut entries: Vec<(&String, &Spilled)> = self.index.iter().collect();
+        entries.sort_by_key(|(_, entry)| entry.offset);
+
+        let mut offsets = Vec::with_capacity(entries.len());
+        let mut offset = 0;
+        let mut buffer = Vec::new();
+        for (fqcn, entry) in entries {
+            buffer.resize(usize::try_from(entry.len + 1).unwrap_or(usize::MAX), 0);
+            source.seek(SeekFrom::Start(entry.offset))?;
+            source.read_exact(&mut buffer)?;
+            out.write_all(&buffer)?;
+            offsets.push((fqcn.clone(), offset));
+            offset += entry.len + 1;
+        }
+
+        let file = out.into_inner().map_err(io::IntoInnerError::into_error)?;
+        fs::rename(temp, &self.path)?;
+        Ok(Compacted {
+            file,
+            offsets,
+            end: offset,
+        })
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    fn class(fqcn: &str, file: &str) -> PhpClassMetadata {
+        PhpClassMetadata::new(fqcn.to_string(), PathBuf::from(file), "class".to_string())
+    }
+
+    fn sorted_names(store: &ClassStore) -> Vec<String> {
+        let mut names: Vec<String> = store
+            .to_vec()
+            .unwrap()
+            .into_iter()
+            .map(|m| m.fqcn)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_spilling_store_keeps_hot_entries_in_memory() {
+        let temp_dir = TempDir::new().unwrap();
+        let spill_path = temp_dir.path().join("aurynx.spill");
+        let mut store = ClassStore::spilling(2, &spill_path).unwrap();
+
+        store.insert("A".to_string(), class("A", "/src/A.php"));
+        store.insert("B".to_string(), class("B", "/src/B.php"));
+        store.insert("C".to_string(), class("C", "/src/C.php"));
+        // Rewriting a spilled entry makes it hot again
+        store.insert("A".to_string(), class("A", "/src/A.php"));
+
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.spilled_len(), 1);
+        assert_eq!(store.evictions(), 2);
+        assert_eq!(store.files().count(), 3);
+        assert!(store.hot.contains_key("A") && store.hot.contains_key("C"));
+        assert_eq!(sorted_names(&store), ["A", "B", "C"]);
+        assert_eq!(store.get("B").unwrap().unwrap().file, PathBuf::from("/src/B.php"));
+        assert!(store.get("Missing").unwrap().is_none());
+
+        store.remove_file(Path::new("/src/B.php"));
+        store.remove_file(Path::new("/src/C.php"));
+        assert_eq!(sorted_names(&store), ["A"]);
+
+        store.clear();
+        assert_eq!(store.len(), 0);
+        assert_eq!(store.memory_bytes(), 0);
+
+        drop(store);
+        assert!(!spill_path.exists());
+    }
+
+    #[test]
+    fn test_remove_file_follows_classes_that_moved() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut store = ClassStore::spilling(1, &temp_dir.path().join("aurynx.spill")).unwrap();
+
+        store.insert("A".to_string(), class("A", "/src/Old.php"));
+        store.insert("B".to_string(), class("B", "/src/Old.php"));
+        // A moves to another file while spilled, B stays
+        store.insert("A".to_string(), class("A", "/src/New.php"));
+
+        store.remove_file(Path::new("/src/Old.php"));
+        assert_eq!(sorted_names(&store), ["A"]);
+        store.remove_file(Path::new("/src/New.php"));
+        assert_eq!(store.len(), 0);
+        assert!(store.by_file.is_empty());
+    }
+
+    #[test]
+    fn test_shed_spills_coldest_entries_and_keeps_the_hot_set_small() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut store = ClassStore::spilling(10, &temp_dir.path().join("aurynx.spill")).unwrap();
+        for name in ["A", "B", "C", "D"] {
+            store.insert(name.to_string(), class(name, "/src/A.php"));
+        }
+        let per_entry = store.memory_bytes() / 4;
+
+        // Just over one entry's worth: the two coldest go to disk
+        assert_eq!(store.shed(per_entry + 1), 2);
+        assert_eq!(store.memory_bytes(), per_entry * 2);
+        assert!(store.hot.contains_key("C") && store.hot.contains_key("D"));
+
+        store.insert("E".to_string(), class("E", "/src/A.php"));
+        assert_eq!(store.spilled_len(), 3);
+        assert_eq!(sorted_names(&store), ["A", "B", "C", "D", "E"]);
+
+        assert_eq!(ClassStore::in_memory().shed(1), 0);
+    }
+
+    #[test]
+    fn test_compaction_preserves_live_records() {
+        let temp_dir = TempDir::new().unwrap();
+        let spill_path = temp_dir.path().join("aurynx.spill");
+        let mut store = ClassStore::spilling(1, &spill_path).unwrap();
+
+        // Churn enough records through the spill file to trigger compaction
+        let mut big = class("Big", "/src/Big.php");
+        big.implements = vec!["\\Some\\Interface".repeat(1000)];
+        for round in 0..200 {
+            store.insert("Big".to_string(), big.clone());
+            store.insert(format!("Hot{round}"), class("Hot", "/src/Hot.php"));
+        }
+        store.remove_file(Path::new("/src/Hot.php"));
+        assert!(store.by_file.keys().eq([Path::new("/src/Big.php")]));
+
+        let live = store.spill.as_ref().unwrap().end;
+        assert!(
+            live < MIN_COMPACT_BYTES,
+            "spill file not compacted: {live} bytes"
+        );
+        assert_eq!(sorted_names(&store), ["Big"]);
+    }
+}
@@ -0,0 +1,217 @@
+#![allow(unsafe_code)]
+//! Restarting supervisor for the daemon (`--supervise`)
+//!
+//! [`run`] starts the daemon as a child process with the same arguments and
+//! starts it again whenever it crashes, waiting between attempts with an
+//! exponential backoff that is reset once a daemon has stayed up for a while.
+//! A crash is a death by signal or one of the [`CRASH_CODES`]; any other
+//! status (a configuration or usage error, a lock held by another daemon)
+//! would only repeat, so the supervisor exits with it.
+//! The socket, PID and cache paths come from the arguments, so clients keep
+//! connecting to the same socket; the restarted daemon removes the stale
+//! socket and lock itself.
+//!
+//! Termination signals are forwarded to the daemon and end supervision, as
+//! does a clean exit. Each crash is reported on stderr with its exit code or
+//! signal, next to whatever the daemon logged before dying.
+
+use super::init::exit_code;
+use std::ffi::OsString;
+use std::io;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus};
+use std::time::{Duration, Instant};
+
+/// First delay before restarting a crashed daemon
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest delay between restarts
+const MAX_BACKOFF: Duration = Duration::from_mins(1);
+
+/// A daemon running this long is considered healthy; its crash restarts the backoff
+const STABLE_AFTER: Duration = Duration::from_mins(1);
+
+/// Exit statuses of a daemon that may run after a restart: file watching
+/// failed (71) or fatal IO (74), see `AurynxError::exit_status`, or a panic
+/// in a build that unwinds (101)
+pub const CRASH_CODES: [i32; 3] = [71, 74, 101];
+
+/// Signals forwarded to the daemon (each ends supervision)
+const FORWARDED: [libc::c_int; 4] = [libc::SIGTERM, libc::SIGINT, libc::SIGHUP, libc::SIGQUIT];
+
+/// Run `program args` until it exits without crashing or is asked to stop
+///
+/// `args` should make the daemon exit with distinct statuses
+/// (`--foreground`). Returns the exit code to leave with. Must be called before any thread is
+/// started, since the forwarded signals are blocked for the whole process.
+///
+/// # Errors
+///
+/// Returns an error if the signal mask cannot be changed or the daemon
+/// cannot be spawned.
+pub fn run(program: &Path, args: &[OsString]) -> io::Result<i32> {
+    let signals = block_signals()?;
+    let mut backoff = INITIAL_BACKOFF;
+    let mut restarts = 0u32;
+
+    loop {
+        let mut command = Command::new(program);
+        command.args(args);
+        // The daemon needs the signals the supervisor blocked (the mask is inherited)
+        unsafe {
+            command.pre_exec(move || {
+                if libc::sigprocmask(libc::SIG_UNBLOCK, &raw const signals, std::ptr::null_mut())
+                    != 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let mut child = command.spawn()?;
+        let started = Instant::now();
+        let (status, stopping) = wait_forwarding(&mut child, &signals)?;
+        let code = exit_code(status.into_raw());
+
+        if stopping || status.success() {
+            return Ok(code);
+        }
+        if !is_crash(status) {
+            eprintln!(
+                "aurynx supervisor: daemon (pid {}) {}; not restarting",
+                child.id(),
+                describe(status)
+            );
+            return Ok(code);
+        }
+
+        if started.elapsed() >= STABLE_AFTER {
+            backoff = INITIAL_BACKOFF;
+        }
+        restarts += 1;
+        eprintln!(
+            "aurynx supervisor: daemon (pid {}) {} after {:.1}s; restart #{restarts} in {}s",
+            child.id(),
+            describe(status),
+            started.elapsed().as_secs_f64(),
+            backoff.as_secs()
+        );
+
+        if wait_signal(&signals, backoff).is_some() {
+            return Ok(code);
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Block the forwarded signals and `SIGCHLD`; returns the blocked set
+fn block_signals() -> io::Result<libc::sigset_t> {
+    unsafe {
+        let mut set: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&raw mut set);
+        for signal in FORWARDED {
+            libc::sigaddset(&raw mut set, signal);
+        }
+        libc::sigaddset(&raw mut set, libc::SIGCHLD);
+        if libc::sigprocmask(libc::SIG_BLOCK, &raw const set, std::ptr::null_mut()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(set)
+    }
+}
+
+/// Wait for a blocked signal other than `SIGCHLD`, for at most `timeout`
+fn wait_signal(signals: &libc::sigset_t, timeout: Duration) -> Option<libc::c_int> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return None;
+        }
+        let timeout = libc::timespec {
+            tv_sec: libc::time_t::try_from(remaining.as_secs()).unwrap_or(libc::time_t::MAX),
+            tv_nsec: libc::c_long::from(remaining.subsec_nanos().cast_signed()),
+        };
+        let signal =
+            unsafe { libc::sigtimedwait(signals, std::ptr::null_mut(), &raw const timeout) };
+        if signal > 0 && signal != libc::SIGCHLD {
+            return Some(signal);
+        }
+    }
+}
+
+/// Wait for `child`, forwarding signals; returns its status and whether a
+/// signal asked it to stop
+fn wait_forwarding(child: &mut Child, signals: &libc::sigset_t) -> io::Result<(ExitStatus, bool)> {
+    let pid = libc::pid_t::try_from(child.id()).unwrap_or(libc::pid_t::MAX);
+    let mut stopping = false;
+    loop {
+        if let Some(signal) = wait_signal(signals, Duration::from_secs(1)) {
+            unsafe { libc::kill(pid, signal) };
+            stopping = true;
+        }
+        if let Some(status) = child.try_wait()? {
+            return Ok((status, stopping));
+        }
+    }
+}
+
+/// Whether a daemon that ended with `status` crashed and is worth restarting
+fn is_crash(status: ExitStatus) -> bool {
+    status
+        .code()
+        .map_or_else(|| status.signal().is_some(), |code| CRASH_CODES.contains(&code))
+}
+
+/// Crash reason of an exit status
+fn describe(status: ExitStatus) -> String {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exited with code {code}"),
+        (None, Some(signal)) => {
+            let name = match signal {
+                libc::SIGABRT => " (SIGABRT)",
+                libc::SIGBUS => " (SIGBUS)",
+                libc::SIGKILL => " (SIGKILL, possibly out of memory)",
+                libc::SIGSEGV => " (SIGSEGV)",
+                _ => "",
+            };
+            let core = if status.core_dumped() {
+                ", core dumped"
+            } else {
+                ""
+            };
+            format!("was killed by signal {signal}{name}{core}")
+        },
+        (None, None) => format!("stopped ({status})"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_crash_reasons() {
+        assert_eq!(
+            describe(ExitStatus::from_raw(101 << 8)),
+            "exited with code 101"
+        );
+        assert_eq!(
+            describe(ExitStatus::from_raw(libc::SIGKILL)),
+            "was killed by signal 9 (SIGKILL, possibly out of memory)"
+        );
+    }
+
+    #[test]
+    fn test_only_crashes_are_restarted() {
+        assert!(is_crash(ExitStatus::from_raw(libc::SIGSEGV)));
+        assert!(is_crash(ExitStatus::from_raw(74 << 8)));
+        assert!(is_crash(ExitStatus::from_raw(101 << 8)));
+        // Configuration, usage and lock errors would fail again
+        assert!(!is_crash(ExitStatus::from_raw(78 << 8)));
+        assert!(!is_crash(ExitStatus::from_raw(75 << 8)));
+        assert!(!is_crash(ExitStatus::from_raw(2 << 8)));
+        assert!(!is_crash(ExitStatus::from_raw(1 << 8)));
+    }
+}
@@ -0,0 +1,235 @@
+//! Derived caches (`derived`)
+//!
+//! Route tables, entity maps and the like are usually produced by running
+//! `discovery:export` after every scan, which a running daemon makes easy to
+//! forget. Each entry of the `derived` config list names an output that is
+//! regenerated with the cache instead: one-shot scans write it after the
+//! cache, the daemon on every flush, and an output whose content did not
+//! change is left untouched, so watchers of those files only fire on real
+//! changes.
+//!
+//! Kinds:
+//! - `attribute_index`: attribute FQCN => FQCNs of the classes using it on
+//!   the class or any member (PHP or JSON, like class lists)
+//! - `routes`: the route table of `discovery:export routes` (needs `preset`)
+//! - `entities`: the Doctrine entity map of `discovery:export entities`
+//! - `tests`: the test manifest of `discovery:export tests`
+
+use crate::metadata::PhpClassMetadata;
+use crate::routes::RoutePreset;
+use crate::writer::php_literal;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+/// Kinds a derived output can have
+pub const DERIVED_KINDS: [&str; 4] = ["attribute_index", "routes", "entities", "tests"];
+
+/// One entry of the `derived` config list
+#[derive(Debug, Clone, Deserialize)]
+pub struct DerivedOutput {
+    /// One of [`DERIVED_KINDS`]
+    pub kind: String,
+    /// Output file
+    pub output: PathBuf,
+    /// Route layout for `routes`: `symfony` or `laravel`
+    pub preset: Option<String>,
+    /// `php` or `json` for `attribute_index` (default: by the output
+    /// extension, otherwise php)
+    pub format: Option<String>,
+}
+
+impl DerivedOutput {
+    /// Check the entry before any scanning happens
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first invalid field.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if !DERIVED_KINDS.contains(&self.kind.as_str()) {
+            return Err(format!(
+                "Invalid derived output kind '{}' (allowed: {})",
+                self.kind,
+                DERIVED_KINDS.join(", ")
+            ));
+        }
+        match (self.kind.as_str(), &self.preset) {
+            ("routes", None) => {
+                return Err(format!(
+                    "Derived routes output {} needs a 'preset' (symfony or laravel)",
+                    self.output.display()
+                ));
+            },
+            ("routes", Some(preset)) => {
+                preset.parse::<RoutePreset>().map_err(|e| e.to_string())?;
+            },
+            (kind, Some(_)) => {
+                return Err(format!("'preset' does not apply to derived {kind} outputs"));
+            },
+            _ => {},
+        }
+        match self.format.as_deref() {
+            None => Ok(()),
+            Some("php" | "json") if self.kind == "attribute_index" => Ok(()),
+            Some(format) => Err(format!(
+                "Invalid format '{format}' for derived {} output {}",
+                self.kind,
+                self.output.display()
+            )),
+        }
+    }
+
+    fn is_json(&self) -> bool {
+        self.format.as_deref().map_or_else(
+            || self.output.extension().is_some_and(|ext| ext == "json"),
+            |format| format == "json",
+        )
+    }
+
+    /// Content of the output and its number of entries
+    fn render(&self, metadata_list: &[PhpClassMetadata]) -> Result<(String, usize)> {
+        match self.kind.as_str() {
+            "routes" => {
+                let preset: RoutePreset = self.preset.as_deref().unwrap_or_default().parse()?;
+                let table = crate::routes::extract_routes(metadata_list, preset);
+                let content = crate::routes::render_route_table(&table, preset);
+                Ok((content, table.routes.len()))
+            },
+            "entities" => {
+                let map = crate::doctrine::build_entity_map(metadata_list);
+                Ok((pretty_json(&map)?, map.entities.len()))
+            },
+            "tests" => {
+                let manifest = crate::phpunit::build_test_manifest(metadata_list);
+                Ok((pretty_json(&manifest)?, manifest.tests))
+            },
+            _ => {
+                let index = attribute_index(metadata_list);
+                let content = if self.is_json() {
+                    pretty_json(&index)?
+                } else {
+                    render_attribute_index_php(&index)
+                };
+                Ok((content, index.len()))
+            },
+        }
+    }
+}
+
+/// Attribute FQCN => classes using it, both without the leading backslash
+#[must_use]
+pub fn attribute_index(metadata_list: &[PhpClassMetadata]) -> BTreeMap<String, BTreeSet<String>> {
+    let mut index: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for class in metadata_list {
+        for attribute in class.attribute_names() {
+            index
+                .entry(attribute.trim_start_matches('\\').to_string())
+                .or_default()
+                .insert(class.fqcn.trim_start_matches('\\').to_string());
+        }
+    }
+    index
+}
+
+fn render_attribute_index_php(index: &BTreeMap<String, BTreeSet<String>>) -> String {
+    let mut out = String::from("<?php declare(strict_types=1);\n\nreturn [\n");
+    for (attribute, classes) in index {
+        let classes: Vec<String> = classes
+            .iter()
+            .map(|class| php_literal(class))
+            .collect();
+        let _ = writeln!(
+            out,
+            "    {} => [{}],",
+            php_literal(attribute),
+            classes.join(", ")
+        );
+    }
+    out.push_str("];\n");
+    out
+}
+
+fn pretty_json(value: &impl serde::Serialize) -> Result<String> {
+    let mut json = serde_json::to_string_pretty(value)?;
+    json.push('\n');
+    Ok(json)
+}
+
+/// Write every output whose content changed; returns each output with its entry count
+///
+/// # Errors
+///
+/// Returns an error if an output cannot be rendered or written.
+pub fn write_derived(
+    metadata_list: &[PhpClassMetadata], outputs: &[DerivedOutput],
+) -> Result<Vec<(PathBuf, usize)>> {
+    let mut written = Vec::with_capacity(outputs.len());
+    for output in outputs {
+        let (content, entries) = output.render(metadata_list)?;
+        crate::writer::write_if_changed(&output.output, content.as_bytes()).with_context(|| {
+            format!("Failed to write derived output {}", output.output.display())
+        })?;
+        written.push((output.output.clone(), entries));
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::parser::PhpMetadataExtractor;
+
+    #[test]
+    fn test_attribute_index_covers_classes_and_members() {
+        let source = r"<?php
+namespace App;
+#[Entity]
+class User { #[Column] public $name; #[Route('/users')] public function list(#[MapQuery] $q) {} }
+#[Entity]
+class Post {}
+";
+        let metadata = PhpMetadataExtractor::new()
+            .unwrap()
+            .extract_metadata(source, PathBuf::from("models.php"))
+            .unwrap();
+
+        let output = DerivedOutput {
+            kind: "attribute_index".to_string(),
+            output: PathBuf::from("attributes.php"),
+            preset: None,
+            format: None,
+        };
+        let (content, entries) = output.render(&metadata).unwrap();
+
+        assert_eq!(entries, 4);
+        assert_eq!(
+            content,
+            "<?php declare(strict_types=1);\n\nreturn [\n    \
+             'App\\\\Column' => ['App\\\\User'],\n    \
+             'App\\\\Entity' => ['App\\\\Post', 'App\\\\User'],\n    \
+             'App\\\\MapQuery' => ['App\\\\User'],\n    \
+             'App\\\\Route' => ['App\\\\User'],\n];\n"
+        );
+    }
+
+    #[test]
+    fn test_validate_checks_kind_preset_and_format() {
+        let output = |kind: &str, preset: Option<&str>, format: Option<&str>| DerivedOutput {
+            kind: kind.to_string(),
+            output: PathBuf::from("out.php"),
+            preset: preset.map(str::to_string),
+            format: format.map(str::to_string),
+        };
+
+        assert!(output("routes", Some("symfony"), None).validate().is_ok());
+        assert!(output("routes", None, None).validate().is_err());
+        assert!(output("routes", Some("rails"), None).validate().is_err());
+        assert!(output("entities", Some("symfony"), None).validate().is_err());
+        assert!(output("attribute_index", None, Some("json")).validate().is_ok());
+        assert!(output("tests", None, Some("php")).validate().is_err());
+        assert!(output("classmap", None, None).validate().is_err());
+    }
+}
@@ -0,0 +1,267 @@
+//! Syntax error diagnostics with source excerpts
+//!
+//! tree-sitter recovers from syntax errors by wrapping the offending tokens in
+//! `ERROR` nodes (or inserting zero-width `MISSING` nodes) and keeps parsing,
+//! so a broken file still yields the declarations around the error. This
+//! module turns the first such node into a compiler-style report with the
+//! location, the offending line and the declaration it occurred in:
+//!
+//! ```text
+//! syntax error: missing `)`
+//!  --> src/User.php:4:13
+//!   |
+//! 3 |     public function save(): void {
+//! 4 |         foo(;
+//!   |             ^
+//!   = in method User::save
+//! ```
+
+use std::fmt::Write as _;
+use std::path::Path;
+use tree_sitter::{Node, Tree};
+
+/// Longest excerpt of the offending token quoted in the message
+const MAX_TOKEN_CHARS: usize = 24;
+
+/// First syntax error of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxDiagnostic {
+    /// 1-based line of the error
+    pub line: usize,
+    /// 1-based column of the error, in characters
+    pub column: usize,
+    /// What is wrong, e.g. ``unexpected `=` `` or ``missing `)` ``
+    pub message: String,
+    /// Source lines shown above the caret, ending with the error line
+    excerpt: Vec<(usize, String)>,
+    /// Width of the caret underline, in characters
+    width: usize,
+    /// Enclosing declaration, e.g. `method User::save`
+    pub context: Option<String>,
+}
+
+impl SyntaxDiagnostic {
+    /// Diagnose the first `ERROR` or `MISSING` node of `tree`, if any
+    #[must_use]
+    pub fn find(tree: &Tree, source: &str) -> Option<Self> {
+        let node = first_error(tree.root_node())?;
+        let token = if node.is_missing() {
+            ""
+        } else {
+            first_line(node.utf8_text(source.as_bytes()).unwrap_or_default())
+        };
+
+        // An error running to the end of the file is an unclosed block: point
+        // at the end of the last line rather than at the start of the block
+        let trimmed = source.trim_end();
+        let unclosed = node.is_error()
+            && node.end_byte() >= trimmed.len()
+            && node.start_position().row != node.end_position().row;
+        let (row, byte_column) = if unclosed {
+            let row = trimmed.lines().count().saturating_sub(1);
+            (row, trimmed.lines().last().unwrap_or_default().len())
+        } else {
+            (node.start_position().row, node.start_position().column)
+        };
+
+        let line_text = source.lines().nth(row).unwrap_or_default();
+        let prefix = line_text.get(..byte_column).unwrap_or(line_text);
+        let column = prefix.chars().count() + 1;
+
+        let message = if unclosed || (node.is_error() && token.is_empty()) {
+            "unexpected end of file".to_string()
+        } else if node.is_missing() && node.is_named() {
+            format!("expected {}", node.kind().replace('_', " "))
+        } else if node.is_missing() {
+            format!("missing `{}`", node.kind())
+        } else {
+            format!("unexpected `{}`", truncate(token))
+        };
+
+        let rest = line_text.chars().count().saturating_sub(column - 1);
+        let width = token.chars().count().clamp(1, rest.max(1));
+
+        let excerpt = row
+            .checked_sub(1)
+            .and_then(|previous| {
+                source
+                    .lines()
+                    .nth(previous)
+                    .map(|text| (previous + 1, text))
+            })
+            .filter(|(_, text)| !text.trim().is_empty())
+            .into_iter()
+            .chain(std::iter::once((row + 1, line_text)))
+            .map(|(line, text)| (line, text.trim_end().to_string()))
+            .collect();
+
+        Some(Self {
+            line: row + 1,
+            column,
+            message,
+            excerpt,
+            width: if unclosed { 1 } else { width },
+            context: declaration_context(node, source),
+        })
+    }
+
+    /// One-line form: ``syntax error at 4:13: missing `)` (in method User::save)``
+    #[must_use]
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "syntax error at {}:{}: {}",
+            self.line, self.column, self.message
+        );
+        if let Some(context) = &self.context {
+            let _ = write!(summary, " (in {context})");
+        }
+        summary
+    }
+
+    /// Multi-line report with the source excerpt and a caret under the error
+    #[must_use]
+    pub fn render(&self, path: &Path) -> String {
+        let gutter = self.line.to_string().len();
+        let mut report = format!(
+            "syntax error: {}\n{:>gutter$}--> {}:{}:{}\n{:>gutter$} |\n",
+            self.message,
+            "",
+            path.display(),
+            self.line,
+            self.column,
+            "",
+        );
+        for (line, text) in &self.excerpt {
+            let _ = writeln!(report, "{line:>gutter$} | {text}");
+        }
+        let _ = write!(
+            report,
+            "{:>gutter$} | {}{}",
+            "",
+            " ".repeat(self.column - 1),
+            "^".repeat(self.width)
+        );
+        if let Some(context) = &self.context {
+            let _ = write!(report, "\n{:>gutter$} = in {context}", "");
+        }
+        report
+    }
+}
+
+/// First `ERROR` or `MISSING` node in document order
+fn first_error(node: Node<'_>) -> Option<Node<'_>> {
+    if node.is_error() || node.is_missing() {
+        return Some(node);
+    }
+    if !node.has_error() {
+        return None;
+    }
+    let mut cursor = node.walk();
+    node.children(&mut cursor).find_map(first_error)
+}
+
+/// Innermost declaration around `node`, qualified with its class-like parent
+fn declaration_context(node: Node<'_>, source: &str) -> Option<String> {
+    let name = |node: Node<'_>| {
+        node.child_by_field_name("name")
+            .and_then(|name| name.utf8_text(source.as_bytes()).ok())
+    };
+
+    let mut member = None;
+    let mut current = node.parent();
+    while let Some(ancestor) = current {
+        match ancestor.kind() {
+            "method_declaration" if member.is_none() => member = name(ancestor),
+            "class_declaration"
+            | "interface_declaration"
+            | "trait_declaration"
+            | "enum_declaration" => {
+                let kind = ancestor.kind().trim_end_matches("_declaration");
+                let class = name(ancestor)?;
+                return Some(member.map_or_else(
+                    || format!("{kind} {class}"),
+                    |method| format!("method {class}::{method}"),
+                ));
+            },
+            "function_definition" => return name(ancestor).map(|f| format!("function {f}")),
+            _ => {},
+        }
+        current = ancestor.parent();
+    }
+    member.map(|method| format!("method {method}"))
+}
+
+/// First line of a node's text, without trailing whitespace
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or_default().trim_end()
+}
+
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= MAX_TOKEN_CHARS {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(MAX_TOKEN_CHARS).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use tree_sitter::Parser;
+
+    fn diagnose(source: &str) -> Option<SyntaxDiagnostic> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_php::LANGUAGE_PHP.into())
+            .unwrap();
+        SyntaxDiagnostic::find(&parser.parse(source, None).unwrap(), source)
+    }
+
+    #[test]
+    fn test_valid_source_has_no_diagnostic() {
+        assert_eq!(diagnose("<?php\nclass User {}\n"), None);
+    }
+
+    #[test]
+    fn test_missing_token_in_method() {
+        let source =
+            "<?php\nclass User {\n    public function save(): void {\n        foo(;\n    }\n}\n";
+        let diagnostic = diagnose(source).unwrap();
+        assert_eq!(
+            diagnostic.summary(),
+            "syntax error at 4:13: missing `)` (in method User::save)"
+        );
+        let expected = [
+            "syntax error: missing `)`",
+            " --> src/User.php:4:13",
+            "  |",
+            "3 |     public function save(): void {",
+            "4 |         foo(;",
+            "  |             ^",
+            "  = in method User::save",
+        ];
+        assert_eq!(
+            diagnostic.render(Path::new("src/User.php")),
+            expected.join("\n")
+        );
+    }
+
+    #[test]
+    fn test_unexpected_token_in_class() {
+        let diagnostic = diagnose("<?php\nclass User {\n    public $x = ;\n}\n").unwrap();
+        assert_eq!(
+            diagnostic.summary(),
+            "syntax error at 3:15: unexpected `=` (in class User)"
+        );
+    }
+
+    #[test]
+    fn test_unclosed_block_points_at_end_of_file() {
+        let source = "<?php\nclass User {\n    public function save(): void {\n        return;\n";
+        let diagnostic = diagnose(source).unwrap();
+        assert_eq!(diagnostic.message, "unexpected end of file");
+        assert_eq!((diagnostic.line, diagnostic.column), (4, 16));
+    }
+}
@@ -0,0 +1,406 @@
+//! High-level scanning API for embedding
+//!
+//! Runs the same one-shot scan as `discovery:scan` without the CLI plumbing:
+//! files are collected with the ignore rules and `.gitignore`, parsed in
+//! parallel, and optionally resolved across the class hierarchy. Nothing is
+//! written; the caller gets the classes and a report of the files that failed.
+//!
+//! ```rust,no_run
+//! use aurynx::discovery::Discovery;
+//!
+//! let report = Discovery::builder()
+//!     .paths(["src", "lib"])
+//!     .ignore(["tests/*"])
+//!     .max_file_size(2 * 1024 * 1024)
+//!     .resolve_hierarchy(true)
+//!     .scan()?;
+//!
+//! for class in &report.classes {
+//!     println!("{}", class.fqcn);
+//! }
+//! for failed in &report.failed {
+//!     eprintln!("{}: [{}] {}", failed.path.display(), failed.failure.code, failed.failure);
+//! }
+//! # Ok::<(), aurynx::AurynxError>(())
+//! ```
+//!
+//! [`DiscoveryBuilder::stream`] yields the classes as files are parsed
+//! instead, so embedders can feed their own stores with bounded memory:
+//!
+//! ```rust,no_run
+//! use aurynx::discovery::{Discovery, ScanItem};
+//!
+//! for item in Discovery::builder().path("src").stream()? {
+//!     match item {
+//!         ScanItem::Class(class) => println!("{}", class.fqcn),
+//!         ScanItem::Failed(failed) => eprintln!("{}: {}", failed.path.display(), failed.failure),
+//!     }
+//! }
+//! # Ok::<(), aurynx::AurynxError>(())
+//! ```
+//!
+//! Failures are always part of the results; whether they are also logged
+//! follows the process-wide [`crate::scanner::on_error`] policy.
+
+use crate::config::ConfigFile;
+use crate::error::{AurynxError, Result};
+use crate::metadata::PhpClassMetadata;
+use crate::scanner::{self, DEFAULT_MAX_FILE_SIZE, ExtractorPool, ScanFailure};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+/// Results a [`DiscoveryStream`] buffers ahead of its consumer
+const STREAM_BUFFER: usize = 256;
+
+/// Entry point of the embedding API
+#[derive(Debug)]
+pub struct Discovery;
+
+impl Discovery {
+    /// Start configuring a scan
+    #[must_use]
+    pub fn builder() -> DiscoveryBuilder {
+        DiscoveryBuilder::default()
+    }
+}
+
+/// Scan options, mirroring the `discovery:scan` flags
+#[derive(Debug, Clone)]
+pub struct DiscoveryBuilder {
+    paths: Vec<PathBuf>,
+    ignore: Vec<String>,
+    max_file_size: u64,
+    resolve_hierarchy: bool,
+    inherit_attributes: bool,
+}
+
+impl Default for DiscoveryBuilder {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            ignore: Vec::new(),
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            resolve_hierarchy: false,
+            inherit_attributes: false,
+        }
+    }
+}
+
+impl DiscoveryBuilder {
+    /// Take the scan options of a config file (`paths`, `ignore`,
+    /// `max_file_size_mb`, `resolve_hierarchy`, `inherit_attributes`)
+    #[must_use]
+    pub fn config(mut self, config: &ConfigFile) -> Self {
+        if let Some(paths) = &config.paths {
+            self.paths.clone_from(paths);
+        }
+        if let Some(ignore) = &config.ignore {
+            self.ignore.clone_from(ignore);
+        }
+        self.max_file_size = config.max_file_size_bytes();
+        self.resolve_hierarchy = config.resolve_hierarchy.unwrap_or(self.resolve_hierarchy);
+        self.inherit_attributes = config.inherit_attributes.unwrap_or(self.inherit_attributes);
+        self
+    }
+
+    /// Directories (or files) to scan, replacing any set before
+    #[must_use]
+    pub fn paths<P: Into<PathBuf>>(mut self, paths: impl IntoIterator<Item = P>) -> Self {
+        self.paths = paths.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Add one directory (or file) to scan
+    #[must_use]
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    /// Ignore patterns (e.g. `vendor/*`), replacing any set before
+    #[must_use]
+    pub fn ignore<S: Into<String>>(mut self, patterns: impl IntoIterator<Item = S>) -> Self {
+        self.ignore = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Largest file parsed, in bytes (default: [`DEFAULT_MAX_FILE_SIZE`])
+    #[must_use]
+    pub const fn max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = bytes;
+        self
+    }
+
+    /// Fill in `ancestors` and `resolved_implements` across the scanned classes
+    #[must_use]
+    pub const fn resolve_hierarchy(mut self, enabled: bool) -> Self {
+        self.resolve_hierarchy = enabled;
+        self
+    }
+
+    /// Resolve the hierarchy and copy inherited attributes to subclasses
+    #[must_use]
+    pub const fn inherit_attributes(mut self, enabled: bool) -> Self {
+        self.inherit_attributes = enabled;
+        self
+    }
+
+    /// Run the scan
+    ///
+    /// # Errors
+    ///
+    /// Returns a configuration error if no paths are set or one of them does
+    /// not exist. Files that fail to scan do not fail the call; they are
+    /// listed in [`DiscoveryReport::failed`].
+    pub fn scan(&self) -> Result<DiscoveryReport> {
+        self.check_paths()?;
+        let started = Instant::now();
+        let files = self.files();
+
+        let mut classes = Vec::new();
+        let mut failed = Vec::new();
+        for scan in scanner::scan_files_detailed(&files, self.max_file_size) {
+            match scan.error {
+                Some(failure) => failed.push(FailedFile {
+                    path: scan.path,
+                    failure,
+                }),
+                None => classes.extend(scan.classes),
+            }
+        }
+        classes.sort_by(|a, b| a.fqcn.cmp(&b.fqcn));
+        failed.sort_by(|a, b| a.path.cmp(&b.path));
+
+        if self.inherit_attributes {
+            crate::resolver::propagate_attributes(&mut classes);
+        } else if self.resolve_hierarchy {
+            crate::resolver::resolve_hierarchy(&mut classes);
+        }
+
+        Ok(DiscoveryReport {
+            classes,
+            files: files.len(),
+            failed,
+            duration: started.elapsed(),
+        })
+    }
+
+    /// Run the scan in the background, yielding results as files are parsed
+    ///
+    /// Classes arrive in parse order, not sorted. At most a few hundred
+    /// results are buffered: parsing waits while the consumer is behind, and
+    /// stops once the stream is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns a configuration error if no paths are set or one of them does
+    /// not exist, or if `resolve_hierarchy` or `inherit_attributes` is set:
+    /// both need the complete class list, so use [`Self::scan`] for them.
+    pub fn stream(&self) -> Result<DiscoveryStream> {
+        self.check_paths()?;
+        if self.resolve_hierarchy || self.inherit_attributes {
+            return Err(AurynxError::config_error(
+                "resolve_hierarchy and inherit_attributes need the complete class list; use scan()",
+            ));
+        }
+
+        let (tx, rx) = mpsc::sync_channel(STREAM_BUFFER);
+        let builder = self.clone();
+        std::thread::spawn(move || {
+            let files = builder.files();
+            let pool = ExtractorPool::default();
+            scanner::visit_files_detailed(&files, builder.max_file_size, &pool, |scan| match scan
+                .error
+            {
+                Some(failure) => tx
+                    .send(ScanItem::Failed(FailedFile {
+                        path: scan.path,
+                        failure,
+                    }))
+                    .is_ok(),
+                None => scan
+                    .classes
+                    .into_iter()
+                    .all(|class| tx.send(ScanItem::Class(class)).is_ok()),
+            });
+        });
+        Ok(DiscoveryStream { rx })
+    }
+
+    fn check_paths(&self) -> Result<()> {
+        if self.paths.is_empty() {
+            return Err(AurynxError::config_error("No paths to scan"));
+        }
+        if let Some(missing) = self.paths.iter().find(|path| !path.exists()) {
+            return Err(AurynxError::config_error(format!(
+                "Path not found: {}",
+                missing.display()
+            )));
+        }
+        Ok(())
+    }
+
+    /// PHP files under the paths, after the ignore rules and `.gitignore`
+    fn files(&self) -> Vec<PathBuf> {
+        let matcher = scanner::ignore_matcher(&self.paths, &self.ignore);
+        self.paths
+            .iter()
+            .flat_map(|root| scanner::php_files_under(root, matcher.as_ref()))
+            .collect()
+    }
+}
+
+/// Results of [`DiscoveryBuilder::stream`], ending when every file is parsed
+#[derive(Debug)]
+pub struct DiscoveryStream {
+    rx: Receiver<ScanItem>,
+}
+
+impl Iterator for DiscoveryStream {
+    type Item = ScanItem;
+
+    fn next(&mut self) -> Option<ScanItem> {
+        self.rx.recv().ok()
+    }
+}
+
+/// One result of a streamed scan
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)] // Nearly every item is a class; boxing would allocate each
+pub enum ScanItem {
+    /// A declaration found in a parsed file
+    Class(PhpClassMetadata),
+    /// A file that could not be scanned
+    Failed(FailedFile),
+}
+
+/// Outcome of [`DiscoveryBuilder::scan`]
+#[derive(Debug, Clone)]
+pub struct DiscoveryReport {
+    /// Declarations found, sorted by FQCN
+    pub classes: Vec<PhpClassMetadata>,
+    /// PHP files considered after applying the ignore rules
+    pub files: usize,
+    /// Files that could not be scanned, sorted by path
+    pub failed: Vec<FailedFile>,
+    pub duration: Duration,
+}
+
+impl DiscoveryReport {
+    /// Whether every file was scanned
+    #[must_use]
+    pub const fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// A file left out of a scan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedFile {
+    pub path: PathBuf,
+    pub failure: ScanFailure,
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_builder_scan_reports_classes_and_failures() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir(root.join("vendor")).unwrap();
+        fs::write(root.join("User.php"), "<?php namespace App; class User {}").unwrap();
+        fs::write(
+            root.join("Admin.php"),
+            "<?php namespace App; class Admin extends User {}",
+        )
+        .unwrap();
+        fs::write(
+            root.join("Large.php"),
+            format!("<?php // {}", "x".repeat(2048)),
+        )
+        .unwrap();
+        fs::write(root.join("vendor/Lib.php"), "<?php class Lib {}").unwrap();
+
+        let report = Discovery::builder()
+            .path(root)
+            .ignore(["vendor/*"])
+            .max_file_size(1024)
+            .resolve_hierarchy(true)
+            .scan()
+            .unwrap();
+
+        let fqcns: Vec<_> = report.classes.iter().map(|c| c.fqcn.as_str()).collect();
+        assert_eq!(fqcns, ["\\App\\Admin", "\\App\\User"]);
+        assert_eq!(
+            report.classes[0].ancestors,
+            Some(vec!["\\App\\User".to_string()])
+        );
+        assert_eq!(report.files, 3);
+        assert!(!report.is_complete());
+        assert_eq!(report.failed[0].path, root.join("Large.php"));
+        assert_eq!(
+            report.failed[0].failure.code,
+            crate::error::code::FILE_SIZE_LIMIT
+        );
+    }
+
+    #[test]
+    fn test_stream_yields_classes_and_failures() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for i in 0..20 {
+            fs::write(
+                root.join(format!("C{i}.php")),
+                format!("<?php namespace App; class C{i} {{}} interface I{i} {{}}"),
+            )
+            .unwrap();
+        }
+        fs::write(root.join("Large.php"), "<?php // ".repeat(200)).unwrap();
+
+        let mut classes = Vec::new();
+        let mut failed = Vec::new();
+        for item in Discovery::builder()
+            .path(root)
+            .max_file_size(1024)
+            .stream()
+            .unwrap()
+        {
+            match item {
+                ScanItem::Class(class) => classes.push(class.fqcn),
+                ScanItem::Failed(file) => failed.push(file.path),
+            }
+        }
+        assert_eq!(classes.len(), 40);
+        assert!(classes.contains(&"\\App\\I7".to_string()));
+        assert_eq!(failed, [root.join("Large.php")]);
+
+        // Dropping the stream early stops the scan
+        let mut stream = Discovery::builder().path(root).stream().unwrap();
+        assert!(stream.next().is_some());
+        drop(stream);
+
+        assert!(
+            Discovery::builder()
+                .path(root)
+                .resolve_hierarchy(true)
+                .stream()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_builder_rejects_missing_paths() {
+        assert!(Discovery::builder().scan().is_err());
+        let err = Discovery::builder()
+            .path("/nonexistent/aurynx")
+            .scan()
+            .unwrap_err();
+        assert_eq!(err.code(), crate::error::code::CONFIG);
+    }
+}
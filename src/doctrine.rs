@@ -0,0 +1,379 @@
+//! Doctrine entity map (`discovery:export entities --preset doctrine`)
+//!
+//! Lists every `#[ORM\Entity]` with its table, repository class, columns and
+//! associations, read from the ORM mapping attributes, so tooling can
+//! inspect the schema without booting Doctrine. Fields declared on mapped
+//! superclasses and parent entities found in the scan are included, as are
+//! promoted constructor properties.
+//!
+//! Names follow Doctrine's `DefaultNamingStrategy` when not declared: the
+//! short class name for tables, the property name for columns and
+//! `<property>_id` for join columns. Column types fall back to the typed
+//! property inference of ORM 2.9+, and so do to-one association targets.
+
+use crate::metadata::{AttributeArgument, AttributeMap, PhpClassMetadata};
+use crate::openapi::{argument, matches_attribute, php_class_name, php_string};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+const MAPPING: &str = "Doctrine\\ORM\\Mapping\\";
+
+const ASSOCIATIONS: &[&str] = &["ManyToOne", "OneToOne", "OneToMany", "ManyToMany"];
+
+/// Entities of the scanned code, keyed by FQCN
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EntityMap {
+    pub entities: BTreeMap<String, Entity>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Entity {
+    pub file: PathBuf,
+    pub table: String,
+    pub repository: Option<String>,
+    pub columns: Vec<Column>,
+    pub associations: Vec<Association>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Column {
+    pub property: String,
+    pub column: String,
+    /// Declared or inferred DBAL type (`None` when neither is possible)
+    #[serde(rename = "type")]
+    pub column_type: Option<String>,
+    pub nullable: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub id: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub unique: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Association {
+    pub property: String,
+    /// `ManyToOne`, `OneToOne`, `OneToMany` or `ManyToMany`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub target: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mapped_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inversed_by: Option<String>,
+    /// Foreign key column of the owning side of a to-one association
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub join_column: Option<String>,
+}
+
+/// Build the entity map from scanned metadata
+#[must_use]
+pub fn build_entity_map(metadata_list: &[PhpClassMetadata]) -> EntityMap {
+    let by_name: HashMap<&str, &PhpClassMetadata> = metadata_list
+        .iter()
+        .map(|class| (class.fqcn.as_str(), class))
+        .collect();
+
+    let mut map = EntityMap::default();
+    for class in metadata_list {
+        let Some(entity_args) = mapping(&class.attributes, "Entity") else {
+            continue;
+        };
+
+        let short_name = class.fqcn.rsplit('\\').next().unwrap_or_default();
+        let table = mapping(&class.attributes, "Table")
+            .and_then(|args| argument(args, "name", Some(0)))
+            .and_then(php_string)
+            .unwrap_or_else(|| short_name.to_string());
+        let repository = argument(entity_args, "repositoryClass", Some(0)).and_then(php_class_name);
+
+        // Parents first, so inherited identifiers lead the column list
+        let mut lineage = vec![class];
+        while let Some(parent) = lineage
+            .last()
+            .and_then(|current| current.extends.as_deref())
+            .and_then(|parent| by_name.get(parent).copied())
+            .filter(|parent| {
+                mapping(&parent.attributes, "MappedSuperclass").is_some()
+                    || mapping(&parent.attributes, "Entity").is_some()
+            })
+        {
+            lineage.push(parent);
+        }
+
+        let mut columns = Vec::new();
+        let mut associations = Vec::new();
+        for declaring in lineage.into_iter().rev() {
+            for (name, type_hint, attributes) in mapped_fields(declaring) {
+                if let Some(column) = column(name, type_hint, attributes) {
+                    columns.push(column);
+                } else if let Some(association) = association(name, type_hint, attributes) {
+                    associations.push(association);
+                }
+            }
+        }
+
+        map.entities.insert(
+            class.fqcn.trim_start_matches('\\').to_string(),
+            Entity {
+                file: class.file.clone(),
+                table,
+                repository,
+                columns,
+                associations,
+            },
+        );
+    }
+
+    map
+}
+
+/// Write the entity map as pretty-printed JSON if it changed
+///
+/// # Errors
+///
+/// Returns an error if the entity map cannot be written.
+pub fn write_entity_map(map: &EntityMap, output_path: &Path) -> Result<()> {
+    let content = serde_json::to_string_pretty(map)? + "\n";
+    crate::writer::write_if_changed(output_path, content.as_bytes())
+        .with_context(|| format!("Failed to write entity map {}", output_path.display()))?;
+    Ok(())
+}
+
+/// Properties and promoted constructor parameters: name, type hint, attributes
+fn mapped_fields(class: &PhpClassMetadata) -> Vec<(&str, Option<&str>, &AttributeMap)> {
+    let properties = class.properties.iter().map(|property| {
+        (
+            property.name.as_str(),
+            property.type_hint.as_deref(),
+            &property.attributes,
+        )
+    });
+    let promoted = class
+        .methods
+        .iter()
+        .filter(|method| method.name.eq_ignore_ascii_case("__construct"))
+        .flat_map(|method| &method.parameters)
+        .map(|parameter| {
+            (
+                parameter.name.as_str(),
+                parameter.type_hint.as_deref(),
+                &parameter.attributes,
+            )
+        });
+    properties.chain(promoted).collect()
+}
+
+/// First instance of the ORM mapping attribute `name`
+fn mapping<'a>(attributes: &'a AttributeMap, name: &str) -> Option<&'a [AttributeArgument]> {
+    let name = format!("{MAPPING}{name}");
+    attributes
+        .iter()
+        .find(|(attribute, _)| matches_attribute(attribute, &name))
+        .and_then(|(_, instances)| instances.first())
+        .map(Vec::as_slice)
+}
+
+fn column(property: &str, type_hint: Option<&str>, attributes: &AttributeMap) -> Option<Column> {
+    let args = mapping(attributes, "Column")?;
+    let flag = |key: &str| argument(args, key, None).is_some_and(|value| value.trim() == "true");
+
+    Some(Column {
+        property: property.to_string(),
+        column: argument(args, "name", Some(0))
+            .and_then(php_string)
+            .unwrap_or_else(|| property.to_string()),
+        column_type: argument(args, "type", Some(1))
+            .and_then(dbal_type)
+            .or_else(|| type_hint.and_then(inferred_type).map(ToString::to_string)),
+        nullable: flag("nullable"),
+        id: mapping(attributes, "Id").is_some(),
+        unique: flag("unique"),
+        length: argument(args, "length", None).and_then(|value| value.trim().parse().ok()),
+    })
+}
+
+fn association(
+    property: &str, type_hint: Option<&str>, attributes: &AttributeMap,
+) -> Option<Association> {
+    let (kind, args) = ASSOCIATIONS
+        .iter()
+        .find_map(|kind| mapping(attributes, kind).map(|args| (*kind, args)))?;
+    let text = |key: &str| argument(args, key, None).and_then(php_string);
+    let mapped_by = text("mappedBy");
+
+    let join_column = match kind {
+        "ManyToOne" | "OneToOne" if mapped_by.is_none() => Some(
+            mapping(attributes, "JoinColumn")
+                .and_then(|args| argument(args, "name", Some(0)))
+                .and_then(php_string)
+                .unwrap_or_else(|| format!("{property}_id")),
+        ),
+        _ => None,
+    };
+
+    Some(Association {
+        property: property.to_string(),
+        kind: kind.to_string(),
+        target: argument(args, "targetEntity", Some(0))
+            .and_then(php_class_name)
+            .or_else(|| to_one_target(kind, type_hint)),
+        mapped_by,
+        inversed_by: text("inversedBy"),
+        join_column,
+    })
+}
+
+/// Target Doctrine infers from the property type of a to-one association
+fn to_one_target(kind: &str, type_hint: Option<&str>) -> Option<String> {
+    let class = type_hint?.trim_start_matches('?');
+    (matches!(kind, "ManyToOne" | "OneToOne") && class.starts_with('\\'))
+        .then(|| class.trim_start_matches('\\').to_string())
+}
+
+/// Declared type: a string literal or a `Types::` constant
+/// (`Types::DATETIME_MUTABLE` → `datetime`)
+fn dbal_type(value: &str) -> Option<String> {
+    if let Some(name) = php_string(value) {
+        return Some(name);
+    }
+    let (_, constant) = value.trim().rsplit_once("Types::")?;
+    let constant = constant.strip_suffix("_MUTABLE").unwrap_or(constant);
+    Some(constant.to_lowercase())
+}
+
+/// Type Doctrine infers from a typed property
+fn inferred_type(type_hint: &str) -> Option<&'static str> {
+    match type_hint.trim_start_matches('?') {
+        "int" => Some("integer"),
+        "string" => Some("string"),
+        "bool" => Some("boolean"),
+        "float" => Some("float"),
+        "array" => Some("json"),
+        "\\DateTime" => Some("datetime"),
+        "\\DateTimeImmutable" => Some("datetime_immutable"),
+        "\\DateInterval" => Some("dateinterval"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::parser::tests::extract;
+
+    #[test]
+    fn test_entity_map_from_orm_attributes() {
+        let metadata = extract(
+            "/src/Entity.php",
+            r"<?php
+namespace App\Entity;
+use App\Repository\PostRepository;
+use Doctrine\DBAL\Types\Types;
+use Doctrine\ORM\Mapping as ORM;
+
+#[ORM\MappedSuperclass]
+abstract class Timestamped {
+    #[ORM\Id]
+    #[ORM\GeneratedValue]
+    #[ORM\Column]
+    protected ?int $id = null;
+
+    #[ORM\Column(type: Types::DATETIME_MUTABLE)]
+    protected \DateTime $createdAt;
+}
+
+#[ORM\Entity(repositoryClass: PostRepository::class)]
+#[ORM\Table(name: 'blog_posts')]
+class Post extends Timestamped {
+    #[ORM\Column(length: 180, unique: true)]
+    private string $slug;
+
+    #[ORM\Column(name: 'body_text', type: 'text', nullable: true)]
+    private ?string $body = null;
+
+    #[ORM\ManyToOne(targetEntity: User::class, inversedBy: 'posts')]
+    private User $author;
+
+    #[ORM\OneToMany(targetEntity: Comment::class, mappedBy: 'post')]
+    private Collection $comments;
+
+    private string $transient;
+}
+
+#[ORM\Entity]
+class Comment {
+    public function __construct(
+        #[ORM\Id, ORM\Column] private int $id,
+        #[ORM\ManyToOne(inversedBy: 'comments')]
+        #[ORM\JoinColumn(name: 'post_ref')]
+        private Post $post,
+    ) {}
+}
+",
+        );
+
+        let map = build_entity_map(&metadata);
+        assert_eq!(map.entities.len(), 2);
+
+        let post = &map.entities["App\\Entity\\Post"];
+        assert_eq!(post.table, "blog_posts");
+        assert_eq!(
+            post.repository.as_deref(),
+            Some("App\\Repository\\PostRepository")
+        );
+
+        let columns: Vec<_> = post
+            .columns
+            .iter()
+            .map(|c| {
+                (
+                    c.column.as_str(),
+                    c.column_type.as_deref(),
+                    c.nullable,
+                    c.id,
+                )
+            })
+            .collect();
+        assert_eq!(
+            columns,
+            [
+                ("id", Some("integer"), false, true),
+                ("createdAt", Some("datetime"), false, false),
+                ("slug", Some("string"), false, false),
+                ("body_text", Some("text"), true, false),
+            ]
+        );
+        assert_eq!(post.columns[2].length, Some(180));
+        assert!(post.columns[2].unique);
+
+        assert_eq!(post.associations.len(), 2);
+        assert_eq!(
+            post.associations[0].target.as_deref(),
+            Some("App\\Entity\\User")
+        );
+        assert_eq!(
+            post.associations[0].join_column.as_deref(),
+            Some("author_id")
+        );
+        assert_eq!(post.associations[1].mapped_by.as_deref(), Some("post"));
+        assert_eq!(post.associations[1].join_column, None);
+
+        let comment = &map.entities["App\\Entity\\Comment"];
+        assert_eq!(comment.table, "Comment");
+        assert_eq!(comment.repository, None);
+        assert_eq!(comment.columns[0].column, "id");
+        assert_eq!(
+            comment.associations[0].target.as_deref(),
+            Some("App\\Entity\\Post")
+        );
+        assert_eq!(
+            comment.associations[0].join_column.as_deref(),
+            Some("post_ref")
+        );
+    }
+}
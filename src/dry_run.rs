@@ -0,0 +1,183 @@
+//! Pending cache changes for `discovery:scan --dry-run`
+//!
+//! The scanned metadata is rendered exactly as the writer would store it and
+//! read back through [`crate::reader`], so it compares like for like with
+//! the cache already on disk (including `path_root`-relative paths). Classes
+//! are matched by FQCN; one counts as updated when any of its stored
+//! metadata differs.
+
+use crate::metadata::PhpClassMetadata;
+use crate::writer::CacheLayout;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Classes a scan would add to, remove from or update in the cache
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct CacheDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub updated: Vec<String>,
+}
+
+impl CacheDiff {
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.updated.is_empty()
+    }
+}
+
+/// Diff `metadata` against the cache at `output`, without writing anything
+///
+/// A missing cache counts as empty, so every class is reported as added.
+///
+/// # Errors
+///
+/// Returns an error if the metadata cannot be rendered or the existing cache
+/// cannot be read.
+pub fn diff_cache(
+    metadata: &[PhpClassMetadata], output: &Path, format: &str, pretty: bool,
+    path_root: Option<&Path>, layout: CacheLayout,
+) -> Result<CacheDiff> {
+    let rendered =
+        crate::writer::render_cache_for(metadata, output, format, pretty, path_root, layout)?;
+    let cache_dir = std::path::absolute(output)?
+        .parent()
+        .map_or_else(PathBuf::new, Path::to_path_buf);
+    let pending = crate::reader::parse_cache(&String::from_utf8_lossy(&rendered), &cache_dir)
+        .context("Failed to read back the rendered cache")?;
+    let current = if output.exists() {
+        crate::reader::read_cache(output)?
+    } else {
+        Vec::new()
+    };
+
+    Ok(diff_classes(&current, &pending))
+}
+
+/// Compare two class lists by FQCN; results are sorted
+#[must_use]
+pub fn diff_classes(current: &[PhpClassMetadata], pending: &[PhpClassMetadata]) -> CacheDiff {
+    let mut previous: HashMap<&str, &PhpClassMetadata> = current
+        .iter()
+        .map(|class| (class.fqcn.as_str(), class))
+        .collect();
+    let mut diff = CacheDiff::default();
+
+    for class in pending {
+        match previous.remove(class.fqcn.as_str()) {
+            None => diff.added.push(class.fqcn.clone()),
+            Some(old) if old != class => diff.updated.push(class.fqcn.clone()),
+            Some(_) => {},
+        }
+    }
+    diff.removed = previous.into_keys().map(str::to_string).collect();
+
+    diff.added.sort();
+    diff.removed.sort();
+    diff.updated.sort();
+    diff
+}
+
+/// Text report: `+`, `-` and `~` lines, then a summary line
+#[must_use]
+pub fn format_diff(diff: &CacheDiff) -> String {
+    let mut report = String::new();
+    for (sign, classes) in [
+        ("+", &diff.added),
+        ("-", &diff.removed),
+        ("~", &diff.updated),
+    ] {
+        for fqcn in classes {
+            let _ = writeln!(report, "  {sign} {fqcn}");
+        }
+    }
+    let _ = writeln!(
+        report,
+        "Dry run: {} added, {} removed, {} updated (cache and manifest not written)",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.updated.len()
+    );
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use tempfile::TempDir;
+
+    fn class(fqcn: &str, kind: &str) -> PhpClassMetadata {
+        PhpClassMetadata::new(
+            fqcn.to_string(),
+            PathBuf::from(format!("/src/{}.php", fqcn.trim_start_matches('\\'))),
+            kind.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_diff_classes() {
+        let current = [
+            class("\\App\\Kept", "class"),
+            class("\\App\\Gone", "class"),
+            class("\\App\\Changed", "class"),
+        ];
+        let pending = [
+            class("\\App\\Kept", "class"),
+            class("\\App\\Changed", "interface"),
+            class("\\App\\New", "class"),
+        ];
+
+        let diff = diff_classes(&current, &pending);
+
+        assert_eq!(diff.added, ["\\App\\New"]);
+        assert_eq!(diff.removed, ["\\App\\Gone"]);
+        assert_eq!(diff.updated, ["\\App\\Changed"]);
+        assert!(format_diff(&diff).ends_with(
+            "Dry run: 1 added, 1 removed, 1 updated (cache and manifest not written)\n"
+        ));
+    }
+
+    #[test]
+    fn test_diff_cache_matches_written_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("cache.php");
+        let metadata = [class("\\App\\User", "class")];
+        crate::writer::write_cache_if_changed(
+            &metadata,
+            &output,
+            "php",
+            false,
+            Some(Path::new("/")),
+            CacheLayout::default(),
+        )
+        .unwrap();
+
+        let unchanged = diff_cache(
+            &metadata,
+            &output,
+            "php",
+            false,
+            Some(Path::new("/")),
+            CacheLayout::default(),
+        )
+        .unwrap();
+        let missing = diff_cache(
+            &metadata,
+            &temp_dir.path().join("none.php"),
+            "php",
+            false,
+            None,
+            CacheLayout::default(),
+        )
+        .unwrap();
+
+        assert!(unchanged.is_empty());
+        assert_eq!(missing.added, ["\\App\\User"]);
+        assert!(!temp_dir.path().join("none.php").exists());
+    }
+}
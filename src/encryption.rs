@@ -0,0 +1,274 @@
+//! Cache encryption at rest (`encryption`)
+//!
+//! Route and entity maps describe an application's attack surface, and some
+//! deployments must not leave them readable on disk. With a key configured,
+//! the JSON cache, the manifest and daemon state snapshots are written
+//! encrypted with AES-256-GCM, and everything that reads them back (the
+//! reader, manifest loading, a `--read-only` daemon) decrypts them
+//! transparently. Every other output (exports, derived outputs, artifacts,
+//! the journal, the spill file) and IPC responses stay plaintext.
+//!
+//! An encrypted file is `AXE1`, the 12-byte nonce, the ciphertext and the
+//! 16-byte tag, which PHP opens with `openssl_decrypt(..., 'aes-256-gcm',
+//! ...)`. Every write uses a fresh nonce, so unchanged content is detected
+//! on the plaintext.
+
+use crate::error::{AurynxError, Result};
+use serde::Deserialize;
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::sync::{PoisonError, RwLock};
+
+/// Leading bytes of an encrypted file
+pub const MAGIC: &[u8] = b"AXE1";
+
+/// AES-256 key
+pub type Key = [u8; 32];
+
+#[cfg(feature = "encryption")]
+const NONCE_LEN: usize = 12;
+
+static KEY: RwLock<Option<Key>> = RwLock::new(None);
+
+thread_local! {
+    /// Key of the workspace project scanned on this thread, overriding `KEY`
+    static PROJECT_KEY: Cell<Option<ProjectKey>> = const { Cell::new(None) };
+}
+
+/// Key of a [`with_key`] scope (`None`: no encryption)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ProjectKey(Option<Key>);
+
+/// Where the key comes from: exactly one of `key_env` and `key_file`
+///
+/// The key is 64 hex digits, e.g. from `openssl rand -hex 32`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EncryptionConfig {
+    /// Environment variable holding the key
+    pub key_env: Option<String>,
+    /// File holding the key (surrounding whitespace is ignored)
+    pub key_file: Option<PathBuf>,
+}
+
+impl EncryptionConfig {
+    /// Read and decode the configured key
+    ///
+    /// # Errors
+    ///
+    /// Returns a config error if not exactly one source is configured, the
+    /// source cannot be read, or it does not hold 64 hex digits.
+    pub fn load_key(&self) -> Result<Key> {
+        let (hex, source) = match (&self.key_env, &self.key_file) {
+            (Some(var), None) => (
+                std::env::var(var).map_err(|_| {
+                    AurynxError::config_error(format!("Encryption key variable {var} is not set"))
+                })?,
+                format!("variable {var}"),
+            ),
+            (None, Some(file)) => (
+                std::fs::read_to_string(file).map_err(|e| {
+                    AurynxError::io_error(
+                        format!("Failed to read encryption key file {}", file.display()),
+                        e,
+                    )
+                })?,
+                format!("file {}", file.display()),
+            ),
+            _ => {
+                return Err(AurynxError::config_error(
+                    "encryption needs exactly one of 'key_env' and 'key_file'",
+                ));
+            },
+        };
+        parse_key(hex.trim()).ok_or_else(|| {
+            AurynxError::config_error(format!(
+                "Encryption key in {source} must be 64 hex digits (32 bytes)"
+            ))
+        })
+    }
+}
+
+/// Encrypt cache files with `key` for the rest of the process (`None` disables)
+pub fn set_key(key: Option<Key>) {
+    *KEY.write().unwrap_or_else(PoisonError::into_inner) = key;
+}
+
+/// Whether cache files are written encrypted
+#[must_use]
+pub fn is_enabled() -> bool {
+    current_key().is_some()
+}
+
+/// Run `f` with `key` (`None`: no encryption) instead of the process-wide
+/// key on the calling thread
+///
+/// Workspace projects are scanned side by side, each with its own key. Only
+/// the calling thread sees `key`, so `f` reads and writes the project's cache
+/// files itself; a scope nested in `f` (another project's scan stolen by this
+/// thread) uses its own key and restores this one.
+pub fn with_key<T>(key: Option<Key>, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<ProjectKey>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            PROJECT_KEY.set(self.0);
+        }
+    }
+
+    let _restore = Restore(PROJECT_KEY.replace(Some(ProjectKey(key))));
+    f()
+}
+
+fn current_key() -> Option<Key> {
+    PROJECT_KEY.get().map_or_else(
+        || *KEY.read().unwrap_or_else(PoisonError::into_inner),
+        |project| project.0,
+    )
+}
+
+/// Encrypt `plain` with the configured key (unchanged without one)
+///
+/// # Errors
+///
+/// Returns an error if encryption fails or is not compiled in.
+pub fn seal(plain: &[u8]) -> anyhow::Result<Vec<u8>> {
+    current_key().map_or_else(|| Ok(plain.to_vec()), |key| seal_with(&key, plain))
+}
+
+/// Decrypt a file read from disk (unchanged unless it is encrypted)
+///
+/// # Errors
+///
+/// Returns an error if the data is encrypted but no key is configured, or
+/// the key does not match.
+pub fn open(data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    open_with(current_key().as_ref(), data)
+}
+
+/// Atomically write `content` encrypted, unless `path` already holds it
+///
+/// Returns `true` if the file was written.
+///
+/// # Errors
+///
+/// Returns an error if encryption fails or the file cannot be written.
+pub fn write_sealed_if_changed(path: &Path, content: &[u8]) -> anyhow::Result<bool> {
+    let unchanged = std::fs::read(path)
+        .ok()
+        .and_then(|existing| open(existing).ok())
+        .is_some_and(|existing| existing == content);
+    if unchanged {
+        return Ok(false);
+    }
+    crate::writer::write_if_changed(path, &seal(content)?)
+}
+
+fn parse_key(hex: &str) -> Option<Key> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut key = [0u8; 32];
+    for (byte, pair) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(key)
+}
+
+#[cfg(feature = "encryption")]
+fn seal_with(key: &Key, plain: &[u8]) -> anyhow::Result<Vec<u8>> {
+    use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+    use aes_gcm::Aes256Gcm;
+
+    let cipher = Aes256Gcm::new(key.into());
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plain)
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt cache"))?;
+
+    let mut sealed = Vec::with_capacity(MAGIC.len() + nonce.len() + ciphertext.len());
+    sealed.extend_from_slice(MAGIC);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+#[cfg(feature = "encryption")]
+fn open_with(key: Option<&Key>, data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    use aes_gcm::aead::{Aead, KeyInit};
+    use aes_gcm::{Aes256Gcm, Nonce};
+
+    let Some(sealed) = data.strip_prefix(MAGIC) else {
+        return Ok(data);
+    };
+    let Some(key) = key else {
+        anyhow::bail!("File is encrypted, but no encryption key is configured");
+    };
+    if sealed.len() < NONCE_LEN {
+        anyhow::bail!("Encrypted file is truncated");
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    Aes256Gcm::new(key.into())
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow::anyhow!("Failed to decrypt file: wrong key or corrupted data"))
+}
+
+#[cfg(not(feature = "encryption"))]
+fn seal_with(_key: &Key, _plain: &[u8]) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("Cache encryption is not supported by this build")
+}
+
+#[cfg(not(feature = "encryption"))]
+fn open_with(_key: Option<&Key>, data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    if data.starts_with(MAGIC) {
+        anyhow::bail!("File is encrypted, but this build does not support encryption");
+    }
+    Ok(data)
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_sealed_data_opens_with_its_key_only() {
+        let key = parse_key(&"0f".repeat(32)).unwrap();
+        let plain = br#"[{"fqcn":"\\App\\User"}]"#;
+
+        let sealed = seal_with(&key, plain).unwrap();
+        assert!(sealed.starts_with(MAGIC));
+        assert_ne!(seal_with(&key, plain).unwrap(), sealed, "nonce is reused");
+        assert_eq!(open_with(Some(&key), sealed.clone()).unwrap(), plain);
+
+        let other = parse_key(&"a0".repeat(32)).unwrap();
+        assert!(open_with(Some(&other), sealed.clone()).is_err());
+        assert!(open_with(None, sealed).is_err());
+        // Plaintext files are read as they are
+        assert_eq!(open_with(None, plain.to_vec()).unwrap(), plain);
+    }
+
+    #[test]
+    fn test_project_key_is_restored_after_its_scope() {
+        let key = parse_key(&"0f".repeat(32)).unwrap();
+
+        with_key(Some(key), || {
+            assert_eq!(current_key(), Some(key));
+            with_key(None, || assert_eq!(current_key(), None));
+            assert_eq!(current_key(), Some(key));
+        });
+
+        assert_eq!(PROJECT_KEY.get(), None);
+    }
+
+    #[test]
+    fn test_key_must_be_64_hex_digits() {
+        assert!(parse_key(&"ab".repeat(32)).is_some());
+        assert!(parse_key("abcd").is_none());
+        assert!(parse_key(&"zz".repeat(32)).is_none());
+
+        let config = EncryptionConfig {
+            key_env: Some("AURYNX_TEST_UNSET_KEY".to_string()),
+            key_file: Some(PathBuf::from("/nonexistent")),
+        };
+        assert!(config.load_key().is_err());
+    }
+}
@@ -2,6 +2,51 @@ use std::fmt;
 use std::io;
 use std::path::PathBuf;
 
+/// Stable error codes
+///
+/// Surfaced in logs (`code` field), IPC `ERROR:` lines, `getErrors` and the
+/// manifest so wrapping tools can branch without matching messages. Codes are
+/// never reused or renumbered; messages may change.
+///
+/// | range  | area                        |
+/// |--------|-----------------------------|
+/// | AX1xxx | configuration               |
+/// | AX2xxx | parsing and scanned files   |
+/// | AX3xxx | IO and serialization        |
+/// | AX4xxx | daemon and IPC              |
+/// | AX5xxx | file watching               |
+/// | AX9xxx | uncategorized               |
+pub mod code {
+    /// Invalid or missing configuration
+    pub const CONFIG: &str = "AX1001";
+    /// Tree-sitter language setup or parse failure
+    pub const TREE_SITTER: &str = "AX2001";
+    /// File exceeds `max_file_size_mb`
+    pub const FILE_SIZE_LIMIT: &str = "AX2002";
+    /// File could not be parsed
+    pub const PARSE: &str = "AX2003";
+    /// Syntax error (reported as a failure under `on_error: fail`)
+    pub const SYNTAX: &str = "AX2004";
+    /// File or socket IO failure
+    pub const IO: &str = "AX3001";
+    /// JSON serialization or deserialization failure
+    pub const JSON: &str = "AX3002";
+    /// Daemon lock could not be acquired
+    pub const LOCK: &str = "AX4001";
+    /// Another daemon is running for the same cache
+    pub const DAEMON_RUNNING: &str = "AX4002";
+    /// Malformed, oversized or unknown IPC request
+    pub const INVALID_REQUEST: &str = "AX4003";
+    /// Requested class (or other item) does not exist
+    pub const NOT_FOUND: &str = "AX4004";
+    /// Requested feature is not enabled in this daemon
+    pub const UNAVAILABLE: &str = "AX4005";
+    /// File watcher failure
+    pub const WATCHER: &str = "AX5001";
+    /// Anything not covered above
+    pub const OTHER: &str = "AX9001";
+}
+
 /// Custom error type for Aurynx library operations
 #[derive(Debug)]
 pub enum AurynxError {
@@ -30,6 +75,9 @@ pub enum AurynxError {
     /// Invalid IPC request
     InvalidRequest { message: String },
 
+    /// Requested item does not exist (e.g. `getClass` of an unknown class)
+    NotFound { message: String },
+
     /// JSON serialization/deserialization errors
     Json {
         context: String,
@@ -40,6 +88,7 @@ pub enum AurynxError {
     TreeSitter { message: String },
 
     /// Watcher errors (notify library)
+    #[cfg(feature = "watch")]
     Watcher {
         context: String,
         source: notify::Error,
@@ -95,10 +144,11 @@ impl fmt::Display for AurynxError {
             Self::TreeSitter { message } => {
                 write!(f, "Tree-sitter error: {message}")
             }
+            #[cfg(feature = "watch")]
             Self::Watcher { context, source } => {
                 write!(f, "File watcher error in {context}: {source}")
             }
-            Self::Other { message } => {
+            Self::NotFound { message } | Self::Other { message } => {
                 write!(f, "{message}")
             }
         }
@@ -110,6 +160,7 @@ impl std::error::Error for AurynxError {
         match self {
             Self::Io { source, .. } => Some(source),
             Self::Json { source, .. } => Some(source),
+            #[cfg(feature = "watch")]
             Self::Watcher { source, .. } => Some(source),
             _ => None,
         }
@@ -135,6 +186,7 @@ impl From<serde_json::Error> for AurynxError {
     }
 }
 
+#[cfg(feature = "watch")]
 impl From<notify::Error> for AurynxError {
     fn from(err: notify::Error) -> Self {
         Self::Watcher {
@@ -154,6 +206,50 @@ impl From<anyhow::Error> for AurynxError {
 
 // Helper methods for creating errors with context
 impl AurynxError {
+    /// Stable code of this error (see [`code`])
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::Io { .. } => code::IO,
+            Self::Config { .. } => code::CONFIG,
+            Self::Parse { .. } => code::PARSE,
+            Self::FileSizeLimit { .. } => code::FILE_SIZE_LIMIT,
+            Self::LockAcquisition { .. } => code::LOCK,
+            Self::DaemonAlreadyRunning { .. } => code::DAEMON_RUNNING,
+            Self::InvalidRequest { .. } => code::INVALID_REQUEST,
+            Self::NotFound { .. } => code::NOT_FOUND,
+            Self::Json { .. } => code::JSON,
+            Self::TreeSitter { .. } => code::TREE_SITTER,
+            #[cfg(feature = "watch")]
+            Self::Watcher { .. } => code::WATCHER,
+            Self::Other { .. } => code::OTHER,
+        }
+    }
+
+    /// Exit status of a daemon stopped by this error (`--foreground`)
+    ///
+    /// Follows `sysexits.h`, so process managers can tell a lock conflict,
+    /// which a restart does not fix, from a watch failure or fatal IO:
+    ///
+    /// | status | cause                                       |
+    /// |--------|---------------------------------------------|
+    /// | 71     | file watcher failure (`EX_OSERR`)           |
+    /// | 74     | IO or serialization (`EX_IOERR`)            |
+    /// | 75     | lock held by another daemon (`EX_TEMPFAIL`) |
+    /// | 78     | configuration (`EX_CONFIG`)                 |
+    /// | 1      | anything else                               |
+    #[must_use]
+    pub const fn exit_status(&self) -> i32 {
+        match self {
+            #[cfg(feature = "watch")]
+            Self::Watcher { .. } => 71,
+            Self::Io { .. } | Self::Json { .. } => 74,
+            Self::LockAcquisition { .. } | Self::DaemonAlreadyRunning { .. } => 75,
+            Self::Config { .. } => 78,
+            _ => 1,
+        }
+    }
+
     pub fn io_error(context: impl Into<String>, source: io::Error) -> Self {
         Self::Io {
             context: context.into(),
@@ -197,6 +293,12 @@ impl AurynxError {
         }
     }
 
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::NotFound {
+            message: message.into(),
+        }
+    }
+
     pub fn json_error(context: impl Into<String>, source: serde_json::Error) -> Self {
         Self::Json {
             context: context.into(),
@@ -210,6 +312,7 @@ impl AurynxError {
         }
     }
 
+    #[cfg(feature = "watch")]
     pub fn watcher_error(context: impl Into<String>, source: notify::Error) -> Self {
         Self::Watcher {
             context: context.into(),
@@ -273,4 +376,15 @@ mod tests {
         );
         assert!(matches!(err, AurynxError::LockAcquisition { .. }));
     }
+
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(AurynxError::config_error("bad").code(), "AX1001");
+        assert_eq!(
+            AurynxError::parse_error(PathBuf::from("test.php"), "bad").code(),
+            "AX2003"
+        );
+        assert_eq!(AurynxError::from(io::Error::other("bad")).code(), "AX3001");
+        assert_eq!(AurynxError::not_found("Class not found").code(), "AX4004");
+    }
 }
@@ -0,0 +1,226 @@
+//! External exporter commands (`exporters`)
+//!
+//! Artifacts the built-in exporters and `artifacts` rules cannot express
+//! can be produced by any program. After a one-shot scan has written the
+//! cache, each configured command runs in order with the class metadata on
+//! stdin, as the JSON cache holds it, and `AURYNX_CACHE_FILE` in its
+//! environment. With `output` set, what it prints on stdout becomes that
+//! file (left untouched if the command fails); otherwise its stdout is
+//! ignored and the command writes whatever it needs itself. Commands run in
+//! the current directory, or in the project's directory for workspace scans.
+//!
+//! A failing exporter does not fail the scan: each outcome is printed with
+//! the scan summary and recorded in the scan report.
+
+use crate::metadata::PhpClassMetadata;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// One entry of the `exporters` config list
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExporterConfig {
+    /// Name shown in the summary and the scan report
+    pub name: String,
+    /// Command and arguments, run with the metadata on stdin
+    pub exec: Vec<String>,
+    /// File receiving the command's stdout
+    pub output: Option<PathBuf>,
+    /// Command timeout in milliseconds (default: 60000)
+    pub timeout_ms: Option<u64>,
+}
+
+/// Outcome of one exporter
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExporterOutcome {
+    pub name: String,
+    /// `ok` or `failed`
+    pub status: &'static str,
+    pub output: Option<PathBuf>,
+    pub duration_ms: u64,
+    /// Why the exporter failed (exit status and the end of its stderr)
+    pub error: Option<String>,
+}
+
+impl ExporterConfig {
+    /// Check the exporter before any scanning happens
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first invalid field.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("exporter name must not be empty".to_string());
+        }
+        if self.exec.is_empty() {
+            return Err(format!("exporter '{}' needs a non-empty 'exec'", self.name));
+        }
+        Ok(())
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.unwrap_or(60_000))
+    }
+
+    fn run(
+        &self, payload: &[u8], cache: &Path, dir: Option<&Path>,
+    ) -> std::result::Result<(), String> {
+        let stdout = run_command(&self.exec, payload, cache, dir, self.timeout())?;
+        if let Some(output) = &self.output {
+            crate::writer::write_if_changed(output, &stdout)
+                .map_err(|e| format!("Failed to write {}: {e:#}", output.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Run `exporters` in order on `metadata`, the content of `cache`, with
+/// `dir` as their working directory (`None`: the current one)
+#[must_use]
+pub fn run_exporters(
+    metadata: &[PhpClassMetadata], exporters: &[ExporterConfig], cache: &Path,
+    dir: Option<&Path>,
+) -> Vec<ExporterOutcome> {
+    if exporters.is_empty() {
+        return Vec::new();
+    }
+    // Serializing metadata into memory cannot fail
+    let payload = serde_json::to_vec(metadata).unwrap_or_default();
+
+    exporters
+        .iter()
+        .map(|exporter| {
+            let started = Instant::now();
+            let result = exporter.run(&payload, cache, dir);
+            ExporterOutcome {
+                name: exporter.name.clone(),
+                status: if result.is_ok() { "ok" } else { "failed" },
+                output: exporter.output.clone(),
+                duration_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+                error: result.err(),
+            }
+        })
+        .collect()
+}
+
+/// Run `command` with `payload` on stdin and return its stdout
+///
+/// Stdin and the output pipes are served by threads, so a command that
+/// prints before it has read all of its input cannot deadlock.
+fn run_command(
+    command: &[String], payload: &[u8], cache: &Path, dir: Option<&Path>, timeout: Duration,
+) -> std::result::Result<Vec<u8>, String> {
+    let [program, args @ ..] = command else {
+        return Err("Empty command".to_string());
+    };
+    let mut command = Command::new(program);
+    if let Some(dir) = dir.filter(|dir| !dir.as_os_str().is_empty()) {
+        command.current_dir(dir);
+    }
+    let mut child = command
+        .args(args)
+        .env("AURYNX_CACHE_FILE", cache)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start {program}: {e}"))?;
+
+    let stdin = child.stdin.take();
+    let payload = payload.to_vec();
+    // A command that ignores stdin closes the pipe early; that is not an error
+    let writer = std::thread::spawn(move || {
+        if let Some(mut stdin) = stdin {
+            let _ = stdin.write_all(&payload);
+        }
+    });
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let status = wait(&mut child, timeout)?;
+    let _ = writer.join();
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    if status.success() {
+        return Ok(stdout);
+    }
+    let stderr = String::from_utf8_lossy(&stderr);
+    Err(stderr.trim().lines().last().map_or_else(
+        || format!("Command exited with {status}"),
+        |line| format!("Command exited with {status}: {line}"),
+    ))
+}
+
+fn read_in_background(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+fn wait(
+    child: &mut std::process::Child, timeout: Duration,
+) -> std::result::Result<ExitStatus, String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => return Ok(status),
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("Command timed out after {}ms", timeout.as_millis()));
+            },
+            None => std::thread::sleep(Duration::from_millis(10)),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::parser::PhpMetadataExtractor;
+    use tempfile::TempDir;
+
+    fn exporter(name: &str, script: &str, output: Option<PathBuf>) -> ExporterConfig {
+        ExporterConfig {
+            name: name.to_string(),
+            exec: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+            output,
+            timeout_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_exporters_receive_metadata_and_report_failures() {
+        let temp_dir = TempDir::new().unwrap();
+        let metadata = PhpMetadataExtractor::new()
+            .unwrap()
+            .extract_metadata("<?php namespace App; class User {}", PathBuf::from("User.php"))
+            .unwrap();
+        let output = temp_dir.path().join("copy.json");
+        let exporters = [
+            exporter("copy", "cat", Some(output.clone())),
+            exporter("broken", "echo 'no such template' >&2; exit 3", None),
+        ];
+
+        let outcomes = run_exporters(&metadata, &exporters, Path::new("cache.json"), None);
+
+        assert_eq!(outcomes[0].status, "ok");
+        let copied: Vec<PhpClassMetadata> =
+            serde_json::from_slice(&std::fs::read(&output).unwrap()).unwrap();
+        assert_eq!(copied, metadata);
+        assert_eq!(outcomes[1].status, "failed");
+        assert_eq!(
+            outcomes[1].error.as_deref(),
+            Some("Command exited with exit status: 3: no such template")
+        );
+    }
+}
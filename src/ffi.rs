@@ -0,0 +1,360 @@
+#![allow(unsafe_code)]
+
+//! C ABI for loading the scanner into PHP through `FFI`
+//!
+//! Built as a shared library with
+//! `cargo rustc --release --lib --crate-type cdylib --no-default-features --features ffi`,
+//! for environments where neither the daemon nor the binary may be spawned.
+//! Strings cross the boundary as NUL-terminated UTF-8 JSON; every string
+//! returned by this module is owned by the caller and must be released with
+//! [`aurynx_free`]. `include/aurynx.h` declares the functions for
+//! `FFI::load()`.
+//!
+//! [`aurynx_scan`] keeps the classes of the last successful scan so that
+//! [`aurynx_get_class`] can look them up without another scan.
+//! [`aurynx_extract`] parses a single source string without touching the
+//! filesystem; it is what the WebAssembly build (see [`crate::wasm`]) is for.
+
+use crate::discovery::{Discovery, DiscoveryReport};
+use crate::error::{AurynxError, Result};
+use crate::metadata::PhpClassMetadata;
+use crate::parser::PhpMetadataExtractor;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+
+/// Classes of the last successful [`aurynx_scan`], by FQCN
+static LAST_SCAN: Mutex<Option<HashMap<String, PhpClassMetadata>>> = Mutex::new(None);
+
+/// A C string argument: `None` for NULL, an error if it is not UTF-8
+type Arg<'a> = Option<std::result::Result<&'a str, std::str::Utf8Error>>;
+
+/// Options accepted by [`aurynx_scan`] as a JSON object
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ScanOptions {
+    ignore: Vec<String>,
+    max_file_size_mb: Option<u64>,
+    resolve_hierarchy: bool,
+    inherit_attributes: bool,
+}
+
+#[derive(Serialize)]
+struct ScanResponse<'a> {
+    classes: &'a [PhpClassMetadata],
+    files: usize,
+    failed: Vec<FailedEntry>,
+    duration_ms: u64,
+}
+
+#[derive(Serialize)]
+struct FailedEntry {
+    path: PathBuf,
+    code: &'static str,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct ExtractResponse {
+    classes: Vec<PhpClassMetadata>,
+    syntax_error: Option<SyntaxError>,
+}
+
+#[derive(Serialize)]
+struct SyntaxError {
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: ErrorBody,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    code: &'static str,
+    message: String,
+}
+
+/// Scan `paths` (a JSON array of paths) with `options` (a JSON object or NULL)
+///
+/// Returns `{"classes": [...], "files": n, "failed": [{"path", "code",
+/// "message"}], "duration_ms": n}`, or `{"error": {"code", "message"}}` when
+/// the arguments are invalid or the scan cannot start. Never returns NULL.
+///
+/// # Safety
+///
+/// `paths` must point to a NUL-terminated string; `options` must be NULL or
+/// point to one. The result must be released with [`aurynx_free`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aurynx_scan(paths: *const c_char, options: *const c_char) -> *mut c_char {
+    // SAFETY: upheld by the caller as documented above
+    let (paths, options) = unsafe { (read_arg(paths), read_arg(options)) };
+
+    let response = catch_unwind(AssertUnwindSafe(|| scan(paths, options)))
+        .unwrap_or_else(|_| Err(AurynxError::other("Scan panicked")))
+        .unwrap_or_else(|e| error_json(&e));
+    into_c_string(response)
+}
+
+/// Look up `fqcn` in the classes of the last successful [`aurynx_scan`]
+///
+/// Returns the class as a JSON object, or NULL if it was not found or no
+/// scan has run yet. The leading backslash of `fqcn` is optional.
+///
+/// # Safety
+///
+/// `fqcn` must be NULL or point to a NUL-terminated string. A non-NULL
+/// result must be released with [`aurynx_free`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aurynx_get_class(fqcn: *const c_char) -> *mut c_char {
+    // SAFETY: upheld by the caller as documented above
+    let Some(Ok(fqcn)) = (unsafe { read_arg(fqcn) }) else {
+        return std::ptr::null_mut();
+    };
+
+    let json = LAST_SCAN
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .and_then(|classes| classes.get(fqcn.trim_start_matches('\\')))
+        .and_then(|class| serde_json::to_string(class).ok());
+    json.map_or(std::ptr::null_mut(), into_c_string)
+}
+
+/// Extract the declarations of one PHP source string
+///
+/// `path` is recorded as the classes' `file` (NULL for none). Returns
+/// `{"classes": [...], "syntax_error": {"line", "column", "message"} | null}`;
+/// as in a scan, the declarations recovered around a syntax error are kept.
+/// Returns `{"error": {"code", "message"}}` if the source cannot be parsed.
+/// Never returns NULL.
+///
+/// # Safety
+///
+/// `source` must point to a NUL-terminated string; `path` must be NULL or
+/// point to one. The result must be released with [`aurynx_free`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aurynx_extract(source: *const c_char, path: *const c_char) -> *mut c_char {
+    // SAFETY: upheld by the caller as documented above
+    let (source, path) = unsafe { (read_arg(source), read_arg(path)) };
+
+    let response = catch_unwind(AssertUnwindSafe(|| extract(source, path)))
+        .unwrap_or_else(|_| Err(AurynxError::other("Extraction panicked")))
+        .unwrap_or_else(|e| error_json(&e));
+    into_c_string(response)
+}
+
+/// Release a string returned by this library
+///
+/// # Safety
+///
+/// `s` must be NULL or a pointer returned by [`aurynx_scan`] or
+/// [`aurynx_get_class`] that has not been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aurynx_free(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: `s` came from `CString::into_raw` in `into_c_string`
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// Borrow a C string argument; `None` for NULL
+///
+/// # Safety
+///
+/// `arg` must be NULL or point to a NUL-terminated string that outlives the
+/// returned reference.
+const unsafe fn read_arg<'a>(arg: *const c_char) -> Arg<'a> {
+    if arg.is_null() {
+        return None;
+    }
+    // SAFETY: non-NULL and NUL-terminated per the caller's contract
+    Some(unsafe { CStr::from_ptr(arg) }.to_str())
+}
+
+fn scan(paths: Arg<'_>, options: Arg<'_>) -> Result<String> {
+    let paths: Vec<PathBuf> = parse_arg(paths, "paths")?
+        .ok_or_else(|| AurynxError::invalid_request_error("paths must not be NULL"))?;
+    let options: ScanOptions = parse_arg(options, "options")?.unwrap_or_default();
+
+    let mut builder = Discovery::builder()
+        .paths(paths)
+        .ignore(options.ignore)
+        .resolve_hierarchy(options.resolve_hierarchy)
+        .inherit_attributes(options.inherit_attributes);
+    if let Some(mb) = options.max_file_size_mb {
+        builder = builder.max_file_size(mb.saturating_mul(1024 * 1024));
+    }
+
+    let report = builder.scan()?;
+    let json = scan_response(&report)?;
+    let classes = report
+        .classes
+        .into_iter()
+        .map(|class| (class.fqcn.trim_start_matches('\\').to_string(), class))
+        .collect();
+    *LAST_SCAN.lock().unwrap_or_else(PoisonError::into_inner) = Some(classes);
+
+    Ok(json)
+}
+
+fn extract(source: Arg<'_>, path: Arg<'_>) -> Result<String> {
+    let source = text_arg(source, "source")?
+        .ok_or_else(|| AurynxError::invalid_request_error("source must not be NULL"))?;
+    let path = text_arg(path, "path")?.map_or_else(PathBuf::new, PathBuf::from);
+
+    let (classes, diagnostic) =
+        PhpMetadataExtractor::new()?.extract_metadata_with_diagnostic(source, path)?;
+    let response = ExtractResponse {
+        classes,
+        syntax_error: diagnostic.map(|diagnostic| SyntaxError {
+            line: diagnostic.line,
+            column: diagnostic.column,
+            message: diagnostic.message,
+        }),
+    };
+    serde_json::to_string(&response).map_err(|e| AurynxError::json_error("extract response", e))
+}
+
+/// Validate a string argument; `None` for NULL
+fn text_arg<'a>(arg: Arg<'a>, what: &str) -> Result<Option<&'a str>> {
+    arg.transpose()
+        .map_err(|e| AurynxError::invalid_request_error(format!("Invalid {what}: {e}")))
+}
+
+/// Deserialize a JSON argument; `None` for NULL
+fn parse_arg<T: DeserializeOwned>(arg: Arg<'_>, what: &str) -> Result<Option<T>> {
+    text_arg(arg, what)?
+        .map(|json| {
+            serde_json::from_str(json)
+                .map_err(|e| AurynxError::invalid_request_error(format!("Invalid {what}: {e}")))
+        })
+        .transpose()
+}
+
+fn scan_response(report: &DiscoveryReport) -> Result<String> {
+    let response = ScanResponse {
+        classes: &report.classes,
+        files: report.files,
+        failed: report
+            .failed
+            .iter()
+            .map(|failed| FailedEntry {
+                path: failed.path.clone(),
+                code: failed.failure.code,
+                message: failed.failure.message.clone(),
+            })
+            .collect(),
+        duration_ms: u64::try_from(report.duration.as_millis()).unwrap_or(u64::MAX),
+    };
+    serde_json::to_string(&response).map_err(|e| AurynxError::json_error("scan response", e))
+}
+
+fn error_json(error: &AurynxError) -> String {
+    let response = ErrorResponse {
+        error: ErrorBody {
+            code: error.code(),
+            message: error.to_string(),
+        },
+    };
+    serde_json::to_string(&response).unwrap_or_else(|_| {
+        format!(
+            r#"{{"error":{{"code":"{}","message":"internal error"}}}}"#,
+            error.code()
+        )
+    })
+}
+
+/// Hand `s` to the caller; interior NULs (impossible in JSON output) are dropped
+fn into_c_string(s: String) -> *mut c_char {
+    let s = CString::new(s).unwrap_or_else(|e| {
+        let mut bytes = e.into_vec();
+        bytes.retain(|&b| b != 0);
+        CString::new(bytes).unwrap_or_default()
+    });
+    s.into_raw()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn call(f: impl FnOnce() -> *mut c_char) -> Option<serde_json::Value> {
+        let ptr = f();
+        if ptr.is_null() {
+            return None;
+        }
+        let json = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { aurynx_free(ptr) };
+        Some(serde_json::from_str(&json).unwrap())
+    }
+
+    #[test]
+    fn test_scan_and_get_class() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("User.php"),
+            "<?php namespace App; #[Entity] final class User {}",
+        )
+        .unwrap();
+        let paths = CString::new(serde_json::to_string(&[temp_dir.path()]).unwrap()).unwrap();
+        let options = CString::new(r#"{"resolve_hierarchy": true}"#).unwrap();
+
+        let report = call(|| unsafe { aurynx_scan(paths.as_ptr(), options.as_ptr()) }).unwrap();
+        assert_eq!(report["files"], 1);
+        assert_eq!(report["classes"][0]["fqcn"], "\\App\\User");
+
+        let fqcn = CString::new("App\\User").unwrap();
+        let class = call(|| unsafe { aurynx_get_class(fqcn.as_ptr()) }).unwrap();
+        assert_eq!(class["modifiers"]["is_final"], true);
+
+        let missing = CString::new("App\\Missing").unwrap();
+        assert!(call(|| unsafe { aurynx_get_class(missing.as_ptr()) }).is_none());
+    }
+
+    #[test]
+    fn test_extract_source() {
+        let source =
+            CString::new("<?php namespace App; enum Status: string { case Active = 'active'; }")
+                .unwrap();
+
+        let response =
+            call(|| unsafe { aurynx_extract(source.as_ptr(), std::ptr::null()) }).unwrap();
+
+        assert_eq!(response["classes"][0]["fqcn"], "\\App\\Status");
+        assert_eq!(response["classes"][0]["cases"][0]["value"], "active");
+        assert!(response["syntax_error"].is_null());
+
+        let broken = CString::new("<?php class Broken { public function ( }").unwrap();
+        let response =
+            call(|| unsafe { aurynx_extract(broken.as_ptr(), std::ptr::null()) }).unwrap();
+        assert!(response["syntax_error"]["line"].is_number());
+    }
+
+    #[test]
+    fn test_scan_reports_invalid_arguments() {
+        let paths = CString::new("not json").unwrap();
+
+        let response = call(|| unsafe { aurynx_scan(paths.as_ptr(), std::ptr::null()) }).unwrap();
+
+        assert_eq!(
+            response["error"]["code"],
+            crate::error::code::INVALID_REQUEST
+        );
+        assert!(
+            call(|| unsafe { aurynx_scan(std::ptr::null(), std::ptr::null()) }).unwrap()["error"]
+                .is_object()
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Language front-ends (`frontends`)
+//!
+//! A front-end is one kind of source file the scanner reads: the extensions
+//! it claims and how such a file turns into class declarations with their
+//! attributes. Directory walks, watchers and incremental scans ask the
+//! scan's [`Frontends`] whether a path is a source file instead of checking
+//! for `.php`, and the scanner hands each file to the parser of
+//! [`Frontends::for_path`], so another grammar only has to implement
+//! [`LanguageFrontend`] with its own [`FrontendParser`] and be listed in
+//! [`FRONTENDS`] (behind a cargo feature if it brings its own grammar crate).
+//!
+//! - `php`: `.php` files, always on
+//! - `phtml`: `.phtml` templates, PHP embedded in HTML, which occasionally
+//!   declare a class. Off unless listed in `frontends`, since template
+//!   directories are large and rarely declare anything.
+
+use crate::diagnostic::SyntaxDiagnostic;
+use crate::error::Result;
+use crate::metadata::PhpClassMetadata;
+use crate::parser::PhpMetadataExtractor;
+use crate::php_version::{NewerSyntax, PhpVersion};
+use crate::scanner::ScanOptions;
+use std::path::{Path, PathBuf};
+
+/// Declarations of a file, its first syntax error and the syntax newer than
+/// the targeted PHP version
+pub type Extraction = (Vec<PhpClassMetadata>, Option<SyntaxDiagnostic>, Vec<NewerSyntax>);
+
+/// One kind of source file
+pub trait LanguageFrontend: Sync {
+    /// Name used in the `frontends` config list
+    fn name(&self) -> &'static str;
+
+    /// Extensions of the files it reads, without the dot
+    fn extensions(&self) -> &'static [&'static str];
+
+    /// New parser for the files of a scan with `options`
+    ///
+    /// Each scanning thread creates its own and reuses it for every file of
+    /// this front-end it reads.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the grammar or its queries cannot be loaded.
+    fn parser(&self, options: &ScanOptions) -> Result<Box<dyn FrontendParser>>;
+}
+
+/// Parser state of a front-end, owned by one thread
+pub trait FrontendParser: Send {
+    /// Extract the declarations of `content`, read from `path`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source cannot be parsed at all.
+    fn extract(
+        &mut self, content: &str, path: PathBuf, target: Option<PhpVersion>,
+    ) -> Result<Extraction>;
+}
+
+impl FrontendParser for PhpMetadataExtractor {
+    fn extract(
+        &mut self, content: &str, path: PathBuf, target: Option<PhpVersion>,
+    ) -> Result<Extraction> {
+        self.extract_metadata_for_version(content, path, target)
+    }
+}
+
+/// PHP extractor configured with the file context and queries of `options`
+fn php_extractor(options: &ScanOptions) -> Result<PhpMetadataExtractor> {
+    let mut extractor = PhpMetadataExtractor::new()?;
+    extractor.set_include_file_context(options.include_file_context);
+    extractor.set_custom_queries(options.queries.clone());
+    Ok(extractor)
+}
+
+/// Plain PHP files
+pub struct PhpFrontend;
+
+impl LanguageFrontend for PhpFrontend {
+    fn name(&self) -> &'static str {
+        "php"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["php"]
+    }
+
+    fn parser(&self, options: &ScanOptions) -> Result<Box<dyn FrontendParser>> {
+        Ok(Box::new(php_extractor(options)?))
+    }
+}
+
+/// PHP templates
+///
+/// The PHP grammar parses the HTML around `<?php ... ?>` blocks as text, so
+/// these go through the same extractor. Most templates only echo (`<?=`);
+/// a file without a `<?php` block (in any case, like PHP's own tag) cannot
+/// declare a class and is not parsed.
+pub struct PhtmlFrontend;
+
+impl LanguageFrontend for PhtmlFrontend {
+    fn name(&self) -> &'static str {
+        "phtml"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["phtml"]
+    }
+
+    fn parser(&self, options: &ScanOptions) -> Result<Box<dyn FrontendParser>> {
+        Ok(Box::new(PhtmlParser(php_extractor(options)?)))
+    }
+}
+
+/// Parser of [`PhtmlFrontend`]
+struct PhtmlParser(PhpMetadataExtractor);
+
+impl FrontendParser for PhtmlParser {
+    fn extract(
+        &mut self, content: &str, path: PathBuf, target: Option<PhpVersion>,
+    ) -> Result<Extraction> {
+        let has_php_block = content
+            .as_bytes()
+            .windows(5)
+            .any(|tag| tag.eq_ignore_ascii_case(b"<?php"));
+        if !has_php_block {
+            return Ok((Vec::new(), None, Vec::new()));
+        }
+        self.0.extract_metadata_for_version(content, path, target)
+    }
+}
+
+/// Every front-end; the first one is always enabled
+pub static FRONTENDS: [&dyn LanguageFrontend; 2] = [&PhpFrontend, &PhtmlFrontend];
+
+/// Whether `name` is the name of a front-end
+#[must_use]
+pub fn is_known(name: &str) -> bool {
+    FRONTENDS.iter().any(|frontend| frontend.name() == name)
+}
+
+/// Front-ends enabled for a scan, one bit per entry of [`FRONTENDS`]
+///
+/// The default enables `php` only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frontends(u32);
+
+impl Default for Frontends {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+impl Frontends {
+    /// The front-ends named in `names`, plus `php`
+    #[must_use]
+    pub fn new(names: &[String]) -> Self {
+        let bits = FRONTENDS
+            .iter()
+            .enumerate()
+            .filter(|(_, frontend)| names.iter().any(|name| name == frontend.name()))
+            .fold(1, |bits, (i, _)| bits | 1 << i);
+        Self(bits)
+    }
+
+    /// Enabled front-end reading `path`, by its extension
+    #[must_use]
+    pub fn for_path(self, path: &Path) -> Option<&'static dyn LanguageFrontend> {
+        let extension = path.extension()?.to_str()?;
+        FRONTENDS
+            .iter()
+            .enumerate()
+            .find(|(i, frontend)| self.0 & 1 << i != 0 && frontend.extensions().contains(&extension))
+            .map(|(_, frontend)| *frontend)
+    }
+
+    /// Whether `path` is a source file of an enabled front-end
+    #[must_use]
+    pub fn is_source_file(self, path: &Path) -> bool {
+        self.for_path(path).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_phtml_declarations_and_plain_templates() {
+        let mut parser = PhtmlFrontend.parser(&ScanOptions::default()).unwrap();
+        let template = r#"<div class="user">
+<?php
+namespace App\View;
+
+#[Helper]
+class UserBadge { public function render(): string { return ''; } }
+?>
+<?= $name ?>
+</div>
+"#;
+
+        let (metadata, diagnostic, _) =
+            parser.extract(template, PathBuf::from("badge.phtml"), None).unwrap();
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(metadata[0].fqcn, "\\App\\View\\UserBadge");
+        assert_eq!(metadata[0].attribute_names().count(), 1);
+        assert!(diagnostic.is_none());
+
+        let (metadata, ..) = parser
+            .extract("<p><?= $title ?></p>", PathBuf::from("t.phtml"), None)
+            .unwrap();
+        assert!(metadata.is_empty());
+
+        let upper = "<p>\n<?PHP class Card {} ?>\n</p>";
+        let (metadata, ..) = parser.extract(upper, PathBuf::from("c.phtml"), None).unwrap();
+        assert_eq!(metadata[0].fqcn, "\\Card");
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Files changed relative to `git_ref` in the repository containing `dir`
+///
+/// Includes committed changes since `git_ref`, uncommitted working tree
+/// changes and untracked files (respecting `.gitignore`). Deleted files are
+/// included so callers can drop them from the cache.
+/// Returned paths are absolute (rooted at the canonical repository top level).
+///
+/// # Errors
+///
+/// Returns an error if git is unavailable, `dir` is not inside a repository
+/// or `git_ref` cannot be resolved.
+pub fn changed_files(dir: &Path, git_ref: &str) -> Result<Vec<PathBuf>> {
+    let toplevel = toplevel(dir)?;
+
+    let diff = run_git(
+        dir,
+        &["diff", "--name-only", "--no-renames", "-z", git_ref, "--"],
+    )
+    .with_context(|| format!("Failed to diff against '{git_ref}'"))?;
+    let untracked = run_git(
+        dir,
+        &[
+            "ls-files",
+            "--others",
+            "--exclude-standard",
+            "--full-name",
+            "-z",
+        ],
+    )?;
+
+    let mut files: Vec<PathBuf> = diff
+        .split('\0')
+        .chain(untracked.split('\0'))
+        .filter(|name| !name.is_empty())
+        .map(|name| toplevel.join(name))
+        .collect();
+    files.sort();
+    files.dedup();
+
+    Ok(files)
+}
+
+/// Files changed relative to `git_ref` in the repositories containing `paths`
+///
+/// Each path is resolved to its own repository, so scan paths spread over
+/// several checkouts are all covered; a repository shared by several paths
+/// is diffed once.
+///
+/// # Errors
+///
+/// Returns an error naming the path if any of them fails like in
+/// [`changed_files`].
+pub fn changed_files_in(paths: &[PathBuf], git_ref: &str) -> Result<Vec<PathBuf>> {
+    let dirs = if paths.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        paths
+            .iter()
+            .map(|path| match path.parent() {
+                Some(parent) if path.is_file() => parent.to_path_buf(),
+                _ => path.clone(),
+            })
+            .collect()
+    };
+
+    let mut repositories = Vec::new();
+    let mut files = Vec::new();
+    for dir in &dirs {
+        let repository =
+            toplevel(dir).with_context(|| format!("{} is not in a git repository", dir.display()))?;
+        if repositories.contains(&repository) {
+            continue;
+        }
+        files.extend(
+            changed_files(&repository, git_ref)
+                .with_context(|| format!("in repository {}", repository.display()))?,
+        );
+        repositories.push(repository);
+    }
+    files.sort();
+    files.dedup();
+
+    Ok(files)
+}
+
+/// Canonical top level of the repository containing `dir`
+fn toplevel(dir: &Path) -> Result<PathBuf> {
+    let toplevel = PathBuf::from(run_git(dir, &["rev-parse", "--show-toplevel"])?.trim());
+    Ok(std::fs::canonicalize(&toplevel).unwrap_or(toplevel))
+}
+
+/// Run a git command in `dir` and return its stdout
+fn run_git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("Failed to run git (is it installed?)")?;
+
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
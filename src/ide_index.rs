@@ -0,0 +1,442 @@
+//! Editor index with source positions (`discovery:export ide`)
+//!
+//! The cache carries no positions, so this pass re-parses the files and
+//! records where every declaration, member and attribute is. Editor plugins
+//! use it for navigation (`classes`) and "find usages of attribute"
+//! (`usages`, keyed by attribute FQCN) without parsing PHP themselves.
+//!
+//! Lines and columns are 1-based; columns count bytes, as tree-sitter does.
+//! FQCNs are written without the leading backslash.
+
+use crate::parser::{FileContext, PhpMetadataExtractor};
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use tree_sitter::{Node, Point};
+
+/// Bumped on incompatible changes of the index layout
+pub const INDEX_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IdeIndex {
+    pub version: u32,
+    /// Declarations ordered by FQCN
+    pub classes: Vec<IndexedClass>,
+    /// Every attribute occurrence, keyed by attribute FQCN
+    pub usages: BTreeMap<String, Vec<AttributeUsage>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IndexedClass {
+    pub fqcn: String,
+    /// `class`, `interface`, `trait` or `enum`
+    pub kind: String,
+    pub file: PathBuf,
+    /// Position of the declared name (navigation target)
+    pub position: Position,
+    /// Whole declaration, attributes included
+    pub span: Span,
+    pub attributes: Vec<IndexedAttribute>,
+    pub members: Vec<IndexedMember>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IndexedMember {
+    /// `method`, `property`, `case` or `parameter`
+    pub kind: String,
+    /// Name without `$`
+    pub name: String,
+    pub position: Position,
+    pub attributes: Vec<IndexedAttribute>,
+    /// Method parameters (promoted constructor properties included)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<Self>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IndexedAttribute {
+    pub name: String,
+    /// The attribute with its arguments, without `#[` and `]`
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AttributeUsage {
+    pub file: PathBuf,
+    pub span: Span,
+    pub class: String,
+    /// `method`, `$property`, `CASE` or `method($parameter)`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl From<Point> for Position {
+    fn from(point: Point) -> Self {
+        Self {
+            line: point.row + 1,
+            column: point.column + 1,
+        }
+    }
+}
+
+impl Span {
+    fn of(node: &Node) -> Self {
+        Self {
+            start: node.start_position().into(),
+            end: node.end_position().into(),
+        }
+    }
+}
+
+/// Index the given PHP files; unreadable and oversized files are skipped
+#[must_use]
+pub fn build_ide_index(files: &[PathBuf], max_file_size: u64) -> IdeIndex {
+    let mut classes: Vec<IndexedClass> = files
+        .par_iter()
+        .map_init(PhpMetadataExtractor::new, |extractor, path| {
+            let extractor = extractor.as_mut().ok()?;
+            let size = std::fs::metadata(path).ok()?.len();
+            if size > max_file_size {
+                return None;
+            }
+            let content = std::fs::read_to_string(path).ok()?;
+            index_source(extractor, &content, path)
+                .map_err(|e| warn!("Could not index {:?}: {}", path, e))
+                .ok()
+        })
+        .flatten()
+        .flatten()
+        .collect();
+    classes.sort_by(|a, b| a.fqcn.cmp(&b.fqcn));
+
+    IdeIndex {
+        version: INDEX_VERSION,
+        usages: group_usages(&classes),
+        classes,
+    }
+}
+
+/// Attribute occurrences of `classes`, keyed by attribute FQCN
+#[must_use]
+pub fn group_usages(classes: &[IndexedClass]) -> BTreeMap<String, Vec<AttributeUsage>> {
+    let mut usages: BTreeMap<String, Vec<AttributeUsage>> = BTreeMap::new();
+    for class in classes {
+        let mut add = |attributes: &[IndexedAttribute], member: Option<String>| {
+            for attribute in attributes {
+                usages
+                    .entry(attribute.name.clone())
+                    .or_default()
+                    .push(AttributeUsage {
+                        file: class.file.clone(),
+                        span: attribute.span,
+                        class: class.fqcn.clone(),
+                        member: member.clone(),
+                    });
+            }
+        };
+        add(&class.attributes, None);
+        for member in &class.members {
+            let label = match member.kind.as_str() {
+                "property" => format!("${}", member.name),
+                _ => member.name.clone(),
+            };
+            add(&member.attributes, Some(label));
+            for parameter in &member.parameters {
+                add(
+                    &parameter.attributes,
+                    Some(format!("{}(${})", member.name, parameter.name)),
+                );
+            }
+        }
+    }
+
+    usages
+}
+
+/// Index the declarations of one file
+///
+/// # Errors
+///
+/// Returns an error if the source cannot be parsed.
+pub fn index_source(
+    extractor: &mut PhpMetadataExtractor, content: &str, path: &Path,
+) -> Result<Vec<IndexedClass>> {
+    let (tree, file_context) = extractor.parse_with_context(content, path)?;
+    let indexer = Indexer {
+        context: &file_context,
+        source: content,
+        file: path,
+    };
+
+    let mut classes = Vec::new();
+    indexer.walk(tree.root_node(), &mut classes);
+    Ok(classes)
+}
+
+/// Write the index as JSON, unless the file already holds it
+///
+/// # Errors
+///
+/// Returns an error if the index cannot be written.
+pub fn write_ide_index(index: &IdeIndex, output_path: &Path) -> Result<()> {
+    let content = serde_json::to_string_pretty(index)? + "\n";
+    crate::writer::write_if_changed(output_path, content.as_bytes())
+        .with_context(|| format!("Failed to write IDE index {}", output_path.display()))?;
+    Ok(())
+}
+
+struct Indexer<'a> {
+    context: &'a FileContext<'a>,
+    source: &'a str,
+    file: &'a Path,
+}
+
+impl Indexer<'_> {
+    fn walk(&self, node: Node, classes: &mut Vec<IndexedClass>) {
+        let kind = match node.kind() {
+            "class_declaration" => "class",
+            "interface_declaration" => "interface",
+            "trait_declaration" => "trait",
+            "enum_declaration" => "enum",
+            _ => {
+                let mut cursor = node.walk();
+                for child in node.children(&mut cursor) {
+                    self.walk(child, classes);
+                }
+                return;
+            },
+        };
+
+        let Some(name) = node.child_by_field_name("name") else {
+            return;
+        };
+        let fqcn = self.context.resolve_fqcn(self.text(&name));
+
+        let mut members = Vec::new();
+        let mut cursor = node.walk();
+        let body = node
+            .children(&mut cursor)
+            .find(|child| matches!(child.kind(), "declaration_list" | "enum_declaration_list"));
+        if let Some(body) = body {
+            let mut cursor = body.walk();
+            for declaration in body.children(&mut cursor) {
+                self.members(&declaration, &mut members);
+            }
+        }
+
+        classes.push(IndexedClass {
+            fqcn: fqcn.trim_start_matches('\\').to_string(),
+            kind: kind.to_string(),
+            file: self.file.to_path_buf(),
+            position: name.start_position().into(),
+            span: Span::of(&node),
+            attributes: self.attributes(&node),
+            members,
+        });
+    }
+
+    /// Members declared by one node of a class body
+    fn members(&self, node: &Node, members: &mut Vec<IndexedMember>) {
+        match node.kind() {
+            "method_declaration" => {
+                let Some(name) = node.child_by_field_name("name") else {
+                    return;
+                };
+                let mut parameters = Vec::new();
+                if let Some(list) = node.child_by_field_name("parameters") {
+                    let mut cursor = list.walk();
+                    for parameter in list.children(&mut cursor) {
+                        if let Some(name) = parameter.child_by_field_name("name") {
+                            parameters.push(self.member("parameter", &name, &parameter));
+                        }
+                    }
+                }
+                members.push(IndexedMember {
+                    parameters,
+                    ..self.member("method", &name, node)
+                });
+            },
+            "property_declaration" => {
+                // One declaration can declare several properties sharing its attributes
+                let mut cursor = node.walk();
+                for element in node.children(&mut cursor) {
+                    if element.kind() == "property_element" {
+                        let name = element.child_by_field_name("name").or_else(|| {
+                            let mut cursor = element.walk();
+                            element
+                                .children(&mut cursor)
+                                .find(|child| child.kind() == "variable_name")
+                        });
+                        if let Some(name) = name {
+                            members.push(self.member("property", &name, node));
+                        }
+                    }
+                }
+            },
+            "enum_case" => {
+                if let Some(name) = node.child_by_field_name("name") {
+                    members.push(self.member("case", &name, node));
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn member(&self, kind: &str, name: &Node, declaration: &Node) -> IndexedMember {
+        IndexedMember {
+            kind: kind.to_string(),
+            name: self.text(name).trim_start_matches('$').to_string(),
+            position: name.start_position().into(),
+            attributes: self.attributes(declaration),
+            parameters: Vec::new(),
+        }
+    }
+
+    /// Attributes in the `attribute_list` children of `node`
+    fn attributes(&self, node: &Node) -> Vec<IndexedAttribute> {
+        let mut attributes = Vec::new();
+        let mut cursor = node.walk();
+        for list in node.children(&mut cursor) {
+            if list.kind() != "attribute_list" {
+                continue;
+            }
+            let mut cursor = list.walk();
+            for group in list.children(&mut cursor) {
+                let mut cursor = group.walk();
+                for attribute in group.children(&mut cursor) {
+                    if attribute.kind() != "attribute" {
+                        continue;
+                    }
+                    let name = attribute.child_by_field_name("name").or_else(|| {
+                        let mut cursor = attribute.walk();
+                        attribute
+                            .children(&mut cursor)
+                            .find(|child| matches!(child.kind(), "name" | "qualified_name"))
+                    });
+                    if let Some(name) = name {
+                        let fqcn = self.context.resolve_fqcn(self.text(&name));
+                        attributes.push(IndexedAttribute {
+                            name: fqcn.trim_start_matches('\\').to_string(),
+                            span: Span::of(&attribute),
+                        });
+                    }
+                }
+            }
+        }
+        attributes
+    }
+
+    fn text(&self, node: &Node) -> &str {
+        &self.source[node.byte_range()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    const SOURCE: &str = r"<?php
+namespace App\Controller;
+
+use Symfony\Component\Routing\Attribute\Route;
+
+#[Route('/users')]
+final class UserController
+{
+    #[\Deprecated]
+    private string $name, $email;
+
+    public function __construct(
+        #[Autowire('%kernel.debug%')] private bool $debug,
+    ) {}
+
+    #[Route('/{id}', methods: ['GET'])]
+    public function show(int $id): void {}
+}
+";
+
+    fn position(line: usize, column: usize) -> Position {
+        Position { line, column }
+    }
+
+    #[test]
+    fn test_index_source_records_positions() {
+        let mut extractor = PhpMetadataExtractor::new().unwrap();
+        let classes = index_source(&mut extractor, SOURCE, Path::new("/src/User.php")).unwrap();
+
+        let class = &classes[0];
+        assert_eq!(class.fqcn, "App\\Controller\\UserController");
+        assert_eq!(class.position, position(7, 13));
+        assert_eq!(class.span.start, position(6, 1));
+        assert_eq!(
+            class.attributes,
+            [IndexedAttribute {
+                name: "Symfony\\Component\\Routing\\Attribute\\Route".to_string(),
+                span: Span {
+                    start: position(6, 3),
+                    end: position(6, 18),
+                },
+            }]
+        );
+
+        let names: Vec<_> = class
+            .members
+            .iter()
+            .map(|m| (m.kind.as_str(), m.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("property", "name"),
+                ("property", "email"),
+                ("method", "__construct"),
+                ("method", "show")
+            ]
+        );
+        assert_eq!(class.members[1].attributes[0].name, "Deprecated");
+        assert_eq!(class.members[3].position, position(17, 21));
+
+        let debug = &class.members[2].parameters[0];
+        assert_eq!(debug.name, "debug");
+        assert_eq!(debug.attributes[0].name, "App\\Controller\\Autowire");
+    }
+
+    #[test]
+    fn test_build_index_groups_usages_by_attribute() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("User.php");
+        std::fs::write(&file, SOURCE).unwrap();
+
+        let index = build_ide_index(std::slice::from_ref(&file), 1024 * 1024);
+
+        assert_eq!(index.version, INDEX_VERSION);
+        let routes = &index.usages["Symfony\\Component\\Routing\\Attribute\\Route"];
+        let members: Vec<_> = routes.iter().map(|usage| usage.member.as_deref()).collect();
+        assert_eq!(members, [None, Some("show")]);
+        assert_eq!(routes[1].file, file);
+        assert_eq!(routes[1].span.start, position(16, 7));
+        assert_eq!(
+            index.usages["App\\Controller\\Autowire"][0]
+                .member
+                .as_deref(),
+            Some("__construct($debug)")
+        );
+        assert_eq!(index.usages["Deprecated"].len(), 2);
+    }
+}
@@ -1,58 +1,235 @@
+use crate::frontend::Frontends;
 use crate::metadata::PhpClassMetadata;
-use anyhow::{Context, Result};
+use crate::scanner::{ExtractorPool, FileScan, ignore_matcher, is_ignored};
+use crate::schema::{SCHEMA_VERSION, migrate_manifest};
+use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
-use std::fs;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File, OpenOptions, TryLockError};
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 
 /// Manifest file name
 pub const MANIFEST_FILE: &str = "aurynx.meta.json";
 
+/// How long a scan waits for a concurrent scan to release the manifest lock
+pub const MANIFEST_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Number of consecutive failed attempts after which a file is reported as a
+/// persistent failure
+pub const PERSISTENT_FAILURE_ATTEMPTS: u32 = 3;
+
 /// Information about a file in the manifest
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct FileEntry {
     pub mtime: u64,
     pub classes: Vec<PhpClassMetadata>,
+    /// Last read/parse error; failed files are retried on every run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parse_error: Option<String>,
+    /// Stable code of `parse_error` (see [`crate::error::code`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    /// Consecutive failed parse attempts
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub attempts: u32,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)] // serde's skip_serializing_if passes a reference
+const fn is_zero(value: &u32) -> bool {
+    *value == 0
 }
 
 /// Manifest structure
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Manifest {
+    /// Schema version (see `crate::schema`)
+    #[serde(default = "crate::schema::legacy_schema_version")]
+    pub schema_version: u32,
     pub files: HashMap<String, FileEntry>,
+    /// Source hash of each custom query the classes were extracted with
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub queries: BTreeMap<String, String>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            files: HashMap::new(),
+            queries: BTreeMap::new(),
+        }
+    }
 }
 
 impl Manifest {
-    /// Load manifest from file
+    /// Load manifest from file (decrypting it), migrating older schema versions
     pub fn load(path: &Path) -> Result<Self> {
         if !path.exists() {
             return Ok(Self::default());
         }
-        let content = fs::read_to_string(path)?;
-        let manifest = serde_json::from_str(&content).context("Failed to parse manifest file")?;
+        let content = crate::retry::with_retry(path, || fs::read(path))?;
+        let content = crate::encryption::open(content).context("Failed to read manifest file")?;
+        let value = serde_json::from_slice(&content).context("Failed to parse manifest file")?;
+        let migrated = migrate_manifest(value).context("Failed to migrate manifest file")?;
+        let manifest = serde_json::from_value(migrated).context("Failed to parse manifest file")?;
         Ok(manifest)
     }
 
-    /// Save manifest to file
+    /// Files whose last scan failed, sorted by path
+    #[must_use]
+    pub fn failed_files(&self) -> Vec<(&str, &FileEntry)> {
+        let mut failed: Vec<(&str, &FileEntry)> = self
+            .files
+            .iter()
+            .filter(|(_, entry)| entry.parse_error.is_some())
+            .map(|(path, entry)| (path.as_str(), entry))
+            .collect();
+        failed.sort_by_key(|(path, _)| *path);
+        failed
+    }
+
+    /// Forget the files under `dir`, so the next scan reparses them whatever
+    /// their mtime; returns how many entries were dropped
+    ///
+    /// Paths are compared canonicalized, since entries keep the form the
+    /// scan paths were given in.
+    pub fn invalidate_under(&mut self, dir: &Path) -> usize {
+        let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        let before = self.files.len();
+        self.files.retain(|path, _| {
+            let path = Path::new(path);
+            !fs::canonicalize(path)
+                .unwrap_or_else(|_| path.to_path_buf())
+                .starts_with(&dir)
+        });
+        before - self.files.len()
+    }
+
+    /// Save manifest to file (atomic: temp file + rename), encrypted if enabled
     pub fn save(&self, path: &Path) -> Result<()> {
-        let content = serde_json::to_string_pretty(self)?;
-        fs::write(path, content)?;
+        let content = crate::encryption::seal(&serde_json::to_vec_pretty(self)?)?;
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, content)?;
+        fs::rename(&temp, path)?;
         Ok(())
     }
 }
 
+/// Advisory lock guarding the manifest for a whole load → scan → save cycle
+///
+/// Concurrent `--incremental` runs against the same output directory (e.g. a
+/// CI matrix) are serialized: the second run waits for the first to finish
+/// and fails with a clear error if the wait exceeds the timeout.
+/// The lock is released when the guard is dropped or the process exits.
+#[derive(Debug)]
+pub struct ManifestLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl ManifestLock {
+    /// Acquire the lock for `manifest_path`, waiting up to `timeout`
+    ///
+    /// # Errors
+    ///
+    /// Fails if the lock file cannot be opened or another scan still holds
+    /// the lock when `timeout` expires.
+    pub fn acquire(manifest_path: &Path, timeout: Duration) -> Result<Self> {
+        let path = Self::path_for(manifest_path);
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open manifest lock file {}", path.display()))?;
+
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Self { file, path }),
+                Err(TryLockError::WouldBlock) => {
+                    if Instant::now() >= deadline {
+                        bail!(
+                            "Manifest is locked by another scan ({}); gave up after {}s",
+                            path.display(),
+                            timeout.as_secs()
+                        );
+                    }
+                    std::thread::sleep(Duration::from_millis(50));
+                },
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).with_context(|| {
+                        format!("Failed to lock manifest lock file {}", path.display())
+                    });
+                },
+            }
+        }
+    }
+
+    /// Lock file path for a manifest (e.g. `aurynx.meta.json.lock`)
+    #[must_use]
+    pub fn path_for(manifest_path: &Path) -> PathBuf {
+        let mut name = manifest_path.as_os_str().to_owned();
+        name.push(".lock");
+        PathBuf::from(name)
+    }
+
+    /// Path of the lock file held by this guard
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ManifestLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
 /// Perform incremental scan using manifest
 pub fn perform_incremental_scan(
-    manifest_path: &Path,
-    scan_paths: &[PathBuf],
-    ignore_patterns: &[String],
-    max_file_size: u64,
+    manifest_path: &Path, scan_paths: &[PathBuf], ignore_patterns: &[String], max_file_size: u64,
 ) -> Result<(Vec<PhpClassMetadata>, Manifest)> {
-    // Load existing manifest
-    let mut manifest = Manifest::load(manifest_path)?;
+    let manifest = Manifest::load(manifest_path)?;
+    scan_with_manifest(
+        manifest,
+        scan_paths,
+        ignore_patterns,
+        max_file_size,
+        &ExtractorPool::default(),
+        false,
+    )
+}
+
+/// Scan the files that changed since `manifest` (all files if it is empty),
+/// with extractors from `pool`
+///
+/// Prints how many files changed and which fail to parse, unless `quiet`
+/// (e.g. for benchmark loops).
+///
+/// # Errors
+///
+/// Returns an error if a scan path lies outside the pool's `allowed_roots`.
+pub fn scan_with_manifest(
+    mut manifest: Manifest, scan_paths: &[PathBuf], ignore_patterns: &[String], max_file_size: u64,
+    pool: &ExtractorPool, quiet: bool,
+) -> Result<(Vec<PhpClassMetadata>, Manifest)> {
+
+    let options = pool.options();
+    options.check_paths(scan_paths)?;
 
     // Collect current files
-    let current_files = collect_php_files(scan_paths, ignore_patterns)?;
+    let current_files = collect_php_files(scan_paths, ignore_patterns, options.frontends)?;
     let current_files_set: HashSet<String> = current_files
         .iter()
         .map(|p| p.to_string_lossy().to_string())
@@ -60,6 +237,7 @@ pub fn perform_incremental_scan(
 
     let mut changed_files = Vec::new();
     let mut removed_files = Vec::new();
+    let mut retried = 0;
 
     // Check for removed files
     let cached_paths: Vec<String> = manifest.files.keys().cloned().collect();
@@ -74,20 +252,30 @@ pub fn perform_incremental_scan(
         manifest.files.remove(path);
     }
 
-    // Check for changed or new files
+    // Check for changed or new files (previously failed files are always retried,
+    // and files cached with or without a file context or custom query captures the
+    // settings no longer match, or with queries whose source changed)
+    let query_hashes = crate::custom_queries::source_hashes(options.queries.as_deref());
+    let queries_changed = manifest.queries != query_hashes;
+    manifest.queries = query_hashes;
     for path in current_files {
         let path_str = path.to_string_lossy().to_string();
-        let mtime = fs::metadata(&path)
-            .and_then(|m| m.modified())
-            .map(|t| {
-                t.duration_since(SystemTime::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs()
-            })
-            .unwrap_or(0);
+        let mtime = file_mtime(&path);
 
         if let Some(entry) = manifest.files.get(&path_str) {
-            if mtime > entry.mtime {
+            if entry.parse_error.is_some() {
+                retried += 1;
+                changed_files.push(path);
+            } else if mtime > entry.mtime
+                || entry
+                    .classes
+                    .iter()
+                    .any(|class| {
+                        queries_changed
+                            || class.file_context.is_some() != options.include_file_context
+                            || class.custom.is_some() != options.queries.is_some()
+                    })
+            {
                 changed_files.push(path);
             }
         } else {
@@ -96,55 +284,208 @@ pub fn perform_incremental_scan(
         }
     }
 
-    println!(
-        "Incremental scan: {} changed/new, {} removed",
-        changed_files.len(),
-        removed_files.len()
-    );
+    if !quiet {
+        println!(
+            "Incremental scan: {} changed/new, {} removed, {} retried",
+            changed_files.len() - retried,
+            removed_files.len(),
+            retried
+        );
+    }
 
     // Scan changed files
     if !changed_files.is_empty() {
-        let new_metadata = crate::scanner::scan_files_with_limit(&changed_files, max_file_size);
-
-        // Group metadata by file
-        let mut file_metadata_map: HashMap<String, Vec<PhpClassMetadata>> = HashMap::new();
-        for meta in new_metadata {
-            let file_path = meta.file.to_string_lossy().to_string();
-            file_metadata_map.entry(file_path).or_default().push(meta);
-        }
+        let scans =
+            crate::scanner::scan_files_detailed_with_pool(&changed_files, max_file_size, pool);
+        let mut scans_by_path: HashMap<String, FileScan> = scans
+            .into_iter()
+            .map(|scan| (scan.path.to_string_lossy().to_string(), scan))
+            .collect();
 
         // Update manifest
         for path in changed_files {
             let path_str = path.to_string_lossy().to_string();
-            let mtime = fs::metadata(&path)
-                .and_then(|m| m.modified())
-                .map(|t| {
-                    t.duration_since(SystemTime::UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_secs()
-                })
-                .unwrap_or(0);
-
-            let classes = file_metadata_map.remove(&path_str).unwrap_or_default();
-
-            manifest
-                .files
-                .insert(path_str, FileEntry { mtime, classes });
+            let mtime = file_mtime(&path);
+            let previous = manifest.files.remove(&path_str);
+
+            let entry = scans_by_path.remove(&path_str).map_or_else(
+                || FileEntry {
+                    mtime,
+                    ..FileEntry::default()
+                },
+                |scan| entry_from_scan(scan, mtime, previous),
+            );
+
+            manifest.files.insert(path_str, entry);
         }
     }
 
-    // Flatten manifest to list of metadata
-    let all_metadata: Vec<PhpClassMetadata> = manifest
+    if !quiet {
+        report_failures(&manifest);
+    }
+
+    // Flatten manifest to list of metadata (sorted for stable output)
+    let mut all_metadata: Vec<PhpClassMetadata> = manifest
         .files
         .values()
         .flat_map(|entry| entry.classes.clone())
         .collect();
+    all_metadata.sort_by(|a, b| a.fqcn.cmp(&b.fqcn));
 
     Ok((all_metadata, manifest))
 }
 
-/// Collect all PHP files in the given paths (without parsing them)
-fn collect_php_files(paths: &[PathBuf], ignored: &[String]) -> Result<Vec<PathBuf>> {
+/// Merge fresh metadata for an explicit list of changed files into the manifest
+///
+/// Used by `--changed-since`: instead of walking the tree, only the given
+/// files are considered. Paths outside `scan_paths`, ignored paths and
+/// non-source files are skipped; paths that no longer exist are removed.
+/// Requires an existing manifest from a previous scan. Files are parsed with
+/// extractors from `pool`.
+///
+/// # Errors
+///
+/// Returns an error if there is no manifest yet or it cannot be loaded, or
+/// if a scan path lies outside the pool's `allowed_roots`.
+pub fn perform_changed_files_scan(
+    manifest_path: &Path, scan_paths: &[PathBuf], ignore_patterns: &[String], max_file_size: u64,
+    changed: &[PathBuf], pool: &ExtractorPool,
+) -> Result<(Vec<PhpClassMetadata>, Manifest)> {
+    pool.options().check_paths(scan_paths)?;
+    if !manifest_path.exists() {
+        bail!(
+            "No manifest at {}; run a full scan first",
+            manifest_path.display()
+        );
+    }
+    let mut manifest = Manifest::load(manifest_path)?;
+    let ignore = ignore_matcher(scan_paths, ignore_patterns);
+
+    // Map changed paths onto the same form the directory walk produces
+    let roots: Vec<(PathBuf, PathBuf)> = scan_paths
+        .iter()
+        .map(|p| (fs::canonicalize(p).unwrap_or_else(|_| p.clone()), p.clone()))
+        .collect();
+
+    let mut to_scan = Vec::new();
+    let mut removed = 0;
+
+    for file in changed {
+        if !pool.options().frontends.is_source_file(file) {
+            continue;
+        }
+        let Some(path) = roots.iter().find_map(|(canonical, original)| {
+            file.strip_prefix(canonical)
+                .ok()
+                .map(|rel| original.join(rel))
+        }) else {
+            continue;
+        };
+        if is_ignored(ignore.as_ref(), &path) {
+            continue;
+        }
+
+        if crate::retry::may_be_file(&path) {
+            to_scan.push(path);
+        } else if manifest
+            .files
+            .remove(&path.to_string_lossy().to_string())
+            .is_some()
+        {
+            removed += 1;
+        }
+    }
+
+    println!(
+        "Changed-files scan: {} changed/new, {} removed",
+        to_scan.len(),
+        removed
+    );
+
+    for scan in crate::scanner::scan_files_detailed_with_pool(&to_scan, max_file_size, pool) {
+        let path_str = scan.path.to_string_lossy().to_string();
+        let previous = manifest.files.remove(&path_str);
+        let mtime = file_mtime(&scan.path);
+        let entry = entry_from_scan(scan, mtime, previous);
+        manifest.files.insert(path_str, entry);
+    }
+
+    report_failures(&manifest);
+
+    let mut all_metadata: Vec<PhpClassMetadata> = manifest
+        .files
+        .values()
+        .flat_map(|entry| entry.classes.clone())
+        .collect();
+    all_metadata.sort_by(|a, b| a.fqcn.cmp(&b.fqcn));
+
+    Ok((all_metadata, manifest))
+}
+
+/// Build the manifest entry for a freshly scanned file
+///
+/// On failure the last known-good classes are kept until the file parses
+/// again, and the attempt counter is incremented.
+pub(crate) fn entry_from_scan(
+    scan: FileScan, mtime: u64, previous: Option<FileEntry>,
+) -> FileEntry {
+    match scan.error {
+        Some(error) => {
+            let (classes, attempts) = previous.map_or((Vec::new(), 0), |p| (p.classes, p.attempts));
+            FileEntry {
+                mtime,
+                classes,
+                parse_error: Some(error.message),
+                error_code: Some(error.code.to_string()),
+                attempts: attempts + 1,
+            }
+        },
+        None => FileEntry {
+            mtime,
+            classes: scan.classes,
+            ..FileEntry::default()
+        },
+    }
+}
+
+/// Print a summary of files that currently fail to parse
+fn report_failures(manifest: &Manifest) {
+    let failed = manifest.failed_files();
+    if failed.is_empty() {
+        return;
+    }
+
+    println!(
+        "Parse failures: {} file(s) will be retried next run",
+        failed.len()
+    );
+    for (path, entry) in failed {
+        if entry.attempts >= PERSISTENT_FAILURE_ATTEMPTS {
+            eprintln!(
+                "Warning: {path} failed to parse {} times in a row: {}",
+                entry.attempts,
+                entry.parse_error.as_deref().unwrap_or("unknown error")
+            );
+        }
+    }
+}
+
+/// Modification time of a file in seconds since the epoch (0 if unavailable)
+fn file_mtime(path: &Path) -> u64 {
+    crate::retry::with_retry(path, || fs::metadata(path))
+        .and_then(|m| m.modified())
+        .map(|t| {
+            t.duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        })
+        .unwrap_or(0)
+}
+
+/// Collect the source files of `frontends` in the given paths (without parsing them)
+fn collect_php_files(
+    paths: &[PathBuf], ignored: &[String], frontends: Frontends,
+) -> Result<Vec<PathBuf>> {
     use ignore::WalkBuilder;
 
     let mut files = Vec::new();
@@ -158,14 +499,7 @@ fn collect_php_files(paths: &[PathBuf], ignored: &[String]) -> Result<Vec<PathBu
         builder.add(path);
     }
 
-    let mut overrides = ignore::overrides::OverrideBuilder::new(&paths[0]);
-    for ignore in ignored {
-        if let Err(e) = overrides.add(&format!("!{ignore}")) {
-            eprintln!("Warning: Invalid ignore pattern '{ignore}': {e}");
-        }
-    }
-
-    if let Ok(ov) = overrides.build() {
+    if let Some(ov) = ignore_matcher(paths, ignored) {
         builder.overrides(ov);
     }
 
@@ -173,13 +507,156 @@ fn collect_php_files(paths: &[PathBuf], ignored: &[String]) -> Result<Vec<PathBu
 
     for entry in builder.build() {
         if let Ok(entry) = entry
-            && entry.file_type().is_some_and(|ft| ft.is_file()) {
-                let path = entry.path();
-                if path.extension().is_some_and(|ext| ext == "php") {
-                    files.push(path.to_path_buf());
-                }
+            && entry.file_type().is_some_and(|ft| ft.is_file())
+        {
+            let path = entry.path();
+            if frontends.is_source_file(path) {
+                files.push(path.to_path_buf());
             }
+        }
     }
 
     Ok(files)
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::expect_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_invalidate_under_drops_only_that_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let vendor = temp_dir.path().join("vendor/acme/lib");
+        fs::create_dir_all(&vendor).unwrap();
+        fs::write(vendor.join("Client.php"), "<?php class Client {}").unwrap();
+        let mut manifest = Manifest::default();
+        for path in [vendor.join("Client.php"), temp_dir.path().join("src/App.php")] {
+            manifest.files.insert(path.display().to_string(), FileEntry::default());
+        }
+
+        assert_eq!(manifest.invalidate_under(&temp_dir.path().join("vendor")), 1);
+        assert_eq!(manifest.files.len(), 1);
+        assert!(manifest.files.keys().all(|path| path.ends_with("App.php")));
+    }
+
+    #[test]
+    fn test_manifest_lock_is_exclusive() {
+        let temp_dir = TempDir::new().unwrap();
+        let manifest_path = temp_dir.path().join(MANIFEST_FILE);
+
+        let first = ManifestLock::acquire(&manifest_path, Duration::from_secs(1)).unwrap();
+        assert!(first.path().ends_with("aurynx.meta.json.lock"));
+
+        let second = ManifestLock::acquire(&manifest_path, Duration::from_millis(100));
+        assert!(
+            second
+                .unwrap_err()
+                .to_string()
+                .contains("locked by another scan")
+        );
+
+        drop(first);
+        assert!(ManifestLock::acquire(&manifest_path, Duration::from_millis(100)).is_ok());
+    }
+
+    #[test]
+    fn test_manifest_save_is_atomic() {
+        let temp_dir = TempDir::new().unwrap();
+        let manifest_path = temp_dir.path().join(MANIFEST_FILE);
+
+        Manifest::default().save(&manifest_path).unwrap();
+
+        assert!(manifest_path.exists());
+        assert!(!manifest_path.with_extension("json.tmp").exists());
+        assert!(Manifest::load(&manifest_path).unwrap().files.is_empty());
+    }
+
+    #[test]
+    fn test_failed_files_are_recorded_and_retried() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        fs::create_dir(&src).unwrap();
+        let manifest_path = temp_dir.path().join(MANIFEST_FILE);
+        let broken = src.join("Broken.php");
+
+        // Invalid UTF-8 cannot be read as PHP source
+        fs::write(&broken, [0x3c, 0x3f, 0x70, 0x68, 0x70, 0xff, 0xfe]).unwrap();
+
+        for attempt in 1..=2 {
+            let (_, manifest) = perform_incremental_scan(
+                &manifest_path,
+                std::slice::from_ref(&src),
+                &[],
+                u64::MAX,
+            )
+            .unwrap();
+            manifest.save(&manifest_path).unwrap();
+
+            let failed = manifest.failed_files();
+            assert_eq!(failed.len(), 1);
+            assert_eq!(failed[0].1.attempts, attempt);
+        }
+
+        // Fixing the file clears the error even when mtime is unchanged
+        fs::write(&broken, "<?php class Fixed {}").unwrap();
+        let (metadata, manifest) =
+            perform_incremental_scan(&manifest_path, &[src], &[], u64::MAX).unwrap();
+
+        assert!(manifest.failed_files().is_empty());
+        assert_eq!(metadata.len(), 1);
+    }
+
+    #[test]
+    fn test_edited_query_reparses_files_with_classes() {
+        let temp_dir = TempDir::new().unwrap();
+        let src = temp_dir.path().join("src");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("User.php"), "<?php class User { const TABLE = 'users'; }").unwrap();
+        let query = temp_dir.path().join("consts.scm");
+        let files = std::collections::BTreeMap::from([("consts".to_string(), query.clone())]);
+        let scan = |manifest| {
+            let queries = crate::custom_queries::load(&files).unwrap();
+            let pool = ExtractorPool::new(crate::scanner::ScanOptions {
+                queries: Some(std::sync::Arc::new(queries)),
+                ..Default::default()
+            });
+            scan_with_manifest(manifest, std::slice::from_ref(&src), &[], u64::MAX, &pool, true)
+                .unwrap()
+        };
+
+        fs::write(&query, "(const_element (name) @name)").unwrap();
+        let (metadata, manifest) = scan(Manifest::default());
+        assert_eq!(metadata[0].custom.as_ref().unwrap()["consts"][0]["name"], "TABLE");
+        assert_eq!(manifest.queries.len(), 1);
+
+        // Same key, different query: the unchanged file is reparsed
+        fs::write(&query, "(const_element (_) @value .)").unwrap();
+        let (metadata, manifest) = scan(manifest);
+        assert_eq!(metadata[0].custom.as_ref().unwrap()["consts"][0]["value"], "'users'");
+        let (_, unchanged) = scan(manifest.clone());
+        assert_eq!(unchanged.queries, manifest.queries);
+    }
+
+    #[test]
+    fn test_scan_refuses_paths_outside_allowed_roots() {
+        let temp_dir = TempDir::new().unwrap();
+        let app = temp_dir.path().join("app");
+        let outside = temp_dir.path().join("etc");
+        fs::create_dir_all(&app).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("Secret.php"), "<?php class Secret {}").unwrap();
+        let pool = ExtractorPool::new(crate::scanner::ScanOptions {
+            allowed_roots: Some(vec![app.clone()]),
+            ..Default::default()
+        });
+
+        let scan = |paths: &[PathBuf]| {
+            scan_with_manifest(Manifest::default(), paths, &[], u64::MAX, &pool, true)
+        };
+        assert!(scan(std::slice::from_ref(&app)).is_ok());
+        let error = scan(&[app, outside]).unwrap_err();
+        assert!(error.to_string().contains("outside allowed_roots"));
+    }
+}
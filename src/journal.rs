@@ -0,0 +1,199 @@
+//! Change journal (`journal_file`, `discovery:log`)
+//!
+//! The cache only shows the current state, so "when did this service
+//! disappear from discovery?" has no answer once the daemon has moved on.
+//! With `journal_file` configured, the daemon appends one JSON line per
+//! flush that changed the cache file, with the added, changed and removed
+//! FQCNs, and one line when it starts. The file keeps the last
+//! `journal_max_entries` lines (default 1000); `discovery:log` prints them.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Entries kept without `journal_max_entries`
+pub const DEFAULT_MAX_ENTRIES: usize = 1000;
+
+/// One line of the journal
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Unix time of the flush, in seconds
+    pub time: u64,
+    /// Classes in the cache after the flush
+    pub classes: usize,
+    /// First flush of a daemon, which is not diffed (the lists are empty)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub start: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added: Vec<String>,
+    /// Modified classes (not in `added`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changed: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String>,
+}
+
+impl JournalEntry {
+    /// Entry for a flush happening now
+    #[must_use]
+    pub fn now(classes: usize) -> Self {
+        Self {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            classes,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the entry lists a class whose FQCN contains `filter`
+    /// (ignoring case and the leading backslash)
+    #[must_use]
+    pub fn mentions(&self, filter: &str) -> bool {
+        let filter = filter.trim_start_matches('\\').to_lowercase();
+        [&self.added, &self.changed, &self.removed]
+            .into_iter()
+            .flatten()
+            .any(|fqcn| fqcn.to_lowercase().contains(&filter))
+    }
+}
+
+/// Append `entry` to the journal at `path`, keeping the last `max_entries`
+///
+/// # Errors
+///
+/// Returns an error if the journal cannot be read or written.
+pub fn append(path: &Path, entry: &JournalEntry, max_entries: usize) -> Result<()> {
+    let existing = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read journal {}", path.display()));
+        },
+    };
+    let line = serde_json::to_string(entry)?;
+    let lines: Vec<&str> = existing
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .chain(std::iter::once(line.as_str()))
+        .collect();
+    let keep = &lines[lines.len().saturating_sub(max_entries)..];
+
+    let mut content = keep.join("\n");
+    content.push('\n');
+    crate::writer::write_if_changed(path, content.as_bytes())
+        .with_context(|| format!("Failed to write journal {}", path.display()))?;
+    Ok(())
+}
+
+/// Read every entry of the journal at `path`, oldest first
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or a line is not an entry.
+pub fn read(path: &Path) -> Result<Vec<JournalEntry>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read journal {}", path.display()))?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Invalid journal entry on line {}", i + 1))
+        })
+        .collect()
+}
+
+/// Human-readable listing of `entries`, one block per entry
+///
+/// With `filter`, only the classes matching it are listed.
+#[must_use]
+pub fn format_entries(entries: &[JournalEntry], filter: Option<&str>) -> String {
+    let mut output = String::new();
+    for entry in entries {
+        let time = format_time(entry.time);
+        if entry.start {
+            let _ = writeln!(output, "{time}  daemon started ({} classes)", entry.classes);
+            continue;
+        }
+        let _ = writeln!(
+            output,
+            "{time}  +{} ~{} -{} ({} classes)",
+            entry.added.len(),
+            entry.changed.len(),
+            entry.removed.len(),
+            entry.classes
+        );
+        for (sign, fqcns) in [('+', &entry.added), ('~', &entry.changed), ('-', &entry.removed)] {
+            for fqcn in fqcns {
+                if filter.is_none_or(|filter| {
+                    fqcn.to_lowercase()
+                        .contains(&filter.trim_start_matches('\\').to_lowercase())
+                }) {
+                    let _ = writeln!(output, "  {sign} {fqcn}");
+                }
+            }
+        }
+    }
+    output
+}
+
+/// `YYYY-MM-DD HH:MM:SS UTC` for a Unix time in seconds
+#[must_use]
+pub fn format_time(time: u64) -> String {
+    let (days, seconds) = (time / 86_400, time % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_journal_keeps_the_last_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("journal.jsonl");
+        for classes in 0..5 {
+            let entry = JournalEntry {
+                removed: vec![format!("\\App\\Service{classes}")],
+                ..JournalEntry::now(classes)
+            };
+            append(&path, &entry, 3).unwrap();
+        }
+
+        let entries = read(&path).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].classes, 2);
+        assert!(entries[2].mentions("app\\service4"));
+        assert!(!entries[2].mentions("Service3"));
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_time(951_782_400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format_time(1_791_016_979), "2026-10-03 08:42:59 UTC");
+    }
+}
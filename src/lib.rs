@@ -1,14 +1,127 @@
+//! PHP attribute discovery powered by tree-sitter
+//!
+//! [`prelude`] exports the supported API for embedding the scanner:
+//! [`Discovery`], the metadata types, the scanner entry points and the cache
+//! reader. Those items, and the modules listed in these docs, follow semver.
+//! Modules hidden from the docs exist for the `aurynx` binary and its
+//! artifact exporters; they are public so the binary and the tests can reach
+//! them, and may change in any release.
+
+#[doc(hidden)]
+pub mod allowed_roots;
+#[doc(hidden)]
+pub mod artifacts;
+#[doc(hidden)]
+pub mod attribute_stubs;
+#[doc(hidden)]
+pub mod benchmark;
+#[doc(hidden)]
 pub mod cache_strategy;
+#[doc(hidden)]
+pub mod class_lists;
+#[doc(hidden)]
+pub mod collisions;
+#[doc(hidden)]
+pub mod composer;
 pub mod config;
+#[doc(hidden)]
+pub mod container;
+#[doc(hidden)]
+pub mod custom_queries;
+#[doc(hidden)]
+pub mod derived;
+#[cfg(feature = "daemon")]
 pub mod daemon;
+pub mod diagnostic;
+pub mod discovery;
+#[doc(hidden)]
+pub mod doctrine;
+#[doc(hidden)]
+pub mod dry_run;
+#[doc(hidden)]
+pub mod encryption;
 pub mod error;
+#[doc(hidden)]
+pub mod exporters;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod frontend;
+#[doc(hidden)]
+pub mod git;
+#[doc(hidden)]
+pub mod ide_index;
+#[doc(hidden)]
 pub mod incremental;
+#[doc(hidden)]
+pub mod journal;
+#[doc(hidden)]
 pub mod logger;
+#[doc(hidden)]
+pub mod lsp;
+#[doc(hidden)]
+pub mod member_limit;
 pub mod metadata;
+#[doc(hidden)]
+pub mod nice;
+#[doc(hidden)]
+pub mod openapi;
 pub mod parser;
+#[doc(hidden)]
+pub mod path_map;
+pub mod php_version;
+#[doc(hidden)]
+pub mod phpunit;
+#[doc(hidden)]
+pub mod pipeline;
+#[doc(hidden)]
+pub mod policy;
+pub mod prelude;
+#[doc(hidden)]
+pub mod priming;
+#[doc(hidden)]
+pub mod profile;
+#[cfg(all(unix, feature = "daemon"))]
+#[doc(hidden)]
+pub mod promote;
+pub mod reader;
+#[doc(hidden)]
+pub mod resolver;
+#[doc(hidden)]
+pub mod retry;
+#[doc(hidden)]
+pub mod routes;
+#[doc(hidden)]
+pub mod scan_report;
 pub mod scanner;
+#[doc(hidden)]
+pub mod service;
+#[doc(hidden)]
+pub mod signature;
+pub mod schema;
+#[doc(hidden)]
+pub mod source;
+#[cfg(feature = "templates")]
+#[doc(hidden)]
+pub mod templates;
+#[doc(hidden)]
+pub mod unknown_attributes;
+#[doc(hidden)]
+pub mod unused_imports;
+#[doc(hidden)]
+pub mod vendor;
+#[cfg(all(unix, feature = "daemon"))]
+#[doc(hidden)]
+pub mod warm;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "watch")]
 pub mod watcher;
+#[cfg(all(unix, feature = "watch"))]
+pub mod watchman;
+#[doc(hidden)]
+pub mod workspace;
 pub mod writer;
 
 // Re-export commonly used types
+pub use discovery::Discovery;
 pub use error::{AurynxError, Result};
@@ -1,16 +1,35 @@
-use anyhow::{Context, Result};
+mod repeats;
+#[cfg(unix)]
+mod system_log;
+
+use anyhow::{Context, Result, anyhow, bail};
+use repeats::RepeatLayer;
+use std::io::IsTerminal;
 use std::path::Path;
 use std::sync::OnceLock;
 use tracing::Level;
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
+pub use repeats::flush_repeated;
+
 static LOGGER_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
 
+/// Log targets besides stdout and `log_file` (`log_target`)
+pub const LOG_TARGETS: [&str; 2] = ["syslog", "journald"];
+
 /// Initialize structured logging with tracing
+///
+/// With a `log_target` of `syslog` or `journald`, events go to the system
+/// logger instead of stdout or `log_file`, and `log_format` does not apply.
+/// `log_filter` holds env-filter directives (`aurynx::parser=warn,...`) that
+/// refine `log_level`; `RUST_LOG` still overrides both. Repeated warnings
+/// are aggregated (see [`flush_repeated`]).
 pub fn init_logger(
     log_file: Option<&Path>,
+    log_target: Option<&str>,
     log_level: &str,
+    log_filter: Option<&str>,
     log_format: &str,
     verbose: bool,
 ) -> Result<()> {
@@ -31,9 +50,14 @@ pub fn init_logger(
     let actual_level = if verbose { Level::DEBUG } else { level };
 
     // Create env filter
-    let filter = EnvFilter::try_from_default_env()
-        .or_else(|_| EnvFilter::try_new(format!("aurynx={actual_level}")))
-        .context("Failed to create log filter")?;
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => build_filter(actual_level, log_filter)?,
+    };
+
+    if let Some(target) = log_target {
+        return init_system_logger(target, filter);
+    }
 
     // Setup logger based on format
     match log_format.to_lowercase().as_str() {
@@ -50,6 +74,7 @@ pub fn init_logger(
 
                 tracing_subscriber::registry()
                     .with(filter)
+                    .with(RepeatLayer)
                     .with(fmt::layer().json().with_writer(non_blocking))
                     .try_init()?;
 
@@ -59,6 +84,7 @@ pub fn init_logger(
                 // JSON to stdout
                 tracing_subscriber::registry()
                     .with(filter)
+                    .with(RepeatLayer)
                     .with(fmt::layer().json())
                     .try_init()?;
             }
@@ -76,6 +102,7 @@ pub fn init_logger(
 
                 tracing_subscriber::registry()
                     .with(filter)
+                    .with(RepeatLayer)
                     .with(
                         fmt::layer()
                             .with_writer(non_blocking)
@@ -90,6 +117,7 @@ pub fn init_logger(
                 // Text to stdout (default)
                 tracing_subscriber::registry()
                     .with(filter)
+                    .with(RepeatLayer)
                     .with(
                         fmt::layer()
                             .with_target(false)
@@ -104,17 +132,105 @@ pub fn init_logger(
     Ok(())
 }
 
+/// Initialize warning output of one-shot commands: plain lines on stderr
+///
+/// Keeps stdout for command output; `RUST_LOG` overrides the `warn` level.
+///
+/// # Errors
+///
+/// Returns an error if a global subscriber is already installed.
+pub fn init_stderr_logger() -> Result<()> {
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => build_filter(Level::WARN, None)?,
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(RepeatLayer)
+        .with(
+            fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_ansi(std::io::stderr().is_terminal())
+                .without_time()
+                .with_target(false),
+        )
+        .try_init()?;
+    Ok(())
+}
+
+/// `aurynx=<level>` followed by the `log_filter` directives, which take
+/// precedence for the modules they name
+fn build_filter(level: Level, log_filter: Option<&str>) -> Result<EnvFilter> {
+    let mut directives = format!("aurynx={level}");
+    if let Some(log_filter) = log_filter.filter(|f| !f.trim().is_empty()) {
+        directives.push(',');
+        directives.push_str(log_filter.trim());
+    }
+    EnvFilter::try_new(&directives).map_err(|e| anyhow!("Invalid log filter '{directives}': {e}"))
+}
+
+/// Check `log_filter` directives without installing them
+///
+/// # Errors
+///
+/// Returns an error describing the first invalid directive.
+pub fn validate_filter(log_filter: &str) -> Result<()> {
+    build_filter(Level::INFO, Some(log_filter)).map(|_| ())
+}
+
+#[cfg(unix)]
+fn init_system_logger(target: &str, filter: EnvFilter) -> Result<()> {
+    use system_log::{JOURNALD_SOCKET, Protocol, SYSLOG_SOCKET, SystemLogLayer};
+
+    let (protocol, socket) = match target {
+        "syslog" => (Protocol::Syslog, SYSLOG_SOCKET),
+        "journald" => (Protocol::Journald, JOURNALD_SOCKET),
+        _ => bail!("Invalid log target '{target}'. Allowed: {LOG_TARGETS:?}"),
+    };
+    let layer = SystemLogLayer::connect(protocol, Path::new(socket))
+        .with_context(|| format!("Failed to connect to {target} at {socket}"))?;
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(RepeatLayer)
+        .with(layer)
+        .try_init()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn init_system_logger(target: &str, _filter: EnvFilter) -> Result<()> {
+    bail!("Log target '{target}' is only available on Unix")
+}
+
 #[cfg(test)]
 mod tests {
+    #![allow(clippy::unwrap_used)]
     use super::*;
 
     #[test]
     fn test_init_logger_twice_does_not_panic() {
         // First init
-        let _ = init_logger(None, "debug", "text", false);
+        let _ = init_logger(None, None, "debug", None, "text", false);
 
         // Second init - should return error but not panic
-        let res = init_logger(None, "debug", "text", false);
+        let res = init_logger(None, None, "debug", None, "text", false);
         assert!(res.is_err());
     }
+
+    #[test]
+    fn test_log_filter_directives() {
+        let filter = build_filter(
+            Level::INFO,
+            Some("aurynx::daemon=debug,aurynx::parser=warn"),
+        )
+        .unwrap();
+        let directives = filter.to_string();
+        assert!(directives.contains("aurynx::daemon=debug"), "{directives}");
+        assert!(directives.contains("aurynx::parser=warn"), "{directives}");
+        assert!(directives.contains("aurynx=info"), "{directives}");
+
+        assert!(validate_filter("aurynx::parser=loud").is_err());
+        assert!(validate_filter("").is_ok());
+    }
 }
@@ -0,0 +1,197 @@
+//! Aggregation of repeated warnings
+//!
+//! A scan over a tree with many oversized files or a bad ignore pattern would
+//! otherwise log the same warning thousands of times. Per call site, the first
+//! warning passes and further ones within [`WINDOW`] are only counted;
+//! [`flush_repeated`] reports each count as one "repeated N times" warning.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fmt;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tracing::callsite::Identifier;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber, warn};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+
+/// How long further warnings of a call site are counted instead of logged
+pub const WINDOW: Duration = Duration::from_secs(10);
+
+/// Target of the summaries, which are never aggregated themselves
+const SUMMARY_TARGET: &str = "aurynx::logger";
+
+static REPEATS: Mutex<Repeats> = Mutex::new(Repeats {
+    sites: None,
+    ended: Vec::new(),
+});
+
+#[derive(Debug)]
+struct Repeats {
+    /// Call sites with a logged warning in the current window
+    sites: Option<HashMap<Identifier, Site>>,
+    /// Windows that ended with suppressed warnings, not yet reported
+    ended: Vec<Site>,
+}
+
+#[derive(Debug)]
+struct Site {
+    /// Message of the warning that opened the window
+    message: String,
+    since: Instant,
+    suppressed: usize,
+}
+
+impl Repeats {
+    /// Whether a warning from this call site opens a new window
+    fn admit(&mut self, callsite: Identifier, event: &Event<'_>) -> bool {
+        let now = Instant::now();
+        match self.sites.get_or_insert_with(HashMap::new).entry(callsite) {
+            Entry::Occupied(mut site) if now.duration_since(site.get().since) < WINDOW => {
+                site.get_mut().suppressed += 1;
+                false
+            },
+            entry => {
+                let site = Site {
+                    message: message(event),
+                    since: now,
+                    suppressed: 0,
+                };
+                match entry {
+                    Entry::Occupied(mut previous) => {
+                        let previous = previous.insert(site);
+                        if previous.suppressed > 0 {
+                            self.ended.push(previous);
+                        }
+                    },
+                    Entry::Vacant(vacant) => {
+                        vacant.insert(site);
+                    },
+                }
+                true
+            },
+        }
+    }
+
+    /// Close windows that have ended (all with `force`) and take the ones
+    /// with suppressed warnings
+    fn take_ended(&mut self, force: bool) -> Vec<Site> {
+        let now = Instant::now();
+        if let Some(sites) = &mut self.sites {
+            for (_, site) in
+                sites.extract_if(|_, site| force || now.duration_since(site.since) >= WINDOW)
+            {
+                if site.suppressed > 0 {
+                    self.ended.push(site);
+                }
+            }
+        }
+        std::mem::take(&mut self.ended)
+    }
+}
+
+/// Layer suppressing repeated warnings for every other layer
+#[derive(Debug, Default)]
+pub struct RepeatLayer;
+
+impl<S: Subscriber> Layer<S> for RepeatLayer {
+    fn event_enabled(&self, event: &Event<'_>, _ctx: Context<'_, S>) -> bool {
+        let metadata = event.metadata();
+        if *metadata.level() != Level::WARN || metadata.target() == SUMMARY_TARGET {
+            return true;
+        }
+        REPEATS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .admit(metadata.callsite(), event)
+    }
+}
+
+/// Log how often each warning was suppressed
+///
+/// Covers call sites whose window has ended, or all of them with `force`
+/// (on shutdown). Cheap enough to call on every iteration of the daemon loop.
+pub fn flush_repeated(force: bool) {
+    // Taken before logging, as the layer locks `REPEATS` for every warning
+    let ended = REPEATS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take_ended(force);
+
+    for site in ended {
+        warn!(
+            target: SUMMARY_TARGET,
+            repeated = site.suppressed,
+            "Repeated {} more times in {}s: {}",
+            site.suppressed,
+            site.since.elapsed().as_secs(),
+            site.message
+        );
+    }
+}
+
+/// The `message` field of an event
+fn message(event: &Event<'_>) -> String {
+    struct MessageVisitor(String);
+
+    impl Visit for MessageVisitor {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                self.0 = format!("{value:?}");
+            }
+        }
+    }
+
+    let mut visitor = MessageVisitor(String::new());
+    event.record(&mut visitor);
+    visitor.0
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use std::sync::Arc;
+    use tracing_subscriber::prelude::*;
+
+    /// Records the message of every event it sees
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<String>>>);
+
+    impl<S: Subscriber> Layer<S> for Capture {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            self.0.lock().unwrap().push(message(event));
+        }
+    }
+
+    #[test]
+    fn test_repeated_warnings_are_counted() {
+        let capture = Capture::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(RepeatLayer)
+            .with(capture.clone());
+        let skip = |i: usize| warn!("Skipping large file: {i}.php");
+        tracing::subscriber::with_default(subscriber, || {
+            for i in 0..5 {
+                skip(i);
+            }
+            tracing::info!("Scan complete");
+            flush_repeated(true);
+            skip(5);
+        });
+
+        // Other tests may log warnings through the same global state
+        let mut messages = capture.0.lock().unwrap().clone();
+        messages.retain(|m| m.contains("large file") || m == "Scan complete");
+        assert_eq!(
+            messages,
+            [
+                "Skipping large file: 0.php",
+                "Scan complete",
+                "Repeated 4 more times in 0s: Skipping large file: 0.php",
+                "Skipping large file: 5.php",
+            ]
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Syslog and journald log targets (`log_target`)
+//!
+//! Events are sent as datagrams to the local socket of the system logger:
+//! RFC 3164 lines to `/dev/log`, or the native journal protocol to
+//! `/run/systemd/journal/socket`, where event fields become journal fields.
+//! Tracing levels map to syslog severities (error → 3, warn → 4, info → 6,
+//! debug and trace → 7).
+
+use std::fmt::{self, Write as _};
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+
+/// `SYSLOG_IDENTIFIER` / syslog tag
+const IDENTIFIER: &str = "aurynx";
+
+/// Syslog facility `daemon`
+const FACILITY_DAEMON: u8 = 3;
+
+#[cfg(target_os = "macos")]
+pub const SYSLOG_SOCKET: &str = "/var/run/syslog";
+#[cfg(not(target_os = "macos"))]
+pub const SYSLOG_SOCKET: &str = "/dev/log";
+
+pub const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Wire format of the system logger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Syslog,
+    Journald,
+}
+
+/// Tracing layer writing every event to the system logger
+pub struct SystemLogLayer {
+    socket: UnixDatagram,
+    protocol: Protocol,
+}
+
+impl SystemLogLayer {
+    /// Connect to the logger socket at `path`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the socket does not exist or refuses the connection.
+    pub fn connect(protocol: Protocol, path: &Path) -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(Self { socket, protocol })
+    }
+}
+
+impl<S: Subscriber> Layer<S> for SystemLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+
+        let level = *event.metadata().level();
+        let datagram = match self.protocol {
+            Protocol::Syslog => syslog_datagram(level, &fields.text()),
+            Protocol::Journald => journald_datagram(level, &fields),
+        };
+        // Logging must never fail the daemon; a full or missing socket drops the line
+        let _ = self.socket.send(&datagram);
+    }
+}
+
+/// Syslog severity of a tracing level
+const fn severity(level: Level) -> u8 {
+    match level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 6,
+        Level::DEBUG | Level::TRACE => 7,
+    }
+}
+
+/// Message and remaining fields of one event
+#[derive(Debug, Default)]
+struct Fields {
+    message: String,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl Fields {
+    /// `message key=value ...` as written by the text formatter
+    fn text(&self) -> String {
+        let mut text = self.message.clone();
+        for (name, value) in &self.fields {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            let _ = write!(text, "{name}={value}");
+        }
+        text
+    }
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.push((field.name(), value.to_string()));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        } else {
+            self.fields.push((field.name(), format!("{value:?}")));
+        }
+    }
+}
+
+/// `<PRI>aurynx[PID]: message` (RFC 3164 without timestamp, which the local logger adds)
+fn syslog_datagram(level: Level, text: &str) -> Vec<u8> {
+    let priority = FACILITY_DAEMON * 8 + severity(level);
+    format!("<{priority}>{IDENTIFIER}[{}]: {text}", std::process::id()).into_bytes()
+}
+
+/// Native journal protocol: `KEY=value` lines, or `KEY\n<u64 le length><value>\n`
+/// for values containing newlines
+fn journald_datagram(level: Level, fields: &Fields) -> Vec<u8> {
+    let mut datagram = Vec::new();
+    append_journal_field(&mut datagram, "MESSAGE", &fields.message);
+    append_journal_field(&mut datagram, "PRIORITY", &severity(level).to_string());
+    append_journal_field(&mut datagram, "SYSLOG_IDENTIFIER", IDENTIFIER);
+    for (name, value) in &fields.fields {
+        if let Some(key) = journal_key(name) {
+            append_journal_field(&mut datagram, &key, value);
+        }
+    }
+    datagram
+}
+
+fn append_journal_field(datagram: &mut Vec<u8>, key: &str, value: &str) {
+    datagram.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        datagram.push(b'\n');
+        datagram.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        datagram.push(b'=');
+    }
+    datagram.extend_from_slice(value.as_bytes());
+    datagram.push(b'\n');
+}
+
+/// Journal field name of an event field: uppercase letters, digits and `_`,
+/// not starting with `_` (reserved for trusted fields) or a digit
+fn journal_key(name: &str) -> Option<String> {
+    let key: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let key = key.trim_start_matches(|c: char| c == '_' || c.is_ascii_digit());
+    // Fields the layer sets itself are not overridden
+    (!key.is_empty() && !matches!(key, "MESSAGE" | "PRIORITY" | "SYSLOG_IDENTIFIER"))
+        .then(|| key.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use tempfile::TempDir;
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn test_syslog_priorities() {
+        let pid = std::process::id();
+        assert_eq!(
+            syslog_datagram(Level::ERROR, "boom"),
+            format!("<27>aurynx[{pid}]: boom").into_bytes()
+        );
+        assert!(syslog_datagram(Level::INFO, "x").starts_with(b"<30>"));
+        assert!(syslog_datagram(Level::TRACE, "x").starts_with(b"<31>"));
+    }
+
+    #[test]
+    fn test_journald_layer_sends_fields() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("journal.socket");
+        let journal = UnixDatagram::bind(&path).unwrap();
+
+        let layer = SystemLogLayer::connect(Protocol::Journald, &path).unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(signal = "SIGTERM", "Received\nSIGTERM");
+        });
+
+        let mut buffer = [0; 1024];
+        let len = journal.recv(&mut buffer).unwrap();
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&16u64.to_le_bytes());
+        expected.extend_from_slice(
+            b"Received\nSIGTERM\nPRIORITY=4\nSYSLOG_IDENTIFIER=aurynx\nSIGNAL=SIGTERM\n",
+        );
+        assert_eq!(&buffer[..len], expected.as_slice());
+    }
+
+    #[test]
+    fn test_journal_keys() {
+        assert_eq!(journal_key("path").as_deref(), Some("PATH"));
+        assert_eq!(journal_key("lock.path").as_deref(), Some("LOCK_PATH"));
+        assert_eq!(journal_key("_pid").as_deref(), Some("PID"));
+        assert_eq!(journal_key("message"), None);
+    }
+}
@@ -0,0 +1,536 @@
+//! Minimal Language Server Protocol mode (`aurynx lsp`)
+//!
+//! Speaks JSON-RPC over stdio with `Content-Length` framing, so editor
+//! extensions can use their stock LSP client instead of a socket client for
+//! the daemon. Supported requests:
+//!
+//! - `workspace/symbol`: classes, interfaces, traits and enums whose FQCN
+//!   contains the query (case-insensitive)
+//! - `aurynx/attributeUsages` (`{"attribute": FQCN}`): every location where
+//!   the attribute is applied, with the class and member it is applied to
+//! - `aurynx/class` (`{"fqcn": FQCN}`): the cached metadata of one class, or
+//!   `null`
+//!
+//! Classes come from the manifest the daemon rewrites on every flush
+//! (`aurynx.meta.json` next to the cache file), reloaded whenever it changes.
+//! Without a running daemon the paths are scanned once, and again after a
+//! `textDocument/didSave`. The cache has no positions, so the files of the
+//! returned classes are re-parsed (see `crate::ide_index`).
+
+use crate::ide_index::{self, IndexedClass};
+use crate::incremental::Manifest;
+use crate::metadata::PhpClassMetadata;
+use crate::parser::PhpMetadataExtractor;
+use anyhow::{Context, Result, bail};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::warn;
+
+/// Maximum number of `workspace/symbol` results
+const MAX_SYMBOLS: usize = 200;
+
+// JSON-RPC and LSP error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+/// Where the server gets its classes from
+pub struct ClassSource {
+    /// Manifest written by the daemon
+    manifest: PathBuf,
+    paths: Vec<PathBuf>,
+    ignore: Vec<String>,
+    max_file_size: u64,
+    /// Modification time of the loaded manifest (`None` after a fallback scan)
+    loaded: Option<SystemTime>,
+    stale: bool,
+    classes: Vec<PhpClassMetadata>,
+}
+
+impl ClassSource {
+    /// Classes of the daemon writing `output`, or of a scan of `paths`
+    #[must_use]
+    pub fn new(
+        output: &Path, paths: Vec<PathBuf>, ignore: Vec<String>, max_file_size: u64,
+    ) -> Self {
+        let manifest = output.parent().map_or_else(
+            || PathBuf::from(crate::incremental::MANIFEST_FILE),
+            |parent| parent.join(crate::incremental::MANIFEST_FILE),
+        );
+        Self {
+            manifest,
+            paths,
+            ignore,
+            max_file_size,
+            loaded: None,
+            stale: true,
+            classes: Vec::new(),
+        }
+    }
+
+    /// Current classes, reloaded if the daemon flushed since the last call
+    pub fn classes(&mut self) -> &[PhpClassMetadata] {
+        let modified = std::fs::metadata(&self.manifest)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        match modified {
+            Some(modified) if self.loaded != Some(modified) => match Manifest::load(&self.manifest)
+            {
+                Ok(manifest) => {
+                    self.classes = manifest
+                        .files
+                        .into_values()
+                        .flat_map(|entry| entry.classes)
+                        .collect();
+                    self.loaded = Some(modified);
+                    self.stale = false;
+                },
+                Err(e) => warn!("Could not load {:?}: {}", self.manifest, e),
+            },
+            None if self.stale => {
+                self.classes = crate::scanner::scan_directory_with_limit(
+                    &self.paths,
+                    &self.ignore,
+                    self.max_file_size,
+                    false,
+                );
+                self.loaded = None;
+                self.stale = false;
+            },
+            _ => {},
+        }
+        &self.classes
+    }
+
+    /// Scan again on the next request (no effect while the daemon manifest is used)
+    const fn invalidate(&mut self) {
+        self.stale = true;
+    }
+}
+
+/// Serve LSP requests from `input` until the `exit` notification
+///
+/// # Errors
+///
+/// Returns an error if the transport fails or the client disconnects
+/// without `exit`.
+pub fn serve(
+    source: &mut ClassSource, mut input: impl BufRead, mut output: impl Write,
+) -> Result<()> {
+    let mut server = Server {
+        source,
+        extractor: PhpMetadataExtractor::new()?,
+        initialized: false,
+        shutdown: false,
+    };
+
+    loop {
+        let Some(body) = read_message(&mut input)? else {
+            bail!("Client disconnected without exit");
+        };
+        let message: Value = match serde_json::from_slice(&body) {
+            Ok(message) => message,
+            Err(e) => {
+                let response = error_response(&Value::Null, PARSE_ERROR, &e.to_string());
+                write_message(&mut output, &response)?;
+                continue;
+            },
+        };
+
+        let method = message["method"].as_str().unwrap_or_default();
+        if method == "exit" {
+            return Ok(());
+        }
+
+        // Notifications have no id and get no response
+        let Some(id) = message.get("id") else {
+            server.notify(method);
+            continue;
+        };
+        let response = match server.handle(method, &message["params"]) {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err((code, error)) => error_response(id, code, &error),
+        };
+        write_message(&mut output, &response)?;
+    }
+}
+
+struct Server<'a> {
+    source: &'a mut ClassSource,
+    extractor: PhpMetadataExtractor,
+    initialized: bool,
+    shutdown: bool,
+}
+
+impl Server<'_> {
+    fn notify(&mut self, method: &str) {
+        if method == "textDocument/didSave" {
+            self.source.invalidate();
+        }
+    }
+
+    fn handle(
+        &mut self, method: &str, params: &Value,
+    ) -> std::result::Result<Value, (i64, String)> {
+        if method == "initialize" {
+            self.initialized = true;
+            return Ok(json!({
+                "capabilities": {
+                    "workspaceSymbolProvider": true,
+                    "textDocumentSync": {"openClose": false, "change": 0, "save": true},
+                },
+                "serverInfo": {"name": "aurynx", "version": env!("CARGO_PKG_VERSION")},
+            }));
+        }
+        if !self.initialized {
+            return Err((SERVER_NOT_INITIALIZED, "Server not initialized".to_string()));
+        }
+        if self.shutdown {
+            return Err((INVALID_REQUEST, "Server is shutting down".to_string()));
+        }
+
+        match method {
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            },
+            "workspace/symbol" => {
+                let query = params["query"].as_str().unwrap_or_default();
+                Ok(Value::Array(self.workspace_symbols(query)))
+            },
+            "aurynx/attributeUsages" => {
+                let attribute = required_param(params, "attribute")?;
+                Ok(Value::Array(self.attribute_usages(attribute)))
+            },
+            "aurynx/class" => {
+                let fqcn = normalize(required_param(params, "fqcn")?);
+                let class = self
+                    .source
+                    .classes()
+                    .iter()
+                    .find(|class| normalize(&class.fqcn) == fqcn);
+                Ok(serde_json::to_value(class).unwrap_or_default())
+            },
+            _ => Err((METHOD_NOT_FOUND, format!("Unsupported method: {method}"))),
+        }
+    }
+
+    fn workspace_symbols(&mut self, query: &str) -> Vec<Value> {
+        let query = query.to_lowercase();
+        let mut by_file: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+        for class in self.source.classes() {
+            let fqcn = normalize(&class.fqcn);
+            if fqcn.to_lowercase().contains(&query) {
+                by_file
+                    .entry(class.file.clone())
+                    .or_default()
+                    .push(fqcn.to_string());
+            }
+        }
+
+        let mut symbols = Vec::new();
+        for (file, fqcns) in by_file {
+            let Some((content, classes)) = self.index(&file) else {
+                continue;
+            };
+            for class in classes.iter().filter(|class| fqcns.contains(&class.fqcn)) {
+                let (namespace, name) = class.fqcn.rsplit_once('\\').unwrap_or(("", &class.fqcn));
+                let start = lsp_position(&content, class.position);
+                let end = json!({
+                    "line": start["line"],
+                    "character": start["character"].as_u64().unwrap_or_default()
+                        + name.encode_utf16().count() as u64,
+                });
+                symbols.push(json!({
+                    "name": name,
+                    "kind": symbol_kind(&class.kind),
+                    "location": {"uri": file_uri(&file), "range": {"start": start, "end": end}},
+                    "containerName": namespace,
+                }));
+                if symbols.len() == MAX_SYMBOLS {
+                    return symbols;
+                }
+            }
+        }
+        symbols
+    }
+
+    fn attribute_usages(&mut self, attribute: &str) -> Vec<Value> {
+        let attribute = normalize(attribute);
+        let mut files: Vec<PathBuf> = self
+            .source
+            .classes()
+            .iter()
+            .filter(|class| uses_attribute(class, attribute))
+            .map(|class| class.file.clone())
+            .collect();
+        files.sort();
+        files.dedup();
+
+        let mut usages = Vec::new();
+        for file in files {
+            let Some((content, classes)) = self.index(&file) else {
+                continue;
+            };
+            let index = ide_index::group_usages(&classes);
+            for usage in index.get(attribute).into_iter().flatten() {
+                usages.push(json!({
+                    "uri": file_uri(&file),
+                    "range": {
+                        "start": lsp_position(&content, usage.span.start),
+                        "end": lsp_position(&content, usage.span.end),
+                    },
+                    "class": usage.class,
+                    "member": usage.member,
+                }));
+            }
+        }
+        usages
+    }
+
+    /// Source and declarations of `file`, as currently on disk
+    fn index(&mut self, file: &Path) -> Option<(String, Vec<IndexedClass>)> {
+        let content = std::fs::read_to_string(file).ok()?;
+        match ide_index::index_source(&mut self.extractor, &content, file) {
+            Ok(classes) => Some((content, classes)),
+            Err(e) => {
+                warn!("Could not index {:?}: {}", file, e);
+                None
+            },
+        }
+    }
+}
+
+/// Whether the class, a member or a parameter declares `attribute`
+fn uses_attribute(class: &PhpClassMetadata, attribute: &str) -> bool {
+    let has = |attributes: &crate::metadata::AttributeMap| {
+        attributes.keys().any(|name| normalize(name) == attribute)
+    };
+    has(&class.attributes)
+        || class.methods.iter().any(|method| {
+            has(&method.attributes)
+                || method
+                    .parameters
+                    .iter()
+                    .any(|parameter| has(&parameter.attributes))
+        })
+        || class
+            .properties
+            .iter()
+            .any(|property| has(&property.attributes))
+        || class.cases.iter().any(|case| has(&case.attributes))
+}
+
+fn required_param<'p>(
+    params: &'p Value, name: &str,
+) -> std::result::Result<&'p str, (i64, String)> {
+    params[name]
+        .as_str()
+        .ok_or_else(|| (INVALID_PARAMS, format!("Missing string parameter '{name}'")))
+}
+
+fn normalize(fqcn: &str) -> &str {
+    fqcn.trim_start_matches('\\')
+}
+
+/// LSP `SymbolKind`: PHP traits have no kind of their own
+fn symbol_kind(kind: &str) -> u8 {
+    match kind {
+        "interface" => 11,
+        "enum" => 10,
+        _ => 5,
+    }
+}
+
+/// 0-based LSP position (UTF-16 columns) of a 1-based byte position
+fn lsp_position(content: &str, position: ide_index::Position) -> Value {
+    let line = position.line - 1;
+    let text = content.lines().nth(line).unwrap_or_default();
+    let prefix = text.get(..position.column - 1).unwrap_or(text);
+    json!({"line": line, "character": prefix.encode_utf16().count()})
+}
+
+/// `file://` URI of an absolute path
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(char::from(byte));
+        } else {
+            let _ = write!(uri, "%{byte:02X}");
+        }
+    }
+    uri
+}
+
+fn error_response(id: &Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+/// Read one `Content-Length` framed message (`None` at end of input)
+fn read_message(input: &mut impl BufRead) -> Result<Option<Vec<u8>>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = Some(
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .context("Invalid Content-Length")?,
+            );
+        }
+    }
+
+    let Some(length) = length else {
+        bail!("Message without Content-Length header");
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> Result<()> {
+    let body = serde_json::to_vec(message)?;
+    write!(output, "Content-Length: {}\r\n\r\n", body.len())?;
+    output.write_all(&body)?;
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn frame(messages: &[Value]) -> Vec<u8> {
+        let mut input = Vec::new();
+        for message in messages {
+            write_message(&mut input, message).unwrap();
+        }
+        input
+    }
+
+    fn responses(mut output: &[u8]) -> Vec<Value> {
+        let mut responses = Vec::new();
+        while let Some(body) = read_message(&mut output).unwrap() {
+            responses.push(serde_json::from_slice(&body).unwrap());
+        }
+        responses
+    }
+
+    fn request(id: u64, method: &str, params: &Value) -> Value {
+        json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+    }
+
+    #[test]
+    fn test_serve_symbols_and_attribute_usages() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("User Controller.php");
+        std::fs::write(
+            &file,
+            "<?php\nnamespace App;\n\nuse Attr\\Route;\n\n#[Route('/users')]\nclass UserController\n{\n    #[Route('/{id}')] public function show() {}\n}\n",
+        )
+        .unwrap();
+        let mut source = ClassSource::new(
+            &dir.path().join("cache/classes.php"),
+            vec![dir.path().to_path_buf()],
+            vec![],
+            1024 * 1024,
+        );
+
+        let input = frame(&[
+            request(0, "workspace/symbol", &json!({"query": "user"})),
+            request(1, "initialize", &json!({"capabilities": {}})),
+            json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}),
+            request(2, "workspace/symbol", &json!({"query": "usercontroller"})),
+            request(
+                3,
+                "aurynx/attributeUsages",
+                &json!({"attribute": "\\Attr\\Route"}),
+            ),
+            request(4, "aurynx/class", &json!({"fqcn": "App\\UserController"})),
+            request(5, "aurynx/unknown", &json!({})),
+            request(6, "shutdown", &Value::Null),
+            json!({"jsonrpc": "2.0", "method": "exit"}),
+        ]);
+        let mut output = Vec::new();
+        serve(&mut source, input.as_slice(), &mut output).unwrap();
+        let responses = responses(&output);
+
+        assert_eq!(responses[0]["error"]["code"], SERVER_NOT_INITIALIZED);
+        assert_eq!(
+            responses[1]["result"]["capabilities"]["workspaceSymbolProvider"],
+            true
+        );
+
+        let uri = file_uri(&file);
+        assert!(uri.ends_with("/User%20Controller.php"));
+        assert_eq!(
+            responses[2]["result"],
+            json!([{
+                "name": "UserController",
+                "kind": 5,
+                "location": {"uri": uri, "range": {
+                    "start": {"line": 6, "character": 6},
+                    "end": {"line": 6, "character": 20},
+                }},
+                "containerName": "App",
+            }])
+        );
+
+        let usages = responses[3]["result"].as_array().unwrap();
+        assert_eq!(usages.len(), 2);
+        assert_eq!(usages[0]["member"], Value::Null);
+        assert_eq!(usages[1]["member"], "show");
+        assert_eq!(
+            usages[1]["range"]["start"],
+            json!({"line": 8, "character": 6})
+        );
+
+        assert_eq!(responses[4]["result"]["fqcn"], "\\App\\UserController");
+        assert_eq!(responses[5]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses[6]["result"], Value::Null);
+    }
+
+    #[test]
+    fn test_source_follows_daemon_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("classes.php");
+        let mut source = ClassSource::new(&output, vec![], vec![], 1024);
+        assert!(source.classes().is_empty());
+
+        let mut manifest = Manifest::default();
+        manifest.files.insert(
+            "/src/A.php".to_string(),
+            crate::incremental::FileEntry {
+                classes: vec![PhpClassMetadata::new(
+                    "\\App\\A".to_string(),
+                    PathBuf::from("/src/A.php"),
+                    "class".to_string(),
+                )],
+                ..Default::default()
+            },
+        );
+        manifest
+            .save(&dir.path().join(crate::incremental::MANIFEST_FILE))
+            .unwrap();
+
+        assert_eq!(source.classes()[0].fqcn, "\\App\\A");
+    }
+}
@@ -1,9 +1,9 @@
 use aurynx::daemon::{Daemon, DaemonConfig};
-use aurynx::scanner::scan_directory;
+use aurynx::php_version::PhpVersion;
 use aurynx::writer::write_php_cache;
 use clap::{Parser, Subcommand};
 use std::io::IsTerminal;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(
@@ -22,7 +22,138 @@ struct Cli {
 enum Commands {
     /// PHP attribute discovery and metadata extraction
     #[command(name = "discovery:scan", visible_alias = "discovery")]
-    DiscoveryScan {
+    DiscoveryScan(Box<ScanArgs>),
+
+    /// Measure scan throughput per phase and compare against a stored baseline
+    #[command(name = "discovery:benchmark")]
+    DiscoveryBenchmark {
+        /// Configuration file path (defaults to aurynx.json)
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Directories to scan for PHP files
+        #[arg(short, long, num_args = 1..)]
+        path: Option<Vec<PathBuf>>,
+
+        /// Ignore patterns (can be used multiple times)
+        #[arg(short, long)]
+        ignore: Option<Vec<String>>,
+
+        /// Number of runs; the first is reported as cold, the median of the rest as warm
+        #[arg(short = 'n', long, default_value_t = 5)]
+        iterations: usize,
+
+        /// Cache format rendered in the render phase: php or json
+        #[arg(long, value_parser = ["php", "json"])]
+        format: Option<String>,
+
+        /// Baseline file to compare against
+        #[arg(long, value_name = "FILE")]
+        baseline: Option<PathBuf>,
+
+        /// Store this run as the new baseline (requires --baseline)
+        #[arg(long, requires = "baseline")]
+        save_baseline: bool,
+    },
+
+    /// Export discovered metadata in a framework-specific format
+    #[command(name = "discovery:export")]
+    DiscoveryExport {
+        /// What to export: routes (route table), tests (test manifest), container
+        /// (service definitions, mapped in the `container` config section),
+        /// entities (Doctrine entity map), redis (per-class hash entries),
+        /// apcu (per-class priming script), ide (editor index with positions) or
+        /// unused-imports (class imports no name in the file refers to)
+        #[arg(value_parser = [
+            "routes", "tests", "container", "entities", "redis", "apcu", "ide", "unused-imports",
+        ])]
+        target: String,
+
+        /// Layout: symfony or laravel for routes, phpunit (default) for tests,
+        /// doctrine (default) for entities
+        #[arg(long, value_parser = ["symfony", "laravel", "phpunit", "doctrine"])]
+        preset: Option<String>,
+
+        /// Configuration file path (defaults to aurynx.json)
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Directories to scan for PHP files
+        #[arg(short, long, num_args = 1..)]
+        path: Option<Vec<PathBuf>>,
+
+        /// Ignore patterns (can be used multiple times)
+        #[arg(short, long)]
+        ignore: Option<Vec<String>>,
+
+        /// File to write (for redis: a `redis-cli --pipe` protocol file)
+        #[arg(short, long, required_unless_present = "redis")]
+        output: Option<PathBuf>,
+
+        /// Redis hash key, or `APCu` key prefix followed by ':' (default: aurynx:classes)
+        #[arg(long)]
+        key: Option<String>,
+
+        /// Send the redis export to this server (redis://[[user]:password@]host[:port][/db])
+        #[arg(long, value_name = "URL")]
+        redis: Option<String>,
+
+        /// `APCu` entry lifetime in seconds (0 = until the cache is cleared)
+        #[arg(long, default_value_t = 0)]
+        ttl: u64,
+    },
+
+    /// Render a Jinja template with the discovered metadata
+    #[command(name = "discovery:render")]
+    DiscoveryRender {
+        /// Template file (`include` and `extends` resolve next to it)
+        #[arg(long, value_name = "FILE")]
+        template: PathBuf,
+
+        /// File to write
+        #[arg(long, value_name = "FILE")]
+        out: PathBuf,
+
+        /// Configuration file path (defaults to aurynx.json)
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Directories to scan for PHP files
+        #[arg(short, long, num_args = 1..)]
+        path: Option<Vec<PathBuf>>,
+
+        /// Ignore patterns (can be used multiple times)
+        #[arg(short, long)]
+        ignore: Option<Vec<String>>,
+    },
+
+    /// Print the change journal written by the daemon (`journal_file`)
+    #[command(name = "discovery:log")]
+    DiscoveryLog {
+        /// Configuration file path (defaults to aurynx.json)
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Journal file (defaults to `journal_file` from the config)
+        #[arg(long, value_name = "FILE")]
+        journal: Option<PathBuf>,
+
+        /// Only entries listing a class whose FQCN contains this
+        #[arg(long, value_name = "FQCN")]
+        class: Option<String>,
+
+        /// Show the last N matching entries
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+
+        /// Output format
+        #[arg(long, value_parser = ["text", "json"], default_value = "text")]
+        format: String,
+    },
+
+    /// Check the `policies` config rules; exits with 1 on violations
+    #[command(name = "discovery:check")]
+    DiscoveryCheck {
         /// Configuration file path (defaults to aurynx.json)
         #[arg(long)]
         config: Option<PathBuf>,
@@ -31,280 +162,2257 @@ enum Commands {
         #[arg(short, long, num_args = 1..)]
         path: Option<Vec<PathBuf>>,
 
-        /// Output cache file path
+        /// Ignore patterns (can be used multiple times)
+        #[arg(short, long)]
+        ignore: Option<Vec<String>>,
+
+        /// Report format: text (one line per violation) or json
+        #[arg(long, value_parser = ["text", "json"], default_value = "text")]
+        format: String,
+
+        /// Baseline of known violations to suppress
+        #[arg(long, value_name = "FILE")]
+        baseline: Option<PathBuf>,
+
+        /// Write the current violations to the baseline instead of failing (requires --baseline)
+        #[arg(long, requires = "baseline")]
+        generate_baseline: bool,
+    },
+
+    /// Compare the cache with Composer's classmap; exits with 1 on differences
+    #[command(name = "discovery:verify-classmap")]
+    DiscoveryVerifyClassmap {
+        /// Configuration file path (defaults to aurynx.json)
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Scanned directories; classmap entries outside them are not checked
+        #[arg(short, long, num_args = 1..)]
+        path: Option<Vec<PathBuf>>,
+
+        /// Ignore patterns (can be used multiple times)
+        #[arg(short, long)]
+        ignore: Option<Vec<String>>,
+
+        /// Cache file to check (defaults to 'output' in config file)
         #[arg(short, long)]
         output: Option<PathBuf>,
 
-        /// Ignore patterns (can be used multiple times, e.g., --ignore "vendor/*" --ignore "tests/*")
+        /// Composer classmap (generate a complete one with `composer dump-autoload -o`)
+        #[arg(long, default_value = aurynx::composer::DEFAULT_CLASSMAP)]
+        classmap: PathBuf,
+
+        /// Report format: text (one line per difference) or json
+        #[arg(long, value_parser = ["text", "json"], default_value = "text")]
+        format: String,
+    },
+
+    /// Install the watch daemon as a user service (systemd user unit or launchd agent)
+    #[command(name = "discovery:install-service")]
+    DiscoveryInstallService {
+        /// Install a systemd user unit (default except on macOS)
+        #[arg(long, conflicts_with = "launchd")]
+        systemd_user: bool,
+
+        /// Install a launchd agent (default on macOS)
+        #[arg(long)]
+        launchd: bool,
+
+        /// Configuration file path (defaults to aurynx.json)
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Directories to watch
+        #[arg(short, long, num_args = 1..)]
+        path: Option<Vec<PathBuf>>,
+
+        /// Ignore patterns (can be used multiple times)
         #[arg(short, long)]
         ignore: Option<Vec<String>>,
 
-        /// Watch for file changes and run as daemon (requires --socket and --pid)
+        /// Cache file path
         #[arg(short, long)]
-        watch: bool,
+        output: Option<PathBuf>,
 
-        /// Unix socket path for IPC (required with --watch)
+        /// Unix socket path for IPC
         #[arg(short, long)]
         socket: Option<PathBuf>,
 
-        /// PID file path (required with --watch)
+        /// PID file path
         #[arg(long)]
         pid: Option<PathBuf>,
 
-        /// Incremental mode: only rescan changed files (scan mode only)
-        #[arg(long, conflicts_with = "watch")]
-        incremental: bool,
+        /// Unit name / launchd label (defaults to aurynx-discovery-<project dir>)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Print the service file instead of installing it
+        #[arg(long)]
+        print: bool,
+
+        /// Install without enabling or starting the service
+        #[arg(long)]
+        no_start: bool,
+    },
+
+    /// Start the watch daemon if it is not running and wait for its initial scan
+    #[command(name = "discovery:warm")]
+    DiscoveryWarm {
+        /// Configuration file path (defaults to aurynx.json)
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Directories to watch (when the daemon has to be started)
+        #[arg(short, long, num_args = 1..)]
+        path: Option<Vec<PathBuf>>,
+
+        /// Ignore patterns (can be used multiple times)
+        #[arg(short, long)]
+        ignore: Option<Vec<String>>,
+
+        /// Cache file path
+        #[arg(short, long)]
+        output: Option<PathBuf>,
 
-        /// Verbose logging (watch mode only)
+        /// Unix socket path for IPC
         #[arg(short, long)]
-        verbose: bool,
+        socket: Option<PathBuf>,
 
-        /// Log file path (optional, defaults to stdout)
+        /// PID file path
         #[arg(long)]
-        log_file: Option<PathBuf>,
+        pid: Option<PathBuf>,
+
+        /// Seconds to wait for the daemon to finish its initial scan
+        #[arg(long, default_value_t = 120)]
+        timeout: u64,
 
-        /// Log level: trace, debug, info, warn, error
+        /// Request the PHP cache once the daemon is ready (warms its snapshot)
         #[arg(long)]
-        log_level: Option<String>,
+        get_code: bool,
+    },
 
-        /// Log format: text or json
+    /// Atomically install a cache built elsewhere (e.g. at deploy time) with
+    /// its manifest, and make a running daemon reload it
+    #[command(name = "discovery:promote")]
+    DiscoveryPromote {
+        /// Configuration file path (defaults to aurynx.json)
         #[arg(long)]
-        log_format: Option<String>,
+        config: Option<PathBuf>,
+
+        /// Built cache file to install (its manifest is read from the same directory)
+        #[arg(long, value_name = "FILE")]
+        from: PathBuf,
 
-        /// Force restart even if daemon is already running (DANGEROUS: kills existing daemon)
+        /// Cache file to replace (defaults to 'output' in config file)
+        #[arg(long, value_name = "FILE")]
+        to: Option<PathBuf>,
+
+        /// Unix socket of the daemon to notify (defaults to 'socket' in config file)
+        #[arg(short, long)]
+        socket: Option<PathBuf>,
+    },
+
+    /// Make the running daemon write its in-memory state to a file
+    #[command(name = "discovery:snapshot")]
+    DiscoverySnapshot {
+        /// Snapshot file to write, inside the daemon's cache directory
+        #[arg(long, value_name = "FILE")]
+        out: PathBuf,
+
+        /// Configuration file path (defaults to aurynx.json)
         #[arg(long)]
-        force: bool,
+        config: Option<PathBuf>,
 
-        /// Force writing cache to disk in watch mode (useful for debugging/testing)
+        /// Unix socket of the daemon (defaults to 'socket' in config file)
+        #[arg(short, long)]
+        socket: Option<PathBuf>,
+    },
+
+    /// Load a snapshot into the running daemon (watching stays paused until `resume`)
+    #[command(name = "discovery:restore")]
+    DiscoveryRestore {
+        /// Snapshot file written by discovery:snapshot, inside the daemon's cache directory
+        file: PathBuf,
+
+        /// Configuration file path (defaults to aurynx.json)
         #[arg(long)]
-        write_to_disk: bool,
+        config: Option<PathBuf>,
+
+        /// Unix socket of the daemon (defaults to 'socket' in config file)
+        #[arg(short, long)]
+        socket: Option<PathBuf>,
+    },
 
-        /// Pretty print output (formatted with indentation)
+    /// Language server on stdio (workspace symbols, attribute queries) for editor extensions
+    #[command(name = "lsp")]
+    Lsp {
+        /// Configuration file path (defaults to aurynx.json)
         #[arg(long)]
-        pretty: bool,
+        config: Option<PathBuf>,
 
-        /// Output format (currently only 'php' is supported)
-        #[arg(long, default_value = "php", hide = true)]
-        format: String,
+        /// Directories to scan when no daemon is running
+        #[arg(short, long, num_args = 1..)]
+        path: Option<Vec<PathBuf>>,
 
-        /// Include attributes in output (enabled by default)
-        #[arg(long, default_value = "true", hide = true)]
-        include_attributes: bool,
+        /// Ignore patterns (can be used multiple times)
+        #[arg(short, long)]
+        ignore: Option<Vec<String>>,
 
-        /// Include parent classes and interfaces (enabled by default)
-        #[arg(long, default_value = "true", hide = true)]
-        include_parents: bool,
+        /// Cache file of the daemon to follow (its manifest is read from the same directory)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
 }
 
+/// Arguments of `discovery:scan`
+#[derive(clap::Args)]
+#[allow(clippy::struct_excessive_bools)] // One per flag
+struct ScanArgs {
+    /// Configuration file path (defaults to aurynx.json)
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Directories to scan for PHP files
+    #[arg(short, long, num_args = 1..)]
+    path: Option<Vec<PathBuf>>,
+
+    /// Output cache file path
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Ignore patterns (can be used multiple times, e.g., --ignore "vendor/*" --ignore "tests/*")
+    #[arg(short, long)]
+    ignore: Option<Vec<String>>,
+
+    /// Scan files matching these patterns but leave their classes out of the
+    /// cache (can be used multiple times, e.g., --output-exclude "tests/Fixtures/*")
+    #[arg(long, value_name = "PATTERN")]
+    output_exclude: Option<Vec<String>>,
+
+    /// Also scan an installed Composer package, resolved from
+    /// vendor/composer/installed.json (can be used multiple times,
+    /// e.g., --vendor-package doctrine/orm)
+    #[arg(long, value_name = "PACKAGE")]
+    vendor_package: Option<Vec<String>>,
+
+    /// Watch for file changes and run as daemon (requires --socket and --pid)
+    #[arg(short, long)]
+    watch: bool,
+
+    /// Unix socket path for IPC (required with --watch)
+    #[arg(short, long)]
+    socket: Option<PathBuf>,
+
+    /// PID file path (required with --watch)
+    #[arg(long)]
+    pid: Option<PathBuf>,
+
+    /// Incremental mode: only rescan changed files (scan mode only)
+    #[arg(long, conflicts_with = "watch")]
+    incremental: bool,
+
+    /// Only rescan PHP files changed since a git ref (merged into the existing cache; fails if
+    /// git cannot diff against the ref)
+    #[arg(long, value_name = "GIT_REF", conflicts_with = "watch")]
+    changed_since: Option<String>,
+
+    /// Verbose logging (watch mode only)
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Log file path (optional, defaults to stdout)
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+
+    /// Log level: trace, debug, info, warn, error
+    #[arg(long)]
+    log_level: Option<String>,
+
+    /// Per-module log directives, e.g. `aurynx::daemon=debug,aurynx::parser=warn`
+    #[arg(long, value_name = "DIRECTIVES")]
+    log_filter: Option<String>,
+
+    /// Log format: text or json
+    #[arg(long)]
+    log_format: Option<String>,
+
+    /// Send logs to the system logger instead of stdout/--log-file: syslog or journald
+    #[arg(long, value_parser = aurynx::logger::LOG_TARGETS)]
+    log_target: Option<String>,
+
+    /// Force restart even if daemon is already running (DANGEROUS: kills existing daemon)
+    #[arg(long)]
+    force: bool,
+
+    /// Force writing cache to disk in watch mode (useful for debugging/testing)
+    #[arg(long)]
+    write_to_disk: bool,
+
+    /// Serve the existing cache file over IPC without scanning or watching
+    /// it, e.g. a cache built at deploy time (watch mode only)
+    #[arg(long)]
+    read_only: bool,
+
+    /// Container mode: reap zombies and forward signals when running as PID 1,
+    /// never treat stdout as a terminal (watch mode only)
+    #[arg(long)]
+    container: bool,
+
+    /// Restart the daemon with backoff when it crashes, from a small parent
+    /// process (watch mode only, Linux)
+    #[arg(long)]
+    supervise: bool,
+
+    /// Run under a process manager (supervisord, runit): exit statuses name
+    /// the failure, stdout is never a terminal (watch mode only)
+    #[arg(long)]
+    foreground: bool,
+
+    /// Exit cleanly after SECONDS without IPC requests or file changes (watch mode only)
+    #[arg(long, value_name = "SECONDS")]
+    exit_on_idle: Option<u64>,
+
+    /// Exit on the first runtime error (watch failure, failed cache write)
+    /// instead of logging it and carrying on (watch mode only)
+    #[arg(long)]
+    exit_on_error: bool,
+
+    /// Fall back to polling when the OS file watch limit is exhausted (watch mode only)
+    #[arg(long)]
+    poll_fallback: bool,
+
+    /// Full rescan, reparsing vendor/, when composer.lock or vendor/composer/installed.json
+    /// changes (watch mode only)
+    #[arg(long)]
+    watch_composer: bool,
+
+    /// Watcher backend: notify (native) or watchman (watch mode only)
+    #[arg(long, value_parser = ["notify", "watchman"])]
+    watcher: Option<String>,
+
+    /// Resolve ancestor chains and inherited interfaces across the scanned code
+    #[arg(long)]
+    resolve_hierarchy: bool,
+
+    /// Merge attributes of parents, traits and interfaces into children (implies --resolve-hierarchy)
+    #[arg(long)]
+    inherit_attributes: bool,
+
+    /// Add a `signature_hash` to every class: names, types and attributes,
+    /// without method bodies, for skipping rebuilds on implementation changes
+    #[arg(long)]
+    signature_hash: bool,
+
+    /// Store file paths relative to this project root (portable caches)
+    #[arg(long, value_name = "DIR")]
+    path_root: Option<PathBuf>,
+
+    /// Also write an `OpenAPI` 3 document built from route attributes (scan mode only)
+    #[arg(long, value_name = "FILE", conflicts_with = "watch")]
+    openapi: Option<PathBuf>,
+
+    /// Files that cannot be scanned (oversized, unreadable, syntax errors):
+    /// skip silently, warn (default) or fail the scan / reject the daemon batch
+    #[arg(long, value_name = "POLICY", value_parser = aurynx::scanner::ON_ERROR_POLICIES)]
+    on_error: Option<String>,
+
+    /// PHP version the code targets (e.g. 8.1): warn about files using newer syntax
+    #[arg(long, value_name = "VERSION", value_parser = parse_php_version)]
+    php_version: Option<PhpVersion>,
+
+    /// Warn about attributes that match no scanned class, PHP built-in or
+    /// `known_attributes` entry (usually a missing `use` import), and
+    /// about attributes used where their `#[Attribute]` flags forbid it
+    #[arg(long, conflicts_with = "watch")]
+    check_attributes: bool,
+
+    /// Write statistics, skipped files, parse errors and timing to
+    /// aurynx.report.json next to the cache (scan mode only)
+    #[arg(long, conflicts_with = "watch")]
+    report: bool,
+
+    /// Scan at low priority on a quarter of the cores (lowest CPU and,
+    /// on Linux, idle IO priority) to keep the machine responsive
+    #[arg(long)]
+    nice: bool,
+
+    /// Report the N slowest files to parse with their sizes (default: 20)
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "20")]
+    profile_files: Option<usize>,
+
+    /// Print the classes the scan would add, remove or update in the cache
+    /// without writing the cache, manifest, `OpenAPI` document or artifacts
+    #[arg(long, conflicts_with_all = ["watch", "workspace"])]
+    dry_run: bool,
+
+    /// Scan every project listed in a workspace file (default: aurynx.workspace.json)
+    /// concurrently on a shared thread pool
+    #[arg(
+        long,
+        value_name = "FILE",
+        num_args = 0..=1,
+        default_missing_value = aurynx::workspace::WORKSPACE_FILE,
+        conflicts_with_all = ["config", "path", "output", "ignore", "vendor_package", "watch", "changed_since", "openapi", "profile_files"]
+    )]
+    workspace: Option<PathBuf>,
+
+    /// Pretty print output (formatted with indentation)
+    #[arg(long)]
+    pretty: bool,
+
+    /// Output format (currently only 'php' is supported)
+    #[arg(long, default_value = "php", hide = true)]
+    format: String,
+
+    /// Include attributes in output (enabled by default)
+    #[arg(long, default_value = "true", hide = true)]
+    include_attributes: bool,
+
+    /// Include parent classes and interfaces (enabled by default)
+    #[arg(long, default_value = "true", hide = true)]
+    include_parents: bool,
+
+    /// Add the namespace, imports, `strict_types` and declared symbols of
+    /// each class's file to its entry (`file_context`)
+    #[arg(long)]
+    include_file_context: bool,
+}
+
 fn main() {
     let cli = Cli::parse();
 
     match &cli.command {
-        Commands::DiscoveryScan {
-            config: config_path,
+        Commands::DiscoveryScan(args) => scan(args),
+        Commands::DiscoveryBenchmark {
+            config,
+            path,
+            ignore,
+            iterations,
+            format,
+            baseline,
+            save_baseline,
+        } => benchmark(
+            config.clone(),
+            path.clone(),
+            ignore.clone(),
+            *iterations,
+            format.clone(),
+            baseline.as_deref(),
+            *save_baseline,
+        ),
+        Commands::DiscoveryExport {
+            target,
+            preset,
+            config,
+            path,
+            ignore,
+            output,
+            key,
+            redis,
+            ttl,
+        } => export(
+            target,
+            preset.as_deref(),
+            config.clone(),
+            path.clone(),
+            ignore.clone(),
+            output.as_deref(),
+            &ExportCacheOptions {
+                key: key.clone(),
+                redis: redis.clone(),
+                ttl: *ttl,
+            },
+        ),
+        Commands::DiscoveryRender {
+            template,
+            out,
+            config,
+            path,
+            ignore,
+        } => render(template, out, config.clone(), path.clone(), ignore.clone()),
+        Commands::DiscoveryLog {
+            config,
+            journal,
+            class,
+            limit,
+            format,
+        } => log(config.clone(), journal.clone(), class.as_deref(), *limit, format),
+        Commands::DiscoveryCheck {
+            config,
+            path,
+            ignore,
+            format,
+            baseline,
+            generate_baseline,
+        } => check(
+            config.clone(),
+            path.clone(),
+            ignore.clone(),
+            format,
+            baseline.as_deref(),
+            *generate_baseline,
+        ),
+        Commands::DiscoveryVerifyClassmap {
+            config,
             path,
+            ignore,
             output,
+            classmap,
+            format,
+        } => verify_classmap(
+            config.clone(),
+            path.clone(),
+            ignore.clone(),
+            output.clone(),
+            classmap,
+            format,
+        ),
+        Commands::DiscoveryWarm {
+            config,
+            path,
             ignore,
-            watch,
+            output,
             socket,
             pid,
-            incremental,
-            verbose,
-            log_file,
-            log_level,
-            log_format,
-            force,
-            write_to_disk,
-            pretty,
-            format,
-            include_attributes: _,
-            include_parents: _,
-        } => {
-            // Load config file
-            let config_file = match aurynx::config::ConfigFile::load(config_path.clone()) {
-                Ok(c) => c,
-                Err(e) => {
-                    eprintln!("Error loading config: {e}");
-                    std::process::exit(1);
+            timeout,
+            get_code,
+        } => warm(
+            config.clone(),
+            path.clone(),
+            ignore.clone(),
+            output.clone(),
+            socket.clone(),
+            pid.clone(),
+            &WarmOptions {
+                timeout: std::time::Duration::from_secs(*timeout),
+                get_code: *get_code,
+            },
+        ),
+        Commands::DiscoveryPromote {
+            config,
+            from,
+            to,
+            socket,
+        } => promote(config.clone(), from, to.clone(), socket.clone()),
+        Commands::DiscoverySnapshot {
+            out,
+            config,
+            socket,
+        } => snapshot(config.clone(), socket.clone(), out, false),
+        Commands::DiscoveryRestore {
+            file,
+            config,
+            socket,
+        } => snapshot(config.clone(), socket.clone(), file, true),
+        Commands::DiscoveryInstallService {
+            systemd_user,
+            launchd,
+            config,
+            path,
+            ignore,
+            output,
+            socket,
+            pid,
+            name,
+            print,
+            no_start,
+        } => install_service(
+            config.clone(),
+            path.clone(),
+            ignore.clone(),
+            output.clone(),
+            socket.clone(),
+            pid.clone(),
+            &ServiceOptions {
+                manager: if *launchd {
+                    aurynx::service::ServiceManager::Launchd
+                } else if *systemd_user {
+                    aurynx::service::ServiceManager::SystemdUser
+                } else {
+                    aurynx::service::ServiceManager::native()
                 },
-            };
+                name: name.clone(),
+                print: *print,
+                start: !*no_start,
+            },
+        ),
+        Commands::Lsp {
+            config,
+            path,
+            ignore,
+            output,
+        } => lsp(config.clone(), path.clone(), ignore.clone(), output.clone()),
+    }
+}
 
-            // Extract limit settings before moving config_file
-            let max_file_size = config_file.max_file_size_bytes();
-            let max_request_size = config_file.max_request_size_bytes();
-            let max_cache_entries = config_file.max_cache_entries_limit();
+/// `discovery:scan` settings shared by watch and one-shot mode
+/// (CLI args > config file > defaults)
+#[allow(clippy::struct_excessive_bools)] // One per flag
+struct ScanSettings {
+    paths: Vec<PathBuf>,
+    output: PathBuf,
+    ignore: Vec<String>,
+    output_exclude: Vec<String>,
+    max_file_size: u64,
+    pretty: bool,
+    nice: bool,
+    cache_layout: aurynx::writer::CacheLayout,
+    resolve_hierarchy: bool,
+    inherit_attributes: bool,
+    signature_hash: bool,
+    max_class_members: Option<usize>,
+    path_root: Option<PathBuf>,
+    path_map: std::collections::HashMap<PathBuf, PathBuf>,
+    class_lists: Vec<aurynx::class_lists::ClassList>,
+    derived: Vec<aurynx::derived::DerivedOutput>,
+    scan_options: aurynx::scanner::ScanOptions,
+}
 
-            // Merge config (CLI args > Config file > Defaults)
-            let path = path.clone().or(config_file.paths).unwrap_or_else(|| {
-                eprintln!("Error: --path is required (or 'paths' in config file)");
-                std::process::exit(1);
-            });
+/// Run `discovery:scan`
+fn scan(args: &ScanArgs) {
+    if let Some(workspace) = &args.workspace {
+        if args.nice {
+            aurynx::nice::enable();
+        }
+        scan_workspace(workspace, &args.format, args.pretty, args.on_error.as_deref());
+        return;
+    }
 
-            let output = output.clone().or(config_file.output).unwrap_or_else(|| {
-                eprintln!("Error: --output is required (or 'output' in config file)");
-                std::process::exit(1);
-            });
+    let config_file = load_config(args.config.clone());
+    aurynx::retry::set_policy(config_file.retry_policy());
+    set_encryption_key(&config_file);
+    let read_only = args.read_only || config_file.read_only.unwrap_or(false);
+    let settings = scan_settings(args, &config_file, read_only);
 
-            let ignore = ignore.clone().or(config_file.ignore).unwrap_or_default();
-            let watch = *watch || config_file.watch.unwrap_or(false);
-            let socket = socket.clone().or(config_file.socket);
-            let pid = pid.clone().or(config_file.pid);
-            let incremental = *incremental || config_file.incremental.unwrap_or(false);
-            let verbose = *verbose || config_file.verbose.unwrap_or(false);
-            let log_file = log_file.clone().or(config_file.log_file);
-            let log_level = log_level
-                .clone()
-                .or(config_file.log_level)
-                .unwrap_or_else(|| "info".to_string());
-            let log_format = log_format
-                .clone()
-                .or(config_file.log_format)
-                .unwrap_or_else(|| "text".to_string());
-            let force = *force || config_file.force.unwrap_or(false);
-            let write_to_disk = *write_to_disk || config_file.write_to_disk.unwrap_or(false);
-            let pretty = *pretty || config_file.pretty.unwrap_or(false);
-
-            // Validate format
-            if format != "php" && format != "json" {
-                eprintln!("Error: Only 'php' and 'json' formats are supported");
-                std::process::exit(1);
-            }
+    // Validate format
+    if args.format != "php" && args.format != "json" {
+        eprintln!("Error: Only 'php' and 'json' formats are supported");
+        std::process::exit(1);
+    }
+    if args.format == "php" && aurynx::encryption::is_enabled() {
+        eprintln!("Error: encryption requires a JSON cache (use --format json)");
+        std::process::exit(1);
+    }
 
-            // WATCH MODE (daemon)
-            if watch {
-                // Validate required arguments
-                let socket_path = if let Some(s) = socket.as_ref() { s } else {
-                    eprintln!("Error: --socket is required with --watch (or in config)");
-                    std::process::exit(1);
-                };
-                let pid_path = if let Some(p) = pid.as_ref() { p } else {
-                    eprintln!("Error: --pid is required with --watch (or in config)");
-                    std::process::exit(1);
-                };
-
-                // Initialize logger
-                let is_tty = std::io::stdout().is_terminal();
-                if let Err(e) = aurynx::logger::init_logger(
-                    log_file.as_deref(),
-                    &log_level,
-                    &log_format,
-                    verbose,
-                ) {
-                    eprintln!("❌ Failed to initialize logger: {e}");
-                    std::process::exit(1);
-                }
-
-                // Show startup info if interactive
-                if is_tty {
-                    println!("🪄 Starting Discovery daemon...");
-                    println!("   Mode: Watch (with atomic lock)");
-                    println!("   Strategy: Adaptive caching");
-                    println!("   Paths: {path:?}");
-                    println!("   Output: {output:?}");
-                    println!("   Socket: {socket_path:?}");
-                    println!("   PID: {pid_path:?}");
-                    if verbose {
-                        println!("   Verbose: enabled 🔮");
-                    }
-                    if let Some(lf) = &log_file {
-                        println!("   Log file: {lf:?}");
-                        println!("   Log format: {log_format}");
-                    }
-                }
-
-                // Create daemon config
-                let config = DaemonConfig {
-                    paths: path,
-                    output_path: output,
-                    socket_path: socket_path.clone(),
-                    pid_file: pid_path.clone(),
-                    ignore_patterns: ignore,
-                    verbose,
-                    is_tty,
-                    force,
-                    write_to_disk,
-                    pretty,
-                    format: format.clone(),
-                    max_file_size,
-                    max_request_size,
-                    max_cache_entries,
-                };
-
-                // Start daemon
-                let mut daemon = match Daemon::new(config) {
-                    Ok(d) => d,
-                    Err(e) => {
-                        eprintln!("Failed to create daemon: {e}");
-                        std::process::exit(1);
-                    },
-                };
-
-                if let Err(e) = daemon.run() {
-                    eprintln!("Daemon error: {e}");
-                    std::process::exit(1);
-                }
-            }
-            // SCAN MODE (one-shot)
-            else {
-                println!("Scanning {path:?} -> {output:?} (ignoring {ignore:?})");
+    let watch = args.watch || config_file.watch.unwrap_or(false);
+    if args.supervise && !watch {
+        eprintln!("Error: --supervise requires --watch (or 'watch' in config)");
+        std::process::exit(1);
+    }
+    if read_only && !watch {
+        eprintln!("Error: --read-only requires --watch (or 'watch' in config)");
+        std::process::exit(1);
+    }
+    if (args.foreground || args.exit_on_idle.is_some() || args.exit_on_error) && !watch {
+        eprintln!(
+            "Error: --foreground, --exit-on-idle and --exit-on-error require --watch (or 'watch' in config)"
+        );
+        std::process::exit(1);
+    }
 
-                let manifest_path = if let Some(parent) = output.parent() {
-                    parent.join(aurynx::incremental::MANIFEST_FILE)
-                } else {
-                    PathBuf::from(aurynx::incremental::MANIFEST_FILE)
-                };
-
-                // Incremental or full scan
-                let (metadata, manifest) = if incremental {
-                    match aurynx::incremental::perform_incremental_scan(
-                        &manifest_path,
-                        &path,
-                        &ignore,
-                        max_file_size,
-                    ) {
-                        Ok(res) => res,
-                        Err(e) => {
-                            eprintln!(
-                                "Warning: Incremental mode failed, falling back to full scan: {e}"
-                            );
-                            let meta = scan_directory(&path, &ignore);
-                            (meta, aurynx::incremental::Manifest::default())
-                        },
-                    }
-                } else {
-                    let meta = scan_directory(&path, &ignore);
-                    match aurynx::incremental::perform_incremental_scan(
-                        &PathBuf::from("/non-existent"), // Force full scan
-                        &path,
-                        &ignore,
-                        max_file_size,
-                    ) {
-                        Ok(res) => res,
-                        Err(_) => (meta, aurynx::incremental::Manifest::default()),
-                    }
-                };
-
-                println!("Found {} classes/interfaces/traits/enums.", metadata.len());
-
-                // Write cache
-                let result = match format.as_str() {
-                    "json" => aurynx::writer::write_json_cache(&metadata, &output, pretty),
-                    _ => write_php_cache(&metadata, &output, pretty),
-                };
-
-                if let Err(e) = result {
-                    eprintln!("Error writing cache: {e}");
-                    std::process::exit(1);
-                }
+    if watch {
+        scan_watch(args, config_file, settings, read_only);
+    } else {
+        scan_once(args, &config_file, &settings);
+    }
+}
 
-                // Write manifest
-                if let Err(e) = manifest.save(&manifest_path) {
-                    eprintln!("Warning: Failed to save manifest: {e}");
-                }
+/// Merge the settings both scan modes use
+fn scan_settings(
+    args: &ScanArgs, config_file: &aurynx::config::ConfigFile, read_only: bool,
+) -> ScanSettings {
+    // A read-only daemon scans nothing
+    let mut paths = args
+        .path
+        .clone()
+        .or_else(|| config_file.paths.clone())
+        .unwrap_or_else(|| {
+            if read_only {
+                return Vec::new();
+            }
+            eprintln!("Error: --path is required (or 'paths' in config file)");
+            std::process::exit(1);
+        });
+    // Installed Composer packages are scanned like any other path
+    let vendor_packages = args
+        .vendor_package
+        .clone()
+        .or_else(|| config_file.vendor_packages.clone())
+        .unwrap_or_default();
+    if !vendor_packages.is_empty() && !read_only {
+        match aurynx::vendor::package_paths(&paths, &vendor_packages) {
+            Ok(package_paths) => paths.extend(package_paths),
+            Err(e) => {
+                eprintln!("Error: {e:#}");
+                std::process::exit(1);
+            },
+        }
+    }
+    check_allowed_roots(config_file.allowed_roots.as_deref(), &paths);
+
+    ScanSettings {
+        paths,
+        output: args
+            .output
+            .clone()
+            .or_else(|| config_file.output.clone())
+            .unwrap_or_else(|| {
+                eprintln!("Error: --output is required (or 'output' in config file)");
+                std::process::exit(1);
+            }),
+        ignore: args
+            .ignore
+            .clone()
+            .or_else(|| config_file.ignore.clone())
+            .unwrap_or_default(),
+        output_exclude: args
+            .output_exclude
+            .clone()
+            .or_else(|| config_file.output_exclude.clone())
+            .unwrap_or_default(),
+        max_file_size: config_file.max_file_size_bytes(),
+        pretty: args.pretty || config_file.pretty.unwrap_or(false),
+        nice: args.nice || config_file.nice.unwrap_or(false),
+        cache_layout: config_file.cache_layout.unwrap_or_default(),
+        resolve_hierarchy: args.resolve_hierarchy
+            || config_file.resolve_hierarchy.unwrap_or(false),
+        inherit_attributes: args.inherit_attributes
+            || config_file.inherit_attributes.unwrap_or(false),
+        signature_hash: args.signature_hash || config_file.signature_hash.unwrap_or(false),
+        max_class_members: config_file.max_class_members,
+        path_root: args
+            .path_root
+            .clone()
+            .or_else(|| config_file.path_root.clone())
+            .map(|root| aurynx::writer::canonical_path_root(&root)),
+        path_map: config_file.path_map.clone().unwrap_or_default(),
+        class_lists: config_file.class_lists.clone().unwrap_or_default(),
+        derived: config_file.derived.clone().unwrap_or_default(),
+        scan_options: scan_options(args, config_file),
+    }
+}
+
+/// How files are parsed: file context, custom queries, error policy,
+/// front-ends and the targeted PHP version
+fn scan_options(
+    args: &ScanArgs, config_file: &aurynx::config::ConfigFile,
+) -> aurynx::scanner::ScanOptions {
+    let mut options = config_file.scan_options(Path::new("")).unwrap_or_else(|e| {
+        eprintln!("Error loading custom queries [{}]: {e}", e.code());
+        std::process::exit(1);
+    });
+    options.include_file_context |= args.include_file_context;
+    if let Some(policy) = args.on_error.as_deref().and_then(aurynx::scanner::OnError::parse) {
+        options.on_error = policy;
+    }
+    if args.php_version.is_some() {
+        options.php_version = args.php_version;
+    }
+    options
+}
+
+/// Logging of the watch daemon (CLI args > config file > defaults)
+struct LogOptions {
+    file: Option<PathBuf>,
+    target: Option<String>,
+    level: String,
+    filter: Option<String>,
+    format: String,
+}
+
+/// Run `discovery:scan --watch`: the daemon
+fn scan_watch(
+    args: &ScanArgs, config_file: aurynx::config::ConfigFile, settings: ScanSettings,
+    read_only: bool,
+) {
+    if args.dry_run {
+        eprintln!("Error: --dry-run only applies to one-shot scans");
+        std::process::exit(1);
+    }
+    if config_file.artifacts.as_ref().is_some_and(|a| !a.is_empty()) {
+        eprintln!("Warning: 'artifacts' are only generated by one-shot scans");
+    }
+    if config_file.exporters.as_ref().is_some_and(|e| !e.is_empty()) {
+        eprintln!("Warning: 'exporters' are only run by one-shot scans");
+    }
+    if args.report || config_file.report.unwrap_or(false) {
+        eprintln!("Warning: 'report' is only written by one-shot scans");
+    }
+
+    let max_request_size = config_file.max_request_size_bytes();
+    let max_cache_entries = config_file.max_cache_entries_limit();
+    let batch_tuning = config_file.batch_tuning();
+    let max_memory_bytes = config_file.max_memory_bytes();
+    // Validate required arguments
+    let Some(socket_path) = args.socket.clone().or(config_file.socket) else {
+        eprintln!("Error: --socket is required with --watch (or in config)");
+        std::process::exit(1);
+    };
+    let Some(pid_path) = args.pid.clone().or(config_file.pid) else {
+        eprintln!("Error: --pid is required with --watch (or in config)");
+        std::process::exit(1);
+    };
+
+    let log = LogOptions {
+        file: args.log_file.clone().or(config_file.log_file),
+        target: args.log_target.clone().or(config_file.log_target),
+        level: args
+            .log_level
+            .clone()
+            .or(config_file.log_level)
+            .unwrap_or_else(|| "info".to_string()),
+        filter: args.log_filter.clone().or(config_file.log_filter),
+        format: args
+            .log_format
+            .clone()
+            .or(config_file.log_format)
+            .unwrap_or_else(|| "text".to_string()),
+    };
+    let config = DaemonConfig {
+        paths: settings.paths,
+        output_path: settings.output,
+        socket_path,
+        pid_file: pid_path,
+        ignore_patterns: settings.ignore,
+        output_exclude: settings.output_exclude,
+        verbose: args.verbose || config_file.verbose.unwrap_or(false),
+        is_tty: !args.container && !args.foreground && std::io::stdout().is_terminal(),
+        force: args.force || config_file.force.unwrap_or(false),
+        write_to_disk: args.write_to_disk || config_file.write_to_disk.unwrap_or(false),
+        read_only,
+        exit_on_idle: args.exit_on_idle.map(std::time::Duration::from_secs),
+        exit_on_error: args.exit_on_error,
+        pretty: settings.pretty,
+        format: args.format.clone(),
+        cache_layout: settings.cache_layout,
+        max_file_size: settings.max_file_size,
+        max_request_size,
+        max_cache_entries,
+        poll_fallback: args.poll_fallback || config_file.poll_fallback.unwrap_or(false),
+        watch_composer: args.watch_composer || config_file.watch_composer.unwrap_or(false),
+        watcher_backend: args
+            .watcher
+            .clone()
+            .or(config_file.watcher)
+            .unwrap_or_else(|| "notify".to_string()),
+        temp_file_patterns: config_file.temp_file_patterns.unwrap_or_default(),
+        resolve_hierarchy: settings.resolve_hierarchy,
+        inherit_attributes: settings.inherit_attributes,
+        signature_hash: settings.signature_hash,
+        path_root: settings.path_root,
+        path_map: settings.path_map,
+        batch_tuning,
+        max_memory_entries: config_file.max_memory_entries,
+        max_class_members: settings.max_class_members,
+        spill_file: config_file.spill_file,
+        max_memory_bytes,
+        on_update: config_file.on_update.unwrap_or_default(),
+        class_lists: settings.class_lists,
+        derived: settings.derived,
+        journal_file: config_file.journal_file,
+        journal_max_entries: config_file
+            .journal_max_entries
+            .unwrap_or(aurynx::journal::DEFAULT_MAX_ENTRIES),
+        shared_memory: config_file.shared_memory,
+        profile_files: args.profile_files,
+        scan_options: settings.scan_options,
+    };
+    run_daemon(args, config, &log, settings.nice);
+}
+
+/// Start the daemon of `config` under the supervisors `args` ask for
+fn run_daemon(args: &ScanArgs, config: DaemonConfig, log: &LogOptions, nice: bool) {
+    // As PID 1, a forked supervisor reaps zombies and forwards
+    // signals (before any thread exists)
+    #[cfg(target_os = "linux")]
+    if args.container {
+        match aurynx::daemon::init::supervise() {
+            Ok(aurynx::daemon::init::Role::Daemon) => {},
+            Ok(aurynx::daemon::init::Role::Exited(code)) => std::process::exit(code),
+            Err(e) => {
+                eprintln!("Failed to start container supervisor: {e}");
+                std::process::exit(1);
+            },
+        }
+    }
+
+    // The supervisor restarts this command without --supervise, with the
+    // exit statuses of --foreground telling crashes from configuration
+    // errors (before any thread exists)
+    #[cfg(target_os = "linux")]
+    if args.supervise {
+        let mut daemon_args: Vec<std::ffi::OsString> = std::env::args_os()
+            .skip(1)
+            .filter(|arg| arg != "--supervise")
+            .collect();
+        if !args.foreground {
+            daemon_args.push("--foreground".into());
+        }
+        match std::env::current_exe()
+            .and_then(|program| aurynx::daemon::supervisor::run(&program, &daemon_args))
+        {
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                eprintln!("Failed to start daemon supervisor: {e}");
+                std::process::exit(1);
+            },
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    if args.supervise {
+        eprintln!("Error: --supervise is only supported on Linux");
+        std::process::exit(1);
+    }
+
+    // Initialize logger
+    if let Err(e) = aurynx::logger::init_logger(
+        log.file.as_deref(),
+        log.target.as_deref(),
+        &log.level,
+        log.filter.as_deref(),
+        &log.format,
+        config.verbose,
+    ) {
+        eprintln!("❌ Failed to initialize logger: {e:#}");
+        std::process::exit(1);
+    }
+    if nice {
+        aurynx::nice::enable();
+    }
+
+    // Show startup info if interactive
+    if config.is_tty {
+        print_daemon_startup(&config, log);
+    }
+
+    // Process managers restart by exit status (see AurynxError::exit_status)
+    let exit_status = |e: &aurynx::error::AurynxError| {
+        if args.foreground { e.exit_status() } else { 1 }
+    };
+
+    // Start daemon
+    let mut daemon = match Daemon::new(config) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("Failed to create daemon [{}]: {e}", e.code());
+            std::process::exit(exit_status(&e));
+        },
+    };
+
+    if let Err(e) = daemon.run() {
+        eprintln!("Daemon error [{}]: {e}", e.code());
+        std::process::exit(exit_status(&e));
+    }
+}
+
+/// Startup banner of an interactive daemon
+fn print_daemon_startup(config: &DaemonConfig, log: &LogOptions) {
+    println!("🪄 Starting Discovery daemon...");
+    if config.read_only {
+        println!("   Mode: Read-only (serving the existing cache)");
+    } else {
+        println!("   Mode: Watch (with atomic lock)");
+    }
+    println!("   Strategy: Adaptive caching");
+    println!("   Paths: {:?}", config.paths);
+    println!("   Output: {}", config.output_path.display());
+    println!("   Socket: {}", config.socket_path.display());
+    println!("   PID: {}", config.pid_file.display());
+    if config.verbose {
+        println!("   Verbose: enabled 🔮");
+    }
+    if let Some(target) = &log.target {
+        println!("   Log target: {target}");
+    } else if let Some(lf) = &log.file {
+        println!("   Log file: {}", lf.display());
+        println!("   Log format: {}", log.format);
+    }
+}
+
+/// Run a one-shot `discovery:scan`
+fn scan_once(args: &ScanArgs, config_file: &aurynx::config::ConfigFile, settings: &ScanSettings) {
+    if config_file.on_update.as_ref().is_some_and(|hooks| !hooks.is_empty()) {
+        eprintln!("Warning: 'on_update' hooks only run in watch mode");
+    }
+    if config_file.shared_memory.is_some() {
+        eprintln!("Warning: 'shared_memory' is only used in watch mode");
+    }
+    // Skipped files and syntax errors are reported on stderr
+    if let Err(e) = aurynx::logger::init_stderr_logger() {
+        eprintln!("Warning: Failed to initialize logger: {e:#}");
+    }
+    if settings.nice {
+        aurynx::nice::enable();
+    }
+    let ScanSettings { paths: path, output, ignore, .. } = settings;
+    println!(
+        "Scanning {path:?} -> {} (ignoring {ignore:?})",
+        output.display()
+    );
+    let started = std::time::Instant::now();
+
+    let manifest_path = output.with_file_name(aurynx::incremental::MANIFEST_FILE);
+    // Dry runs write nothing, not even the report
+    let report = args.report || config_file.report.unwrap_or(false);
+    let report_path = (report && !args.dry_run)
+        .then(|| manifest_path.with_file_name(aurynx::scan_report::REPORT_FILE));
+
+    // Serialize concurrent scans sharing the same manifest (a dry
+    // run writes nothing, not even the lock file)
+    let _manifest_lock = (!args.dry_run).then(|| {
+        aurynx::incremental::ManifestLock::acquire(
+            &manifest_path,
+            aurynx::incremental::MANIFEST_LOCK_TIMEOUT,
+        )
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        })
+    });
+
+    if args.profile_files.is_some() {
+        aurynx::profile::start();
+    }
+    if report_path.is_some() {
+        aurynx::scan_report::start();
+    }
+
+    let incremental = args.incremental || config_file.incremental.unwrap_or(false);
+    let (mode, mut metadata, manifest) =
+        run_scan(settings, &manifest_path, incremental, args.changed_since.as_deref());
+    let incremental = incremental || args.changed_since.is_some();
+
+    aurynx::logger::flush_repeated(true);
+    let scan_time = started.elapsed();
+    let mut scan_report = report_path
+        .as_ref()
+        .map(|_| aurynx::scan_report::ScanReport::new(mode, &manifest, &metadata));
+    if let Err(e) = check_scan_failures(settings.scan_options.on_error, &manifest) {
+        eprintln!("Error: {e}");
+        if let (Some(report), Some(report_path)) = (&mut scan_report, &report_path) {
+            report.status = "failed";
+            report.set_timing(scan_time, started.elapsed());
+            write_scan_report(report, report_path);
+        }
+        std::process::exit(1);
+    }
+    print_scan_summary(args, config_file, path, &metadata);
+    prepare_output(settings, &mut metadata, scan_report.as_mut());
+
+    // Rewrite local paths for consumers on the other side of a path_map
+    let path_map = aurynx::path_map::PathMap::new(&settings.path_map);
+    let path_root = settings
+        .path_root
+        .as_ref()
+        .map(|root| path_map.to_mapped(root));
+
+    if args.dry_run {
+        path_map.apply(&mut metadata);
+        print_dry_run(&args.format, settings, &metadata, path_root.as_deref());
+        return;
+    }
+
+    write_generated(args, config_file, &metadata);
+    path_map.apply(&mut metadata);
+    let written = write_cache(
+        &args.format,
+        settings,
+        &metadata,
+        path_root.as_deref(),
+        incremental,
+    );
+
+    let outcomes = write_cache_outputs(config_file, settings, &metadata);
+    if let Some(report) = &mut scan_report {
+        report.exporters = outcomes;
+    }
+
+    // Write manifest
+    if let Err(e) = manifest.save(&manifest_path) {
+        eprintln!("Warning: Failed to save manifest: {e}");
+    }
+
+    if let (Some(report), Some(report_path)) = (&mut scan_report, &report_path) {
+        report.set_timing(scan_time, started.elapsed());
+        write_scan_report(report, report_path);
+    }
+
+    if written {
+        println!("Cache written successfully to {}", output.display());
+    } else {
+        println!("Cache unchanged, kept {}", output.display());
+    }
+}
+
+/// Print what a one-shot scan found: counts, case collisions, unknown
+/// attributes (`check_attributes`) and the slowest files (`--profile-files`)
+fn print_scan_summary(
+    args: &ScanArgs, config_file: &aurynx::config::ConfigFile, paths: &[PathBuf],
+    metadata: &[aurynx::metadata::PhpClassMetadata],
+) {
+    println!("Found {} classes/interfaces/traits/enums.", metadata.len());
+    print!(
+        "{}",
+        aurynx::scan_report::Breakdown::of(metadata).format()
+    );
+    for collision in aurynx::collisions::find_case_collisions(metadata) {
+        eprintln!("Warning: case collision: {collision}");
+    }
+    if args.check_attributes || config_file.check_attributes.unwrap_or(false) {
+        check_attribute_usage(
+            metadata,
+            paths,
+            config_file.known_attributes.as_deref().unwrap_or_default(),
+            config_file.attribute_stubs.unwrap_or(false),
+        );
+    }
+
+    if let Some(top) = args.profile_files {
+        print!(
+            "{}",
+            aurynx::profile::format_report(&aurynx::profile::finish(), top)
+        );
+    }
+}
+
+/// Resolve, trim and hash the scanned classes, then drop the ones
+/// `output_exclude` leaves out of the cache
+fn prepare_output(
+    settings: &ScanSettings, metadata: &mut Vec<aurynx::metadata::PhpClassMetadata>,
+    scan_report: Option<&mut aurynx::scan_report::ScanReport>,
+) {
+    let output_exclude = aurynx::scanner::ignore_matcher(&settings.paths, &settings.output_exclude);
+    let prepared = aurynx::pipeline::OutputPasses {
+        inherit_attributes: settings.inherit_attributes,
+        resolve_hierarchy: settings.resolve_hierarchy,
+        signature_hash: settings.signature_hash,
+        max_class_members: settings.max_class_members,
+        output_exclude: output_exclude.as_ref(),
+    }
+    .apply(metadata);
+    if let Some(max) = settings.max_class_members {
+        for class in &prepared.truncated {
+            eprintln!("Warning: over max_class_members ({max}), truncated: {class}");
+        }
+    }
+    if prepared.excluded > 0 {
+        println!(
+            "Excluded {} classes from the output (output_exclude).",
+            prepared.excluded
+        );
+    }
+    if let Some(report) = scan_report {
+        report.stats.excluded = prepared.excluded;
+        report.truncated = prepared.truncated;
+    }
+}
+
+/// Print the changes `--dry-run` would make to the cache
+fn print_dry_run(
+    format: &str, settings: &ScanSettings, metadata: &[aurynx::metadata::PhpClassMetadata],
+    path_root: Option<&Path>,
+) {
+    match aurynx::dry_run::diff_cache(
+        metadata,
+        &settings.output,
+        format,
+        settings.pretty,
+        path_root,
+        settings.cache_layout,
+    ) {
+        Ok(diff) => print!("{}", aurynx::dry_run::format_diff(&diff)),
+        Err(e) => {
+            eprintln!("Error comparing with the cache: {e:#}");
+            std::process::exit(1);
+        },
+    }
+}
+
+/// Write the cache of a one-shot scan; returns whether it changed
+///
+/// Incremental runs leave an unchanged cache untouched.
+fn write_cache(
+    format: &str, settings: &ScanSettings, metadata: &[aurynx::metadata::PhpClassMetadata],
+    path_root: Option<&Path>, incremental: bool,
+) -> bool {
+    let output = &settings.output;
+    let result = if incremental || path_root.is_some() || !settings.cache_layout.is_default() {
+        aurynx::writer::write_cache_if_changed(
+            metadata,
+            output,
+            format,
+            settings.pretty,
+            path_root,
+            settings.cache_layout,
+        )
+    } else {
+        match format {
+            "json" => aurynx::writer::write_json_cache(metadata, output, settings.pretty),
+            _ => write_php_cache(metadata, output, settings.pretty),
+        }
+        .map(|()| true)
+    };
+
+    result.unwrap_or_else(|e| {
+        eprintln!("Error writing cache: {e}");
+        std::process::exit(1);
+    })
+}
+
+/// Scan for a one-shot `discovery:scan`: the files changed since a git ref,
+/// the files changed since the manifest, or everything
+///
+/// Returns the scan mode named in the report with the classes and manifest.
+fn run_scan(
+    settings: &ScanSettings, manifest_path: &Path, incremental: bool, changed_since: Option<&str>,
+) -> (
+    &'static str,
+    Vec<aurynx::metadata::PhpClassMetadata>,
+    aurynx::incremental::Manifest,
+) {
+    use aurynx::incremental::{Manifest, scan_with_manifest};
+
+    let ScanSettings { paths: path, ignore, max_file_size, .. } = settings;
+    // The process exits after this scan, so large files may be mapped
+    let pool = aurynx::scanner::ExtractorPool::new(settings.scan_options.clone()).with_mmap();
+
+    // Git-aware scan: only files changed since the given ref
+    let git_scan = changed_since.and_then(|git_ref| {
+        let changed = aurynx::git::changed_files_in(path, git_ref).unwrap_or_else(|e| {
+            eprintln!("Error: --changed-since {git_ref}: {e:#}");
+            std::process::exit(1);
+        });
+        // Without a usable manifest there is nothing to merge into
+        aurynx::incremental::perform_changed_files_scan(
+            manifest_path,
+            path,
+            ignore,
+            *max_file_size,
+            &changed,
+            &pool,
+        )
+        .inspect_err(|e| {
+            eprintln!("Warning: --changed-since: {e}; running an incremental scan");
+        })
+        .ok()
+    });
+    if let Some((metadata, manifest)) = git_scan {
+        return ("changed_since", metadata, manifest);
+    }
+
+    let scan = |manifest| scan_with_manifest(manifest, path, ignore, *max_file_size, &pool, false);
+    let full_scan = || {
+        scan(Manifest::default()).unwrap_or_else(|e| {
+            eprintln!("Error: {e:#}");
+            std::process::exit(1);
+        })
+    };
+
+    if incremental || changed_since.is_some() {
+        let (metadata, manifest) = Manifest::load(manifest_path)
+            .and_then(scan)
+            .unwrap_or_else(|e| {
+                eprintln!("Warning: Incremental mode failed, falling back to full scan: {e}");
+                full_scan()
+            });
+        return ("incremental", metadata, manifest);
+    }
+
+    let (metadata, manifest) = full_scan();
+    ("full", metadata, manifest)
+}
+
+/// Write the `OpenAPI` document and artifacts of a one-shot scan, built from
+/// local paths before any `path_map` rewrite
+fn write_generated(
+    args: &ScanArgs, config_file: &aurynx::config::ConfigFile,
+    metadata: &[aurynx::metadata::PhpClassMetadata],
+) {
+    let openapi_config = config_file.openapi.clone().unwrap_or_default();
+    if let Some(openapi_path) = args.openapi.as_ref().or(openapi_config.output.as_ref()) {
+        let export = aurynx::openapi::generate_openapi(metadata, &openapi_config);
+        for handler in &export.skipped {
+            eprintln!("Warning: OpenAPI: skipped {handler} (path is not a literal)");
+        }
+        if let Err(e) = aurynx::openapi::write_openapi(&export.document, openapi_path) {
+            eprintln!("Error writing OpenAPI document: {e}");
+            std::process::exit(1);
+        }
+        println!("OpenAPI document written to {}", openapi_path.display());
+    }
+
+    let artifacts = config_file.artifacts.as_deref().unwrap_or_default();
+    match aurynx::artifacts::write_artifacts(metadata, artifacts) {
+        Ok(written) => {
+            for (artifact, entries) in written {
+                println!(
+                    "Artifact written to {} ({entries} entries)",
+                    artifact.display()
+                );
+            }
+        },
+        Err(e) => {
+            eprintln!("Error writing artifact: {e}");
+            std::process::exit(1);
+        },
+    }
+}
+
+/// Write the class lists and derived outputs that accompany the cache, then
+/// run the exporters
+fn write_cache_outputs(
+    config_file: &aurynx::config::ConfigFile, settings: &ScanSettings,
+    metadata: &[aurynx::metadata::PhpClassMetadata],
+) -> Vec<aurynx::exporters::ExporterOutcome> {
+    match aurynx::class_lists::write_class_lists(metadata, &settings.class_lists) {
+        Ok(written) => {
+            for (list, entries) in written {
+                println!(
+                    "Class list written to {} ({entries} classes)",
+                    list.display()
+                );
+            }
+        },
+        Err(e) => {
+            eprintln!("Error writing class list: {e:#}");
+            std::process::exit(1);
+        },
+    }
+    match aurynx::derived::write_derived(metadata, &settings.derived) {
+        Ok(written) => {
+            for (output, entries) in written {
+                println!(
+                    "Derived output written to {} ({entries} entries)",
+                    output.display()
+                );
+            }
+        },
+        Err(e) => {
+            eprintln!("Error writing derived output: {e:#}");
+            std::process::exit(1);
+        },
+    }
+
+    let outcomes = aurynx::exporters::run_exporters(
+        metadata,
+        config_file.exporters.as_deref().unwrap_or_default(),
+        &settings.output,
+        None,
+    );
+    for outcome in &outcomes {
+        match &outcome.error {
+            None => println!(
+                "Exporter '{}' succeeded ({}ms)",
+                outcome.name, outcome.duration_ms
+            ),
+            Some(e) => eprintln!("Warning: exporter '{}' failed: {e}", outcome.name),
+        }
+    }
+    outcomes
+}
+
+/// Error naming the failed files under `on_error: fail`
+///
+/// Runs before anything but the scan report is written; under `warn` the
+/// files were already logged while scanning.
+fn check_scan_failures(
+    policy: aurynx::scanner::OnError, manifest: &aurynx::incremental::Manifest,
+) -> Result<(), String> {
+    let failed = manifest.failed_files();
+    let failures = failed.iter().map(|(path, entry)| {
+        (
+            Path::new(*path),
+            entry.parse_error.as_deref().unwrap_or_default(),
+        )
+    });
+    aurynx::scanner::check_failures(policy, failures)
+}
+
+/// Write the `--report` file; a failed write only warns
+fn write_scan_report(report: &aurynx::scan_report::ScanReport, report_path: &Path) {
+    match aurynx::scan_report::write_report(report, report_path) {
+        Ok(()) => println!("Scan report written to {}", report_path.display()),
+        Err(e) => eprintln!("Warning: {e:#}"),
+    }
+}
+
+/// Load the config file (`aurynx.json` without `--config`); exits on errors
+fn load_config(config_path: Option<PathBuf>) -> aurynx::config::ConfigFile {
+    aurynx::config::ConfigFile::load(config_path).unwrap_or_else(|e| {
+        eprintln!("Error loading config [{}]: {e}", e.code());
+        std::process::exit(1);
+    })
+}
+
+/// Directories to scan: `--path`, else `paths` from the config file; exits
+/// without either or when one is outside `allowed_roots`
+fn scan_paths(
+    path: Option<Vec<PathBuf>>, config_file: &aurynx::config::ConfigFile,
+) -> Vec<PathBuf> {
+    let path = path
+        .or_else(|| config_file.paths.clone())
+        .unwrap_or_else(|| {
+            eprintln!("Error: --path is required (or 'paths' in config file)");
+            std::process::exit(1);
+        });
+    check_allowed_roots(config_file.allowed_roots.as_deref(), &path);
+    path
+}
+
+/// Refuse to scan paths outside the configured `allowed_roots`
+fn check_allowed_roots(roots: Option<&[PathBuf]>, path: &[PathBuf]) {
+    if let Some(roots) = roots
+        && let Err(e) = aurynx::allowed_roots::check(path, roots)
+    {
+        eprintln!("Error [{}]: {e}", e.code());
+        std::process::exit(1);
+    }
+}
+
+/// Encrypt and decrypt cache files with the configured key
+fn set_encryption_key(config_file: &aurynx::config::ConfigFile) {
+    match config_file.encryption_key() {
+        Ok(key) => aurynx::encryption::set_key(key),
+        Err(e) => {
+            eprintln!("Error loading encryption key [{}]: {e}", e.code());
+            std::process::exit(1);
+        },
+    }
+}
+
+/// Parse `--php-version`
+fn parse_php_version(value: &str) -> Result<PhpVersion, String> {
+    PhpVersion::parse(value).ok_or_else(|| format!("expected MAJOR.MINOR, e.g. 8.1, got '{value}'"))
+}
+
+/// Run `discovery:scan --workspace`
+fn scan_workspace(workspace_path: &Path, format: &str, pretty: bool, on_error: Option<&str>) {
+    use aurynx::workspace::{WorkspaceConfig, WorkspaceOptions};
+
+    if format != "php" && format != "json" {
+        eprintln!("Error: Only 'php' and 'json' formats are supported");
+        std::process::exit(1);
+    }
+    let workspace = match WorkspaceConfig::load(workspace_path) {
+        Ok(workspace) => workspace,
+        Err(e) => {
+            eprintln!("Error loading workspace [{}]: {e}", e.code());
+            std::process::exit(1);
+        },
+    };
+    let on_error = on_error.and_then(aurynx::scanner::OnError::parse);
+    if let Err(e) = aurynx::logger::init_stderr_logger() {
+        eprintln!("Warning: Failed to initialize logger: {e:#}");
+    }
+    println!(
+        "Scanning {} projects from {}",
+        workspace.projects.len(),
+        workspace_path.display()
+    );
+
+    let started = std::time::Instant::now();
+    let reports =
+        match aurynx::workspace::scan_workspace(&workspace, WorkspaceOptions {
+            format,
+            pretty,
+            on_error,
+        }) {
+            Ok(reports) => reports,
+            Err(e) => {
+                eprintln!("Error: {e:#}");
+                std::process::exit(1);
+            },
+        };
+    aurynx::logger::flush_repeated(true);
+
+    print!(
+        "{}",
+        aurynx::workspace::format_summary(&reports, started.elapsed())
+    );
+    if reports.iter().any(|report| report.result.is_err()) {
+        std::process::exit(1);
+    }
+}
+
+/// Options of the per-class cache exports (`redis`, `apcu`)
+struct ExportCacheOptions {
+    key: Option<String>,
+    redis: Option<String>,
+    ttl: u64,
+}
+
+/// Run `discovery:export`
+fn export(
+    target: &str, preset: Option<&str>, config_path: Option<PathBuf>, path: Option<Vec<PathBuf>>,
+    ignore: Option<Vec<String>>, output: Option<&Path>, cache_options: &ExportCacheOptions,
+) {
+    use aurynx::routes::RoutePreset;
+
+    if cache_options.redis.is_some() && target != "redis" {
+        eprintln!("Error: --redis only applies to the redis export");
+        std::process::exit(1);
+    }
+    let output = match (output, target) {
+        (Some(output), _) => output,
+        (None, "redis") => Path::new(""),
+        (None, _) => {
+            eprintln!("Error: --output is required to export {target}");
+            std::process::exit(1);
+        },
+    };
 
-                println!("Cache written successfully to {output:?}");
+    // Check the target/preset combination before scanning
+    let route_preset = match (target, preset) {
+        ("routes", Some(preset)) => match preset.parse::<RoutePreset>() {
+            Ok(preset) => Some(preset),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            },
+        },
+        ("routes", None) => {
+            eprintln!("Error: --preset symfony|laravel is required to export routes");
+            std::process::exit(1);
+        },
+        ("tests", Some("phpunit")) | ("entities", Some("doctrine")) | (_, None) => None,
+        (target, Some(preset)) => {
+            eprintln!("Error: preset '{preset}' does not apply to {target}");
+            std::process::exit(1);
+        },
+    };
+
+    let config_file = load_config(config_path);
+
+    let max_file_size = config_file.max_file_size_bytes();
+    aurynx::retry::set_policy(config_file.retry_policy());
+    let path = scan_paths(path, &config_file);
+    let ignore = ignore.or(config_file.ignore).unwrap_or_default();
+    let container = config_file.container.unwrap_or_default();
+    if target == "container" && container.service.is_none() {
+        eprintln!("Error: container export requires a 'container.service' mapping in config file");
+        std::process::exit(1);
+    }
+    if target == "ide" {
+        export_ide(&path, &ignore, max_file_size, output);
+        return;
+    }
+    if target == "unused-imports" {
+        export_unused_imports(&path, &ignore, max_file_size, output);
+        return;
+    }
+
+    let metadata = aurynx::scanner::scan_directory_with_limit(&path, &ignore, max_file_size, false);
+    match (target, route_preset) {
+        (_, Some(preset)) => export_routes(&metadata, preset, output),
+        ("container", None) => export_container(&metadata, &container, output),
+        ("entities", None) => export_entities(&metadata, output),
+        ("redis" | "apcu", None) => export_cache(&metadata, target, output, cache_options),
+        _ => export_tests(&metadata, output),
+    }
+}
+
+fn export_routes(
+    metadata: &[aurynx::metadata::PhpClassMetadata], preset: aurynx::routes::RoutePreset,
+    output: &Path,
+) {
+    use aurynx::routes::{extract_routes, write_route_table};
+
+    let table = extract_routes(metadata, preset);
+    for handler in &table.skipped {
+        eprintln!("Warning: skipped route on {handler} (path is not a literal)");
+    }
+
+    if let Err(e) = write_route_table(&table, preset, output) {
+        eprintln!("Error writing route table: {e}");
+        std::process::exit(1);
+    }
+    println!(
+        "Route table with {} route(s) written to {}",
+        table.routes.len(),
+        output.display()
+    );
+}
+
+fn export_container(
+    metadata: &[aurynx::metadata::PhpClassMetadata], config: &aurynx::container::ContainerConfig,
+    output: &Path,
+) {
+    use aurynx::container::{build_definitions, write_definitions};
+
+    let Some(definitions) = build_definitions(metadata, config) else {
+        return;
+    };
+    for parameter in &definitions.unresolved {
+        eprintln!("Warning: cannot autowire {parameter} (no class type, override or default)");
+    }
+
+    if let Err(e) = write_definitions(&definitions, output) {
+        eprintln!("Error writing container definitions: {e}");
+        std::process::exit(1);
+    }
+    println!(
+        "Container definitions for {} service(s) written to {}",
+        definitions.services.len(),
+        output.display()
+    );
+}
+
+fn export_entities(metadata: &[aurynx::metadata::PhpClassMetadata], output: &Path) {
+    use aurynx::doctrine::{build_entity_map, write_entity_map};
+
+    let map = build_entity_map(metadata);
+    if let Err(e) = write_entity_map(&map, output) {
+        eprintln!("Error writing entity map: {e}");
+        std::process::exit(1);
+    }
+    println!(
+        "Entity map with {} entity class(es) written to {}",
+        map.entities.len(),
+        output.display()
+    );
+}
+
+fn export_ide(path: &[PathBuf], ignore: &[String], max_file_size: u64, output: &Path) {
+    use aurynx::ide_index::{build_ide_index, write_ide_index};
+    use aurynx::scanner::{ignore_matcher, php_files_under};
+
+    // Positions are not in the metadata, so the files are parsed again
+    let matcher = ignore_matcher(path, ignore);
+    let files: Vec<PathBuf> = path
+        .iter()
+        .flat_map(|root| php_files_under(root, matcher.as_ref()))
+        .collect();
+
+    let index = build_ide_index(&files, max_file_size);
+    if let Err(e) = write_ide_index(&index, output) {
+        eprintln!("Error writing IDE index: {e}");
+        std::process::exit(1);
+    }
+    println!(
+        "IDE index with {} class(es) and {} attribute(s) written to {}",
+        index.classes.len(),
+        index.usages.len(),
+        output.display()
+    );
+}
+
+fn export_unused_imports(path: &[PathBuf], ignore: &[String], max_file_size: u64, output: &Path) {
+    use aurynx::scanner::{ignore_matcher, php_files_under};
+    use aurynx::unused_imports::{build_unused_imports_report, write_unused_imports_report};
+
+    // Imports are not in the metadata, so the files are parsed again
+    let matcher = ignore_matcher(path, ignore);
+    let files: Vec<PathBuf> = path
+        .iter()
+        .flat_map(|root| php_files_under(root, matcher.as_ref()))
+        .collect();
+
+    let report = build_unused_imports_report(&files, max_file_size);
+    if let Err(e) = write_unused_imports_report(&report, output) {
+        eprintln!("Error writing unused imports report: {e}");
+        std::process::exit(1);
+    }
+    println!(
+        "Unused imports report with {} import(s) in {} file(s) written to {}",
+        report.count(),
+        report.files.len(),
+        output.display()
+    );
+}
+
+fn export_cache(
+    metadata: &[aurynx::metadata::PhpClassMetadata], target: &str, output: &Path,
+    options: &ExportCacheOptions,
+) {
+    use aurynx::priming::{DEFAULT_KEY, push_to_redis, write_apcu_script, write_redis_protocol};
+
+    let key = options.key.as_deref().unwrap_or(DEFAULT_KEY);
+    let result = match (target, &options.redis) {
+        ("apcu", _) => write_apcu_script(metadata, &format!("{key}:"), options.ttl, output)
+            .map(|()| format!("APCu priming script written to {}", output.display())),
+        (_, Some(url)) => {
+            push_to_redis(metadata, key, url).map(|()| format!("Redis hash '{key}' updated"))
+        },
+        _ => write_redis_protocol(metadata, key, output)
+            .map(|()| {
+                format!(
+                    "Redis protocol file for redis-cli --pipe written to {}",
+                    output.display()
+                )
+            }),
+    };
+
+    match result {
+        Ok(message) => println!("{message} ({} classes)", metadata.len()),
+        Err(e) => {
+            eprintln!("Error exporting {target}: {e:#}");
+            std::process::exit(1);
+        },
+    }
+}
+
+fn export_tests(metadata: &[aurynx::metadata::PhpClassMetadata], output: &Path) {
+    use aurynx::phpunit::{build_test_manifest, write_test_manifest};
+
+    let manifest = build_test_manifest(metadata);
+    if let Err(e) = write_test_manifest(&manifest, output) {
+        eprintln!("Error writing test manifest: {e}");
+        std::process::exit(1);
+    }
+    println!(
+        "Test manifest with {} test(s) in {} class(es) written to {}",
+        manifest.tests,
+        manifest.classes.len(),
+        output.display()
+    );
+}
+
+/// Run `discovery:render`
+fn render(
+    template: &Path, out: &Path, config_path: Option<PathBuf>, path: Option<Vec<PathBuf>>,
+    ignore: Option<Vec<String>>,
+) {
+    let config_file = load_config(config_path);
+
+    let max_file_size = config_file.max_file_size_bytes();
+    aurynx::retry::set_policy(config_file.retry_policy());
+    let path = scan_paths(path, &config_file);
+    let ignore = ignore.or(config_file.ignore).unwrap_or_default();
+
+    let metadata = aurynx::scanner::scan_directory_with_limit(&path, &ignore, max_file_size, false);
+    let rendered = match aurynx::templates::render_file(&metadata, template) {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            eprintln!("Error rendering {}: {e:#}", template.display());
+            std::process::exit(1);
+        },
+    };
+    if let Err(e) = aurynx::writer::write_if_changed(out, rendered.as_bytes()) {
+        eprintln!("Error writing {}: {e:#}", out.display());
+        std::process::exit(1);
+    }
+    println!(
+        "Rendered {} to {} ({} classes)",
+        template.display(),
+        out.display(),
+        metadata.len()
+    );
+}
+
+/// Warn about unknown and misused attributes (`--check-attributes`)
+fn check_attribute_usage(
+    metadata: &[aurynx::metadata::PhpClassMetadata], paths: &[PathBuf], known: &[String],
+    stubs: bool,
+) {
+    use aurynx::attribute_stubs::{find_attribute_stubs, find_classmap, find_misused_attributes};
+
+    let stubs = if stubs {
+        let loaded = find_classmap(paths)
+            .ok_or_else(|| anyhow::anyhow!("no vendor/composer/autoload_classmap.php found"))
+            .and_then(|classmap| aurynx::composer::load_classmap(&classmap))
+            .and_then(|classmap| find_attribute_stubs(metadata, &classmap));
+        loaded.unwrap_or_else(|e| {
+            eprintln!("Warning: attribute_stubs: {e:#}");
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+    let mut known = known.to_vec();
+    known.extend(stubs.iter().map(|stub| stub.fqcn.clone()));
+
+    for unknown in aurynx::unknown_attributes::find_unknown_attributes(metadata, &known) {
+        eprintln!("Warning: unknown attribute: {unknown}");
+    }
+    for misused in find_misused_attributes(metadata, &stubs) {
+        eprintln!("Warning: misused attribute: {misused}");
+    }
+}
+
+/// Run `discovery:log`
+fn log(
+    config_path: Option<PathBuf>, journal: Option<PathBuf>, class: Option<&str>, limit: usize,
+    format: &str,
+) {
+    let journal = journal.unwrap_or_else(|| {
+        let config_file = load_config(config_path);
+        config_file.journal_file.unwrap_or_else(|| {
+            eprintln!("Error: --journal is required (or 'journal_file' in config file)");
+            std::process::exit(1);
+        })
+    });
+
+    let mut entries = match aurynx::journal::read(&journal) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            std::process::exit(1);
+        },
+    };
+    if let Some(class) = class {
+        entries.retain(|entry| entry.mentions(class));
+    }
+    let entries = &entries[entries.len().saturating_sub(limit)..];
+
+    if format == "json" {
+        match serde_json::to_string_pretty(entries) {
+            Ok(json) => println!("{json}"),
+            Err(e) => {
+                eprintln!("Error serializing journal entries: {e}");
+                std::process::exit(1);
+            },
+        }
+    } else if entries.is_empty() {
+        println!("No journal entries");
+    } else {
+        print!("{}", aurynx::journal::format_entries(entries, class));
+    }
+}
+
+/// Run `discovery:check`
+fn check(
+    config_path: Option<PathBuf>, path: Option<Vec<PathBuf>>, ignore: Option<Vec<String>>,
+    format: &str, baseline: Option<&Path>, generate_baseline: bool,
+) {
+    use aurynx::policy::{PolicyBaseline, check_policies, format_violations, parse_error};
+    use aurynx::scanner::{ignore_matcher, php_files_under, scan_files_detailed};
+
+    let config_file = load_config(config_path);
+
+    let max_file_size = config_file.max_file_size_bytes();
+    let rules = config_file.policies.clone().unwrap_or_default();
+    if rules.is_empty() {
+        eprintln!("Error: no 'policies' configured in config file");
+        std::process::exit(1);
+    }
+    let path = scan_paths(path, &config_file);
+    let ignore = ignore.or(config_file.ignore).unwrap_or_default();
+
+    let matcher = ignore_matcher(&path, &ignore);
+    let files: Vec<PathBuf> = path
+        .iter()
+        .flat_map(|root| php_files_under(root, matcher.as_ref()))
+        .collect();
+    let mut violations = Vec::new();
+    let mut metadata = Vec::new();
+    for scan in scan_files_detailed(&files, max_file_size) {
+        if let Some(error) = &scan.error {
+            violations.push(parse_error(&scan.path, &error.message));
+        }
+        metadata.extend(scan.classes);
+    }
+    violations.sort_by(|a, b| a.file.cmp(&b.file));
+    aurynx::resolver::resolve_hierarchy(&mut metadata);
+    violations.extend(check_policies(&metadata, &rules));
+
+    let mut suppressed = 0;
+    if let Some(baseline_path) = baseline {
+        if generate_baseline {
+            let generated = PolicyBaseline::from_violations(&violations, baseline_path);
+            if let Err(e) = generated.save(baseline_path) {
+                eprintln!("Error writing baseline: {e}");
+                std::process::exit(1);
+            }
+            println!(
+                "Baseline with {} violation(s) written to {}",
+                generated.ignored.len(),
+                baseline_path.display()
+            );
+            return;
+        }
+
+        // A missing baseline means nothing is suppressed yet
+        if baseline_path.exists() {
+            let loaded = match PolicyBaseline::load(baseline_path) {
+                Ok(b) => b,
+                Err(e) => {
+                    eprintln!("Error loading baseline: {e:#}");
+                    std::process::exit(1);
+                },
+            };
+            let result = loaded.apply(violations, baseline_path);
+            for entry in &result.unmatched {
+                let subject = entry
+                    .class
+                    .as_ref()
+                    .map_or_else(|| format!("{:?}", entry.file), Clone::clone);
+                eprintln!(
+                    "Warning: baseline entry for {subject} [{}] no longer matches",
+                    entry.rule
+                );
+            }
+            violations = result.violations;
+            suppressed = result.suppressed;
+        }
+    }
+
+    if format == "json" {
+        match serde_json::to_string_pretty(&violations) {
+            Ok(json) => println!("{json}"),
+            Err(e) => {
+                eprintln!("Error serializing violations: {e}");
+                std::process::exit(1);
+            },
+        }
+    } else {
+        print!("{}", format_violations(&violations));
+        println!(
+            "{} violation(s) of {} rule(s) in {} class(es), {suppressed} in baseline",
+            violations.len(),
+            rules.len(),
+            metadata.len()
+        );
+    }
+
+    if !violations.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Run `discovery:verify-classmap`
+fn verify_classmap(
+    config_path: Option<PathBuf>, path: Option<Vec<PathBuf>>, ignore: Option<Vec<String>>,
+    output: Option<PathBuf>, classmap_path: &Path, format: &str,
+) {
+    use aurynx::composer::{compare, format_diff, load_classmap};
+    use aurynx::scanner::{ignore_matcher, is_ignored};
+
+    let config_file = load_config(config_path);
+    set_encryption_key(&config_file);
+
+    let path = scan_paths(path, &config_file);
+    let ignore = ignore.or(config_file.ignore).unwrap_or_default();
+    let output_exclude = config_file.output_exclude.unwrap_or_default();
+    let output = output.or(config_file.output).unwrap_or_else(|| {
+        eprintln!("Error: --output is required (or 'output' in config file)");
+        std::process::exit(1);
+    });
+
+    let classes = match aurynx::reader::read_cache(&output) {
+        Ok(classes) => classes,
+        Err(e) => {
+            eprintln!("Error reading cache: {e:#}");
+            std::process::exit(1);
+        },
+    };
+    let classmap = match load_classmap(classmap_path) {
+        Ok(classmap) => classmap,
+        Err(e) => {
+            eprintln!("Error loading classmap: {e:#}");
+            std::process::exit(1);
+        },
+    };
+
+    // Classmap paths are absolute; match them against the roots as given so
+    // ignore and output_exclude patterns apply the way they do during a scan
+    let matcher = ignore_matcher(&path, &ignore);
+    let exclude = ignore_matcher(&path, &output_exclude);
+    let roots: Vec<(PathBuf, &PathBuf)> = path
+        .iter()
+        .filter_map(|root| Some((std::path::absolute(root).ok()?, root)))
+        .collect();
+    let scanned = |file: &Path| {
+        roots.iter().any(|(absolute, root)| {
+            file.strip_prefix(absolute).is_ok_and(|rest| {
+                let file = root.join(rest);
+                !is_ignored(matcher.as_ref(), &file) && !is_ignored(exclude.as_ref(), &file)
+            })
+        })
+    };
+    let diff = compare(&classmap, &classes, scanned);
+
+    if format == "json" {
+        match serde_json::to_string_pretty(&diff) {
+            Ok(json) => println!("{json}"),
+            Err(e) => {
+                eprintln!("Error serializing report: {e}");
+                std::process::exit(1);
+            },
+        }
+    } else {
+        print!("{}", format_diff(&diff));
+    }
+
+    if !diff.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Run `discovery:benchmark`
+fn benchmark(
+    config_path: Option<PathBuf>, path: Option<Vec<PathBuf>>, ignore: Option<Vec<String>>,
+    iterations: usize, format: Option<String>, baseline: Option<&Path>, save_baseline: bool,
+) {
+    use aurynx::benchmark::{BenchmarkOptions, BenchmarkReport, format_comparison, format_report};
+
+    let config_file = load_config(config_path);
+
+    let options = BenchmarkOptions {
+        max_file_size: config_file.max_file_size_bytes(),
+        paths: scan_paths(path, &config_file),
+        ignore: ignore.or(config_file.ignore).unwrap_or_default(),
+        iterations,
+        format: format.unwrap_or_else(|| "php".to_string()),
+    };
+
+    println!(
+        "Benchmarking {:?} ({} iteration(s))",
+        options.paths, options.iterations
+    );
+
+    let report = match aurynx::benchmark::run_benchmark(&options) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        },
+    };
+    print!("{}", format_report(&report));
+
+    let Some(baseline) = baseline else {
+        return;
+    };
+
+    if baseline.exists() {
+        match BenchmarkReport::load(baseline) {
+            Ok(previous) => print!("{}", format_comparison(&report, &previous)),
+            Err(e) => eprintln!("Warning: {e}"),
+        }
+    } else if !save_baseline {
+        eprintln!(
+            "Warning: baseline {} does not exist (use --save-baseline to create it)",
+            baseline.display()
+        );
+    }
+
+    if save_baseline {
+        if let Err(e) = report.save(baseline) {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+        println!("Baseline saved to {}", baseline.display());
+    }
+}
+
+/// Resolve the settings a started daemon is given (CLI args > config file)
+fn daemon_args(
+    config_path: Option<PathBuf>, path: Option<Vec<PathBuf>>, ignore: Option<Vec<String>>,
+    output: Option<PathBuf>, socket: Option<PathBuf>, pid: Option<PathBuf>,
+) -> (aurynx::config::ConfigFile, aurynx::service::DaemonArgs) {
+    let config_file = load_config(config_path.clone());
+    // The daemon reads the same config file (limits, batching, hooks)
+    let config = config_path.or_else(|| {
+        let default = PathBuf::from("aurynx.json");
+        default.exists().then_some(default)
+    });
+
+    let required = |value: Option<PathBuf>, flag: &str, key: &str| {
+        value.unwrap_or_else(|| {
+            eprintln!("Error: --{flag} is required (or '{key}' in config file)");
+            std::process::exit(1);
+        })
+    };
+    let paths = scan_paths(path, &config_file);
+    let daemon = aurynx::service::DaemonArgs {
+        config,
+        paths,
+        output: required(
+            output.or_else(|| config_file.output.clone()),
+            "output",
+            "output",
+        ),
+        socket: required(
+            socket.or_else(|| config_file.socket.clone()),
+            "socket",
+            "socket",
+        ),
+        pid: required(pid.or_else(|| config_file.pid.clone()), "pid", "pid"),
+        ignore: ignore.unwrap_or_default(),
+    };
+    (config_file, daemon)
+}
+
+/// This binary and the current directory, which a started daemon runs in
+fn current_program() -> (PathBuf, PathBuf) {
+    match (std::env::current_exe(), std::env::current_dir()) {
+        (Ok(program), Ok(working_dir)) => (program, working_dir),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        },
+    }
+}
+
+/// Options of `discovery:warm` besides the daemon settings
+struct WarmOptions {
+    timeout: std::time::Duration,
+    get_code: bool,
+}
+
+/// Run `discovery:warm`
+fn warm(
+    config_path: Option<PathBuf>, path: Option<Vec<PathBuf>>, ignore: Option<Vec<String>>,
+    output: Option<PathBuf>, socket: Option<PathBuf>, pid: Option<PathBuf>, options: &WarmOptions,
+) {
+    let (_, daemon) = daemon_args(config_path, path, ignore, output, socket, pid);
+    let (program, working_dir) = current_program();
+    let spec = aurynx::service::ServiceSpec::daemon("warm", program, working_dir, &daemon);
+
+    match aurynx::warm::warm(&spec, &daemon.socket, options.timeout, options.get_code) {
+        Ok(report) => {
+            if report.started {
+                println!(
+                    "Daemon started, initial scan finished in {:.2}s",
+                    report.ready_after.as_secs_f64()
+                );
+            } else {
+                println!("Daemon already running on {}", daemon.socket.display());
+            }
+            if let Some(bytes) = report.code_bytes {
+                println!("getCode answered with {bytes} bytes");
+            }
+        },
+        Err(e) => {
+            eprintln!("Error warming the daemon: {e:#}");
+            std::process::exit(1);
+        },
+    }
+}
+
+/// Run `discovery:promote`
+fn promote(
+    config_path: Option<PathBuf>, from: &Path, to: Option<PathBuf>, socket: Option<PathBuf>,
+) {
+    let config_file = load_config(config_path);
+    set_encryption_key(&config_file);
+    let to = to.or(config_file.output).unwrap_or_else(|| {
+        eprintln!("Error: --to is required (or 'output' in config file)");
+        std::process::exit(1);
+    });
+    let socket = socket.or(config_file.socket);
+
+    match aurynx::promote::promote(from, &to, socket.as_deref()) {
+        Ok(promotion) => {
+            println!(
+                "Promoted {} -> {} ({} classes)",
+                from.display(),
+                to.display(),
+                promotion.classes
+            );
+            if !promotion.manifest {
+                println!(
+                    "No manifest next to the built cache; the next incremental scan is a full scan"
+                );
+            }
+            match promotion.reloaded {
+                Some(response) => println!("Daemon reloaded: {response}"),
+                None => println!("No daemon running, nothing to reload"),
+            }
+        },
+        Err(e) => {
+            eprintln!("Error promoting the cache: {e:#}");
+            std::process::exit(1);
+        },
+    }
+}
+
+/// Run `discovery:snapshot`, or `discovery:restore` with `restore`
+fn snapshot(config_path: Option<PathBuf>, socket: Option<PathBuf>, file: &Path, restore: bool) {
+    let socket = socket.unwrap_or_else(|| {
+        let config_file = load_config(config_path);
+        config_file.socket.unwrap_or_else(|| {
+            eprintln!("Error: --socket is required (or 'socket' in config file)");
+            std::process::exit(1);
+        })
+    });
+
+    let result = if restore {
+        aurynx::daemon::state::request_restore(&socket, file)
+    } else {
+        aurynx::daemon::state::request_snapshot(&socket, file)
+    };
+    match result {
+        Ok(response) => println!("{response}"),
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            std::process::exit(1);
+        },
+    }
+}
+
+/// Options of `discovery:install-service` besides the daemon settings
+struct ServiceOptions {
+    manager: aurynx::service::ServiceManager,
+    name: Option<String>,
+    print: bool,
+    start: bool,
+}
+
+/// Run `discovery:install-service`
+fn install_service(
+    config_path: Option<PathBuf>, path: Option<Vec<PathBuf>>, ignore: Option<Vec<String>>,
+    output: Option<PathBuf>, socket: Option<PathBuf>, pid: Option<PathBuf>,
+    options: &ServiceOptions,
+) {
+    use aurynx::service::{ServiceSpec, default_name, install};
+
+    let (config_file, daemon) = daemon_args(config_path, path, ignore, output, socket, pid);
+    let (program, working_dir) = current_program();
+    let name = options
+        .name
+        .clone()
+        .unwrap_or_else(|| default_name(&working_dir));
+    let mut spec = ServiceSpec::daemon(&name, program, working_dir, &daemon);
+    spec.log_file = config_file
+        .log_file
+        .map(|log_file| spec.working_dir.join(log_file));
+
+    if options.print {
+        print!("{}", spec.render(options.manager));
+        return;
+    }
+
+    match install(&spec, options.manager, options.start) {
+        Ok(unit_path) => {
+            println!("Service file written to {}", unit_path.display());
+            if options.start {
+                println!("Service {name} enabled and started");
             }
         },
+        Err(e) => {
+            eprintln!("Error installing service: {e:#}");
+            std::process::exit(1);
+        },
+    }
+}
+
+/// Run `lsp` until the client sends `exit`
+fn lsp(
+    config_path: Option<PathBuf>, path: Option<Vec<PathBuf>>, ignore: Option<Vec<String>>,
+    output: Option<PathBuf>,
+) {
+    use aurynx::lsp::{ClassSource, serve};
+
+    let config_file = load_config(config_path);
+    set_encryption_key(&config_file);
+
+    let max_file_size = config_file.max_file_size_bytes();
+    aurynx::retry::set_policy(config_file.retry_policy());
+    let path = scan_paths(path, &config_file);
+    let output = output.or(config_file.output).unwrap_or_else(|| {
+        eprintln!("Error: --output is required (or 'output' in config file)");
+        std::process::exit(1);
+    });
+    let ignore = ignore.or(config_file.ignore).unwrap_or_default();
+
+    // stdout carries the protocol; diagnostics go to stderr
+    let mut source = ClassSource::new(&output, path, ignore, max_file_size);
+    if let Err(e) = serve(
+        &mut source,
+        std::io::stdin().lock(),
+        std::io::stdout().lock(),
+    ) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
     }
 }
@@ -0,0 +1,109 @@
+//! Per-class member cap (`max_class_members`)
+//!
+//! Generated classes (API clients, proxies, ORM base classes) can have
+//! thousands of methods, and a handful of them can make up most of the
+//! cache. With `max_class_members` set, the methods, properties and enum
+//! cases of every class are each cut to their first N entries, in source
+//! order. A class that lost members carries `truncated` with the number cut
+//! from each list, so consumers can tell a partial class from a complete
+//! one; the scan itself does not fail.
+
+use crate::metadata::{PhpClassMetadata, TruncatedMembers};
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
+
+/// A class whose member lists were cut
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OversizedClass {
+    pub fqcn: String,
+    pub file: PathBuf,
+    /// Members found before cutting, per list
+    pub methods: usize,
+    pub properties: usize,
+    pub cases: usize,
+}
+
+impl fmt::Display for OversizedClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}): {} methods, {} properties, {} cases",
+            self.fqcn,
+            self.file.display(),
+            self.methods,
+            self.properties,
+            self.cases
+        )
+    }
+}
+
+/// Cut the member lists of every class in `metadata` to `max` entries each
+///
+/// Returns the classes that were cut, in order.
+pub fn truncate_members(metadata: &mut [PhpClassMetadata], max: usize) -> Vec<OversizedClass> {
+    let mut oversized = Vec::new();
+    for class in metadata {
+        let (methods, properties, cases) =
+            (class.methods.len(), class.properties.len(), class.cases.len());
+        if methods.max(properties).max(cases) <= max {
+            continue;
+        }
+        class.methods.truncate(max);
+        class.properties.truncate(max);
+        class.cases.truncate(max);
+        class.truncated = Some(TruncatedMembers {
+            methods: methods.saturating_sub(max),
+            properties: properties.saturating_sub(max),
+            cases: cases.saturating_sub(max),
+        });
+        oversized.push(OversizedClass {
+            fqcn: class.fqcn.clone(),
+            file: class.file.clone(),
+            methods,
+            properties,
+            cases,
+        });
+    }
+    oversized
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::parser::PhpMetadataExtractor;
+
+    #[test]
+    fn test_members_are_cut_and_marked() {
+        let source = r"<?php
+namespace App;
+class Client { public $a; public $b; public function one() {} public function two() {}
+    public function three() {} }
+class Small { public function one() {} }
+";
+        let mut metadata = PhpMetadataExtractor::new()
+            .unwrap()
+            .extract_metadata(source, PathBuf::from("Client.php"))
+            .unwrap();
+
+        let oversized = truncate_members(&mut metadata, 2);
+
+        assert_eq!(oversized.len(), 1);
+        assert_eq!(
+            oversized[0].to_string(),
+            "\\App\\Client (Client.php): 3 methods, 2 properties, 0 cases"
+        );
+        let names: Vec<&str> = metadata[0].methods.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["one", "two"]);
+        assert_eq!(metadata[0].properties.len(), 2);
+        assert_eq!(
+            metadata[0].truncated,
+            Some(TruncatedMembers {
+                methods: 1,
+                ..TruncatedMembers::default()
+            })
+        );
+        assert_eq!(metadata[1].truncated, None);
+    }
+}
@@ -1,14 +1,31 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use indexmap::IndexMap;
 use std::path::PathBuf;
 
+/// Attributes keyed by attribute FQCN, in source declaration order
+pub type AttributeMap = IndexMap<String, Vec<Vec<AttributeArgument>>>;
+
+/// Matches of the custom queries keyed by query key, one capture name =>
+/// text map per match (see `crate::custom_queries`)
+pub type CustomCaptures = IndexMap<String, Vec<IndexMap<String, String>>>;
+
 /// Represents metadata for a single PHP class/interface/trait/enum
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct PhpClassMetadata {
+    /// Schema version of this record (see `crate::schema`)
+    #[serde(default = "crate::schema::legacy_schema_version")]
+    pub schema_version: u32,
     /// Fully Qualified Class Name (e.g., "App\\Entities\\User")
     pub fqcn: String,
     /// Absolute path to the file containing this class
     pub file: PathBuf,
+    /// xxh3 hash of the file source (16 hex digits), for staleness checks
+    #[serde(default)]
+    pub file_hash: String,
+    /// xxh3 hash of the signature, without method bodies or the file (only
+    /// with `signature_hash`, see `crate::signature`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature_hash: Option<String>,
     /// Type of the definition: 'class', 'interface', 'trait', or 'enum'
     #[serde(rename = "type")]
     pub kind: String,
@@ -17,11 +34,25 @@ pub struct PhpClassMetadata {
     /// Attributes applied to this class/interface/trait/enum
     /// Key: FQCN of the attribute (e.g., "Doctrine\\ORM\\Mapping\\Entity")
     /// Value: List of argument lists (one list of arguments per attribute instance)
-    pub attributes: HashMap<String, Vec<Vec<AttributeArgument>>>,
+    pub attributes: AttributeMap,
+    /// Class-level attributes inherited from parents, traits and interfaces
+    /// that are not declared on this class itself (set by attribute propagation)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inherited_attributes: Option<AttributeMap>,
     /// Parent class FQCN, if any (only for classes)
     pub extends: Option<String>,
     /// List of implemented interface FQCNs
     pub implements: Vec<String>,
+    /// All implemented interfaces, including inherited ones (set by the resolution pass)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_implements: Option<Vec<String>>,
+    /// Parent class chain, nearest first (set by the resolution pass)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ancestors: Option<Vec<String>>,
+    /// FQCNs of traits used directly by this class/trait/enum (left out of
+    /// the cache when empty)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub traits: Vec<String>,
     /// Methods of this class
     pub methods: Vec<PhpMethodMetadata>,
     /// Properties of this class
@@ -30,6 +61,41 @@ pub struct PhpClassMetadata {
     pub backing_type: Option<String>,
     /// Enum cases (only for enums)
     pub cases: Vec<EnumCase>,
+    /// Namespace, imports and declarations of the declaring file (only with
+    /// `--include-file-context`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_context: Option<PhpFileContext>,
+    /// Captures of the configured custom queries (only with `queries`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom: Option<CustomCaptures>,
+    /// Members left out by `max_class_members` (only for classes over it)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<TruncatedMembers>,
+}
+
+/// Members cut from each list of a class over `max_class_members`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct TruncatedMembers {
+    pub methods: usize,
+    pub properties: usize,
+    pub cases: usize,
+}
+
+/// File-level context of a declaration, shared by all classes of one file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct PhpFileContext {
+    /// Namespace without the leading backslash, if any
+    pub namespace: Option<String>,
+    /// Whether the file declares `strict_types=1`
+    pub strict_types: bool,
+    /// Class `use` imports: alias => FQCN, in source order
+    pub imports: IndexMap<String, String>,
+    /// FQCNs of the classes, interfaces, traits and enums declared in the file
+    pub classes: Vec<String>,
+    /// FQCNs of the functions declared in the file
+    pub functions: Vec<String>,
+    /// FQCNs of the constants declared with `const` in the file
+    pub constants: Vec<String>,
 }
 
 /// Class modifiers (abstract, final, readonly)
@@ -50,7 +116,7 @@ pub struct PhpMethodMetadata {
     /// Method modifiers
     pub modifiers: MethodModifiers,
     /// Attributes applied to this method
-    pub attributes: HashMap<String, Vec<Vec<AttributeArgument>>>,
+    pub attributes: AttributeMap,
     /// Method parameters
     pub parameters: Vec<PhpParameterMetadata>,
     /// Return type hint, if any
@@ -75,7 +141,7 @@ pub struct PhpParameterMetadata {
     /// Default value, if any
     pub default_value: Option<String>,
     /// Attributes applied to this parameter
-    pub attributes: HashMap<String, Vec<Vec<AttributeArgument>>>,
+    pub attributes: AttributeMap,
 }
 
 /// Represents a class property
@@ -92,7 +158,7 @@ pub struct PhpPropertyMetadata {
     /// Default value, if any
     pub default_value: Option<String>,
     /// Attributes applied to this property
-    pub attributes: HashMap<String, Vec<Vec<AttributeArgument>>>,
+    pub attributes: AttributeMap,
 }
 
 /// Property modifiers (static, readonly)
@@ -110,7 +176,7 @@ pub struct EnumCase {
     /// Backed value for backed enums (string or int)
     pub value: Option<String>,
     /// Attributes applied to this enum case
-    pub attributes: HashMap<String, Vec<Vec<AttributeArgument>>>,
+    pub attributes: AttributeMap,
 }
 
 /// Represents a single argument in an attribute
@@ -123,21 +189,103 @@ pub enum AttributeArgument {
     Positional(String),
 }
 
+/// Declaration an attribute map is attached to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeSite {
+    Class,
+    Method,
+    /// Method parameter; constructor parameters may be promoted properties
+    Parameter { constructor: bool },
+    Property,
+    EnumCase,
+}
+
+/// Comparison key of a class name: no leading backslash, ASCII case folded
+/// (as PHP does)
+#[must_use]
+pub fn fold_class_name(name: &str) -> String {
+    name.trim_start_matches('\\').to_ascii_lowercase()
+}
+
 impl PhpClassMetadata {
     #[must_use] 
     pub fn new(fqcn: String, file: PathBuf, kind: String) -> Self {
         Self {
+            schema_version: crate::schema::SCHEMA_VERSION,
             fqcn,
             file,
+            file_hash: String::new(),
+            signature_hash: None,
             kind,
             modifiers: ClassModifiers::default(),
-            attributes: HashMap::new(),
+            attributes: AttributeMap::new(),
+            inherited_attributes: None,
             extends: None,
             implements: Vec::new(),
+            resolved_implements: None,
+            ancestors: None,
+            traits: Vec::new(),
             methods: Vec::new(),
             properties: Vec::new(),
             backing_type: None,
             cases: Vec::new(),
+            file_context: None,
+            custom: None,
+            truncated: None,
+        }
+    }
+
+    /// FQCNs of the attributes declared on the class or any method,
+    /// parameter, property or enum case (with repetitions)
+    pub fn attribute_names(&self) -> impl Iterator<Item = &String> {
+        let methods = self.methods.iter().flat_map(|method| {
+            std::iter::once(&method.attributes)
+                .chain(method.parameters.iter().map(|parameter| &parameter.attributes))
+        });
+        std::iter::once(&self.attributes)
+            .chain(methods)
+            .chain(self.properties.iter().map(|property| &property.attributes))
+            .chain(self.cases.iter().map(|case| &case.attributes))
+            .flat_map(AttributeMap::keys)
+    }
+
+    /// Non-empty attribute maps of the class and its members, labelled
+    /// `Class`, `Class::method()`, `Class::method($parameter)`,
+    /// `Class::$property` or `Class::CASE`
+    #[must_use]
+    pub fn attribute_maps(&self) -> Vec<(String, AttributeSite, &AttributeMap)> {
+        let fqcn = &self.fqcn;
+        let mut maps = vec![(fqcn.clone(), AttributeSite::Class, &self.attributes)];
+        for method in &self.methods {
+            maps.push((
+                format!("{fqcn}::{}()", method.name),
+                AttributeSite::Method,
+                &method.attributes,
+            ));
+            let constructor = method.name.eq_ignore_ascii_case("__construct");
+            for parameter in &method.parameters {
+                maps.push((
+                    format!("{fqcn}::{}(${})", method.name, parameter.name),
+                    AttributeSite::Parameter { constructor },
+                    &parameter.attributes,
+                ));
+            }
+        }
+        for property in &self.properties {
+            maps.push((
+                format!("{fqcn}::${}", property.name),
+                AttributeSite::Property,
+                &property.attributes,
+            ));
+        }
+        for case in &self.cases {
+            maps.push((
+                format!("{fqcn}::{}", case.name),
+                AttributeSite::EnumCase,
+                &case.attributes,
+            ));
         }
+        maps.retain(|(_, _, attributes)| !attributes.is_empty());
+        maps
     }
 }
@@ -0,0 +1,113 @@
+#![allow(unsafe_code)]
+
+//! Low-priority scanning (`--nice`)
+//!
+//! A full scan uses every core and reads files as fast as the disk allows,
+//! which can make an IDE or browser stutter on a developer laptop. In nice
+//! mode the scan runs on a quarter of the cores, its threads yield between
+//! files, and the process runs at the lowest CPU priority and, on Linux, in
+//! the idle IO class, so interactive work always comes first. The scan is
+//! slower, but its results are the same.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::warn;
+
+/// Worker threads in nice mode (0 while disabled)
+static THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Scheduling priority of a niced process (the lowest)
+#[cfg(unix)]
+const NICE_LEVEL: libc::c_int = 19;
+
+/// Enter nice mode for the rest of the process
+///
+/// Must run before the first parallel scan: the priorities are inherited by
+/// threads created afterwards, and the rayon thread pool can only be sized
+/// once. Failures are logged, and the scan proceeds at normal priority.
+pub fn enable() {
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |cores| cores.get() / 4)
+        .max(1);
+    THREADS.store(threads, Ordering::Relaxed);
+
+    lower_priority();
+    if let Err(e) = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()
+    {
+        warn!("Could not limit scan threads: {}", e);
+    }
+}
+
+/// Thread count for parallel scans, if nice mode limits it
+#[must_use]
+pub fn threads() -> Option<usize> {
+    match THREADS.load(Ordering::Relaxed) {
+        0 => None,
+        threads => Some(threads),
+    }
+}
+
+/// Let other threads run before the next file (no-op unless nice)
+pub fn pause() {
+    if threads().is_some() {
+        std::thread::yield_now();
+    }
+}
+
+#[cfg(unix)]
+fn lower_priority() {
+    // SAFETY: setpriority only reads its integer arguments
+    let result = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, NICE_LEVEL) };
+    if result != 0 {
+        warn!(
+            "Could not lower CPU priority: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    lower_io_priority();
+}
+
+#[cfg(not(unix))]
+fn lower_priority() {}
+
+/// Move the process into the idle IO scheduling class
+#[cfg(target_os = "linux")]
+fn lower_io_priority() {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+    // SAFETY: ioprio_set only reads its integer arguments
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        )
+    };
+    if result != 0 {
+        warn!(
+            "Could not lower IO priority: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn lower_io_priority() {}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lower_priority_sets_lowest_nice_level() {
+        // Only this test thread is affected on Linux
+        lower_priority();
+        // SAFETY: getpriority only reads its integer arguments
+        let level = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+        assert_eq!(level, NICE_LEVEL);
+    }
+}
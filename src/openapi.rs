@@ -0,0 +1,533 @@
+//! Route attribute export to an `OpenAPI` 3 document
+//!
+//! Operations are read from method attributes matching configured *shapes*:
+//! which attribute declares an operation and which of its arguments hold the
+//! path, HTTP methods, summary, operation id and tags. Without configured
+//! shapes, Symfony-style `#[Route]` and swagger-php `#[OA\Get]`, `#[OA\Post]`,
+//! ... attributes are recognised. A class-level attribute of the same shape
+//! contributes a path prefix, as Symfony route prefixes do.
+//!
+//! Only literal argument values can be exported; operations whose path is a
+//! constant or expression are reported as skipped.
+
+use crate::metadata::{AttributeArgument, PhpClassMetadata, PhpMethodMetadata};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use std::path::{Path, PathBuf};
+
+/// `openapi` section of `aurynx.json`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OpenApiConfig {
+    /// Output file for the document (overridden by `--openapi`)
+    pub output: Option<PathBuf>,
+    /// `info.title` (default: "API")
+    pub title: Option<String>,
+    /// `info.version` (default: "1.0.0")
+    pub version: Option<String>,
+    /// Attribute shapes declaring operations (default: [`default_shapes`])
+    pub operations: Option<Vec<OperationShape>>,
+}
+
+/// How to read an operation from an attribute
+#[derive(Debug, Clone, Deserialize)]
+pub struct OperationShape {
+    /// Attribute name, matched against the end of the resolved FQCN on a
+    /// namespace boundary (`Route` matches `\Symfony\...\Route`)
+    pub attribute: String,
+    /// Named argument holding the path (falls back to the first positional one)
+    #[serde(default = "default_path_argument")]
+    pub path: String,
+    /// Fixed HTTP method for this attribute (e.g. `get` for `OA\Get`)
+    pub method: Option<String>,
+    /// Named argument listing HTTP methods (used when `method` is not set)
+    pub methods: Option<String>,
+    pub summary: Option<String>,
+    pub operation_id: Option<String>,
+    pub tags: Option<String>,
+}
+
+fn default_path_argument() -> String {
+    "path".to_string()
+}
+
+/// Symfony `Route` plus swagger-php `OA\Get` .. `OA\Delete`
+#[must_use]
+pub fn default_shapes() -> Vec<OperationShape> {
+    let mut shapes = vec![OperationShape {
+        attribute: "Route".to_string(),
+        path: default_path_argument(),
+        method: None,
+        methods: Some("methods".to_string()),
+        summary: None,
+        operation_id: Some("name".to_string()),
+        tags: None,
+    }];
+
+    for method in ["Get", "Post", "Put", "Patch", "Delete"] {
+        shapes.push(OperationShape {
+            attribute: format!("OpenApi\\Attributes\\{method}"),
+            path: default_path_argument(),
+            method: Some(method.to_lowercase()),
+            methods: None,
+            summary: Some("summary".to_string()),
+            operation_id: Some("operationId".to_string()),
+            tags: Some("tags".to_string()),
+        });
+    }
+
+    shapes
+}
+
+/// Generated document and the operations that could not be exported
+#[derive(Debug)]
+pub struct OpenApiExport {
+    pub document: Value,
+    /// `Class::method` handlers whose path is not a literal
+    pub skipped: Vec<String>,
+}
+
+/// Build the API document (`OpenAPI` 3.0) from the scanned metadata
+#[must_use]
+pub fn generate_openapi(
+    metadata_list: &[PhpClassMetadata], config: &OpenApiConfig,
+) -> OpenApiExport {
+    let shapes = config.operations.clone().unwrap_or_else(default_shapes);
+    let mut paths = Map::new();
+    let mut skipped = Vec::new();
+
+    for class in metadata_list {
+        for method in &class.methods {
+            for (name, instances) in &method.attributes {
+                let Some(shape) = shapes
+                    .iter()
+                    .find(|s| matches_attribute(name, &s.attribute))
+                else {
+                    continue;
+                };
+                // Class-level attribute: path and route name prefixes
+                let class_args = class.attributes.get(name).and_then(|args| args.first());
+                let class_literal = |key: Option<&str>, position: Option<usize>| {
+                    class_args
+                        .zip(key)
+                        .and_then(|(args, key)| argument(args, key, position))
+                        .and_then(php_string)
+                        .unwrap_or_default()
+                };
+                let prefix = class_literal(Some(&shape.path), Some(0));
+                let id_prefix = class_literal(shape.operation_id.as_deref(), None);
+
+                for args in instances {
+                    let handler = format!("{}::{}", class.fqcn, method.name);
+                    let Some(path) = argument(args, &shape.path, Some(0)).and_then(php_string)
+                    else {
+                        skipped.push(handler);
+                        continue;
+                    };
+                    let path = join_paths(&prefix, &path);
+                    let operation = operation(shape, args, method, &path, &handler, &id_prefix);
+
+                    let item = paths
+                        .entry(openapi_path(&path))
+                        .or_insert_with(|| Value::Object(Map::new()));
+                    for (i, http_method) in http_methods(shape, args).into_iter().enumerate() {
+                        // operationId must be unique: suffix all but the first method
+                        let mut operation = operation.clone();
+                        if i > 0
+                            && let Some(Value::String(id)) = operation.get_mut("operationId")
+                        {
+                            id.push('_');
+                            id.push_str(&http_method);
+                        }
+                        item[http_method.as_str()] = operation;
+                    }
+                }
+            }
+        }
+    }
+
+    let document = json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": config.title.as_deref().unwrap_or("API"),
+            "version": config.version.as_deref().unwrap_or("1.0.0"),
+        },
+        "paths": paths,
+    });
+
+    OpenApiExport { document, skipped }
+}
+
+/// Write the document as pretty-printed JSON, keeping an unchanged file as
+/// it is
+///
+/// # Errors
+///
+/// Returns an error if the document cannot be written.
+pub fn write_openapi(document: &Value, output_path: &Path) -> Result<()> {
+    let content = serde_json::to_string_pretty(document)? + "\n";
+    crate::writer::write_if_changed(output_path, content.as_bytes())
+        .with_context(|| format!("Failed to write OpenAPI document {}", output_path.display()))?;
+    Ok(())
+}
+
+fn operation(
+    shape: &OperationShape, args: &[AttributeArgument], method: &PhpMethodMetadata, path: &str,
+    handler: &str, id_prefix: &str,
+) -> Value {
+    let mut operation = Map::new();
+    let text = |key: Option<&str>| {
+        key.and_then(|key| argument(args, key, None))
+            .and_then(php_string)
+    };
+
+    if let Some(operation_id) = text(shape.operation_id.as_deref()) {
+        operation.insert(
+            "operationId".to_string(),
+            format!("{id_prefix}{operation_id}").into(),
+        );
+    }
+    if let Some(summary) = text(shape.summary.as_deref()) {
+        operation.insert("summary".to_string(), summary.into());
+    }
+    if let Some(tags) = shape
+        .tags
+        .as_deref()
+        .and_then(|key| argument(args, key, None))
+        .and_then(php_string_list)
+    {
+        operation.insert("tags".to_string(), tags.into());
+    }
+
+    let parameters: Vec<Value> = path_parameters(path)
+        .into_iter()
+        .map(|name| {
+            let schema = method
+                .parameters
+                .iter()
+                .find(|p| p.name == name)
+                .and_then(|p| p.type_hint.as_deref())
+                .map_or("string", schema_type);
+            json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": {"type": schema},
+            })
+        })
+        .collect();
+    if !parameters.is_empty() {
+        operation.insert("parameters".to_string(), parameters.into());
+    }
+
+    operation.insert(
+        "responses".to_string(),
+        json!({"default": {"description": "Default response"}}),
+    );
+    operation.insert("x-handler".to_string(), handler.into());
+    Value::Object(operation)
+}
+
+/// HTTP methods of an operation, lowercased (`get` when none are declared)
+fn http_methods(shape: &OperationShape, args: &[AttributeArgument]) -> Vec<String> {
+    if let Some(method) = &shape.method {
+        return vec![method.to_lowercase()];
+    }
+
+    let methods: Vec<String> = shape
+        .methods
+        .as_deref()
+        .and_then(|key| argument(args, key, None))
+        .and_then(php_string_list)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|m| m.to_lowercase())
+        .collect();
+
+    if methods.is_empty() {
+        vec!["get".to_string()]
+    } else {
+        methods
+    }
+}
+
+/// Whether the resolved attribute `fqcn` is `name` (suffix on a namespace boundary)
+pub(crate) fn matches_attribute(fqcn: &str, name: &str) -> bool {
+    let name = name.trim_start_matches('\\');
+    fqcn.trim_start_matches('\\')
+        .strip_suffix(name)
+        .is_some_and(|rest| rest.is_empty() || rest.ends_with('\\'))
+}
+
+/// Raw value of the named argument `key`, or of the positional one at `position`
+pub(crate) fn argument<'a>(
+    args: &'a [AttributeArgument], key: &str, position: Option<usize>,
+) -> Option<&'a str> {
+    let named = args.iter().find_map(|arg| match arg {
+        AttributeArgument::Named { key: k, value } if k == key => Some(value.as_str()),
+        _ => None,
+    });
+    let Some(position) = position.filter(|_| named.is_none()) else {
+        return named;
+    };
+
+    args.iter()
+        .filter_map(|arg| match arg {
+            AttributeArgument::Positional(value) => Some(value.as_str()),
+            AttributeArgument::Named { .. } => None,
+        })
+        .nth(position)
+}
+
+/// Value of a PHP string literal (`'..'` or `".."`)
+pub(crate) fn php_string(value: &str) -> Option<String> {
+    let value = value.trim();
+    let quote = value.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+    let inner = value.strip_prefix(quote)?.strip_suffix(quote)?;
+
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some(next) if next == quote || next == '\\' => out.push(next),
+                Some(next) => {
+                    out.push('\\');
+                    out.push(next);
+                },
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    Some(out)
+}
+
+/// Class name of a `Foo::class` reference or string literal, without the
+/// leading backslash
+pub(crate) fn php_class_name(value: &str) -> Option<String> {
+    let value = value.trim();
+    let name = match value.strip_suffix("::class") {
+        Some(name) => name.to_string(),
+        None => php_string(value)?,
+    };
+    Some(name.trim_start_matches('\\').to_string())
+}
+
+/// Strings of a PHP array literal of strings, or a single string literal
+pub(crate) fn php_string_list(value: &str) -> Option<Vec<String>> {
+    let value = value.trim();
+    let inner = value
+        .strip_prefix('[')
+        .and_then(|v| v.strip_suffix(']'))
+        .or_else(|| {
+            value
+                .strip_prefix("array(")
+                .and_then(|v| v.strip_suffix(')'))
+        });
+
+    let Some(inner) = inner else {
+        return php_string(value).map(|s| vec![s]);
+    };
+
+    split_top_level(inner)
+        .into_iter()
+        .filter(|item| !item.trim().is_empty())
+        .map(php_string)
+        .collect()
+}
+
+/// Split on commas outside string literals
+fn split_top_level(list: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (i, c) in list.char_indices() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            },
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == ',' => {
+                items.push(&list[start..i]);
+                start = i + 1;
+            },
+            None => {},
+        }
+    }
+    items.push(&list[start..]);
+    items
+}
+
+pub(crate) fn join_paths(prefix: &str, path: &str) -> String {
+    let joined = match (prefix.trim_end_matches('/'), path) {
+        ("", path) => path.to_string(),
+        (prefix, "") => prefix.to_string(),
+        (prefix, path) => format!("{prefix}/{}", path.trim_start_matches('/')),
+    };
+    if joined.starts_with('/') {
+        joined
+    } else {
+        format!("/{joined}")
+    }
+}
+
+/// Path with Symfony inline requirements and defaults removed (`{id<\d+>?1}` → `{id}`)
+fn openapi_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    let mut in_placeholder = false;
+    let mut in_name = false;
+    for c in path.chars() {
+        match c {
+            '{' => {
+                in_placeholder = true;
+                in_name = true;
+                out.push(c);
+            },
+            '}' if in_placeholder => {
+                in_placeholder = false;
+                out.push(c);
+            },
+            '<' | '?' if in_placeholder => in_name = false,
+            _ if in_placeholder && !in_name => {},
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Placeholder names in `path`
+fn path_parameters(path: &str) -> Vec<String> {
+    openapi_path(path)
+        .split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}').map(|(name, _)| name.to_string()))
+        .collect()
+}
+
+fn schema_type(type_hint: &str) -> &'static str {
+    match type_hint.trim_start_matches('?') {
+        "int" => "integer",
+        "float" => "number",
+        "bool" => "boolean",
+        _ => "string",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::parser::tests::extract;
+
+    #[test]
+    fn test_symfony_routes_with_class_prefix() {
+        let metadata = extract(
+            "/src/Controller.php",
+            r#"<?php
+namespace App\Controller;
+use Symfony\Component\Routing\Attribute\Route;
+
+#[Route('/api/users', name: 'users_')]
+class UserController {
+    #[Route('/{id<\d+>}', name: 'show', methods: ['GET', "HEAD"])]
+    public function show(int $id): void {}
+
+    #[Route(path: '', name: 'list')]
+    public function list(): void {}
+
+    #[Route(self::PATH)]
+    public function dynamic(): void {}
+}
+"#,
+        );
+
+        let export = generate_openapi(&metadata, &OpenApiConfig::default());
+        let paths = &export.document["paths"];
+
+        let show = &paths["/api/users/{id}"]["get"];
+        assert_eq!(show["operationId"], "users_show");
+        assert_eq!(show["parameters"][0]["name"], "id");
+        assert_eq!(show["parameters"][0]["schema"]["type"], "integer");
+        assert_eq!(show["x-handler"], "\\App\\Controller\\UserController::show");
+        assert_eq!(
+            paths["/api/users/{id}"]["head"]["operationId"],
+            "users_show_head"
+        );
+        assert_eq!(paths["/api/users"]["get"]["operationId"], "users_list");
+        assert_eq!(
+            export.skipped,
+            ["\\App\\Controller\\UserController::dynamic"]
+        );
+    }
+
+    #[test]
+    fn test_swagger_php_attributes() {
+        let metadata = extract(
+            "/src/Controller.php",
+            r#"<?php
+namespace App\Controller;
+use OpenApi\Attributes as OA;
+
+class PetController {
+    #[OA\Post(path: "/pets", summary: 'Add a pet', operationId: 'addPet', tags: ['Pets'])]
+    public function create(): void {}
+}
+"#,
+        );
+
+        let export = generate_openapi(&metadata, &OpenApiConfig::default());
+        let create = &export.document["paths"]["/pets"]["post"];
+
+        assert_eq!(export.document["openapi"], "3.0.3");
+        assert_eq!(create["summary"], "Add a pet");
+        assert_eq!(create["operationId"], "addPet");
+        assert_eq!(create["tags"], json!(["Pets"]));
+    }
+
+    #[test]
+    fn test_configured_shapes_replace_defaults() {
+        let metadata = extract(
+            "/src/Controller.php",
+            r"<?php
+namespace App;
+use Framework\Http\Endpoint;
+use Symfony\Component\Routing\Attribute\Route;
+
+class Api {
+    #[Endpoint(uri: '/health', verb: 'GET', title: 'Health check')]
+    public function health(): void {}
+
+    #[Route('/ignored')]
+    public function ignored(): void {}
+}
+",
+        );
+        let config: OpenApiConfig = serde_json::from_value(json!({
+            "title": "Internal",
+            "operations": [{
+                "attribute": "Http\\Endpoint",
+                "path": "uri",
+                "methods": "verb",
+                "summary": "title",
+            }],
+        }))
+        .unwrap();
+
+        let export = generate_openapi(&metadata, &config);
+        let paths = export.document["paths"].as_object().unwrap();
+
+        assert_eq!(export.document["info"]["title"], "Internal");
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths["/health"]["get"]["summary"], "Health check");
+    }
+}
@@ -1,68 +1,157 @@
+use crate::diagnostic::SyntaxDiagnostic;
 use crate::error::{AurynxError, Result};
-use crate::metadata::{AttributeArgument, EnumCase, PhpClassMetadata};
-use std::collections::HashMap;
-use std::path::PathBuf;
-use tree_sitter::{Node, Parser, Query, QueryCursor, StreamingIterator, Tree};
+use crate::metadata::{
+    AttributeArgument, AttributeMap, EnumCase, PhpClassMetadata, PhpFileContext,
+};
+use crate::php_version::{NewerSyntax, PhpVersion};
+use indexmap::IndexMap;
+use std::path::{Path, PathBuf};
+use crate::custom_queries::CustomQueries;
+use std::sync::LazyLock;
+use tree_sitter::{Language, Node, Parser, Query, QueryCursor, StreamingIterator, Tree};
 use tree_sitter_php::LANGUAGE_PHP;
 
+/// PHP grammar, loaded once per process
+pub(crate) static PHP_LANGUAGE: LazyLock<Language> = LazyLock::new(|| LANGUAGE_PHP.into());
+
+/// Namespace and `use` import query, compiled once and shared by all extractors
+static IMPORTS_QUERY: LazyLock<std::result::Result<Query, String>> = LazyLock::new(|| {
+    Query::new(
+        &PHP_LANGUAGE,
+        r"
+        (namespace_definition name: (_) @namespace)
+        (namespace_use_clause
+          [
+            (qualified_name)
+            (name)
+          ] @fqcn
+          alias: (name)? @alias
+        )
+        ",
+    )
+    .map_err(|e| format!("Error compiling imports query: {e:?}"))
+});
+
+/// Attribute group query used by `AttributeChecker`
+static ATTRIBUTE_QUERY: LazyLock<std::result::Result<Arc<Query>, String>> = LazyLock::new(|| {
+    Query::new(&PHP_LANGUAGE, "(attribute_group) @attr")
+        .map(Arc::new)
+        .map_err(|e| format!("Error compiling query: {e:?}"))
+});
+
 pub struct PhpMetadataExtractor {
     parser: Parser,
-    imports_query: Query,
+    imports_query: &'static Query,
+    /// Attach the file context ([`PhpFileContext`]) to each class (`include_file_context`)
+    include_file_context: bool,
+    /// Custom queries run inside each class declaration (`queries`)
+    queries: Option<Arc<CustomQueries>>,
 }
 
 impl PhpMetadataExtractor {
     pub fn new() -> Result<Self> {
         let mut parser = Parser::new();
-        let language = LANGUAGE_PHP.into();
-        parser.set_language(&language).map_err(|e| {
+        parser.set_language(&PHP_LANGUAGE).map_err(|e| {
             AurynxError::tree_sitter_error(format!("Error loading PHP grammar: {e:?}"))
         })?;
 
-        let imports_query = Query::new(
-            &language,
-            r"
-            (namespace_definition name: (_) @namespace)
-            (namespace_use_clause
-              [
-                (qualified_name)
-                (name)
-              ] @fqcn
-              alias: (name)? @alias
-            )
-            ",
-        )
-        .map_err(|e| {
-            AurynxError::tree_sitter_error(format!("Error compiling imports query: {e:?}"))
-        })?;
+        let imports_query = IMPORTS_QUERY
+            .as_ref()
+            .map_err(|e| AurynxError::tree_sitter_error(e.clone()))?;
 
         Ok(Self {
             parser,
             imports_query,
+            include_file_context: false,
+            queries: None,
         })
     }
 
+    /// Attach the file context to every class extracted from now on
+    pub const fn set_include_file_context(&mut self, enabled: bool) {
+        self.include_file_context = enabled;
+    }
+
+    /// Run `queries` on every class extracted from now on (`None` disables)
+    pub fn set_custom_queries(&mut self, queries: Option<Arc<CustomQueries>>) {
+        self.queries = queries;
+    }
+
     /// Extract all class/interface/trait/enum metadata from PHP source code
     pub fn extract_metadata(
         &mut self, content: &str, file_path: PathBuf,
     ) -> Result<Vec<PhpClassMetadata>> {
-        let tree = self
-            .parser
-            .parse(content, None)
-            .ok_or_else(|| AurynxError::parse_error(file_path.clone(), "Error parsing PHP code"))?;
+        self.extract_metadata_with_diagnostic(content, file_path)
+            .map(|(metadata, _)| metadata)
+    }
+
+    /// Extract metadata and diagnose the first syntax error, if any
+    ///
+    /// tree-sitter recovers from syntax errors, so the declarations around
+    /// the error are returned as well.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source cannot be parsed at all.
+    pub fn extract_metadata_with_diagnostic(
+        &mut self, content: &str, file_path: PathBuf,
+    ) -> Result<(Vec<PhpClassMetadata>, Option<SyntaxDiagnostic>)> {
+        self.extract_metadata_for_version(content, file_path, None)
+            .map(|(metadata, diagnostic, _)| (metadata, diagnostic))
+    }
+
+    /// Extract metadata, diagnose the first syntax error and list the syntax
+    /// `target` does not support (see [`crate::php_version`])
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source cannot be parsed at all.
+    pub fn extract_metadata_for_version(
+        &mut self, content: &str, file_path: PathBuf, target: Option<PhpVersion>,
+    ) -> Result<(Vec<PhpClassMetadata>, Option<SyntaxDiagnostic>, Vec<NewerSyntax>)> {
+        let (tree, file_context) = self.parse_with_context(content, &file_path)?;
+        let diagnostic = SyntaxDiagnostic::find(&tree, content);
+        let newer_syntax = target
+            .map(|target| crate::php_version::find_newer_syntax(&tree, target))
+            .unwrap_or_default();
+
+        let mut metadata = self.extract_declarations(&tree, &file_context, file_path)?;
+
+        let file_hash = format!("{:016x}", xxhash_rust::xxh3::xxh3_64(content.as_bytes()));
+        for class in &mut metadata {
+            class.file_hash.clone_from(&file_hash);
+        }
+        if self.include_file_context {
+            let file_context = file_context.to_metadata(&tree, &metadata);
+            for class in &mut metadata {
+                class.file_context = Some(file_context.clone());
+            }
+        }
+
+        Ok((metadata, diagnostic, newer_syntax))
+    }
+
+    /// Parse `content` and collect its namespace and imports
+    ///
+    /// For passes that walk the syntax tree themselves (see `crate::ide_index`).
+    pub(crate) fn parse_with_context<'a>(
+        &mut self, content: &'a str, file_path: &Path,
+    ) -> Result<(Tree, FileContext<'a>)> {
+        let tree = self.parser.parse(content, None).ok_or_else(|| {
+            AurynxError::parse_error(file_path.to_path_buf(), "Error parsing PHP code")
+        })?;
 
         let mut context = FileContext::new(content);
         self.extract_namespace_and_imports(&tree, &mut context)?;
 
-        let metadata = self.extract_declarations(&tree, &context, file_path)?;
-
-        Ok(metadata)
+        Ok((tree, context))
     }
 
     /// Extract namespace and use imports from the file
     fn extract_namespace_and_imports(&self, tree: &Tree, context: &mut FileContext) -> Result<()> {
         let mut cursor = QueryCursor::new();
         let mut matches = cursor.matches(
-            &self.imports_query,
+            self.imports_query,
             tree.root_node(),
             context.source.as_bytes(),
         );
@@ -99,7 +188,11 @@ impl PhpMetadataExtractor {
                             continue;
                         }
 
-                let fqcn = self.node_text(&fqcn_cap.node, context.source);
+                // Function and constant imports do not resolve class names
+                let Some(prefix) = self.class_import_prefix(&fqcn_cap.node, context.source) else {
+                    continue;
+                };
+                let fqcn = format!("{prefix}{}", self.node_text(&fqcn_cap.node, context.source));
                 let alias = match_
                     .captures
                     .iter()
@@ -112,6 +205,34 @@ impl PhpMetadataExtractor {
         Ok(())
     }
 
+    /// Namespace prefix of a class import (non-empty in group uses such as
+    /// `use App\{User, Order}`), or `None` for `use function` and `use
+    /// const` imports
+    fn class_import_prefix(&self, name: &Node, source: &str) -> Option<String> {
+        let clause = name.parent()?;
+        let group = clause.parent().filter(|parent| parent.kind() == "namespace_use_group");
+        let declaration = group.map_or_else(|| clause.parent(), |group| group.parent())?;
+        let is_function_or_const = |node: &Node| {
+            let mut cursor = node.walk();
+            node.children(&mut cursor)
+                .any(|child| matches!(child.kind(), "function" | "const"))
+        };
+        if is_function_or_const(&clause) || is_function_or_const(&declaration) {
+            return None;
+        }
+
+        let prefix = group
+            .and_then(|_| {
+                let mut cursor = declaration.walk();
+                declaration
+                    .children(&mut cursor)
+                    .find(|child| child.kind() == "namespace_name")
+            })
+            .map(|namespace| format!("{}\\", self.node_text(&namespace, source)))
+            .unwrap_or_default();
+        Some(prefix)
+    }
+
     /// Extract all class/interface/trait/enum declarations
     fn extract_declarations(
         &self, tree: &Tree, context: &FileContext, file_path: PathBuf,
@@ -184,6 +305,10 @@ impl PhpMetadataExtractor {
         let fqcn = context.resolve_fqcn(&class_name);
 
         let mut metadata = PhpClassMetadata::new(fqcn, file_path, kind.to_string());
+        metadata.custom = self
+            .queries
+            .as_ref()
+            .map(|queries| crate::custom_queries::extract(queries, node, context.source));
 
         // Extract class modifiers (abstract, final, readonly)
         self.extract_class_modifiers(&node, &mut metadata);
@@ -238,6 +363,11 @@ impl PhpMetadataExtractor {
             }
         }
 
+        // Extract trait uses (for classes, traits, enums)
+        if kind == "class" || kind == "trait" || kind == "enum" {
+            metadata.traits = self.extract_trait_uses(&node, context);
+        }
+
         // Extract methods (for classes, interfaces, traits, enums)
         if kind == "class" || kind == "interface" || kind == "trait" || kind == "enum" {
             self.extract_methods(&node, context, &mut metadata)?;
@@ -484,6 +614,31 @@ impl PhpMetadataExtractor {
         Ok(interfaces)
     }
 
+    /// Extract trait FQCNs from `use` declarations in a class body
+    fn extract_trait_uses(&self, node: &Node, context: &FileContext) -> Vec<String> {
+        let mut traits = Vec::new();
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            if child.kind() == "declaration_list" || child.kind() == "enum_declaration_list" {
+                let mut decl_cursor = child.walk();
+                for decl_child in child.children(&mut decl_cursor) {
+                    if decl_child.kind() != "use_declaration" {
+                        continue;
+                    }
+                    let mut use_cursor = decl_child.walk();
+                    for name in decl_child.children(&mut use_cursor) {
+                        if name.kind() == "name" || name.kind() == "qualified_name" {
+                            let trait_name = self.node_text(&name, context.source);
+                            traits.push(context.resolve_fqcn(&trait_name));
+                        }
+                    }
+                }
+                break;
+            }
+        }
+        traits
+    }
+
     /// Get text content of a node
     fn node_text(&self, node: &Node, source: &str) -> String {
         node.utf8_text(source.as_bytes()).unwrap_or("").to_string()
@@ -548,7 +703,7 @@ impl PhpMetadataExtractor {
         // Extract visibility and modifiers
         let mut visibility = "public".to_string();
         let mut modifiers = MethodModifiers::default();
-        let mut attributes: HashMap<String, Vec<Vec<AttributeArgument>>> = HashMap::new();
+        let mut attributes = AttributeMap::new();
 
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
@@ -587,7 +742,7 @@ impl PhpMetadataExtractor {
                 if rt_child.kind() != ":" && rt_child.kind() != "?" {
                     let type_text = self.node_text(&rt_child, context.source);
                     if !type_text.is_empty() {
-                        found_type = Some(context.resolve_fqcn(&type_text));
+                        found_type = Some(context.resolve_type(&type_text));
                         break;
                     }
                 }
@@ -597,7 +752,7 @@ impl PhpMetadataExtractor {
             if found_type.is_none() {
                 let type_text = self.node_text(&rt_node, context.source);
                 if !type_text.is_empty() {
-                    found_type = Some(context.resolve_fqcn(&type_text));
+                    found_type = Some(context.resolve_type(&type_text));
                 }
             }
 
@@ -618,7 +773,7 @@ impl PhpMetadataExtractor {
                         || child.kind() == "optional_type")
                 {
                     let type_text = self.node_text(&child, context.source);
-                    found_type = Some(context.resolve_fqcn(&type_text));
+                    found_type = Some(context.resolve_type(&type_text));
                     break;
                 }
             }
@@ -668,7 +823,7 @@ impl PhpMetadataExtractor {
         // Extract visibility
         let mut visibility = "public".to_string();
         let mut modifiers = PropertyModifiers::default();
-        let mut attributes: HashMap<String, Vec<Vec<AttributeArgument>>> = HashMap::new();
+        let mut attributes = AttributeMap::new();
         let mut type_hint: Option<String> = None;
 
         let mut cursor = node.walk();
@@ -691,7 +846,7 @@ impl PhpMetadataExtractor {
                 "union_type" | "intersection_type" | "primitive_type" | "optional_type"
                 | "named_type" => {
                     let type_text = self.node_text(&child, context.source);
-                    type_hint = Some(context.resolve_fqcn(&type_text));
+                    type_hint = Some(context.resolve_type(&type_text));
                 },
                 "property_element" => {
                     // Extract individual property from property_element
@@ -721,7 +876,7 @@ impl PhpMetadataExtractor {
     fn extract_single_property(
         &self, node: &Node, context: &FileContext, visibility: &str,
         modifiers: &crate::metadata::PropertyModifiers,
-        attributes: &HashMap<String, Vec<Vec<AttributeArgument>>>, type_hint: &Option<String>,
+        attributes: &AttributeMap, type_hint: &Option<String>,
     ) -> Result<Option<crate::metadata::PhpPropertyMetadata>> {
         // Get property name from variable_name child
         let name = if let Some(var_name_node) = node.child_by_field_name("name") {
@@ -855,7 +1010,7 @@ impl PhpMetadataExtractor {
         };
 
         // Extract attributes
-        let mut attributes = HashMap::new();
+        let mut attributes = AttributeMap::new();
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
             if child.kind() == "attribute_list" {
@@ -878,7 +1033,7 @@ impl PhpMetadataExtractor {
     /// Extract attributes for an enum case
     fn extract_case_attributes(
         &self, group_node: &Node, context: &FileContext,
-        attributes: &mut HashMap<String, Vec<Vec<AttributeArgument>>>,
+        attributes: &mut AttributeMap,
     ) -> Result<()> {
         let mut cursor = group_node.walk();
         for child in group_node.children(&mut cursor) {
@@ -892,7 +1047,7 @@ impl PhpMetadataExtractor {
     /// Extract method attributes
     fn extract_method_attributes(
         &self, group_node: &Node, context: &FileContext,
-        attributes: &mut HashMap<String, Vec<Vec<AttributeArgument>>>,
+        attributes: &mut AttributeMap,
     ) -> Result<()> {
         let mut cursor = group_node.walk();
         for child in group_node.children(&mut cursor) {
@@ -903,10 +1058,10 @@ impl PhpMetadataExtractor {
         Ok(())
     }
 
-    /// Extract attribute to a `HashMap`
+    /// Extract attribute into an `AttributeMap`
     fn extract_attribute_to_map(
         &self, attr_node: &Node, context: &FileContext,
-        attributes: &mut HashMap<String, Vec<Vec<AttributeArgument>>>,
+        attributes: &mut AttributeMap,
     ) -> Result<()> {
         // Try field first, then find by child kind
         let mut cursor = attr_node.walk();
@@ -975,7 +1130,7 @@ impl PhpMetadataExtractor {
         // Extract type hint
         let type_hint = node.child_by_field_name("type").map(|type_node| {
             let type_text = self.node_text(&type_node, context.source);
-            context.resolve_fqcn(&type_text)
+            context.resolve_type(&type_text)
         });
 
         // Extract default value
@@ -985,7 +1140,7 @@ impl PhpMetadataExtractor {
             .transpose()?;
 
         // Extract parameter attributes
-        let mut attributes: HashMap<String, Vec<Vec<AttributeArgument>>> = HashMap::new();
+        let mut attributes = AttributeMap::new();
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
             if child.kind() == "attribute_list" {
@@ -1008,10 +1163,10 @@ impl PhpMetadataExtractor {
 }
 
 /// Context for a single PHP file (namespace, imports)
-struct FileContext<'a> {
+pub(crate) struct FileContext<'a> {
     source: &'a str,
     namespace: Option<String>,
-    imports: HashMap<String, String>,
+    imports: IndexMap<String, String>,
 }
 
 impl<'a> FileContext<'a> {
@@ -1019,12 +1174,68 @@ impl<'a> FileContext<'a> {
         Self {
             source,
             namespace: None,
-            imports: HashMap::new(),
+            imports: IndexMap::new(),
+        }
+    }
+
+    /// File context of `metadata`, the declarations extracted from `tree`
+    fn to_metadata(&self, tree: &Tree, metadata: &[PhpClassMetadata]) -> PhpFileContext {
+        let mut file_context = PhpFileContext {
+            namespace: self.namespace.clone(),
+            imports: self.imports.clone(),
+            classes: metadata.iter().map(|class| class.fqcn.clone()).collect(),
+            ..PhpFileContext::default()
+        };
+        self.collect_file_symbols(tree.root_node(), &mut file_context);
+        file_context
+    }
+
+    /// `strict_types`, functions and `const` constants outside class bodies
+    fn collect_file_symbols(&self, node: Node, file_context: &mut PhpFileContext) {
+        let qualify = |name: &Node| {
+            let name = name.utf8_text(self.source.as_bytes()).unwrap_or_default();
+            self.namespace
+                .as_ref()
+                .map_or_else(|| format!("\\{name}"), |ns| format!("\\{ns}\\{name}"))
+        };
+
+        match node.kind() {
+            "class_declaration" | "interface_declaration" | "trait_declaration"
+            | "enum_declaration" => return,
+            "declare_directive" => {
+                let mut cursor = node.walk();
+                let mut children = node.children(&mut cursor);
+                if children.next().is_some_and(|child| child.kind() == "strict_types") {
+                    file_context.strict_types = children.any(|value| {
+                        value.utf8_text(self.source.as_bytes()).is_ok_and(|v| v == "1")
+                    });
+                }
+                return;
+            },
+            "function_definition" => {
+                if let Some(name) = node.child_by_field_name("name") {
+                    file_context.functions.push(qualify(&name));
+                }
+            },
+            "const_element" => {
+                let mut cursor = node.walk();
+                if let Some(name) = node.children(&mut cursor).find(|child| child.kind() == "name")
+                {
+                    file_context.constants.push(qualify(&name));
+                }
+                return;
+            },
+            _ => {},
+        }
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            self.collect_file_symbols(child, file_context);
         }
     }
 
     /// Resolve a class name to its FQCN based on namespace and imports
-    fn resolve_fqcn(&self, name: &str) -> String {
+    pub(crate) fn resolve_fqcn(&self, name: &str) -> String {
         // Already fully qualified
         if name.starts_with('\\') {
             return name.to_string();
@@ -1060,6 +1271,35 @@ impl<'a> FileContext<'a> {
         }
     }
 
+    /// Resolve the class names of a type declaration
+    ///
+    /// Nullable, union and intersection members are resolved one by one, so
+    /// `?Foo` becomes `?\App\Foo` rather than `\App\?Foo`.
+    fn resolve_type(&self, type_text: &str) -> String {
+        let type_text = type_text.trim();
+        if let Some(inner) = type_text.strip_prefix('?') {
+            return format!("?{}", self.resolve_type(inner));
+        }
+
+        let mut resolved = String::with_capacity(type_text.len());
+        let mut start = 0;
+        for (i, c) in type_text.char_indices() {
+            if matches!(c, '|' | '&' | '(' | ')') {
+                let member = type_text[start..i].trim();
+                if !member.is_empty() {
+                    resolved.push_str(&self.resolve_fqcn(member));
+                }
+                resolved.push(c);
+                start = i + 1;
+            }
+        }
+        let member = type_text[start..].trim();
+        if !member.is_empty() {
+            resolved.push_str(&self.resolve_fqcn(member));
+        }
+        resolved
+    }
+
     /// Resolve constant reference (`ClassName::CONSTANT`) to FQCN
     /// Example: `UserStatus::ACTIVE` -> \`App\Enum\UserStatus::ACTIVE`
     fn resolve_constant_reference(&self, value: &str) -> String {
@@ -1094,11 +1334,11 @@ use std::sync::Arc;
 
 impl AttributeChecker {
     pub fn new() -> Result<Self> {
-        let query = Query::new(&LANGUAGE_PHP.into(), "(attribute_group) @attr").map_err(|e| {
-            AurynxError::tree_sitter_error(format!("Error compiling query: {e:?}"))
-        })?;
+        let query = ATTRIBUTE_QUERY
+            .as_ref()
+            .map_err(|e| AurynxError::tree_sitter_error(e.clone()))?;
         Ok(Self {
-            query: Arc::new(query),
+            query: Arc::clone(query),
         })
     }
 }
@@ -1112,7 +1352,7 @@ pub struct ThreadLocalParser {
 impl ThreadLocalParser {
     pub fn new(query: Arc<Query>) -> Result<Self> {
         let mut parser = Parser::new();
-        parser.set_language(&LANGUAGE_PHP.into()).map_err(|e| {
+        parser.set_language(&PHP_LANGUAGE).map_err(|e| {
             AurynxError::tree_sitter_error(format!("Error loading PHP grammar: {e:?}"))
         })?;
         let cursor = QueryCursor::new();
@@ -1140,10 +1380,18 @@ impl ThreadLocalParser {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     #![allow(clippy::unwrap_used, clippy::expect_used)]
     use super::*;
 
+    /// Classes `code` declares, extracted as if read from `file`
+    pub fn extract(file: &str, code: &str) -> Vec<PhpClassMetadata> {
+        PhpMetadataExtractor::new()
+            .unwrap()
+            .extract_metadata(code, PathBuf::from(file))
+            .unwrap()
+    }
+
     #[test]
     fn test_extract_simple_class() {
         let code = r#"<?php
@@ -1161,6 +1409,81 @@ class User {}
         assert_eq!(metadata[0].kind, "class");
     }
 
+    #[test]
+    fn test_group_use_resolves_and_function_imports_are_skipped() {
+        let code = r"<?php
+namespace App\Http;
+
+use App\Attribute\{Route, Middleware as Mw};
+use function Vendor\Helpers\Cache;
+use const Vendor\Config\Secured;
+
+#[Route('/users')]
+#[Mw('auth')]
+#[Cache]
+#[Secured]
+class UserController {}
+";
+        let mut extractor = PhpMetadataExtractor::new().unwrap();
+        let metadata = extractor
+            .extract_metadata(code, PathBuf::from("/test/UserController.php"))
+            .unwrap();
+
+        let names: Vec<&String> = metadata[0].attributes.keys().collect();
+        assert_eq!(
+            names,
+            [
+                "\\App\\Attribute\\Route",
+                "\\App\\Attribute\\Middleware",
+                "\\App\\Http\\Cache",
+                "\\App\\Http\\Secured",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_hash_shared_per_file_and_content_sensitive() {
+        let code = "<?php namespace App; class A {} interface B {}";
+        let mut extractor = PhpMetadataExtractor::new().unwrap();
+        let metadata = extractor
+            .extract_metadata(code, PathBuf::from("/test/A.php"))
+            .unwrap();
+
+        assert_eq!(metadata[0].file_hash.len(), 16);
+        assert_eq!(metadata[0].file_hash, metadata[1].file_hash);
+
+        let changed = extractor
+            .extract_metadata(
+                "<?php namespace App; class A {} interface B {} // edit",
+                PathBuf::from("/test/A.php"),
+            )
+            .unwrap();
+        assert_ne!(changed[0].file_hash, metadata[0].file_hash);
+    }
+
+    #[test]
+    fn test_extract_reports_syntax_newer_than_target() {
+        let code = "<?php namespace App; #[Entity] enum Status {}";
+        let mut extractor = PhpMetadataExtractor::new().unwrap();
+
+        let (metadata, _, newer) = extractor
+            .extract_metadata_for_version(
+                code,
+                PathBuf::from("/test/Status.php"),
+                PhpVersion::parse("8.0"),
+            )
+            .unwrap();
+        let (_, _, latest) = extractor
+            .extract_metadata_for_version(code, PathBuf::from("/test/Status.php"), None)
+            .unwrap();
+
+        // Metadata is still extracted; the caller decides what to report
+        assert_eq!(metadata[0].fqcn, "\\App\\Status");
+        assert_eq!(newer.len(), 1);
+        assert_eq!(newer[0].summary(), "enums (PHP 8.1) at 1:22");
+        assert!(latest.is_empty());
+    }
+
     #[test]
     fn test_extract_class_with_namespace_and_imports() {
         let code = r#"<?php
@@ -1188,6 +1511,58 @@ class User {
         );
     }
 
+    #[test]
+    fn test_file_context_lists_imports_and_symbols() {
+        let code = r"<?php
+declare(strict_types=1);
+namespace App\Http;
+
+use App\Entity\{User, Order as PurchaseOrder};
+use Psr\Log\LoggerInterface;
+use function App\helper;
+
+const VERSION = 2;
+function render() {}
+
+class Controller {
+    const LIMIT = 10;
+    public function show(User $user): PurchaseOrder {}
+}
+";
+        let mut extractor = PhpMetadataExtractor::new().unwrap();
+        let path = Path::new("/test/Controller.php");
+        let (tree, context) = extractor.parse_with_context(code, path).unwrap();
+        let metadata = extractor
+            .extract_declarations(&tree, &context, path.to_path_buf())
+            .unwrap();
+
+        assert_eq!(
+            metadata[0].methods[0].return_type.as_deref(),
+            Some("\\App\\Entity\\Order")
+        );
+        assert_eq!(
+            context.to_metadata(&tree, &metadata),
+            PhpFileContext {
+                namespace: Some("App\\Http".to_string()),
+                strict_types: true,
+                imports: IndexMap::from([
+                    ("User".to_string(), "\\App\\Entity\\User".to_string()),
+                    (
+                        "PurchaseOrder".to_string(),
+                        "\\App\\Entity\\Order".to_string()
+                    ),
+                    (
+                        "LoggerInterface".to_string(),
+                        "\\Psr\\Log\\LoggerInterface".to_string()
+                    ),
+                ]),
+                classes: vec!["\\App\\Http\\Controller".to_string()],
+                functions: vec!["\\App\\Http\\render".to_string()],
+                constants: vec!["\\App\\Http\\VERSION".to_string()],
+            }
+        );
+    }
+
     #[test]
     fn test_extract_class_with_extends() {
         let code = r#"<?php
@@ -1233,6 +1608,55 @@ class User implements \JsonSerializable, \Stringable {
         assert!(metadata[0].implements.contains(&"\\Stringable".to_string()));
     }
 
+    #[test]
+    fn test_attributes_keep_declaration_order() {
+        let code = r"<?php
+namespace App;
+
+#[Zeta]
+#[Alpha, Mid]
+#[Zeta(2)]
+class Ordered {}
+";
+        let mut extractor = PhpMetadataExtractor::new().unwrap();
+        let metadata = extractor
+            .extract_metadata(code, PathBuf::from("/test/Ordered.php"))
+            .unwrap();
+
+        let names: Vec<&str> = metadata[0].attributes.keys().map(String::as_str).collect();
+        assert_eq!(names, ["\\App\\Zeta", "\\App\\Alpha", "\\App\\Mid"]);
+        assert_eq!(metadata[0].attributes["\\App\\Zeta"].len(), 2);
+    }
+
+    #[test]
+    fn test_extract_trait_uses() {
+        let code = r"<?php
+namespace App\Entity;
+
+use App\Concerns\HasTimestamps;
+
+class User {
+    use HasTimestamps, \SoftDeletes;
+    use Auditable {
+        Auditable::log as protected;
+    }
+}
+";
+        let mut extractor = PhpMetadataExtractor::new().unwrap();
+        let metadata = extractor
+            .extract_metadata(code, PathBuf::from("/test/User.php"))
+            .unwrap();
+
+        assert_eq!(
+            metadata[0].traits,
+            [
+                "\\App\\Concerns\\HasTimestamps",
+                "\\SoftDeletes",
+                "\\App\\Entity\\Auditable"
+            ]
+        );
+    }
+
     #[test]
     fn test_extract_interface() {
         let code = r#"<?php
@@ -1575,6 +1999,40 @@ class Test {
         assert_eq!(methods[3].return_type, Some("self".to_string()));
     }
 
+    #[test]
+    fn test_nullable_and_union_types_resolve_members() {
+        let code = r"<?php
+namespace App;
+
+use Psr\Log\LoggerInterface;
+
+class Test {
+    private ?string $name;
+    private ?Clock $clock;
+
+    public function handle(?LoggerInterface $logger, int|Money $amount): void {}
+}
+";
+        let mut extractor = PhpMetadataExtractor::new().unwrap();
+        let metadata = extractor
+            .extract_metadata(code, PathBuf::from("/test/Test.php"))
+            .unwrap();
+
+        let class = &metadata[0];
+        assert_eq!(class.properties[0].type_hint.as_deref(), Some("?string"));
+        assert_eq!(class.properties[1].type_hint.as_deref(), Some("?\\App\\Clock"));
+
+        let method = &class.methods[0];
+        assert_eq!(
+            method.parameters[0].type_hint.as_deref(),
+            Some("?\\Psr\\Log\\LoggerInterface")
+        );
+        assert_eq!(
+            method.parameters[1].type_hint.as_deref(),
+            Some("int|\\App\\Money")
+        );
+    }
+
     #[test]
     fn test_extract_parameter_attributes() {
         let code = r#"<?php
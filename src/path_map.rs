@@ -0,0 +1,124 @@
+//! Path prefix remapping between the scanning side and cache consumers
+//!
+//! Mixed setups (daemon inside Docker, PHP on the host or vice versa) see the
+//! same files under different prefixes. A `path_map` such as
+//! `{"/var/www/html": "/app"}` rewrites local paths to consumer paths when the
+//! cache is written and maps consumer-side paths in watcher events back to
+//! local ones. The longest matching prefix wins.
+
+use crate::metadata::PhpClassMetadata;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Ordered list of `local => mapped` prefix rules
+#[derive(Debug, Clone, Default)]
+pub struct PathMap {
+    rules: Vec<(PathBuf, PathBuf)>,
+}
+
+impl PathMap {
+    /// Build from the `path_map` config object (local prefix → mapped prefix)
+    #[must_use]
+    pub fn new(map: &HashMap<PathBuf, PathBuf>) -> Self {
+        let mut rules: Vec<(PathBuf, PathBuf)> = map
+            .iter()
+            .map(|(from, to)| (from.clone(), to.clone()))
+            .collect();
+        // Most specific prefix first
+        rules.sort_by(|a, b| {
+            b.0.components()
+                .count()
+                .cmp(&a.0.components().count())
+                .then_with(|| a.0.cmp(&b.0))
+        });
+        Self { rules }
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Map a local path to the path consumers of the cache see
+    #[must_use]
+    pub fn to_mapped(&self, path: &Path) -> PathBuf {
+        Self::rewrite(self.rules.iter().map(|(from, to)| (from, to)), path)
+    }
+
+    /// Map a consumer-side path (e.g. from an external watcher) to the local path
+    #[must_use]
+    pub fn to_local(&self, path: &Path) -> PathBuf {
+        let mut reversed: Vec<(&PathBuf, &PathBuf)> =
+            self.rules.iter().map(|(from, to)| (to, from)).collect();
+        reversed.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.components().count()));
+        Self::rewrite(reversed.into_iter(), path)
+    }
+
+    /// Apply [`Self::to_mapped`] to every class file path
+    pub fn apply(&self, metadata_list: &mut [PhpClassMetadata]) {
+        if self.is_empty() {
+            return;
+        }
+        for metadata in metadata_list {
+            metadata.file = self.to_mapped(&metadata.file);
+        }
+    }
+
+    fn rewrite<'a>(
+        rules: impl Iterator<Item = (&'a PathBuf, &'a PathBuf)>, path: &Path,
+    ) -> PathBuf {
+        for (from, to) in rules {
+            if let Ok(rest) = path.strip_prefix(from) {
+                return to.join(rest);
+            }
+        }
+        path.to_path_buf()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(rules: &[(&str, &str)]) -> PathMap {
+        PathMap::new(
+            &rules
+                .iter()
+                .map(|(from, to)| (PathBuf::from(from), PathBuf::from(to)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let map = map(&[("/var/www", "/srv"), ("/var/www/html", "/app")]);
+
+        assert_eq!(
+            map.to_mapped(Path::new("/var/www/html/src/User.php")),
+            PathBuf::from("/app/src/User.php")
+        );
+        assert_eq!(
+            map.to_mapped(Path::new("/var/www/other/A.php")),
+            PathBuf::from("/srv/other/A.php")
+        );
+        // Component-wise matching: no partial directory names
+        assert_eq!(
+            map.to_mapped(Path::new("/var/www-old/A.php")),
+            PathBuf::from("/var/www-old/A.php")
+        );
+    }
+
+    #[test]
+    fn test_to_local_reverses_mapping() {
+        let map = map(&[("/var/www/html", "/app")]);
+
+        assert_eq!(
+            map.to_local(Path::new("/app/src/User.php")),
+            PathBuf::from("/var/www/html/src/User.php")
+        );
+        assert_eq!(
+            map.to_local(Path::new("/var/www/html/src/User.php")),
+            PathBuf::from("/var/www/html/src/User.php")
+        );
+    }
+}
@@ -0,0 +1,282 @@
+//! PHP version targeting (`php_version`)
+//!
+//! The grammar always parses the newest PHP syntax, so a project targeting an
+//! older PHP gets metadata for code its runtime would reject: an `enum` is a
+//! parse error before 8.1, and `#[...]` is a comment before 8.0, so the
+//! attributes the cache reports are never seen by PHP. With a target set,
+//! every file is checked for syntax newer than the target and each feature
+//! is reported once per file, at its first occurrence.
+
+use serde::Serialize;
+use std::fmt;
+use tree_sitter::{Node, Tree};
+
+/// A `major.minor` PHP version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct PhpVersion {
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl PhpVersion {
+    #[must_use]
+    pub const fn new(major: u8, minor: u8) -> Self {
+        Self { major, minor }
+    }
+
+    /// Parse `"8.1"` (a patch level, as in `"8.1.2"`, is ignored)
+    #[must_use]
+    pub fn parse(version: &str) -> Option<Self> {
+        let mut parts = version.trim().split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        if parts
+            .next()
+            .is_some_and(|patch| patch.parse::<u8>().is_err())
+            || major < 5
+        {
+            return None;
+        }
+        Some(Self { major, minor })
+    }
+}
+
+impl fmt::Display for PhpVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Syntax that needs a newer PHP than some targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyntaxFeature {
+    Attributes,
+    ConstructorPromotion,
+    MatchExpression,
+    NullsafeOperator,
+    UnionTypes,
+    Enums,
+    ReadonlyProperties,
+    NeverType,
+    FirstClassCallables,
+    IntersectionTypes,
+    ReadonlyClasses,
+    DnfTypes,
+    TypedClassConstants,
+    PropertyHooks,
+    AsymmetricVisibility,
+}
+
+impl SyntaxFeature {
+    /// First PHP version supporting the feature
+    #[must_use]
+    pub const fn since(self) -> PhpVersion {
+        match self {
+            Self::Attributes
+            | Self::ConstructorPromotion
+            | Self::MatchExpression
+            | Self::NullsafeOperator
+            | Self::UnionTypes => PhpVersion::new(8, 0),
+            Self::Enums
+            | Self::ReadonlyProperties
+            | Self::NeverType
+            | Self::FirstClassCallables
+            | Self::IntersectionTypes => PhpVersion::new(8, 1),
+            Self::ReadonlyClasses | Self::DnfTypes => PhpVersion::new(8, 2),
+            Self::TypedClassConstants => PhpVersion::new(8, 3),
+            Self::PropertyHooks | Self::AsymmetricVisibility => PhpVersion::new(8, 4),
+        }
+    }
+
+    /// Human-readable name, e.g. `readonly properties`
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Attributes => "attributes",
+            Self::ConstructorPromotion => "constructor property promotion",
+            Self::MatchExpression => "match expressions",
+            Self::NullsafeOperator => "the nullsafe operator",
+            Self::UnionTypes => "union types",
+            Self::Enums => "enums",
+            Self::ReadonlyProperties => "readonly properties",
+            Self::NeverType => "the never type",
+            Self::FirstClassCallables => "first-class callable syntax",
+            Self::IntersectionTypes => "intersection types",
+            Self::ReadonlyClasses => "readonly classes",
+            Self::DnfTypes => "DNF types",
+            Self::TypedClassConstants => "typed class constants",
+            Self::PropertyHooks => "property hooks",
+            Self::AsymmetricVisibility => "asymmetric visibility",
+        }
+    }
+
+    /// Feature introduced by `node` itself, if any
+    fn of(node: Node) -> Option<Self> {
+        let feature = match node.kind() {
+            "attribute_list" => Self::Attributes,
+            "property_promotion_parameter" => Self::ConstructorPromotion,
+            "match_expression" => Self::MatchExpression,
+            "nullsafe_member_access_expression" | "nullsafe_member_call_expression" => {
+                Self::NullsafeOperator
+            },
+            "union_type" if node.named_child_count() > 1 => Self::UnionTypes,
+            "enum_declaration" => Self::Enums,
+            "readonly_modifier" => match node.parent().map(|parent| parent.kind()) {
+                Some("class_declaration") => Self::ReadonlyClasses,
+                _ => Self::ReadonlyProperties,
+            },
+            "bottom_type" => Self::NeverType,
+            "variadic_placeholder" => Self::FirstClassCallables,
+            "intersection_type" => Self::IntersectionTypes,
+            "disjunctive_normal_form_type" => Self::DnfTypes,
+            "const_declaration" if node.child_by_field_name("type").is_some() => {
+                Self::TypedClassConstants
+            },
+            "property_hook_list" => Self::PropertyHooks,
+            "visibility_modifier" if node.named_child_count() > 0 => Self::AsymmetricVisibility,
+            _ => return None,
+        };
+        Some(feature)
+    }
+}
+
+/// First use of a feature newer than the targeted version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NewerSyntax {
+    pub feature: SyntaxFeature,
+    /// 1-based line of the first occurrence
+    pub line: usize,
+    /// 1-based column of the first occurrence, in bytes
+    pub column: usize,
+}
+
+impl NewerSyntax {
+    /// One-line form: `enums (PHP 8.1) at 3:1`
+    #[must_use]
+    pub fn summary(&self) -> String {
+        format!(
+            "{} (PHP {}) at {}:{}",
+            self.feature.description(),
+            self.feature.since(),
+            self.line,
+            self.column
+        )
+    }
+}
+
+/// Features of `tree` that `target` does not support, in source order
+#[must_use]
+pub fn find_newer_syntax(tree: &Tree, target: PhpVersion) -> Vec<NewerSyntax> {
+    let mut found: Vec<NewerSyntax> = Vec::new();
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        if let Some(feature) = SyntaxFeature::of(node)
+            && feature.since() > target
+            && !found.iter().any(|newer| newer.feature == feature)
+        {
+            found.push(NewerSyntax {
+                feature,
+                line: node.start_position().row + 1,
+                column: node.start_position().column + 1,
+            });
+        }
+
+        if cursor.goto_first_child() || cursor.goto_next_sibling() {
+            continue;
+        }
+        loop {
+            if !cursor.goto_parent() {
+                return found;
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use tree_sitter::Parser;
+
+    fn newer(source: &str, target: &str) -> Vec<(SyntaxFeature, usize)> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_php::LANGUAGE_PHP.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        find_newer_syntax(&tree, PhpVersion::parse(target).unwrap())
+            .into_iter()
+            .map(|newer| (newer.feature, newer.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(PhpVersion::parse("8.1"), Some(PhpVersion::new(8, 1)));
+        assert_eq!(PhpVersion::parse("7.4.33"), Some(PhpVersion::new(7, 4)));
+        assert_eq!(PhpVersion::parse("8"), None);
+        assert_eq!(PhpVersion::parse("eight.one"), None);
+        assert!(PhpVersion::new(8, 0) < PhpVersion::new(8, 10));
+    }
+
+    #[test]
+    fn test_reports_each_newer_feature_once() {
+        let source = "<?php
+enum Status { case Active; }
+final class User {
+    public function __construct(public readonly int $id, private ?string $name) {}
+    public readonly string $email;
+}
+readonly class Point {}
+";
+        assert_eq!(
+            newer(source, "8.0"),
+            [
+                (SyntaxFeature::Enums, 2),
+                (SyntaxFeature::ReadonlyProperties, 4),
+                (SyntaxFeature::ReadonlyClasses, 7),
+            ]
+        );
+        assert_eq!(
+            newer(source, "7.4"),
+            [
+                (SyntaxFeature::Enums, 2),
+                (SyntaxFeature::ConstructorPromotion, 4),
+                (SyntaxFeature::ReadonlyProperties, 4),
+                (SyntaxFeature::ReadonlyClasses, 7),
+            ]
+        );
+        assert!(newer(source, "8.2").is_empty());
+    }
+
+    #[test]
+    fn test_detects_attributes_types_and_hooks() {
+        let source = "<?php
+#[Entity]
+class Order {
+    public int|string $id;
+    public string $label { get => 'x'; }
+    const string PREFIX = 'o';
+    public function total(): never { exit; }
+}
+";
+        assert_eq!(
+            newer(source, "7.4"),
+            [
+                (SyntaxFeature::Attributes, 2),
+                (SyntaxFeature::UnionTypes, 4),
+                (SyntaxFeature::PropertyHooks, 5),
+                (SyntaxFeature::TypedClassConstants, 6),
+                (SyntaxFeature::NeverType, 7),
+            ]
+        );
+        // Plain types are not union types
+        assert!(newer("<?php function f(int $a): ?string {}", "7.4").is_empty());
+    }
+}
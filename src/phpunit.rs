@@ -0,0 +1,253 @@
+//! `PHPUnit` test manifest (`discovery:export tests --preset phpunit`)
+//!
+//! Lists the tests of every concrete test class with their groups and data
+//! providers, so test splitters can distribute tests without a PHP
+//! reflection pass. A class is a test class when its name ends with `Test`
+//! (`PHPUnit`'s default suffix) or one of its methods carries `#[Test]`.
+//! Tests are public `test*` methods and `#[Test]` methods, including those
+//! inherited from (abstract) parent classes found in the scan.
+//!
+//! Groups follow `PHPUnit`: class and method `#[Group]` and `#[Ticket]`, plus
+//! `small`/`medium`/`large` for the size attributes.
+
+use crate::metadata::{AttributeMap, PhpClassMetadata, PhpMethodMetadata};
+use crate::openapi::{argument, matches_attribute, php_class_name, php_string};
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+const ATTRIBUTES: &str = "PHPUnit\\Framework\\Attributes\\";
+
+/// Tests of the scanned code, keyed by test class FQCN
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TestManifest {
+    /// Total number of test methods
+    pub tests: usize,
+    pub classes: BTreeMap<String, TestClass>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TestClass {
+    pub file: PathBuf,
+    /// Groups declared on the class (applied to every test)
+    pub groups: Vec<String>,
+    /// Tests in declaration order, parents' tests last
+    pub tests: IndexMap<String, TestMethod>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TestMethod {
+    /// Effective groups: class groups followed by the method's own
+    pub groups: Vec<String>,
+    /// Same-class provider methods, or `Class::method` for external ones
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub data_providers: Vec<String>,
+}
+
+/// Build the manifest from scanned metadata
+#[must_use]
+pub fn build_test_manifest(metadata_list: &[PhpClassMetadata]) -> TestManifest {
+    let by_name: HashMap<&str, &PhpClassMetadata> = metadata_list
+        .iter()
+        .map(|class| (class.fqcn.as_str(), class))
+        .collect();
+
+    let mut manifest = TestManifest::default();
+    for class in metadata_list {
+        if class.kind != "class" || class.modifiers.is_abstract {
+            continue;
+        }
+
+        let class_groups = groups(&class.attributes);
+        let mut seen = HashSet::new();
+        let mut tests = IndexMap::new();
+        let mut marked = false;
+
+        // Own methods first, then each ancestor's that are not overridden
+        let mut current = Some(class);
+        while let Some(declaring) = current {
+            for method in &declaring.methods {
+                if !seen.insert(method.name.to_lowercase()) {
+                    continue;
+                }
+                let has_test_attribute = has(&method.attributes, "Test");
+                if !is_test(method, has_test_attribute) {
+                    continue;
+                }
+                marked |= has_test_attribute;
+
+                let mut test_groups = class_groups.clone();
+                for group in groups(&method.attributes) {
+                    if !test_groups.contains(&group) {
+                        test_groups.push(group);
+                    }
+                }
+                tests.insert(
+                    method.name.clone(),
+                    TestMethod {
+                        groups: test_groups,
+                        data_providers: data_providers(&method.attributes),
+                    },
+                );
+            }
+            current = declaring
+                .extends
+                .as_deref()
+                .and_then(|parent| by_name.get(parent).copied());
+        }
+
+        let short_name = class.fqcn.rsplit('\\').next().unwrap_or_default();
+        if tests.is_empty() || !(short_name.ends_with("Test") || marked) {
+            continue;
+        }
+
+        manifest.tests += tests.len();
+        manifest.classes.insert(
+            class.fqcn.trim_start_matches('\\').to_string(),
+            TestClass {
+                file: class.file.clone(),
+                groups: class_groups,
+                tests,
+            },
+        );
+    }
+
+    manifest
+}
+
+/// Write the manifest as pretty-printed JSON; an unchanged file is not
+/// rewritten
+///
+/// # Errors
+///
+/// Returns an error if the manifest cannot be written.
+pub fn write_test_manifest(manifest: &TestManifest, output_path: &Path) -> Result<()> {
+    let content = serde_json::to_string_pretty(manifest)? + "\n";
+    crate::writer::write_if_changed(output_path, content.as_bytes())
+        .with_context(|| format!("Failed to write test manifest {}", output_path.display()))?;
+    Ok(())
+}
+
+fn is_test(method: &PhpMethodMetadata, has_test_attribute: bool) -> bool {
+    method.visibility == "public"
+        && !method.modifiers.is_abstract
+        && !method.modifiers.is_static
+        && (has_test_attribute || method.name.starts_with("test"))
+}
+
+fn has(attributes: &AttributeMap, name: &str) -> bool {
+    attributes
+        .keys()
+        .any(|key| matches_attribute(key, &format!("{ATTRIBUTES}{name}")))
+}
+
+/// Literal first argument (named `key`) of every instance of the `PHPUnit` attribute `name`
+fn literals<'a>(
+    attributes: &'a AttributeMap, name: &'a str, key: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    attributes
+        .iter()
+        .filter(move |(attribute, _)| matches_attribute(attribute, &format!("{ATTRIBUTES}{name}")))
+        .flat_map(|(_, instances)| instances)
+        .filter_map(|args| argument(args, key, Some(0)).and_then(php_string))
+}
+
+fn groups(attributes: &AttributeMap) -> Vec<String> {
+    let mut groups: Vec<String> = literals(attributes, "Group", "name")
+        .chain(literals(attributes, "Ticket", "text"))
+        .collect();
+    for (attribute, group) in [("Small", "small"), ("Medium", "medium"), ("Large", "large")] {
+        if has(attributes, attribute) {
+            groups.push(group.to_string());
+        }
+    }
+    let mut seen = HashSet::new();
+    groups.retain(|group| seen.insert(group.clone()));
+    groups
+}
+
+fn data_providers(attributes: &AttributeMap) -> Vec<String> {
+    let mut providers: Vec<String> = literals(attributes, "DataProvider", "methodName").collect();
+
+    let external = attributes
+        .iter()
+        .filter(|(key, _)| matches_attribute(key, &format!("{ATTRIBUTES}DataProviderExternal")))
+        .flat_map(|(_, instances)| instances);
+    for args in external {
+        let class = argument(args, "className", Some(0)).and_then(php_class_name);
+        let method = argument(args, "methodName", Some(1)).and_then(php_string);
+        if let (Some(class), Some(method)) = (class, method) {
+            providers.push(format!("{class}::{method}"));
+        }
+    }
+
+    providers
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::parser::tests::extract;
+
+    #[test]
+    fn test_manifest_lists_tests_groups_and_providers() {
+        let metadata = extract(
+            "/tests/UserTest.php",
+            r"<?php
+namespace App\Tests;
+use PHPUnit\Framework\Attributes\DataProvider;
+use PHPUnit\Framework\Attributes\DataProviderExternal;
+use PHPUnit\Framework\Attributes\Group;
+use PHPUnit\Framework\Attributes\Large;
+use PHPUnit\Framework\Attributes\Test;
+use PHPUnit\Framework\TestCase;
+
+abstract class DatabaseTestCase extends TestCase {
+    public function testConnection(): void {}
+}
+
+#[Group('users')]
+final class UserTest extends DatabaseTestCase {
+    #[Test]
+    #[Group('slow')]
+    #[Large]
+    #[DataProvider('names')]
+    #[DataProviderExternal(Fixtures::class, 'emails')]
+    public function it_creates_users(string $name): void {}
+
+    public function testDelete(): void {}
+
+    public static function names(): array { return []; }
+
+    private function testHelper(): void {}
+}
+
+class UserFactory {
+    public function testMode(): bool { return true; }
+}
+",
+        );
+
+        let manifest = build_test_manifest(&metadata);
+        let class = &manifest.classes["App\\Tests\\UserTest"];
+
+        assert_eq!(manifest.tests, 3);
+        assert_eq!(manifest.classes.len(), 1);
+        assert_eq!(class.groups, ["users"]);
+        assert_eq!(
+            class.tests.keys().collect::<Vec<_>>(),
+            ["it_creates_users", "testDelete", "testConnection"]
+        );
+
+        let test = &class.tests["it_creates_users"];
+        assert_eq!(test.groups, ["users", "slow", "large"]);
+        assert_eq!(
+            test.data_providers,
+            ["names", "App\\Tests\\Fixtures::emails"]
+        );
+        assert_eq!(class.tests["testConnection"].groups, ["users"]);
+    }
+}
@@ -0,0 +1,96 @@
+//! Post-scan passes over the scanned classes
+//!
+//! One-shot scans, workspace projects and the daemon turn scanned classes
+//! into cache entries with the same passes, in the same order: hierarchy
+//! resolution (or attribute inheritance), signature hashes, the member cap,
+//! then `output_exclude`. Signature hashes come before the cap, so they
+//! still change when a dropped member does.
+
+use crate::member_limit::OversizedClass;
+use crate::metadata::PhpClassMetadata;
+use ignore::overrides::Override;
+
+/// Which passes run, from the scanned project's settings
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutputPasses<'a> {
+    /// `inherit_attributes`; implies resolving the hierarchy
+    pub inherit_attributes: bool,
+    /// `resolve_hierarchy`
+    pub resolve_hierarchy: bool,
+    /// `signature_hash`
+    pub signature_hash: bool,
+    /// `max_class_members`
+    pub max_class_members: Option<usize>,
+    /// Matcher of the `output_exclude` patterns
+    pub output_exclude: Option<&'a Override>,
+}
+
+/// What the passes changed
+#[derive(Debug, Clone, Default)]
+pub struct PreparedOutput {
+    /// Classes cut to `max_class_members`, in order
+    pub truncated: Vec<OversizedClass>,
+    /// Classes left out by `output_exclude`
+    pub excluded: usize,
+}
+
+impl OutputPasses<'_> {
+    /// Run every pass over the scanned classes
+    pub fn apply(&self, metadata: &mut Vec<PhpClassMetadata>) -> PreparedOutput {
+        if self.inherit_attributes {
+            crate::resolver::propagate_attributes(metadata);
+        } else if self.resolve_hierarchy {
+            crate::resolver::resolve_hierarchy(metadata);
+        }
+        let truncated = self.trim(metadata);
+        let scanned = metadata.len();
+        crate::scanner::exclude_from_output(metadata, self.output_exclude);
+        PreparedOutput {
+            truncated,
+            excluded: scanned - metadata.len(),
+        }
+    }
+
+    /// Hash and cap `classes` without resolving them, for classes that
+    /// were resolved before (read back from the spill file)
+    ///
+    /// Returns the classes that were cut.
+    pub fn trim(&self, classes: &mut [PhpClassMetadata]) -> Vec<OversizedClass> {
+        if self.signature_hash {
+            crate::signature::add_signature_hashes(classes);
+        }
+        self.max_class_members
+            .map(|max| crate::member_limit::truncate_members(classes, max))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::parser::PhpMetadataExtractor;
+
+    #[test]
+    fn test_signature_hash_covers_truncated_members() {
+        let passes = OutputPasses {
+            signature_hash: true,
+            max_class_members: Some(1),
+            ..OutputPasses::default()
+        };
+        let hash = |method: &str| {
+            let source =
+                format!("<?php class Client {{ function a() {{}} function {method}() {{}} }}");
+            let mut metadata = PhpMetadataExtractor::new()
+                .unwrap()
+                .extract_metadata(&source, "Client.php".into())
+                .unwrap();
+            let prepared = passes.apply(&mut metadata);
+            assert_eq!(prepared.truncated.len(), 1);
+            assert_eq!(metadata[0].methods.len(), 1);
+            metadata[0].signature_hash.clone().unwrap()
+        };
+
+        assert_ne!(hash("b"), hash("c"));
+    }
+}
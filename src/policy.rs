@@ -0,0 +1,538 @@
+//! Attribute policies (`discovery:check`)
+//!
+//! Rules from the `policies` config list are evaluated against the scanned
+//! code, e.g. "every class in `App\Controller` must have `#[Route]` on at
+//! least one public method" or "classes implementing X must not use
+//! `#[Deprecated]`". A rule selects classes (namespace, interface, parent,
+//! class attribute, kind) and either requires an attribute or forbids it.
+//!
+//! Required attributes are only checked on concrete classes: an abstract
+//! base is not expected to be complete on its own. Interface and parent
+//! selectors see the full hierarchy, so run the check on resolved metadata
+//! (see [`crate::resolver::resolve_hierarchy`]).
+//!
+//! Files that cannot be read or parsed are reported as `parse-error`
+//! violations. A [`PolicyBaseline`] (like `PHPStan`'s) lists known violations
+//! so new rules can be adopted on legacy code: baselined violations are
+//! suppressed, anything new still fails the check.
+
+use crate::metadata::{AttributeMap, PhpClassMetadata};
+use crate::openapi::matches_attribute;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Rule name of violations for files that could not be read or parsed
+pub const PARSE_ERROR: &str = "parse-error";
+
+const TARGETS: &[&str] = &["class", "method", "public_method", "property", "any"];
+const KINDS: &[&str] = &["class", "interface", "trait", "enum"];
+
+/// One rule of the `policies` config list
+#[derive(Debug, Clone, Deserialize)]
+pub struct PolicyRule {
+    /// Rule name shown with each violation
+    pub name: String,
+    /// Custom violation message
+    pub message: Option<String>,
+    #[serde(default)]
+    pub select: ClassSelector,
+    /// Attribute that must be present (exclusive with `forbid`)
+    pub require: Option<AttributeConstraint>,
+    /// Attribute that must not be present (exclusive with `require`)
+    pub forbid: Option<AttributeConstraint>,
+}
+
+/// Classes a rule applies to; every given criterion must match
+///
+/// Names are matched against the end of the FQCN on a namespace boundary.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ClassSelector {
+    /// Namespace prefix (`App\Controller` also covers sub-namespaces)
+    pub namespace: Option<String>,
+    /// Implemented interface, directly or inherited
+    pub implements: Option<String>,
+    /// Parent class, direct or further up
+    pub extends: Option<String>,
+    /// Class-level attribute
+    pub attribute: Option<String>,
+    /// `class` (default), `interface`, `trait` or `enum`
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AttributeConstraint {
+    pub attribute: String,
+    /// `class` (default), `method`, `public_method`, `property` or `any`
+    pub on: Option<String>,
+}
+
+/// A rule broken by a class or one of its members
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    pub rule: String,
+    /// Class FQCN without the leading backslash (`None` for parse errors)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member: Option<String>,
+    pub file: PathBuf,
+    pub message: String,
+}
+
+impl PolicyRule {
+    /// Check the rule before any scanning happens
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first invalid field.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        let ((Some(constraint), None) | (None, Some(constraint))) = (&self.require, &self.forbid)
+        else {
+            return Err(format!(
+                "Policy '{}' must have exactly one of 'require' or 'forbid'",
+                self.name
+            ));
+        };
+        if let Some(on) = &constraint.on
+            && !TARGETS.contains(&on.as_str())
+        {
+            return Err(format!(
+                "Invalid policy target '{on}' in '{}' (allowed: {TARGETS:?})",
+                self.name
+            ));
+        }
+        if let Some(kind) = &self.select.kind
+            && !KINDS.contains(&kind.as_str())
+        {
+            return Err(format!(
+                "Invalid policy kind '{kind}' in '{}' (allowed: {KINDS:?})",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl ClassSelector {
+    fn matches(&self, class: &PhpClassMetadata) -> bool {
+        let fqcn = class.fqcn.trim_start_matches('\\');
+        let in_namespace = |namespace: &String| {
+            let namespace = namespace.trim_matches('\\');
+            fqcn.strip_prefix(namespace)
+                .is_some_and(|rest| rest.starts_with('\\'))
+        };
+        class.kind == self.kind.as_deref().unwrap_or("class")
+            && self.namespace.as_ref().is_none_or(in_namespace)
+            && self.implements.as_ref().is_none_or(|interface| {
+                let interfaces = class
+                    .resolved_implements
+                    .as_ref()
+                    .unwrap_or(&class.implements);
+                interfaces
+                    .iter()
+                    .any(|name| matches_attribute(name, interface))
+            })
+            && self.extends.as_ref().is_none_or(|parent| {
+                let ancestors = class.ancestors.as_deref().unwrap_or_default();
+                class
+                    .extends
+                    .iter()
+                    .chain(ancestors)
+                    .any(|name| matches_attribute(name, parent))
+            })
+            && self
+                .attribute
+                .as_ref()
+                .is_none_or(|attribute| has(&class.attributes, attribute))
+    }
+}
+
+/// Evaluate every rule; violations are ordered by rule, then class name
+#[must_use]
+pub fn check_policies(metadata_list: &[PhpClassMetadata], rules: &[PolicyRule]) -> Vec<Violation> {
+    let mut classes: Vec<&PhpClassMetadata> = metadata_list.iter().collect();
+    classes.sort_by(|a, b| a.fqcn.cmp(&b.fqcn));
+
+    let mut violations = Vec::new();
+    for rule in rules {
+        for class in classes.iter().filter(|class| rule.select.matches(class)) {
+            let violation = |member: Option<&str>, default: String| Violation {
+                rule: rule.name.clone(),
+                class: Some(class.fqcn.trim_start_matches('\\').to_string()),
+                member: member.map(ToString::to_string),
+                file: class.file.clone(),
+                message: rule.message.clone().unwrap_or(default),
+            };
+
+            if let Some(required) = &rule.require {
+                let on = required.on.as_deref().unwrap_or("class");
+                let found = targets(class, on)
+                    .iter()
+                    .any(|(_, attributes)| has(attributes, &required.attribute));
+                if !found && !class.modifiers.is_abstract {
+                    violations.push(violation(
+                        None,
+                        format!("missing #[{}] on {}", required.attribute, describe(on)),
+                    ));
+                }
+            }
+
+            if let Some(forbidden) = &rule.forbid {
+                let on = forbidden.on.as_deref().unwrap_or("class");
+                for (member, attributes) in targets(class, on) {
+                    if has(attributes, &forbidden.attribute) {
+                        violations.push(violation(
+                            member,
+                            format!("#[{}] is not allowed here", forbidden.attribute),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    violations
+}
+
+/// Violation for a file the scanner could not read or parse
+#[must_use]
+pub fn parse_error(file: &Path, error: &str) -> Violation {
+    Violation {
+        rule: PARSE_ERROR.to_string(),
+        class: None,
+        member: None,
+        file: file.to_path_buf(),
+        message: error.to_string(),
+    }
+}
+
+/// One line per violation: `file: [Class[::member]: ]message [rule]`
+#[must_use]
+pub fn format_violations(violations: &[Violation]) -> String {
+    let mut out = String::new();
+    for v in violations {
+        let subject = v.class.as_ref().map_or_else(String::new, |class| {
+            let member = v
+                .member
+                .as_ref()
+                .map(|m| format!("::{m}"))
+                .unwrap_or_default();
+            format!("{class}{member}: ")
+        });
+        let _ = writeln!(
+            out,
+            "{}: {subject}{} [{}]",
+            v.file.display(),
+            v.message,
+            v.rule
+        );
+    }
+    out
+}
+
+/// Known violations that `discovery:check` does not report
+///
+/// Policy violations are identified by rule, class and member, so they
+/// survive file moves and message changes. Parse errors are identified by
+/// file, stored relative to the baseline's directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyBaseline {
+    pub ignored: BTreeSet<BaselineEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub rule: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+}
+
+/// Outcome of applying a baseline
+#[derive(Debug, Clone, Default)]
+pub struct BaselineResult {
+    /// Violations not covered by the baseline
+    pub violations: Vec<Violation>,
+    /// Number of violations the baseline suppressed
+    pub suppressed: usize,
+    /// Baseline entries that matched nothing (fixed since the baseline was made)
+    pub unmatched: Vec<BaselineEntry>,
+}
+
+impl PolicyBaseline {
+    /// Baseline covering every given violation
+    ///
+    /// `baseline_path` is where the baseline will be stored; file paths
+    /// are made relative to its directory.
+    #[must_use]
+    pub fn from_violations(violations: &[Violation], baseline_path: &Path) -> Self {
+        let base_dir = base_dir(baseline_path);
+        Self {
+            ignored: violations
+                .iter()
+                .map(|violation| BaselineEntry::of(violation, &base_dir))
+                .collect(),
+        }
+    }
+
+    /// Load a baseline written by [`PolicyBaseline::save`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a baseline.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid baseline {}", path.display()))
+    }
+
+    /// Store the baseline as pretty-printed JSON, leaving an unchanged file
+    /// alone
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the baseline cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)? + "\n";
+        crate::writer::write_if_changed(path, content.as_bytes())
+            .with_context(|| format!("Failed to write baseline {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Drop the violations listed in the baseline (loaded from `baseline_path`)
+    #[must_use]
+    pub fn apply(&self, violations: Vec<Violation>, baseline_path: &Path) -> BaselineResult {
+        let base_dir = base_dir(baseline_path);
+        let mut matched = BTreeSet::new();
+        let mut result = BaselineResult::default();
+
+        for violation in violations {
+            let entry = BaselineEntry::of(&violation, &base_dir);
+            if self.ignored.contains(&entry) {
+                result.suppressed += 1;
+                matched.insert(entry);
+            } else {
+                result.violations.push(violation);
+            }
+        }
+        result.unmatched = self.ignored.difference(&matched).cloned().collect();
+
+        result
+    }
+}
+
+impl BaselineEntry {
+    fn of(violation: &Violation, base_dir: &Path) -> Self {
+        Self {
+            rule: violation.rule.clone(),
+            class: violation.class.clone(),
+            member: violation.member.clone(),
+            file: violation
+                .class
+                .is_none()
+                .then(|| relative_to(&violation.file, base_dir)),
+        }
+    }
+}
+
+fn base_dir(baseline_path: &Path) -> PathBuf {
+    let dir = baseline_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf())
+}
+
+fn relative_to(path: &Path, base_dir: &Path) -> PathBuf {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    path.strip_prefix(base_dir)
+        .map_or_else(|_| path.clone(), Path::to_path_buf)
+}
+
+/// Attribute maps a constraint looks at, with the member name for members
+fn targets<'a>(class: &'a PhpClassMetadata, on: &str) -> Vec<(Option<&'a str>, &'a AttributeMap)> {
+    let class_level = std::iter::once((None, &class.attributes));
+    let methods = class
+        .methods
+        .iter()
+        .filter(move |method| on != "public_method" || method.visibility == "public")
+        .map(|method| (Some(method.name.as_str()), &method.attributes));
+    let properties = class
+        .properties
+        .iter()
+        .map(|property| (Some(property.name.as_str()), &property.attributes));
+
+    match on {
+        "method" | "public_method" => methods.collect(),
+        "property" => properties.collect(),
+        "any" => class_level.chain(methods).chain(properties).collect(),
+        _ => class_level.collect(),
+    }
+}
+
+fn has(attributes: &AttributeMap, name: &str) -> bool {
+    attributes.keys().any(|key| matches_attribute(key, name))
+}
+
+fn describe(on: &str) -> &'static str {
+    match on {
+        "method" => "any method",
+        "public_method" => "any public method",
+        "property" => "any property",
+        "any" => "the class or any member",
+        _ => "the class",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::parser::tests::extract;
+    use crate::resolver::resolve_hierarchy;
+    use serde_json::json;
+
+    fn resolved(code: &str) -> Vec<PhpClassMetadata> {
+        let mut metadata = extract("/src/App.php", code);
+        resolve_hierarchy(&mut metadata);
+        metadata
+    }
+
+    fn rules(value: serde_json::Value) -> Vec<PolicyRule> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_required_and_forbidden_attributes() {
+        let metadata = resolved(
+            r"<?php
+namespace App\Controller;
+use App\Handler;
+use Symfony\Component\Routing\Attribute\Route;
+
+abstract class BaseController {}
+
+final class UserController extends BaseController {
+    #[Route('/users')]
+    public function list(): void {}
+}
+
+final class BrokenController extends BaseController {
+    #[Route('/hidden')]
+    private function hidden(): void {}
+}
+
+final class LegacyHandler implements Handler {
+    #[\Deprecated]
+    public function handle(): void {}
+}
+",
+        );
+        let rules = rules(json!([
+            {
+                "name": "controllers-routed",
+                "select": {"namespace": "App\\Controller", "extends": "BaseController"},
+                "require": {"attribute": "Route", "on": "public_method"},
+            },
+            {
+                "name": "no-deprecated-handlers",
+                "message": "handlers must not be deprecated",
+                "select": {"implements": "App\\Handler"},
+                "forbid": {"attribute": "Deprecated", "on": "any"},
+            },
+        ]));
+        assert!(rules.iter().all(|rule| rule.validate().is_ok()));
+
+        let violations = check_policies(&metadata, &rules);
+
+        assert_eq!(violations.len(), 2);
+        assert_eq!(
+            violations[0].class.as_deref(),
+            Some("App\\Controller\\BrokenController")
+        );
+        assert_eq!(
+            violations[0].message,
+            "missing #[Route] on any public method"
+        );
+        assert_eq!(violations[1].member.as_deref(), Some("handle"));
+        assert_eq!(
+            format_violations(&violations[1..]),
+            "/src/App.php: App\\Controller\\LegacyHandler::handle: handlers must not be deprecated [no-deprecated-handlers]\n"
+        );
+    }
+
+    #[test]
+    fn test_rule_validation() {
+        let both = rules(json!([{
+            "name": "both",
+            "require": {"attribute": "A"},
+            "forbid": {"attribute": "B"},
+        }]));
+        assert!(both[0].validate().unwrap_err().contains("exactly one"));
+
+        let target = rules(json!([{"name": "t", "require": {"attribute": "A", "on": "function"}}]));
+        assert!(
+            target[0]
+                .validate()
+                .unwrap_err()
+                .contains("Invalid policy target")
+        );
+    }
+
+    #[test]
+    fn test_baseline_suppresses_known_violations() {
+        let dir = tempfile::tempdir().unwrap();
+        let baseline_path = dir.path().join("policy-baseline.json");
+        let broken = dir.path().join("src/Broken.php");
+        let violation = |class: &str| Violation {
+            rule: "controllers-routed".to_string(),
+            class: Some(class.to_string()),
+            member: None,
+            file: dir.path().join("src/Controller.php"),
+            message: "missing #[Route] on any public method".to_string(),
+        };
+
+        let known = vec![
+            violation("App\\LegacyController"),
+            violation("App\\FixedController"),
+            parse_error(&broken, "syntax error"),
+        ];
+        PolicyBaseline::from_violations(&known, &baseline_path)
+            .save(&baseline_path)
+            .unwrap();
+        let baseline = PolicyBaseline::load(&baseline_path).unwrap();
+        assert!(
+            baseline
+                .ignored
+                .iter()
+                .any(|entry| entry.file.as_deref() == Some(Path::new("src/Broken.php")))
+        );
+
+        let current = vec![
+            violation("App\\LegacyController"),
+            violation("App\\NewController"),
+            parse_error(&broken, "another syntax error"),
+        ];
+        let result = baseline.apply(current, &baseline_path);
+
+        assert_eq!(result.suppressed, 2);
+        assert_eq!(result.violations.len(), 1);
+        assert_eq!(
+            result.violations[0].class.as_deref(),
+            Some("App\\NewController")
+        );
+        assert_eq!(result.unmatched.len(), 1);
+        assert_eq!(
+            result.unmatched[0].class.as_deref(),
+            Some("App\\FixedController")
+        );
+    }
+}
@@ -0,0 +1,36 @@
+//! The supported API for embedding the scanner
+//!
+//! ```rust,no_run
+//! use aurynx::prelude::*;
+//!
+//! let report = Discovery::builder().path("src").scan()?;
+//! let entities: Vec<&PhpClassMetadata> = report
+//!     .classes
+//!     .iter()
+//!     .filter(|class| class.attributes.contains_key("Doctrine\\ORM\\Mapping\\Entity"))
+//!     .collect();
+//! # Ok::<(), AurynxError>(())
+//! ```
+//!
+//! Everything exported here follows semver: it is only removed or changed
+//! incompatibly in a major release. Serialized metadata is versioned
+//! separately (see [`crate::schema`]). `aurynx::Result` is left out so that
+//! a glob import does not shadow [`std::result::Result`].
+
+pub use crate::config::ConfigFile;
+pub use crate::diagnostic::SyntaxDiagnostic;
+pub use crate::discovery::{
+    Discovery, DiscoveryBuilder, DiscoveryReport, DiscoveryStream, FailedFile, ScanItem,
+};
+pub use crate::error::AurynxError;
+pub use crate::metadata::{
+    AttributeArgument, AttributeMap, ClassModifiers, EnumCase, MethodModifiers, PhpClassMetadata,
+    PhpMethodMetadata, PhpParameterMetadata, PhpPropertyMetadata, PropertyModifiers,
+};
+pub use crate::parser::PhpMetadataExtractor;
+pub use crate::reader::read_cache;
+pub use crate::scanner::{
+    DEFAULT_MAX_FILE_SIZE, ExtractorPool, FileScan, OnError, ScanFailure, ScanOptions,
+    scan_files, scan_files_detailed, scan_files_with_limit,
+};
+pub use crate::writer::render_cache;
@@ -0,0 +1,308 @@
+//! Per-class cache priming (`discovery:export redis|apcu`)
+//!
+//! High-traffic apps can look up single classes instead of loading the
+//! monolithic cache. The Redis exporter stores every class as a field of one
+//! hash (`HSET aurynx:classes <fqcn> <json>`), either sent directly to a
+//! server or written as a protocol file for `redis-cli --pipe`. The hash is
+//! filled under a temporary key and renamed at the end, so readers never see
+//! a half-written hash and removed classes disappear.
+//!
+//! The `APCu` exporter writes a PHP script that stores every class under
+//! `<prefix><fqcn>` with `apcu_store()`. `APCu` memory is per SAPI, so the
+//! script has to run inside the web server (e.g. from a warmup endpoint),
+//! not from the CLI.
+
+use crate::metadata::PhpClassMetadata;
+use crate::writer::{escape_php_string, render_php_cache};
+use anyhow::{Context, Result, bail};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::time::Duration;
+
+/// Default Redis hash key and `APCu` key prefix
+pub const DEFAULT_KEY: &str = "aurynx:classes";
+
+/// Fields per `HSET` command
+const HSET_BATCH: usize = 1000;
+
+/// Redis commands replacing `key` with one field per class
+///
+/// # Errors
+///
+/// Returns an error if a class cannot be serialized.
+pub fn redis_commands(metadata_list: &[PhpClassMetadata], key: &str) -> Result<Vec<Vec<Vec<u8>>>> {
+    let temp_key = format!("{key}:tmp");
+    let command = |args: &[&str]| -> Vec<Vec<u8>> {
+        args.iter().map(|arg| arg.as_bytes().to_vec()).collect()
+    };
+
+    let mut commands = vec![command(&["DEL", &temp_key])];
+    for batch in metadata_list.chunks(HSET_BATCH) {
+        let mut hset = command(&["HSET", &temp_key]);
+        for class in batch {
+            hset.push(class.fqcn.as_bytes().to_vec());
+            hset.push(serde_json::to_vec(class)?);
+        }
+        commands.push(hset);
+    }
+    commands.push(if metadata_list.is_empty() {
+        command(&["DEL", key])
+    } else {
+        command(&["RENAME", &temp_key, key])
+    });
+
+    Ok(commands)
+}
+
+/// Encode commands in the Redis serialization protocol (RESP)
+#[must_use]
+pub fn encode_resp(commands: &[Vec<Vec<u8>>]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for args in commands {
+        out.extend_from_slice(format!("*{}\r\n", args.len()).as_bytes());
+        for arg in args {
+            out.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+            out.extend_from_slice(arg);
+            out.extend_from_slice(b"\r\n");
+        }
+    }
+    out
+}
+
+/// Write a protocol file for `redis-cli --pipe` (kept as is when unchanged)
+///
+/// # Errors
+///
+/// Returns an error if the commands cannot be encoded or written.
+pub fn write_redis_protocol(
+    metadata_list: &[PhpClassMetadata], key: &str, output_path: &Path,
+) -> Result<()> {
+    let content = encode_resp(&redis_commands(metadata_list, key)?);
+    crate::writer::write_if_changed(output_path, &content).with_context(|| {
+        format!(
+            "Failed to write Redis protocol file {}",
+            output_path.display()
+        )
+    })?;
+    Ok(())
+}
+
+/// Send the classes to the Redis server at `url`
+///
+/// `url` has the form `redis://[[user]:password@]host[:port][/db]`.
+///
+/// # Errors
+///
+/// Returns an error if the URL is invalid, the server is unreachable or
+/// rejects a command.
+pub fn push_to_redis(metadata_list: &[PhpClassMetadata], key: &str, url: &str) -> Result<()> {
+    let target = RedisUrl::parse(url)?;
+    let stream = TcpStream::connect((target.host, target.port)).with_context(|| {
+        format!(
+            "Failed to connect to Redis at {}:{}",
+            target.host, target.port
+        )
+    })?;
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+
+    let mut commands = Vec::new();
+    if let Some(password) = target.password {
+        let mut auth = vec![b"AUTH".to_vec()];
+        auth.extend(target.user.map(|user| user.as_bytes().to_vec()));
+        auth.push(password.as_bytes().to_vec());
+        commands.push(auth);
+    }
+    if let Some(db) = target.db {
+        commands.push(vec![b"SELECT".to_vec(), db.as_bytes().to_vec()]);
+    }
+    commands.extend(redis_commands(metadata_list, key)?);
+
+    // Pipelined: send everything, then check one reply per command
+    (&stream).write_all(&encode_resp(&commands))?;
+    let mut reader = BufReader::new(&stream);
+    for args in &commands {
+        let mut reply = String::new();
+        if reader.read_line(&mut reply)? == 0 {
+            bail!("Redis closed the connection");
+        }
+        if let Some(error) = reply.strip_prefix('-') {
+            let command = String::from_utf8_lossy(&args[0]);
+            bail!("Redis rejected {command}: {}", error.trim_end());
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a PHP script storing every class in `APCu` under `<prefix><fqcn>`
+///
+/// The script returns the number of stored classes. A `ttl` of 0 keeps the
+/// entries until the cache is cleared. An unchanged script is not rewritten.
+///
+/// # Errors
+///
+/// Returns an error if the script cannot be written.
+pub fn write_apcu_script(
+    metadata_list: &[PhpClassMetadata], prefix: &str, ttl: u64, output_path: &Path,
+) -> Result<()> {
+    let mut cache = Vec::new();
+    render_php_cache(metadata_list, &mut cache, true)?;
+    let cache = String::from_utf8(cache)?;
+    // Keep the `return [...];` statement, dropping the file header
+    let statement = cache
+        .find("return ")
+        .map_or(cache.as_str(), |start| &cache[start..]);
+
+    let prefix = escape_php_string(prefix);
+    let script = format!(
+        "<?php\n\ndeclare(strict_types=1);\n\n\
+         // Run inside the web server: the CLI has its own APCu cache\n\
+         $classes = (static function (): array {{\n{statement}}})();\n\n\
+         foreach ($classes as $fqcn => $class) {{\n    \
+         apcu_store('{prefix}' . $fqcn, $class, {ttl});\n}}\n\n\
+         return \\count($classes);\n"
+    );
+
+    crate::writer::write_if_changed(output_path, script.as_bytes())
+        .with_context(|| format!("Failed to write APCu script {}", output_path.display()))?;
+    Ok(())
+}
+
+struct RedisUrl<'a> {
+    host: &'a str,
+    port: u16,
+    user: Option<&'a str>,
+    password: Option<&'a str>,
+    db: Option<&'a str>,
+}
+
+impl<'a> RedisUrl<'a> {
+    fn parse(url: &'a str) -> Result<Self> {
+        let Some(rest) = url.strip_prefix("redis://") else {
+            bail!("Redis URL '{url}' must start with redis://");
+        };
+        let (authority, db) = match rest.split_once('/') {
+            Some((authority, db)) if !db.is_empty() => (authority, Some(db)),
+            Some((authority, _)) => (authority, None),
+            None => (rest, None),
+        };
+        let (credentials, address) = match authority.rsplit_once('@') {
+            Some((credentials, address)) => (Some(credentials), address),
+            None => (None, authority),
+        };
+        let (user, password) = match credentials.map(|c| c.split_once(':')) {
+            Some(Some((user, password))) => ((!user.is_empty()).then_some(user), Some(password)),
+            Some(None) => (None, credentials),
+            None => (None, None),
+        };
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .with_context(|| format!("Invalid port in Redis URL '{url}'"))?,
+            ),
+            None => (address, 6379),
+        };
+        if host.is_empty() {
+            bail!("Missing host in Redis URL '{url}'");
+        }
+
+        Ok(Self {
+            host,
+            port,
+            user,
+            password,
+            db,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::parser::tests::extract;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_redis_commands_replace_hash() {
+        let metadata = extract("/src/App.php", "<?php namespace App; class A {} class B {}");
+        let commands = redis_commands(&metadata, "app:classes").unwrap();
+
+        let names: Vec<_> = commands
+            .iter()
+            .map(|args| String::from_utf8_lossy(&args[0]).to_string())
+            .collect();
+        assert_eq!(names, ["DEL", "HSET", "RENAME"]);
+        assert_eq!(commands[1][1], b"app:classes:tmp");
+        assert_eq!(commands[1][2], b"\\App\\A");
+        assert_eq!(commands[2][2], b"app:classes");
+
+        let payload: PhpClassMetadata = serde_json::from_slice(&commands[1][3]).unwrap();
+        assert_eq!(payload, metadata[0]);
+
+        assert_eq!(
+            encode_resp(&commands[2..]),
+            b"*3\r\n$6\r\nRENAME\r\n$15\r\napp:classes:tmp\r\n$11\r\napp:classes\r\n"
+        );
+        assert_eq!(
+            redis_commands(&[], "k").unwrap()[1],
+            [b"DEL".to_vec(), b"k".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_push_to_redis_authenticates_and_selects_db() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            let mut buffer = [0; 4096];
+            while !received.ends_with(b"aurynx:classes\r\n") {
+                let n = stream.read(&mut buffer).unwrap();
+                received.extend_from_slice(&buffer[..n]);
+            }
+            // AUTH, SELECT, DEL, HSET, RENAME
+            stream
+                .write_all(b"+OK\r\n+OK\r\n:0\r\n:1\r\n+OK\r\n")
+                .unwrap();
+            String::from_utf8(received).unwrap()
+        });
+
+        let metadata = extract("/src/App.php", "<?php namespace App; class A {}");
+        push_to_redis(
+            &metadata,
+            DEFAULT_KEY,
+            &format!("redis://:secret@127.0.0.1:{port}/2"),
+        )
+        .unwrap();
+
+        let received = server.join().unwrap();
+        assert!(
+            received.starts_with(
+                "*2\r\n$4\r\nAUTH\r\n$6\r\nsecret\r\n*2\r\n$6\r\nSELECT\r\n$1\r\n2\r\n"
+            )
+        );
+        assert!(received.contains("$4\r\nHSET\r\n"));
+    }
+
+    #[test]
+    fn test_apcu_script() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("prime.php");
+        let metadata = extract("/src/App.php", "<?php namespace App; class A {}");
+
+        write_apcu_script(&metadata, "app:", 3600, &output).unwrap();
+
+        let script = std::fs::read_to_string(output).unwrap();
+        assert!(script.starts_with("<?php\n\ndeclare(strict_types=1);\n"));
+        assert_eq!(script.matches("declare(strict_types=1)").count(), 1);
+        assert!(script.contains("$classes = (static function (): array {\nreturn [\n"));
+        assert!(script.contains("'\\\\App\\\\A' => ["));
+        assert!(script.contains("apcu_store('app:' . $fqcn, $class, 3600);"));
+        assert!(script.ends_with("return \\count($classes);\n"));
+    }
+}
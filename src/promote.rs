@@ -0,0 +1,147 @@
+//! Deploy-time cache promotion (`discovery:promote`)
+//!
+//! A cache built in CI or in a release directory has to replace the live
+//! cache without a moment in which PHP reads a half-written file, and
+//! without a running daemon overwriting it with its older state on the next
+//! flush. Promotion checks that the built file is a cache, installs it and
+//! the manifest scanned with it by atomic renames, and then asks the daemon
+//! to `reload`: a read-only daemon loads the new file, a watching daemon
+//! rescans on top of the new manifest, reusing the entries of unchanged
+//! files.
+
+use crate::incremental::{MANIFEST_FILE, MANIFEST_LOCK_TIMEOUT, Manifest, ManifestLock};
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What promotion did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Promotion {
+    /// Classes in the installed cache
+    pub classes: usize,
+    /// Whether a manifest was installed with the cache (otherwise the stale
+    /// one was removed, and the next incremental scan is a full scan)
+    pub manifest: bool,
+    /// `reload` response of the daemon, if one is running
+    pub reloaded: Option<String>,
+}
+
+/// Install the cache `from` as `to` and notify the daemon behind `socket`
+///
+/// # Errors
+///
+/// Returns an error if `from` is not a readable cache, the manifest lock is
+/// not released in time, a file cannot be installed, or the daemon fails to
+/// reload.
+pub fn promote(from: &Path, to: &Path, socket: Option<&Path>) -> Result<Promotion> {
+    let classes = crate::reader::read_cache(from)?.len();
+    let content =
+        fs::read(from).with_context(|| format!("Failed to read cache {}", from.display()))?;
+
+    // One-shot scans of the target wait until both files are in place
+    let target_manifest = manifest_path(to);
+    let _lock = ManifestLock::acquire(&target_manifest, MANIFEST_LOCK_TIMEOUT)?;
+
+    crate::writer::write_if_changed(to, &content)
+        .with_context(|| format!("Failed to install cache {}", to.display()))?;
+
+    let source_manifest = manifest_path(from);
+    let manifest = source_manifest.exists();
+    if manifest {
+        Manifest::load(&source_manifest)
+            .and_then(|loaded| loaded.save(&target_manifest))
+            .with_context(|| format!("Failed to install manifest {}", target_manifest.display()))?;
+    } else if let Err(e) = fs::remove_file(&target_manifest)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        return Err(e).with_context(|| {
+            format!("Failed to remove stale manifest {}", target_manifest.display())
+        });
+    }
+
+    let reloaded = match socket {
+        Some(socket) if crate::warm::ping(socket).is_ok() => Some(reload(socket)?),
+        _ => None,
+    };
+
+    Ok(Promotion {
+        classes,
+        manifest,
+        reloaded,
+    })
+}
+
+/// Ask the daemon to load the promoted cache; returns its response line
+fn reload(socket: &Path) -> Result<String> {
+    let response = crate::warm::request(socket, "reload")?;
+    let response = String::from_utf8_lossy(&response).trim_end().to_string();
+    if !response.starts_with("RELOADED") {
+        bail!("Daemon answered reload with {response}");
+    }
+    Ok(response)
+}
+
+/// Manifest next to a cache file
+fn manifest_path(cache: &Path) -> PathBuf {
+    cache
+        .parent()
+        .map_or_else(|| PathBuf::from(MANIFEST_FILE), |dir| dir.join(MANIFEST_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_promote_installs_cache_and_manifest() {
+        let temp_dir = TempDir::new().unwrap();
+        let build = temp_dir.path().join("build");
+        let live = temp_dir.path().join("live");
+        fs::create_dir_all(&build).unwrap();
+        fs::create_dir_all(&live).unwrap();
+
+        let rendered = crate::writer::render_cache(&[], "php", false, None).unwrap();
+        fs::write(build.join("cache.php"), &rendered).unwrap();
+        Manifest::default()
+            .save(&build.join(MANIFEST_FILE))
+            .unwrap();
+        fs::write(live.join("cache.php"), "<?php return ['old'];").unwrap();
+
+        let promotion = promote(
+            &build.join("cache.php"),
+            &live.join("cache.php"),
+            Some(&temp_dir.path().join("no-daemon.sock")),
+        )
+        .unwrap();
+
+        assert_eq!(
+            promotion,
+            Promotion {
+                classes: 0,
+                manifest: true,
+                reloaded: None,
+            }
+        );
+        assert_eq!(fs::read(live.join("cache.php")).unwrap(), rendered);
+        assert!(live.join(MANIFEST_FILE).exists());
+
+        // Without a built manifest, the live one no longer matches the cache
+        fs::remove_file(build.join(MANIFEST_FILE)).unwrap();
+        let promotion = promote(&build.join("cache.php"), &live.join("cache.php"), None).unwrap();
+        assert!(!promotion.manifest);
+        assert!(!live.join(MANIFEST_FILE).exists());
+    }
+
+    #[test]
+    fn test_promote_rejects_files_that_are_not_caches() {
+        let temp_dir = TempDir::new().unwrap();
+        let from = temp_dir.path().join("notes.txt");
+        let to = temp_dir.path().join("cache.php");
+        fs::write(&from, "not a cache").unwrap();
+
+        assert!(promote(&from, &to, None).is_err());
+        assert!(!to.exists());
+    }
+}
@@ -0,0 +1,707 @@
+//! Load previously written caches back into metadata
+//!
+//! JSON caches go through [`crate::schema::read_json_cache`], so caches from
+//! older schema versions are migrated on the way in. PHP caches are parsed
+//! with a small reader for the array literal subset [`crate::writer`] emits;
+//! it is not a general PHP parser.
+//!
+//! Default values, enum case values and attribute arguments are kept as the
+//! PHP source the writer produced, like the scanner reports them. A `null`
+//! default reads back as no default, and PHP caches carry no schema version,
+//! so their records get the current one. Relative paths in PHP caches written
+//! with `path_root` resolve against the cache location, as PHP would resolve
+//! them; JSON caches keep them relative.
+
+use crate::metadata::{
+    AttributeArgument, AttributeMap, ClassModifiers, CustomCaptures, EnumCase, MethodModifiers,
+    PhpClassMetadata, PhpFileContext, PhpMethodMetadata, PhpParameterMetadata, PhpPropertyMetadata,
+    PropertyModifiers, TruncatedMembers,
+};
+use anyhow::{Context, Result, bail};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Read a JSON or PHP cache file, detecting the format from its content
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is not a cache written by
+/// this tool.
+pub fn read_cache(path: &Path) -> Result<Vec<PhpClassMetadata>> {
+    let content = crate::retry::with_retry(path, || std::fs::read(path))
+        .with_context(|| format!("Failed to read cache {}", path.display()))?;
+    let content = crate::encryption::open(content)
+        .and_then(|content| Ok(String::from_utf8(content)?))
+        .with_context(|| format!("Failed to read cache {}", path.display()))?;
+    let cache_dir = std::path::absolute(path)?
+        .parent()
+        .map_or_else(PathBuf::new, Path::to_path_buf);
+
+    parse_cache(&content, &cache_dir)
+        .with_context(|| format!("Failed to load cache {}", path.display()))
+}
+
+/// Parse cache content in either format
+///
+/// `cache_dir` stands in for `__DIR__` when resolving relative PHP paths.
+///
+/// # Errors
+///
+/// Returns an error if the content is not a JSON or PHP cache.
+pub fn parse_cache(content: &str, cache_dir: &Path) -> Result<Vec<PhpClassMetadata>> {
+    if content.trim_start().starts_with("<?php") {
+        parse_php_cache(content, cache_dir)
+    } else {
+        crate::schema::read_json_cache(content)
+    }
+}
+
+/// Parse a PHP cache as written by [`crate::writer::render_php_cache`]
+///
+/// # Errors
+///
+/// Returns an error if the content does not have the writer's layout.
+pub fn parse_php_cache(content: &str, cache_dir: &Path) -> Result<Vec<PhpClassMetadata>> {
+    let Some(start) = content.find("return") else {
+        bail!("PHP cache has no return statement");
+    };
+
+    let mut parser = Parser {
+        src: content,
+        pos: start + "return".len(),
+    };
+    let root = parser.value()?;
+    parser.skip_whitespace();
+    if !parser.rest().starts_with(';') {
+        bail!("Expected ';' after the cache array at byte {}", parser.pos);
+    }
+
+    // Grouped caches (`cache_layout`) nest the class entries one level deeper;
+    // a class listed under several attributes is read once
+    let mut seen = HashSet::new();
+    let mut classes = Vec::new();
+    for entry in root.entries()? {
+        let group = if entry.1.opt_field("file").is_some() {
+            std::slice::from_ref(entry)
+        } else {
+            entry.1.entries()?
+        };
+        for (fqcn, class) in group {
+            let fqcn = fqcn.clone().context("Class entry without a FQCN key")?;
+            if seen.insert(fqcn.clone()) {
+                classes.push(
+                    read_class(fqcn.clone(), class, cache_dir)
+                        .with_context(|| format!("Invalid cache entry for {fqcn}"))?,
+                );
+            }
+        }
+    }
+    Ok(classes)
+}
+
+fn read_class(fqcn: String, node: &Node, cache_dir: &Path) -> Result<PhpClassMetadata> {
+    let modifiers = node.field("modifiers")?;
+    let mut class = PhpClassMetadata::new(
+        fqcn,
+        resolve_file(node.field("file")?, cache_dir)?,
+        node.field("type")?.string()?,
+    );
+
+    class.file_hash = node.field("file_hash")?.string()?;
+    class.signature_hash = node
+        .opt_field("signature_hash")
+        .map(Node::string)
+        .transpose()?;
+    class.file_context = node
+        .opt_field("file_context")
+        .map(read_file_context)
+        .transpose()?;
+    class.custom = node.opt_field("custom").map(read_custom).transpose()?;
+    class.truncated = node
+        .opt_field("truncated")
+        .map(read_truncated)
+        .transpose()?;
+    class.modifiers = ClassModifiers {
+        is_abstract: modifiers.field("abstract")?.bool()?,
+        is_final: modifiers.field("final")?.bool()?,
+        is_readonly: modifiers.field("readonly")?.bool()?,
+    };
+    class.attributes = node.field("attributes")?.attributes()?;
+    class.inherited_attributes = node
+        .opt_field("inherited_attributes")
+        .map(Node::attributes)
+        .transpose()?;
+    class.extends = node.field("extends")?.opt_string()?;
+    class.implements = node.field("implements")?.string_list()?;
+    class.traits = node
+        .opt_field("traits")
+        .map(Node::string_list)
+        .transpose()?
+        .unwrap_or_default();
+    class.resolved_implements = node
+        .opt_field("resolved_implements")
+        .map(Node::string_list)
+        .transpose()?;
+    class.ancestors = node
+        .opt_field("ancestors")
+        .map(Node::string_list)
+        .transpose()?;
+    class.methods = named_entries(node.field("methods")?, read_method)?;
+    class.properties = named_entries(node.field("properties")?, read_property)?;
+
+    if let Some(backing_type) = node.opt_field("backing_type") {
+        class.backing_type = backing_type.opt_string()?;
+    }
+    if let Some(cases) = node.opt_field("cases") {
+        class.cases = named_entries(cases, |name, case| {
+            Ok(EnumCase {
+                name,
+                value: case.field("value")?.opt_literal(),
+                attributes: case.field("attributes")?.attributes()?,
+            })
+        })?;
+    }
+
+    Ok(class)
+}
+
+fn read_file_context(node: &Node) -> Result<PhpFileContext> {
+    Ok(PhpFileContext {
+        namespace: node.field("namespace")?.opt_string()?,
+        strict_types: node.field("strict_types")?.bool()?,
+        imports: named_entries(node.field("imports")?, |alias, fqcn| {
+            Ok((alias, fqcn.string()?))
+        })?
+        .into_iter()
+        .collect(),
+        classes: node.field("classes")?.string_list()?,
+        functions: node.field("functions")?.string_list()?,
+        constants: node.field("constants")?.string_list()?,
+    })
+}
+
+fn read_custom(node: &Node) -> Result<CustomCaptures> {
+    Ok(named_entries(node, |key, matches| {
+        let matches = matches
+            .entries()?
+            .iter()
+            .map(|(_, captures)| {
+                Ok(named_entries(captures, |name, text| Ok((name, text.string()?)))?
+                    .into_iter()
+                    .collect())
+            })
+            .collect::<Result<_>>()?;
+        Ok((key, matches))
+    })?
+    .into_iter()
+    .collect())
+}
+
+fn read_truncated(node: &Node) -> Result<TruncatedMembers> {
+    Ok(TruncatedMembers {
+        methods: node.field("methods")?.usize()?,
+        properties: node.field("properties")?.usize()?,
+        cases: node.field("cases")?.usize()?,
+    })
+}
+
+fn read_method(name: String, node: &Node) -> Result<PhpMethodMetadata> {
+    let modifiers = node.field("modifiers")?;
+    Ok(PhpMethodMetadata {
+        name,
+        visibility: node.field("visibility")?.string()?,
+        modifiers: MethodModifiers {
+            is_abstract: modifiers.field("abstract")?.bool()?,
+            is_final: modifiers.field("final")?.bool()?,
+            is_static: modifiers.field("static")?.bool()?,
+        },
+        attributes: node.field("attributes")?.attributes()?,
+        parameters: named_entries(node.field("parameters")?, |name, param| {
+            Ok(PhpParameterMetadata {
+                name,
+                type_hint: param.field("type")?.opt_string()?,
+                default_value: param.field("default")?.opt_literal(),
+                attributes: param.field("attributes")?.attributes()?,
+            })
+        })?,
+        return_type: node.field("return_type")?.opt_string()?,
+    })
+}
+
+fn read_property(name: String, node: &Node) -> Result<PhpPropertyMetadata> {
+    let modifiers = node.field("modifiers")?;
+    Ok(PhpPropertyMetadata {
+        name,
+        visibility: node.field("visibility")?.string()?,
+        modifiers: PropertyModifiers {
+            is_static: modifiers.field("static")?.bool()?,
+            is_readonly: modifiers.field("readonly")?.bool()?,
+        },
+        type_hint: node.field("type")?.opt_string()?,
+        default_value: node.field("default")?.opt_literal(),
+        attributes: node.field("attributes")?.attributes()?,
+    })
+}
+
+/// Map an array keyed by member name (methods, parameters, ...) in order
+fn named_entries<T>(node: &Node, read: impl Fn(String, &Node) -> Result<T>) -> Result<Vec<T>> {
+    node.entries()?
+        .iter()
+        .map(|(name, value)| {
+            let name = name.clone().context("Member entry without a name key")?;
+            read(name.clone(), value).with_context(|| format!("in member {name}"))
+        })
+        .collect()
+}
+
+/// Evaluate a `file` value: a path literal or `<root expr>.'/relative/path'`
+///
+/// The root expressions are those of [`crate::writer::php_root_expr`].
+fn resolve_file(node: &Node, cache_dir: &Path) -> Result<PathBuf> {
+    if let Kind::Str(path) = &node.kind {
+        return Ok(PathBuf::from(path));
+    }
+
+    let raw = node.raw;
+    let (root, rest) = if let Some(rest) = raw.strip_prefix("__DIR__") {
+        (cache_dir.to_path_buf(), rest)
+    } else if let Some(rest) = raw.strip_prefix("\\dirname(__DIR__, ") {
+        let (levels, rest) = rest
+            .split_once(')')
+            .context("Unterminated dirname() in file path")?;
+        let levels: usize = levels.trim().parse().context("Invalid dirname() level")?;
+        let root = cache_dir.ancestors().nth(levels).unwrap_or(cache_dir);
+        (root.to_path_buf(), rest)
+    } else {
+        let (root, rest) = string_literal(raw).context("Unsupported file path expression")?;
+        (PathBuf::from(root), rest)
+    };
+
+    let relative = rest
+        .trim_start()
+        .strip_prefix('.')
+        .and_then(|rest| string_literal(rest.trim_start()))
+        .filter(|(_, rest)| rest.trim().is_empty())
+        .with_context(|| format!("Unsupported file path expression: {raw}"))?
+        .0;
+
+    Ok(root.join(relative.trim_start_matches('/')))
+}
+
+/// Split a leading single-quoted PHP string off `s`, returning its value
+pub(crate) fn string_literal(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.strip_prefix('\'')?.char_indices();
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' => return Some((value, &s[i + 2..])),
+            '\\' => match chars.next() {
+                Some((_, escaped @ ('\\' | '\''))) => value.push(escaped),
+                Some((_, other)) => {
+                    value.push('\\');
+                    value.push(other);
+                },
+                None => value.push('\\'),
+            },
+            _ => value.push(c),
+        }
+    }
+    None
+}
+
+/// A parsed PHP value with the source text it was parsed from
+#[derive(Debug)]
+struct Node<'a> {
+    raw: &'a str,
+    kind: Kind<'a>,
+}
+
+#[derive(Debug)]
+enum Kind<'a> {
+    /// Single-quoted string, unescaped
+    Str(String),
+    /// Any other expression (null, booleans, numbers, constants, ...)
+    Scalar,
+    /// Array literal; keys are `None` for list items
+    Array(Vec<(Option<String>, Node<'a>)>),
+}
+
+impl Node<'_> {
+    fn entries(&self) -> Result<&[(Option<String>, Self)]> {
+        match &self.kind {
+            Kind::Array(entries) => Ok(entries),
+            _ => bail!("Expected an array, found {}", self.raw),
+        }
+    }
+
+    fn opt_field(&self, key: &str) -> Option<&Self> {
+        let Kind::Array(entries) = &self.kind else {
+            return None;
+        };
+        entries
+            .iter()
+            .find(|(k, _)| k.as_deref() == Some(key))
+            .map(|(_, value)| value)
+    }
+
+    fn field(&self, key: &str) -> Result<&Self> {
+        self.opt_field(key)
+            .with_context(|| format!("Missing key '{key}'"))
+    }
+
+    const fn is_null(&self) -> bool {
+        matches!(self.kind, Kind::Scalar) && self.raw.eq_ignore_ascii_case("null")
+    }
+
+    fn string(&self) -> Result<String> {
+        match &self.kind {
+            Kind::Str(value) => Ok(value.clone()),
+            _ => bail!("Expected a string, found {}", self.raw),
+        }
+    }
+
+    fn opt_string(&self) -> Result<Option<String>> {
+        if self.is_null() {
+            return Ok(None);
+        }
+        self.string().map(Some)
+    }
+
+    fn bool(&self) -> Result<bool> {
+        match self.raw {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            other => bail!("Expected a boolean, found {other}"),
+        }
+    }
+
+    fn usize(&self) -> Result<usize> {
+        self.raw
+            .parse()
+            .with_context(|| format!("Expected an integer, found {}", self.raw))
+    }
+
+    fn string_list(&self) -> Result<Vec<String>> {
+        self.entries()?
+            .iter()
+            .map(|(_, item)| item.string())
+            .collect()
+    }
+
+    /// The value as PHP source, undoing the writer's quoting of `new` expressions
+    fn literal(&self) -> String {
+        match &self.kind {
+            Kind::Str(value) if is_new_expression(value) => value.clone(),
+            _ => self.raw.to_string(),
+        }
+    }
+
+    fn opt_literal(&self) -> Option<String> {
+        (!self.is_null()).then(|| self.literal())
+    }
+
+    fn attributes(&self) -> Result<AttributeMap> {
+        let mut attributes = AttributeMap::new();
+        for (name, instances) in self.entries()? {
+            let name = name.clone().context("Attribute entry without a name key")?;
+            let instances = instances
+                .entries()?
+                .iter()
+                .map(|(_, args)| {
+                    Ok(args
+                        .entries()?
+                        .iter()
+                        .map(|(key, value)| {
+                            key.as_ref().map_or_else(
+                                || AttributeArgument::Positional(value.literal()),
+                                |key| AttributeArgument::Named {
+                                    key: key.clone(),
+                                    value: value.literal(),
+                                },
+                            )
+                        })
+                        .collect())
+                })
+                .collect::<Result<_>>()?;
+            attributes.insert(name, instances);
+        }
+        Ok(attributes)
+    }
+}
+
+/// Whether the writer quoted `value` as a string because it builds objects
+fn is_new_expression(value: &str) -> bool {
+    let trimmed = value.trim();
+    trimmed.starts_with("new ")
+        || (trimmed.starts_with('[') && trimmed.ends_with(']') && trimmed.contains("new "))
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn value(&mut self) -> Result<Node<'a>> {
+        self.skip_whitespace();
+        let start = self.pos;
+        if self.rest().starts_with('[') {
+            let entries = self.array()?;
+            return Ok(Node {
+                raw: &self.src[start..self.pos],
+                kind: Kind::Array(entries),
+            });
+        }
+
+        self.pos += expression_len(self.rest());
+        let raw = self.src[start..self.pos].trim_end();
+        if raw.is_empty() {
+            bail!("Expected a value at byte {start}");
+        }
+        let kind = match string_literal(raw) {
+            Some((value, "")) => Kind::Str(value),
+            _ => Kind::Scalar,
+        };
+        Ok(Node { raw, kind })
+    }
+
+    fn array(&mut self) -> Result<Vec<(Option<String>, Node<'a>)>> {
+        self.pos += 1; // '['
+        let mut entries = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with(']') {
+                self.pos += 1;
+                return Ok(entries);
+            }
+
+            let first = self.value()?;
+            self.skip_whitespace();
+            let entry = if let Some(rest) = self.rest().strip_prefix("=>") {
+                self.pos = self.src.len() - rest.len();
+                let Kind::Str(key) = first.kind else {
+                    bail!("Unsupported array key {}", first.raw);
+                };
+                (Some(key), self.value()?)
+            } else {
+                (None, first)
+            };
+            entries.push(entry);
+
+            self.skip_whitespace();
+            match self.rest().chars().next() {
+                Some(',') => self.pos += 1,
+                Some(']') => {},
+                _ => bail!("Expected ',' or ']' at byte {}", self.pos),
+            }
+        }
+    }
+}
+
+/// Length of the expression at the start of `s`, up to the next `,`, `]`,
+/// `;` or `=>` outside strings and brackets
+fn expression_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if let Some(q) = quote {
+            if b == b'\\' {
+                i += 1;
+            } else if b == q {
+                quote = None;
+            }
+        } else {
+            match b {
+                b'\'' | b'"' => quote = Some(b),
+                b'(' | b'[' => depth += 1,
+                b')' | b']' if depth > 0 => depth -= 1,
+                b',' | b']' | b';' if depth == 0 => return i,
+                b'=' if depth == 0 && bytes.get(i + 1) == Some(&b'>') => return i,
+                _ => {},
+            }
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use crate::writer::{
+        CacheGroup, CacheLayout, CacheSort, php_root_expr, relativize_paths, render_cache,
+        render_php_cache_with_layout, render_php_cache_with_root,
+    };
+    use indexmap::IndexMap;
+
+    fn route_attribute() -> AttributeMap {
+        IndexMap::from([(
+            "App\\Attribute\\Route".to_string(),
+            vec![
+                vec![
+                    AttributeArgument::Positional("'/users/{id}'".to_string()),
+                    AttributeArgument::Named {
+                        key: "methods".to_string(),
+                        value: "['GET', 'HEAD']".to_string(),
+                    },
+                    AttributeArgument::Named {
+                        key: "options".to_string(),
+                        value: "new Options(cache: true)".to_string(),
+                    },
+                ],
+                vec![],
+            ],
+        )])
+    }
+
+    fn sample() -> Vec<PhpClassMetadata> {
+        let mut class = PhpClassMetadata::new(
+            "App\\Http\\UserController".to_string(),
+            PathBuf::from("/project/src/Http/User'Controller.php"),
+            "class".to_string(),
+        );
+        class.file_hash = "0123456789abcdef".to_string();
+        class.signature_hash = Some("fedcba9876543210".to_string());
+        class.modifiers.is_final = true;
+        class.attributes = route_attribute();
+        class.extends = Some("App\\Http\\Controller".to_string());
+        class.implements = vec!["Psr\\Log\\LoggerAwareInterface".to_string()];
+        class.ancestors = Some(vec!["App\\Http\\Controller".to_string()]);
+        class.file_context = Some(PhpFileContext {
+            namespace: Some("App\\Http".to_string()),
+            strict_types: true,
+            imports: IndexMap::from([(
+                "Route".to_string(),
+                "\\App\\Attribute\\Route".to_string(),
+            )]),
+            classes: vec!["App\\Http\\UserController".to_string()],
+            functions: vec!["\\App\\Http\\helper".to_string()],
+            constants: vec![],
+        });
+        class.methods = vec![PhpMethodMetadata {
+            name: "show".to_string(),
+            visibility: "public".to_string(),
+            modifiers: MethodModifiers {
+                is_static: true,
+                ..MethodModifiers::default()
+            },
+            attributes: route_attribute(),
+            parameters: vec![PhpParameterMetadata {
+                name: "format".to_string(),
+                type_hint: Some("?string".to_string()),
+                default_value: Some("\\App\\Format::JSON".to_string()),
+                attributes: AttributeMap::new(),
+            }],
+            return_type: Some("void".to_string()),
+        }];
+        class.properties = vec![PhpPropertyMetadata {
+            name: "limit".to_string(),
+            visibility: "private".to_string(),
+            modifiers: PropertyModifiers {
+                is_readonly: true,
+                ..PropertyModifiers::default()
+            },
+            type_hint: Some("int".to_string()),
+            default_value: Some("-1".to_string()),
+            attributes: AttributeMap::new(),
+        }];
+
+        let mut status = PhpClassMetadata::new(
+            "App\\Status".to_string(),
+            PathBuf::from("/project/src/Status.php"),
+            "enum".to_string(),
+        );
+        status.backing_type = Some("string".to_string());
+        status.cases = vec![EnumCase {
+            name: "Active".to_string(),
+            value: Some("'active'".to_string()),
+            attributes: AttributeMap::new(),
+        }];
+        status.truncated = Some(TruncatedMembers {
+            cases: 3,
+            ..TruncatedMembers::default()
+        });
+
+        vec![class, status]
+    }
+
+    #[test]
+    fn test_php_cache_round_trip() {
+        for pretty in [true, false] {
+            let content = render_cache(&sample(), "php", pretty, None).unwrap();
+
+            let classes =
+                parse_cache(std::str::from_utf8(&content).unwrap(), Path::new("/")).unwrap();
+
+            assert_eq!(classes, sample(), "pretty: {pretty}");
+        }
+    }
+
+    #[test]
+    fn test_grouped_php_cache_round_trip() {
+        for group_by in [CacheGroup::Namespace, CacheGroup::Attribute] {
+            let layout = CacheLayout {
+                group_by: Some(group_by),
+                sort: Some(CacheSort::Fqcn),
+            };
+            let mut content = Vec::new();
+            render_php_cache_with_layout(&sample(), &mut content, true, None, layout).unwrap();
+
+            let mut classes =
+                parse_cache(std::str::from_utf8(&content).unwrap(), Path::new("/")).unwrap();
+            let mut expected = sample();
+            classes.sort_by(|a, b| a.fqcn.cmp(&b.fqcn));
+            expected.sort_by(|a, b| a.fqcn.cmp(&b.fqcn));
+
+            assert_eq!(classes, expected, "group_by: {group_by:?}");
+        }
+    }
+
+    #[test]
+    fn test_json_cache_round_trip() {
+        let content = render_cache(&sample(), "json", false, None).unwrap();
+
+        let classes = parse_cache(std::str::from_utf8(&content).unwrap(), Path::new("/")).unwrap();
+
+        assert_eq!(classes, sample());
+    }
+
+    #[test]
+    fn test_php_cache_resolves_relative_paths() {
+        let mut relative = sample();
+        relativize_paths(&mut relative, Path::new("/project"));
+        let output = Path::new("/project/var/cache/classes.php");
+        let root = php_root_expr(output, Path::new("/project"));
+        let mut content = Vec::new();
+        render_php_cache_with_root(&relative, &mut content, true, Some(&root)).unwrap();
+
+        let classes = parse_php_cache(
+            std::str::from_utf8(&content).unwrap(),
+            output.parent().unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(classes[1].file, PathBuf::from("/project/src/Status.php"));
+    }
+
+    #[test]
+    fn test_rejects_foreign_php() {
+        let err = parse_php_cache("<?php\nreturn [1, 2", Path::new("/")).unwrap_err();
+
+        assert!(err.to_string().contains("Expected"));
+    }
+}
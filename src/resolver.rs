@@ -0,0 +1,277 @@
+use crate::metadata::{AttributeMap, PhpClassMetadata};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Post-scan hierarchy resolution over the full class map
+///
+/// Fills `ancestors` (parent chain, nearest first) and `resolved_implements`
+/// (every interface implemented directly, through parents or through
+/// interface inheritance) for each declaration. Resolution stays within the
+/// scanned code: a parent or interface that was not scanned is still listed
+/// but its own hierarchy is unknown. Cycles are broken silently.
+pub fn resolve_hierarchy(metadata_list: &mut [PhpClassMetadata]) {
+    let index: HashMap<&str, &PhpClassMetadata> =
+        metadata_list.iter().map(|m| (m.fqcn.as_str(), m)).collect();
+
+    let resolved: Vec<(Vec<String>, Vec<String>)> = metadata_list
+        .iter()
+        .map(|m| {
+            let ancestors = if m.kind == "class" {
+                ancestor_chain(&index, m)
+            } else {
+                Vec::new()
+            };
+
+            // Interfaces inherit from their parent interfaces; classes also
+            // from every ancestor's implements list
+            let mut direct: Vec<&str> = m.implements.iter().map(String::as_str).collect();
+            if m.kind == "interface" {
+                direct.extend(m.extends.as_deref());
+            }
+            for ancestor in &ancestors {
+                if let Some(parent) = index.get(ancestor.as_str()) {
+                    direct.extend(parent.implements.iter().map(String::as_str));
+                }
+            }
+
+            let mut interfaces = BTreeSet::new();
+            for interface in direct {
+                collect_interfaces(&index, interface, &mut interfaces);
+            }
+            interfaces.remove(m.fqcn.as_str());
+
+            (
+                ancestors,
+                interfaces.into_iter().map(str::to_string).collect(),
+            )
+        })
+        .collect();
+
+    for (m, (ancestors, interfaces)) in metadata_list.iter_mut().zip(resolved) {
+        m.ancestors = Some(ancestors);
+        m.resolved_implements = Some(interfaces);
+    }
+}
+
+/// Merge class-level attributes up the hierarchy into `inherited_attributes`
+///
+/// Sources are visited in reflection order: the class's own traits, then each
+/// ancestor followed by its traits (nearest first), then every resolved
+/// interface. The first source declaring an attribute wins, and attributes
+/// declared on the class itself are never shadowed. Runs the hierarchy
+/// resolution first if it has not been applied yet.
+pub fn propagate_attributes(metadata_list: &mut [PhpClassMetadata]) {
+    if metadata_list.iter().any(|m| m.ancestors.is_none()) {
+        resolve_hierarchy(metadata_list);
+    }
+
+    let index: HashMap<&str, &PhpClassMetadata> =
+        metadata_list.iter().map(|m| (m.fqcn.as_str(), m)).collect();
+
+    let inherited: Vec<AttributeMap> = metadata_list
+        .iter()
+        .map(|m| {
+            let mut sources = Vec::new();
+            let mut seen = HashSet::from([m.fqcn.as_str()]);
+            collect_traits(&index, m, &mut seen, &mut sources);
+            for ancestor in m.ancestors.iter().flatten() {
+                if let Some(parent) = index.get(ancestor.as_str())
+                    && seen.insert(ancestor.as_str())
+                {
+                    sources.push(*parent);
+                    collect_traits(&index, parent, &mut seen, &mut sources);
+                }
+            }
+            for interface in m.resolved_implements.iter().flatten() {
+                if let Some(meta) = index.get(interface.as_str())
+                    && seen.insert(interface.as_str())
+                {
+                    sources.push(*meta);
+                }
+            }
+
+            let mut attributes = AttributeMap::new();
+            for source in sources {
+                for (name, instances) in &source.attributes {
+                    if !m.attributes.contains_key(name) && !attributes.contains_key(name) {
+                        attributes.insert(name.clone(), instances.clone());
+                    }
+                }
+            }
+            attributes
+        })
+        .collect();
+
+    for (m, attributes) in metadata_list.iter_mut().zip(inherited) {
+        m.inherited_attributes = Some(attributes);
+    }
+}
+
+/// Append the traits used by `class` (depth first, including nested trait uses) to `out`
+fn collect_traits<'a>(
+    index: &HashMap<&str, &'a PhpClassMetadata>, class: &'a PhpClassMetadata,
+    seen: &mut HashSet<&'a str>, out: &mut Vec<&'a PhpClassMetadata>,
+) {
+    for name in &class.traits {
+        if let Some(meta) = index.get(name.as_str())
+            && seen.insert(name.as_str())
+        {
+            out.push(*meta);
+            collect_traits(index, meta, seen, out);
+        }
+    }
+}
+
+/// Parent chain of a class, nearest first
+fn ancestor_chain(
+    index: &HashMap<&str, &PhpClassMetadata>, class: &PhpClassMetadata,
+) -> Vec<String> {
+    let mut chain = Vec::new();
+    let mut seen = HashSet::from([class.fqcn.as_str()]);
+    let mut current = class.extends.as_deref();
+
+    while let Some(parent) = current {
+        if !seen.insert(parent) {
+            break;
+        }
+        chain.push(parent.to_string());
+        current = index.get(parent).and_then(|p| p.extends.as_deref());
+    }
+
+    chain
+}
+
+/// Add `interface` and all interfaces it extends to `out`
+fn collect_interfaces<'a>(
+    index: &HashMap<&str, &'a PhpClassMetadata>, interface: &'a str, out: &mut BTreeSet<&'a str>,
+) {
+    if !out.insert(interface) {
+        return;
+    }
+    if let Some(meta) = index.get(interface) {
+        for parent in meta.extends.iter().chain(&meta.implements) {
+            collect_interfaces(index, parent, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::metadata::AttributeArgument;
+    use std::path::PathBuf;
+
+    fn declare(
+        fqcn: &str, kind: &str, extends: Option<&str>, implements: &[&str],
+    ) -> PhpClassMetadata {
+        let mut m = PhpClassMetadata::new(
+            fqcn.to_string(),
+            PathBuf::from("/src/x.php"),
+            kind.to_string(),
+        );
+        m.extends = extends.map(str::to_string);
+        m.implements = implements.iter().map(|s| (*s).to_string()).collect();
+        m
+    }
+
+    #[test]
+    fn test_resolve_hierarchy_flattens_parents_and_interfaces() {
+        let mut list = vec![
+            declare("\\App\\Countable", "interface", None, &[]),
+            declare(
+                "\\App\\Collection",
+                "interface",
+                Some("\\App\\Countable"),
+                &[],
+            ),
+            declare(
+                "\\App\\Base",
+                "class",
+                Some("\\Vendor\\Model"),
+                &["\\App\\Collection"],
+            ),
+            declare(
+                "\\App\\Users",
+                "class",
+                Some("\\App\\Base"),
+                &["\\JsonSerializable"],
+            ),
+        ];
+
+        resolve_hierarchy(&mut list);
+
+        let users = &list[3];
+        assert_eq!(
+            users.ancestors.as_deref().unwrap(),
+            ["\\App\\Base", "\\Vendor\\Model"]
+        );
+        assert_eq!(
+            users.resolved_implements.as_deref().unwrap(),
+            [
+                "\\App\\Collection",
+                "\\App\\Countable",
+                "\\JsonSerializable"
+            ]
+        );
+
+        let collection = &list[1];
+        assert!(collection.ancestors.as_ref().unwrap().is_empty());
+        assert_eq!(
+            collection.resolved_implements.as_deref().unwrap(),
+            ["\\App\\Countable"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_hierarchy_breaks_cycles() {
+        let mut list = vec![
+            declare("\\App\\A", "class", Some("\\App\\B"), &[]),
+            declare("\\App\\B", "class", Some("\\App\\A"), &[]),
+        ];
+
+        resolve_hierarchy(&mut list);
+
+        assert_eq!(list[0].ancestors.as_deref().unwrap(), ["\\App\\B"]);
+        assert_eq!(list[1].ancestors.as_deref().unwrap(), ["\\App\\A"]);
+    }
+
+    fn with_attribute(mut m: PhpClassMetadata, name: &str, arg: &str) -> PhpClassMetadata {
+        m.attributes.insert(
+            name.to_string(),
+            vec![vec![AttributeArgument::Positional(arg.to_string())]],
+        );
+        m
+    }
+
+    #[test]
+    fn test_propagate_attributes_from_parents_traits_and_interfaces() {
+        let mut timestamps = declare("\\App\\Timestamps", "trait", None, &[]);
+        timestamps = with_attribute(timestamps, "\\App\\Audited", "'trait'");
+        let contract = with_attribute(
+            declare("\\App\\Contract", "interface", None, &[]),
+            "\\App\\Route",
+            "'/contract'",
+        );
+        let mut base = declare("\\App\\Base", "class", None, &["\\App\\Contract"]);
+        base = with_attribute(base, "\\App\\Table", "'base'");
+        base = with_attribute(base, "\\App\\Audited", "'base'");
+        let mut child = declare("\\App\\Child", "class", Some("\\App\\Base"), &[]);
+        child.traits = vec!["\\App\\Timestamps".to_string()];
+        child = with_attribute(child, "\\App\\Table", "'child'");
+
+        let mut list = vec![timestamps, contract, base, child];
+        propagate_attributes(&mut list);
+
+        let child = &list[3];
+        let inherited = child.inherited_attributes.as_ref().unwrap();
+        // Own attributes are not shadowed, traits win over parents
+        assert!(!inherited.contains_key("\\App\\Table"));
+        assert_eq!(
+            inherited["\\App\\Audited"],
+            vec![vec![AttributeArgument::Positional("'trait'".to_string())]]
+        );
+        assert!(inherited.contains_key("\\App\\Route"));
+        assert_eq!(child.attributes.len(), 1);
+        assert!(list[0].inherited_attributes.as_ref().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,164 @@
+//! Retries of transient filesystem errors
+//!
+//! Network filesystems report short-lived failures such as `ESTALE` (a file
+//! handle invalidated by a rename on the server) or `EAGAIN` for files that
+//! are perfectly readable a moment later. File reads and metadata lookups of
+//! a scan are retried with exponential backoff before the file counts as
+//! failed; other errors (missing file, permission denied) fail at once.
+
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+use tracing::debug;
+
+/// Retry settings (`io_retries`, `io_retry_delay_ms`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts after the first one
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every further one
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            delay: Duration::from_millis(50),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Run `op` on `path`, retrying transient errors
+    ///
+    /// # Errors
+    ///
+    /// Returns the first non-transient error, or the last transient one once
+    /// the retries are used up.
+    pub fn run<T>(&self, path: &Path, mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+        let mut delay = self.delay;
+        let mut attempt = 0;
+        loop {
+            match op() {
+                Err(e) if attempt < self.retries && is_transient(&e) => {
+                    attempt += 1;
+                    debug!(
+                        path = %path.display(),
+                        attempt,
+                        "Transient IO error on {}: {e}, retrying in {delay:?}",
+                        path.display()
+                    );
+                    std::thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                },
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Process-wide policy, set once from the configuration
+static RETRIES: AtomicU32 = AtomicU32::new(3);
+static DELAY_MS: AtomicU64 = AtomicU64::new(50);
+
+/// Set the policy for every following scan
+pub fn set_policy(policy: RetryPolicy) {
+    RETRIES.store(policy.retries, Ordering::Relaxed);
+    DELAY_MS.store(
+        u64::try_from(policy.delay.as_millis()).unwrap_or(u64::MAX),
+        Ordering::Relaxed,
+    );
+}
+
+/// Current retry policy
+#[must_use]
+pub fn policy() -> RetryPolicy {
+    RetryPolicy {
+        retries: RETRIES.load(Ordering::Relaxed),
+        delay: Duration::from_millis(DELAY_MS.load(Ordering::Relaxed)),
+    }
+}
+
+/// Run `op` on `path` with the current policy
+///
+/// # Errors
+///
+/// See [`RetryPolicy::run`].
+pub fn with_retry<T>(path: &Path, op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    policy().run(path, op)
+}
+
+/// Whether `path` is to be scanned as a file
+///
+/// Unlike [`Path::is_file`], a file whose metadata keeps failing with an
+/// error other than `NotFound` counts as one, so the scan reports the error
+/// instead of treating the file as deleted.
+#[must_use]
+pub fn may_be_file(path: &Path) -> bool {
+    match with_retry(path, || path.metadata()) {
+        Ok(metadata) => metadata.is_file(),
+        Err(e) => e.kind() != io::ErrorKind::NotFound,
+    }
+}
+
+/// Whether `error` is worth retrying
+#[must_use]
+pub fn is_transient(error: &io::Error) -> bool {
+    #[cfg(unix)]
+    if matches!(
+        error.raw_os_error(),
+        Some(libc::ESTALE | libc::EAGAIN | libc::EINTR | libc::ETIMEDOUT)
+    ) {
+        return true;
+    }
+    matches!(
+        error.kind(),
+        io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: RetryPolicy = RetryPolicy {
+        retries: 2,
+        delay: Duration::from_millis(1),
+    };
+
+    #[cfg(unix)]
+    #[test]
+    fn test_transient_errors_are_retried() {
+        let mut calls = 0;
+        let result = POLICY.run(Path::new("/nfs/User.php"), || {
+            calls += 1;
+            if calls < 3 {
+                Err(io::Error::from_raw_os_error(libc::ESTALE))
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.ok(), Some(3));
+
+        let mut calls = 0;
+        let result: io::Result<()> = POLICY.run(Path::new("/nfs/User.php"), || {
+            calls += 1;
+            Err(io::Error::from_raw_os_error(libc::EAGAIN))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_other_errors_fail_at_once() {
+        let mut calls = 0;
+        let result: io::Result<()> = POLICY.run(Path::new("/src/Missing.php"), || {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+}
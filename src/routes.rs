@@ -0,0 +1,429 @@
+//! Route table export (`discovery:export routes`)
+//!
+//! Extracts method-level route attributes into a compact PHP array that a
+//! framework can load directly instead of reflecting over every controller
+//! on boot. Presets describe which attributes declare routes and the layout
+//! of the table:
+//!
+//! - `symfony`: `#[Route]` with class-level path and name prefixes, keyed by
+//!   route name (unnamed routes get Symfony's default name)
+//! - `laravel`: `spatie/laravel-route-attributes` (`#[Get]`, `#[Post]`, ...,
+//!   `#[Any]`, `#[Route]`, class-level `#[Prefix]`), as a list of
+//!   `Route::match()` arguments
+//!
+//! Routes keep their declaration order (classes sorted by name), since both
+//! frameworks match in registration order. Symfony `priority` is honoured.
+
+use crate::metadata::PhpClassMetadata;
+use crate::openapi::{argument, join_paths, matches_attribute, php_string, php_string_list};
+use crate::writer::php_literal;
+use anyhow::{Context, Result, bail};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Laravel `Router::$verbs`
+const ALL_VERBS: &[&str] = &["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"];
+
+/// Framework layout of the route table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoutePreset {
+    Symfony,
+    Laravel,
+}
+
+impl FromStr for RoutePreset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "symfony" => Ok(Self::Symfony),
+            "laravel" => Ok(Self::Laravel),
+            _ => bail!("Unknown route preset '{s}' (expected symfony or laravel)"),
+        }
+    }
+}
+
+/// Where an attribute keeps its HTTP methods
+#[derive(Debug, Clone, Copy)]
+enum Methods {
+    /// Implied by the attribute (`#[Get]`)
+    Fixed(&'static [&'static str]),
+    /// Named argument, optionally also accepted at a positional index
+    Argument(&'static str, Option<usize>),
+}
+
+/// Attribute declaring a route
+#[derive(Debug, Clone, Copy)]
+struct RouteShape {
+    attribute: &'static str,
+    /// Named argument holding the path, and its positional index
+    path: (&'static str, usize),
+    methods: Methods,
+}
+
+const SYMFONY_ROUTES: &[RouteShape] = &[RouteShape {
+    attribute: "Route",
+    path: ("path", 0),
+    methods: Methods::Argument("methods", None),
+}];
+
+const LARAVEL_ROUTES: &[RouteShape] = &[
+    laravel_verb("Spatie\\RouteAttributes\\Attributes\\Get", &["GET", "HEAD"]),
+    laravel_verb("Spatie\\RouteAttributes\\Attributes\\Post", &["POST"]),
+    laravel_verb("Spatie\\RouteAttributes\\Attributes\\Put", &["PUT"]),
+    laravel_verb("Spatie\\RouteAttributes\\Attributes\\Patch", &["PATCH"]),
+    laravel_verb("Spatie\\RouteAttributes\\Attributes\\Delete", &["DELETE"]),
+    laravel_verb("Spatie\\RouteAttributes\\Attributes\\Options", &["OPTIONS"]),
+    laravel_verb("Spatie\\RouteAttributes\\Attributes\\Any", ALL_VERBS),
+    RouteShape {
+        attribute: "Spatie\\RouteAttributes\\Attributes\\Route",
+        path: ("uri", 1),
+        methods: Methods::Argument("methods", Some(0)),
+    },
+];
+
+const fn laravel_verb(attribute: &'static str, verbs: &'static [&'static str]) -> RouteShape {
+    RouteShape {
+        attribute,
+        path: ("uri", 0),
+        methods: Methods::Fixed(verbs),
+    }
+}
+
+impl RoutePreset {
+    const fn shapes(self) -> &'static [RouteShape] {
+        match self {
+            Self::Symfony => SYMFONY_ROUTES,
+            Self::Laravel => LARAVEL_ROUTES,
+        }
+    }
+
+    /// Class-level attribute contributing path and name prefixes, and its path argument
+    const fn class_prefix(self) -> (&'static str, &'static str) {
+        match self {
+            Self::Symfony => ("Route", "path"),
+            Self::Laravel => ("Spatie\\RouteAttributes\\Attributes\\Prefix", "prefix"),
+        }
+    }
+}
+
+/// One exported route
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteEntry {
+    pub name: Option<String>,
+    /// Path as the framework expects it (Laravel URIs have no leading slash)
+    pub path: String,
+    /// Uppercase HTTP methods; empty means any method
+    pub methods: Vec<String>,
+    /// Controller FQCN without the leading backslash
+    pub controller: String,
+    pub action: String,
+}
+
+/// Outcome of a route extraction
+#[derive(Debug, Clone, Default)]
+pub struct RouteTable {
+    pub routes: Vec<RouteEntry>,
+    /// `Class::method` handlers whose path is not a literal
+    pub skipped: Vec<String>,
+}
+
+/// Collect the routes declared in `metadata_list`
+#[must_use]
+pub fn extract_routes(metadata_list: &[PhpClassMetadata], preset: RoutePreset) -> RouteTable {
+    let mut classes: Vec<&PhpClassMetadata> = metadata_list.iter().collect();
+    classes.sort_by(|a, b| a.fqcn.cmp(&b.fqcn));
+
+    let mut prioritized = Vec::new();
+    let mut skipped = Vec::new();
+
+    for class in classes {
+        let controller = class.fqcn.trim_start_matches('\\').to_string();
+
+        let (prefix_attribute, prefix_key) = preset.class_prefix();
+        let class_args = class
+            .attributes
+            .iter()
+            .find(|(name, _)| matches_attribute(name, prefix_attribute))
+            .and_then(|(_, instances)| instances.first());
+        let class_literal = |key: &str, position: Option<usize>| {
+            class_args
+                .and_then(|args| argument(args, key, position))
+                .and_then(php_string)
+                .unwrap_or_default()
+        };
+        let path_prefix = class_literal(prefix_key, Some(0));
+        let name_prefix = class_literal("name", None);
+
+        for method in &class.methods {
+            let mut index = 0;
+            for (name, instances) in &method.attributes {
+                let Some(shape) = preset
+                    .shapes()
+                    .iter()
+                    .find(|shape| matches_attribute(name, shape.attribute))
+                else {
+                    continue;
+                };
+
+                for args in instances {
+                    let (path_key, path_position) = shape.path;
+                    let Some(path) =
+                        argument(args, path_key, Some(path_position)).and_then(php_string)
+                    else {
+                        skipped.push(format!("{}::{}", class.fqcn, method.name));
+                        continue;
+                    };
+
+                    let methods: Vec<String> = match shape.methods {
+                        Methods::Fixed(verbs) => verbs.iter().map(ToString::to_string).collect(),
+                        Methods::Argument(key, position) => argument(args, key, position)
+                            .and_then(php_string_list)
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|m| m.to_uppercase())
+                            .collect(),
+                    };
+                    let name = argument(args, "name", None).and_then(php_string);
+                    let priority = argument(args, "priority", None)
+                        .and_then(|p| p.trim().parse::<i64>().ok())
+                        .unwrap_or(0);
+
+                    let route = match preset {
+                        RoutePreset::Symfony => RouteEntry {
+                            name: Some(format!(
+                                "{name_prefix}{}",
+                                name.unwrap_or_else(|| symfony_default_name(
+                                    &controller,
+                                    &method.name,
+                                    index
+                                ))
+                            )),
+                            path: symfony_path(&path_prefix, &path),
+                            methods,
+                            controller: controller.clone(),
+                            action: method.name.clone(),
+                        },
+                        RoutePreset::Laravel => RouteEntry {
+                            name: name.map(|name| format!("{name_prefix}{name}")),
+                            path: laravel_uri(&path_prefix, &path),
+                            methods: laravel_methods(methods),
+                            controller: controller.clone(),
+                            action: method.name.clone(),
+                        },
+                    };
+                    prioritized.push((priority, route));
+                    index += 1;
+                }
+            }
+        }
+    }
+
+    // Stable: equal priorities keep declaration order
+    prioritized.sort_by_key(|(priority, _)| std::cmp::Reverse(*priority));
+
+    RouteTable {
+        routes: prioritized.into_iter().map(|(_, route)| route).collect(),
+        skipped,
+    }
+}
+
+/// Route name Symfony generates for an unnamed route
+/// (`App\Controller\BlogController::show` → `app_blog_show`)
+fn symfony_default_name(controller: &str, action: &str, index: usize) -> String {
+    let mut name = format!("{}_{action}", controller.replace('\\', "_")).to_lowercase();
+    name = name.replace("bundle_", "_").replace("controller_", "_");
+    if let Some(stripped) = name.strip_suffix("action") {
+        name = stripped.to_string();
+    }
+    while name.contains("__") {
+        name = name.replace("__", "_");
+    }
+    if index > 0 {
+        let _ = write!(name, "_{index}");
+    }
+    name
+}
+
+/// Symfony concatenates prefix and path as-is, then ensures a leading slash
+fn symfony_path(prefix: &str, path: &str) -> String {
+    format!(
+        "/{}",
+        format!("{prefix}{path}").trim().trim_start_matches('/')
+    )
+}
+
+/// Laravel URI: prefix joined with `/`, without a leading slash (`/` for the root)
+fn laravel_uri(prefix: &str, uri: &str) -> String {
+    let joined = join_paths(prefix, uri);
+    match joined.trim_matches('/') {
+        "" => "/".to_string(),
+        uri => uri.to_string(),
+    }
+}
+
+/// `Router::match()` semantics: GET also answers HEAD, no methods means GET
+fn laravel_methods(mut methods: Vec<String>) -> Vec<String> {
+    if methods.is_empty() {
+        methods.push("GET".to_string());
+    }
+    if methods.iter().any(|m| m == "GET") && !methods.iter().any(|m| m == "HEAD") {
+        methods.push("HEAD".to_string());
+    }
+    methods
+}
+
+/// Render the route table as a PHP file returning an array
+#[must_use]
+pub fn render_route_table(table: &RouteTable, preset: RoutePreset) -> String {
+    let mut out = String::from("<?php declare(strict_types=1);\n\n");
+    let (label, layout) = match preset {
+        RoutePreset::Symfony => ("symfony", "name => [path, methods, controller]"),
+        RoutePreset::Laravel => ("laravel", "[methods, uri, action, name]"),
+    };
+    let _ = writeln!(
+        out,
+        "// Generated by `aurynx discovery:export routes --preset {label}`: {layout}"
+    );
+    out.push_str("return [\n");
+
+    for route in &table.routes {
+        let methods = route
+            .methods
+            .iter()
+            .map(|m| php_literal(m))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let _ = match preset {
+            RoutePreset::Symfony => writeln!(
+                out,
+                "    {} => ['path' => {}, 'methods' => [{methods}], 'controller' => {}],",
+                php_literal(route.name.as_deref().unwrap_or_default()),
+                php_literal(&route.path),
+                php_literal(&format!("{}::{}", route.controller, route.action)),
+            ),
+            RoutePreset::Laravel => writeln!(
+                out,
+                "    ['methods' => [{methods}], 'uri' => {}, 'action' => {}, 'name' => {}],",
+                php_literal(&route.path),
+                php_literal(&format!("{}@{}", route.controller, route.action)),
+                route
+                    .name
+                    .as_deref()
+                    .map_or_else(|| "null".to_string(), php_literal),
+            ),
+        };
+    }
+
+    out.push_str("];\n");
+    out
+}
+
+/// Write the rendered route table to `output_path` unless it already holds it
+///
+/// # Errors
+///
+/// Returns an error if the route table cannot be written.
+pub fn write_route_table(
+    table: &RouteTable, preset: RoutePreset, output_path: &Path,
+) -> Result<()> {
+    let content = render_route_table(table, preset);
+    crate::writer::write_if_changed(output_path, content.as_bytes())
+        .with_context(|| format!("Failed to write route table {}", output_path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::parser::tests::extract;
+
+    #[test]
+    fn test_symfony_route_table() {
+        let metadata = extract(
+            "/src/Controller.php",
+            r"<?php
+namespace App\Controller;
+use Symfony\Component\Routing\Attribute\Route;
+
+#[Route('/blog', name: 'blog_')]
+class BlogController {
+    #[Route('/{slug}', methods: ['GET'])]
+    public function showAction(string $slug): void {}
+
+    #[Route('', name: 'index')]
+    public function index(): void {}
+
+    #[Route('/latest', name: 'latest', priority: 10)]
+    public function latest(): void {}
+
+    #[Route(self::PATH)]
+    public function dynamic(): void {}
+}
+",
+        );
+
+        let table = extract_routes(&metadata, RoutePreset::Symfony);
+        let names: Vec<_> = table
+            .routes
+            .iter()
+            .map(|r| r.name.clone().unwrap())
+            .collect();
+
+        assert_eq!(names, ["blog_latest", "blog_app_blog_show", "blog_index"]);
+        assert_eq!(table.routes[1].path, "/blog/{slug}");
+        assert_eq!(table.routes[1].methods, ["GET"]);
+        assert_eq!(table.routes[2].path, "/blog");
+        assert_eq!(
+            table.skipped,
+            ["\\App\\Controller\\BlogController::dynamic"]
+        );
+
+        let php = render_route_table(&table, RoutePreset::Symfony);
+        assert!(php.contains(
+            "    'blog_index' => ['path' => '/blog', 'methods' => [], 'controller' => 'App\\\\Controller\\\\BlogController::index'],\n"
+        ));
+    }
+
+    #[test]
+    fn test_laravel_route_table() {
+        let metadata = extract(
+            "/src/Controller.php",
+            r"<?php
+namespace App\Http\Controllers;
+use Spatie\RouteAttributes\Attributes\Get;
+use Spatie\RouteAttributes\Attributes\Post;
+use Spatie\RouteAttributes\Attributes\Prefix;
+use Spatie\RouteAttributes\Attributes\Route;
+
+#[Prefix('api/users')]
+class UserController {
+    #[Get('/', name: 'users.index')]
+    public function index(): void {}
+
+    #[Post('{user}/avatar')]
+    public function avatar(): void {}
+
+    #[Route(['PUT', 'patch'], '{user}')]
+    public function update(): void {}
+}
+",
+        );
+
+        let table = extract_routes(&metadata, RoutePreset::Laravel);
+
+        assert_eq!(table.routes.len(), 3);
+        assert_eq!(table.routes[0].path, "api/users");
+        assert_eq!(table.routes[0].methods, ["GET", "HEAD"]);
+        assert_eq!(table.routes[1].path, "api/users/{user}/avatar");
+        assert_eq!(table.routes[2].methods, ["PUT", "PATCH"]);
+
+        let php = render_route_table(&table, RoutePreset::Laravel);
+        assert!(php.contains(
+            "    ['methods' => ['GET', 'HEAD'], 'uri' => 'api/users', 'action' => 'App\\\\Http\\\\Controllers\\\\UserController@index', 'name' => 'users.index'],\n"
+        ));
+        assert!(php.contains("'name' => null],"));
+    }
+}
@@ -0,0 +1,366 @@
+//! Scan report artifact (`report`)
+//!
+//! One-shot scans print their statistics, skipped files and timing for a
+//! human at a terminal. With `report` enabled they also write them as JSON
+//! to `aurynx.report.json` next to the cache, whether or not stdout is a
+//! terminal, so build pipelines can archive one report per run. Failed files
+//! come from the manifest, which records every file that could not be
+//! scanned, whichever scan path (full, incremental, `--changed-since`) ran.
+//! Syntax errors whose surrounding declarations were recovered are not
+//! failures, so the scanner records them process-wide while a report is being
+//! collected, like the timings of `--profile-files`.
+//!
+//! The [`Breakdown`] of the declarations by kind and top-level namespace is
+//! also printed below the class count of every one-shot scan.
+
+use crate::diagnostic::SyntaxDiagnostic;
+use crate::error::code;
+use crate::exporters::ExporterOutcome;
+use crate::incremental::Manifest;
+use crate::member_limit::OversizedClass;
+use crate::metadata::PhpClassMetadata;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Report file name
+pub const REPORT_FILE: &str = "aurynx.report.json";
+
+/// Namespace key of declarations outside any namespace
+pub const GLOBAL_NAMESPACE: &str = "(global)";
+
+static RECORDING: AtomicBool = AtomicBool::new(false);
+static SYNTAX_ERRORS: Mutex<Vec<SyntaxError>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanReport {
+    /// aurynx version that wrote the report
+    pub version: &'static str,
+    /// `full`, `incremental` or `changed_since`
+    pub mode: &'static str,
+    /// `ok`, or `failed` when the `on_error: fail` policy aborted the scan
+    pub status: &'static str,
+    pub stats: ScanStats,
+    /// Files left out without being parsed (over `max_file_size_mb`)
+    pub skipped: Vec<FileProblem>,
+    /// Files that could not be read or parsed
+    pub errors: Vec<FileProblem>,
+    /// Syntax errors in files parsed by this run, sorted by path
+    pub syntax_errors: Vec<SyntaxError>,
+    /// Classes cut to `max_class_members`, in scan order
+    pub truncated: Vec<OversizedClass>,
+    /// Outcome of each configured exporter, in order
+    pub exporters: Vec<ExporterOutcome>,
+    pub timing: ScanTiming,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ScanStats {
+    /// PHP files known to the manifest
+    pub files: usize,
+    /// Declarations found by the scan
+    pub classes: usize,
+    /// Declarations left out of the cache by `output_exclude`
+    pub excluded: usize,
+    pub skipped: usize,
+    pub errors: usize,
+    pub syntax_errors: usize,
+    #[serde(flatten)]
+    pub breakdown: Breakdown,
+}
+
+/// Declarations found by the scan, per kind and per top-level namespace
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Breakdown {
+    /// `class`, `interface`, `trait` or `enum`
+    pub kinds: BTreeMap<String, usize>,
+    /// First namespace segment, [`GLOBAL_NAMESPACE`] without a namespace
+    pub namespaces: BTreeMap<String, usize>,
+}
+
+/// A file that could not be scanned, with the reason
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileProblem {
+    pub path: String,
+    /// Stable error code (see [`crate::error::code`])
+    pub code: String,
+    pub message: String,
+    /// Consecutive runs the file has failed in
+    pub attempts: u32,
+}
+
+/// A syntax error the scan recovered from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SyntaxError {
+    pub path: String,
+    /// 1-based line of the error
+    pub line: usize,
+    pub message: String,
+}
+
+/// Wall-clock time per phase, in milliseconds
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ScanTiming {
+    /// Collecting and parsing files
+    pub scan_ms: u64,
+    /// Post-processing and writing the cache and its artifacts
+    pub write_ms: u64,
+    pub total_ms: u64,
+}
+
+impl Breakdown {
+    #[must_use]
+    pub fn of(classes: &[PhpClassMetadata]) -> Self {
+        let mut breakdown = Self::default();
+        for class in classes {
+            *breakdown.kinds.entry(class.kind.clone()).or_default() += 1;
+            let namespace = class
+                .fqcn
+                .trim_start_matches('\\')
+                .split_once('\\')
+                .map_or(GLOBAL_NAMESPACE, |(namespace, _)| namespace);
+            *breakdown
+                .namespaces
+                .entry(namespace.to_string())
+                .or_default() += 1;
+        }
+        breakdown
+    }
+
+    /// One line per breakdown, most frequent first (empty without classes)
+    #[must_use]
+    pub fn format(&self) -> String {
+        let mut output = String::new();
+        for (label, counts) in [("kind", &self.kinds), ("namespace", &self.namespaces)] {
+            if counts.is_empty() {
+                continue;
+            }
+            let mut counts: Vec<(&String, &usize)> = counts.iter().collect();
+            counts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            let counts: Vec<String> = counts
+                .into_iter()
+                .map(|(name, count)| format!("{count} {name}"))
+                .collect();
+            let _ = writeln!(output, "  by {label}: {}", counts.join(", "));
+        }
+        output
+    }
+}
+
+impl ScanReport {
+    /// Report of a scan that produced `manifest` and `classes`, with the
+    /// syntax errors recorded since [`start`]
+    #[must_use]
+    pub fn new(mode: &'static str, manifest: &Manifest, classes: &[PhpClassMetadata]) -> Self {
+        Self::with_syntax_errors(mode, manifest, classes, finish())
+    }
+
+    /// Report of one of several scans recording at the same time (workspace
+    /// projects), with the recorded syntax errors of the files in `manifest`
+    #[must_use]
+    pub fn for_files_of(
+        mode: &'static str, manifest: &Manifest, classes: &[PhpClassMetadata],
+    ) -> Self {
+        let mut recorded = SYNTAX_ERRORS.lock().unwrap_or_else(PoisonError::into_inner);
+        let (mut syntax_errors, others) = std::mem::take(&mut *recorded)
+            .into_iter()
+            .partition(|error: &SyntaxError| manifest.files.contains_key(&error.path));
+        *recorded = others;
+        drop(recorded);
+        sort(&mut syntax_errors);
+        Self::with_syntax_errors(mode, manifest, classes, syntax_errors)
+    }
+
+    fn with_syntax_errors(
+        mode: &'static str, manifest: &Manifest, classes: &[PhpClassMetadata],
+        syntax_errors: Vec<SyntaxError>,
+    ) -> Self {
+        let (skipped, errors): (Vec<FileProblem>, Vec<FileProblem>) = manifest
+            .failed_files()
+            .into_iter()
+            .map(|(path, entry)| FileProblem {
+                path: path.to_string(),
+                code: entry.error_code.clone().unwrap_or_default(),
+                message: entry.parse_error.clone().unwrap_or_default(),
+                attempts: entry.attempts,
+            })
+            .partition(|problem| problem.code == code::FILE_SIZE_LIMIT);
+
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            mode,
+            status: "ok",
+            stats: ScanStats {
+                files: manifest.files.len(),
+                classes: classes.len(),
+                excluded: 0,
+                skipped: skipped.len(),
+                errors: errors.len(),
+                syntax_errors: syntax_errors.len(),
+                breakdown: Breakdown::of(classes),
+            },
+            skipped,
+            errors,
+            syntax_errors,
+            truncated: Vec::new(),
+            exporters: Vec::new(),
+            timing: ScanTiming::default(),
+        }
+    }
+
+    /// Record the scan phase and the total run time
+    pub fn set_timing(&mut self, scan: Duration, total: Duration) {
+        self.timing = ScanTiming {
+            scan_ms: millis(scan),
+            write_ms: millis(total.saturating_sub(scan)),
+            total_ms: millis(total),
+        };
+    }
+}
+
+/// Start recording syntax errors, discarding earlier ones
+pub fn start() {
+    SYNTAX_ERRORS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+    RECORDING.store(true, Ordering::Relaxed);
+}
+
+/// Record a syntax error in `path` (ignored unless recording)
+pub fn record_syntax_error(path: &Path, diagnostic: &SyntaxDiagnostic) {
+    if RECORDING.load(Ordering::Relaxed) {
+        SYNTAX_ERRORS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(SyntaxError {
+                path: path.to_string_lossy().to_string(),
+                line: diagnostic.line,
+                message: diagnostic.summary(),
+            });
+    }
+}
+
+/// Stop recording and return the syntax errors since [`start`], sorted
+fn finish() -> Vec<SyntaxError> {
+    RECORDING.store(false, Ordering::Relaxed);
+    let mut syntax_errors =
+        std::mem::take(&mut *SYNTAX_ERRORS.lock().unwrap_or_else(PoisonError::into_inner));
+    sort(&mut syntax_errors);
+    syntax_errors
+}
+
+/// Stop recording and discard the syntax errors no report took
+pub fn stop() {
+    finish();
+}
+
+fn sort(syntax_errors: &mut [SyntaxError]) {
+    syntax_errors.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+}
+
+/// Atomically replace the report with `report` as pretty-printed JSON
+///
+/// # Errors
+///
+/// Returns an error if the report cannot be written.
+pub fn write_report(report: &ScanReport, output_path: &Path) -> Result<()> {
+    let content = serde_json::to_string_pretty(report)? + "\n";
+    crate::writer::write_if_changed(output_path, content.as_bytes())
+        .with_context(|| format!("Failed to write scan report {}", output_path.display()))?;
+    Ok(())
+}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::incremental::FileEntry;
+    use std::path::PathBuf;
+
+    fn failed(message: &str, error_code: &str) -> FileEntry {
+        FileEntry {
+            parse_error: Some(message.to_string()),
+            error_code: Some(error_code.to_string()),
+            attempts: 1,
+            ..FileEntry::default()
+        }
+    }
+
+    #[test]
+    fn test_report_separates_skipped_files_from_errors() {
+        let mut manifest = Manifest::default();
+        manifest
+            .files
+            .insert("/src/Ok.php".to_string(), FileEntry::default());
+        manifest.files.insert(
+            "/src/Huge.php".to_string(),
+            failed("File too large", code::FILE_SIZE_LIMIT),
+        );
+        manifest.files.insert(
+            "/src/Broken.php".to_string(),
+            failed("Could not read file", code::IO),
+        );
+
+        let mut report = ScanReport::new("full", &manifest, &[]);
+        report.set_timing(Duration::from_millis(40), Duration::from_millis(55));
+
+        assert_eq!(
+            report.stats,
+            ScanStats {
+                files: 3,
+                classes: 0,
+                excluded: 0,
+                skipped: 1,
+                errors: 1,
+                syntax_errors: 0,
+                breakdown: Breakdown::default(),
+            }
+        );
+        assert_eq!(report.skipped[0].path, "/src/Huge.php");
+        assert_eq!(report.errors[0].code, code::IO);
+        assert_eq!(report.timing.write_ms, 15);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&report).unwrap()).unwrap();
+        assert_eq!(json["status"], "ok");
+        assert_eq!(json["errors"][0]["message"], "Could not read file");
+    }
+
+    #[test]
+    fn test_breakdown_by_kind_and_top_level_namespace() {
+        let classes: Vec<PhpClassMetadata> = [
+            ("\\App\\Entity\\User", "class"),
+            ("\\App\\Contract", "interface"),
+            ("\\Tests\\UserTest", "class"),
+            ("\\Helper", "trait"),
+        ]
+        .into_iter()
+        .map(|(fqcn, kind)| {
+            PhpClassMetadata::new(fqcn.to_string(), PathBuf::from("/src/A.php"), kind.to_string())
+        })
+        .collect();
+
+        let breakdown = Breakdown::of(&classes);
+        assert_eq!(breakdown.namespaces["App"], 2);
+        assert_eq!(breakdown.namespaces[GLOBAL_NAMESPACE], 1);
+        assert_eq!(
+            breakdown.format(),
+            "  by kind: 2 class, 1 interface, 1 trait\n  by namespace: 2 App, 1 (global), 1 Tests\n"
+        );
+
+        let json = serde_json::to_value(ScanReport::new("full", &Manifest::default(), &classes))
+            .unwrap();
+        assert_eq!(json["stats"]["kinds"]["class"], 2);
+        assert_eq!(json["stats"]["namespaces"]["Tests"], 1);
+    }
+}
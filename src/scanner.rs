@@ -1,24 +1,126 @@
+use crate::custom_queries::CustomQueries;
+use crate::error::code;
+use crate::frontend::{FrontendParser, Frontends, LanguageFrontend};
 use crate::metadata::PhpClassMetadata;
-use crate::parser::PhpMetadataExtractor;
+use crate::php_version::{NewerSyntax, PhpVersion};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::overrides::{Override, OverrideBuilder};
 use ignore::{WalkBuilder, WalkState};
+use rayon::prelude::*;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
 use tracing::{error, warn};
 
+/// `on_error` policies, by name
+pub const ON_ERROR_POLICIES: [&str; 3] = ["skip", "warn", "fail"];
+
+/// How files that cannot be scanned affect a run (`on_error`)
+///
+/// Covers oversized, unreadable and unparsable files, and files with syntax
+/// errors. Failed files are always recorded in the manifest (and listed by
+/// the daemon's `getErrors`); the policy decides whether they are logged and
+/// whether they fail the run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnError {
+    /// Leave failed files out without logging them
+    Skip,
+    /// Leave failed files out and log each one
+    #[default]
+    Warn,
+    /// Fail the scan, or reject the daemon batch, when any file fails
+    Fail,
+}
+
+impl OnError {
+    /// Policy named `name` (one of [`ON_ERROR_POLICIES`])
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "skip" => Some(Self::Skip),
+            "warn" => Some(Self::Warn),
+            "fail" => Some(Self::Fail),
+            _ => None,
+        }
+    }
+}
+
+/// How files are read and parsed, from the configuration of the scanned project
+///
+/// Carried by the [`ExtractorPool`] a scan draws its extractors from, so
+/// projects scanned side by side (a workspace) each keep their own.
+#[derive(Clone, Default)]
+pub struct ScanOptions {
+    /// `on_error` policy
+    pub on_error: OnError,
+    /// Targeted PHP version; newer syntax is reported (`php_version`)
+    pub php_version: Option<PhpVersion>,
+    /// File kinds scanned (`frontends`)
+    pub frontends: Frontends,
+    /// Attach the file context to every class (`include_file_context`)
+    pub include_file_context: bool,
+    /// Custom queries run inside every class (`queries`)
+    pub queries: Option<Arc<CustomQueries>>,
+    /// Roots every scanned path must lie inside (`allowed_roots`)
+    pub allowed_roots: Option<Vec<PathBuf>>,
+}
+
+impl ScanOptions {
+    /// Check that every path in `paths` lies inside the `allowed_roots`, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns a config error naming the first path outside the roots.
+    pub fn check_paths(&self, paths: &[PathBuf]) -> crate::error::Result<()> {
+        self.allowed_roots
+            .as_deref()
+            .map_or(Ok(()), |roots| crate::allowed_roots::check(paths, roots))
+    }
+}
+
+/// Fail with the first of the `failed` files under [`OnError::Fail`]
+///
+/// # Errors
+///
+/// Returns a message naming the first failed file and the number of others.
+pub fn check_failures<'a>(
+    policy: OnError, failed: impl IntoIterator<Item = (&'a Path, &'a str)>,
+) -> std::result::Result<(), String> {
+    if policy != OnError::Fail {
+        return Ok(());
+    }
+    let mut failed = failed.into_iter();
+    let Some((path, message)) = failed.next() else {
+        return Ok(());
+    };
+    let others = failed.count();
+    let error = format!("Failed to scan {}: {message}", path.display());
+    if others > 0 {
+        return Err(format!("{error} (and {others} more files)"));
+    }
+    Err(error)
+}
+
 /// Default maximum file size allowed for parsing (10MB)
 /// Files larger than this will be skipped to prevent OOM
 /// Can be overridden via config file
 pub const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
 
-#[must_use] 
+#[must_use]
 pub fn scan_directory(paths: &[PathBuf], ignored: &[String]) -> Vec<PhpClassMetadata> {
-    scan_directory_with_limit(paths, ignored, DEFAULT_MAX_FILE_SIZE)
+    scan_directory_with_limit(paths, ignored, DEFAULT_MAX_FILE_SIZE, false)
 }
 
 /// Scan directory with custom file size limit
+///
+/// Large files are memory-mapped with `mmap`, which only callers that exit
+/// after the scan may ask for (see `crate::source`).
+#[must_use]
 pub fn scan_directory_with_limit(
-    paths: &[PathBuf], ignored: &[String], max_file_size: u64,
+    paths: &[PathBuf], ignored: &[String], max_file_size: u64, mmap: bool,
 ) -> Vec<PhpClassMetadata> {
     if paths.is_empty() {
         return vec![];
@@ -29,30 +131,20 @@ pub fn scan_directory_with_limit(
         builder.add(path);
     }
 
-    let mut overrides = ignore::overrides::OverrideBuilder::new(&paths[0]);
-    for ignore in ignored {
-        if let Err(e) = overrides.add(&format!("!{ignore}")) {
-            warn!("Invalid ignore pattern '{}': {}", ignore, e);
-        }
-    }
-
-    if let Ok(ov) = overrides.build() {
+    if let Some(ov) = ignore_matcher(paths, ignored) {
         builder.overrides(ov);
     }
 
     builder.git_ignore(true);
+    if let Some(threads) = crate::nice::threads() {
+        builder.threads(threads);
+    }
 
     let (tx, rx) = channel();
 
     builder.build_parallel().run(|| {
         let tx = tx.clone();
-        let mut extractor = match PhpMetadataExtractor::new() {
-            Ok(e) => Some(e),
-            Err(e) => {
-                error!("Error creating metadata extractor: {}", e);
-                None
-            },
-        };
+        let mut parsers = Parsers::default();
 
         Box::new(move |entry| {
             let entry = match entry {
@@ -65,41 +157,14 @@ pub fn scan_directory_with_limit(
             }
 
             let path = entry.path();
-            if path.extension().is_some_and(|ext| ext == "php")
-                && let Some(extractor) = &mut extractor {
-                    // Check file size before reading to prevent OOM
-                    match fs::metadata(path) {
-                        Ok(metadata) => {
-                            let file_size = metadata.len();
-                            if file_size > max_file_size {
-                                warn!(
-                                    "Skipping large file: {:?} ({:.2}MB exceeds limit of {:.2}MB)",
-                                    path,
-                                    file_size as f64 / 1024.0 / 1024.0,
-                                    max_file_size as f64 / 1024.0 / 1024.0
-                                );
-                                return WalkState::Continue;
-                            }
-                        },
-                        Err(e) => {
-                            warn!("Could not read metadata for {:?}: {}", path, e);
-                            return WalkState::Continue;
-                        },
-                    }
-
-                    if let Ok(content) = fs::read_to_string(path) {
-                        match extractor.extract_metadata(&content, path.to_path_buf()) {
-                            Ok(metadata_list) => {
-                                for metadata in metadata_list {
-                                    let _ = tx.send(metadata);
-                                }
-                            },
-                            Err(e) => {
-                                error!("Error parsing file {:?}: {}", path, e);
-                            },
-                        }
-                    }
+            if Frontends::default().is_source_file(path)
+                && let Ok(metadata_list) =
+                    extract_file(&mut parsers, path, max_file_size, mmap, &ScanOptions::default())
+            {
+                for metadata in metadata_list {
+                    let _ = tx.send(metadata);
                 }
+            }
 
             WalkState::Continue
         })
@@ -112,65 +177,485 @@ pub fn scan_directory_with_limit(
     results
 }
 
+/// Build the ignore-pattern matcher used for scans rooted at `paths[0]`
+#[must_use]
+pub fn ignore_matcher(paths: &[PathBuf], ignored: &[String]) -> Option<Override> {
+    let root = paths.first()?;
+    let mut overrides = OverrideBuilder::new(root);
+    for ignore in ignored {
+        if let Err(e) = overrides.add(&format!("!{ignore}")) {
+            warn!("Invalid ignore pattern '{}': {}", ignore, e);
+        }
+    }
+    overrides.build().ok()
+}
+
+/// Whether `path` or any of its parent directories below the matcher root
+/// matches an ignore pattern
+///
+/// Directories from the root up are not checked, so a project checked out
+/// under e.g. `build/` is not ignored by a `build` pattern.
+#[must_use]
+pub fn is_ignored(matcher: Option<&Override>, path: &Path) -> bool {
+    let Some(matcher) = matcher else {
+        return false;
+    };
+    path.ancestors()
+        .take_while(|a| !a.as_os_str().is_empty() && *a != matcher.path())
+        .any(|a| matcher.matched(a, a != path).is_ignore())
+}
+
+/// Drop the classes whose file matches `exclude` (`output_exclude` patterns)
+///
+/// The files are still scanned, so their classes take part in collision
+/// checks and hierarchy resolution; they only never reach an output.
+pub fn exclude_from_output(metadata: &mut Vec<PhpClassMetadata>, exclude: Option<&Override>) {
+    if exclude.is_some() {
+        metadata.retain(|class| !is_ignored(exclude, &class.file));
+    }
+}
+
+/// Built-in file name patterns for editor swap, backup and atomic-save temp files
+pub const EDITOR_TEMP_PATTERNS: &[&str] = &[
+    "*.swp",
+    "*.swo",
+    "*.swx",
+    "*~",
+    ".#*",
+    "\\#*#",
+    "*.tmp",
+    "*.crswap",
+    "*.kate-swp",
+    "*___jb_tmp___",
+    "*___jb_old___",
+    "4913",
+];
+
+/// Build the file name matcher for editor temp files
+///
+/// Combines [`EDITOR_TEMP_PATTERNS`] with user-configured patterns (gitignore
+/// syntax, matched against the file name only).
+#[must_use]
+pub fn temp_file_matcher(extra: &[String]) -> Gitignore {
+    let mut builder = GitignoreBuilder::new("");
+    for pattern in EDITOR_TEMP_PATTERNS
+        .iter()
+        .copied()
+        .chain(extra.iter().map(String::as_str))
+    {
+        if let Err(e) = builder.add_line(None, pattern) {
+            warn!("Invalid temp file pattern '{}': {}", pattern, e);
+        }
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+/// Whether `path` names an editor temp file
+#[must_use]
+pub fn is_temp_file(matcher: &Gitignore, path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| matcher.matched(Path::new(name), false).is_ignore())
+}
+
+/// Directories under `root` that a watcher should register
+///
+/// Applies the same ignore rules as the scanner (ignore patterns and
+/// `.gitignore`), so excluded trees like `vendor/` cost no watches.
+#[must_use]
+pub fn watch_directories(root: &Path, matcher: Option<&Override>) -> Vec<PathBuf> {
+    walk_filtered(root, matcher)
+        .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_dir()))
+        .map(ignore::DirEntry::into_path)
+        .collect()
+}
+
+/// PHP files under `root` that are not excluded by `matcher` or `.gitignore`
+#[must_use]
+pub fn php_files_under(root: &Path, matcher: Option<&Override>) -> Vec<PathBuf> {
+    source_files_under(root, matcher, Frontends::default())
+}
+
+/// Source files of the `frontends` (see [`crate::frontend`]) under `root`
+/// that are not excluded by `matcher` or `.gitignore`
+#[must_use]
+pub fn source_files_under(
+    root: &Path, matcher: Option<&Override>, frontends: Frontends,
+) -> Vec<PathBuf> {
+    walk_filtered(root, matcher)
+        .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+        .map(ignore::DirEntry::into_path)
+        .filter(|path| frontends.is_source_file(path))
+        .collect()
+}
+
+/// Sequential walk of `root` honouring an existing matcher and `.gitignore`
+fn walk_filtered(
+    root: &Path, matcher: Option<&Override>,
+) -> impl Iterator<Item = ignore::DirEntry> {
+    let mut builder = WalkBuilder::new(root);
+    if let Some(ov) = matcher {
+        builder.overrides(ov.clone());
+    }
+    builder.git_ignore(true);
+    builder.build().filter_map(std::result::Result::ok)
+}
+
 /// Scan only specific files (for incremental updates)
-#[must_use] 
+#[must_use]
 pub fn scan_files(files: &[PathBuf]) -> Vec<PhpClassMetadata> {
     scan_files_with_limit(files, DEFAULT_MAX_FILE_SIZE)
 }
 
+/// Result of scanning a single file
+#[derive(Debug, Clone)]
+pub struct FileScan {
+    /// Path of the scanned file
+    pub path: PathBuf,
+    /// Declarations extracted from the file
+    pub classes: Vec<PhpClassMetadata>,
+    /// Read or parse error (`None` when the file was processed successfully)
+    pub error: Option<ScanFailure>,
+}
+
+/// Why a file could not be scanned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanFailure {
+    /// Stable error code (see [`crate::error::code`])
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ScanFailure {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ScanFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
 /// Scan specific files with custom file size limit
+///
+/// Files are parsed in parallel on the rayon pool, with one extractor per
+/// worker thread, so rescanning a large changeset is as fast as a full scan.
+#[must_use]
 pub fn scan_files_with_limit(files: &[PathBuf], max_file_size: u64) -> Vec<PhpClassMetadata> {
-    let mut results = Vec::new();
+    scan_files_with_pool(files, max_file_size, &ExtractorPool::default())
+}
+
+/// Scan specific files, reusing extractors from `pool`
+#[must_use]
+pub fn scan_files_with_pool(
+    files: &[PathBuf], max_file_size: u64, pool: &ExtractorPool,
+) -> Vec<PhpClassMetadata> {
+    let mut results: Vec<PhpClassMetadata> =
+        scan_files_detailed_with_pool(files, max_file_size, pool)
+            .into_iter()
+            .flat_map(|scan| scan.classes)
+            .collect();
+
+    results.sort_by(|a, b| a.fqcn.cmp(&b.fqcn));
+    results
+}
+
+/// Scan specific files and report the outcome per file
+///
+/// Missing and non-PHP paths are skipped and do not appear in the result;
+/// paths that cannot be read after retries are reported as failed.
+#[must_use]
+pub fn scan_files_detailed(files: &[PathBuf], max_file_size: u64) -> Vec<FileScan> {
+    scan_files_detailed_with_pool(files, max_file_size, &ExtractorPool::default())
+}
+
+/// Scan specific files and report the outcome per file, reusing extractors from `pool`
+#[must_use]
+pub fn scan_files_detailed_with_pool(
+    files: &[PathBuf], max_file_size: u64, pool: &ExtractorPool,
+) -> Vec<FileScan> {
+    files
+        .par_iter()
+        .filter(|path| is_scannable(path, pool.options.frontends))
+        .map_init(
+            || pool.checkout(),
+            |extractor, path| scan_file(extractor, path, max_file_size),
+        )
+        .collect()
+}
+
+/// Scan specific files, handing each outcome to `visit` as soon as it is parsed
+///
+/// Outcomes arrive in completion order, on the worker thread that parsed the
+/// file; nothing is collected. Returning `false` from `visit` stops the scan
+/// (files already being parsed still finish). Returns whether every file was
+/// visited.
+pub fn visit_files_detailed(
+    files: &[PathBuf], max_file_size: u64, pool: &ExtractorPool,
+    visit: impl Fn(FileScan) -> bool + Sync + Send,
+) -> bool {
+    files
+        .par_iter()
+        .filter(|path| is_scannable(path, pool.options.frontends))
+        .try_for_each_init(
+            || pool.checkout(),
+            |extractor, path| {
+                if visit(scan_file(extractor, path, max_file_size)) {
+                    Ok(())
+                } else {
+                    Err(())
+                }
+            },
+        )
+        .is_ok()
+}
+
+/// Whether `path` is a source file to scan (see [`crate::retry::may_be_file`])
+fn is_scannable(path: &Path, frontends: Frontends) -> bool {
+    frontends.is_source_file(path) && crate::retry::may_be_file(path)
+}
 
-    let mut extractor = match PhpMetadataExtractor::new() {
-        Ok(e) => e,
-        Err(e) => {
-            error!("Error creating metadata extractor: {}", e);
-            return vec![];
+/// Scan one file with pooled parsers, recording its timing when profiling
+fn scan_file(parsers: &mut PooledParsers<'_>, path: &Path, max_file_size: u64) -> FileScan {
+    let started = crate::profile::enabled().then(Instant::now);
+    let pool = parsers.pool;
+    let result =
+        extract_file(&mut parsers.parsers, path, max_file_size, pool.mmap, &pool.options);
+    if let Some(started) = started {
+        crate::profile::record(crate::profile::FileTiming {
+            path: path.to_path_buf(),
+            bytes: fs::metadata(path).map_or(0, |metadata| metadata.len()),
+            duration: started.elapsed(),
+        });
+    }
+    match result {
+        Ok(classes) => FileScan {
+            path: path.to_path_buf(),
+            classes,
+            error: None,
         },
-    };
+        Err(error) => FileScan {
+            path: path.to_path_buf(),
+            classes: Vec::new(),
+            error: Some(error),
+        },
+    }
+}
 
-    for path in files {
-        if !path.exists() || !path.is_file() {
-            continue;
+/// Front-end parsers of one worker, each created on first use
+#[derive(Default)]
+struct Parsers(Vec<(&'static str, Box<dyn FrontendParser>)>);
+
+impl Parsers {
+    /// Parser of `frontend`, created with `options` if there is none yet
+    fn get(
+        &mut self, frontend: &'static dyn LanguageFrontend, options: &ScanOptions,
+    ) -> crate::error::Result<&mut dyn FrontendParser> {
+        let name = frontend.name();
+        let index = self.0.iter().position(|(known, _)| *known == name).unwrap_or(self.0.len());
+        if index == self.0.len() {
+            self.0.push((name, frontend.parser(options)?));
         }
+        Ok(self.0[index].1.as_mut())
+    }
+}
 
-        if path.extension().is_some_and(|ext| ext == "php") {
-            // Check file size before reading to prevent OOM
-            match fs::metadata(path) {
-                Ok(metadata) => {
-                    let file_size = metadata.len();
-                    if file_size > max_file_size {
-                        warn!(
-                            "Skipping large file: {:?} ({:.2}MB exceeds limit of {:.2}MB)",
-                            path,
-                            file_size as f64 / 1024.0 / 1024.0,
-                            max_file_size as f64 / 1024.0 / 1024.0
-                        );
-                        continue;
-                    }
-                },
-                Err(e) => {
-                    warn!("Could not read metadata for {:?}: {}", path, e);
-                    continue;
-                },
-            }
+/// Parsers kept alive across scans
+///
+/// Building a front-end parser loads the grammar and compiles its queries.
+/// Long-lived callers such as the daemon own a pool so every watch batch
+/// reuses the parsers created by earlier batches. Every scan using the pool
+/// follows its [`ScanOptions`].
+#[derive(Default)]
+pub struct ExtractorPool {
+    idle: Mutex<Vec<Parsers>>,
+    options: ScanOptions,
+    /// Memory-map large files instead of reading them (see `crate::source`)
+    mmap: bool,
+}
 
-            if let Ok(content) = fs::read_to_string(path) {
-                match extractor.extract_metadata(&content, path.clone()) {
-                    Ok(metadata_list) => {
-                        results.extend(metadata_list);
-                    },
-                    Err(e) => {
-                        error!("Error parsing file {:?}: {}", path, e);
-                    },
-                }
-            }
+impl ExtractorPool {
+    /// Pool whose scans follow `options`
+    #[must_use]
+    pub fn new(options: ScanOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
         }
     }
 
-    results.sort_by(|a, b| a.fqcn.cmp(&b.fqcn));
-    results
+    /// Memory-map large files, for processes that exit after one scan (the
+    /// one-shot CLI scan)
+    ///
+    /// Off by default: a mapped file truncated while it is parsed raises
+    /// SIGBUS, which would kill a long-lived host process.
+    #[must_use]
+    pub const fn with_mmap(mut self) -> Self {
+        self.mmap = true;
+        self
+    }
+
+    /// Options of the scans using this pool
+    #[must_use]
+    pub const fn options(&self) -> &ScanOptions {
+        &self.options
+    }
+
+    /// Number of parser sets currently available for reuse
+    #[must_use]
+    pub fn idle_count(&self) -> usize {
+        self.idle
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Take idle parsers, or an empty set if the pool is empty
+    fn checkout(&self) -> PooledParsers<'_> {
+        let idle = self
+            .idle
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop();
+        PooledParsers {
+            pool: self,
+            parsers: idle.unwrap_or_default(),
+        }
+    }
+}
+
+/// Parsers borrowed from an `ExtractorPool`, returned on drop
+struct PooledParsers<'a> {
+    pool: &'a ExtractorPool,
+    parsers: Parsers,
+}
+
+impl Drop for PooledParsers<'_> {
+    fn drop(&mut self) {
+        let parsers = std::mem::take(&mut self.parsers);
+        if !parsers.0.is_empty() {
+            self.pool
+                .idle
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(parsers);
+        }
+    }
+}
+
+/// Read and parse a single PHP file, honouring the size limit
+///
+/// Oversized, unreadable and unparsable files are returned as a
+/// [`ScanFailure`] and reported as the `on_error` policy says. Syntax
+/// errors keep the declarations recovered around them, except with
+/// [`OnError::Fail`].
+fn extract_file(
+    parsers: &mut Parsers, path: &Path, max_file_size: u64, mmap: bool, options: &ScanOptions,
+) -> std::result::Result<Vec<PhpClassMetadata>, ScanFailure> {
+    crate::nice::pause();
+    let policy = options.on_error;
+    let result = try_extract_file(parsers, path, max_file_size, mmap, options);
+    if let Err(failure) = &result {
+        match policy {
+            OnError::Skip => {},
+            OnError::Warn => {
+                warn!(
+                    path = %path.display(),
+                    code = failure.code,
+                    "Skipping {}: {failure}",
+                    path.display()
+                );
+            },
+            OnError::Fail => {
+                error!(
+                    path = %path.display(),
+                    code = failure.code,
+                    "Failed to scan {}: {failure}",
+                    path.display()
+                );
+            },
+        }
+    }
+    result
+}
+
+fn try_extract_file(
+    parsers: &mut Parsers, path: &Path, max_file_size: u64, mmap: bool, options: &ScanOptions,
+) -> std::result::Result<Vec<PhpClassMetadata>, ScanFailure> {
+    let policy = options.on_error;
+    // Check file size before reading to prevent OOM
+    let file_size = crate::retry::with_retry(path, || fs::metadata(path))
+        .map_err(|e| ScanFailure::new(code::IO, format!("Could not read metadata: {e}")))?
+        .len();
+    if file_size > max_file_size {
+        return Err(ScanFailure::new(
+            code::FILE_SIZE_LIMIT,
+            format!(
+                "File too large: {:.2}MB exceeds limit of {:.2}MB",
+                megabytes(file_size),
+                megabytes(max_file_size)
+            ),
+        ));
+    }
+
+    let target = options.php_version;
+    let frontend = options
+        .frontends
+        .for_path(path)
+        .unwrap_or(crate::frontend::FRONTENDS[0]);
+    let parser = parsers.get(frontend, options).map_err(|e| {
+        ScanFailure::new(
+            code::TREE_SITTER,
+            format!("Metadata extractor unavailable: {e}"),
+        )
+    })?;
+    let (classes, diagnostic, newer_syntax) =
+        crate::source::with_source(path, file_size, mmap, |content| {
+            parser.extract(content, path.to_path_buf(), target)
+        })
+        .map_err(|e| ScanFailure::new(code::IO, format!("Could not read file: {e}")))?
+        .map_err(|e| ScanFailure::new(e.code(), e.to_string()))?;
+
+    if let Some(diagnostic) = diagnostic {
+        // Under `fail` it is reported as a failure instead
+        if policy != OnError::Fail {
+            crate::scan_report::record_syntax_error(path, &diagnostic);
+        }
+        match policy {
+            OnError::Skip => {},
+            // The declarations recovered around a syntax error are still used
+            OnError::Warn => warn!(
+                path = %path.display(),
+                line = diagnostic.line,
+                "{}",
+                diagnostic.render(path)
+            ),
+            OnError::Fail => return Err(ScanFailure::new(code::SYNTAX, diagnostic.summary())),
+        }
+    }
+    // The grammar accepts newer syntax than the target, so say where it is used
+    if let Some(target) = target
+        && !newer_syntax.is_empty()
+    {
+        let features: Vec<String> = newer_syntax.iter().map(NewerSyntax::summary).collect();
+        warn!(
+            path = %path.display(),
+            php_version = %target,
+            "{} uses syntax newer than PHP {target}: {}",
+            path.display(),
+            features.join(", ")
+        );
+    }
+    Ok(classes)
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn megabytes(bytes: u64) -> f64 {
+    bytes as f64 / 1024.0 / 1024.0
 }
 
 #[cfg(test)]
@@ -219,4 +704,119 @@ mod tests {
         assert!(fqcns.contains(&"\\App\\B".to_string()));
         assert!(!fqcns.contains(&"\\App\\C".to_string())); // Should be ignored
     }
+
+    #[test]
+    fn test_watch_directories_skip_ignored() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src/Domain")).unwrap();
+        fs::create_dir_all(root.join("vendor/acme/lib")).unwrap();
+
+        let paths = vec![root.to_path_buf()];
+        let matcher = ignore_matcher(&paths, &["vendor".to_string()]);
+        let dirs = watch_directories(root, matcher.as_ref());
+
+        assert!(dirs.contains(&root.to_path_buf()));
+        assert!(dirs.contains(&root.join("src/Domain")));
+        assert!(!dirs.iter().any(|d| d.starts_with(root.join("vendor"))));
+        assert!(is_ignored(
+            matcher.as_ref(),
+            &root.join("vendor/acme/lib/Foo.php")
+        ));
+        assert!(!is_ignored(matcher.as_ref(), &root.join("src/Foo.php")));
+    }
+
+    #[test]
+    fn test_ignore_patterns_skip_directories_above_the_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("build/app");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::write(root.join("src/A.php"), "<?php class A {}").unwrap();
+        fs::write(root.join("build/B.php"), "<?php class B {}").unwrap();
+
+        let paths = vec![root.clone()];
+        let matcher = ignore_matcher(&paths, &["build".to_string()]);
+
+        assert!(!is_ignored(matcher.as_ref(), &root.join("src/A.php")));
+        assert!(is_ignored(matcher.as_ref(), &root.join("build/B.php")));
+        let metadata = scan_directory(&paths, &["build".to_string()]);
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(metadata[0].fqcn, "\\A");
+    }
+
+    #[test]
+    fn test_exclude_from_output() {
+        let root = Path::new("/app/src");
+        let class = |file: &str| {
+            PhpClassMetadata::new(
+                format!("\\App\\{file}"),
+                root.join(file),
+                "class".to_string(),
+            )
+        };
+        let mut metadata = vec![class("User.php"), class("Fixtures/Fake.php")];
+
+        let exclude = ignore_matcher(&[root.to_path_buf()], &["Fixtures/".to_string()]);
+        exclude_from_output(&mut metadata, exclude.as_ref());
+
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(metadata[0].file, root.join("User.php"));
+    }
+
+    #[test]
+    fn test_extractor_pool_reuses_extractors() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("A.php");
+        fs::write(&file, "<?php class A {}").unwrap();
+        let pool = ExtractorPool::default();
+
+        let first = scan_files_with_pool(std::slice::from_ref(&file), DEFAULT_MAX_FILE_SIZE, &pool);
+        let idle = pool.idle_count();
+        let second = scan_files_with_pool(&[file], DEFAULT_MAX_FILE_SIZE, &pool);
+
+        assert_eq!(first, second);
+        assert!(idle >= 1);
+        assert_eq!(pool.idle_count(), idle);
+    }
+
+    #[test]
+    fn test_temp_file_matcher() {
+        let matcher = temp_file_matcher(&["*.bak.php".to_string()]);
+
+        for temp in [
+            "src/.User.php.swp",
+            "src/User.php~",
+            "src/.#User.php",
+            "src/#User.php#",
+            "src/User.php___jb_tmp___",
+            "src/User.bak.php",
+        ] {
+            assert!(is_temp_file(&matcher, Path::new(temp)), "{temp}");
+        }
+        assert!(!is_temp_file(&matcher, Path::new("src/User.php")));
+        assert!(!is_temp_file(&matcher, Path::new("tmp/User.php")));
+    }
+
+    #[test]
+    fn test_scan_files_parallel_preserves_all_results() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        let mut files = Vec::new();
+        for i in 0..50 {
+            let path = root.join(format!("Class{i}.php"));
+            let mut f = File::create(&path).unwrap();
+            writeln!(f, "<?php namespace App; class Class{i} {{}}").unwrap();
+            files.push(path);
+        }
+        // Missing and non-PHP files are skipped
+        files.push(root.join("Missing.php"));
+        files.push(root.join("notes.txt"));
+
+        let results = scan_files(&files);
+
+        assert_eq!(results.len(), 50);
+        assert!(results.windows(2).all(|w| w[0].fqcn <= w[1].fqcn));
+    }
 }
@@ -0,0 +1,177 @@
+//! Schema versioning for serialized metadata
+//!
+//! Manifests and every class record in JSON caches carry a `schema_version`.
+//! Artifacts written before versioning existed have no such field and are
+//! read as version 1. Older documents are upgraded step by step on the raw
+//! JSON value before deserialization, so the serde structs only ever describe
+//! the current layout. The JSON cache stays a plain array of records, so
+//! consumers that ignore unknown fields keep working across upgrades.
+
+use crate::metadata::PhpClassMetadata;
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+
+/// Schema version written by this build
+///
+/// - 2: `file_hash` and `traits`
+/// - 3: `file_context`, `custom`, `signature_hash` and `truncated`, and
+///   group `use` imports resolved with their prefix
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// Version assumed for artifacts written before `schema_version` existed
+pub const LEGACY_SCHEMA_VERSION: u32 = 1;
+
+/// Serde default for documents without a `schema_version` field
+#[must_use]
+pub const fn legacy_schema_version() -> u32 {
+    LEGACY_SCHEMA_VERSION
+}
+
+/// Upgrade a manifest document to the current schema
+///
+/// Records in older manifests lack the fields added since (see
+/// [`SCHEMA_VERSION`]) or resolved names differently, so their file entries
+/// get a zero mtime and the next incremental run re-parses every file instead
+/// of serving them.
+///
+/// # Errors
+///
+/// Returns an error if the document was written by a newer schema or is
+/// malformed.
+pub fn migrate_manifest(mut manifest: Value) -> Result<Value> {
+    let version = document_version(&manifest, "manifest")?;
+
+    if let Some(files) = manifest.get_mut("files").and_then(Value::as_object_mut) {
+        for entry in files.values_mut() {
+            if version < SCHEMA_VERSION {
+                entry["mtime"] = Value::from(0);
+            }
+            if let Some(classes) = entry.get_mut("classes") {
+                migrate_class_list(classes)?;
+            }
+        }
+    }
+
+    manifest["schema_version"] = Value::from(SCHEMA_VERSION);
+    Ok(manifest)
+}
+
+/// Upgrade a list of class records (JSON cache or manifest entry) in place
+///
+/// # Errors
+///
+/// Returns an error if a record was written by a newer schema or the value
+/// is not an array.
+pub fn migrate_class_list(classes: &mut Value) -> Result<()> {
+    let Some(records) = classes.as_array_mut() else {
+        bail!("Expected an array of class records");
+    };
+
+    for record in records {
+        // Every upgrade so far only added fields with serde defaults
+        document_version(record, "class record")?;
+        record["schema_version"] = Value::from(SCHEMA_VERSION);
+    }
+
+    Ok(())
+}
+
+/// Parse a JSON cache written by this or any previous schema version
+///
+/// # Errors
+///
+/// Returns an error if the content is not valid JSON, was written by a newer
+/// schema, or does not match the metadata layout.
+pub fn read_json_cache(content: &str) -> Result<Vec<PhpClassMetadata>> {
+    let mut classes: Value = serde_json::from_str(content).context("Failed to parse JSON cache")?;
+    migrate_class_list(&mut classes)?;
+    serde_json::from_value(classes).context("Invalid JSON cache layout")
+}
+
+/// Read `schema_version` from a document, rejecting versions from the future
+fn document_version(document: &Value, what: &str) -> Result<u32> {
+    let version = match document.get("schema_version") {
+        None => LEGACY_SCHEMA_VERSION,
+        Some(value) => value
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .with_context(|| format!("Invalid schema_version in {what}: {value}"))?,
+    };
+
+    if version > SCHEMA_VERSION {
+        bail!(
+            "{what} uses schema version {version}, but this build only supports up to {SCHEMA_VERSION}; upgrade aurynx"
+        );
+    }
+
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_read_legacy_json_cache() {
+        let legacy = json!([{
+            "fqcn": "\\App\\User",
+            "file": "/src/User.php",
+            "type": "class",
+            "modifiers": {"is_abstract": false, "is_final": false, "is_readonly": false},
+            "attributes": {},
+            "extends": null,
+            "implements": [],
+            "methods": [],
+            "properties": [],
+            "backing_type": null,
+            "cases": [],
+        }]);
+
+        let classes = read_json_cache(&legacy.to_string()).unwrap();
+
+        assert_eq!(classes.len(), 1);
+        assert_eq!(classes[0].schema_version, SCHEMA_VERSION);
+        assert!(classes[0].file_hash.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_legacy_manifest_forces_rescan() {
+        let legacy = json!({
+            "files": {"/src/User.php": {"mtime": 1_700_000_000, "classes": []}},
+        });
+
+        let migrated = migrate_manifest(legacy).unwrap();
+
+        assert_eq!(migrated["schema_version"], SCHEMA_VERSION);
+        assert_eq!(migrated["files"]["/src/User.php"]["mtime"], 0);
+    }
+
+    #[test]
+    fn test_migrate_previous_manifest_forces_rescan() {
+        let previous = json!({
+            "schema_version": SCHEMA_VERSION - 1,
+            "files": {"/src/User.php": {"mtime": 1_700_000_000, "classes": []}},
+        });
+        let current = json!({
+            "schema_version": SCHEMA_VERSION,
+            "files": {"/src/User.php": {"mtime": 1_700_000_000, "classes": []}},
+        });
+
+        assert_eq!(migrate_manifest(previous).unwrap()["files"]["/src/User.php"]["mtime"], 0);
+        assert_eq!(
+            migrate_manifest(current).unwrap()["files"]["/src/User.php"]["mtime"],
+            1_700_000_000
+        );
+    }
+
+    #[test]
+    fn test_rejects_newer_schema() {
+        let future = json!({"schema_version": SCHEMA_VERSION + 1, "files": {}});
+
+        let err = migrate_manifest(future).unwrap_err();
+
+        assert!(err.to_string().contains("upgrade aurynx"));
+    }
+}
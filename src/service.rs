@@ -0,0 +1,367 @@
+//! User service files for the watch daemon (`discovery:install-service`)
+//!
+//! Renders a systemd user unit or a launchd agent that runs
+//! `discovery:scan --watch` with the resolved paths, socket and output of the
+//! current configuration, writes it to the per-user service directory and
+//! optionally starts it.
+
+use anyhow::{Context, Result, bail};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Service manager the daemon is installed for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceManager {
+    /// `systemctl --user` unit in `~/.config/systemd/user`
+    SystemdUser,
+    /// launchd agent in `~/Library/LaunchAgents`
+    Launchd,
+}
+
+impl ServiceManager {
+    /// launchd on macOS, systemd everywhere else
+    #[must_use]
+    pub const fn native() -> Self {
+        if cfg!(target_os = "macos") {
+            Self::Launchd
+        } else {
+            Self::SystemdUser
+        }
+    }
+
+    /// Service file of `name` under `home` (`xdg_config_home` only applies to systemd)
+    #[must_use]
+    pub fn unit_path(self, name: &str, home: &Path, xdg_config_home: Option<&Path>) -> PathBuf {
+        match self {
+            Self::SystemdUser => xdg_config_home
+                .map_or_else(|| home.join(".config"), Path::to_path_buf)
+                .join("systemd/user")
+                .join(format!("{name}.service")),
+            Self::Launchd => home
+                .join("Library/LaunchAgents")
+                .join(format!("{name}.plist")),
+        }
+    }
+}
+
+/// Everything a service file needs to run the daemon
+#[derive(Debug, Clone)]
+pub struct ServiceSpec {
+    /// Unit name (systemd) or label (launchd)
+    pub name: String,
+    /// Absolute path of the aurynx binary
+    pub program: PathBuf,
+    /// Arguments after the program
+    pub args: Vec<String>,
+    pub working_dir: PathBuf,
+    /// Daemon log file; launchd redirects stdout/stderr there
+    pub log_file: Option<PathBuf>,
+}
+
+/// Daemon settings baked into the service arguments
+#[derive(Debug, Clone, Default)]
+pub struct DaemonArgs {
+    pub config: Option<PathBuf>,
+    pub paths: Vec<PathBuf>,
+    pub output: PathBuf,
+    pub socket: PathBuf,
+    pub pid: PathBuf,
+    pub ignore: Vec<String>,
+}
+
+impl ServiceSpec {
+    /// Service running `discovery:scan --watch` in `working_dir`
+    ///
+    /// Relative paths are made absolute against `working_dir`, so the file
+    /// keeps working when the service manager starts it elsewhere.
+    #[must_use]
+    pub fn daemon(name: &str, program: PathBuf, working_dir: PathBuf, daemon: &DaemonArgs) -> Self {
+        let absolute = |path: &Path| working_dir.join(path).display().to_string();
+
+        let mut args = vec!["discovery:scan".to_string(), "--watch".to_string()];
+        if let Some(config) = &daemon.config {
+            args.extend(["--config".to_string(), absolute(config)]);
+        }
+        args.push("--path".to_string());
+        args.extend(daemon.paths.iter().map(|path| absolute(path)));
+        for pattern in &daemon.ignore {
+            args.extend(["--ignore".to_string(), pattern.clone()]);
+        }
+        args.extend([
+            "--output".to_string(),
+            absolute(&daemon.output),
+            "--socket".to_string(),
+            absolute(&daemon.socket),
+            "--pid".to_string(),
+            absolute(&daemon.pid),
+        ]);
+
+        Self {
+            name: name.to_string(),
+            program,
+            args,
+            working_dir,
+            log_file: None,
+        }
+    }
+
+    /// Service file contents for `manager`
+    #[must_use]
+    pub fn render(&self, manager: ServiceManager) -> String {
+        match manager {
+            ServiceManager::SystemdUser => self.render_systemd(),
+            ServiceManager::Launchd => self.render_launchd(),
+        }
+    }
+
+    fn render_systemd(&self) -> String {
+        let command = std::iter::once(self.program.display().to_string())
+            .chain(self.args.iter().cloned())
+            .map(|arg| systemd_quote(&arg))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        format!(
+            "[Unit]\n\
+             Description=Aurynx discovery daemon ({dir})\n\
+             \n\
+             [Service]\n\
+             Type=simple\n\
+             WorkingDirectory={dir}\n\
+             ExecStart={command}\n\
+             Restart=on-failure\n\
+             RestartSec=2\n\
+             \n\
+             [Install]\n\
+             WantedBy=default.target\n",
+            dir = self.working_dir.display().to_string().replace('%', "%%"),
+        )
+    }
+
+    fn render_launchd(&self) -> String {
+        let mut plist = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \
+             \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+             <plist version=\"1.0\">\n<dict>\n",
+        );
+        let _ = writeln!(
+            plist,
+            "  <key>Label</key>\n  <string>{}</string>",
+            xml_escape(&self.name)
+        );
+        plist.push_str("  <key>ProgramArguments</key>\n  <array>\n");
+        for arg in
+            std::iter::once(self.program.display().to_string()).chain(self.args.iter().cloned())
+        {
+            let _ = writeln!(plist, "    <string>{}</string>", xml_escape(&arg));
+        }
+        plist.push_str("  </array>\n");
+        let _ = writeln!(
+            plist,
+            "  <key>WorkingDirectory</key>\n  <string>{}</string>",
+            xml_escape(&self.working_dir.display().to_string())
+        );
+        plist.push_str(
+            "  <key>RunAtLoad</key>\n  <true/>\n  \
+             <key>KeepAlive</key>\n  <dict>\n    \
+             <key>SuccessfulExit</key>\n    <false/>\n  </dict>\n",
+        );
+        if let Some(log_file) = &self.log_file {
+            let log_file = xml_escape(&log_file.display().to_string());
+            let _ = writeln!(
+                plist,
+                "  <key>StandardOutPath</key>\n  <string>{log_file}</string>\n  \
+                 <key>StandardErrorPath</key>\n  <string>{log_file}</string>"
+            );
+        }
+        plist.push_str("</dict>\n</plist>\n");
+        plist
+    }
+}
+
+/// Service name derived from the project directory, e.g. `aurynx-discovery-shop`
+#[must_use]
+pub fn default_name(working_dir: &Path) -> String {
+    let slug: String = working_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        "aurynx-discovery".to_string()
+    } else {
+        format!("aurynx-discovery-{slug}")
+    }
+}
+
+/// Write the service file for the current user and return its path
+///
+/// With `start`, the service is also enabled and (re)started.
+///
+/// # Errors
+///
+/// Returns an error if `HOME` is unset, the file cannot be written or the
+/// service manager command fails.
+pub fn install(spec: &ServiceSpec, manager: ServiceManager, start: bool) -> Result<PathBuf> {
+    let home = std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .context("HOME is not set")?;
+    let xdg_config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute());
+    let unit_path = manager.unit_path(&spec.name, &home, xdg_config_home.as_deref());
+
+    if let Some(parent) = unit_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(&unit_path, spec.render(manager))
+        .with_context(|| format!("Failed to write {}", unit_path.display()))?;
+
+    if start {
+        match manager {
+            ServiceManager::SystemdUser => {
+                run(Command::new("systemctl").args(["--user", "daemon-reload"]))?;
+                run(Command::new("systemctl")
+                    .args(["--user", "enable"])
+                    .arg(format!("{}.service", spec.name)))?;
+                // Restart also picks up a changed unit of a running service
+                run(Command::new("systemctl")
+                    .args(["--user", "restart"])
+                    .arg(format!("{}.service", spec.name)))?;
+            },
+            ServiceManager::Launchd => {
+                // Unloading fails when the agent is not loaded yet
+                let _ = Command::new("launchctl")
+                    .arg("unload")
+                    .arg(&unit_path)
+                    .output();
+                run(Command::new("launchctl")
+                    .args(["load", "-w"])
+                    .arg(&unit_path))?;
+            },
+        }
+    }
+
+    Ok(unit_path)
+}
+
+/// Run a service manager command, failing on a non-zero exit
+fn run(command: &mut Command) -> Result<()> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .output()
+        .with_context(|| format!("Failed to run {program} (is it installed?)"))?;
+    if !output.status.success() {
+        bail!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Quote one `ExecStart=` word; `%` and `$` are escaped so systemd does not
+/// expand them
+fn systemd_quote(arg: &str) -> String {
+    let escaped = arg.replace('%', "%%").replace('$', "$$");
+    let plain = !escaped.is_empty()
+        && !escaped
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';'));
+    if plain {
+        escaped
+    } else {
+        format!("\"{}\"", escaped.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> ServiceSpec {
+        ServiceSpec::daemon(
+            "aurynx-discovery-shop",
+            PathBuf::from("/usr/local/bin/aurynx"),
+            PathBuf::from("/home/dev/My Shop"),
+            &DaemonArgs {
+                config: Some(PathBuf::from("aurynx.json")),
+                paths: vec![PathBuf::from("src")],
+                output: PathBuf::from("var/cache/discovery.php"),
+                socket: PathBuf::from("/tmp/aurynx.sock"),
+                pid: PathBuf::from("var/aurynx.pid"),
+                ignore: vec!["vendor/*".to_string()],
+            },
+        )
+    }
+
+    #[test]
+    fn test_render_systemd_unit() {
+        let unit = spec().render(ServiceManager::SystemdUser);
+
+        assert!(unit.contains("WorkingDirectory=/home/dev/My Shop\n"));
+        assert!(unit.contains(
+            "ExecStart=/usr/local/bin/aurynx discovery:scan --watch \
+             --config \"/home/dev/My Shop/aurynx.json\" --path \"/home/dev/My Shop/src\" \
+             --ignore vendor/* --output \"/home/dev/My Shop/var/cache/discovery.php\" \
+             --socket /tmp/aurynx.sock --pid \"/home/dev/My Shop/var/aurynx.pid\"\n"
+        ));
+        assert!(unit.contains("Restart=on-failure\n"));
+        assert!(unit.ends_with("WantedBy=default.target\n"));
+        assert_eq!(systemd_quote("100%$HOME"), "100%%$$HOME");
+    }
+
+    #[test]
+    fn test_render_launchd_plist() {
+        let mut spec = spec();
+        spec.log_file = Some(PathBuf::from("/tmp/a&b.log"));
+        let plist = spec.render(ServiceManager::Launchd);
+
+        assert!(plist.contains("<key>Label</key>\n  <string>aurynx-discovery-shop</string>"));
+        assert!(plist.contains(
+            "    <string>/usr/local/bin/aurynx</string>\n    <string>discovery:scan</string>\n"
+        ));
+        assert!(plist.contains("<string>/home/dev/My Shop/src</string>"));
+        assert!(
+            plist.contains("<key>StandardErrorPath</key>\n  <string>/tmp/a&amp;b.log</string>")
+        );
+        assert!(plist.ends_with("</dict>\n</plist>\n"));
+    }
+
+    #[test]
+    fn test_unit_paths_and_default_name() {
+        let home = Path::new("/home/dev");
+        assert_eq!(
+            ServiceManager::SystemdUser.unit_path("svc", home, None),
+            PathBuf::from("/home/dev/.config/systemd/user/svc.service")
+        );
+        assert_eq!(
+            ServiceManager::SystemdUser.unit_path("svc", home, Some(Path::new("/xdg"))),
+            PathBuf::from("/xdg/systemd/user/svc.service")
+        );
+        assert_eq!(
+            ServiceManager::Launchd.unit_path("svc", home, None),
+            PathBuf::from("/home/dev/Library/LaunchAgents/svc.plist")
+        );
+        assert_eq!(
+            default_name(Path::new("/srv/My Shop")),
+            "aurynx-discovery-my-shop"
+        );
+        assert_eq!(default_name(Path::new("/")), "aurynx-discovery");
+    }
+}
@@ -0,0 +1,103 @@
+//! Signature hashes (`signature_hash`)
+//!
+//! `file_hash` changes with every edit of a file, including edits inside
+//! method bodies that no consumer of the cache can observe. The signature
+//! hash covers what the cache describes instead: names, modifiers, types,
+//! defaults, attributes and the class hierarchy, but not the file path or
+//! hash. Consumers that only depend on signatures (container compilers,
+//! route tables, generated proxies) can skip a rebuild while it stays the
+//! same.
+
+use crate::metadata::{
+    AttributeMap, ClassModifiers, CustomCaptures, EnumCase, PhpClassMetadata, PhpMethodMetadata,
+    PhpPropertyMetadata, TruncatedMembers,
+};
+use serde::Serialize;
+
+/// The hashed part of a class entry
+#[derive(Serialize)]
+struct Signature<'a> {
+    fqcn: &'a str,
+    kind: &'a str,
+    modifiers: &'a ClassModifiers,
+    attributes: &'a AttributeMap,
+    inherited_attributes: Option<&'a AttributeMap>,
+    extends: Option<&'a str>,
+    implements: &'a [String],
+    resolved_implements: Option<&'a [String]>,
+    ancestors: Option<&'a [String]>,
+    traits: &'a [String],
+    methods: &'a [PhpMethodMetadata],
+    properties: &'a [PhpPropertyMetadata],
+    backing_type: Option<&'a str>,
+    cases: &'a [EnumCase],
+    /// Custom query captures can come from method bodies, which consumers
+    /// of them do depend on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    custom: Option<&'a CustomCaptures>,
+    /// Members added past `max_class_members` still change the hash
+    #[serde(skip_serializing_if = "Option::is_none")]
+    truncated: Option<&'a TruncatedMembers>,
+}
+
+/// Signature hash of one class (16 hex digits, like `file_hash`)
+#[must_use]
+pub fn signature_hash(class: &PhpClassMetadata) -> String {
+    let signature = Signature {
+        fqcn: &class.fqcn,
+        kind: &class.kind,
+        modifiers: &class.modifiers,
+        attributes: &class.attributes,
+        inherited_attributes: class.inherited_attributes.as_ref(),
+        extends: class.extends.as_deref(),
+        implements: &class.implements,
+        resolved_implements: class.resolved_implements.as_deref(),
+        ancestors: class.ancestors.as_deref(),
+        traits: &class.traits,
+        methods: &class.methods,
+        properties: &class.properties,
+        backing_type: class.backing_type.as_deref(),
+        cases: &class.cases,
+        custom: class.custom.as_ref(),
+        truncated: class.truncated.as_ref(),
+    };
+    // Serializing borrowed metadata into memory cannot fail
+    let bytes = serde_json::to_vec(&signature).unwrap_or_default();
+    format!("{:016x}", xxhash_rust::xxh3::xxh3_64(&bytes))
+}
+
+/// Set `signature_hash` on every class
+///
+/// Runs after the hierarchy passes, so inherited attributes and interfaces
+/// count as part of the signature.
+pub fn add_signature_hashes(metadata_list: &mut [PhpClassMetadata]) {
+    for class in metadata_list {
+        class.signature_hash = Some(signature_hash(class));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_signature_hash_ignores_file_and_body_changes() {
+        let mut class = PhpClassMetadata::new(
+            "\\App\\User".to_string(),
+            PathBuf::from("/src/User.php"),
+            "class".to_string(),
+        );
+        class.file_hash = "0123456789abcdef".to_string();
+        let hash = signature_hash(&class);
+        assert_eq!(hash.len(), 16);
+
+        // A body edit changes the file hash only
+        class.file_hash = "fedcba9876543210".to_string();
+        class.file = PathBuf::from("/moved/User.php");
+        assert_eq!(signature_hash(&class), hash);
+
+        class.modifiers.is_final = true;
+        assert_ne!(signature_hash(&class), hash);
+    }
+}
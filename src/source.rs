@@ -0,0 +1,98 @@
+#![allow(unsafe_code)]
+
+//! Source file loading for the scanner hot path
+//!
+//! Files above [`MMAP_THRESHOLD`] are memory-mapped instead of copied into a
+//! heap buffer, which saves a read syscall loop and an allocation per file on
+//! large scans. Small files are cheaper to read directly, and any mapping
+//! failure (unsupported filesystem, special file) falls back to a plain read.
+//!
+//! Mapping is opt-in and only the one-shot CLI scan turns it on. Everything
+//! else (the daemon, the watcher, the LSP server, `Discovery`, FFI callers
+//! inside PHP workers) scans files while editors, VCS or deploys rewrite
+//! them, and a mapped file truncated underneath the parser raises SIGBUS,
+//! so they always read (see `ExtractorPool::with_mmap`).
+
+use memmap2::Mmap;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+/// Files at least this large (in bytes) are memory-mapped
+pub const MMAP_THRESHOLD: u64 = 16 * 1024;
+
+/// Run `f` over the UTF-8 source of `path`
+///
+/// `len` is the file size already obtained by the caller (for the size limit
+/// check), used to choose between mapping and reading; without `mmap` the
+/// file is always read.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is not valid UTF-8.
+/// Transient read errors are retried (see [`crate::retry`]).
+pub fn with_source<T>(
+    path: &Path, len: u64, mmap: bool, f: impl FnOnce(&str) -> T,
+) -> io::Result<T> {
+    if mmap
+        && len >= MMAP_THRESHOLD
+        && let Some(map) = map_file(path)
+    {
+        let text =
+            std::str::from_utf8(&map).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        return Ok(f(text));
+    }
+
+    let text = crate::retry::with_retry(path, || fs::read_to_string(path))?;
+    Ok(f(&text))
+}
+
+/// Map `path` read-only, or `None` to fall back to a regular read
+fn map_file(path: &Path) -> Option<Mmap> {
+    let file = File::open(path).ok()?;
+    // SAFETY: the mapping is read-only and dropped before `with_source`
+    // returns. Only the one-shot CLI scan maps files; it exits after the
+    // scan, and a file truncated during it at worst ends that process.
+    unsafe { Mmap::map(&file) }.ok()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_with_source_reads_small_and_mapped_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let small = temp_dir.path().join("Small.php");
+        let large = temp_dir.path().join("Large.php");
+        fs::write(&small, "<?php class Small {}").unwrap();
+        let body = format!("<?php class Large {{}}\n{}", "// padding\n".repeat(2048));
+        fs::write(&large, &body).unwrap();
+
+        let small_len = fs::metadata(&small).unwrap().len();
+        let large_len = fs::metadata(&large).unwrap().len();
+        assert!(large_len >= MMAP_THRESHOLD);
+
+        assert_eq!(
+            with_source(&small, small_len, true, str::len).unwrap(),
+            usize::try_from(small_len).unwrap()
+        );
+        assert!(with_source(&large, large_len, true, |s| s == body).unwrap());
+        assert!(with_source(&large, large_len, false, |s| s == body).unwrap());
+    }
+
+    #[test]
+    fn test_with_source_rejects_invalid_utf8_when_mapped() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("Broken.php");
+        let mut bytes = vec![b' '; usize::try_from(MMAP_THRESHOLD).unwrap()];
+        bytes.push(0xff);
+        fs::write(&path, &bytes).unwrap();
+
+        let len = fs::metadata(&path).unwrap().len();
+        let err = with_source(&path, len, true, |_| ()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,135 @@
+//! Template-rendered artifacts (`discovery:render`)
+//!
+//! Between the `artifacts` rules and an external exporter there is a wide
+//! range of small custom outputs: a route file, a listener map, a Markdown
+//! inventory. `discovery:render` scans the project and renders a Jinja
+//! template ([minijinja](https://docs.rs/minijinja)) with every class, as
+//! the JSON cache holds it, in `classes` (sorted by FQCN). Templates can
+//! `include` and `extend` other templates from their directory.
+//!
+//! On top of the built-in filters, `attribute(name)` picks the instances of
+//! an attribute from an attribute map, each a map of its arguments (named
+//! ones by name, positional ones by index), and `php_string` and
+//! `php_escape` convert between PHP string literals and text.
+
+use crate::metadata::{AttributeArgument, AttributeMap, PhpClassMetadata};
+use crate::openapi::{matches_attribute, php_string};
+use crate::writer::escape_php_string;
+use anyhow::{Context, Result};
+use minijinja::value::ViaDeserialize;
+use minijinja::{Environment, Value, context, path_loader};
+use std::path::Path;
+
+/// Render the template file `template` with `metadata`
+///
+/// # Errors
+///
+/// Returns an error if the template cannot be loaded, does not compile or
+/// fails to render (with the template name and line).
+pub fn render_file(metadata: &[PhpClassMetadata], template: &Path) -> Result<String> {
+    let name = template
+        .file_name()
+        .with_context(|| format!("Template path {} has no file name", template.display()))?
+        .to_string_lossy();
+    let mut env = environment();
+    env.set_loader(path_loader(template.parent().unwrap_or_else(|| Path::new("."))));
+    let template = env
+        .get_template(&name)
+        .map_err(|e| anyhow::anyhow!("{e:#}"))?;
+    render(&template, metadata)
+}
+
+fn render(template: &minijinja::Template, metadata: &[PhpClassMetadata]) -> Result<String> {
+    let mut classes: Vec<&PhpClassMetadata> = metadata.iter().collect();
+    classes.sort_by(|a, b| a.fqcn.cmp(&b.fqcn));
+    template
+        .render(context! { classes => Value::from_serialize(&classes) })
+        .map_err(|e| anyhow::anyhow!("{e:#}"))
+}
+
+/// Environment with the discovery filters, set up for source code: the
+/// newline after a block tag is dropped and the trailing one kept
+fn environment() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_trim_blocks(true);
+    env.set_keep_trailing_newline(true);
+    env.add_filter("attribute", attribute);
+    env.add_filter("php_string", |value: &str| php_string(value));
+    env.add_filter("php_escape", |value: &str| escape_php_string(value));
+    env
+}
+
+/// Instances of the attribute `name` (suffix match) in `attributes`
+fn attribute(attributes: ViaDeserialize<AttributeMap>, name: &str) -> Vec<Value> {
+    attributes
+        .0
+        .into_iter()
+        .filter(|(fqcn, _)| matches_attribute(fqcn, name))
+        .flat_map(|(_, instances)| instances)
+        .map(|args| {
+            let mut position = 0;
+            args.into_iter()
+                .map(|arg| match arg {
+                    AttributeArgument::Named { key, value } => {
+                        (Value::from(key), Value::from(value))
+                    },
+                    AttributeArgument::Positional(value) => {
+                        position += 1;
+                        (Value::from(position - 1), Value::from(value))
+                    },
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::parser::PhpMetadataExtractor;
+
+    #[test]
+    fn test_render_routes_from_attributes() {
+        let source = r#"<?php
+namespace App\Controller;
+use Symfony\Component\Routing\Attribute\Route;
+class UserController {
+    #[Route('/users', name: 'user_list')]
+    public function list() {}
+    #[Route("/users/{id}", methods: ['GET'])]
+    public function show() {}
+}
+"#;
+        let metadata = PhpMetadataExtractor::new()
+            .unwrap()
+            .extract_metadata(source, "UserController.php".into())
+            .unwrap();
+        let mut env = environment();
+        env.add_template(
+            "routes.php.j2",
+            r"<?php return [
+{% for class in classes %}
+{% for method in class.methods %}
+{% for route in method.attributes|attribute('Route') %}
+    '{{ route[0]|php_string|php_escape }}' => ['{{ class.fqcn|php_escape }}', '{{ method.name }}', {{ route.name or 'null' }}],
+{% endfor %}
+{% endfor %}
+{% endfor %}
+];
+",
+        )
+        .unwrap();
+
+        let rendered = render(&env.get_template("routes.php.j2").unwrap(), &metadata).unwrap();
+
+        assert_eq!(
+            rendered,
+            r"<?php return [
+    '/users' => ['\\App\\Controller\\UserController', 'list', 'user_list'],
+    '/users/{id}' => ['\\App\\Controller\\UserController', 'show', null],
+];
+"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Attributes that reference no known class
+//!
+//! PHP resolves an attribute name like any other class name, relative to the
+//! current namespace unless a `use` import says otherwise, and only
+//! instantiates it on `newInstance()`. A forgotten import therefore still
+//! parses and lands in the cache as `\App\Controller\Route` instead of the
+//! intended `\Symfony\Component\Routing\Attribute\Route`, and consumers
+//! looking for the real attribute silently miss the class. This module checks
+//! attribute names against the scanned classes, PHP's built-in attributes
+//! and an allowlist (`known_attributes`) for vendor attributes that are not
+//! scanned.
+
+use crate::metadata::{PhpClassMetadata, fold_class_name};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+
+/// Attributes declared by PHP itself
+const BUILTIN_ATTRIBUTES: &[&str] = &[
+    "AllowDynamicProperties",
+    "Attribute",
+    "Deprecated",
+    "Override",
+    "ReturnTypeWillChange",
+    "SensitiveParameter",
+];
+
+/// An attribute name that matches no known class, with where it is used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownAttribute {
+    /// Attribute FQCN as written to the cache
+    pub attribute: String,
+    /// Declarations using it (`Class` or `Class::member`) with their files
+    pub usages: Vec<(String, PathBuf)>,
+}
+
+impl fmt::Display for UnknownAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list: Vec<_> = self
+            .usages
+            .iter()
+            .map(|(usage, file)| format!("{usage} ({})", file.display()))
+            .collect();
+        write!(
+            f,
+            "{} matches no scanned class or known attribute (missing `use` import?), used by {}",
+            self.attribute,
+            list.join(", ")
+        )
+    }
+}
+
+/// Find attributes that are neither scanned classes, PHP built-ins nor
+/// covered by `known`
+///
+/// `known` entries are attribute FQCNs, or namespace prefixes ending in `\`
+/// (e.g. `Doctrine\ORM\Mapping\`); the leading backslash is optional and
+/// names compare case-insensitively, as in PHP. Results are sorted by
+/// attribute name, and their usages in scan order.
+#[must_use]
+pub fn find_unknown_attributes(
+    classes: &[PhpClassMetadata], known: &[String],
+) -> Vec<UnknownAttribute> {
+    let mut names: HashSet<String> = classes
+        .iter()
+        .map(|class| fold_class_name(&class.fqcn))
+        .collect();
+    names.extend(BUILTIN_ATTRIBUTES.iter().map(|name| fold_class_name(name)));
+    let (prefixes, exact): (Vec<String>, Vec<String>) = known
+        .iter()
+        .map(|entry| fold_class_name(entry))
+        .partition(|entry| entry.ends_with('\\'));
+    names.extend(exact);
+
+    let is_known = |attribute: &str| {
+        let attribute = fold_class_name(attribute);
+        names.contains(&attribute) || prefixes.iter().any(|prefix| attribute.starts_with(prefix))
+    };
+
+    let mut unknown: BTreeMap<String, Vec<(String, PathBuf)>> = BTreeMap::new();
+    for class in classes {
+        for (usage, _, attributes) in class.attribute_maps() {
+            for attribute in attributes.keys().filter(|attribute| !is_known(attribute)) {
+                unknown
+                    .entry(attribute.clone())
+                    .or_default()
+                    .push((usage.clone(), class.file.clone()));
+            }
+        }
+    }
+
+    unknown
+        .into_iter()
+        .map(|(attribute, usages)| UnknownAttribute { attribute, usages })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{AttributeMap, MethodModifiers, PhpMethodMetadata};
+
+    fn class(fqcn: &str, attributes: &[&str]) -> PhpClassMetadata {
+        let mut class = PhpClassMetadata::new(
+            fqcn.to_string(),
+            PathBuf::from("/src/A.php"),
+            "class".to_string(),
+        );
+        for attribute in attributes {
+            class
+                .attributes
+                .insert((*attribute).to_string(), vec![vec![]]);
+        }
+        class
+    }
+
+    #[test]
+    fn test_finds_unknown_attributes() {
+        let mut controller = class("\\App\\Controller\\Home", &["\\App\\Attribute\\Controller"]);
+        let mut attributes = AttributeMap::new();
+        attributes.insert("\\App\\Controller\\Route".to_string(), vec![vec![]]);
+        attributes.insert("\\Override".to_string(), vec![vec![]]);
+        controller.methods.push(PhpMethodMetadata {
+            name: "index".to_string(),
+            visibility: "public".to_string(),
+            modifiers: MethodModifiers::default(),
+            attributes,
+            parameters: vec![],
+            return_type: None,
+        });
+        let classes = [
+            controller,
+            class("\\App\\Attribute\\Controller", &["\\Attribute"]),
+            class(
+                "\\App\\Entity\\User",
+                &[
+                    "\\Doctrine\\ORM\\Mapping\\Entity",
+                    "\\Vendor\\Cache",
+                    "\\App\\Entity\\Table",
+                ],
+            ),
+        ];
+        let known = [
+            "Doctrine\\ORM\\Mapping\\".to_string(),
+            "\\vendor\\cache".to_string(),
+        ];
+
+        assert_eq!(
+            find_unknown_attributes(&classes, &known),
+            vec![
+                UnknownAttribute {
+                    attribute: "\\App\\Controller\\Route".to_string(),
+                    usages: vec![(
+                        "\\App\\Controller\\Home::index()".to_string(),
+                        PathBuf::from("/src/A.php")
+                    )],
+                },
+                UnknownAttribute {
+                    attribute: "\\App\\Entity\\Table".to_string(),
+                    usages: vec![(
+                        "\\App\\Entity\\User".to_string(),
+                        PathBuf::from("/src/A.php")
+                    )],
+                },
+            ]
+        );
+    }
+}
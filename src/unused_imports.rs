@@ -0,0 +1,273 @@
+//! Unused `use` imports (`discovery:export unused-imports`)
+//!
+//! A cheap lint that comes with the parse: every class import is checked
+//! against the names the file references, in declarations, types,
+//! attributes and code (`new`, `::`, `instanceof`, `catch`). Function and
+//! constant imports are not checked, and names that only appear in
+//! docblocks do not count as references, so imports kept for `@var` or
+//! `@throws` annotations are reported.
+
+use crate::parser::PhpMetadataExtractor;
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use tree_sitter::Node;
+
+/// Parents under which a bare name refers to a class
+const CLASS_REFERENCE_PARENTS: &[&str] = &[
+    "attribute",
+    "base_clause",
+    "binary_expression", // instanceof
+    "class_constant_access_expression",
+    "class_interface_clause",
+    "named_type",
+    "object_creation_expression",
+    "scoped_call_expression",
+    "scoped_property_access_expression",
+    "type_list",
+    "use_declaration", // trait use
+];
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UnusedImportsReport {
+    /// Unused imports per file, in source order
+    pub files: BTreeMap<PathBuf, Vec<UnusedImport>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnusedImport {
+    /// Imported class or namespace, without the leading backslash
+    pub name: String,
+    /// Alias given with `as`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// 1-based line of the import
+    pub line: usize,
+}
+
+impl UnusedImportsReport {
+    /// Number of unused imports across all files
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.files.values().map(Vec::len).sum()
+    }
+}
+
+/// Check the given PHP files; unreadable and oversized files are skipped
+#[must_use]
+pub fn build_unused_imports_report(files: &[PathBuf], max_file_size: u64) -> UnusedImportsReport {
+    let files = files
+        .par_iter()
+        .map_init(PhpMetadataExtractor::new, |extractor, path| {
+            let extractor = extractor.as_mut().ok()?;
+            let size = std::fs::metadata(path).ok()?.len();
+            if size > max_file_size {
+                return None;
+            }
+            let content = std::fs::read_to_string(path).ok()?;
+            let unused = find_unused_imports(extractor, &content, path)
+                .map_err(|e| warn!("Could not check imports of {:?}: {}", path, e))
+                .ok()?;
+            (!unused.is_empty()).then(|| (path.clone(), unused))
+        })
+        .flatten()
+        .collect();
+
+    UnusedImportsReport { files }
+}
+
+/// Class imports of one file that no name in it references
+///
+/// # Errors
+///
+/// Returns an error if the source cannot be parsed.
+pub fn find_unused_imports(
+    extractor: &mut PhpMetadataExtractor, content: &str, path: &Path,
+) -> Result<Vec<UnusedImport>> {
+    let (tree, _) = extractor.parse_with_context(content, path)?;
+    let mut imports = Vec::new();
+    let mut references = HashSet::new();
+    collect(tree.root_node(), content, &mut imports, &mut references);
+
+    Ok(imports
+        .into_iter()
+        .filter(|(key, _)| !references.contains(key))
+        .map(|(_, import)| import)
+        .collect())
+}
+
+/// Write the report as JSON if it changed
+///
+/// # Errors
+///
+/// Returns an error if the report cannot be written.
+pub fn write_unused_imports_report(report: &UnusedImportsReport, output_path: &Path) -> Result<()> {
+    let content = serde_json::to_string_pretty(report)? + "\n";
+    crate::writer::write_if_changed(output_path, content.as_bytes()).with_context(|| {
+        format!(
+            "Failed to write unused imports report {}",
+            output_path.display()
+        )
+    })?;
+    Ok(())
+}
+
+/// Collect class imports (keyed by their lowercased alias, as PHP compares
+/// them) and the lowercased first segments of referenced names
+fn collect(
+    node: Node, source: &str, imports: &mut Vec<(String, UnusedImport)>,
+    references: &mut HashSet<String>,
+) {
+    match node.kind() {
+        "namespace_use_declaration" => {
+            imports_of(node, source, imports);
+            return;
+        },
+        "qualified_name" => {
+            let name = text(&node, source);
+            if !name.starts_with('\\') {
+                let first = name.split('\\').next().unwrap_or(name);
+                references.insert(first.to_ascii_lowercase());
+            }
+            return;
+        },
+        "name" => {
+            if node
+                .parent()
+                .is_some_and(|parent| CLASS_REFERENCE_PARENTS.contains(&parent.kind()))
+            {
+                references.insert(text(&node, source).to_ascii_lowercase());
+            }
+            return;
+        },
+        _ => {},
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect(child, source, imports, references);
+    }
+}
+
+/// Class imports of one `use` declaration, group uses included
+fn imports_of(declaration: Node, source: &str, imports: &mut Vec<(String, UnusedImport)>) {
+    if is_function_or_const(&declaration) {
+        return;
+    }
+
+    let mut prefix = String::new();
+    let mut clauses = Vec::new();
+    let mut cursor = declaration.walk();
+    for child in declaration.children(&mut cursor) {
+        match child.kind() {
+            "namespace_name" => prefix = format!("{}\\", text(&child, source)),
+            "namespace_use_clause" => clauses.push(child),
+            "namespace_use_group" => {
+                let mut cursor = child.walk();
+                clauses.extend(
+                    child
+                        .children(&mut cursor)
+                        .filter(|clause| clause.kind() == "namespace_use_clause"),
+                );
+            },
+            _ => {},
+        }
+    }
+
+    for clause in clauses {
+        if is_function_or_const(&clause) {
+            continue;
+        }
+        let alias = clause.child_by_field_name("alias");
+        let mut cursor = clause.walk();
+        let Some(imported) = clause.children(&mut cursor).find(|child| {
+            matches!(child.kind(), "qualified_name" | "name")
+                && alias.is_none_or(|alias| alias.id() != child.id())
+        }) else {
+            continue;
+        };
+
+        let name = format!("{prefix}{}", text(&imported, source));
+        let name = name.trim_start_matches('\\').to_string();
+        let alias = alias.map(|alias| text(&alias, source).to_string());
+        let key = alias
+            .as_deref()
+            .unwrap_or_else(|| name.rsplit('\\').next().unwrap_or(&name))
+            .to_ascii_lowercase();
+        imports.push((
+            key,
+            UnusedImport {
+                name,
+                alias,
+                line: clause.start_position().row + 1,
+            },
+        ));
+    }
+}
+
+/// Whether a `use` declaration or clause imports functions or constants
+fn is_function_or_const(node: &Node) -> bool {
+    let mut cursor = node.walk();
+    node.children(&mut cursor)
+        .any(|child| matches!(child.kind(), "function" | "const"))
+}
+
+fn text<'a>(node: &Node, source: &'a str) -> &'a str {
+    node.utf8_text(source.as_bytes()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    const SOURCE: &str = r"<?php
+namespace App\Controller;
+
+use App\Attribute\Route;
+use App\Entity\{User, Order as PurchaseOrder, Invoice};
+use App\Service;
+use App\Exception\NotFound;
+use Psr\Log\LoggerInterface as Logger;
+use function App\helper;
+use App\Unused;
+
+#[Route('/users')]
+final class UserController extends Service\Base
+{
+    public function show(User $user, Logger $logger): PurchaseOrder
+    {
+        try {
+            return $this->Unused();
+        } catch (NotFound $e) {
+            return \App\Unused::fallback();
+        }
+    }
+}
+";
+
+    #[test]
+    fn test_finds_unused_imports() {
+        let mut extractor = PhpMetadataExtractor::new().unwrap();
+        let unused = find_unused_imports(&mut extractor, SOURCE, Path::new("/src/A.php")).unwrap();
+
+        assert_eq!(
+            unused,
+            [
+                UnusedImport {
+                    name: "App\\Entity\\Invoice".to_string(),
+                    alias: None,
+                    line: 5,
+                },
+                UnusedImport {
+                    name: "App\\Unused".to_string(),
+                    alias: None,
+                    line: 10,
+                },
+            ]
+        );
+    }
+}
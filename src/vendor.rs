@@ -0,0 +1,130 @@
+//! Selective vendor scanning (`--vendor-package`)
+//!
+//! Attribute classes shipped by libraries (Doctrine mappings, Symfony
+//! routes) live under `vendor/`, which is usually far too large to scan
+//! whole. Composer records where it installed every package in
+//! `vendor/composer/installed.json`, so individual packages can be added to
+//! the scanned paths by name. The project root is the nearest directory with
+//! a `composer.json` above the scanned paths; its `config.vendor-dir` is
+//! honored.
+
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Install directories of `packages` (e.g. `doctrine/orm`), in the given order
+///
+/// # Errors
+///
+/// Returns an error if no `composer.json` is found above `scan_paths`, if
+/// `installed.json` cannot be read, or if a package is not installed or has
+/// no files (metapackages).
+pub fn package_paths(scan_paths: &[PathBuf], packages: &[String]) -> Result<Vec<PathBuf>> {
+    let Some(composer_dir) = composer_dir(scan_paths) else {
+        bail!("No composer.json found above the scanned paths");
+    };
+    let installed_path = composer_dir.join("installed.json");
+    let content = std::fs::read_to_string(&installed_path)
+        .with_context(|| format!("Failed to read {}", installed_path.display()))?;
+    let installed: Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", installed_path.display()))?;
+
+    packages
+        .iter()
+        .map(|name| install_path(&installed, &composer_dir, name))
+        .collect()
+}
+
+/// `vendor/composer` of the project the scanned paths belong to
+pub(crate) fn composer_dir(scan_paths: &[PathBuf]) -> Option<PathBuf> {
+    Some(vendor_dir(&composer_root(scan_paths)?).join("composer"))
+}
+
+/// Nearest directory with a `composer.json`, starting from the scanned paths
+fn composer_root(scan_paths: &[PathBuf]) -> Option<PathBuf> {
+    scan_paths.iter().find_map(|path| {
+        std::path::absolute(path)
+            .ok()?
+            .ancestors()
+            .find(|dir| dir.join("composer.json").is_file())
+            .map(Path::to_path_buf)
+    })
+}
+
+/// `config.vendor-dir` of the project's `composer.json` (default `vendor`)
+fn vendor_dir(root: &Path) -> PathBuf {
+    let configured = std::fs::read_to_string(root.join("composer.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .and_then(|composer| {
+            composer["config"]["vendor-dir"]
+                .as_str()
+                .map(str::to_string)
+        });
+    root.join(configured.as_deref().unwrap_or("vendor"))
+}
+
+/// Directory of one package; Composer 1 lists no install paths
+fn install_path(installed: &Value, composer_dir: &Path, name: &str) -> Result<PathBuf> {
+    // Composer 2 wraps the list in `packages`, Composer 1 does not
+    let packages = installed["packages"]
+        .as_array()
+        .or_else(|| installed.as_array())
+        .map_or(&[][..], Vec::as_slice);
+    let Some(package) = packages
+        .iter()
+        .find(|package| package["name"].as_str().is_some_and(|n| n.eq_ignore_ascii_case(name)))
+    else {
+        bail!("Package {name} is not installed (not listed in installed.json)");
+    };
+
+    let dir = match package.get("install-path") {
+        Some(Value::String(path)) => composer_dir.join(path),
+        Some(Value::Null) => bail!("Package {name} has no files to scan (metapackage)"),
+        _ => composer_dir.join("..").join(name),
+    };
+    dir.canonicalize()
+        .with_context(|| format!("Package {name} is not installed at {}", dir.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_package_paths_from_installed_json() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("libs/composer")).unwrap();
+        fs::create_dir_all(root.join("libs/doctrine/orm")).unwrap();
+        fs::write(
+            root.join("composer.json"),
+            r#"{"config": {"vendor-dir": "libs"}}"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("libs/composer/installed.json"),
+            r#"{"packages": [
+                {"name": "doctrine/orm", "install-path": "../doctrine/orm"},
+                {"name": "symfony/pack", "install-path": null}
+            ]}"#,
+        )
+        .unwrap();
+        let scan_paths = [root.join("src")];
+
+        let paths = package_paths(&scan_paths, &["Doctrine/ORM".to_string()]).unwrap();
+        assert_eq!(
+            paths,
+            [root.join("libs/doctrine/orm").canonicalize().unwrap()]
+        );
+
+        let error = package_paths(&scan_paths, &["symfony/pack".to_string()]).unwrap_err();
+        assert!(error.to_string().contains("metapackage"));
+        let error = package_paths(&scan_paths, &["acme/missing".to_string()]).unwrap_err();
+        assert!(error.to_string().contains("not installed"));
+    }
+}
@@ -0,0 +1,220 @@
+//! Daemon warm-up for cold starts (`discovery:warm`)
+//!
+//! The daemon binds its socket only after the initial scan, so a `ping`
+//! answered with `PONG` means the cache is complete. Warming starts the
+//! daemon in the background when nothing answers, polls until it does and
+//! can finally request `getCode` once, so the first PHP request is served
+//! from a warm snapshot (and page cache) instead of paying for the scan.
+
+use crate::service::ServiceSpec;
+use anyhow::{Context, Result, bail};
+use std::io::{Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Interval between readiness checks while the daemon starts
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Timeout of a single IPC request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What warming did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WarmReport {
+    /// Whether the daemon had to be started
+    pub started: bool,
+    /// Time until the daemon answered
+    pub ready_after: Duration,
+    /// Size of the `getCode` response, if requested
+    pub code_bytes: Option<usize>,
+}
+
+/// Make sure the daemon behind `socket` is ready, starting `spec` if needed
+///
+/// # Errors
+///
+/// Returns an error if the daemon cannot be started, is not ready within
+/// `timeout`, or answers `getCode` with an error.
+pub fn warm(
+    spec: &ServiceSpec, socket: &Path, timeout: Duration, get_code: bool,
+) -> Result<WarmReport> {
+    let started_at = Instant::now();
+    let started = ping(socket).is_err();
+
+    if started {
+        let child = spawn(spec)?;
+        wait_until_ready(socket, child, started_at + timeout)?;
+    }
+    let ready_after = started_at.elapsed();
+
+    let code_bytes = if get_code {
+        Some(request_code(socket)?)
+    } else {
+        None
+    };
+
+    Ok(WarmReport {
+        started,
+        ready_after,
+        code_bytes,
+    })
+}
+
+/// Send `ping` and expect `PONG`
+///
+/// # Errors
+///
+/// Returns an error if nothing listens on `socket` or the answer is not `PONG`.
+pub fn ping(socket: &Path) -> Result<()> {
+    let response = request(socket, "ping")?;
+    if response.trim_ascii_end() != b"PONG" {
+        bail!(
+            "Unexpected ping response: {}",
+            String::from_utf8_lossy(&response).trim_end()
+        );
+    }
+    Ok(())
+}
+
+/// Request the PHP cache once; returns its size
+fn request_code(socket: &Path) -> Result<usize> {
+    let code = request(socket, "getCode")?;
+    if code.starts_with(b"ERROR:") {
+        bail!(
+            "Daemon answered getCode with {}",
+            String::from_utf8_lossy(&code).trim_end()
+        );
+    }
+    Ok(code.len())
+}
+
+/// Send one command and read the response until the daemon closes
+pub(crate) fn request(socket: &Path, command: &str) -> Result<Vec<u8>> {
+    let mut stream = UnixStream::connect(socket)
+        .with_context(|| format!("Failed to connect to socket {}", socket.display()))?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.write_all(format!("{command}\n").as_bytes())?;
+    // The daemon closes the connection once it has answered every line
+    stream.shutdown(Shutdown::Write)?;
+
+    let mut response = Vec::new();
+    stream
+        .read_to_end(&mut response)
+        .with_context(|| format!("Failed to read the {command} response"))?;
+    Ok(response)
+}
+
+/// Start the daemon in its own process group, detached from the terminal
+fn spawn(spec: &ServiceSpec) -> Result<Child> {
+    Command::new(&spec.program)
+        .args(&spec.args)
+        .current_dir(&spec.working_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .with_context(|| format!("Failed to start {}", spec.program.display()))
+}
+
+/// Poll `socket` until the daemon answers or `deadline` passes
+///
+/// A child that exits is not fatal by itself: it may have lost the daemon
+/// lock to another instance that is still scanning.
+fn wait_until_ready(socket: &Path, mut child: Child, deadline: Instant) -> Result<()> {
+    let mut exit_status = None;
+    loop {
+        if ping(socket).is_ok() {
+            return Ok(());
+        }
+        if exit_status.is_none() {
+            exit_status = child.try_wait()?;
+        }
+        if Instant::now() >= deadline {
+            if let Some(status) = exit_status {
+                bail!("Daemon exited during startup ({status})");
+            }
+            bail!(
+                "Daemon did not answer on {} before the timeout",
+                socket.display()
+            );
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixListener;
+    use tempfile::TempDir;
+
+    /// Answer `connections` connections like the daemon: one response per line
+    fn fake_daemon(socket: &Path, connections: usize) -> std::thread::JoinHandle<()> {
+        let listener = UnixListener::bind(socket).unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(connections) {
+                let mut stream = stream.unwrap();
+                let reader = BufReader::new(stream.try_clone().unwrap());
+                for line in reader.lines() {
+                    let response: &[u8] = match line.unwrap().as_str() {
+                        "ping" => b"PONG\n",
+                        "getCode" => b"<?php return [];",
+                        _ => b"ERROR: E_INVALID_REQUEST Unknown command\n",
+                    };
+                    stream.write_all(response).unwrap();
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_running_daemon_is_not_started_again() {
+        let temp_dir = TempDir::new().unwrap();
+        let socket = temp_dir.path().join("daemon.sock");
+        let daemon = fake_daemon(&socket, 2);
+        let spec = ServiceSpec {
+            name: "test".to_string(),
+            program: temp_dir.path().join("missing-binary"),
+            args: Vec::new(),
+            working_dir: temp_dir.path().to_path_buf(),
+            log_file: None,
+        };
+
+        let report = warm(&spec, &socket, Duration::from_secs(1), true).unwrap();
+        daemon.join().unwrap();
+
+        assert!(!report.started);
+        assert_eq!(report.code_bytes, Some("<?php return [];".len()));
+    }
+
+    #[test]
+    fn test_reports_daemon_that_never_answers() {
+        let temp_dir = TempDir::new().unwrap();
+        let spec = ServiceSpec {
+            name: "test".to_string(),
+            program: "true".into(),
+            args: Vec::new(),
+            working_dir: temp_dir.path().to_path_buf(),
+            log_file: None,
+        };
+
+        let err = warm(
+            &spec,
+            &temp_dir.path().join("daemon.sock"),
+            Duration::from_millis(300),
+            false,
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().contains("Daemon exited during startup"));
+    }
+}
@@ -0,0 +1,67 @@
+#![allow(unsafe_code)]
+
+//! WebAssembly build of the parser
+//!
+//! Built with
+//! `cargo rustc --release --lib --crate-type cdylib --target wasm32-wasip1 --no-default-features --features wasm`;
+//! tree-sitter's C sources need a clang that targets WebAssembly (e.g. from
+//! wasi-sdk, passed as `CC_wasm32_wasip1`). The module exports the
+//! [`crate::ffi`] functions, so [`aurynx_extract`](crate::ffi::aurynx_extract)
+//! runs the same extractor as the CLI on a source string and needs no
+//! filesystem. `aurynx_scan` only works where the WASI host preopens the
+//! scanned directories.
+//!
+//! Hosts pass a string by reserving `len + 1` bytes with [`aurynx_alloc`],
+//! writing the UTF-8 bytes and a NUL terminator, and releasing the buffer
+//! with [`aurynx_dealloc`] after the call. Results are released with
+//! [`aurynx_free`](crate::ffi::aurynx_free) as usual.
+
+/// Reserve `len` zeroed bytes of linear memory for an argument
+#[unsafe(no_mangle)]
+pub extern "C" fn aurynx_alloc(len: usize) -> *mut u8 {
+    Box::into_raw(vec![0u8; len].into_boxed_slice()).cast::<u8>()
+}
+
+/// Release a buffer returned by [`aurynx_alloc`]
+///
+/// # Safety
+///
+/// `ptr` must be NULL or come from [`aurynx_alloc`] called with the same
+/// `len`, and must not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aurynx_dealloc(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        // SAFETY: `ptr` and `len` describe the boxed slice leaked by `aurynx_alloc`
+        drop(unsafe { Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len)) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use crate::ffi::{aurynx_extract, aurynx_free};
+    use std::ffi::CStr;
+
+    #[test]
+    fn test_extract_through_allocated_buffer() {
+        let source = b"<?php namespace App; interface Clock {}";
+        let len = source.len() + 1;
+        let buffer = aurynx_alloc(len);
+        unsafe { std::ptr::copy_nonoverlapping(source.as_ptr(), buffer, source.len()) };
+
+        let result = unsafe { aurynx_extract(buffer.cast(), std::ptr::null()) };
+        let json = unsafe { CStr::from_ptr(result) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        unsafe {
+            aurynx_free(result);
+            aurynx_dealloc(buffer, len);
+        }
+
+        let response: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(response["classes"][0]["type"], "interface");
+    }
+}
@@ -67,7 +67,7 @@ pub fn watch_directory(paths: &[PathBuf], ignored: &[String], output: &Path) ->
                         continue;
                     }
 
-                    if path.extension().is_some_and(|ext| ext == "php") {
+                    if crate::frontend::Frontends::default().is_source_file(&path) {
                         if path.exists() {
                             // File created or modified
                             if let Ok(content) = fs::read_to_string(&path) {
@@ -0,0 +1,275 @@
+//! Watchman watcher backend
+//!
+//! Subscribes to an already running Watchman server over its JSON socket
+//! protocol and forwards file changes as `notify` events, so the daemon
+//! pipeline (batching, ignore filtering, rescans) is shared with the native
+//! backends. Watchman keeps its own recursive watches, so large monorepos
+//! that already run it avoid a second set of inotify/FSEvents watches.
+
+use notify::event::{CreateKind, DataChange, ModifyKind, RemoveKind};
+use notify::{Event, EventHandler, EventKind, RecursiveMode, Watcher, WatcherKind};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Subscription name registered with Watchman
+const SUBSCRIPTION_NAME: &str = "aurynx";
+
+/// A single Watchman subscription (one socket connection per watched path)
+struct Subscription {
+    stream: UnixStream,
+    closed: Arc<AtomicBool>,
+}
+
+/// `notify::Watcher` implementation backed by a Watchman server
+pub struct WatchmanWatcher {
+    handler: Arc<Mutex<dyn EventHandler>>,
+    sockname: PathBuf,
+    subscriptions: HashMap<PathBuf, Subscription>,
+}
+
+impl WatchmanWatcher {
+    /// Locate the Watchman socket (`WATCHMAN_SOCK` or `watchman get-sockname`)
+    fn sockname() -> notify::Result<PathBuf> {
+        if let Some(sock) = std::env::var_os("WATCHMAN_SOCK") {
+            return Ok(PathBuf::from(sock));
+        }
+
+        let output = Command::new("watchman")
+            .args(["--no-pretty", "get-sockname"])
+            .output()
+            .map_err(|e| notify::Error::generic(&format!("Failed to run watchman: {e}")))?;
+        if !output.status.success() {
+            return Err(notify::Error::generic(&format!(
+                "watchman get-sockname failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let response: Value = serde_json::from_slice(&output.stdout)
+            .map_err(|e| notify::Error::generic(&format!("Invalid watchman response: {e}")))?;
+        response["unix_domain"]
+            .as_str()
+            .or_else(|| response["sockname"].as_str())
+            .map(PathBuf::from)
+            .ok_or_else(|| notify::Error::generic("watchman did not report a socket path"))
+    }
+}
+
+impl Watcher for WatchmanWatcher {
+    fn new<F: EventHandler>(event_handler: F, _config: notify::Config) -> notify::Result<Self> {
+        Ok(Self {
+            handler: Arc::new(Mutex::new(event_handler)),
+            sockname: Self::sockname()?,
+            subscriptions: HashMap::new(),
+        })
+    }
+
+    fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> notify::Result<()> {
+        // Re-registering (e.g. after a full rescan) must not duplicate events
+        if self.subscriptions.contains_key(path) {
+            return Ok(());
+        }
+
+        let stream = UnixStream::connect(&self.sockname).map_err(notify::Error::io)?;
+        let mut reader = BufReader::new(stream.try_clone().map_err(notify::Error::io)?);
+        let mut writer = stream.try_clone().map_err(notify::Error::io)?;
+
+        let project = request(&mut writer, &mut reader, &json!(["watch-project", path]))?;
+        let root = project["watch"]
+            .as_str()
+            .ok_or_else(|| notify::Error::generic("watch-project returned no root"))?;
+
+        // Names are reported relative to `path` itself
+        let mut query = json!({
+            "expression": ["type", "f"],
+            "fields": ["name", "exists", "new"],
+        });
+        if let Some(relative) = project["relative_path"].as_str() {
+            query["relative_root"] = json!(relative);
+        }
+        request(
+            &mut writer,
+            &mut reader,
+            &json!(["subscribe", root, SUBSCRIPTION_NAME, query]),
+        )?;
+
+        let closed = Arc::new(AtomicBool::new(false));
+        let handler = Arc::clone(&self.handler);
+        let base = path.to_path_buf();
+        let recursive = recursive_mode == RecursiveMode::Recursive;
+        let thread_closed = Arc::clone(&closed);
+
+        std::thread::spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else { break };
+                let Ok(pdu) = serde_json::from_str::<Value>(&line) else {
+                    continue;
+                };
+                let events = events_from_pdu(&pdu, &base, recursive);
+                let mut handler = handler
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner);
+                for event in events {
+                    handler.handle_event(Ok(event));
+                }
+            }
+
+            if !thread_closed.load(Ordering::Relaxed) {
+                let mut handler = handler
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner);
+                handler.handle_event(Err(notify::Error::generic(
+                    "Watchman subscription closed unexpectedly",
+                )
+                .add_path(base)));
+            }
+        });
+
+        self.subscriptions
+            .insert(path.to_path_buf(), Subscription { stream, closed });
+        Ok(())
+    }
+
+    fn unwatch(&mut self, path: &Path) -> notify::Result<()> {
+        let subscription = self
+            .subscriptions
+            .remove(path)
+            .ok_or_else(|| notify::Error::watch_not_found().add_path(path.to_path_buf()))?;
+        subscription.close();
+        Ok(())
+    }
+
+    fn kind() -> WatcherKind {
+        // notify has no variant for external backends
+        WatcherKind::NullWatcher
+    }
+}
+
+impl Subscription {
+    fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+    }
+}
+
+impl Drop for WatchmanWatcher {
+    fn drop(&mut self) {
+        for subscription in self.subscriptions.values() {
+            subscription.close();
+        }
+    }
+}
+
+/// Send one command and wait for its (non-unilateral) response
+fn request(
+    writer: &mut UnixStream, reader: &mut BufReader<UnixStream>, command: &Value,
+) -> notify::Result<Value> {
+    writeln!(writer, "{command}").map_err(notify::Error::io)?;
+    writer.flush().map_err(notify::Error::io)?;
+
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).map_err(notify::Error::io)? == 0 {
+            return Err(notify::Error::generic("Watchman closed the connection"));
+        }
+        let response: Value = serde_json::from_str(&line)
+            .map_err(|e| notify::Error::generic(&format!("Invalid watchman response: {e}")))?;
+        if response["unilateral"].as_bool() == Some(true) {
+            continue;
+        }
+        if let Some(error) = response["error"].as_str() {
+            return Err(notify::Error::generic(&format!("watchman: {error}")));
+        }
+        return Ok(response);
+    }
+}
+
+/// Convert a subscription PDU into `notify` events for files under `base`
+///
+/// Fresh-instance results (the initial file listing) are skipped because the
+/// daemon already performed its own initial scan.
+fn events_from_pdu(pdu: &Value, base: &Path, recursive: bool) -> Vec<Event> {
+    if pdu["subscription"].as_str() != Some(SUBSCRIPTION_NAME)
+        || pdu["is_fresh_instance"].as_bool() == Some(true)
+    {
+        return Vec::new();
+    }
+    let Some(files) = pdu["files"].as_array() else {
+        return Vec::new();
+    };
+
+    files
+        .iter()
+        .filter_map(|file| {
+            let name = file["name"].as_str()?;
+            if !recursive && name.contains('/') {
+                return None;
+            }
+            let kind = if file["exists"].as_bool() == Some(false) {
+                EventKind::Remove(RemoveKind::File)
+            } else if file["new"].as_bool() == Some(true) {
+                EventKind::Create(CreateKind::File)
+            } else {
+                EventKind::Modify(ModifyKind::Data(DataChange::Any))
+            };
+            Some(Event::new(kind).add_path(base.join(name)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_from_pdu_maps_file_states() {
+        let pdu = json!({
+            "subscription": SUBSCRIPTION_NAME,
+            "unilateral": true,
+            "files": [
+                {"name": "src/New.php", "exists": true, "new": true},
+                {"name": "src/Changed.php", "exists": true, "new": false},
+                {"name": "Gone.php", "exists": false, "new": false},
+            ],
+        });
+
+        let events = events_from_pdu(&pdu, Path::new("/project"), true);
+
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0].kind, EventKind::Create(_)));
+        assert_eq!(events[0].paths, vec![PathBuf::from("/project/src/New.php")]);
+        assert!(matches!(events[1].kind, EventKind::Modify(_)));
+        assert!(matches!(events[2].kind, EventKind::Remove(_)));
+    }
+
+    #[test]
+    fn test_events_from_pdu_skips_fresh_instance_and_nested_non_recursive() {
+        let fresh = json!({
+            "subscription": SUBSCRIPTION_NAME,
+            "is_fresh_instance": true,
+            "files": [{"name": "A.php", "exists": true, "new": true}],
+        });
+        assert!(events_from_pdu(&fresh, Path::new("/project"), true).is_empty());
+
+        let update = json!({
+            "subscription": SUBSCRIPTION_NAME,
+            "files": [
+                {"name": "composer.lock", "exists": true, "new": false},
+                {"name": "src/A.php", "exists": true, "new": false},
+            ],
+        });
+        let events = events_from_pdu(&update, Path::new("/project"), false);
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].paths,
+            vec![PathBuf::from("/project/composer.lock")]
+        );
+    }
+}
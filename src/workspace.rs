@@ -0,0 +1,624 @@
+//! One-shot scans of several projects of a monorepo
+//!
+//! A workspace file lists the config files of the projects (usually each
+//! package's `aurynx.json`). `discovery:scan --workspace` scans them
+//! concurrently on a single rayon pool, so the packages share one thread
+//! budget instead of each sizing its own, and prints a combined summary.
+//!
+//! Paths in a project config (`paths`, `output`, `path_root`, `queries`,
+//! `allowed_roots`, `key_file`, exporter, artifact and derived outputs) are
+//! relative to that config file, so every package keeps the config it uses
+//! on its own. Each project is scanned with its own settings (`on_error`,
+//! `php_version`, `frontends`, `encryption`...) and writes its cache,
+//! manifest, report and other outputs like `discovery:scan` does.
+
+use crate::artifacts::ArtifactMapping;
+use crate::class_lists::ClassList;
+use crate::collisions::CaseCollision;
+use crate::config::ConfigFile;
+use crate::derived::DerivedOutput;
+use crate::encryption::EncryptionConfig;
+use crate::error::{AurynxError, Result};
+use crate::exporters::ExporterConfig;
+use crate::incremental::{MANIFEST_FILE, MANIFEST_LOCK_TIMEOUT, Manifest, ManifestLock};
+use crate::member_limit::OversizedClass;
+use crate::pipeline::OutputPasses;
+use crate::scan_report::ScanReport;
+use crate::scanner::{ExtractorPool, OnError};
+use anyhow::{Context, bail};
+use rayon::prelude::*;
+use serde::Deserialize;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Default workspace file name
+pub const WORKSPACE_FILE: &str = "aurynx.workspace.json";
+
+/// Contents of a workspace file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceConfig {
+    /// Project config files, relative to the workspace file
+    pub projects: Vec<PathBuf>,
+    /// Worker threads shared by all projects (default: one per CPU)
+    pub threads: Option<usize>,
+}
+
+impl WorkspaceConfig {
+    /// Load a workspace file, resolving project paths against its directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed, lists no
+    /// projects, or sets `threads` to 0.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            AurynxError::io_error(
+                format!("Failed to read workspace file: {}", path.display()),
+                e,
+            )
+        })?;
+        let mut workspace: Self = serde_json::from_str(&content).map_err(|e| {
+            AurynxError::json_error(
+                format!("Failed to parse workspace file: {}", path.display()),
+                e,
+            )
+        })?;
+
+        if workspace.projects.is_empty() {
+            return Err(AurynxError::config_error(format!(
+                "Workspace file {} lists no projects",
+                path.display()
+            )));
+        }
+        if workspace.threads == Some(0) {
+            return Err(AurynxError::config_error(
+                "Workspace threads must be at least 1",
+            ));
+        }
+
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        for project in &mut workspace.projects {
+            *project = base.join(&*project);
+        }
+        Ok(workspace)
+    }
+}
+
+/// Cache settings applied to every project
+#[derive(Debug, Clone, Copy)]
+pub struct WorkspaceOptions<'a> {
+    /// Cache format: "php" or "json"
+    pub format: &'a str,
+    /// Pretty-print every cache (projects can also enable it in their config)
+    pub pretty: bool,
+    /// `on_error` policy overriding the projects' own (`--on-error`)
+    pub on_error: Option<OnError>,
+}
+
+/// Outcome of one project
+#[derive(Debug)]
+pub struct ProjectReport {
+    /// Project config file
+    pub config: PathBuf,
+    pub result: anyhow::Result<ProjectSummary>,
+}
+
+/// What a successful project scan produced
+#[derive(Debug, Clone)]
+pub struct ProjectSummary {
+    pub output: PathBuf,
+    pub classes: usize,
+    /// PHP files in the manifest after the scan
+    pub files: usize,
+    /// Files that could not be scanned
+    pub failed: usize,
+    /// Whether the cache changed on disk
+    pub written: bool,
+    /// Paths and class names that only differ in case
+    pub collisions: Vec<CaseCollision>,
+    /// Classes cut to `max_class_members`
+    pub truncated: Vec<OversizedClass>,
+    /// `OpenAPI` handlers left out because their path is not a literal
+    pub skipped_handlers: Vec<String>,
+    /// Exporters that failed, with the reason
+    pub failed_exporters: Vec<(String, String)>,
+    pub duration: Duration,
+}
+
+/// Scan every project of `workspace`, in the order they are listed
+///
+/// # Errors
+///
+/// Returns an error only if the thread pool cannot be created; failing
+/// projects are reported in their [`ProjectReport`].
+pub fn scan_workspace(
+    workspace: &WorkspaceConfig, options: WorkspaceOptions<'_>,
+) -> anyhow::Result<Vec<ProjectReport>> {
+    let mut pool = rayon::ThreadPoolBuilder::new();
+    // `--nice` caps the pool as well
+    if let Some(threads) = workspace.threads.into_iter().chain(crate::nice::threads()).min() {
+        pool = pool.num_threads(threads);
+    }
+    let pool = pool
+        .build()
+        .context("Failed to create the workspace thread pool")?;
+
+    // Syntax errors are recorded for every project; each report takes its own
+    crate::scan_report::start();
+    // Project scans and the per-file parsing inside them share the pool
+    let reports = pool.install(|| {
+        workspace
+            .projects
+            .par_iter()
+            .map(|config| ProjectReport {
+                config: config.clone(),
+                result: scan_project(config, options),
+            })
+            .collect()
+    });
+    crate::scan_report::stop();
+    Ok(reports)
+}
+
+/// Run the one-shot scan of a single project config
+fn scan_project(
+    config_path: &Path, options: WorkspaceOptions<'_>,
+) -> anyhow::Result<ProjectSummary> {
+    let started = Instant::now();
+    let config = ConfigFile::load(Some(config_path.to_path_buf()))?;
+    let base = config_path.parent().unwrap_or_else(|| Path::new(""));
+
+    let key = config
+        .encryption
+        .as_ref()
+        .map(|encryption| {
+            EncryptionConfig {
+                key_file: encryption.key_file.as_ref().map(|file| base.join(file)),
+                ..encryption.clone()
+            }
+            .load_key()
+        })
+        .transpose()?;
+    if key.is_some() && options.format == "php" {
+        bail!("encryption requires a JSON cache (use --format json)");
+    }
+    // The project's cache and manifest are read and written on this thread
+    crate::encryption::with_key(key, || {
+        scan_project_with(&config, base, options, started)
+    })
+}
+
+fn scan_project_with(
+    config: &ConfigFile, base: &Path, options: WorkspaceOptions<'_>, started: Instant,
+) -> anyhow::Result<ProjectSummary> {
+    let paths = project_paths(config, base)?;
+    let output = base.join(
+        config
+            .output
+            .as_ref()
+            .context("'output' is required in workspace projects")?,
+    );
+    let ignore = config.ignore.clone().unwrap_or_default();
+    let output_exclude = config.output_exclude.clone().unwrap_or_default();
+    let manifest_path = output.parent().map_or_else(
+        || PathBuf::from(MANIFEST_FILE),
+        |dir| dir.join(MANIFEST_FILE),
+    );
+    let report_path = config
+        .report
+        .unwrap_or(false)
+        .then(|| manifest_path.with_file_name(crate::scan_report::REPORT_FILE));
+    let mut scan_options = config.scan_options(base)?;
+    if let Some(on_error) = options.on_error {
+        scan_options.on_error = on_error;
+    }
+    let pool = ExtractorPool::new(scan_options);
+
+    let _lock = ManifestLock::acquire(&manifest_path, MANIFEST_LOCK_TIMEOUT)?;
+
+    // Without --incremental, start from an empty manifest (full scan)
+    let incremental = config.incremental.unwrap_or(false);
+    let previous = if incremental {
+        Manifest::load(&manifest_path)?
+    } else {
+        Manifest::default()
+    };
+    let (mut metadata, manifest) = crate::incremental::scan_with_manifest(
+        previous,
+        &paths,
+        &ignore,
+        config.max_file_size_bytes(),
+        &pool,
+        false,
+    )?;
+
+    let scan_time = started.elapsed();
+    let mut scan_report = report_path.as_ref().map(|_| {
+        let mode = if incremental { "incremental" } else { "full" };
+        ScanReport::for_files_of(mode, &manifest, &metadata)
+    });
+    if let Err(e) = check_failures(pool.options().on_error, &manifest) {
+        if let (Some(report), Some(report_path)) = (&mut scan_report, &report_path) {
+            report.status = "failed";
+            report.set_timing(scan_time, started.elapsed());
+            crate::scan_report::write_report(report, report_path)?;
+        }
+        bail!(e);
+    }
+    let failed = manifest.failed_files().len();
+    let collisions = crate::collisions::find_case_collisions(&metadata);
+
+    let prepared = OutputPasses {
+        inherit_attributes: config.inherit_attributes.unwrap_or(false),
+        resolve_hierarchy: config.resolve_hierarchy.unwrap_or(false),
+        signature_hash: config.signature_hash.unwrap_or(false),
+        max_class_members: config.max_class_members,
+        output_exclude: crate::scanner::ignore_matcher(&paths, &output_exclude).as_ref(),
+    }
+    .apply(&mut metadata);
+    if let Some(report) = &mut scan_report {
+        report.stats.excluded = prepared.excluded;
+        report.truncated.clone_from(&prepared.truncated);
+    }
+
+    // Built from local paths, before the path_map rewrite
+    let skipped_handlers = write_generated(config, base, &metadata)?;
+
+    let written = write_cache(config, base, options, &mut metadata, &output)?;
+
+    write_cache_outputs(config, base, &metadata)?;
+
+    let outcomes = run_exporters(config, base, &metadata, &output);
+    let failed_exporters = outcomes
+        .iter()
+        .filter_map(|outcome| {
+            outcome
+                .error
+                .clone()
+                .map(|error| (outcome.name.clone(), error))
+        })
+        .collect();
+
+    manifest
+        .save(&manifest_path)
+        .with_context(|| format!("Failed to save manifest {}", manifest_path.display()))?;
+
+    if let (Some(mut report), Some(report_path)) = (scan_report, &report_path) {
+        report.exporters = outcomes;
+        report.set_timing(scan_time, started.elapsed());
+        crate::scan_report::write_report(&report, report_path)?;
+    }
+
+    Ok(ProjectSummary {
+        output,
+        classes: metadata.len(),
+        files: manifest.files.len(),
+        failed,
+        written,
+        collisions,
+        truncated: prepared.truncated,
+        skipped_handlers,
+        failed_exporters,
+        duration: started.elapsed(),
+    })
+}
+
+/// Error naming the failed files under `on_error: fail`
+fn check_failures(policy: OnError, manifest: &Manifest) -> std::result::Result<(), String> {
+    let failed = manifest.failed_files();
+    crate::scanner::check_failures(
+        policy,
+        failed.iter().map(|(path, entry)| {
+            (
+                Path::new(*path),
+                entry.parse_error.as_deref().unwrap_or_default(),
+            )
+        }),
+    )
+}
+
+/// Write the project's cache with its `path_map` applied; returns whether
+/// it changed
+fn write_cache(
+    config: &ConfigFile, base: &Path, options: WorkspaceOptions<'_>,
+    metadata: &mut [crate::metadata::PhpClassMetadata], output: &Path,
+) -> anyhow::Result<bool> {
+    let path_map = crate::path_map::PathMap::new(&config.path_map.clone().unwrap_or_default());
+    path_map.apply(metadata);
+    let path_root = config
+        .path_root
+        .as_ref()
+        .map(|root| path_map.to_mapped(&crate::writer::canonical_path_root(&base.join(root))));
+
+    crate::writer::write_cache_if_changed(
+        metadata,
+        output,
+        options.format,
+        options.pretty || config.pretty.unwrap_or(false),
+        path_root.as_deref(),
+        config.cache_layout.unwrap_or_default(),
+    )
+    .with_context(|| format!("Failed to write cache {}", output.display()))
+}
+
+/// Write the project's `OpenAPI` document and artifacts, relative to its
+/// directory
+///
+/// Returns the `OpenAPI` handlers skipped because their path is not a literal.
+fn write_generated(
+    config: &ConfigFile, base: &Path, metadata: &[crate::metadata::PhpClassMetadata],
+) -> anyhow::Result<Vec<String>> {
+    let skipped = if let Some(openapi) = &config.openapi
+        && let Some(output) = &openapi.output
+    {
+        let export = crate::openapi::generate_openapi(metadata, openapi);
+        crate::openapi::write_openapi(&export.document, &base.join(output))
+            .context("Failed to write OpenAPI document")?;
+        export.skipped
+    } else {
+        Vec::new()
+    };
+
+    let artifacts: Vec<ArtifactMapping> = config
+        .artifacts
+        .iter()
+        .flatten()
+        .map(|artifact| ArtifactMapping {
+            output: base.join(&artifact.output),
+            ..artifact.clone()
+        })
+        .collect();
+    crate::artifacts::write_artifacts(metadata, &artifacts).context("Failed to write artifact")?;
+    Ok(skipped)
+}
+
+/// Write the project's class lists and derived outputs, relative to its
+/// directory
+fn write_cache_outputs(
+    config: &ConfigFile, base: &Path, metadata: &[crate::metadata::PhpClassMetadata],
+) -> anyhow::Result<()> {
+    let class_lists: Vec<ClassList> = config
+        .class_lists
+        .iter()
+        .flatten()
+        .map(|list| ClassList {
+            output: base.join(&list.output),
+            ..list.clone()
+        })
+        .collect();
+    crate::class_lists::write_class_lists(metadata, &class_lists)
+        .context("Failed to write class list")?;
+    let derived: Vec<DerivedOutput> = config
+        .derived
+        .iter()
+        .flatten()
+        .map(|derived| DerivedOutput {
+            output: base.join(&derived.output),
+            ..derived.clone()
+        })
+        .collect();
+    crate::derived::write_derived(metadata, &derived).context("Failed to write derived output")?;
+    Ok(())
+}
+
+/// Scanned paths of a project: `paths` and `vendor_packages`
+///
+/// The scan checks them against the project's `allowed_roots`.
+fn project_paths(config: &ConfigFile, base: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = config
+        .paths
+        .as_ref()
+        .context("'paths' is required in workspace projects")?
+        .iter()
+        .map(|path| base.join(path))
+        .collect();
+    if let Some(packages) = config.vendor_packages.as_ref().filter(|p| !p.is_empty()) {
+        let package_paths = crate::vendor::package_paths(&paths, packages)?;
+        paths.extend(package_paths);
+    }
+    Ok(paths)
+}
+
+/// Run the project's exporters from its directory
+fn run_exporters(
+    config: &ConfigFile, base: &Path, metadata: &[crate::metadata::PhpClassMetadata],
+    output: &Path,
+) -> Vec<crate::exporters::ExporterOutcome> {
+    let exporters: Vec<ExporterConfig> = config
+        .exporters
+        .iter()
+        .flatten()
+        .map(|exporter| ExporterConfig {
+            output: exporter.output.as_ref().map(|file| base.join(file)),
+            ..exporter.clone()
+        })
+        .collect();
+    let output = std::path::absolute(output).unwrap_or_else(|_| output.to_path_buf());
+    crate::exporters::run_exporters(metadata, &exporters, &output, Some(base))
+}
+
+/// Combined summary: one line per project, then the totals
+#[must_use]
+pub fn format_summary(reports: &[ProjectReport], elapsed: Duration) -> String {
+    let mut summary = String::new();
+    let (mut classes, mut files, mut failed_files, mut failed_projects) = (0, 0, 0, 0);
+
+    for report in reports {
+        match &report.result {
+            Ok(project) => {
+                classes += project.classes;
+                files += project.files;
+                failed_files += project.failed;
+                let _ = writeln!(
+                    summary,
+                    "  ok    {}: {} classes, {} files, {} failed, {:.2}s, cache {}",
+                    report.config.display(),
+                    project.classes,
+                    project.files,
+                    project.failed,
+                    project.duration.as_secs_f64(),
+                    if project.written {
+                        "written"
+                    } else {
+                        "unchanged"
+                    }
+                );
+                for collision in &project.collisions {
+                    let _ = writeln!(summary, "        warning: case collision: {collision}");
+                }
+                for class in &project.truncated {
+                    let _ = writeln!(
+                        summary,
+                        "        warning: over max_class_members, truncated: {class}"
+                    );
+                }
+                for handler in &project.skipped_handlers {
+                    let _ = writeln!(
+                        summary,
+                        "        warning: OpenAPI: skipped {handler} (path is not a literal)"
+                    );
+                }
+                for (name, error) in &project.failed_exporters {
+                    let _ = writeln!(summary, "        warning: exporter '{name}' failed: {error}");
+                }
+            },
+            Err(e) => {
+                failed_projects += 1;
+                let _ = writeln!(summary, "  error {}: {e:#}", report.config.display());
+            },
+        }
+    }
+
+    let _ = writeln!(
+        summary,
+        "Workspace: {} projects ({failed_projects} failed), {classes} classes, {files} files ({failed_files} failed) in {:.2}s",
+        reports.len(),
+        elapsed.as_secs_f64()
+    );
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn project(root: &Path, name: &str, class: &str) {
+        let dir = root.join("packages").join(name);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(
+            dir.join("src").join(format!("{class}.php")),
+            format!("<?php namespace Pkg; class {class} {{}}"),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("aurynx.json"),
+            r#"{"paths": ["src"], "output": "var/cache.json"}"#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_scans_projects_relative_to_their_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        project(root, "a", "Alpha");
+        project(root, "b", "Beta");
+        fs::write(
+            root.join(WORKSPACE_FILE),
+            r#"{"projects": ["packages/a/aurynx.json", "packages/b/aurynx.json", "packages/c/aurynx.json"], "threads": 2}"#,
+        )
+        .unwrap();
+
+        let workspace = WorkspaceConfig::load(&root.join(WORKSPACE_FILE)).unwrap();
+        let options = WorkspaceOptions {
+            format: "json",
+            pretty: false,
+            on_error: None,
+        };
+        let reports = scan_workspace(&workspace, options).unwrap();
+
+        let alpha = reports[0].result.as_ref().unwrap();
+        assert_eq!(alpha.classes, 1);
+        assert!(alpha.written);
+        let cache = fs::read_to_string(root.join("packages/b/var/cache.json")).unwrap();
+        assert!(cache.contains("Beta"));
+        assert!(reports[2].result.is_err());
+
+        let summary = format_summary(&reports, Duration::from_secs(1));
+        assert!(summary.ends_with(
+            "Workspace: 3 projects (1 failed), 2 classes, 2 files (0 failed) in 1.00s\n"
+        ));
+    }
+
+    #[test]
+    fn test_projects_keep_their_own_settings() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        project(root, "a", "Alpha");
+        project(root, "b", "Beta");
+        project(root, "c", "Gamma");
+        for name in ["a", "b"] {
+            fs::write(
+                root.join("packages").join(name).join("src/View.phtml"),
+                "<?php namespace Pkg; class View { function a() {} function b() {} } ?>",
+            )
+            .unwrap();
+        }
+        fs::write(
+            root.join("packages/a/aurynx.json"),
+            r#"{"paths": ["src"], "output": "var/cache.json", "frontends": ["phtml"], "max_class_members": 1}"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("packages/c/aurynx.json"),
+            r#"{"paths": ["src"], "output": "var/cache.json", "report": true,
+                "class_lists": [{"kind": "interface", "output": "var/interfaces.php"}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join(WORKSPACE_FILE),
+            r#"{"projects": ["packages/a/aurynx.json", "packages/b/aurynx.json", "packages/c/aurynx.json"]}"#,
+        )
+        .unwrap();
+
+        let workspace = WorkspaceConfig::load(&root.join(WORKSPACE_FILE)).unwrap();
+        let options = WorkspaceOptions {
+            format: "json",
+            pretty: false,
+            on_error: None,
+        };
+        let reports = scan_workspace(&workspace, options).unwrap();
+
+        let alpha = reports[0].result.as_ref().unwrap();
+        assert_eq!(alpha.classes, 2);
+        assert_eq!(alpha.truncated.len(), 1);
+        let cache = fs::read_to_string(root.join("packages/a/var/cache.json")).unwrap();
+        assert!(cache.contains("\"truncated\""));
+        assert_eq!(reports[1].result.as_ref().unwrap().classes, 1);
+        assert!(reports[2].result.is_ok());
+        assert!(root.join("packages/c/var/interfaces.php").exists());
+        let report = fs::read_to_string(root.join("packages/c/var/aurynx.report.json")).unwrap();
+        assert!(report.contains("\"status\": \"ok\""));
+        assert!(!root.join("packages/a/var/aurynx.report.json").exists());
+
+        let summary = format_summary(&reports, Duration::from_secs(1));
+        assert!(summary.contains("warning: over max_class_members, truncated: \\Pkg\\View"));
+    }
+
+    #[test]
+    fn test_rejects_empty_workspace() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(WORKSPACE_FILE);
+        fs::write(&path, r#"{"projects": []}"#).unwrap();
+
+        let err = WorkspaceConfig::load(&path).unwrap_err();
+
+        assert!(err.to_string().contains("no projects"));
+    }
+}
@@ -1,8 +1,96 @@
-use crate::metadata::{AttributeArgument, PhpClassMetadata};
+use crate::metadata::{
+    AttributeArgument, AttributeMap, CustomCaptures, PhpClassMetadata, PhpFileContext,
+    TruncatedMembers,
+};
 use anyhow::Result;
+use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// Shape of the PHP cache array (`cache_layout`)
+///
+/// The default is one flat `FQCN => class` map in the given order. Grouped
+/// caches nest those maps in one array per group, keyed by the namespace or
+/// attribute (without the leading backslash); classes without a namespace
+/// or class attribute are grouped under `''`, so every class stays in the
+/// cache. JSON caches and `getCode` responses always use the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub struct CacheLayout {
+    pub group_by: Option<CacheGroup>,
+    pub sort: Option<CacheSort>,
+}
+
+/// Grouping of the PHP cache array
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheGroup {
+    Namespace,
+    /// One group per class attribute; a class appears in each of its groups
+    Attribute,
+}
+
+/// Order of the classes in the PHP cache array
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheSort {
+    Fqcn,
+    /// By file path, then FQCN
+    File,
+}
+
+impl CacheLayout {
+    /// Whether the cache is the plain flat map
+    #[must_use]
+    pub fn is_default(self) -> bool {
+        self == Self::default()
+    }
+
+    fn sorted(self, metadata_list: &[PhpClassMetadata]) -> Vec<&PhpClassMetadata> {
+        let mut classes: Vec<&PhpClassMetadata> = metadata_list.iter().collect();
+        match self.sort {
+            None => {},
+            Some(CacheSort::Fqcn) => classes.sort_by(|a, b| a.fqcn.cmp(&b.fqcn)),
+            Some(CacheSort::File) => {
+                classes.sort_by(|a, b| a.file.cmp(&b.file).then_with(|| a.fqcn.cmp(&b.fqcn)));
+            },
+        }
+        classes
+    }
+}
+
+impl CacheGroup {
+    /// Classes per group key, keeping their order within each group
+    fn groups<'a>(
+        self, classes: &[&'a PhpClassMetadata],
+    ) -> BTreeMap<String, Vec<&'a PhpClassMetadata>> {
+        let mut groups: BTreeMap<String, Vec<&PhpClassMetadata>> = BTreeMap::new();
+        for class in classes {
+            match self {
+                Self::Namespace => {
+                    let fqcn = class.fqcn.trim_start_matches('\\');
+                    let namespace = fqcn
+                        .rsplit_once('\\')
+                        .map_or("", |(namespace, _)| namespace);
+                    groups.entry(namespace.to_string()).or_default().push(class);
+                },
+                Self::Attribute if class.attributes.is_empty() => {
+                    groups.entry(String::new()).or_default().push(class);
+                },
+                Self::Attribute => {
+                    for attribute in class.attributes.keys() {
+                        groups
+                            .entry(attribute.trim_start_matches('\\').to_string())
+                            .or_default()
+                            .push(class);
+                    }
+                },
+            }
+        }
+        groups
+    }
+}
 
 pub fn write_php_cache(
     metadata_list: &[PhpClassMetadata],
@@ -15,7 +103,50 @@ pub fn write_php_cache(
     }
 
     let file = File::create(output_path)?;
-    let mut writer = PhpFormatter::new(file, pretty);
+    render_php_cache(metadata_list, file, pretty)
+}
+
+/// Render the PHP cache into any writer
+///
+/// # Errors
+///
+/// Returns an error if writing to `out` fails.
+pub fn render_php_cache<W: Write>(
+    metadata_list: &[PhpClassMetadata], out: W, pretty: bool,
+) -> Result<()> {
+    render_php_cache_with_root(metadata_list, out, pretty, None)
+}
+
+/// Render the PHP cache, prefixing relative `file` paths with `file_root`
+///
+/// `file_root` is a PHP expression for the project root (see
+/// [`php_root_expr`]), so relative paths resolve when the cache is loaded.
+///
+/// # Errors
+///
+/// Returns an error if writing to `out` fails.
+pub fn render_php_cache_with_root<W: Write>(
+    metadata_list: &[PhpClassMetadata], out: W, pretty: bool, file_root: Option<&str>,
+) -> Result<()> {
+    render_php_cache_with_layout(
+        metadata_list,
+        out,
+        pretty,
+        file_root,
+        CacheLayout::default(),
+    )
+}
+
+/// Render the PHP cache with the sort order and grouping of `layout`
+///
+/// # Errors
+///
+/// Returns an error if writing to `out` fails.
+pub fn render_php_cache_with_layout<W: Write>(
+    metadata_list: &[PhpClassMetadata], out: W, pretty: bool, file_root: Option<&str>,
+    layout: CacheLayout,
+) -> Result<()> {
+    let mut writer = PhpFormatter::new(out, pretty);
 
     writer.writeln("<?php")?;
     if pretty {
@@ -31,8 +162,36 @@ pub fn write_php_cache(
     writer.write("return ")?;
     writer.array_start()?;
 
-    let metadata_count = metadata_list.len();
-    for (i, metadata) in metadata_list.iter().enumerate() {
+    let classes = layout.sorted(metadata_list);
+    match layout.group_by {
+        None => write_classes(&mut writer, &classes, file_root)?,
+        Some(group_by) => {
+            let groups = group_by.groups(&classes);
+            let group_count = groups.len();
+            for (i, (key, classes)) in groups.iter().enumerate() {
+                writer.key_array_start(&escape_php_string(key))?;
+                write_classes(&mut writer, classes, file_root)?;
+                writer.array_end(pretty || i + 1 < group_count)?;
+            }
+        },
+    }
+
+    writer.write("];")?;
+    if pretty {
+        writer.writeln("")?;
+    }
+
+    writer.writer.flush()?;
+    Ok(())
+}
+
+/// Write class entries (`'FQCN' => [...]`) into the array being rendered
+fn write_classes<W: Write>(
+    writer: &mut PhpFormatter<W>, classes: &[&PhpClassMetadata], file_root: Option<&str>,
+) -> Result<()> {
+    let pretty = writer.pretty;
+    let metadata_count = classes.len();
+    for (i, metadata) in classes.iter().enumerate() {
         let is_last = i == metadata_count - 1;
         let fqcn = escape_php_string(&metadata.fqcn);
 
@@ -46,7 +205,31 @@ pub fn write_php_cache(
         // File path
         let file_path = metadata.file.to_string_lossy();
         let escaped_path = escape_php_string(&file_path);
-        writer.key_value_string("file", &escaped_path, false)?;
+        match file_root {
+            Some(root) if metadata.file.is_relative() => {
+                writer.key_value_raw("file", &format!("{root}.'/{escaped_path}'"), false)?;
+            },
+            _ => writer.key_value_string("file", &escaped_path, false)?,
+        }
+
+        // Source hash (lets consumers detect stale derived artifacts)
+        writer.key_value_string("file_hash", &metadata.file_hash, false)?;
+        if let Some(signature_hash) = &metadata.signature_hash {
+            writer.key_value_string("signature_hash", signature_hash, false)?;
+        }
+
+        // File context (only with --include-file-context)
+        if let Some(file_context) = &metadata.file_context {
+            writer.write_file_context(file_context)?;
+        }
+
+        // Custom query captures (only with `queries`)
+        if let Some(custom) = &metadata.custom {
+            writer.write_custom(custom)?;
+        }
+        if let Some(truncated) = &metadata.truncated {
+            writer.write_truncated(truncated)?;
+        }
 
         // Type
         writer.key_value_string("type", &metadata.kind, false)?;
@@ -60,6 +243,9 @@ pub fn write_php_cache(
 
         // Attributes
         writer.write_attributes(&metadata.attributes, false)?;
+        if let Some(inherited) = &metadata.inherited_attributes {
+            writer.write_attribute_map("inherited_attributes", inherited, false)?;
+        }
 
         // Extends
         if let Some(parent) = &metadata.extends {
@@ -70,21 +256,19 @@ pub fn write_php_cache(
         }
 
         // Implements
-        if metadata.implements.is_empty() {
-            writer.key_array_empty("implements", false)?;
-        } else {
-            writer.key_array_start("implements")?;
-            let impl_count = metadata.implements.len();
-            for (j, interface) in metadata.implements.iter().enumerate() {
-                let is_last_impl = j == impl_count - 1;
-                let escaped_interface = escape_php_string(interface);
-                writer.write_indent()?;
-                writer.write("'")?;
-                writer.write(&escaped_interface)?;
-                writer.write("'")?;
-                writer.write_comma_newline(is_last_impl)?;
-            }
-            writer.array_end(true)?;
+        writer.write_string_list("implements", &metadata.implements, false)?;
+
+        // Traits (only when used, so caches without traits keep their shape)
+        if !metadata.traits.is_empty() {
+            writer.write_string_list("traits", &metadata.traits, false)?;
+        }
+
+        // Resolved hierarchy (only when the resolution pass ran)
+        if let Some(interfaces) = &metadata.resolved_implements {
+            writer.write_string_list("resolved_implements", interfaces, false)?;
+        }
+        if let Some(ancestors) = &metadata.ancestors {
+            writer.write_string_list("ancestors", ancestors, false)?;
         }
 
         // Methods
@@ -264,12 +448,6 @@ pub fn write_php_cache(
 
         writer.array_end(pretty || !is_last)?;
     }
-
-    writer.write("];")?;
-    if pretty {
-        writer.writeln("")?;
-    }
-
     Ok(())
 }
 
@@ -361,15 +539,19 @@ impl<W: Write> PhpFormatter<W> {
     }
 
     fn write_attributes(
-        &mut self,
-        attributes: &std::collections::HashMap<String, Vec<Vec<AttributeArgument>>>,
-        is_last_block: bool,
+        &mut self, attributes: &AttributeMap, is_last_block: bool,
+    ) -> std::io::Result<()> {
+        self.write_attribute_map("attributes", attributes, is_last_block)
+    }
+
+    fn write_attribute_map(
+        &mut self, key: &str, attributes: &AttributeMap, is_last_block: bool,
     ) -> std::io::Result<()> {
         if attributes.is_empty() {
-            return self.key_array_empty("attributes", is_last_block);
+            return self.key_array_empty(key, is_last_block);
         }
 
-        self.key_array_start("attributes")?;
+        self.key_array_start(key)?;
         let attr_count = attributes.len();
         for (j, (attr_name, instances)) in attributes.iter().enumerate() {
             let is_last_attr = j == attr_count - 1;
@@ -385,8 +567,10 @@ impl<W: Write> PhpFormatter<W> {
             for (k, args) in instances.iter().enumerate() {
                 let is_last_instance = k == instance_count - 1;
 
+                self.write_indent()?;
                 if args.is_empty() {
                     self.write("[]")?;
+                    self.write_comma_newline(is_last_instance)?;
                 } else {
                     self.array_start()?; // Start arguments
                     let arg_count = args.len();
@@ -408,13 +592,111 @@ impl<W: Write> PhpFormatter<W> {
                     }
                     self.array_end(self.pretty || !is_last_instance)?;
                 }
-                self.write_comma_newline(is_last_instance)?;
             }
             self.array_end(self.pretty || !is_last_attr)?;
         }
         self.array_end(self.pretty || !is_last_block)
     }
 
+    fn write_file_context(&mut self, file_context: &PhpFileContext) -> std::io::Result<()> {
+        self.key_array_start("file_context")?;
+        match &file_context.namespace {
+            Some(namespace) => {
+                self.key_value_string("namespace", &escape_php_string(namespace), false)?;
+            },
+            None => self.key_value_null("namespace", false)?,
+        }
+        self.key_value_bool("strict_types", file_context.strict_types, false)?;
+        if file_context.imports.is_empty() {
+            self.key_array_empty("imports", false)?;
+        } else {
+            self.key_array_start("imports")?;
+            let import_count = file_context.imports.len();
+            for (j, (alias, fqcn)) in file_context.imports.iter().enumerate() {
+                self.key_value_string(
+                    &escape_php_string(alias),
+                    &escape_php_string(fqcn),
+                    j == import_count - 1,
+                )?;
+            }
+            self.array_end(true)?;
+        }
+        self.write_string_list("classes", &file_context.classes, false)?;
+        self.write_string_list("functions", &file_context.functions, false)?;
+        self.write_string_list("constants", &file_context.constants, true)?;
+        self.array_end(true)
+    }
+
+    fn write_custom(&mut self, custom: &CustomCaptures) -> std::io::Result<()> {
+        if custom.is_empty() {
+            return self.key_array_empty("custom", false);
+        }
+
+        self.key_array_start("custom")?;
+        let key_count = custom.len();
+        for (j, (key, matches)) in custom.iter().enumerate() {
+            let is_last_key = j == key_count - 1;
+            let escaped_key = escape_php_string(key);
+            if matches.is_empty() {
+                self.key_array_empty(&escaped_key, is_last_key)?;
+                continue;
+            }
+
+            self.key_array_start(&escaped_key)?;
+            let match_count = matches.len();
+            for (k, captures) in matches.iter().enumerate() {
+                let is_last_match = k == match_count - 1;
+                self.write_indent()?;
+                if captures.is_empty() {
+                    self.write("[]")?;
+                    self.write_comma_newline(is_last_match)?;
+                    continue;
+                }
+                self.array_start()?;
+                let capture_count = captures.len();
+                for (l, (name, text)) in captures.iter().enumerate() {
+                    self.key_value_string(
+                        &escape_php_string(name),
+                        &escape_php_string(text),
+                        l == capture_count - 1,
+                    )?;
+                }
+                self.array_end(self.pretty || !is_last_match)?;
+            }
+            self.array_end(self.pretty || !is_last_key)?;
+        }
+        self.array_end(true)
+    }
+
+    fn write_truncated(&mut self, truncated: &TruncatedMembers) -> std::io::Result<()> {
+        self.key_array_start("truncated")?;
+        self.key_value_raw("methods", &truncated.methods.to_string(), false)?;
+        self.key_value_raw("properties", &truncated.properties.to_string(), false)?;
+        self.key_value_raw("cases", &truncated.cases.to_string(), true)?;
+        self.array_end(true)
+    }
+
+    fn write_string_list(
+        &mut self, key: &str, items: &[String], is_last_block: bool,
+    ) -> std::io::Result<()> {
+        if items.is_empty() {
+            return self.key_array_empty(key, is_last_block);
+        }
+
+        self.key_array_start(key)?;
+        let item_count = items.len();
+        for (j, item) in items.iter().enumerate() {
+            let is_last_item = j == item_count - 1;
+            let escaped_item = escape_php_string(item);
+            self.write_indent()?;
+            self.write("'")?;
+            self.write(&escaped_item)?;
+            self.write("'")?;
+            self.write_comma_newline(is_last_item)?;
+        }
+        self.array_end(self.pretty || !is_last_block)
+    }
+
     fn key_array_empty(&mut self, key: &str, is_last: bool) -> std::io::Result<()> {
         self.write_indent()?;
         self.write("'")?;
@@ -479,10 +761,15 @@ impl<W: Write> PhpFormatter<W> {
 }
 
 /// Escape a string for use in single-quoted PHP string
-fn escape_php_string(s: &str) -> String {
+pub(crate) fn escape_php_string(s: &str) -> String {
     s.replace('\\', "\\\\").replace('\'', "\\'")
 }
 
+/// Single-quoted PHP string literal of `value`
+pub(crate) fn php_literal(value: &str) -> String {
+    format!("'{}'", escape_php_string(value))
+}
+
 /// Format a value for PHP output
 fn format_php_value(value: &str) -> String {
     let trimmed = value.trim();
@@ -551,6 +838,12 @@ pub fn write_json_cache(
         std::fs::create_dir_all(parent)?;
     }
 
+    if crate::encryption::is_enabled() {
+        let content = render_cache(metadata_list, "json", pretty, None)?;
+        write_if_changed(output_path, &crate::encryption::seal(&content)?)?;
+        return Ok(());
+    }
+
     let file = File::create(output_path)?;
     if pretty {
         serde_json::to_writer_pretty(file, metadata_list)?;
@@ -560,3 +853,170 @@ pub fn write_json_cache(
 
     Ok(())
 }
+
+/// Render the cache in `format` ("php" or "json") into memory
+///
+/// # Errors
+///
+/// Returns an error if serialization fails.
+pub fn render_cache(
+    metadata_list: &[PhpClassMetadata], format: &str, pretty: bool, file_root: Option<&str>,
+) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    match format {
+        "json" => {
+            if pretty {
+                serde_json::to_writer_pretty(&mut buffer, metadata_list)?;
+            } else {
+                serde_json::to_writer(&mut buffer, metadata_list)?;
+            }
+        },
+        _ => render_php_cache_with_root(metadata_list, &mut buffer, pretty, file_root)?,
+    }
+    Ok(buffer)
+}
+
+/// Render the cache as [`write_cache_if_changed`] would write it to `output_path`
+///
+/// # Errors
+///
+/// Returns an error if serialization fails.
+pub fn render_cache_for(
+    metadata_list: &[PhpClassMetadata], output_path: &Path, format: &str, pretty: bool,
+    path_root: Option<&Path>, layout: CacheLayout,
+) -> Result<Vec<u8>> {
+    let render = |metadata_list: &[PhpClassMetadata], file_root: Option<&str>| {
+        if format == "json" || layout.is_default() {
+            return render_cache(metadata_list, format, pretty, file_root);
+        }
+        let mut buffer = Vec::new();
+        render_php_cache_with_layout(metadata_list, &mut buffer, pretty, file_root, layout)?;
+        Ok(buffer)
+    };
+
+    path_root.map_or_else(
+        || render(metadata_list, None),
+        |root| {
+            let mut relative = metadata_list.to_vec();
+            relativize_paths(&mut relative, root);
+            let file_root = php_root_expr(output_path, root);
+            render(&relative, Some(&file_root))
+        },
+    )
+}
+
+/// Write the cache only if its content differs from what is already on disk
+///
+/// The replacement is atomic (temp file + rename). When nothing changed the
+/// existing file is left untouched, avoiding write amplification for
+/// incremental runs and daemon flushes (notably on network filesystems).
+/// With `path_root`, file paths under it are stored relative to it (see
+/// [`relativize_paths`]). PHP caches follow `layout`, JSON caches are
+/// encrypted when [`crate::encryption`] is enabled. Returns `true` if the
+/// file was written.
+///
+/// # Errors
+///
+/// Returns an error if rendering or writing the file fails.
+pub fn write_cache_if_changed(
+    metadata_list: &[PhpClassMetadata], output_path: &Path, format: &str, pretty: bool,
+    path_root: Option<&Path>, layout: CacheLayout,
+) -> Result<bool> {
+    let content = render_cache_for(
+        metadata_list,
+        output_path,
+        format,
+        pretty,
+        path_root,
+        layout,
+    )?;
+    if format == "json" && crate::encryption::is_enabled() {
+        return crate::encryption::write_sealed_if_changed(output_path, &content);
+    }
+    write_if_changed(output_path, &content)
+}
+
+/// Atomically replace `path` with `content` unless it already holds it
+///
+/// Returns `true` if the file was written.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn write_if_changed(path: &Path, content: &[u8]) -> Result<bool> {
+    if std::fs::read(path).is_ok_and(|existing| existing == content) {
+        return Ok(false);
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let temp = path.with_extension("tmp");
+    std::fs::write(&temp, content)?;
+    std::fs::rename(&temp, path)?;
+
+    Ok(true)
+}
+
+/// `path_root` as given in the config or on the command line, canonicalized
+///
+/// Scanned files are reported under their canonical paths (the daemon
+/// canonicalizes the watched paths), so a symlinked or relative root would
+/// never match them. A root that does not exist is only made absolute.
+#[must_use]
+pub fn canonical_path_root(root: &Path) -> PathBuf {
+    std::fs::canonicalize(root)
+        .or_else(|_| std::path::absolute(root))
+        .unwrap_or_else(|_| root.to_path_buf())
+}
+
+/// Rewrite `file` paths under `root` as `/`-separated paths relative to it
+///
+/// Files outside `root` keep their absolute path. Files scanned through a
+/// symlink are matched by their canonical path.
+pub fn relativize_paths(metadata_list: &mut [PhpClassMetadata], root: &Path) {
+    let Ok(root) = std::path::absolute(root) else {
+        return;
+    };
+
+    for metadata in metadata_list {
+        let Ok(file) = std::path::absolute(&metadata.file) else {
+            continue;
+        };
+        let file = if file.starts_with(&root) {
+            file
+        } else {
+            std::fs::canonicalize(&file).unwrap_or(file)
+        };
+        if let Ok(relative) = file.strip_prefix(&root) {
+            let parts: Vec<_> = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect();
+            metadata.file = PathBuf::from(parts.join("/"));
+        }
+    }
+}
+
+/// PHP expression for `root` as seen from the cache file at `output_path`
+///
+/// Uses `__DIR__` when the cache lives inside the project so the cache stays
+/// valid after the project is moved; otherwise falls back to the absolute root.
+#[must_use]
+pub fn php_root_expr(output_path: &Path, root: &Path) -> String {
+    let out_dir = output_path.parent().unwrap_or_else(|| Path::new("."));
+    let root = std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf());
+    let literal = || format!("'{}'", escape_php_string(&root.to_string_lossy()));
+
+    let Ok(out_dir) = std::path::absolute(out_dir) else {
+        return literal();
+    };
+    out_dir.strip_prefix(&root).map_or_else(
+        |_| literal(),
+        |depth| match depth.components().count() {
+            0 => "__DIR__".to_string(),
+            levels => format!("\\dirname(__DIR__, {levels})"),
+        },
+    )
+}
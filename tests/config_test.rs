@@ -93,3 +93,52 @@ fn test_default_config_not_found() {
     let result = ConfigFile::load(Some(std::path::PathBuf::from("non_existent.json")));
     assert!(result.is_err());
 }
+
+#[test]
+fn test_load_poll_fallback() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("aurynx.json");
+
+    let mut file = File::create(&file_path).unwrap();
+    file.write_all(br#"{ "watch": true, "poll_fallback": true }"#)
+        .unwrap();
+
+    let config = ConfigFile::load(Some(file_path)).unwrap();
+    assert_eq!(config.poll_fallback, Some(true));
+}
+
+#[test]
+fn test_validation_invalid_watcher() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("aurynx.json");
+
+    let mut file = File::create(&file_path).unwrap();
+    file.write_all(br#"{ "watcher": "fswatch" }"#).unwrap();
+
+    let result = ConfigFile::load(Some(file_path));
+    assert!(result.unwrap_err().to_string().contains("Invalid watcher"));
+}
+
+#[test]
+fn test_validation_invalid_artifact_rule() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("aurynx.json");
+
+    let config_content = r#"{
+        "artifacts": [
+            {"attribute": "AsCommand", "key": "name:0", "output": "var/commands.php"},
+            {"attribute": "AsListener", "on": "function", "output": "var/listeners.php"}
+        ]
+    }"#;
+
+    let mut file = File::create(&file_path).unwrap();
+    file.write_all(config_content.as_bytes()).unwrap();
+
+    let result = ConfigFile::load(Some(file_path));
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("Invalid artifact target 'function'")
+    );
+}
@@ -1,6 +1,7 @@
 use aurynx::config::ConfigFile;
 use std::fs::File;
 use std::io::Write;
+use std::time::Duration;
 use tempfile::TempDir;
 
 /// Test default values when config fields are not set
@@ -239,3 +240,51 @@ fn test_min_boundary_values_accepted() {
     assert_eq!(config.max_request_size_bytes(), 256); // 256B
     assert_eq!(config.max_cache_entries_limit(), 1); // 1
 }
+
+/// Test adaptive batching thresholds (defaults and custom values)
+#[test]
+fn test_batch_tuning_values() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("aurynx.json");
+
+    let mut file = File::create(&config_path).unwrap();
+    writeln!(
+        file,
+        r#"{{
+        "paths": ["/tmp"],
+        "output": "/tmp/cache.php",
+        "batch_threshold": 500,
+        "debounce_ms": 150
+    }}"#
+    )
+    .unwrap();
+
+    let tuning = ConfigFile::load(Some(config_path)).unwrap().batch_tuning();
+
+    assert_eq!(tuning.mass_threshold, 500);
+    assert_eq!(tuning.debounce, Duration::from_millis(150));
+    assert_eq!(tuning.mass_debounce, Duration::from_millis(1000)); // default
+}
+
+/// Test validation: debounce windows must be within 10ms..60s
+#[test]
+fn test_validation_debounce_out_of_range() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("aurynx.json");
+
+    let mut file = File::create(&config_path).unwrap();
+    writeln!(
+        file,
+        r#"{{
+        "paths": ["/tmp"],
+        "output": "/tmp/cache.php",
+        "mass_debounce_ms": 120000
+    }}"#
+    )
+    .unwrap();
+
+    let result = ConfigFile::load(Some(config_path));
+    assert!(result.is_err());
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("mass_debounce_ms out of range"));
+}
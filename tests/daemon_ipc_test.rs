@@ -95,3 +95,955 @@ fn test_ipc_large_request_error_is_text() {
         "Response should not contain JSON type field"
     );
 }
+
+/// Send one command on a fresh connection and return the response line
+fn send_command(socket: &std::path::Path, command: &str) -> String {
+    let mut stream = UnixStream::connect(socket).expect("Failed to connect to socket");
+    stream.write_all(command.as_bytes()).unwrap();
+    stream.write_all(b"\n").unwrap();
+    stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap_or_default();
+    response.trim().to_string()
+}
+
+#[test]
+fn test_ipc_pause_and_resume() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(src_dir.join("First.php"), "<?php class First {}").unwrap();
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "discovery:scan",
+            "--path",
+            src_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--watch",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+
+    if !socket.exists() {
+        child.kill().ok();
+        panic!("Daemon failed to start (socket not found)");
+    }
+
+    assert!(send_command(&socket, "stats").starts_with("total:1 "));
+    assert_eq!(send_command(&socket, "pause"), "PAUSED");
+
+    // Changes while paused are not picked up
+    std::fs::write(src_dir.join("Second.php"), "<?php class Second {}").unwrap();
+    thread::sleep(Duration::from_millis(1500));
+    let paused_stats = send_command(&socket, "stats");
+
+    // Resume triggers a single consolidated rescan
+    let resumed = send_command(&socket, "resume");
+    thread::sleep(Duration::from_millis(500));
+    let resumed_stats = send_command(&socket, "stats");
+
+    child.kill().ok();
+
+    assert!(paused_stats.starts_with("total:1 "), "got: {paused_stats}");
+    assert_eq!(resumed, "RESUMED");
+    assert!(
+        resumed_stats.starts_with("total:2 "),
+        "got: {resumed_stats}"
+    );
+}
+
+#[test]
+fn test_ipc_rescan_path() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(src_dir.join("First.php"), "<?php class First {}").unwrap();
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "discovery:scan",
+            "--path",
+            src_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--watch",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+
+    if !socket.exists() {
+        child.kill().ok();
+        panic!("Daemon failed to start (socket not found)");
+    }
+
+    // Paused, only the explicit rescan picks up generated code
+    assert_eq!(send_command(&socket, "pause"), "PAUSED");
+    let generated = src_dir.join("generated");
+    std::fs::create_dir(&generated).unwrap();
+    std::fs::write(generated.join("Proxy.php"), "<?php class Proxy {}").unwrap();
+    let added = send_command(
+        &socket,
+        &format!("rescanPath {}", generated.to_str().unwrap()),
+    );
+
+    std::fs::remove_file(generated.join("Proxy.php")).unwrap();
+    let removed = send_command(
+        &socket,
+        &format!("rescanPath {}", generated.to_str().unwrap()),
+    );
+    let outside = send_command(
+        &socket,
+        &format!("rescanPath {}", temp_dir.path().to_str().unwrap()),
+    );
+
+    child.kill().ok();
+
+    assert_eq!(added, "RESCANNED files:1 removed:0 classes:2");
+    assert_eq!(removed, "RESCANNED files:0 removed:1 classes:1");
+    assert!(outside.starts_with("ERROR: "), "got: {outside}");
+}
+
+#[test]
+fn test_ipc_scan_on_demand_writes_cache_before_answering() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(src_dir.join("First.php"), "<?php class First {}").unwrap();
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "discovery:scan",
+            "--path",
+            src_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--watch",
+            "--write-to-disk",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+
+    if !socket.exists() {
+        child.kill().ok();
+        panic!("Daemon failed to start (socket not found)");
+    }
+
+    // Paused, only the requested scan picks up the new file
+    assert_eq!(send_command(&socket, "pause"), "PAUSED");
+    std::fs::write(src_dir.join("Second.php"), "<?php class Second {}").unwrap();
+    let incremental = send_command(&socket, "scanOnDemand");
+    let cache = std::fs::read_to_string(&output).unwrap();
+    let full = send_command(&socket, "scanOnDemand full");
+    let unknown = send_command(&socket, "scanOnDemand partial");
+
+    child.kill().ok();
+
+    assert!(
+        incremental.starts_with("SCANNED mode:incremental classes:2 duration_ms:"),
+        "got: {incremental}"
+    );
+    assert!(cache.contains("Second"), "cache not written before the response");
+    assert!(
+        full.starts_with("SCANNED mode:full classes:2 duration_ms:"),
+        "got: {full}"
+    );
+    assert!(unknown.starts_with("ERROR: AX4"), "got: {unknown}");
+}
+
+#[test]
+fn test_ipc_stats_reports_batching_counters() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(src_dir.join("First.php"), "<?php class First {}").unwrap();
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "discovery:scan",
+            "--path",
+            src_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--watch",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+
+    if !socket.exists() {
+        child.kill().ok();
+        panic!("Daemon failed to start (socket not found)");
+    }
+
+    let initial = send_command(&socket, "stats");
+    std::fs::write(src_dir.join("Second.php"), "<?php class Second {}").unwrap();
+    thread::sleep(Duration::from_millis(1500));
+    let after_change = send_command(&socket, "stats");
+
+    child.kill().ok();
+
+    assert!(initial.contains(" batches:0 "), "got: {initial}");
+    assert!(after_change.starts_with("total:2 "), "got: {after_change}");
+    assert!(!after_change.contains(" batches:0 "), "got: {after_change}");
+    assert!(
+        !after_change.contains(" debounce_normal:0 "),
+        "got: {after_change}"
+    );
+    assert!(
+        after_change.contains(" rescan_total_ms:"),
+        "got: {after_change}"
+    );
+}
+
+#[test]
+fn test_bounded_memory_mode_spills_to_disk() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    for name in ["Alpha", "Beta", "Gamma"] {
+        std::fs::write(
+            src_dir.join(format!("{name}.php")),
+            format!("<?php class {name} {{}}"),
+        )
+        .unwrap();
+    }
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+    let spill_file = temp_dir.path().join("spill/classes.spill");
+    let config = temp_dir.path().join("aurynx.json");
+    std::fs::write(
+        &config,
+        format!(
+            r#"{{"max_memory_entries": 1, "spill_file": {:?}}}"#,
+            spill_file.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "discovery:scan",
+            "--config",
+            config.to_str().unwrap(),
+            "--path",
+            src_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--watch",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+
+    if !socket.exists() {
+        child.kill().ok();
+        panic!("Daemon failed to start (socket not found)");
+    }
+
+    let stats = send_command(&socket, "stats");
+    let code = send_command(&socket, "getCode");
+    // Spilled classes are read back one by one
+    let classes = ["Alpha", "Beta", "Gamma"]
+        .map(|name| send_command(&socket, &format!("getClass {name} json")));
+    let spill_exists = spill_file.exists();
+
+    child.kill().ok();
+
+    assert!(stats.starts_with("total:3 "), "got: {stats}");
+    assert!(stats.ends_with(" spilled:2"), "got: {stats}");
+    assert!(spill_exists);
+    for (name, class) in ["Alpha", "Beta", "Gamma"].iter().zip(&classes) {
+        assert!(
+            code.contains(&format!("'\\\\{name}'")),
+            "missing {name} in: {code}"
+        );
+        assert!(
+            class.contains(&format!("\"fqcn\":\"\\\\{name}\"")),
+            "got: {class}"
+        );
+    }
+}
+
+// RSS is only read on Linux
+#[cfg(target_os = "linux")]
+#[test]
+fn test_memory_budget_sheds_classes_to_disk() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    for name in ["Alpha", "Beta", "Gamma"] {
+        std::fs::write(
+            src_dir.join(format!("{name}.php")),
+            format!("<?php class {name} {{}}"),
+        )
+        .unwrap();
+    }
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+    let config = temp_dir.path().join("aurynx.json");
+    // Any daemon exceeds 1 MB, so every class is shed
+    std::fs::write(
+        &config,
+        r#"{"max_memory_entries": 100, "max_memory_mb": 1}"#,
+    )
+    .unwrap();
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "discovery:scan",
+            "--config",
+            config.to_str().unwrap(),
+            "--path",
+            src_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--watch",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+
+    if !socket.exists() {
+        child.kill().ok();
+        panic!("Daemon failed to start (socket not found)");
+    }
+
+    let stats = send_command(&socket, "stats verbose");
+    let code = send_command(&socket, "getCode");
+
+    child.kill().ok();
+
+    let lines: Vec<&str> = stats.lines().collect();
+    for expected in [
+        "total:3",
+        "spilled:3",
+        "memory_cache_bytes:0",
+        "memory_budget_bytes:1048576",
+        "memory_over_budget:1",
+    ] {
+        assert!(lines.contains(&expected), "missing {expected} in: {stats}");
+    }
+    assert!(code.contains("'\\\\Alpha'"), "got: {code}");
+}
+
+#[test]
+fn test_ipc_get_code_serves_written_cache_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(src_dir.join("Served.php"), "<?php class Served {}").unwrap();
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "discovery:scan",
+            "--path",
+            src_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--watch",
+            "--write-to-disk",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+
+    if !socket.exists() {
+        child.kill().ok();
+        panic!("Daemon failed to start (socket not found)");
+    }
+
+    let code = send_command(&socket, "getCode");
+    let cached = std::fs::read_to_string(&output).unwrap_or_default();
+
+    child.kill().ok();
+
+    assert!(code.contains("'\\\\Served'"), "got: {code}");
+    assert_eq!(code, cached.trim());
+}
+
+#[test]
+fn test_ipc_get_errors_lists_broken_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(src_dir.join("Valid.php"), "<?php class Valid {}").unwrap();
+    // Not UTF-8: the file cannot be read as PHP source
+    std::fs::write(src_dir.join("Broken.php"), b"<?php class Broken {} \xff").unwrap();
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "discovery:scan",
+            "--path",
+            src_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--watch",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+
+    if !socket.exists() {
+        child.kill().ok();
+        panic!("Daemon failed to start (socket not found)");
+    }
+
+    let errors = send_command(&socket, "getErrors");
+    let stats = send_command(&socket, "stats");
+
+    // Fixing the file clears its error
+    std::fs::write(src_dir.join("Broken.php"), "<?php class Broken {}").unwrap();
+    thread::sleep(Duration::from_millis(1500));
+    let fixed = send_command(&socket, "getErrors");
+
+    child.kill().ok();
+
+    let lines: Vec<&str> = errors.lines().collect();
+    assert_eq!(lines[0], "ERRORS 1", "got: {errors}");
+    let fields: Vec<&str> = lines[1].split('\t').collect();
+    assert!(fields[0].parse::<u64>().is_ok(), "got: {errors}");
+    assert!(fields[1].ends_with("Broken.php"), "got: {errors}");
+    assert!(
+        fields[2].starts_with("Could not read file"),
+        "got: {errors}"
+    );
+    assert_eq!(fields[3], "AX3001", "got: {errors}");
+    assert!(stats.contains(" errors:1 "), "got: {stats}");
+    assert_eq!(fixed, "ERRORS 0");
+}
+
+#[test]
+fn test_ipc_get_removed_lists_removal_reasons() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(src_dir.join("Gone.php"), "<?php class Gone {}").unwrap();
+    std::fs::write(
+        src_dir.join("Pair.php"),
+        "<?php class Kept {} class Dropped {}",
+    )
+    .unwrap();
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "discovery:scan",
+            "--path",
+            src_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--watch",
+            "--write-to-disk",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+
+    if !socket.exists() {
+        child.kill().ok();
+        panic!("Daemon failed to start (socket not found)");
+    }
+
+    let initial = send_command(&socket, "getRemoved");
+
+    std::fs::remove_file(src_dir.join("Gone.php")).unwrap();
+    thread::sleep(Duration::from_millis(1500));
+    let deleted = send_command(&socket, "getRemoved");
+
+    std::fs::write(src_dir.join("Pair.php"), "<?php class Kept {}").unwrap();
+    thread::sleep(Duration::from_millis(1500));
+    let edited = send_command(&socket, "getRemoved");
+
+    child.kill().ok();
+
+    assert!(initial.starts_with("REMOVED 0 flush:"), "got: {initial}");
+    let flushes: u64 = initial.rsplit(':').next().unwrap().parse().unwrap();
+    let lines: Vec<&str> = deleted.lines().collect();
+    assert_eq!(
+        lines[0],
+        format!("REMOVED 1 flush:{}", flushes + 1),
+        "got: {deleted}"
+    );
+    let fields: Vec<&str> = lines[1].split('\t').collect();
+    assert_eq!(fields[0], "\\Gone", "got: {deleted}");
+    assert!(fields[1].ends_with("Gone.php"), "got: {deleted}");
+    assert_eq!(fields[2], "file_deleted", "got: {deleted}");
+    let lines: Vec<&str> = edited.lines().collect();
+    assert_eq!(
+        lines[0],
+        format!("REMOVED 1 flush:{}", flushes + 2),
+        "got: {edited}"
+    );
+    assert!(
+        lines[1].starts_with("\\Dropped\t") && lines[1].ends_with("\tclass_removed"),
+        "got: {edited}"
+    );
+}
+
+#[test]
+fn test_ipc_verbose_stats_lines() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    let lib_dir = temp_dir.path().join("lib");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::create_dir(&lib_dir).unwrap();
+    std::fs::write(src_dir.join("One.php"), "<?php class One {} class Two {}").unwrap();
+    std::fs::write(lib_dir.join("Three.php"), "<?php class Three {}").unwrap();
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "discovery:scan",
+            "--path",
+            src_dir.to_str().unwrap(),
+            lib_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--watch",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+
+    if !socket.exists() {
+        child.kill().ok();
+        panic!("Daemon failed to start (socket not found)");
+    }
+
+    let stats = send_command(&socket, "stats verbose");
+
+    child.kill().ok();
+
+    let lines: Vec<&str> = stats.lines().collect();
+    assert_eq!(lines[0], "version:2", "got: {stats}");
+    for expected in [
+        "total:3".to_string(),
+        "queue_depth:0".to_string(),
+        "evictions:0".to_string(),
+        "errors:0".to_string(),
+        format!("path:2 {}", src_dir.display()),
+        format!("path:1 {}", lib_dir.display()),
+    ] {
+        assert!(
+            lines.contains(&expected.as_str()),
+            "missing {expected} in: {stats}"
+        );
+    }
+    for key in [
+        "memory_rss_bytes:",
+        "memory_cache_bytes:",
+        "memory_snapshot_bytes:",
+        "rescan_last_ms:",
+        "uptime:",
+    ] {
+        assert!(
+            lines.iter().any(|line| line.starts_with(key)),
+            "missing {key} in: {stats}"
+        );
+    }
+}
+
+#[test]
+fn test_ipc_version_lists_features() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(src_dir.join("Test.php"), "<?php class Test {}").unwrap();
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "discovery:scan",
+            "--path",
+            src_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--watch",
+            "--write-to-disk",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+
+    if !socket.exists() {
+        child.kill().ok();
+        panic!("Daemon failed to start (socket not found)");
+    }
+
+    let version = send_command(&socket, "version");
+
+    child.kill().ok();
+
+    let fields: std::collections::HashMap<&str, &str> = version
+        .split(' ')
+        .filter_map(|field| field.split_once(':'))
+        .collect();
+    assert_eq!(
+        fields["version"],
+        env!("CARGO_PKG_VERSION"),
+        "got: {version}"
+    );
+    assert!(fields["schema"].parse::<u32>().is_ok(), "got: {version}");
+    assert_eq!(fields["protocol"], "2", "got: {version}");
+    let features: Vec<&str> = fields["features"].split(',').collect();
+    assert!(features.contains(&"getFilePath"), "got: {version}");
+    assert!(features.contains(&"version"), "got: {version}");
+    assert!(!features.contains(&"getShm"), "got: {version}");
+}
+
+#[test]
+fn test_read_only_daemon_serves_existing_cache() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(src_dir.join("Built.php"), "<?php class Built {}").unwrap();
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+
+    // Deploy-time build
+    let status = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "discovery:scan",
+            "--path",
+            src_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .expect("Failed to run scan");
+    assert!(status.success());
+    let built = std::fs::read_to_string(&output).unwrap();
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "discovery:scan",
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--watch",
+            "--read-only",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+
+    if !socket.exists() {
+        child.kill().ok();
+        panic!("Daemon failed to start (socket not found)");
+    }
+
+    // New files are neither watched nor rescanned on request
+    std::fs::write(src_dir.join("Later.php"), "<?php class Later {}").unwrap();
+    thread::sleep(Duration::from_millis(1000));
+    let code = send_command(&socket, "getCode");
+    let stats = send_command(&socket, "stats");
+    let rescan = send_command(
+        &socket,
+        &format!("rescanPath {}", src_dir.to_str().unwrap()),
+    );
+    let pause = send_command(&socket, "pause");
+    let version = send_command(&socket, "version");
+
+    child.kill().ok();
+
+    assert!(code.contains("'\\\\Built'"), "got: {code}");
+    assert!(stats.starts_with("total:1 "), "got: {stats}");
+    assert!(rescan.starts_with("ERROR: AX4005 "), "got: {rescan}");
+    assert!(pause.starts_with("ERROR: AX4005 "), "got: {pause}");
+    assert!(!version.contains("rescanPath"), "got: {version}");
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), built);
+}
+
+#[test]
+fn test_ipc_code_requests_select_format() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("User.php"),
+        "<?php namespace App; #[Entity] class User {}",
+    )
+    .unwrap();
+    std::fs::write(
+        src_dir.join("Post.php"),
+        "<?php namespace App; class Post {}",
+    )
+    .unwrap();
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "discovery:scan",
+            "--path",
+            src_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--watch",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+
+    if !socket.exists() {
+        child.kill().ok();
+        panic!("Daemon failed to start (socket not found)");
+    }
+
+    let php = send_command(&socket, "getCode php");
+    let json = send_command(&socket, "getCode json");
+    let class_php = send_command(&socket, "getClass \\App\\User");
+    let class_json = send_command(&socket, "getClass App\\User json");
+    let missing = send_command(&socket, "getClass App\\Missing json");
+    let bad_format = send_command(&socket, "getCode yaml");
+
+    child.kill().ok();
+
+    assert!(php.starts_with("<?php"), "got: {php}");
+    assert!(php.contains("App\\\\Post"), "got: {php}");
+
+    let classes: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let fqcns: Vec<&str> = classes
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|class| class["fqcn"].as_str().unwrap())
+        .collect();
+    assert_eq!(fqcns, ["\\App\\Post", "\\App\\User"]);
+
+    assert!(class_php.starts_with("<?php"), "got: {class_php}");
+    assert!(class_php.contains("App\\\\User"), "got: {class_php}");
+    assert!(!class_php.contains("App\\\\Post"), "got: {class_php}");
+
+    let class: serde_json::Value = serde_json::from_str(&class_json).unwrap();
+    assert_eq!(class["fqcn"], "\\App\\User");
+    assert!(
+        !class["attributes"]["\\App\\Entity"].is_null(),
+        "got: {class_json}"
+    );
+
+    assert_eq!(missing, "ERROR: AX4004 Class not found: App\\Missing");
+    assert!(
+        bad_format.starts_with("ERROR: AX4003 Unknown format: yaml"),
+        "got: {bad_format}"
+    );
+}
@@ -111,3 +111,257 @@ fn test_daemon_pid_file_creation() {
         );
     }
 }
+
+#[test]
+fn test_daemon_rescans_on_composer_lock_change() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    fs::create_dir(&src_dir).unwrap();
+    fs::write(src_dir.join("Test.php"), "<?php class Test {}").unwrap();
+
+    let composer_lock = temp_dir.path().join("composer.lock");
+    fs::write(&composer_lock, "{}").unwrap();
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+    let log_file = temp_dir.path().join("daemon.log");
+
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "discovery:scan",
+            "--path",
+            src_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--log-file",
+            log_file.to_str().unwrap(),
+            "--watch",
+            "--watch-composer",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+
+    fs::write(&composer_lock, r#"{"packages": []}"#).unwrap();
+    thread::sleep(Duration::from_millis(1500));
+
+    child.kill().ok();
+    child.wait().ok();
+
+    let log = fs::read_to_string(&log_file).unwrap_or_default();
+    assert!(
+        log.contains("composer.lock changed, scheduling full rescan"),
+        "log: {log}"
+    );
+    assert!(log.contains("Full rescan complete"), "log: {log}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_container_mode_stops_promptly_on_sigterm() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    fs::create_dir(&src_dir).unwrap();
+    fs::write(src_dir.join("Test.php"), "<?php class Test {}").unwrap();
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_aurynx"))
+        .args([
+            "discovery:scan",
+            "--path",
+            src_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--watch",
+            "--container",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+    assert!(socket.exists(), "daemon did not start");
+
+    let status = Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let started = std::time::Instant::now();
+    let exit = loop {
+        if let Some(exit) = child.try_wait().unwrap() {
+            break exit;
+        }
+        assert!(
+            started.elapsed() < Duration::from_secs(3),
+            "daemon did not stop within 3s of SIGTERM"
+        );
+        thread::sleep(Duration::from_millis(20));
+    };
+
+    assert!(exit.success(), "exit status: {exit}");
+    assert!(!pid_file.exists(), "PID file not cleaned up");
+    assert!(!socket.exists(), "socket not cleaned up");
+}
+
+#[test]
+fn test_foreground_exit_statuses() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    fs::create_dir(&src_dir).unwrap();
+    fs::write(src_dir.join("Test.php"), "<?php class Test {}").unwrap();
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+    let daemon = |extra: &[&str]| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_aurynx"));
+        command
+            .args([
+                "discovery:scan",
+                "--path",
+                src_dir.to_str().unwrap(),
+                "--output",
+                output.to_str().unwrap(),
+                "--socket",
+                socket.to_str().unwrap(),
+                "--pid",
+                pid_file.to_str().unwrap(),
+                "--watch",
+                "--foreground",
+            ])
+            .args(extra)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        command
+    };
+
+    let mut child = daemon(&["--exit-on-idle", "2"])
+        .spawn()
+        .expect("Failed to start daemon");
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+    assert!(socket.exists(), "daemon did not start");
+
+    // A second daemon for the same cache cannot take the lock
+    let conflict = daemon(&[]).status().unwrap();
+    assert_eq!(conflict.code(), Some(75), "exit status: {conflict}");
+
+    let started = std::time::Instant::now();
+    let exit = loop {
+        if let Some(exit) = child.try_wait().unwrap() {
+            break exit;
+        }
+        assert!(
+            started.elapsed() < Duration::from_secs(10),
+            "daemon did not exit when idle"
+        );
+        thread::sleep(Duration::from_millis(50));
+    };
+    assert!(exit.success(), "exit status: {exit}");
+    assert!(!socket.exists(), "socket not cleaned up");
+}
+
+#[test]
+fn test_json_logs_carry_stable_events() {
+    let temp_dir = TempDir::new().unwrap();
+    let src_dir = temp_dir.path().join("src");
+    fs::create_dir(&src_dir).unwrap();
+    fs::write(src_dir.join("First.php"), "<?php class First {}").unwrap();
+
+    let output = temp_dir.path().join("cache.php");
+    let socket = temp_dir.path().join("daemon.sock");
+    let pid_file = temp_dir.path().join("daemon.pid");
+    let log_file = temp_dir.path().join("daemon.log");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_aurynx"))
+        .args([
+            "discovery:scan",
+            "--path",
+            src_dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--socket",
+            socket.to_str().unwrap(),
+            "--pid",
+            pid_file.to_str().unwrap(),
+            "--log-file",
+            log_file.to_str().unwrap(),
+            "--log-format",
+            "json",
+            "--watch",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start daemon");
+
+    let mut attempts = 0;
+    while !socket.exists() && attempts < 50 {
+        thread::sleep(Duration::from_millis(100));
+        attempts += 1;
+    }
+    assert!(socket.exists(), "daemon did not start");
+
+    fs::write(src_dir.join("Second.php"), "<?php class Second {}").unwrap();
+    thread::sleep(Duration::from_millis(1000));
+
+    Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+    child.wait().unwrap();
+
+    let events: Vec<serde_json::Value> = fs::read_to_string(&log_file)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["fields"].clone())
+        .filter(|fields| fields.get("event").is_some())
+        .collect();
+    let find = |name: &str| {
+        events
+            .iter()
+            .find(|fields| fields["event"] == name)
+            .unwrap_or_else(|| panic!("no {name} event in {events:?}"))
+    };
+
+    assert!(find("daemon_start")["pid"].is_u64());
+    let initial = find("initial_scan");
+    assert_eq!(initial["classes"], 1);
+    assert!(initial["duration_ms"].is_u64());
+    let batch = find("scan_batch");
+    assert_eq!(batch["files"], 1);
+    assert_eq!(batch["classes"], 2);
+    assert!(batch["duration_ms"].is_u64());
+    assert!(find("daemon_stop")["uptime_s"].is_u64());
+}
@@ -0,0 +1,101 @@
+use aurynx::incremental::{MANIFEST_FILE, perform_changed_files_scan, perform_incremental_scan};
+use aurynx::scanner::ExtractorPool;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {args:?} failed");
+}
+
+#[test]
+fn test_changed_since_merges_only_git_changes() {
+    let temp_dir = TempDir::new().unwrap();
+    let repo = temp_dir.path();
+    let src = repo.join("src");
+    fs::create_dir(&src).unwrap();
+
+    fs::write(src.join("Keep.php"), "<?php namespace App; class Keep {}").unwrap();
+    fs::write(src.join("Edit.php"), "<?php namespace App; class Edit {}").unwrap();
+    fs::write(src.join("Gone.php"), "<?php namespace App; class Gone {}").unwrap();
+
+    git(repo, &["init", "-q"]);
+    git(repo, &["add", "."]);
+    git(repo, &["commit", "-q", "-m", "initial"]);
+
+    // Full scan establishes the manifest
+    let manifest_path = repo.join(MANIFEST_FILE);
+    let paths = vec![src.clone()];
+    let (_, manifest) = perform_incremental_scan(&manifest_path, &paths, &[], u64::MAX).unwrap();
+    manifest.save(&manifest_path).unwrap();
+
+    // Modify, add (untracked) and delete files
+    fs::write(src.join("Edit.php"), "<?php namespace App; class Edited {}").unwrap();
+    fs::write(src.join("New.php"), "<?php namespace App; class Added {}").unwrap();
+    fs::remove_file(src.join("Gone.php")).unwrap();
+
+    let changed = aurynx::git::changed_files(&src, "HEAD").unwrap();
+    let changed_php = changed
+        .iter()
+        .filter(|p| p.extension().is_some_and(|e| e == "php"));
+    assert_eq!(changed_php.count(), 3);
+
+    let pool = ExtractorPool::default();
+    let (metadata, _) =
+        perform_changed_files_scan(&manifest_path, &paths, &[], u64::MAX, &changed, &pool)
+            .unwrap();
+
+    let mut fqcns: Vec<&str> = metadata.iter().map(|m| m.fqcn.as_str()).collect();
+    fqcns.sort_unstable();
+    assert_eq!(fqcns, ["\\App\\Added", "\\App\\Edited", "\\App\\Keep"]);
+}
+
+#[test]
+fn test_changed_since_requires_manifest() {
+    let temp_dir = TempDir::new().unwrap();
+    let manifest_path = temp_dir.path().join(MANIFEST_FILE);
+
+    let result = perform_changed_files_scan(
+        &manifest_path,
+        &[temp_dir.path().to_path_buf()],
+        &[],
+        u64::MAX,
+        &[],
+        &ExtractorPool::default(),
+    );
+
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("run a full scan first")
+    );
+}
+
+#[test]
+fn test_changed_since_covers_every_repository() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut paths = Vec::new();
+    for name in ["app", "lib"] {
+        let repo = temp_dir.path().join(name);
+        fs::create_dir(&repo).unwrap();
+        git(&repo, &["init", "-q"]);
+        git(&repo, &["commit", "-q", "--allow-empty", "-m", "initial"]);
+        fs::write(repo.join("New.php"), "<?php class New {}").unwrap();
+        paths.push(repo);
+    }
+
+    let changed = aurynx::git::changed_files_in(&paths, "HEAD").unwrap();
+    assert_eq!(changed.len(), 2);
+
+    let error = aurynx::git::changed_files_in(&paths, "no-such-ref").unwrap_err();
+    assert!(format!("{error:#}").contains("no-such-ref"));
+}
@@ -314,3 +314,88 @@ fn test_concurrent_daemon_startup_atomicity() {
         NUM_PROCESSES
     );
 }
+
+#[test]
+fn test_on_error_policy_of_one_shot_scan() {
+    let temp_dir = TempDir::new().unwrap();
+    let src = temp_dir.path().join("src");
+    fs::create_dir(&src).unwrap();
+    fs::write(src.join("Valid.php"), "<?php class Valid {}").unwrap();
+    fs::write(
+        src.join("Broken.php"),
+        "<?php class Broken { public function f() { foo(; } }",
+    )
+    .unwrap();
+
+    let scan = |policy: &str, output: &str| {
+        Command::new(env!("CARGO_BIN_EXE_aurynx"))
+            .args([
+                "discovery:scan",
+                "--path",
+                src.to_str().unwrap(),
+                "--output",
+            ])
+            .arg(temp_dir.path().join(output))
+            .args(["--on-error", policy])
+            .output()
+            .unwrap()
+    };
+
+    // Recovered declarations are kept, and the syntax error is reported
+    let warned = scan("warn", "warn.php");
+    assert!(warned.status.success());
+    let stderr = String::from_utf8_lossy(&warned.stderr);
+    assert!(stderr.contains("Broken.php"), "{stderr}");
+    let cache = fs::read_to_string(temp_dir.path().join("warn.php")).unwrap();
+    assert!(cache.contains("Broken"), "{cache}");
+
+    let skipped = scan("skip", "skip.php");
+    assert!(skipped.status.success());
+    assert!(skipped.stderr.is_empty());
+
+    let failed = scan("fail", "fail.php");
+    assert_eq!(failed.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&failed.stderr);
+    assert!(
+        stderr.contains("syntax error at 1:48: missing `)` (in method Broken::f)"),
+        "{stderr}"
+    );
+    assert!(!temp_dir.path().join("fail.php").exists());
+}
+
+#[test]
+fn test_signature_hash_covers_truncated_members() {
+    let temp_dir = TempDir::new().unwrap();
+    let src = temp_dir.path().join("src");
+    fs::create_dir(&src).unwrap();
+    let config = temp_dir.path().join("aurynx.json");
+    fs::write(&config, r#"{"max_class_members": 1}"#).unwrap();
+
+    let hash = |third: &str| {
+        fs::write(
+            src.join("Client.php"),
+            format!("<?php class Client {{ function a() {{}} function {third}() {{}} }}"),
+        )
+        .unwrap();
+        let output = temp_dir.path().join("cache.json");
+        let status = Command::new(env!("CARGO_BIN_EXE_aurynx"))
+            .args(["discovery:scan", "--signature-hash", "--format", "json", "--config"])
+            .arg(&config)
+            .arg("--path")
+            .arg(&src)
+            .arg("--output")
+            .arg(&output)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let cache: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(cache[0]["truncated"]["methods"], 1);
+        cache[0]["signature_hash"].as_str().unwrap().to_string()
+    };
+
+    // Only the dropped method differs
+    assert_ne!(hash("b"), hash("c"));
+}
@@ -9,7 +9,7 @@
 /// Protocol specification:
 /// - Commands: Plain text strings ("getCode", "ping", etc)
 /// - Responses: Plain text or PHP code directly
-/// - Errors: Start with "ERROR:" prefix
+/// - Errors: Start with "ERROR:" prefix, followed by a stable error code
 /// - No structured data formats (JSON, XML, etc)
 
 #[test]
@@ -78,11 +78,17 @@ fn test_php_code_format() {
 #[test]
 fn test_error_format() {
     // Errors MUST start with "ERROR:" prefix
-    let error = "ERROR: Unknown command: foo";
+    let error = "ERROR: AX4003 Unknown command: foo";
     assert!(error.starts_with("ERROR:"), "Errors must start with ERROR:");
 
-    // Extract message
-    let message = error.strip_prefix("ERROR:").unwrap().trim();
+    // Extract code and message
+    let (code, message) = error
+        .strip_prefix("ERROR:")
+        .unwrap()
+        .trim()
+        .split_once(' ')
+        .unwrap();
+    assert_eq!(code, "AX4003");
     assert_eq!(message, "Unknown command: foo");
 
     // CRITICAL: Must NOT be JSON
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fs;
 use std::panic;
 use std::sync::{Arc, Mutex};
@@ -218,15 +219,42 @@ fn test_daemon_has_panic_hook() {
         socket_path: socket.clone(),
         pid_file: pid.clone(),
         ignore_patterns: vec![],
+        output_exclude: vec![],
         verbose: false,
         is_tty: false,
         force: true,
         write_to_disk: false,
+        read_only: false,
+        exit_on_idle: None,
+        exit_on_error: false,
         pretty: false,
         format: "php".to_string(),
         max_file_size: 10 * 1024 * 1024, // 10MB default
         max_request_size: 1024,          // 1KB default
         max_cache_entries: 50_000,       // 50k default
+        poll_fallback: false,
+        watch_composer: false,
+        watcher_backend: "notify".to_string(),
+        temp_file_patterns: vec![],
+        resolve_hierarchy: false,
+        inherit_attributes: false,
+        signature_hash: false,
+        path_root: None,
+        path_map: HashMap::new(),
+        batch_tuning: aurynx::daemon::batching::BatchTuning::default(),
+        max_memory_entries: None,
+        max_class_members: None,
+        spill_file: None,
+        max_memory_bytes: None,
+        on_update: vec![],
+        class_lists: vec![],
+        derived: vec![],
+        journal_file: None,
+        journal_max_entries: aurynx::journal::DEFAULT_MAX_ENTRIES,
+        cache_layout: aurynx::writer::CacheLayout::default(),
+        shared_memory: None,
+        profile_files: None,
+        scan_options: aurynx::scanner::ScanOptions::default(),
     };
 
     // Create daemon (this should set up panic hook in run())
@@ -1,8 +1,8 @@
 use aurynx::metadata::{
-    AttributeArgument, ClassModifiers, MethodModifiers, PhpClassMetadata, PhpMethodMetadata,
+    AttributeArgument, AttributeMap, ClassModifiers, MethodModifiers, PhpClassMetadata,
+    PhpMethodMetadata,
 };
-use aurynx::writer::write_php_cache;
-use std::collections::HashMap;
+use aurynx::writer::{CacheLayout, write_cache_if_changed, write_php_cache};
 use std::fs;
 use std::path::PathBuf;
 use tempfile::TempDir;
@@ -12,31 +12,41 @@ fn test_compact_output_format() {
     let temp_dir = TempDir::new().unwrap();
     let output_path = temp_dir.path().join("cache.php");
 
-    let mut attributes = HashMap::new();
+    let mut attributes = AttributeMap::new();
     attributes.insert(
         "\\App\\Attribute\\Route".to_string(),
         vec![vec![AttributeArgument::Positional("/api".to_string())]],
     );
 
     let metadata = PhpClassMetadata {
+        schema_version: aurynx::schema::SCHEMA_VERSION,
         fqcn: "\\App\\Test".to_string(),
         file: PathBuf::from("/tmp/test.php"),
+        file_hash: "0123456789abcdef".to_string(),
+        signature_hash: None,
         kind: "class".to_string(),
         modifiers: ClassModifiers::default(),
         attributes,
+        inherited_attributes: None,
         extends: None,
         implements: vec![],
+        resolved_implements: None,
+        ancestors: None,
+        traits: vec![],
         methods: vec![PhpMethodMetadata {
             name: "index".to_string(),
             visibility: "public".to_string(),
             modifiers: MethodModifiers::default(),
-            attributes: HashMap::new(),
+            attributes: AttributeMap::new(),
             parameters: vec![],
             return_type: Some("void".to_string()),
         }],
         properties: vec![],
         backing_type: None,
         cases: vec![],
+        file_context: None,
+        custom: None,
+        truncated: None,
     };
 
     write_php_cache(&[metadata], &output_path, false).unwrap();
@@ -58,6 +68,19 @@ fn test_compact_output_format() {
         &content[..100.min(content.len())]
     );
 
+    assert!(
+        content.contains("'file'=>'/tmp/test.php','file_hash'=>'0123456789abcdef'"),
+        "Should contain the file hash. Content: {}",
+        content
+    );
+
+    // A class without traits keeps the pre-traits shape
+    assert!(
+        content.contains("'implements'=>[],'methods'=>"),
+        "Should leave out the empty traits list. Content: {}",
+        content
+    );
+
     // Check specific structure to ensure valid PHP
     assert!(
         content.contains("'methods'=>['index'=>["),
@@ -75,3 +98,160 @@ fn test_compact_output_format() {
         content
     );
 }
+
+#[test]
+fn test_write_cache_if_changed_skips_identical_content() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("cache.php");
+
+    let metadata = vec![PhpClassMetadata::new(
+        "\\App\\Test".to_string(),
+        PathBuf::from("/tmp/test.php"),
+        "class".to_string(),
+    )];
+
+    assert!(
+        write_cache_if_changed(
+            &metadata,
+            &output_path,
+            "php",
+            false,
+            None,
+            CacheLayout::default()
+        )
+        .unwrap()
+    );
+    assert!(
+        !write_cache_if_changed(
+            &metadata,
+            &output_path,
+            "php",
+            false,
+            None,
+            CacheLayout::default()
+        )
+        .unwrap()
+    );
+
+    let content = fs::read_to_string(&output_path).unwrap();
+    assert!(content.contains("'\\\\App\\\\Test'"));
+    assert!(!output_path.with_extension("tmp").exists());
+
+    // A different format renders different bytes and is written again
+    assert!(
+        write_cache_if_changed(
+            &metadata,
+            &output_path,
+            "json",
+            false,
+            None,
+            CacheLayout::default()
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn test_path_root_writes_portable_paths() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    let output_path = root.join("var/cache/aurynx.php");
+
+    let metadata = vec![
+        PhpClassMetadata::new(
+            "\\App\\User".to_string(),
+            root.join("src/Entity/User.php"),
+            "class".to_string(),
+        ),
+        PhpClassMetadata::new(
+            "\\Outside".to_string(),
+            PathBuf::from("/opt/lib/Outside.php"),
+            "class".to_string(),
+        ),
+    ];
+
+    assert!(
+        write_cache_if_changed(
+            &metadata,
+            &output_path,
+            "php",
+            false,
+            Some(root),
+            CacheLayout::default()
+        )
+        .unwrap()
+    );
+    let content = fs::read_to_string(&output_path).unwrap();
+    assert!(content.contains("'file'=>\\dirname(__DIR__, 2).'/src/Entity/User.php'"));
+    assert!(content.contains("'file'=>'/opt/lib/Outside.php'"));
+
+    let json_path = root.join("cache.json");
+    assert!(
+        write_cache_if_changed(
+            &metadata,
+            &json_path,
+            "json",
+            false,
+            Some(root),
+            CacheLayout::default()
+        )
+        .unwrap()
+    );
+    let json = fs::read_to_string(&json_path).unwrap();
+    assert!(json.contains("\"file\":\"src/Entity/User.php\""));
+}
+
+#[test]
+fn test_cache_layout_groups_by_namespace() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("cache.php");
+
+    let class = |fqcn: &str| {
+        PhpClassMetadata::new(
+            fqcn.to_string(),
+            PathBuf::from("/tmp/test.php"),
+            "class".to_string(),
+        )
+    };
+    let metadata = vec![
+        class("\\App\\B"),
+        class("\\Lib\\C"),
+        class("\\App\\A"),
+        class("\\Root"),
+    ];
+    let layout: CacheLayout =
+        serde_json::from_str(r#"{"group_by": "namespace", "sort": "fqcn"}"#).unwrap();
+
+    assert!(write_cache_if_changed(&metadata, &output_path, "php", false, None, layout).unwrap());
+    let content = fs::read_to_string(&output_path).unwrap();
+
+    let root = content.find("''=>['\\\\Root'").unwrap();
+    let app = content.find("'App'=>['\\\\App\\\\A'").unwrap();
+    let app_b = content.find("'\\\\App\\\\B'").unwrap();
+    let lib = content.find("'Lib'=>['\\\\Lib\\\\C'").unwrap();
+    assert!(
+        root < app && app < app_b && app_b < lib,
+        "Content: {content}"
+    );
+    assert!(!content.contains(",]"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlinked_path_root_matches_canonical_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let real = temp_dir.path().join("releases/42");
+    fs::create_dir_all(real.join("src")).unwrap();
+    let link = temp_dir.path().join("current");
+    std::os::unix::fs::symlink(&real, &link).unwrap();
+
+    let mut metadata = vec![PhpClassMetadata::new(
+        "\\App\\User".to_string(),
+        fs::canonicalize(&real).unwrap().join("src/User.php"),
+        "class".to_string(),
+    )];
+    let root = aurynx::writer::canonical_path_root(&link);
+    aurynx::writer::relativize_paths(&mut metadata, &root);
+
+    assert_eq!(metadata[0].file, PathBuf::from("src/User.php"));
+}